@@ -393,6 +393,12 @@ export interface Project {
    * If not specified, will default to the default theme in the configuration settings.
    */
   theme?: string
+  /**
+   * The citation style to use for documents in this project
+   *
+   * The name of one of the bundled styles (e.g. `apa`, `vancouver`) or the path (within the project) of a CSL file. Can be overridden by documents. If not specified, will default to APA.
+   */
+  citationStyle?: string
   /**
    * A list of project sources and their destination within the project
    */
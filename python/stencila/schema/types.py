@@ -76,6 +76,9 @@ class Cite(Entity):
     content: Optional[Array["InlineContent"]] = None
     """Optional structured content/text of this citation."""
 
+    errors: Optional[Array[String]] = None
+    """Errors that occurred when resolving or rendering the citation."""
+
     pageEnd: Optional[Union[Integer, String]] = None
     """The page on which the work ends; for example "138" or "xvi"."""
 
@@ -96,6 +99,7 @@ for example, "1-6, 9, 55".
         citationPrefix: Optional[String] = None,
         citationSuffix: Optional[String] = None,
         content: Optional[Array["InlineContent"]] = None,
+        errors: Optional[Array[String]] = None,
         id: Optional[String] = None,
         meta: Optional[Object] = None,
         pageEnd: Optional[Union[Integer, String]] = None,
@@ -118,6 +122,8 @@ for example, "1-6, 9, 55".
             self.citationSuffix = citationSuffix
         if content is not None:
             self.content = content
+        if errors is not None:
+            self.errors = errors
         if pageEnd is not None:
             self.pageEnd = pageEnd
         if pageStart is not None:
@@ -597,6 +603,9 @@ class CodeError(Entity):
     errorMessage: String
     """The error message or brief description of the error."""
 
+    codeLocation: Optional[Array[Integer]] = None
+    """The location of the error within the code."""
+
     errorType: Optional[String] = None
     """The type of error e.g. "SyntaxError", "ZeroDivisionError"."""
 
@@ -607,6 +616,7 @@ class CodeError(Entity):
     def __init__(
         self,
         errorMessage: String,
+        codeLocation: Optional[Array[Integer]] = None,
         errorType: Optional[String] = None,
         id: Optional[String] = None,
         meta: Optional[Object] = None,
@@ -618,6 +628,8 @@ class CodeError(Entity):
         )
         if errorMessage is not None:
             self.errorMessage = errorMessage
+        if codeLocation is not None:
+            self.codeLocation = codeLocation
         if errorType is not None:
             self.errorType = errorType
         if stackTrace is not None:
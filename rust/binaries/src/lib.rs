@@ -40,6 +40,7 @@ static BINARIES: Lazy<BTreeMap<String, Box<dyn BinaryTrait>>> = Lazy::new(|| {
     binary_new!("binary-chrome", binary_chrome::ChromeBinary {});
     binary_new!("binary-chromium", binary_chromium::ChromiumBinary {});
     binary_new!("binary-node", binary_node::NodeBinary {});
+    binary_new!("binary-micromamba", binary_micromamba::MicromambaBinary {});
     binary_new!("binary-pack", binary_pack::PackBinary {});
     binary_new!("binary-pandoc", binary_pandoc::PandocBinary {});
     binary_new!("binary-podman", binary_podman::PodmanBinary {});
@@ -48,6 +49,7 @@ static BINARIES: Lazy<BTreeMap<String, Box<dyn BinaryTrait>>> = Lazy::new(|| {
     binary_new!("binary-r", binary_r::RBinary {});
     binary_new!("binary-stencila", binary_stencila::StencilaBinary {});
     binary_new!("binary-task", binary_task::TaskBinary {});
+    binary_new!("binary-uv", binary_uv::UvBinary {});
 
     map
 });
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+pub use binary::BinaryTrait;
+use binary::{
+    binary_clone_box,
+    common::{
+        async_trait::async_trait,
+        eyre::{bail, Result},
+    },
+    Binary,
+};
+
+mod versions;
+
+pub struct MicromambaBinary;
+
+#[async_trait]
+impl BinaryTrait for MicromambaBinary {
+    fn spec(&self) -> Binary {
+        Binary::new("micromamba", &[], &[])
+    }
+
+    binary_clone_box!();
+
+    async fn versions(&self, _os: &str) -> Result<Vec<String>> {
+        // Release tags have a build number suffix (e.g. `1.5.8-0`) which needs to be
+        // removed so that they are not treated as pre-releases
+        let more = self
+            .versions_github_releases("mamba-org", "micromamba-releases")
+            .await
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter_map(|version| version.split('-').next().map(String::from))
+                    .collect()
+            });
+        let versions = self.versions_update_maybe(versions::VERSIONS, more);
+        Ok(self.semver_versions_matching(&versions, ">=1.5"))
+    }
+
+    async fn install_version(
+        &self,
+        version: &str,
+        dest: &Path,
+        os: &str,
+        arch: &str,
+    ) -> Result<()> {
+        let platform = match os {
+            "linux" => match arch {
+                "arm" => "linux-aarch64",
+                _ => "linux-64",
+            },
+            "macos" => match arch {
+                "arm" => "osx-arm64",
+                _ => "osx-64",
+            },
+            "windows" => "win-64",
+            _ => bail!(
+                "Installation of `micromamba` for operating system `{}` is not supported",
+                os
+            ),
+        };
+        let url = format!(
+            "https://github.com/mamba-org/micromamba-releases/releases/download/{version}-0/micromamba-{platform}",
+            version = version,
+            platform = platform
+        );
+
+        // The release is a standalone executable so download it directly into the destination
+        let filename = match os {
+            "windows" => "micromamba.exe",
+            _ => "micromamba",
+        };
+        self.download(&url, Some(filename.to_string()), Some(dest.to_path_buf()))
+            .await?;
+        self.executables(dest, &["micromamba"])?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+// Generated using `stencila binaries versions micromamba --write <file>`
+
+#[rustfmt::skip]
+pub const VERSIONS: &[&str] = &[
+  "2.0.2",
+  "2.0.1",
+  "2.0.0",
+  "1.5.10",
+  "1.5.9",
+  "1.5.8",
+  "1.5.7",
+  "1.5.6",
+  "1.5.5",
+  "1.5.4",
+  "1.5.3",
+  "1.5.1",
+  "1.5.0",
+];
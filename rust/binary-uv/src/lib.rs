@@ -0,0 +1,68 @@
+use std::path::Path;
+
+pub use binary::BinaryTrait;
+use binary::{
+    binary_clone_box,
+    common::{
+        async_trait::async_trait,
+        eyre::{bail, Result},
+    },
+    Binary,
+};
+
+mod versions;
+
+pub struct UvBinary;
+
+#[async_trait]
+impl BinaryTrait for UvBinary {
+    fn spec(&self) -> Binary {
+        Binary::new("uv", &[], &[])
+    }
+
+    binary_clone_box!();
+
+    async fn versions(&self, _os: &str) -> Result<Vec<String>> {
+        let versions = self.versions_update_maybe(
+            versions::VERSIONS,
+            self.versions_github_releases("astral-sh", "uv").await,
+        );
+        // `UV_PROJECT_ENVIRONMENT`, used when installing packages, was introduced in 0.4.4
+        Ok(self.semver_versions_matching(&versions, ">=0.4.4"))
+    }
+
+    async fn install_version(
+        &self,
+        version: &str,
+        dest: &Path,
+        os: &str,
+        arch: &str,
+    ) -> Result<()> {
+        let (target, strip) = match os {
+            "linux" => match arch {
+                "arm" => ("aarch64-unknown-linux-gnu.tar.gz", 1),
+                _ => ("x86_64-unknown-linux-gnu.tar.gz", 1),
+            },
+            "macos" => match arch {
+                "arm" => ("aarch64-apple-darwin.tar.gz", 1),
+                _ => ("x86_64-apple-darwin.tar.gz", 1),
+            },
+            "windows" => ("x86_64-pc-windows-msvc.zip", 0),
+            _ => bail!(
+                "Installation of `uv` for operating system `{}` is not supported",
+                os
+            ),
+        };
+        let url = format!(
+            "https://github.com/astral-sh/uv/releases/download/{version}/uv-{target}",
+            version = version,
+            target = target
+        );
+        let archive = self.download(&url, None, None).await?;
+
+        self.extract(&archive, dest, strip)?;
+        self.executables(dest, &["uv", "uvx"])?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+// Generated using `stencila binaries versions uv --write <file>`
+
+#[rustfmt::skip]
+pub const VERSIONS: &[&str] = &[
+  "0.4.20",
+  "0.4.19",
+  "0.4.18",
+  "0.4.17",
+  "0.4.16",
+  "0.4.15",
+  "0.4.14",
+  "0.4.13",
+  "0.4.12",
+  "0.4.11",
+  "0.4.10",
+  "0.4.9",
+  "0.4.8",
+  "0.4.7",
+  "0.4.6",
+  "0.4.5",
+  "0.4.4",
+];
@@ -1,18 +1,20 @@
 use std::{
     env::{self, current_dir, set_current_dir},
     ffi::OsString,
-    fs::{create_dir_all, read_to_string, remove_file},
+    fs::{create_dir_all, read_to_string, remove_file, write},
     path::{Path, PathBuf},
 };
 
+use binary_micromamba::MicromambaBinary;
 use binary_poetry::PoetryBinary;
 use binary_python::{BinaryInstallation, BinaryTrait, PythonBinary};
+use binary_uv::UvBinary;
 use buildpack::{
     common::{
         eyre::{self, bail, eyre},
         maplit::hashmap,
         serde::{Deserialize, Serialize},
-        toml, tracing,
+        serde_json, serde_yaml, toml, tracing,
     },
     fs_utils::{copy_if_exists, symlink_dir, symlink_file},
     hash_utils::str_sha256_hex,
@@ -38,11 +40,16 @@ impl BuildpackTrait for PythonBuildpack {
     }
 }
 
+const ENVIRONMENT_YAML: &str = "environment.yaml";
+const ENVIRONMENT_YML: &str = "environment.yml";
+const PIPFILE: &str = "Pipfile";
+const PIPFILE_LOCK: &str = "Pipfile.lock";
 const POETRY_LOCK: &str = "poetry.lock";
 const PYPROJECT_TOML: &str = "pyproject.toml";
 const REQUIREMENTS_TXT: &str = "requirements.txt";
 const RUNTIME_TXT: &str = "runtime.txt";
 const TOOL_VERSIONS: &str = ".tool-versions";
+const UV_LOCK: &str = "uv.lock";
 const VENV: &str = ".venv";
 
 impl Buildpack for PythonBuildpack {
@@ -54,11 +61,35 @@ impl Buildpack for PythonBuildpack {
         // Read `.tool-versions` for Python version
         let tool_versions = Self::tool_versions();
 
+        // Read `environment.yml` for Python version and Conda packages
+        let (environment_yml, environment_yml_path) = match [ENVIRONMENT_YML, ENVIRONMENT_YAML]
+            .iter()
+            .find(|path| PathBuf::from(path).exists())
+        {
+            Some(path) => (
+                read_to_string(path)
+                    .ok()
+                    .and_then(|yaml| serde_yaml::from_str::<serde_yaml::Value>(&yaml).ok()),
+                *path,
+            ),
+            None => (None, ENVIRONMENT_YML),
+        };
+
         // Read `pyproject.toml` for Python version and packages
         let pyproject_toml = read_to_string(PYPROJECT_TOML)
             .ok()
             .and_then(|json| toml::from_str::<toml::Value>(&json).ok());
 
+        // Read `Pipfile.lock` for Python version
+        let pipfile_lock = read_to_string(PIPFILE_LOCK)
+            .ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
+
+        // Read `uv.lock` for Python version
+        let uv_lock = read_to_string(UV_LOCK)
+            .ok()
+            .and_then(|json| toml::from_str::<toml::Value>(&json).ok());
+
         // May read `runtime.txt` for Python version
         let runtime_txt = PathBuf::from(RUNTIME_TXT);
 
@@ -70,7 +101,10 @@ impl Buildpack for PythonBuildpack {
 
         // Fail early
         if !(tool_versions.contains_key("python")
+            || environment_yml.is_some()
             || pyproject_toml.is_some()
+            || pipfile_lock.is_some()
+            || uv_lock.is_some()
             || runtime_txt.exists()
             || poetry_lock.exists()
             || requirements_txt.exists()
@@ -82,9 +116,15 @@ impl Buildpack for PythonBuildpack {
         let mut requires = Vec::new();
         let mut provides = Vec::new();
 
-        // Resolve Python version from `.tool-versions`, `runtime.txt` or `pyproject.toml`
+        // Resolve Python version from `.tool-versions`, `environment.yml`, `pyproject.toml`,
+        // `Pipfile.lock`, `runtime.txt` or `uv.lock`
         let (version, source) = if let Some(version) = tool_versions.get("python") {
             (version.to_string(), TOOL_VERSIONS)
+        } else if let Some(semver_req) = environment_yml
+            .as_ref()
+            .and_then(Self::environment_yml_python)
+        {
+            (semver_req, environment_yml_path)
         } else if let Some(semver_req) = pyproject_toml
             .as_ref()
             .and_then(|project| project.get("tool"))
@@ -94,6 +134,12 @@ impl Buildpack for PythonBuildpack {
             .and_then(|semver_req| semver_req.as_str())
         {
             (semver_req.to_string(), PYPROJECT_TOML)
+        } else if let Some(version) = pipfile_lock
+            .as_ref()
+            .and_then(|lock| lock.pointer("/_meta/requires/python_version"))
+            .and_then(|version| version.as_str())
+        {
+            (["~", version].concat(), PIPFILE_LOCK)
         } else if let Some(version) = read_to_string(runtime_txt).ok().and_then(|content| {
             content
                 .trim()
@@ -101,10 +147,61 @@ impl Buildpack for PythonBuildpack {
                 .map(|version| version.to_string())
         }) {
             (version, RUNTIME_TXT)
+        } else if let Some((specifiers, source)) = uv_lock
+            .as_ref()
+            .and_then(|lock| lock.get("requires-python"))
+            .map(|specifiers| (specifiers, UV_LOCK))
+            .or_else(|| {
+                pyproject_toml
+                    .as_ref()
+                    .and_then(|project| project.get("project"))
+                    .and_then(|project| project.get("requires-python"))
+                    .map(|specifiers| (specifiers, PYPROJECT_TOML))
+            })
+            .and_then(|(specifiers, source)| {
+                specifiers
+                    .as_str()
+                    .map(|specifiers| (Self::pep440_to_semver(specifiers), source))
+            })
+        {
+            (specifiers, source)
         } else {
             ("*".to_string(), "")
         };
 
+        if environment_yml.is_some() {
+            // Python is installed into the Conda environment (rather than in a separate
+            // layer) so only pass on the version if it is not already in `environment.yml`
+            let (require, provide) = Self::require_and_provide(
+                "micromamba",
+                environment_yml_path,
+                "Install Micromamba",
+                None,
+            );
+            requires.push(require);
+            provides.push(provide);
+
+            let mut options = hashmap! {
+                "environment_file" => environment_yml_path.to_string()
+            };
+            if source != environment_yml_path && !source.is_empty() {
+                options.insert("python_version", version);
+            }
+            let (require, provide) = Self::require_and_provide(
+                "conda",
+                environment_yml_path,
+                "Create Conda environment using Micromamba",
+                Some(options),
+            );
+            requires.push(require);
+            provides.push(provide);
+
+            let mut build_plan = BuildPlan::new();
+            build_plan.requires = requires;
+            build_plan.provides = provides;
+            return DetectResultBuilder::pass().build_plan(build_plan).build();
+        }
+
         // Require and provide Python
         let (require, provide) = Self::require_and_provide(
             "python",
@@ -118,35 +215,44 @@ impl Buildpack for PythonBuildpack {
         provides.push(provide);
 
         // Determine how packages are to be installed
-        if pyproject_toml.is_some() || poetry_lock.exists() {
+        let package_manager = if uv_lock.is_some() {
+            Some(("uv", "uv", UV_LOCK))
+        } else if poetry_lock.exists() || (pyproject_toml.is_some() && pipfile_lock.is_none()) {
             let source = if poetry_lock.exists() {
                 POETRY_LOCK
             } else {
                 PYPROJECT_TOML
             };
+            Some(("poetry", "Poetry", source))
+        } else if pipfile_lock.is_some() {
+            Some(("pipenv", "Pipenv", PIPFILE_LOCK))
+        } else if requirements_txt.exists() {
+            Some(("pip", "Pip", REQUIREMENTS_TXT))
+        } else {
+            None
+        };
 
-            let (require, provide) =
-                Self::require_and_provide("poetry", source, "Install Poetry", None);
-            requires.push(require);
-            provides.push(provide);
+        if let Some((package_manager, title, source)) = package_manager {
+            if package_manager != "pip" {
+                let (require, provide) = Self::require_and_provide(
+                    package_manager,
+                    source,
+                    format!("Install {}", title),
+                    None,
+                );
+                requires.push(require);
+                provides.push(provide);
+            }
 
             let (require, provide) = Self::require_and_provide(
                 "venv",
                 source,
-                "Install Python packages into virtual environment using Poetry",
+                format!(
+                    "Install Python packages into virtual environment using {}",
+                    title
+                ),
                 Some(hashmap! {
-                    "package_manager" => "poetry".to_string()
-                }),
-            );
-            requires.push(require);
-            provides.push(provide);
-        } else if requirements_txt.exists() {
-            let (require, provide) = Self::require_and_provide(
-                "venv",
-                REQUIREMENTS_TXT,
-                "Install Python packages into virtual environment using Pip",
-                Some(hashmap! {
-                    "package_manager" => "pip".to_string()
+                    "package_manager" => package_manager.to_string()
                 }),
             );
             requires.push(require);
@@ -174,6 +280,29 @@ impl Buildpack for PythonBuildpack {
             self.set_layer_env_vars(&layer_data.env);
         }
 
+        if entries.contains_key("pipenv") {
+            let layer_data = context.handle_layer(layer_name!("pipenv"), PipenvLayer::new())?;
+            self.set_layer_env_vars(&layer_data.env);
+        }
+
+        if entries.contains_key("uv") {
+            let layer_data = context.handle_layer(layer_name!("uv"), UvLayer::new())?;
+            self.set_layer_env_vars(&layer_data.env);
+        }
+
+        if entries.contains_key("micromamba") {
+            let layer_data =
+                context.handle_layer(layer_name!("micromamba"), MicromambaLayer::new())?;
+            self.set_layer_env_vars(&layer_data.env);
+        }
+
+        if let Some(options) = entries.get("conda") {
+            context.handle_layer(
+                layer_name!("conda"),
+                CondaLayer::new(options, &context.app_dir),
+            )?;
+        }
+
         if let Some(options) = entries.get("venv") {
             context.handle_layer(
                 layer_name!("venv"),
@@ -186,6 +315,84 @@ impl Buildpack for PythonBuildpack {
     }
 }
 
+impl PythonBuildpack {
+    /// Get the Python version from the dependencies in a Conda `environment.yml` file
+    ///
+    /// Conda match specifications for Python e.g. `python=3.10`, `python>=3.9,<3.12`,
+    /// `conda-forge::python 3.10.*` are converted to semver requirements.
+    fn environment_yml_python(environment: &serde_yaml::Value) -> Option<String> {
+        environment
+            .get("dependencies")?
+            .as_sequence()?
+            .iter()
+            .find_map(|dependency| {
+                let spec = dependency.as_str()?.trim();
+                let spec = match spec.split_once("::") {
+                    Some((_channel, spec)) => spec,
+                    None => spec,
+                };
+                let constraint = spec.strip_prefix("python")?;
+
+                // Avoid matching packages such as `python-dateutil`
+                if constraint.starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_') {
+                    return None;
+                }
+
+                let constraint = constraint.trim();
+                if constraint.is_empty() {
+                    None
+                } else if let Some(version) = constraint.strip_prefix("==") {
+                    Some(["=", version].concat())
+                } else if constraint.starts_with(|c: char| c == '=' || c.is_ascii_digit()) {
+                    // A "fuzzy" constraint e.g. `=3.10` or `3.10.*` matches any patch version
+                    let version = constraint
+                        .trim_start_matches('=')
+                        .trim_end_matches(".*")
+                        .trim_end_matches('*');
+                    Some(["~", version].concat())
+                } else {
+                    Some(constraint.to_string())
+                }
+            })
+    }
+
+    /// Convert PEP 440 version specifiers (e.g. from `requires-python`) to a semver requirement
+    ///
+    /// See https://peps.python.org/pep-0440/#version-specifiers.
+    fn pep440_to_semver(specifiers: &str) -> String {
+        specifiers
+            .split(',')
+            .filter_map(|specifier| {
+                let specifier = specifier.trim();
+                if specifier.is_empty() || specifier.starts_with("!=") {
+                    // Exclusions can not be represented as a semver comparator
+                    None
+                } else if let Some(version) = specifier.strip_prefix("~=") {
+                    // A compatible release clause e.g. `~=3.10` means `>=3.10,<4`
+                    // but `~=3.10.2` means `>=3.10.2,<3.11`
+                    let version = version.trim();
+                    match version.matches('.').count() {
+                        0 | 1 => Some(["^", version].concat()),
+                        _ => Some(["~", version].concat()),
+                    }
+                } else if let Some(version) = specifier.strip_prefix("===") {
+                    Some(["=", version.trim()].concat())
+                } else if let Some(version) = specifier.strip_prefix("==") {
+                    let version = version.trim();
+                    if version.ends_with(".*") {
+                        Some(version.to_string())
+                    } else {
+                        Some(["=", version].concat())
+                    }
+                } else {
+                    Some(specifier.replace(' ', ""))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
 struct PythonLayer {
     /// The semantic version requirement for the Python binary
     requirement: String,
@@ -496,12 +703,465 @@ impl Layer for PoetryLayer {
     }
 }
 
+struct PipenvLayer {
+    // The version requirement for Pipenv (in PEP 440 format since it is installed using Pip)
+    // Currently fixed to versions that support the `requirements` command
+    requirement: String,
+}
+
+impl PipenvLayer {
+    fn new() -> Self {
+        let requirement = ">=2022.4".to_string();
+        PipenvLayer { requirement }
+    }
+}
+
+impl Layer for PipenvLayer {
+    type Buildpack = PythonBuildpack;
+    type Metadata = LayerVersionMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            build: true,
+            launch: false,
+            cache: true,
+        }
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &libcnb::layer::LayerData<Self::Metadata>,
+    ) -> Result<libcnb::layer::ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        let strategy = if layer_data.path.join("bin").join("pipenv").exists() {
+            tracing::info!(
+                "Existing `pipenv` layer has `pipenv {}`; will keep",
+                layer_data.content_metadata.metadata.version
+            );
+            ExistingLayerStrategy::Keep
+        } else {
+            tracing::info!("Existing `pipenv` layer does not have `./bin/pipenv`; will recreate");
+            ExistingLayerStrategy::Recreate
+        };
+        Ok(strategy)
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, eyre::Report> {
+        tracing::info!(
+            "Creating `pipenv` layer with requirement `{}`",
+            self.requirement
+        );
+
+        // Pipenv does not have a standalone installer so install it into a virtual
+        // environment in the layer using the Python in the `python` layer
+        let python_layer_path = layer_path
+            .parent()
+            .expect("Should have parent")
+            .join("python");
+        let python = BinaryInstallation::new(
+            "python3",
+            python_layer_path.join("bin").join("python3"),
+            None,
+            vec![],
+        );
+        python.run_sync(&[
+            OsString::from("-m"),
+            "venv".into(),
+            "--clear".into(),
+            layer_path.into(),
+        ])?;
+
+        let python = BinaryInstallation::new(
+            "python3",
+            layer_path.join("bin").join("python3"),
+            None,
+            vec![],
+        );
+        let pipenv = ["pipenv", &self.requirement].concat();
+        python.run_sync(["-m", "pip", "install", pipenv.as_str()])?;
+
+        // Get the version installed e.g. from `pipenv, version 2022.9.8`
+        let pipenv = BinaryInstallation::new(
+            "pipenv",
+            layer_path.join("bin").join("pipenv"),
+            None,
+            vec![],
+        );
+        let output = pipenv.command_sync().arg("--version").output()?;
+        let version = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .last()
+            .unwrap_or_default()
+            .to_string();
+
+        LayerResultBuilder::new(LayerVersionMetadata { version }).build()
+    }
+}
+
+struct UvLayer {
+    // The semantic version requirement for the uv binary
+    // Currently fixed
+    requirement: String,
+}
+
+impl UvLayer {
+    fn new() -> Self {
+        let requirement = ">=0.4.4".to_string();
+        UvLayer { requirement }
+    }
+}
+
+impl Layer for UvLayer {
+    type Buildpack = PythonBuildpack;
+    type Metadata = LayerVersionMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            build: true,
+            launch: true,
+            cache: true,
+        }
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &libcnb::layer::LayerData<Self::Metadata>,
+    ) -> Result<libcnb::layer::ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        let installed = UvBinary {}.semver_version_matches(
+            &layer_data.content_metadata.metadata.version,
+            &self.requirement,
+        )?;
+        let strategy = if installed {
+            tracing::info!(
+                "Existing `uv` layer has `./bin/uv` matching semver requirement `{}`; will keep",
+                self.requirement
+            );
+            ExistingLayerStrategy::Keep
+        } else {
+            tracing::info!(
+                "Existing `uv` layer does not have `./bin/uv` matching semver requirement `{}`; will recreate",
+                self.requirement
+            );
+            ExistingLayerStrategy::Recreate
+        };
+        Ok(strategy)
+    }
+
+    fn create(
+        &self,
+        context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, eyre::Report> {
+        tracing::info!(
+            "Creating `uv` layer with semver requirement `{}`",
+            self.requirement
+        );
+
+        let bin_path = layer_path.join("bin");
+        let version = if context.is_local() {
+            let uv = UvBinary {}.ensure_version_sync(&self.requirement)?;
+            let version = uv.version()?.to_string();
+
+            tracing::info!("Linking to `uv {}`", version);
+            create_dir_all(&bin_path)?;
+            symlink_file(uv.path, bin_path.join(uv.name))?;
+
+            version
+        } else {
+            let uv = UvBinary {}.find_version(&self.requirement).ok();
+
+            if let Some(uv) = uv {
+                let version = uv.version()?.to_string();
+                tracing::info!("Using `uv {}` installed on stack image", version);
+
+                version
+            } else {
+                tracing::info!(
+                    "Installing `uv` with semver requirement `{}`",
+                    self.requirement
+                );
+
+                UvBinary {}.install_in_sync(Some(self.requirement.clone()), Some(bin_path))?
+            }
+        };
+
+        LayerResultBuilder::new(LayerVersionMetadata { version }).build()
+    }
+}
+
+struct MicromambaLayer {
+    // The semantic version requirement for the Micromamba binary
+    // Currently fixed
+    requirement: String,
+}
+
+impl MicromambaLayer {
+    fn new() -> Self {
+        let requirement = ">=1.5".to_string();
+        MicromambaLayer { requirement }
+    }
+}
+
+impl Layer for MicromambaLayer {
+    type Buildpack = PythonBuildpack;
+    type Metadata = LayerVersionMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            build: true,
+            launch: false,
+            cache: true,
+        }
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &libcnb::layer::LayerData<Self::Metadata>,
+    ) -> Result<libcnb::layer::ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        let installed = MicromambaBinary {}.semver_version_matches(
+            &layer_data.content_metadata.metadata.version,
+            &self.requirement,
+        )?;
+        let strategy = if installed {
+            tracing::info!(
+                "Existing `micromamba` layer has `./bin/micromamba` matching semver requirement `{}`; will keep",
+                self.requirement
+            );
+            ExistingLayerStrategy::Keep
+        } else {
+            tracing::info!(
+                "Existing `micromamba` layer does not have `./bin/micromamba` matching semver requirement `{}`; will recreate",
+                self.requirement
+            );
+            ExistingLayerStrategy::Recreate
+        };
+        Ok(strategy)
+    }
+
+    fn create(
+        &self,
+        context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, eyre::Report> {
+        tracing::info!(
+            "Creating `micromamba` layer with semver requirement `{}`",
+            self.requirement
+        );
+
+        let bin_path = layer_path.join("bin");
+        let mut layer_env = LayerEnv::new();
+        let version = if context.is_local() {
+            let micromamba = MicromambaBinary {}.ensure_version_sync(&self.requirement)?;
+            let version = micromamba.version()?.to_string();
+
+            tracing::info!("Linking to `micromamba {}`", version);
+            create_dir_all(&bin_path)?;
+            symlink_file(micromamba.path, bin_path.join(micromamba.name))?;
+
+            version
+        } else {
+            // Use the layer as the root prefix so that downloaded packages are cached
+            layer_env = layer_env.chainable_insert(
+                Scope::Build,
+                ModificationBehavior::Override,
+                "MAMBA_ROOT_PREFIX",
+                layer_path.join("root"),
+            );
+
+            let micromamba = MicromambaBinary {}.find_version(&self.requirement).ok();
+            if let Some(micromamba) = micromamba {
+                let version = micromamba.version()?.to_string();
+                tracing::info!("Using `micromamba {}` installed on stack image", version);
+
+                version
+            } else {
+                tracing::info!(
+                    "Installing `micromamba` with semver requirement `{}`",
+                    self.requirement
+                );
+
+                MicromambaBinary {}
+                    .install_in_sync(Some(self.requirement.clone()), Some(bin_path))?
+            }
+        };
+
+        LayerResultBuilder::new(LayerVersionMetadata { version })
+            .env(layer_env)
+            .build()
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(crate = "buildpack::common::serde")]
+struct CondaLayer {
+    /// The name of the Conda environment file e.g. `environment.yml`
+    environment_file: String,
+
+    /// The version of Python to install into the environment
+    ///
+    /// Only used if the environment file does not specify the version of Python
+    /// and it is specified elsewhere (e.g. in `.tool-versions`).
+    python_version: Option<String>,
+
+    /// A hash of the environment file
+    ///
+    /// Used to update the environment if the file changes.
+    packages_hash: String,
+}
+
+impl CondaLayer {
+    fn new(options: &LayerOptions, app_path: &Path) -> Self {
+        let environment_file = options
+            .get("environment_file")
+            .cloned()
+            .unwrap_or_else(|| ENVIRONMENT_YML.to_string());
+
+        // Conda match specifications differ from semver requirements so only pass
+        // on exact versions e.g. `3.10.4` (as in `.tool-versions` and `runtime.txt`)
+        let python_version = options.get("python_version").and_then(|version| {
+            if version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                Some(version.clone())
+            } else {
+                tracing::warn!(
+                    "Python version `{}` is not an exact version so will not be added to Conda environment",
+                    version
+                );
+                None
+            }
+        });
+
+        let packages_hash =
+            str_sha256_hex(&read_to_string(app_path.join(&environment_file)).unwrap_or_default());
+
+        CondaLayer {
+            environment_file,
+            python_version,
+            packages_hash,
+        }
+    }
+}
+
+impl Layer for CondaLayer {
+    type Buildpack = PythonBuildpack;
+    type Metadata = CondaLayer;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            build: true,
+            launch: true,
+            cache: true,
+        }
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &libcnb::layer::LayerData<Self::Metadata>,
+    ) -> Result<libcnb::layer::ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        let existing = &layer_data.content_metadata.metadata;
+        let strategy = if self.python_version != existing.python_version {
+            tracing::info!(
+                "Existing `conda` layer is for different Python version (`{:?}` => `{:?}`); will recreate",
+                existing.python_version,
+                self.python_version,
+            );
+            ExistingLayerStrategy::Recreate
+        } else if self.packages_hash != existing.packages_hash {
+            tracing::info!("Existing `conda` layer has different packages hash; will update");
+            ExistingLayerStrategy::Update
+        } else {
+            tracing::info!("Existing `conda` layer meets requirements; will keep");
+            ExistingLayerStrategy::Keep
+        };
+        Ok(strategy)
+    }
+
+    fn create(
+        &self,
+        context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, eyre::Report> {
+        tracing::info!("Creating `conda` layer");
+        self.install(context, layer_path)
+    }
+
+    fn update(
+        &self,
+        context: &BuildContext<Self::Buildpack>,
+        layer_data: &libcnb::layer::LayerData<Self::Metadata>,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        tracing::info!("Updating `conda` layer");
+        self.install(context, &layer_data.path)
+    }
+}
+
+impl CondaLayer {
+    fn install(
+        &self,
+        context: &BuildContext<PythonBuildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<CondaLayer>, eyre::Report> {
+        let app_path = &context.app_dir.canonicalize()?;
+        let layer_path = &layer_path.canonicalize()?;
+        let env_path = layer_path.join("env");
+
+        // Create the environment, or if it already exists, install any new or
+        // changed packages into it
+        let command = if env_path.join("conda-meta").exists() {
+            "install"
+        } else {
+            "create"
+        };
+        tracing::info!(
+            "Installing packages into `{}` using Micromamba",
+            env_path.display()
+        );
+
+        let micromamba = MicromambaBinary {}.require_version_sync(">=1.5")?;
+        let mut args: Vec<OsString> = vec![
+            command.into(),
+            "--yes".into(),
+            "--prefix".into(),
+            env_path.clone().into(),
+            "--file".into(),
+            app_path.join(&self.environment_file).into(),
+        ];
+        if let Some(version) = &self.python_version {
+            args.push(["python=", version].concat().into());
+        }
+        micromamba.run_sync(&args)?;
+
+        // Add the environment to the PATH
+        let layer_env = LayerEnv::new()
+            .chainable_insert(
+                Scope::All,
+                ModificationBehavior::Prepend,
+                "PATH",
+                format!("{}:", env_path.join("bin").display()),
+            )
+            .chainable_insert(
+                Scope::All,
+                ModificationBehavior::Override,
+                "CONDA_PREFIX",
+                &env_path,
+            );
+
+        LayerResultBuilder::new(self.clone()).env(layer_env).build()
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 #[serde(crate = "buildpack::common::serde")]
 struct VenvLayer {
     /// The package manager used to do the installation of packages
     ///
-    /// Currently can be "pip" or "poetry"
+    /// Currently can be "pip", "poetry", "pipenv" or "uv"
     package_manager: String,
 
     /// The minor version of Python to install packages for e.g. `3.10`
@@ -509,9 +1169,10 @@ struct VenvLayer {
     /// Used to bust cached `venv` if the Python minor version changes.
     minor_version: String,
 
-    /// A hash of Poetry & Pip related files in an app directory
+    /// A hash of package manager related files in an app directory
     ///
-    /// The hash is the combined contents of `poetry.lock`, `pyproject.toml`, `requirements.txt`.
+    /// The hash is the combined contents of `poetry.lock`, `pyproject.toml`, `requirements.txt`,
+    /// `Pipfile.lock` and `uv.lock`.
     /// This means that if any one is changed or removed that the hash will change.
     packages_hash: String,
 }
@@ -534,6 +1195,8 @@ impl VenvLayer {
                 read_to_string(app_path.join(POETRY_LOCK)).unwrap_or_default(),
                 read_to_string(app_path.join(PYPROJECT_TOML)).unwrap_or_default(),
                 read_to_string(app_path.join(REQUIREMENTS_TXT)).unwrap_or_default(),
+                read_to_string(app_path.join(PIPFILE_LOCK)).unwrap_or_default(),
+                read_to_string(app_path.join(UV_LOCK)).unwrap_or_default(),
             ]
             .concat(),
         );
@@ -653,7 +1316,84 @@ impl VenvLayer {
             ])?;
         }
 
-        if self.package_manager == "pip" {
+        if self.package_manager == "pipenv" {
+            tracing::info!(
+                "Installing packages into `{}` using Pipenv",
+                venv_path.display()
+            );
+
+            // Generate a requirements file from `Pipfile.lock` using the `pipenv` in the `pipenv` layer
+            let mut pipenv = BinaryInstallation::new(
+                "pipenv",
+                layer_path
+                    .parent()
+                    .expect("Should have parent")
+                    .join("pipenv")
+                    .join("bin")
+                    .join("pipenv"),
+                None,
+                vec![],
+            );
+            pipenv.env_list(&[("PIPENV_PIPFILE", app_path.join(PIPFILE).as_os_str())]);
+            let output = pipenv.command_sync().arg("requirements").output()?;
+            if !output.status.success() {
+                bail!(
+                    "Unable to generate requirements from `{}`: {}",
+                    PIPFILE_LOCK,
+                    String::from_utf8_lossy(&output.stderr)
+                )
+            }
+            let requirements = layer_path.join(REQUIREMENTS_TXT);
+            write(&requirements, output.stdout)?;
+
+            // Install the requirements using the Python in the virtual environment (as for Pip)
+            let mut python = BinaryInstallation::new(
+                "python3",
+                venv_path.join("bin").join("python3"),
+                None,
+                vec![],
+            );
+            if !context.is_local() {
+                python.env_list(&[("PIP_CACHE_DIR", layer_path.join("cache").as_os_str())]);
+            }
+            let result = python.run_sync(&[
+                OsString::from("-m"),
+                "pip".into(),
+                "install".into(),
+                "-r".into(),
+                requirements.clone().into(),
+            ]);
+
+            // Remove the file, so it is not there next time
+            remove_file(requirements).ok();
+
+            result?;
+        } else if self.package_manager == "uv" {
+            tracing::info!(
+                "Installing packages into `{}` using uv",
+                venv_path.display()
+            );
+
+            let mut uv = UvBinary {}.require_version_sync(">=0.4.4")?;
+            let mut envs: Vec<(OsString, OsString)> = vec![
+                // Install into the virtual environment (instead of `.venv` in the project)
+                ("UV_PROJECT_ENVIRONMENT".into(), venv_path.clone().into()),
+            ];
+
+            // If not a local build use `layer_path/cache` as the uv cache
+            if !context.is_local() {
+                envs.push(("UV_CACHE_DIR".into(), layer_path.join("cache").into()));
+            }
+            uv.env_list(&envs);
+
+            // Use `--frozen` so that `uv.lock` is used as is, without checking it is up to date
+            uv.run_sync(&[
+                OsString::from("sync"),
+                "--frozen".into(),
+                "--project".into(),
+                app_path.into(),
+            ])?;
+        } else if self.package_manager == "pip" {
             tracing::info!(
                 "Installing packages into `{}` using Pip",
                 venv_path.display()
@@ -738,3 +1478,150 @@ impl VenvLayer {
         LayerResultBuilder::new(self.clone()).env(layer_env).build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buildpack::common::tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn environment_yml_python() {
+        let python = |yaml: &str| {
+            let environment = serde_yaml::from_str(yaml).expect("Should be valid YAML");
+            PythonBuildpack::environment_yml_python(&environment)
+        };
+
+        assert_eq!(
+            python("dependencies: [python=3.10]"),
+            Some("~3.10".to_string())
+        );
+        assert_eq!(
+            python("dependencies: [python==3.10.4]"),
+            Some("=3.10.4".to_string())
+        );
+        assert_eq!(
+            python("dependencies: ['conda-forge::python 3.10.*']"),
+            Some("~3.10".to_string())
+        );
+        assert_eq!(
+            python("dependencies: ['python>=3.9,<3.12']"),
+            Some(">=3.9,<3.12".to_string())
+        );
+        assert_eq!(
+            python("dependencies: [python-dateutil, numpy, python=3.9]"),
+            Some("~3.9".to_string())
+        );
+        assert_eq!(python("dependencies: [python]"), None);
+        assert_eq!(python("dependencies: [{pip: [requests]}]"), None);
+        assert_eq!(python("name: test"), None);
+    }
+
+    #[test]
+    fn pep440_to_semver() {
+        let semver = PythonBuildpack::pep440_to_semver;
+        assert_eq!(semver(">=3.10"), ">=3.10");
+        assert_eq!(semver(">= 3.8, < 3.12"), ">=3.8, <3.12");
+        assert_eq!(semver("~=3.10"), "^3.10");
+        assert_eq!(semver("~=3.10.2"), "~3.10.2");
+        assert_eq!(semver("==3.10.*"), "3.10.*");
+        assert_eq!(semver("==3.10.4"), "=3.10.4");
+        assert_eq!(semver("===3.10.4"), "=3.10.4");
+        assert_eq!(semver(">=3.8,!=3.9.0"), ">=3.8");
+        assert_eq!(semver(""), "");
+    }
+
+    /// Test that `detect` generates the expected build plan for each of the
+    /// Python fixture projects
+    ///
+    /// The fixtures are detected in a single test because `detect` is run within
+    /// the current working directory.
+    #[test]
+    fn detect() -> eyre::Result<()> {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../../fixtures/projects/python")
+            .canonicalize()?;
+        let current = current_dir()?;
+
+        for (fixture, expected) in [
+            (
+                "environment-yml",
+                vec![
+                    ("micromamba", vec![("source", "environment.yml")]),
+                    ("conda", vec![("environment_file", "environment.yml")]),
+                ],
+            ),
+            (
+                "pipfile-lock",
+                vec![
+                    (
+                        "python",
+                        vec![("source", "Pipfile.lock"), ("version", "~3.10")],
+                    ),
+                    ("pipenv", vec![("source", "Pipfile.lock")]),
+                    ("venv", vec![("package_manager", "pipenv")]),
+                ],
+            ),
+            (
+                "uv-lock",
+                vec![
+                    ("python", vec![("source", "uv.lock"), ("version", ">=3.10")]),
+                    ("uv", vec![("source", "uv.lock")]),
+                    ("venv", vec![("package_manager", "uv")]),
+                ],
+            ),
+        ] {
+            let temp = tempdir()?;
+            let platform_dir = temp.path().join("platform");
+            create_dir_all(&platform_dir)?;
+            let build_plan = temp.path().join("build-plan.toml");
+
+            set_current_dir(fixtures.join(fixture))?;
+            let code = PythonBuildpack.detect_with(&platform_dir, &build_plan);
+            set_current_dir(&current)?;
+            assert_eq!(code?, 0, "{} should pass detection", fixture);
+
+            let plan: toml::Value = toml::from_str(&read_to_string(&build_plan)?)?;
+            let requires = plan
+                .get("requires")
+                .and_then(|requires| requires.as_array())
+                .cloned()
+                .unwrap_or_default();
+            assert_eq!(
+                requires
+                    .iter()
+                    .filter_map(|require| require.get("name")?.as_str())
+                    .collect::<Vec<_>>(),
+                expected.iter().map(|(name, ..)| *name).collect::<Vec<_>>(),
+                "{}",
+                fixture
+            );
+            for (require, (name, metadata)) in requires.iter().zip(expected) {
+                for (key, value) in metadata {
+                    assert_eq!(
+                        require
+                            .get("metadata")
+                            .and_then(|metadata| metadata.get(key))
+                            .and_then(|value| value.as_str()),
+                        Some(value),
+                        "{} {} {}",
+                        fixture,
+                        name,
+                        key
+                    );
+                }
+            }
+
+            // Python is installed into the Conda environment and its version is
+            // only passed on if it is not in `environment.yml`
+            if fixture == "environment-yml" {
+                assert!(requires[1]
+                    .get("metadata")
+                    .and_then(|metadata| metadata.get("python_version"))
+                    .is_none());
+            }
+        }
+
+        Ok(())
+    }
+}
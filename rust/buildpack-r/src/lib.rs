@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     env,
     fs::{self, create_dir_all, read_to_string, remove_file, write},
     io::Write,
@@ -57,8 +58,10 @@ impl Buildpack for RBuildpack {
             .ok()
             .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
 
-        // Read `DESCRIPTION` for parsing for R version
-        let description = read_to_string(DESCRIPTION).ok();
+        // Read `DESCRIPTION` for R version and packages
+        let description = read_to_string(DESCRIPTION)
+            .ok()
+            .map(|content| Self::description_fields(&content));
 
         // Check for any R files. This is used to detect if to add a Renv layer (which
         // is not required is R is only in `.tool-versions`)
@@ -87,7 +90,7 @@ impl Buildpack for RBuildpack {
         let mut requires = Vec::new();
         let mut provides = Vec::new();
 
-        // Resolve R version from `.tool-versions`, `DESCRIPTION` or `renv`
+        // Resolve R version from `.tool-versions`, `renv.lock` or `DESCRIPTION`
         let (version, source) =
             if let Some(version) = tool_versions.get("r").or_else(|| tool_versions.get("R")) {
                 (version.to_string(), TOOL_VERSIONS)
@@ -97,6 +100,11 @@ impl Buildpack for RBuildpack {
                     .and_then(|version| version.as_str().map(|version| version.to_string()))
             }) {
                 (version, RENV_LOCK)
+            } else if let Some(version) = description
+                .as_ref()
+                .and_then(|fields| Self::description_dependencies(fields).0)
+            {
+                (version, DESCRIPTION)
             } else {
                 ("*".to_string(), "")
             };
@@ -136,6 +144,21 @@ impl Buildpack for RBuildpack {
             );
             requires.push(require);
             provides.push(provide);
+        } else if description
+            .as_ref()
+            .map(|fields| !Self::description_dependencies(fields).1.is_empty())
+            .unwrap_or(false)
+        {
+            let (require, provide) = Self::require_and_provide(
+                "renv",
+                DESCRIPTION,
+                "Install R packages listed in `DESCRIPTION` into `renv`",
+                Some(hashmap! {
+                    "method" => "description".to_string()
+                }),
+            );
+            requires.push(require);
+            provides.push(provide);
         } else if r_files_exist {
             // Default behavior is to use `renv`'s init method that scans files,
             // including DESCRIPTION files and `library` statements, for R packages
@@ -178,6 +201,126 @@ impl Buildpack for RBuildpack {
     }
 }
 
+impl RBuildpack {
+    /// Parse the fields of a `DESCRIPTION` file
+    ///
+    /// `DESCRIPTION` files use the Debian Control File (DCF) format in which the value of
+    /// a field can be continued onto following lines by indenting them.
+    fn description_fields(content: &str) -> HashMap<String, String> {
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in content.lines() {
+            if line.starts_with(char::is_whitespace) {
+                if let Some(value) = current.as_ref().and_then(|name| fields.get_mut(name)) {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(line.trim());
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                let name = name.trim().to_string();
+                fields.insert(name.clone(), value.trim().to_string());
+                current = Some(name);
+            }
+        }
+        fields
+    }
+
+    /// Get the R version requirement, and the packages to install, from the fields of a `DESCRIPTION` file
+    ///
+    /// The packages are those in the `Depends`, `Imports` and `LinkingTo` fields. Any packages
+    /// in the `Remotes` field (e.g. `github::user/repo`, `bioc::package`) are placed first, and
+    /// replace the package of the same name, so that they are installed from the remote.
+    fn description_dependencies(fields: &HashMap<String, String>) -> (Option<String>, Vec<String>) {
+        let mut version = None;
+        let mut packages: Vec<String> = Vec::new();
+        for field in ["Depends", "Imports", "LinkingTo"] {
+            let value = match fields.get(field) {
+                Some(value) => value,
+                None => continue,
+            };
+            for dependency in value.split(',') {
+                let dependency = dependency.trim();
+                // Dependencies may have a version constraint e.g. `R (>= 4.0.0)`, `dplyr (>= 1.0)`
+                let (name, constraint) = match dependency.split_once('(') {
+                    Some((name, constraint)) => (
+                        name.trim(),
+                        Some(constraint.trim_end_matches(')').replace(' ', "")),
+                    ),
+                    None => (dependency, None),
+                };
+                if name.is_empty() {
+                    continue;
+                } else if name == "R" {
+                    version = constraint;
+                } else if !packages.iter().any(|package| package == name) {
+                    packages.push(name.to_string());
+                }
+            }
+        }
+
+        let remotes: Vec<String> = fields
+            .get("Remotes")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|remote| remote.trim().to_string())
+                    .filter(|remote| !remote.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let packages = remotes
+            .iter()
+            .cloned()
+            .chain(packages.into_iter().filter(|package| {
+                !remotes
+                    .iter()
+                    .any(|remote| Self::remote_package(remote) == package)
+            }))
+            .collect();
+
+        (version, packages)
+    }
+
+    /// Get the names and sources (e.g. `Repository`, `Bioconductor`, `GitHub`) of the packages in a `renv.lock` file
+    fn renv_lock_packages(lock: &serde_json::Value) -> Vec<(String, String)> {
+        lock.get("Packages")
+            .and_then(|packages| packages.as_object())
+            .map(|packages| {
+                packages
+                    .iter()
+                    .map(|(name, package)| {
+                        let source = package
+                            .get("Source")
+                            .and_then(|source| source.as_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        (name.clone(), source)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Generate an R character vector from a list of strings e.g. `c("a", "b")`
+    fn r_vector<'a>(items: impl Iterator<Item = &'a str>) -> String {
+        format!(
+            "c({})",
+            items
+                .map(|item| format!("\"{}\"", item))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+
+    /// Get the name of the package from a remote specification e.g. `github::user/repo@ref` => `repo`
+    fn remote_package(remote: &str) -> &str {
+        let remote = remote.rsplit("::").next().unwrap_or(remote);
+        let remote = remote.rsplit('/').next().unwrap_or(remote);
+        remote.split(['@', '#']).next().unwrap_or(remote)
+    }
+}
+
 struct RLayer {
     /// The semantic version requirement for the `R` binary
     requirement: String,
@@ -509,6 +652,7 @@ struct RenvLayer {
     /// The method used to do the installation of packages
     ///
     /// - "restore": use `renv::restore()` to install packages based on an existing a `renv.lock` file
+    /// - "description": use `renv::install()` to install the packages listed in a `DESCRIPTION` file
     /// - "init": use `renv::init()` to install packages discovered in R files (including `DESCRIPTION`)
     /// - "rscript": run an R script (usually `install.R`)
     method: String,
@@ -520,9 +664,16 @@ struct RenvLayer {
 
     // Hash of files that affect which packages/versions are installed into `renv`
     //
-    // The hash is the combined contents of `renv.lock` and `install.R`.
+    // The hash is of the file used by the method (i.e. the digest of `renv.lock` when restoring)
+    // or, for "init", the combined contents of `renv.lock`, `install.R` and `DESCRIPTION`.
     // This means that if any one is changed or removed that the hash will change.
     packages_hash: String,
+
+    /// The R packages that failed to install
+    ///
+    /// Used to retry installing them the next time the layer is built.
+    #[serde(default)]
+    failed_packages: Vec<String>,
 }
 
 impl RenvLayer {
@@ -538,18 +689,25 @@ impl RenvLayer {
             .and_then(|version| RBinary {}.semver_version_minor(&version))
             .unwrap_or_default();
 
+        let files = match method.as_str() {
+            "restore" => vec![RENV_LOCK],
+            "description" => vec![DESCRIPTION],
+            "rscript" => vec![INSTALL_R],
+            _ => vec![RENV_LOCK, INSTALL_R, DESCRIPTION],
+        };
         let packages_hash = str_sha256_hex(
-            &[
-                read_to_string(app_path.join(RENV_LOCK)).unwrap_or_default(),
-                read_to_string(app_path.join(INSTALL_R)).unwrap_or_default(),
-            ]
-            .concat(),
+            &files
+                .iter()
+                .map(|file| read_to_string(app_path.join(file)).unwrap_or_default())
+                .collect::<Vec<String>>()
+                .concat(),
         );
 
         RenvLayer {
             method,
             minor_version,
             packages_hash,
+            failed_packages: Vec::new(),
         }
     }
 }
@@ -582,6 +740,12 @@ impl Layer for RenvLayer {
         } else if self.packages_hash != existing.packages_hash {
             tracing::info!("Existing `renv` layer has different packages hash; will update",);
             ExistingLayerStrategy::Update
+        } else if !existing.failed_packages.is_empty() {
+            tracing::info!(
+                "Existing `renv` layer has packages that failed to install (`{}`); will update",
+                existing.failed_packages.join("`, `")
+            );
+            ExistingLayerStrategy::Update
         } else {
             tracing::info!("Existing `renv` layer meets requirements; will keep",);
             ExistingLayerStrategy::Keep
@@ -614,6 +778,7 @@ impl RenvLayer {
         context: &BuildContext<RBuildpack>,
         layer_path: &Path,
     ) -> Result<LayerResult<RenvLayer>, eyre::Report> {
+        let app_path = &context.app_dir.canonicalize()?;
         let layer_path = &layer_path.canonicalize()?;
 
         tracing::info!("Installing R packages into `renv` layer");
@@ -627,6 +792,11 @@ impl RenvLayer {
         let renv_paths_cache = layer_path.join("cache");
         create_dir_all(&renv_paths_cache)?;
 
+        // The packages expected to be installed and a file to write the names of the packages
+        // that are actually installed to
+        let mut expected: Option<Vec<String>> = None;
+        let installed_file = layer_path.join("installed.txt");
+
         let expr = if self.method == "rscript" {
             // Determine which file to run
             let file = if PathBuf::from(INSTALL_R).exists() {
@@ -651,11 +821,6 @@ source("{install_script}")
                 install_script = file
             )
         } else {
-            tracing::info!(
-                "Installing packages into `renv` using `renv::{}`",
-                self.method
-            );
-
             // If not a local build use the layer as the renv cache
             if !context.is_local() {
                 r.env_list(&[
@@ -664,24 +829,156 @@ source("{install_script}")
                 ]);
             }
 
-            // Run a script that does the install if necessary and options for non-interactive use
+            // Generate R code, specific to the method, for doing the install. For the "restore" and
+            // "description" methods, the names of the packages expected to be installed are also
+            // collected so that any that fail can be reported.
+            let (prelude, install, packages) = match self.method.as_str() {
+                "restore" => {
+                    let lockfile = app_path.join(RENV_LOCK);
+                    let packages = read_to_string(&lockfile)
+                        .ok()
+                        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+                        .map(|lock| RBuildpack::renv_lock_packages(&lock))
+                        .unwrap_or_default();
+
+                    let count = |sources: &[&str]| {
+                        packages
+                            .iter()
+                            .filter(|(_, source)| sources.contains(&source.as_str()))
+                            .count()
+                    };
+                    let bioconductor = count(&["Bioconductor"]);
+                    let remotes = count(&["GitHub", "GitLab", "Bitbucket"]);
+                    tracing::info!(
+                        "Restoring {} packages from `{}` ({} from Bioconductor, {} from GitHub or other remotes)",
+                        packages.len(),
+                        RENV_LOCK,
+                        bioconductor,
+                        remotes
+                    );
+                    if remotes > 0 && env::var_os("GITHUB_PAT").is_none() {
+                        tracing::info!("Set `GITHUB_PAT` to avoid rate limits when installing packages from GitHub");
+                    }
+
+                    // `renv` requires `BiocManager` to restore packages from Bioconductor
+                    let prelude = if bioconductor > 0 {
+                        r#"if (!requireNamespace("BiocManager", quietly=TRUE)) install.packages("BiocManager")"#
+                    } else {
+                        ""
+                    }
+                    .to_string();
+
+                    // Restore all packages and then, if that fails part way, attempt to restore
+                    // each missing package individually so that one failure does not prevent others
+                    let names =
+                        RBuildpack::r_vector(packages.iter().map(|(name, _)| name.as_str()));
+                    let install = format!(
+                        r#"
+tryCatch(
+    renv::restore(lockfile = "{lockfile}", prompt = FALSE),
+    error = function(error) message("Error restoring packages: ", conditionMessage(error))
+)
+for (pkg in setdiff({names}, rownames(installed.packages()))) tryCatch(
+    renv::restore(lockfile = "{lockfile}", packages = pkg, prompt = FALSE),
+    error = function(error) message("Error restoring package `", pkg, "`: ", conditionMessage(error))
+)"#,
+                        lockfile = lockfile.display(),
+                        names = names
+                    );
+
+                    let expected = packages.into_iter().map(|(name, _)| name).collect();
+                    (prelude, install, Some(expected))
+                }
+                "description" => {
+                    let fields = RBuildpack::description_fields(
+                        &read_to_string(app_path.join(DESCRIPTION)).unwrap_or_default(),
+                    );
+                    let (.., packages) = RBuildpack::description_dependencies(&fields);
+                    tracing::info!(
+                        "Installing {} packages listed in `{}`",
+                        packages.len(),
+                        DESCRIPTION
+                    );
+
+                    // Packages with `biocViews` are Bioconductor packages and usually depend
+                    // upon other Bioconductor packages so use those repositories as well as CRAN
+                    let prelude = if fields.contains_key("biocViews") {
+                        r#"
+if (!requireNamespace("BiocManager", quietly=TRUE)) install.packages("BiocManager")
+options(repos = BiocManager::repositories())"#
+                    } else {
+                        ""
+                    }
+                    .to_string();
+
+                    // Install each package individually so that one failure does not prevent others
+                    let install = format!(
+                        r#"
+for (pkg in {packages}) tryCatch(
+    renv::install(pkg, prompt = FALSE),
+    error = function(error) message("Error installing package `", pkg, "`: ", conditionMessage(error))
+)"#,
+                        packages =
+                            RBuildpack::r_vector(packages.iter().map(|package| package.as_str()))
+                    );
+
+                    let expected = packages
+                        .iter()
+                        .map(|package| RBuildpack::remote_package(package).to_string())
+                        .collect();
+                    (prelude, install, Some(expected))
+                }
+                _ => {
+                    tracing::info!("Installing packages into `renv` using `renv::init`");
+                    (
+                        "renv::init()".to_string(),
+                        "renv::restore(prompt = FALSE)".to_string(),
+                        None,
+                    )
+                }
+            };
+            expected = packages;
+
+            // Run a script that does the install if necessary and options for non-interactive use.
+            // The names of installed packages are written to a file to check for failures.
             format!(
                 r#"
 options(renv.consent = TRUE)
 if (!suppressMessages(require(renv, quietly=TRUE))) install.packages("renv")
-{}
+{prelude}
 renv::activate()
-renv::restore(prompt = FALSE)"#,
-                if self.method == "init" {
-                    "renv::init()"
-                } else {
-                    ""
-                }
+{install}
+writeLines(rownames(installed.packages()), "{installed_file}")"#,
+                prelude = prelude,
+                install = install,
+                installed_file = installed_file.display()
             )
         };
         // Do not use --vanilla or --no-site-file here because we want the `Rprofile.site` from above to be used
         r.run_sync(&["--slave", "--no-restore", "-e", &expr])?;
 
+        // Report any packages that failed to install
+        let mut failed_packages = Vec::new();
+        if let Some(packages) = expected {
+            let installed = read_to_string(&installed_file).unwrap_or_default();
+            let installed: Vec<&str> = installed.lines().collect();
+            failed_packages = packages
+                .into_iter()
+                .filter(|package| !installed.contains(&package.as_str()))
+                .collect();
+            remove_file(&installed_file).ok();
+
+            if failed_packages.is_empty() {
+                tracing::info!("All R packages were installed successfully");
+            } else {
+                tracing::warn!(
+                    "{} R package(s) failed to install (see above for details): `{}`",
+                    failed_packages.len(),
+                    failed_packages.join("`, `")
+                );
+            }
+        }
+
         let layer_env = LayerEnv::new()
             // Add `renv/library` to the R_LIBS_USER
             // See https://stat.ethz.ch/R-manual/R-devel/library/base/html/libPaths.html for more
@@ -706,6 +1003,86 @@ renv::restore(prompt = FALSE)"#,
                 renv_paths_cache,
             );
 
-        LayerResultBuilder::new(self.clone()).env(layer_env).build()
+        let metadata = RenvLayer {
+            failed_packages,
+            ..self.clone()
+        };
+
+        LayerResultBuilder::new(metadata).env(layer_env).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the path of an R fixture project
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../../fixtures/projects/r")
+            .join(name)
+    }
+
+    #[test]
+    fn description_fields() {
+        let fields = RBuildpack::description_fields(
+            "Package: test\nTitle: A title that is\n  continued\nImports:\n    dplyr,\n    tidyr\n",
+        );
+        assert_eq!(fields["Package"], "test");
+        assert_eq!(fields["Title"], "A title that is continued");
+        assert_eq!(fields["Imports"], "dplyr, tidyr");
+        assert!(!fields.contains_key("Depends"));
+    }
+
+    #[test]
+    fn description_dependencies() -> eyre::Result<()> {
+        let content = read_to_string(fixture("description-remotes").join(DESCRIPTION))?;
+        let fields = RBuildpack::description_fields(&content);
+        let (version, packages) = RBuildpack::description_dependencies(&fields);
+        assert_eq!(version, Some(">=4.0.0".to_string()));
+        assert_eq!(
+            packages,
+            vec!["WinVector/wrapr", "bioc::BiocGenerics", "jsonlite"]
+        );
+
+        let fields = RBuildpack::description_fields(
+            "Depends: ggplot2\nImports: dplyr (>= 1.0), ggplot2\nLinkingTo: Rcpp\n",
+        );
+        let (version, packages) = RBuildpack::description_dependencies(&fields);
+        assert_eq!(version, None);
+        assert_eq!(packages, vec!["ggplot2", "dplyr", "Rcpp"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remote_package() {
+        assert_eq!(RBuildpack::remote_package("WinVector/wrapr"), "wrapr");
+        assert_eq!(RBuildpack::remote_package("github::user/repo@v1.0"), "repo");
+        assert_eq!(RBuildpack::remote_package("user/repo#123"), "repo");
+        assert_eq!(
+            RBuildpack::remote_package("bioc::BiocGenerics"),
+            "BiocGenerics"
+        );
+        assert_eq!(RBuildpack::remote_package("dplyr"), "dplyr");
+    }
+
+    #[test]
+    fn renv_lock_packages() -> eyre::Result<()> {
+        let json = read_to_string(fixture("renv-lock-remotes").join(RENV_LOCK))?;
+        let lock = serde_json::from_str(&json)?;
+        let packages = RBuildpack::renv_lock_packages(&lock);
+        assert_eq!(
+            packages,
+            [
+                ("BiocGenerics", "Bioconductor"),
+                ("jsonlite", "Repository"),
+                ("renv", "Repository"),
+                ("wrapr", "GitHub"),
+            ]
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+        );
+
+        Ok(())
     }
 }
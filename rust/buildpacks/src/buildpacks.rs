@@ -164,7 +164,7 @@ impl Buildpacks {
     ///
     /// Converts non-alphanumeric characters to underscores as seems
     /// to be the convention used by Pack
-    fn slugify_buildpack_id(buildpack_id: &BuildpackId) -> String {
+    pub fn slugify_buildpack_id(buildpack_id: &BuildpackId) -> String {
         static REGEX: Lazy<Regex> =
             Lazy::new(|| Regex::new("[^A-Za-z0-9 ]").expect("Unable to create regex"));
 
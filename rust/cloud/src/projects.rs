@@ -457,7 +457,7 @@ pub mod cli {
     }
 
     #[derive(Parser)]
-    enum Action {
+    pub enum Action {
         List(List),
         Show(Show),
         Create(Create),
@@ -471,7 +471,14 @@ pub mod cli {
     #[async_trait]
     impl Run for Command {
         async fn run(&self) -> Result {
-            match &self.action {
+            self.action.run().await
+        }
+    }
+
+    #[async_trait]
+    impl Run for Action {
+        async fn run(&self) -> Result {
+            match self {
                 Action::List(action) => action.run().await,
                 Action::Show(action) => action.run().await,
                 Action::Create(action) => action.run().await,
@@ -496,7 +503,7 @@ pub mod cli {
     /// projects that you are not a member of but which are public. Use the `--role` flag to only
     /// include projects for which you have a particular role.
     #[derive(Default, Parser)]
-    struct List {
+    pub struct List {
         /// A search string to filter projects by
         #[clap(short, long)]
         search: Option<String>,
@@ -549,7 +556,7 @@ pub mod cli {
     /// By default, this command shows details for the current project. Use the `--project` option
     /// to target another project.
     #[derive(Parser)]
-    struct Show {
+    pub struct Show {
         #[clap(flatten)]
         project: ProjectArg,
     }
@@ -570,7 +577,7 @@ pub mod cli {
     /// Use the `--org` option to select the organization for the project.
     #[derive(Parser)]
     #[clap(alias = "init")]
-    struct Create {
+    pub struct Create {
         /// The name of the project
         ///
         /// Must be unique within the organization. Defaults to a randomly generated name.
@@ -613,7 +620,7 @@ pub mod cli {
     ///
     /// Use this command to create a local clone of a project on Stencila Cloud.
     #[derive(Parser)]
-    struct Clone {
+    pub struct Clone {
         /// The id of the project to clone
         project: u64,
 
@@ -636,7 +643,7 @@ pub mod cli {
     /// Updates the local project configuration file (e.g. `stencila.yaml`) from Stencila Cloud.
     /// The file must have a project id.
     #[derive(Parser)]
-    struct Pull;
+    pub struct Pull;
 
     #[async_trait]
     impl Run for Pull {
@@ -651,7 +658,7 @@ pub mod cli {
     /// Updates the project on Stencila Cloud based on the local configuration file (e.g. `stencila.yaml`).
     /// The file must have a project id. You can create a new project from a file with no id using `stencila projects create --from <file>`.
     #[derive(Parser)]
-    struct Push;
+    pub struct Push;
 
     #[async_trait]
     impl Run for Push {
@@ -670,7 +677,7 @@ pub mod cli {
     /// Only project owners can delete a project. Because a project can not be un-deleted,
     /// this command asks you to confirm by typing the name of the project.
     #[derive(Parser)]
-    struct Delete {
+    pub struct Delete {
         #[clap(flatten)]
         project: ProjectArg,
     }
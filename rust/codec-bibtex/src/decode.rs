@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+
+use codec::common::{
+    eyre::{bail, Result},
+    serde_json::{self, json},
+};
+
+use crate::ITEM_TYPES;
+
+/// Decode BibTeX into a list of CSL-JSON items
+pub(crate) fn decode(bibtex: &str) -> Result<Vec<serde_json::Value>> {
+    let entries = Parser::new(bibtex).parse()?;
+    Ok(entries.into_iter().map(entry_to_item).collect())
+}
+
+/// A BibTeX entry
+///
+/// Field names are lowercased and field values have had macros
+/// expanded and concatenations applied but are otherwise raw (i.e. may
+/// contain braces and LaTeX commands).
+struct Entry {
+    type_: String,
+    key: String,
+    fields: Vec<(String, String)>,
+}
+
+/// A parser for BibTeX
+///
+/// A simple recursive descent parser. Text outside of entries is ignored
+/// (as it is by BibTeX itself).
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    macros: HashMap<String, String>,
+}
+
+impl Parser {
+    fn new(bibtex: &str) -> Self {
+        let macros = [
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(index, month)| (month.to_string(), (index + 1).to_string()))
+        .collect();
+
+        Self {
+            chars: bibtex.chars().collect(),
+            pos: 0,
+            macros,
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        while self.skip_to('@') {
+            self.pos += 1;
+            let start = self.pos;
+            let type_ = self.identifier().to_lowercase();
+            self.skip_whitespace();
+            let close = match self.peek() {
+                Some('{') if !type_.is_empty() => '}',
+                Some('(') if !type_.is_empty() => ')',
+                _ => {
+                    // Not the start of an entry (e.g. an email address in text outside
+                    // of entries) so continue scanning from after the `@`
+                    self.pos = start;
+                    continue;
+                }
+            };
+            self.pos += 1;
+            match type_.as_str() {
+                "comment" | "preamble" => {
+                    self.balanced(close)?;
+                }
+                "string" => {
+                    self.skip_whitespace();
+                    let name = self.identifier().to_lowercase();
+                    self.skip_whitespace();
+                    if self.next() != Some('=') {
+                        bail!("Expected `=` after `@string` name `{}`", name)
+                    }
+                    let value = self.value()?;
+                    self.macros.insert(name, value);
+                    self.skip_whitespace();
+                    if self.next() != Some(close) {
+                        bail!("Expected `{}` at end of `@string`", close)
+                    }
+                }
+                _ => entries.push(self.entry(type_, close)?),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Parse the key and fields of an entry
+    fn entry(&mut self, type_: String, close: char) -> Result<Entry> {
+        self.skip_whitespace();
+        let mut key = String::new();
+        while let Some(char) = self.peek() {
+            if char == ',' || char == close {
+                break;
+            }
+            key.push(char);
+            self.pos += 1;
+        }
+        let key = key.trim().to_string();
+
+        let mut fields = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(char) if char == close => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => {}
+                None => bail!("Unexpected end of input in entry `{}`", key),
+            }
+
+            let name = self.identifier().to_lowercase();
+            if name.is_empty() {
+                bail!("Expected a field name in entry `{}`", key)
+            }
+            self.skip_whitespace();
+            if self.next() != Some('=') {
+                bail!("Expected `=` after field `{}` in entry `{}`", name, key)
+            }
+            let value = self.value()?;
+            fields.push((name, value));
+        }
+
+        Ok(Entry { type_, key, fields })
+    }
+
+    /// Parse a field value, expanding macros and applying `#` concatenations
+    fn value(&mut self) -> Result<String> {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.pos += 1;
+                    value.push_str(&self.balanced('}')?);
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    value.push_str(&self.quoted()?);
+                }
+                Some(char) if char.is_ascii_digit() => {
+                    while let Some(char) = self.peek() {
+                        if !char.is_ascii_digit() {
+                            break;
+                        }
+                        value.push(char);
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => {
+                    let name = self.identifier();
+                    if name.is_empty() {
+                        bail!("Expected a field value")
+                    }
+                    match self.macros.get(&name.to_lowercase()) {
+                        Some(expansion) => value.push_str(expansion),
+                        None => value.push_str(&name),
+                    }
+                }
+                None => bail!("Unexpected end of input in field value"),
+            }
+
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Get the content up to the closing char, allowing for nested braces
+    fn balanced(&mut self, close: char) -> Result<String> {
+        let mut content = String::new();
+        let mut depth = 0;
+        while let Some(char) = self.next() {
+            match char {
+                '\\' => {
+                    content.push(char);
+                    if let Some(escaped) = self.next() {
+                        content.push(escaped);
+                    }
+                    continue;
+                }
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                _ if char == close && depth == 0 => return Ok(content),
+                _ => {}
+            }
+            content.push(char);
+        }
+        bail!("Unbalanced braces: expected `{}`", close)
+    }
+
+    /// Get the content up to a closing double quote that is not within braces
+    fn quoted(&mut self) -> Result<String> {
+        let mut content = String::new();
+        let mut depth = 0;
+        while let Some(char) = self.next() {
+            match char {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' if depth == 0 => return Ok(content),
+                _ => {}
+            }
+            content.push(char);
+        }
+        bail!("Unterminated quoted value")
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut identifier = String::new();
+        while let Some(char) = self.peek() {
+            if char.is_alphanumeric() || "_-:.+/'".contains(char) {
+                identifier.push(char);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        identifier
+    }
+
+    fn skip_to(&mut self, target: char) -> bool {
+        while let Some(char) = self.peek() {
+            if char == target {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(char) if char.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek();
+        self.pos += 1;
+        char
+    }
+}
+
+/// Convert a BibTeX entry into a CSL-JSON item
+fn entry_to_item(entry: Entry) -> serde_json::Value {
+    let mut item = serde_json::Map::new();
+
+    if !entry.key.is_empty() {
+        item.insert("id".to_string(), json!(entry.key));
+    }
+
+    let type_ = ITEM_TYPES
+        .iter()
+        .find(|(bibtex, ..)| *bibtex == entry.type_)
+        .map(|(.., csl)| *csl)
+        .unwrap_or("document");
+    item.insert("type".to_string(), json!(type_));
+
+    let field = |name: &str| {
+        entry
+            .fields
+            .iter()
+            .find(|(key, ..)| key == name)
+            .map(|(.., value)| value.as_str())
+    };
+
+    if let Some(authors) = field("author") {
+        item.insert("author".to_string(), json!(decode_names(authors)));
+    }
+    if let Some(editors) = field("editor") {
+        item.insert("editor".to_string(), json!(decode_names(editors)));
+    }
+
+    if let Some(title) = field("title") {
+        item.insert("title".to_string(), json!(decode_text(title)));
+    }
+
+    if let Some(container) = field("journal")
+        .or_else(|| field("journaltitle"))
+        .or_else(|| field("booktitle"))
+    {
+        item.insert("container-title".to_string(), json!(decode_text(container)));
+    }
+
+    if let Some(date) = decode_date(field("date"), field("year"), field("month"), field("day")) {
+        item.insert("issued".to_string(), date);
+    }
+
+    if let Some(volume) = field("volume") {
+        item.insert("volume".to_string(), json!(decode_text(volume)));
+    }
+    if let Some(issue) = field("number").or_else(|| field("issue")) {
+        item.insert("issue".to_string(), json!(decode_text(issue)));
+    }
+    if let Some(pages) = field("pages") {
+        let pages = decode_text(&pages.replace("--", "-"));
+        item.insert("page".to_string(), json!(pages));
+    }
+
+    if let Some(publisher) = field("publisher")
+        .or_else(|| field("school"))
+        .or_else(|| field("institution"))
+        .or_else(|| field("organization"))
+    {
+        item.insert("publisher".to_string(), json!(decode_text(publisher)));
+    }
+
+    if let Some(doi) = field("doi") {
+        let doi = strip_braces(doi);
+        let doi = doi
+            .trim()
+            .trim_start_matches("https://doi.org/")
+            .trim_start_matches("http://dx.doi.org/");
+        item.insert("DOI".to_string(), json!(doi));
+    }
+    if let Some(url) = field("url") {
+        item.insert("URL".to_string(), json!(strip_braces(url).trim()));
+    }
+
+    if let Some(abstract_) = field("abstract") {
+        item.insert("abstract".to_string(), json!(decode_text(abstract_)));
+    }
+
+    serde_json::Value::Object(item)
+}
+
+/// Decode a list of names separated by `and` into CSL `name-variable`s
+///
+/// Names can be in "First von Last", "von Last, First" or "von Last, Jr, First" form.
+/// Names entirely wrapped in braces (e.g. `{World Health Organization}`) are
+/// decoded as `literal` names.
+fn decode_names(names: &str) -> Vec<serde_json::Value> {
+    split_top_level(names, |word| word.eq_ignore_ascii_case("and"))
+        .into_iter()
+        .filter(|words| !words.is_empty())
+        .map(|words| decode_name(&words.join(" ")))
+        .collect()
+}
+
+/// Decode a single name into a CSL `name-variable`
+fn decode_name(name: &str) -> serde_json::Value {
+    let name = name.trim();
+
+    if name.starts_with('{')
+        && name.ends_with('}')
+        && split_top_level(name, |_| false)[0].len() == 1
+    {
+        return json!({ "literal": decode_text(name) });
+    }
+
+    let parts: Vec<String> = split_commas(name)
+        .iter()
+        .map(|part| part.trim().to_string())
+        .collect();
+    let (family, given) = match parts.len() {
+        1 => {
+            let words = split_top_level(&parts[0], |_| false).remove(0);
+            // The family name starts at the first lowercase "von" word (if any)
+            // or is the last word
+            let last = words.len().saturating_sub(1);
+            let start = words
+                .iter()
+                .take(last)
+                .skip(1)
+                .position(|word| word.starts_with(|c: char| c.is_lowercase()))
+                .map(|index| index + 1)
+                .unwrap_or(last);
+            (words[start..].join(" "), words[..start].join(" "))
+        }
+        _ => (parts[0].clone(), parts[parts.len() - 1].clone()),
+    };
+
+    let mut value = serde_json::Map::new();
+    value.insert("family".to_string(), json!(decode_text(&family)));
+    if !given.is_empty() {
+        value.insert("given".to_string(), json!(decode_text(&given)));
+    }
+    serde_json::Value::Object(value)
+}
+
+/// Split a string into groups of whitespace separated words, where groups are separated
+/// by words matching the predicate
+///
+/// Whitespace and separators within braces are ignored.
+fn split_top_level<F: Fn(&str) -> bool>(string: &str, is_separator: F) -> Vec<Vec<String>> {
+    let mut groups = vec![Vec::new()];
+    let mut word = String::new();
+    let mut depth = 0;
+    let end_word = |word: &mut String, groups: &mut Vec<Vec<String>>| {
+        if word.is_empty() {
+            return;
+        }
+        if is_separator(word) {
+            groups.push(Vec::new());
+        } else if let Some(group) = groups.last_mut() {
+            group.push(word.clone());
+        }
+        word.clear();
+    };
+    for char in string.chars() {
+        match char {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if char.is_whitespace() && depth == 0 {
+            end_word(&mut word, &mut groups);
+        } else {
+            word.push(char);
+        }
+    }
+    end_word(&mut word, &mut groups);
+    groups
+}
+
+/// Split a string on commas that are not within braces
+fn split_commas(string: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    for char in string.chars() {
+        match char {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(part) = parts.last_mut() {
+            part.push(char)
+        }
+    }
+    parts
+}
+
+/// Decode a date from either a BibLaTeX `date` field, or `year`, `month` and `day` fields
+fn decode_date(
+    date: Option<&str>,
+    year: Option<&str>,
+    month: Option<&str>,
+    day: Option<&str>,
+) -> Option<serde_json::Value> {
+    if let Some(date) = date {
+        let date = strip_braces(date);
+        let parts = date
+            .trim()
+            .split('-')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>();
+        return Some(match parts {
+            Ok(parts) if !parts.is_empty() && parts.len() <= 3 => json!({ "date-parts": [parts] }),
+            _ => json!({ "raw": date.trim() }),
+        });
+    }
+
+    let year = strip_braces(year?).trim().parse::<u64>().ok()?;
+    let mut parts = vec![year];
+    if let Some(month) = month.and_then(decode_month) {
+        parts.push(month);
+        if let Some(day) = day.and_then(|day| strip_braces(day).trim().parse::<u64>().ok()) {
+            parts.push(day);
+        }
+    }
+    Some(json!({ "date-parts": [parts] }))
+}
+
+/// Decode a month number or name (e.g. `3`, `March`, `Mar`)
+fn decode_month(month: &str) -> Option<u64> {
+    let month = strip_braces(month).trim().to_lowercase();
+    if let Ok(month) = month.parse::<u64>() {
+        return Some(month);
+    }
+    [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ]
+    .iter()
+    .position(|name| month.starts_with(name))
+    .map(|index| index as u64 + 1)
+}
+
+/// Remove grouping braces from a value
+fn strip_braces(value: &str) -> String {
+    value.replace(['{', '}'], "")
+}
+
+/// Decode a text value by converting LaTeX escapes, accents and dashes to
+/// Unicode, removing braces and collapsing whitespace
+fn decode_text(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some(accent) if ACCENTS.iter().any(|(mark, ..)| *mark == accent) => {
+                    // Skip any brace or space between the accent and the letter e.g. `\"{o}`
+                    while matches!(chars.peek(), Some('{') | Some(' ')) {
+                        chars.next();
+                    }
+                    if let Some(letter) = chars.next() {
+                        match ACCENTS
+                            .iter()
+                            .find(|(mark, base, ..)| *mark == accent && *base == letter)
+                        {
+                            Some((.., accented)) => text.push(*accented),
+                            None => text.push(letter),
+                        }
+                    }
+                }
+                Some(escaped) if "&%$#_{}".contains(escaped) => text.push(escaped),
+                Some(command) if command.is_alphabetic() => {
+                    // Drop other commands (e.g. `\textit`) but keep their arguments
+                    while matches!(chars.peek(), Some(char) if char.is_alphabetic()) {
+                        chars.next();
+                    }
+                }
+                Some(other) => text.push(other),
+                None => {}
+            },
+            '~' => text.push(' '),
+            '{' | '}' => {}
+            _ => text.push(char),
+        }
+    }
+
+    let text = text.replace("---", "—").replace("--", "–");
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// LaTeX accent commands and the accented characters they produce for common letters
+const ACCENTS: &[(char, char, char)] = &[
+    ('"', 'a', 'ä'),
+    ('"', 'e', 'ë'),
+    ('"', 'i', 'ï'),
+    ('"', 'o', 'ö'),
+    ('"', 'u', 'ü'),
+    ('"', 'A', 'Ä'),
+    ('"', 'O', 'Ö'),
+    ('"', 'U', 'Ü'),
+    ('\'', 'a', 'á'),
+    ('\'', 'e', 'é'),
+    ('\'', 'i', 'í'),
+    ('\'', 'o', 'ó'),
+    ('\'', 'u', 'ú'),
+    ('\'', 'E', 'É'),
+    ('`', 'a', 'à'),
+    ('`', 'e', 'è'),
+    ('`', 'o', 'ò'),
+    ('^', 'a', 'â'),
+    ('^', 'e', 'ê'),
+    ('^', 'i', 'î'),
+    ('^', 'o', 'ô'),
+    ('~', 'n', 'ñ'),
+    ('~', 'a', 'ã'),
+    ('~', 'o', 'õ'),
+];
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+
+use codec::common::serde_json;
+
+/// The mapping between CSL item types and BibTeX entry types
+///
+/// Used when encoding (first match on the CSL type). Types that
+/// are not in this list are encoded as `misc`.
+const ENTRY_TYPES: &[(&str, &str)] = &[
+    ("article", "article"),
+    ("article-journal", "article"),
+    ("article-magazine", "article"),
+    ("article-newspaper", "article"),
+    ("book", "book"),
+    ("chapter", "incollection"),
+    ("dataset", "dataset"),
+    ("manuscript", "unpublished"),
+    ("pamphlet", "booklet"),
+    ("paper-conference", "inproceedings"),
+    ("report", "techreport"),
+    ("software", "software"),
+    ("thesis", "phdthesis"),
+    ("webpage", "online"),
+];
+
+/// Encode a list of CSL-JSON items as BibTeX
+pub(crate) fn encode(items: &[serde_json::Value]) -> String {
+    let mut keys = HashSet::new();
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| encode_item(item, index, &mut keys))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Encode a single CSL-JSON item as a BibTeX entry
+fn encode_item(item: &serde_json::Value, index: usize, keys: &mut HashSet<String>) -> String {
+    let string = |name: &str| item.get(name).and_then(|value| value.as_str());
+
+    let type_ = string("type")
+        .and_then(|type_| {
+            ENTRY_TYPES
+                .iter()
+                .find(|(csl, ..)| *csl == type_)
+                .map(|(.., bibtex)| *bibtex)
+        })
+        .unwrap_or("misc");
+
+    let key = unique_key(
+        string("id")
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| generate_key(item, index)),
+        keys,
+    );
+
+    let mut fields: Vec<(&str, String)> = Vec::new();
+
+    if let Some(authors) = item.get("author").and_then(encode_names) {
+        fields.push(("author", authors));
+    }
+    if let Some(editors) = item.get("editor").and_then(encode_names) {
+        fields.push(("editor", editors));
+    }
+
+    if let Some(title) = string("title") {
+        fields.push(("title", escape(title)));
+    }
+
+    if let Some(container) = string("container-title") {
+        let name = match type_ {
+            "incollection" | "inproceedings" => "booktitle",
+            _ => "journal",
+        };
+        fields.push((name, escape(container)));
+    }
+
+    if let Some(issued) = item.get("issued") {
+        match issued
+            .get("date-parts")
+            .and_then(|parts| parts.get(0))
+            .and_then(|parts| parts.as_array())
+        {
+            Some(parts) => {
+                for (name, part) in ["year", "month", "day"].iter().zip(parts) {
+                    let part = part
+                        .as_u64()
+                        .map(|part| part.to_string())
+                        .or_else(|| part.as_str().map(|part| part.to_string()));
+                    if let Some(part) = part {
+                        fields.push((*name, part));
+                    }
+                }
+            }
+            None => {
+                if let Some(raw) = issued
+                    .get("raw")
+                    .or_else(|| issued.get("literal"))
+                    .and_then(|raw| raw.as_str())
+                {
+                    fields.push(("date", escape(raw)));
+                }
+            }
+        }
+    }
+
+    if let Some(volume) = string("volume") {
+        fields.push(("volume", escape(volume)));
+    }
+    if let Some(issue) = string("issue") {
+        fields.push(("number", escape(issue)));
+    }
+    if let Some(page) = string("page") {
+        fields.push(("pages", escape(&page.replace('-', "--"))));
+    }
+
+    if let Some(publisher) = string("publisher") {
+        let name = match type_ {
+            "phdthesis" => "school",
+            "techreport" => "institution",
+            _ => "publisher",
+        };
+        fields.push((name, escape(publisher)));
+    }
+
+    // DOIs and URLs are not escaped because they are usually treated verbatim
+    if let Some(doi) = string("DOI") {
+        fields.push(("doi", doi.to_string()));
+    }
+    if let Some(url) = string("URL") {
+        fields.push(("url", url.to_string()));
+    }
+
+    if let Some(abstract_) = string("abstract") {
+        fields.push(("abstract", escape(abstract_)));
+    }
+
+    let fields = fields
+        .into_iter()
+        .map(|(name, value)| format!("  {} = {{{}}},\n", name, value))
+        .collect::<String>();
+
+    format!("@{}{{{},\n{}}}\n", type_, key, fields)
+}
+
+/// Encode a list of CSL `name-variable`s as a BibTeX name list
+///
+/// Literal names are wrapped in braces so that they are not split into parts.
+fn encode_names(names: &serde_json::Value) -> Option<String> {
+    let names = names.as_array()?;
+    let names: Vec<String> = names
+        .iter()
+        .filter_map(|name| {
+            if let Some(literal) = name.get("literal").and_then(|literal| literal.as_str()) {
+                return Some(["{", &escape(literal), "}"].concat());
+            }
+            let family = name.get("family").and_then(|family| family.as_str())?;
+            match name.get("given").and_then(|given| given.as_str()) {
+                Some(given) => Some([&escape(family), ", ", &escape(given)].concat()),
+                None => Some(escape(family)),
+            }
+        })
+        .collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join(" and "))
+    }
+}
+
+/// Generate a citation key for an item that does not have an `id`
+///
+/// Uses the family name of the first author and the year e.g. `carlsson2019`,
+/// falling back to `ref` and the position of the item e.g. `ref3`.
+fn generate_key(item: &serde_json::Value, index: usize) -> String {
+    let name = item
+        .get("author")
+        .and_then(|authors| authors.get(0))
+        .and_then(|author| author.get("family").or_else(|| author.get("literal")))
+        .and_then(|name| name.as_str())
+        .map(|name| {
+            name.chars()
+                .filter(|char| char.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|name| !name.is_empty());
+    let year = item
+        .get("issued")
+        .and_then(|issued| issued.get("date-parts"))
+        .and_then(|parts| parts.get(0))
+        .and_then(|parts| parts.get(0))
+        .and_then(|year| year.as_u64());
+
+    match (name, year) {
+        (Some(name), Some(year)) => format!("{}{}", name, year),
+        (Some(name), None) => name,
+        _ => format!("ref{}", index + 1),
+    }
+}
+
+/// Ensure that a key is unique by adding a suffix if necessary
+///
+/// Uses letters (e.g. `smith2020a`) and then numbers if those are exhausted.
+fn unique_key(key: String, keys: &mut HashSet<String>) -> String {
+    let mut unique = key.clone();
+    let mut count = 0;
+    while keys.contains(&unique) {
+        let suffix = if count < 26 {
+            char::from(b'a' + count as u8).to_string()
+        } else {
+            count.to_string()
+        };
+        unique = [key.as_str(), &suffix].concat();
+        count += 1;
+    }
+    keys.insert(unique.clone());
+    unique
+}
+
+/// Escape characters that have special meaning in LaTeX
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if "&%$#_".contains(char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
@@ -0,0 +1,196 @@
+use codec::{
+    common::eyre::Result, stencila_schema::Node, utils::vec_string, Codec, CodecTrait,
+    DecodeOptions, EncodeOptions,
+};
+use codec_csl::CslCodec;
+
+mod decode;
+mod encode;
+
+/// The mapping between BibTeX entry types and CSL item types
+///
+/// Used when decoding (first match on the BibTeX type). For encoding
+/// see `encode::ENTRY_TYPES`.
+const ITEM_TYPES: &[(&str, &str)] = &[
+    ("article", "article-journal"),
+    ("book", "book"),
+    ("booklet", "pamphlet"),
+    ("conference", "paper-conference"),
+    ("dataset", "dataset"),
+    ("inbook", "chapter"),
+    ("incollection", "chapter"),
+    ("inproceedings", "paper-conference"),
+    ("manual", "report"),
+    ("mastersthesis", "thesis"),
+    ("misc", "document"),
+    ("online", "webpage"),
+    ("phdthesis", "thesis"),
+    ("proceedings", "book"),
+    ("report", "report"),
+    ("software", "software"),
+    ("techreport", "report"),
+    ("thesis", "thesis"),
+    ("unpublished", "manuscript"),
+];
+
+/// A codec for BibTeX
+///
+/// Decodes a BibTeX file to an `Article` with each entry as one of its `references`,
+/// and encodes the `references` of an `Article` to BibTeX. Uses CSL-JSON as an intermediate
+/// representation so that the mapping to and from Stencila nodes is done by `CslCodec`.
+///
+/// Supports `@string` macros, `#` concatenation, quoted and braced values, and the
+/// most common LaTeX accent commands. `@comment` and `@preamble` entries are ignored.
+pub struct BibtexCodec;
+
+impl CodecTrait for BibtexCodec {
+    fn spec() -> Codec {
+        Codec {
+            formats: vec_string!["bibtex", "bib"],
+            root_types: vec_string!["Article"],
+            ..Default::default()
+        }
+    }
+
+    fn from_str(str: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+        let items = decode::decode(str)?;
+        CslCodec::from_json_items(&items)
+    }
+
+    fn to_string(node: &Node, _options: Option<EncodeOptions>) -> Result<String> {
+        let items = CslCodec::to_json_items(node)?;
+        Ok(encode::encode(&items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::common::serde_json;
+    use test_utils::{assert_json_eq, assert_json_is, pretty_assertions::assert_eq};
+
+    #[test]
+    fn decode() -> Result<()> {
+        let bibtex = r#"
+This text is ignored, as is this email address: someone@example.org, and this @ sign
+
+@string{ jors = "Journal of Open Research Software" }
+
+@comment{ This entry is ignored }
+
+@article{carlsson2019,
+  author = {Carlsson, Kristoffer and Fredrik Ekre},
+  title = {{Tensors.jl} --- Tensor Computations in {J}ulia},
+  journal = jors,
+  year = 2019,
+  month = mar,
+  volume = "7",
+  number = {1},
+  pages = {65--70},
+  doi = {10.5334/jors.182},
+}
+
+@book{schrodinger1944,
+  author = {Erwin Schr{\"o}dinger and {World Health Organization}},
+  title = "What is Life? " # "The Physical Aspect of the Living Cell",
+  publisher = {Cambridge University Press},
+  year = {1944}
+}
+"#;
+        let items = decode::decode(bibtex)?;
+        assert_json_is!(
+            items,
+            [{
+                "id": "carlsson2019",
+                "type": "article-journal",
+                "author": [
+                    {"family": "Carlsson", "given": "Kristoffer"},
+                    {"family": "Ekre", "given": "Fredrik"}
+                ],
+                "title": "Tensors.jl — Tensor Computations in Julia",
+                "container-title": "Journal of Open Research Software",
+                "issued": {"date-parts": [[2019, 3]]},
+                "volume": "7",
+                "issue": "1",
+                "page": "65-70",
+                "DOI": "10.5334/jors.182"
+            }, {
+                "id": "schrodinger1944",
+                "type": "book",
+                "author": [
+                    {"family": "Schrödinger", "given": "Erwin"},
+                    {"literal": "World Health Organization"}
+                ],
+                "title": "What is Life? The Physical Aspect of the Living Cell",
+                "publisher": "Cambridge University Press",
+                "issued": {"date-parts": [[1944]]}
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn encode() -> Result<()> {
+        let items = vec![serde_json::json!({
+            "type": "article-journal",
+            "author": [
+                {"family": "Carlsson", "given": "Kristoffer"},
+                {"literal": "The Julia Community"}
+            ],
+            "title": "Tensors & Julia",
+            "container-title": "Journal of Open Research Software",
+            "issued": {"date-parts": [[2019, 3, 21]]},
+            "page": "65-70",
+            "DOI": "10.5334/jors.182"
+        })];
+        assert_eq!(
+            encode::encode(&items),
+            r#"@article{carlsson2019,
+  author = {Carlsson, Kristoffer and {The Julia Community}},
+  title = {Tensors \& Julia},
+  journal = {Journal of Open Research Software},
+  year = {2019},
+  month = {3},
+  day = {21},
+  pages = {65--70},
+  doi = {10.5334/jors.182},
+}
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let bibtex = r#"@article{carlsson2019,
+  author = {Carlsson, Kristoffer and Ekre, Fredrik},
+  title = {Tensors.jl — Tensor Computations in Julia},
+  journal = {Journal of Open Research Software},
+  year = {2019},
+  volume = {7},
+  number = {1},
+  pages = {65--70},
+  publisher = {Ubiquity Press, Ltd.},
+  doi = {10.5334/jors.182},
+  url = {http://dx.doi.org/10.5334/jors.182},
+}
+
+@incollection{smith2020,
+  author = {Smith, Jane},
+  editor = {Doe, John},
+  title = {A Chapter},
+  booktitle = {A Book},
+  year = {2020},
+}
+"#;
+        let node = BibtexCodec::from_str(bibtex, None)?;
+        assert_eq!(BibtexCodec::to_string(&node, None)?, bibtex);
+
+        let again = BibtexCodec::from_str(&BibtexCodec::to_string(&node, None)?, None)?;
+        assert_json_eq!(again, node);
+
+        Ok(())
+    }
+}
@@ -2,18 +2,21 @@ pub use codec::CodecTrait;
 use codec::{
     common::{
         eyre::{bail, eyre, Result},
-        serde_json,
+        serde_json::{self, json},
     },
     stencila_schema::{
-        Article, CreativeWorkAuthors, CreativeWorkPublisher, CreativeWorkTitle, CreativeWorkTypes,
-        Date, Node, Organization, Periodical, Person, PublicationIssue,
-        PublicationIssueIssueNumber, PublicationVolume, PublicationVolumeVolumeNumber,
-        ThingIdentifiers,
+        Article, ArticlePageEnd, ArticlePageStart, CreativeWork, CreativeWorkAuthors,
+        CreativeWorkPublisher, CreativeWorkReferences, CreativeWorkTitle, CreativeWorkTypes, Date,
+        InlineContent, Node, Organization, Periodical, Person, PropertyValue, PropertyValueValue,
+        PublicationIssue, PublicationIssueIssueNumber, PublicationVolume,
+        PublicationVolumeVolumeNumber, ThingDescription, ThingIdentifiers,
     },
     utils::vec_string,
-    Codec, DecodeOptions,
+    Codec, DecodeOptions, EncodeOptions,
 };
 
+pub mod style;
+
 /// A codec for Citation Style Language (CSL) JSON
 ///
 /// This uses `serde_json` to parse the JSON into a hash map and then extracts
@@ -27,110 +30,262 @@ use codec::{
 ///
 /// but it was found to be quite strict (e.g did not handle `"type": "journal-article"`)
 /// and the parsed `Reference` still needed to be translated to a Stencila node.
+///
+/// A CSL-JSON array of items is decoded to an `Article` with those items as its `references`
+/// (and encoded back to an array). This allows CSL-JSON to be used as an intermediate
+/// representation by other bibliographic codecs (e.g. BibTeX and RIS).
 pub struct CslCodec;
 
 impl CslCodec {
+    /// Decode a single CSL item into a `Node`
     pub fn from_json(data: serde_json::Value) -> Result<Node> {
-        let mut article = Article::default();
+        decode_item(&data).map(Node::Article)
+    }
+
+    /// Decode a list of CSL items into an `Article` with those items as its `references`
+    pub fn from_json_items(items: &[serde_json::Value]) -> Result<Node> {
+        let references = items
+            .iter()
+            .map(|item| {
+                decode_item(item).map(|article| {
+                    CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(article))
+                })
+            })
+            .collect::<Result<Vec<CreativeWorkReferences>>>()?;
 
-        if let Some(title) = data.get("title") {
-            article.title = Some(Box::new(CreativeWorkTitle::String(decode_string(title)?)));
+        Ok(Node::Article(Article {
+            references: Some(references),
+            ..Default::default()
+        }))
+    }
+
+    /// Encode a single `CreativeWork` node into a CSL item
+    pub fn to_json(node: &Node) -> Result<serde_json::Value> {
+        match node {
+            Node::Article(article) => Ok(encode_item(article)),
+            Node::CreativeWork(work) => Ok(encode_item(&work_to_article(work))),
+            _ => bail!("Unable to encode node of this type as a CSL item"),
         }
+    }
 
-        if let Some(date) = data.get("published") {
-            article.date_published = Some(decode_date(date).map(Box::new)?);
+    /// Encode a node into a list of CSL items
+    ///
+    /// If the node is an `Article` with `references` then each of those references
+    /// is encoded (i.e. the article's reference list is exported). Otherwise, the
+    /// node itself is encoded as the only item in the list.
+    pub fn to_json_items(node: &Node) -> Result<Vec<serde_json::Value>> {
+        if let Node::Article(Article {
+            references: Some(references),
+            ..
+        }) = node
+        {
+            return Ok(references.iter().filter_map(encode_reference).collect());
         }
-        if let Some(date) = data.get("submitted") {
-            article.date_received = Some(decode_date(date).map(Box::new)?);
+
+        Ok(vec![Self::to_json(node)?])
+    }
+
+    /// Encode one of the `references` of a `CreativeWork` into a CSL item
+    ///
+    /// Returns `None` if the reference is not of a type that can be encoded.
+    pub fn to_json_reference(reference: &CreativeWorkReferences) -> Option<serde_json::Value> {
+        encode_reference(reference)
+    }
+}
+
+impl CodecTrait for CslCodec {
+    fn spec() -> Codec {
+        Codec {
+            formats: vec_string!["csl", "csl-json"],
+            root_types: vec_string!["Article"],
+            ..Default::default()
         }
+    }
 
-        if let Some(authors) = data.get("author") {
-            let authors = if let Some(authors) = authors.as_array() {
-                authors
-            } else {
-                bail!("Expected `authors` to be an array")
-            };
-            let authors = authors
-                .iter()
-                .map(|author| {
-                    let person = decode_person(author).unwrap();
-                    CreativeWorkAuthors::Person(person)
-                })
-                .collect();
-            article.authors = Some(authors);
+    fn from_str(str: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+        let data: serde_json::Value = serde_json::from_str(str)?;
+        match data.as_array() {
+            Some(items) => Self::from_json_items(items),
+            None => Self::from_json(data),
         }
+    }
 
-        let is_part_of = data
-            .get("container-title")
-            .and_then(|title| decode_string(title).ok())
-            .map(|name| {
-                Box::new(CreativeWorkTypes::Periodical(Periodical {
-                    name: Some(Box::new(name)),
-                    ..Default::default()
-                }))
-            });
+    fn to_string(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
+        let compact = options.map_or_else(|| false, |options| options.compact);
 
-        let is_part_of = if let Some(volume_number) = data.get("volume").and_then(|num| {
-            num.as_str()
-                .map(|num| PublicationVolumeVolumeNumber::String(num.to_string()))
-                .or_else(|| num.as_i64().map(PublicationVolumeVolumeNumber::Integer))
-        }) {
-            Some(Box::new(CreativeWorkTypes::PublicationVolume(
-                PublicationVolume {
-                    is_part_of,
-                    volume_number: Some(Box::new(volume_number)),
-                    ..Default::default()
-                },
-            )))
-        } else {
-            is_part_of
+        let json = match node {
+            Node::Article(Article {
+                references: Some(..),
+                ..
+            }) => serde_json::Value::Array(Self::to_json_items(node)?),
+            _ => Self::to_json(node)?,
         };
 
-        let is_part_of = if let Some(issue_number) = data.get("volume").and_then(|num| {
-            num.as_str()
-                .map(|num| PublicationIssueIssueNumber::String(num.to_string()))
-                .or_else(|| num.as_i64().map(PublicationIssueIssueNumber::Integer))
-        }) {
-            Some(Box::new(CreativeWorkTypes::PublicationIssue(
-                PublicationIssue {
-                    is_part_of,
-                    issue_number: Some(Box::new(issue_number)),
-                    ..Default::default()
-                },
-            )))
-        } else {
-            is_part_of
+        let json = match compact {
+            true => serde_json::to_string(&json)?,
+            false => serde_json::to_string_pretty(&json)?,
         };
+        Ok(json)
+    }
+}
+
+/// The prefix used for DOI identifiers
+const DOI_URL: &str = "https://doi.org/";
+
+/// CSL item types that are considered the default type for an `Article`
+///
+/// Other types (e.g. `book`, `chapter`) are stored in the `genre` of the
+/// `Article` so that they can be round-tripped.
+const ARTICLE_TYPES: &[&str] = &["article", "article-journal", "journal-article"];
+
+/// Decode a CSL item into an `Article`
+fn decode_item(data: &serde_json::Value) -> Result<Article> {
+    if !data.is_object() {
+        bail!("Expected a CSL item to be an object")
+    }
+
+    let mut article = Article::default();
+
+    if let Some(id) = data.get("id").and_then(|id| {
+        id.as_str()
+            .map(|id| id.to_string())
+            .or_else(|| id.as_i64().map(|id| id.to_string()))
+    }) {
+        article.id = Some(Box::new(id));
+    }
 
-        article.is_part_of = is_part_of;
+    if let Some(type_) = data.get("type").and_then(|type_| type_.as_str()) {
+        if !ARTICLE_TYPES.contains(&type_) {
+            article.genre = Some(vec![type_.to_string()]);
+        }
+    }
+
+    if let Some(title) = data.get("title") {
+        article.title = Some(Box::new(CreativeWorkTitle::String(decode_string(title)?)));
+    }
 
-        if let Some(publisher) = data.get("publisher") {
-            let name = decode_string(publisher)?;
-            article.publisher = Some(Box::new(CreativeWorkPublisher::Organization(
-                Organization {
+    if let Some(date) = data.get("issued").or_else(|| data.get("published")) {
+        article.date_published = Some(decode_date(date).map(Box::new)?);
+    }
+    if let Some(date) = data.get("submitted") {
+        article.date_received = Some(decode_date(date).map(Box::new)?);
+    }
+
+    if let Some(authors) = data.get("author") {
+        let authors = if let Some(authors) = authors.as_array() {
+            authors
+        } else {
+            bail!("Expected `authors` to be an array")
+        };
+        let authors = authors
+            .iter()
+            .map(|author| match decode_literal(author) {
+                Some(name) => Ok(CreativeWorkAuthors::Organization(Organization {
                     name: Some(Box::new(name)),
                     ..Default::default()
-                },
-            )));
-        }
+                })),
+                None => decode_person(author).map(CreativeWorkAuthors::Person),
+            })
+            .collect::<Result<Vec<CreativeWorkAuthors>>>()?;
+        article.authors = Some(authors);
+    }
 
-        Ok(Node::Article(article))
+    if let Some(editors) = data.get("editor") {
+        let editors = if let Some(editors) = editors.as_array() {
+            editors
+        } else {
+            bail!("Expected `editors` to be an array")
+        };
+        let editors = editors
+            .iter()
+            .map(decode_person)
+            .collect::<Result<Vec<Person>>>()?;
+        article.editors = Some(editors);
     }
-}
 
-impl CodecTrait for CslCodec {
-    fn spec() -> Codec {
-        Codec {
-            formats: vec_string!["csl", "csl-json"],
-            root_types: vec_string!["Article"],
-            ..Default::default()
-        }
+    let is_part_of = data
+        .get("container-title")
+        .and_then(|title| decode_string(title).ok())
+        .map(|name| {
+            Box::new(CreativeWorkTypes::Periodical(Periodical {
+                name: Some(Box::new(name)),
+                ..Default::default()
+            }))
+        });
+
+    let is_part_of = if let Some(volume_number) = data.get("volume").and_then(|num| {
+        num.as_str()
+            .map(|num| PublicationVolumeVolumeNumber::String(num.to_string()))
+            .or_else(|| num.as_i64().map(PublicationVolumeVolumeNumber::Integer))
+    }) {
+        Some(Box::new(CreativeWorkTypes::PublicationVolume(
+            PublicationVolume {
+                is_part_of,
+                volume_number: Some(Box::new(volume_number)),
+                ..Default::default()
+            },
+        )))
+    } else {
+        is_part_of
+    };
+
+    let is_part_of = if let Some(issue_number) = data.get("issue").and_then(|num| {
+        num.as_str()
+            .map(|num| PublicationIssueIssueNumber::String(num.to_string()))
+            .or_else(|| num.as_i64().map(PublicationIssueIssueNumber::Integer))
+    }) {
+        Some(Box::new(CreativeWorkTypes::PublicationIssue(
+            PublicationIssue {
+                is_part_of,
+                issue_number: Some(Box::new(issue_number)),
+                ..Default::default()
+            },
+        )))
+    } else {
+        is_part_of
+    };
+
+    article.is_part_of = is_part_of;
+
+    if let Some(page) = data.get("page").and_then(|page| {
+        page.as_str()
+            .map(|page| page.to_string())
+            .or_else(|| page.as_i64().map(|page| page.to_string()))
+    }) {
+        let (page_start, page_end, pagination) = decode_page(&page);
+        article.page_start = page_start.map(Box::new);
+        article.page_end = page_end.map(Box::new);
+        article.pagination = pagination.map(Box::new);
     }
 
-    fn from_str(str: &str, _options: Option<DecodeOptions>) -> Result<Node> {
-        let data = serde_json::from_str(str)?;
-        Self::from_json(data)
+    if let Some(publisher) = data.get("publisher") {
+        let name = decode_string(publisher)?;
+        article.publisher = Some(Box::new(CreativeWorkPublisher::Organization(
+            Organization {
+                name: Some(Box::new(name)),
+                ..Default::default()
+            },
+        )));
     }
+
+    if let Some(doi) = data.get("DOI").or_else(|| data.get("doi")) {
+        let doi = decode_string(doi)?;
+        article.identifiers = Some(vec![ThingIdentifiers::String(
+            [DOI_URL, doi.trim_start_matches(DOI_URL)].concat(),
+        )]);
+    }
+
+    if let Some(url) = data.get("URL").or_else(|| data.get("url")) {
+        article.url = Some(Box::new(decode_string(url)?));
+    }
+
+    if let Some(abstract_) = data.get("abstract") {
+        article.description = Some(Box::new(ThingDescription::String(decode_string(
+            abstract_,
+        )?)));
+    }
+
+    Ok(article)
 }
 
 /// Decode a `string` variable
@@ -141,6 +296,16 @@ fn decode_string(string: &serde_json::Value) -> Result<String> {
         .ok_or_else(|| eyre!("Expected a string value"))
 }
 
+/// Decode the `literal` of a `name-variable`
+///
+/// Literal names are used for organizations and other names that should
+/// not be split into parts.
+fn decode_literal(data: &serde_json::Value) -> Option<String> {
+    data.get("literal")
+        .and_then(|literal| literal.as_str())
+        .map(|literal| literal.to_string())
+}
+
 /// Decode a `name-variable` into a `Person`
 ///
 /// See https://github.com/citation-style-language/schema/blob/506040022f6c37846343edd36658b23e85b5b8ff/schemas/input/csl-data.json#L463
@@ -161,6 +326,7 @@ fn decode_person(data: &serde_json::Value) -> Result<Person> {
                 .map(|name| name.to_string())
                 .collect::<Vec<String>>()
         });
+    let name = decode_literal(data).map(Box::new);
     let identifiers = data
         .get("ORCID")
         .or_else(|| data.get("orcid"))
@@ -170,6 +336,7 @@ fn decode_person(data: &serde_json::Value) -> Result<Person> {
     Ok(Person {
         given_names,
         family_names,
+        name,
         identifiers,
         ..Default::default()
     })
@@ -232,10 +399,320 @@ fn decode_date(data: &serde_json::Value) -> Result<Date> {
     })
 }
 
+/// Decode a `page` variable into a start page, end page and pagination
+///
+/// A single page or page range (e.g. `65-70`) is decoded to `pageStart` and `pageEnd`.
+/// Anything more complicated (e.g. `1-6, 9, 55`) is decoded to `pagination`.
+fn decode_page(
+    page: &str,
+) -> (
+    Option<ArticlePageStart>,
+    Option<ArticlePageEnd>,
+    Option<String>,
+) {
+    let page = page.trim();
+    if page.contains(',') {
+        return (None, None, Some(page.to_string()));
+    }
+
+    let mut parts = page
+        .split(['-', '–'])
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty());
+    let start = parts
+        .next()
+        .map(|start| ArticlePageStart::String(start.to_string()));
+    let end = parts
+        .next()
+        .map(|end| ArticlePageEnd::String(end.to_string()));
+
+    (start, end, None)
+}
+
+/// Convert a `CreativeWork` into an `Article` for encoding
+///
+/// Only copies the properties that are encoded.
+fn work_to_article(work: &CreativeWork) -> Article {
+    Article {
+        id: work.id.clone(),
+        genre: work.genre.clone(),
+        title: work.title.clone(),
+        authors: work.authors.clone(),
+        editors: work.editors.clone(),
+        date_published: work.date_published.clone(),
+        date_received: work.date_received.clone(),
+        is_part_of: work.is_part_of.clone(),
+        publisher: work.publisher.clone(),
+        identifiers: work.identifiers.clone(),
+        url: work.url.clone(),
+        description: work.description.clone(),
+        ..Default::default()
+    }
+}
+
+/// Encode one of the `references` of a `CreativeWork` into a CSL item
+///
+/// String references (e.g. unstructured citations) are encoded as a `document`
+/// with the string as the title.
+fn encode_reference(reference: &CreativeWorkReferences) -> Option<serde_json::Value> {
+    match reference {
+        CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(article)) => {
+            Some(encode_item(article))
+        }
+        CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(work)) => {
+            Some(encode_item(&work_to_article(work)))
+        }
+        CreativeWorkReferences::String(string) => Some(json!({
+            "type": "document",
+            "title": string
+        })),
+        _ => None,
+    }
+}
+
+/// Encode an `Article` into a CSL item
+fn encode_item(article: &Article) -> serde_json::Value {
+    let mut item = serde_json::Map::new();
+
+    if let Some(id) = &article.id {
+        item.insert("id".to_string(), json!(id.as_str()));
+    }
+
+    let type_ = article
+        .genre
+        .iter()
+        .flatten()
+        .next()
+        .map_or("article-journal", |genre| genre.as_str());
+    item.insert("type".to_string(), json!(type_));
+
+    if let Some(title) = &article.title {
+        item.insert("title".to_string(), json!(encode_title(title)));
+    }
+
+    if let Some(authors) = &article.authors {
+        let authors: Vec<serde_json::Value> = authors
+            .iter()
+            .map(|author| match author {
+                CreativeWorkAuthors::Person(person) => encode_person(person),
+                CreativeWorkAuthors::Organization(org) => encode_organization(org),
+            })
+            .collect();
+        item.insert("author".to_string(), json!(authors));
+    }
+
+    if let Some(editors) = &article.editors {
+        let editors: Vec<serde_json::Value> = editors.iter().map(encode_person).collect();
+        item.insert("editor".to_string(), json!(editors));
+    }
+
+    if let Some(date) = &article.date_published {
+        item.insert("issued".to_string(), encode_date(date));
+    }
+    if let Some(date) = &article.date_received {
+        item.insert("submitted".to_string(), encode_date(date));
+    }
+
+    if let Some(is_part_of) = &article.is_part_of {
+        encode_is_part_of(is_part_of, &mut item);
+    }
+
+    if let Some(page) = encode_page(article) {
+        item.insert("page".to_string(), json!(page));
+    }
+
+    if let Some(publisher) = &article.publisher {
+        let name = match publisher.as_ref() {
+            CreativeWorkPublisher::Organization(org) => org.name.as_deref().cloned(),
+            CreativeWorkPublisher::Person(person) => encode_person_name(person),
+        };
+        if let Some(name) = name {
+            item.insert("publisher".to_string(), json!(name));
+        }
+    }
+
+    if let Some(doi) = article.identifiers.iter().flatten().find_map(encode_doi) {
+        item.insert("DOI".to_string(), json!(doi));
+    }
+
+    if let Some(url) = &article.url {
+        item.insert("URL".to_string(), json!(url.as_str()));
+    }
+
+    if let Some(ThingDescription::String(abstract_)) = article.description.as_deref() {
+        item.insert("abstract".to_string(), json!(abstract_));
+    }
+
+    serde_json::Value::Object(item)
+}
+
+/// Encode a `CreativeWorkTitle` to a string
+fn encode_title(title: &CreativeWorkTitle) -> String {
+    match title {
+        CreativeWorkTitle::String(string) => string.clone(),
+        CreativeWorkTitle::VecInlineContent(inlines) => inlines
+            .iter()
+            .map(|inline| match inline {
+                InlineContent::String(string) => string.clone(),
+                _ => String::new(),
+            })
+            .collect::<Vec<String>>()
+            .concat(),
+    }
+}
+
+/// Encode a `Person` into a `name-variable`
+fn encode_person(person: &Person) -> serde_json::Value {
+    let mut name = serde_json::Map::new();
+    if let Some(family_names) = &person.family_names {
+        name.insert("family".to_string(), json!(family_names.join(" ")));
+    }
+    if let Some(given_names) = &person.given_names {
+        name.insert("given".to_string(), json!(given_names.join(" ")));
+    }
+    if name.is_empty() {
+        if let Some(literal) = &person.name {
+            name.insert("literal".to_string(), json!(literal.as_str()));
+        }
+    }
+    if let Some(orcid) = person.identifiers.iter().flatten().find_map(|id| match id {
+        ThingIdentifiers::String(id) if id.contains("orcid.org") => Some(id.clone()),
+        _ => None,
+    }) {
+        name.insert("ORCID".to_string(), json!(orcid));
+    }
+    serde_json::Value::Object(name)
+}
+
+/// Encode a `Person` as a single name string (e.g. for use as a publisher)
+fn encode_person_name(person: &Person) -> Option<String> {
+    let names: Vec<String> = person
+        .given_names
+        .iter()
+        .flatten()
+        .chain(person.family_names.iter().flatten())
+        .cloned()
+        .collect();
+    if names.is_empty() {
+        person.name.as_deref().cloned()
+    } else {
+        Some(names.join(" "))
+    }
+}
+
+/// Encode an `Organization` into a literal `name-variable`
+fn encode_organization(org: &Organization) -> serde_json::Value {
+    json!({ "literal": org.name.as_deref().cloned().unwrap_or_default() })
+}
+
+/// Encode a `Date` into a `date-variable`
+///
+/// Dates that start with `YYYY`, `YYYY-MM`, or `YYYY-MM-DD` are encoded as `date-parts`.
+/// Others are encoded as `raw` strings.
+fn encode_date(date: &Date) -> serde_json::Value {
+    let value = date.value.split('T').next().unwrap_or_default();
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>();
+    match parts {
+        Ok(parts) if !parts.is_empty() && parts.len() <= 3 => json!({ "date-parts": [parts] }),
+        _ => json!({ "raw": date.value }),
+    }
+}
+
+/// Encode the `isPartOf` chain of an `Article` into CSL variables
+///
+/// Walks the chain (usually `PublicationIssue` > `PublicationVolume` > `Periodical`)
+/// extracting the `issue`, `volume` and `container-title`.
+fn encode_is_part_of(
+    is_part_of: &CreativeWorkTypes,
+    item: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    match is_part_of {
+        CreativeWorkTypes::PublicationIssue(PublicationIssue {
+            issue_number,
+            is_part_of,
+            ..
+        }) => {
+            if let Some(issue_number) = issue_number.as_deref() {
+                let issue = match issue_number {
+                    PublicationIssueIssueNumber::String(string) => string.clone(),
+                    PublicationIssueIssueNumber::Integer(integer) => integer.to_string(),
+                };
+                item.insert("issue".to_string(), json!(issue));
+            }
+            if let Some(is_part_of) = is_part_of {
+                encode_is_part_of(is_part_of, item)
+            }
+        }
+        CreativeWorkTypes::PublicationVolume(PublicationVolume {
+            volume_number,
+            is_part_of,
+            ..
+        }) => {
+            if let Some(volume_number) = volume_number.as_deref() {
+                let volume = match volume_number {
+                    PublicationVolumeVolumeNumber::String(string) => string.clone(),
+                    PublicationVolumeVolumeNumber::Integer(integer) => integer.to_string(),
+                };
+                item.insert("volume".to_string(), json!(volume));
+            }
+            if let Some(is_part_of) = is_part_of {
+                encode_is_part_of(is_part_of, item)
+            }
+        }
+        CreativeWorkTypes::Periodical(Periodical {
+            name: Some(name), ..
+        }) => {
+            item.insert("container-title".to_string(), json!(name.as_str()));
+        }
+        CreativeWorkTypes::CreativeWork(CreativeWork {
+            name: Some(name), ..
+        }) => {
+            item.insert("container-title".to_string(), json!(name.as_str()));
+        }
+        _ => {}
+    }
+}
+
+/// Encode the pages of an `Article` into a `page` variable
+fn encode_page(article: &Article) -> Option<String> {
+    if let Some(pagination) = &article.pagination {
+        return Some(pagination.to_string());
+    }
+
+    let start = article.page_start.as_deref().map(|start| match start {
+        ArticlePageStart::String(string) => string.clone(),
+        ArticlePageStart::Integer(integer) => integer.to_string(),
+    })?;
+    match article.page_end.as_deref().map(|end| match end {
+        ArticlePageEnd::String(string) => string.clone(),
+        ArticlePageEnd::Integer(integer) => integer.to_string(),
+    }) {
+        Some(end) => Some([start, end].join("-")),
+        None => Some(start),
+    }
+}
+
+/// Encode a DOI identifier (without the `https://doi.org/` prefix)
+fn encode_doi(id: &ThingIdentifiers) -> Option<String> {
+    let id = match id {
+        ThingIdentifiers::String(id) => id,
+        ThingIdentifiers::PropertyValue(PropertyValue {
+            value: PropertyValueValue::String(id),
+            ..
+        }) => id,
+        _ => return None,
+    };
+    id.strip_prefix(DOI_URL).map(|doi| doi.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use test_snaps::{insta::assert_json_snapshot, snapshot_fixtures_content};
+    use test_utils::assert_json_eq;
 
     #[test]
     fn fragments() {
@@ -244,4 +721,36 @@ mod tests {
             assert_json_snapshot!(node);
         });
     }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let items = json!([{
+            "id": "carlsson2019",
+            "type": "article-journal",
+            "title": "Tensors.jl — Tensor Computations in Julia",
+            "author": [
+                {"family": "Carlsson", "given": "Kristoffer"},
+                {"literal": "The Julia Community"}
+            ],
+            "issued": {"date-parts": [[2019, 3, 21]]},
+            "container-title": "Journal of Open Research Software",
+            "volume": "7",
+            "issue": "1",
+            "page": "65-70",
+            "publisher": "Ubiquity Press, Ltd.",
+            "DOI": "10.5334/jors.182",
+            "URL": "http://dx.doi.org/10.5334/jors.182"
+        }, {
+            "id": "bathe1996",
+            "type": "book",
+            "title": "Finite Element Procedures",
+            "issued": {"date-parts": [[1996]]}
+        }]);
+
+        let node = CslCodec::from_str(&items.to_string(), None)?;
+        let json = CslCodec::to_string(&node, None)?;
+        assert_json_eq!(serde_json::from_str::<serde_json::Value>(&json)?, items);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,1474 @@
+//! Rendering of citations and bibliographies using Citation Style Language (CSL) styles
+//!
+//! This is a processor for the subset of CSL 1.0 needed to render in-text citations, and
+//! bibliography entries, as plain text from CSL-JSON items. Attributes which have no plain
+//! text equivalent (e.g. `font-style`) are ignored, as are disambiguation, the collapsing
+//! of citations and the sorting of cites within a citation.
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs, mem,
+    path::Path,
+};
+
+use codec::common::{
+    eyre::{bail, Result},
+    itertools::Itertools,
+    serde_json,
+};
+
+/// The names and XML of the bundled styles
+const BUNDLED: &[(&str, &str)] = &[
+    ("apa", include_str!("../styles/apa.csl")),
+    (
+        "chicago-author-date",
+        include_str!("../styles/chicago-author-date.csl"),
+    ),
+    ("vancouver", include_str!("../styles/vancouver.csl")),
+];
+
+/// The `names` element used to render the author in narrative citations when the
+/// citation layout of the style does not render any names (e.g. numeric styles)
+const NARRATIVE_AUTHOR: &str = r#"
+<names xmlns="http://purl.org/net/xbiblio/csl" variable="author">
+  <name form="short" and="text" delimiter=", " et-al-min="3" et-al-use-first="1"/>
+  <substitute>
+    <names variable="editor"/>
+    <text variable="title" form="short"/>
+  </substitute>
+</names>
+"#;
+
+/// Name options that are inherited from the `style`, `citation` and `bibliography` elements
+const NAME_OPTIONS: &[&str] = &[
+    "and",
+    "delimiter-precedes-et-al",
+    "delimiter-precedes-last",
+    "et-al-min",
+    "et-al-use-first",
+    "et-al-use-last",
+    "initialize",
+    "initialize-with",
+    "name-as-sort-order",
+    "sort-separator",
+    "name-form",
+    "name-delimiter",
+    "names-delimiter",
+];
+
+/// English terms, as name, form, single and multiple, used unless overridden by the style
+const TERMS: &[(&str, &str, &str, &str)] = &[
+    ("accessed", "long", "accessed", "accessed"),
+    ("and", "long", "and", "and"),
+    ("and", "symbol", "&", "&"),
+    ("anonymous", "long", "anonymous", "anonymous"),
+    ("anonymous", "short", "anon.", "anon."),
+    ("available at", "long", "available at", "available at"),
+    ("chapter", "long", "chapter", "chapters"),
+    ("chapter", "short", "chap.", "chaps."),
+    ("close-inner-quote", "long", "’", "’"),
+    ("close-quote", "long", "”", "”"),
+    ("edition", "long", "edition", "editions"),
+    ("edition", "short", "ed.", "eds."),
+    ("editor", "long", "editor", "editors"),
+    ("editor", "short", "ed.", "eds."),
+    ("et-al", "long", "et al.", "et al."),
+    ("from", "long", "from", "from"),
+    ("in", "long", "in", "in"),
+    ("issue", "long", "issue", "issues"),
+    ("issue", "short", "no.", "nos."),
+    ("no date", "long", "no date", "no date"),
+    ("no date", "short", "n.d.", "n.d."),
+    ("open-inner-quote", "long", "‘", "‘"),
+    ("open-quote", "long", "“", "“"),
+    ("page", "long", "page", "pages"),
+    ("page", "short", "p.", "pp."),
+    ("retrieved", "long", "retrieved", "retrieved"),
+    ("translator", "long", "translator", "translators"),
+    ("translator", "short", "trans.", "trans."),
+    ("volume", "long", "volume", "volumes"),
+    ("volume", "short", "vol.", "vols."),
+];
+
+/// English month names, as long and short forms
+const MONTHS: [(&str, &str); 12] = [
+    ("January", "Jan."),
+    ("February", "Feb."),
+    ("March", "Mar."),
+    ("April", "Apr."),
+    ("May", "May"),
+    ("June", "June"),
+    ("July", "July"),
+    ("August", "Aug."),
+    ("September", "Sept."),
+    ("October", "Oct."),
+    ("November", "Nov."),
+    ("December", "Dec."),
+];
+
+/// Words that are not capitalized in title case (unless they are the first word)
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "the", "to", "up", "with",
+];
+
+/// An element of a CSL style
+#[derive(Debug, Clone, Default)]
+struct Element {
+    /// The local name of the element e.g. `text`
+    name: String,
+
+    /// The attributes of the element
+    attrs: HashMap<String, String>,
+
+    /// The child elements
+    children: Vec<Element>,
+
+    /// The text content of the element (only used for terms)
+    text: String,
+}
+
+impl Element {
+    /// Parse an element, and its descendants, from XML
+    fn parse(xml: &str) -> Result<Element> {
+        let doc = roxmltree::Document::parse(xml)?;
+        Ok(Element::from_node(doc.root_element()))
+    }
+
+    /// Create an element from a `roxmltree` node
+    fn from_node(node: roxmltree::Node) -> Element {
+        Element {
+            name: node.tag_name().name().to_string(),
+            attrs: node
+                .attributes()
+                .iter()
+                .map(|attr| (attr.name().to_string(), attr.value().to_string()))
+                .collect(),
+            children: node
+                .children()
+                .filter(|child| child.is_element())
+                .map(Element::from_node)
+                .collect(),
+            text: node
+                .children()
+                .filter(|child| child.is_text())
+                .filter_map(|child| child.text())
+                .collect::<String>()
+                .trim()
+                .to_string(),
+        }
+    }
+
+    /// Get an attribute of the element
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(|value| value.as_str())
+    }
+
+    /// Get the first child element with a name
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// A CSL style
+#[derive(Debug, Clone)]
+pub struct Style {
+    /// The root `style` element
+    root: Element,
+
+    /// The `citation` element
+    citation: Element,
+
+    /// The `bibliography` element, if any
+    bibliography: Option<Element>,
+
+    /// The macros of the style, by name
+    macros: HashMap<String, Element>,
+
+    /// The terms of the style, by name and form, with their single and multiple forms
+    terms: HashMap<(String, String), (String, String)>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style::bundled("apa").expect("Bundled style should be valid")
+    }
+}
+
+/// The mode of a cite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiteMode {
+    /// Render the cite as specified by the citation layout of the style
+    Normal,
+
+    /// Render the cite without the author (the first `names` element of the layout)
+    SuppressAuthor,
+
+    /// Render only the author of the cite
+    AuthorOnly,
+}
+
+/// A cite of a CSL item
+#[derive(Debug, Clone)]
+pub struct CiteItem<'item> {
+    /// The CSL-JSON item being cited
+    pub item: &'item serde_json::Value,
+
+    /// The number of the item in the bibliography (used by numeric styles)
+    pub number: usize,
+
+    /// The page/s of the item being cited, if any
+    pub locator: Option<String>,
+
+    /// The mode of the cite
+    pub mode: CiteMode,
+}
+
+impl Style {
+    /// Get the names of the bundled styles
+    pub fn bundled_names() -> Vec<&'static str> {
+        BUNDLED.iter().map(|(name, ..)| *name).collect()
+    }
+
+    /// Get one of the bundled styles
+    pub fn bundled(name: &str) -> Result<Style> {
+        match BUNDLED.iter().find(|(bundled, ..)| *bundled == name) {
+            Some((.., xml)) => Style::parse(xml),
+            None => bail!(
+                "There is no bundled citation style named `{}`; available styles are: {}",
+                name,
+                Style::bundled_names().join(", ")
+            ),
+        }
+    }
+
+    /// Read a style from a CSL file
+    pub fn read(path: &Path) -> Result<Style> {
+        let xml = fs::read_to_string(path)?;
+        Style::parse(&xml)
+    }
+
+    /// Parse a style from CSL XML
+    pub fn parse(xml: &str) -> Result<Style> {
+        let root = Element::parse(xml)?;
+        if root.name != "style" {
+            bail!("Expected a CSL `style` element but got `{}`", root.name)
+        }
+
+        let citation = match root.child("citation") {
+            Some(citation) if citation.child("layout").is_some() => citation.clone(),
+            _ => bail!("CSL style does not have a `citation` element with a `layout`"),
+        };
+        let bibliography = root
+            .child("bibliography")
+            .filter(|bibliography| bibliography.child("layout").is_some())
+            .cloned();
+
+        let macros = root
+            .children
+            .iter()
+            .filter(|child| child.name == "macro")
+            .filter_map(|child| {
+                child
+                    .attr("name")
+                    .map(|name| (name.to_string(), child.clone()))
+            })
+            .collect();
+
+        let mut terms: HashMap<(String, String), (String, String)> = TERMS
+            .iter()
+            .map(|(name, form, single, multiple)| {
+                (
+                    (name.to_string(), form.to_string()),
+                    (single.to_string(), multiple.to_string()),
+                )
+            })
+            .collect();
+        for (index, (long, short)) in MONTHS.iter().enumerate() {
+            let name = format!("month-{:02}", index + 1);
+            for (form, month) in [("long", long), ("short", short)] {
+                terms.insert(
+                    (name.clone(), form.to_string()),
+                    (month.to_string(), month.to_string()),
+                );
+            }
+        }
+        for locale in root
+            .children
+            .iter()
+            .filter(|child| child.name == "locale")
+            .filter(|locale| !matches!(locale.attr("lang"), Some(lang) if !lang.starts_with("en")))
+        {
+            for term in locale
+                .child("terms")
+                .iter()
+                .flat_map(|terms| terms.children.iter())
+                .filter(|child| child.name == "term")
+            {
+                let name = match term.attr("name") {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                };
+                let form = term.attr("form").unwrap_or("long").to_string();
+                let value = match (term.child("single"), term.child("multiple")) {
+                    (Some(single), Some(multiple)) => (single.text.clone(), multiple.text.clone()),
+                    _ => (term.text.clone(), term.text.clone()),
+                };
+                terms.insert((name, form), value);
+            }
+        }
+
+        Ok(Style {
+            root,
+            citation,
+            bibliography,
+            macros,
+            terms,
+        })
+    }
+
+    /// Get the prefix, delimiter and suffix of the citation layout e.g. `("(", "; ", ")")`
+    pub fn citation_affixes(&self) -> (&str, &str, &str) {
+        let layout = self.citation.child("layout");
+        let attr = |name: &str| layout.and_then(|layout| layout.attr(name)).unwrap_or("");
+        (attr("prefix"), attr("delimiter"), attr("suffix"))
+    }
+
+    /// Render a cite using the citation layout of the style
+    ///
+    /// The prefix and suffix of the layout are not added so that several cites can be
+    /// combined into a single citation (see [`Style::citation_affixes`]).
+    pub fn cite(&self, cite: &CiteItem) -> String {
+        let mut context = Context::new(
+            self,
+            &self.citation,
+            cite.item,
+            cite.number,
+            cite.locator.as_deref(),
+            cite.mode,
+        );
+        let text = context.layout(&self.citation, false);
+
+        let text = match cite.mode {
+            CiteMode::AuthorOnly => match context.author.take() {
+                Some(author) if !author.is_empty() => author,
+                _ => {
+                    let names = Element::parse(NARRATIVE_AUTHOR).unwrap_or_default();
+                    let mut context = Context::new(
+                        self,
+                        &self.citation,
+                        cite.item,
+                        cite.number,
+                        None,
+                        CiteMode::Normal,
+                    );
+                    context.names(&names, None).text
+                }
+            },
+            _ => text,
+        };
+
+        tidy(&text)
+    }
+
+    /// Render the bibliography entry for an item
+    ///
+    /// Returns `None` if the style does not have a bibliography.
+    pub fn entry(&self, item: &serde_json::Value, number: usize) -> Option<String> {
+        let bibliography = self.bibliography.as_ref()?;
+        let mut context = Context::new(self, bibliography, item, number, None, CiteMode::Normal);
+        Some(tidy(&context.layout(bibliography, true)))
+    }
+
+    /// Get the order of items in the bibliography
+    ///
+    /// Returns `None` if the style does not sort the bibliography (other than by
+    /// citation number) in which case items should be listed in the order that they
+    /// were first cited.
+    pub fn sort(&self, items: &[&serde_json::Value]) -> Option<Vec<usize>> {
+        let bibliography = self.bibliography.as_ref()?;
+        let keys: Vec<&Element> = bibliography
+            .child("sort")?
+            .children
+            .iter()
+            .filter(|child| child.name == "key")
+            .collect();
+        if keys
+            .iter()
+            .all(|key| key.attr("variable") == Some("citation-number"))
+        {
+            return None;
+        }
+
+        let values: Vec<Vec<String>> = items
+            .iter()
+            .map(|item| {
+                keys.iter()
+                    .map(|key| self.sort_value(bibliography, key, item))
+                    .collect()
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by(|a, b| {
+            for (index, key) in keys.iter().enumerate() {
+                let (a, b) = (&values[*a][index], &values[*b][index]);
+                // Empty values are always sorted last
+                let ordering = match (a.is_empty(), b.is_empty()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => match key.attr("sort") {
+                        Some("descending") => b.cmp(a),
+                        _ => a.cmp(b),
+                    },
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        Some(order)
+    }
+
+    /// Get the value of a sort key for an item
+    fn sort_value(&self, parent: &Element, key: &Element, item: &serde_json::Value) -> String {
+        let mut context = Context::new(self, parent, item, 0, None, CiteMode::Normal);
+        context
+            .options
+            .insert("name-as-sort-order".to_string(), "all".to_string());
+
+        let value = if let Some(variable) = key.attr("variable") {
+            match item.get(variable) {
+                Some(serde_json::Value::Array(names)) => names
+                    .iter()
+                    .map(|name| {
+                        ["non-dropping-particle", "family", "given", "literal"]
+                            .iter()
+                            .filter_map(|part| name.get(part).and_then(|part| part.as_str()))
+                            .join(" ")
+                    })
+                    .join(", "),
+                Some(date @ serde_json::Value::Object(..)) => match date_parts(date) {
+                    Some(parts) => parts
+                        .iter()
+                        .map(|part| format!("{:04}", part.unwrap_or_default()))
+                        .join(""),
+                    None => String::new(),
+                },
+                _ => context.variable(variable, false),
+            }
+        } else if let Some(name) = key.attr("macro") {
+            match self.macros.get(name) {
+                Some(macro_) => {
+                    let outputs = context.children(&macro_.children);
+                    join(outputs, "").text
+                }
+                None => String::new(),
+            }
+        } else {
+            String::new()
+        };
+        value.to_lowercase()
+    }
+
+    /// Get a term
+    ///
+    /// Falls back to the long form of the term if the requested form is not available.
+    fn term(&self, name: &str, form: &str, plural: bool) -> String {
+        let forms: &[&str] = match form {
+            "verb-short" => &["verb-short", "verb", "long"],
+            "symbol" => &["symbol", "short", "long"],
+            "short" => &["short", "long"],
+            "verb" => &["verb", "long"],
+            _ => &["long"],
+        };
+        forms
+            .iter()
+            .find_map(|form| self.terms.get(&(name.to_string(), form.to_string())))
+            .map(|(single, multiple)| if plural { multiple } else { single }.clone())
+            .unwrap_or_default()
+    }
+
+    /// Should page ranges be rendered with an en dash?
+    fn page_ranges(&self) -> bool {
+        self.root.attr("page-range-format").is_some()
+    }
+}
+
+/// The output of rendering an element
+#[derive(Debug, Default)]
+struct Output {
+    /// The rendered text
+    text: String,
+
+    /// Whether any variables were called when rendering the element
+    called: bool,
+
+    /// Whether any of the variables that were called were non-empty
+    nonempty: bool,
+}
+
+impl Output {
+    /// Create output that did not call any variables (e.g. a term)
+    fn text(text: String) -> Output {
+        Output {
+            text,
+            ..Default::default()
+        }
+    }
+
+    /// Create output that called a variable
+    fn variable(text: String, nonempty: bool) -> Output {
+        Output {
+            text,
+            called: true,
+            nonempty,
+        }
+    }
+}
+
+/// Join the outputs of rendering several elements
+fn join(outputs: Vec<Output>, delimiter: &str) -> Output {
+    let called = outputs.iter().any(|output| output.called);
+    let nonempty = outputs.iter().any(|output| output.nonempty);
+    let text = outputs
+        .into_iter()
+        .map(|output| output.text)
+        .filter(|text| !text.is_empty())
+        .join(delimiter);
+    Output {
+        text,
+        called,
+        nonempty,
+    }
+}
+
+/// The context for rendering a single item using a layout of a style
+struct Context<'style> {
+    /// The style being used
+    style: &'style Style,
+
+    /// The item being rendered
+    item: &'style serde_json::Value,
+
+    /// The number of the item in the bibliography
+    number: usize,
+
+    /// The locator of the cite, if any
+    locator: Option<&'style str>,
+
+    /// The mode of the cite
+    mode: CiteMode,
+
+    /// Name options inherited from the `style` and `citation` or `bibliography` elements
+    options: HashMap<String, String>,
+
+    /// Whether a `names` element has been rendered yet
+    names_rendered: bool,
+
+    /// The output of the first `names` element (i.e. the author)
+    author: Option<String>,
+
+    /// Variables that have been substituted for names and which are not rendered again
+    suppressed: HashSet<String>,
+
+    /// The non-empty variables rendered during the current substitution, if any
+    substituted: Option<HashSet<String>>,
+}
+
+impl<'style> Context<'style> {
+    /// Create a new rendering context
+    fn new(
+        style: &'style Style,
+        parent: &'style Element,
+        item: &'style serde_json::Value,
+        number: usize,
+        locator: Option<&'style str>,
+        mode: CiteMode,
+    ) -> Self {
+        let mut options = HashMap::new();
+        for element in [&style.root, parent] {
+            for (name, value) in &element.attrs {
+                if NAME_OPTIONS.contains(&name.as_str()) {
+                    let name = match name.as_str() {
+                        "name-form" => "form",
+                        "name-delimiter" => "delimiter",
+                        name => name,
+                    };
+                    options.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+
+        Context {
+            style,
+            item,
+            number,
+            locator,
+            mode,
+            options,
+            names_rendered: false,
+            author: None,
+            suppressed: HashSet::new(),
+            substituted: None,
+        }
+    }
+
+    /// Render the `layout` of a `citation` or `bibliography` element
+    ///
+    /// The prefix and suffix of the layout are only added if `affixes` is true.
+    fn layout(&mut self, parent: &'style Element, affixes: bool) -> String {
+        let layout = match parent.child("layout") {
+            Some(layout) => layout,
+            None => return String::new(),
+        };
+        let text = join(self.children(&layout.children), "").text;
+        if affixes && !text.is_empty() {
+            [
+                layout.attr("prefix").unwrap_or(""),
+                &text,
+                layout.attr("suffix").unwrap_or(""),
+            ]
+            .concat()
+        } else {
+            text
+        }
+    }
+
+    /// Render a list of elements
+    fn children(&mut self, elements: &'style [Element]) -> Vec<Output> {
+        let mut outputs = Vec::new();
+        for element in elements {
+            outputs.append(&mut self.element(element));
+        }
+        outputs
+    }
+
+    /// Render an element
+    ///
+    /// Returns a list of outputs because a `choose` element renders the children of
+    /// the matching branch, and these are delimited as if they were children of the
+    /// parent element.
+    fn element(&mut self, element: &'style Element) -> Vec<Output> {
+        let output = match element.name.as_str() {
+            "text" => self.text(element),
+            "number" => self.number(element),
+            "label" => self.label(element),
+            "names" => {
+                let is_author = !self.names_rendered;
+                self.names_rendered = true;
+                let output = self.names(element, None);
+                if is_author {
+                    match self.mode {
+                        CiteMode::SuppressAuthor => {
+                            return vec![Output::variable(String::new(), false)]
+                        }
+                        CiteMode::AuthorOnly => self.author = Some(output.text.clone()),
+                        CiteMode::Normal => {}
+                    }
+                }
+                output
+            }
+            "date" => self.date(element),
+            "group" => self.group(element),
+            "choose" => return self.choose(element),
+            _ => Output::default(),
+        };
+        vec![output]
+    }
+
+    /// Render a `text` element
+    fn text(&mut self, element: &'style Element) -> Output {
+        if let Some(variable) = element.attr("variable") {
+            let value = self.variable(variable, element.attr("form") == Some("short"));
+            let nonempty = !value.is_empty();
+            Output::variable(self.format(element, &value), nonempty)
+        } else if let Some(name) = element.attr("macro") {
+            let style = self.style;
+            match style.macros.get(name) {
+                Some(macro_) => {
+                    let output = join(self.children(&macro_.children), "");
+                    self.suppressible(element, output)
+                }
+                None => Output::default(),
+            }
+        } else if let Some(term) = element.attr("term") {
+            let term = self.style.term(
+                term,
+                element.attr("form").unwrap_or("long"),
+                element.attr("plural") == Some("true"),
+            );
+            Output::text(self.format(element, &term))
+        } else if let Some(value) = element.attr("value") {
+            Output::text(self.format(element, value))
+        } else {
+            Output::default()
+        }
+    }
+
+    /// Render a `number` element
+    fn number(&mut self, element: &'style Element) -> Output {
+        let variable = element.attr("variable").unwrap_or_default();
+        let value = self.variable(variable, false);
+        let nonempty = !value.is_empty();
+        let value = match (element.attr("form"), value.parse::<u32>()) {
+            (Some("ordinal"), Ok(number)) => ordinal(number),
+            _ => value,
+        };
+        Output::variable(self.format(element, &value), nonempty)
+    }
+
+    /// Render a `label` element
+    fn label(&mut self, element: &'style Element) -> Output {
+        let variable = element.attr("variable").unwrap_or_default();
+        let value = match variable {
+            "locator" => self.locator.unwrap_or_default().to_string(),
+            _ => self.string(variable),
+        };
+        if value.is_empty() {
+            return Output::default();
+        }
+
+        let term = match variable {
+            "locator" => "page",
+            _ => variable,
+        };
+        let plural = match element.attr("plural") {
+            Some("always") => true,
+            Some("never") => false,
+            _ => is_plural(&value),
+        };
+        let term = self
+            .style
+            .term(term, element.attr("form").unwrap_or("long"), plural);
+        Output::text(self.format(element, &term))
+    }
+
+    /// Render a `names` element
+    ///
+    /// Elements of substituted `names` elements which have no `name`, `et-al` or `label`
+    /// element inherit them from the parent `names` element.
+    fn names(&mut self, element: &'style Element, parent: Option<&'style Element>) -> Output {
+        let inherited = |name: &str| {
+            element
+                .child(name)
+                .or_else(|| parent.and_then(|parent| parent.child(name)))
+        };
+        let name = inherited("name");
+        let et_al = inherited("et-al");
+        let label = inherited("label");
+        let label_first = match (&name, &label) {
+            (Some(name), Some(label)) => {
+                let position = |target: &Element| {
+                    element
+                        .children
+                        .iter()
+                        .position(|child| std::ptr::eq(child, target))
+                };
+                position(label) < position(name)
+            }
+            _ => false,
+        };
+
+        let mut parts = Vec::new();
+        for variable in element
+            .attr("variable")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            if self.suppressed.contains(variable) {
+                continue;
+            }
+            let names = match self.item.get(variable).and_then(|names| names.as_array()) {
+                Some(names) if !names.is_empty() => names,
+                _ => continue,
+            };
+            self.used(variable);
+
+            let text = self.name_list(names, name, et_al);
+            let label = match label {
+                Some(label) => {
+                    let term = self.style.term(
+                        variable,
+                        label.attr("form").unwrap_or("long"),
+                        names.len() > 1,
+                    );
+                    self.format(label, &term)
+                }
+                None => String::new(),
+            };
+            parts.push(if label_first {
+                [label, text].concat()
+            } else {
+                [text, label].concat()
+            });
+        }
+
+        if !parts.is_empty() {
+            let delimiter = element
+                .attr("delimiter")
+                .map(|delimiter| delimiter.to_string())
+                .or_else(|| self.options.get("names-delimiter").cloned())
+                .unwrap_or_default();
+            let text = parts.join(&delimiter);
+            return Output::variable(self.format(element, &text), true);
+        }
+
+        if let Some(substitute) = element.child("substitute") {
+            for child in &substitute.children {
+                let outer = self.substituted.replace(HashSet::new());
+                let output = if child.name == "names" {
+                    self.names(child, Some(parent.unwrap_or(element)))
+                } else {
+                    join(self.element(child), "")
+                };
+                let substituted = mem::replace(&mut self.substituted, outer).unwrap_or_default();
+
+                if !output.text.is_empty() {
+                    if let Some(outer) = &mut self.substituted {
+                        outer.extend(substituted.iter().cloned());
+                    }
+                    self.suppressed.extend(substituted);
+                    return Output::variable(self.format(element, &output.text), true);
+                }
+            }
+        }
+
+        Output::variable(String::new(), false)
+    }
+
+    /// Render a list of names
+    fn name_list(
+        &self,
+        names: &[serde_json::Value],
+        name: Option<&Element>,
+        et_al: Option<&Element>,
+    ) -> String {
+        let option = |key: &str| {
+            name.and_then(|name| name.attr(key))
+                .or_else(|| self.options.get(key).map(|value| value.as_str()))
+        };
+        let number = |key: &str| option(key).and_then(|value| value.parse::<usize>().ok());
+
+        let form = option("form").unwrap_or("long");
+        let names: Vec<(String, bool)> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| self.name(name, index, form, &option))
+            .filter(|(name, ..)| !name.is_empty())
+            .collect();
+        let count = names.len();
+        if form == "count" {
+            return count.to_string();
+        }
+        if count == 0 {
+            return String::new();
+        }
+
+        let delimiter = option("delimiter").unwrap_or(", ");
+        let et_al_min = number("et-al-min").unwrap_or(0);
+        let et_al_use_first = number("et-al-use-first").unwrap_or(1).max(1);
+        if et_al_min > 0 && count >= et_al_min && et_al_use_first < count {
+            let shown = &names[..et_al_use_first];
+            let list = shown.iter().map(|(name, ..)| name).join(delimiter);
+            if option("et-al-use-last") == Some("true") && et_al_use_first + 1 < count {
+                return [list.as_str(), delimiter, "… ", &names[count - 1].0].concat();
+            }
+            let term = self.style.term(
+                et_al
+                    .and_then(|et_al| et_al.attr("term"))
+                    .unwrap_or("et-al"),
+                "long",
+                false,
+            );
+            let precedes = match option("delimiter-precedes-et-al") {
+                Some("always") => true,
+                Some("never") => false,
+                Some("after-inverted-name") => shown[shown.len() - 1].1,
+                _ => shown.len() > 1,
+            };
+            return [list.as_str(), if precedes { delimiter } else { " " }, &term].concat();
+        }
+
+        if count == 1 {
+            return names[0].0.clone();
+        }
+        match option("and") {
+            Some(and) => {
+                let and = match and {
+                    "symbol" => "&".to_string(),
+                    _ => self.style.term("and", "long", false),
+                };
+                let precedes = match option("delimiter-precedes-last") {
+                    Some("always") => true,
+                    Some("never") => false,
+                    Some("after-inverted-name") => names[count - 2].1,
+                    _ => count > 2,
+                };
+                [
+                    names[..count - 1]
+                        .iter()
+                        .map(|(name, ..)| name)
+                        .join(delimiter)
+                        .as_str(),
+                    if precedes { delimiter } else { " " },
+                    &and,
+                    " ",
+                    &names[count - 1].0,
+                ]
+                .concat()
+            }
+            None => names.iter().map(|(name, ..)| name).join(delimiter),
+        }
+    }
+
+    /// Render a single name
+    ///
+    /// Returns the rendered name and whether it is inverted (i.e. family name first).
+    fn name<'option>(
+        &self,
+        name: &serde_json::Value,
+        index: usize,
+        form: &str,
+        option: &dyn Fn(&str) -> Option<&'option str>,
+    ) -> (String, bool) {
+        let part = |part: &str| {
+            name.get(part)
+                .and_then(|part| part.as_str())
+                .map(|part| part.trim())
+                .unwrap_or_default()
+        };
+
+        let literal = part("literal");
+        if !literal.is_empty() {
+            return (literal.to_string(), false);
+        }
+
+        let family = [part("non-dropping-particle"), part("family")]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .join(" ");
+        let given = part("given");
+        if family.is_empty() {
+            return (given.to_string(), false);
+        }
+        if form == "short" || given.is_empty() {
+            return (family, false);
+        }
+
+        let given = match option("initialize-with") {
+            Some(with) if option("initialize") != Some("false") => initialize(given, with),
+            _ => given.to_string(),
+        };
+        let inverted = match option("name-as-sort-order") {
+            Some("all") => true,
+            Some("first") => index == 0,
+            _ => false,
+        };
+        if inverted {
+            let separator = option("sort-separator").unwrap_or(", ");
+            ([&family, separator, &given].concat(), true)
+        } else {
+            ([&given, " ", &family].concat(), false)
+        }
+    }
+
+    /// Render a `date` element
+    ///
+    /// Localized dates (those with a `form` attribute) use the US English date formats.
+    fn date(&mut self, element: &'style Element) -> Output {
+        let variable = element.attr("variable").unwrap_or_default();
+        let date = match self.item.get(variable) {
+            Some(date) if !self.suppressed.contains(variable) => date,
+            _ => return Output::variable(String::new(), false),
+        };
+        self.used(variable);
+
+        let parts = match date_parts(date) {
+            Some(parts) => parts,
+            None => {
+                let literal = date
+                    .get("literal")
+                    .or_else(|| date.get("raw"))
+                    .and_then(|literal| literal.as_str())
+                    .unwrap_or_default();
+                return Output::variable(self.format(element, literal), !literal.is_empty());
+            }
+        };
+
+        let date_part = |name: &str, form: &str, affixes: (&str, &str)| -> String {
+            let value = match name {
+                "year" => parts[0],
+                "month" => parts[1],
+                "day" => parts[2],
+                _ => None,
+            };
+            let value = match (name, value) {
+                (_, None) => return String::new(),
+                ("year", Some(year)) => match form {
+                    "short" => format!("{:02}", year % 100),
+                    _ => year.to_string(),
+                },
+                ("month", Some(month)) => {
+                    let (long, short) = MONTHS[(month as usize).clamp(1, 12) - 1];
+                    match form {
+                        "short" => short.to_string(),
+                        "numeric" => month.to_string(),
+                        "numeric-leading-zeros" => format!("{:02}", month),
+                        _ => long.to_string(),
+                    }
+                }
+                (_, Some(day)) => match form {
+                    "numeric-leading-zeros" => format!("{:02}", day),
+                    "ordinal" => ordinal(day as u32),
+                    _ => day.to_string(),
+                },
+            };
+            [affixes.0, &value, affixes.1].concat()
+        };
+
+        let text = match element.attr("form") {
+            Some(form) => {
+                let localized: &[(&str, &str, &str)] = match form {
+                    "numeric" => &[
+                        ("month", "numeric", "/"),
+                        ("day", "numeric", "/"),
+                        ("year", "long", ""),
+                    ],
+                    _ => &[
+                        ("month", "long", " "),
+                        ("day", "numeric", ", "),
+                        ("year", "long", ""),
+                    ],
+                };
+                let included = match element.attr("date-parts") {
+                    Some("year") => vec!["year"],
+                    Some("year-month") => vec!["year", "month"],
+                    _ => vec!["year", "month", "day"],
+                };
+                localized
+                    .iter()
+                    .filter(|(name, ..)| included.contains(name))
+                    .map(|(name, form, suffix)| {
+                        let custom = element.children.iter().find(|child| {
+                            child.name == "date-part" && child.attr("name") == Some(name)
+                        });
+                        let attr = |attr: &str| custom.and_then(|custom| custom.attr(attr));
+                        date_part(
+                            name,
+                            attr("form").unwrap_or(form),
+                            (
+                                attr("prefix").unwrap_or(""),
+                                attr("suffix").unwrap_or(suffix),
+                            ),
+                        )
+                    })
+                    .collect::<String>()
+            }
+            None => element
+                .children
+                .iter()
+                .filter(|child| child.name == "date-part")
+                .map(|child| {
+                    date_part(
+                        child.attr("name").unwrap_or_default(),
+                        child.attr("form").unwrap_or("long"),
+                        (
+                            child.attr("prefix").unwrap_or(""),
+                            child.attr("suffix").unwrap_or(""),
+                        ),
+                    )
+                })
+                .filter(|part| !part.is_empty())
+                .join(element.attr("delimiter").unwrap_or("")),
+        };
+
+        let nonempty = !text.is_empty();
+        Output::variable(self.format(element, text.trim()), nonempty)
+    }
+
+    /// Render a `group` element
+    ///
+    /// The group is suppressed if at least one of its elements calls a variable and
+    /// all of the variables that are called are empty.
+    fn group(&mut self, element: &'style Element) -> Output {
+        let output = join(
+            self.children(&element.children),
+            element.attr("delimiter").unwrap_or(""),
+        );
+        self.suppressible(element, output)
+    }
+
+    /// Format the output of a group, or macro, or suppress it if all of the variables it called were empty
+    fn suppressible(&self, element: &Element, output: Output) -> Output {
+        if output.called && !output.nonempty {
+            Output::variable(String::new(), false)
+        } else {
+            Output {
+                text: self.format(element, &output.text),
+                ..output
+            }
+        }
+    }
+
+    /// Render a `choose` element
+    fn choose(&mut self, element: &'style Element) -> Vec<Output> {
+        for branch in &element.children {
+            let matches = match branch.name.as_str() {
+                "if" | "else-if" => self.condition(branch),
+                "else" => true,
+                _ => false,
+            };
+            if matches {
+                return self.children(&branch.children);
+            }
+        }
+        Vec::new()
+    }
+
+    /// Evaluate the condition of an `if` or `else-if` element
+    fn condition(&self, branch: &Element) -> bool {
+        let mut tests = Vec::new();
+        for (attr, values) in &branch.attrs {
+            for value in values.split_whitespace() {
+                let test = match attr.as_str() {
+                    "type" => self.item.get("type").and_then(|type_| type_.as_str()) == Some(value),
+                    "variable" => self.has_variable(value),
+                    "is-numeric" => is_numeric(&self.string(value)),
+                    "locator" => self.locator.is_some() && value == "page",
+                    "position" => value == "first",
+                    "is-uncertain-date" | "disambiguate" => false,
+                    _ => continue,
+                };
+                tests.push(test);
+            }
+        }
+        match branch.attr("match") {
+            Some("any") => tests.iter().any(|test| *test),
+            Some("none") => !tests.iter().any(|test| *test),
+            _ => tests.iter().all(|test| *test),
+        }
+    }
+
+    /// Does the item have a non-empty value for a variable?
+    fn has_variable(&self, variable: &str) -> bool {
+        if self.suppressed.contains(variable) {
+            return false;
+        }
+        match variable {
+            "citation-number" => self.number > 0,
+            "locator" => self.locator.is_some(),
+            _ => match self.item.get(variable) {
+                Some(serde_json::Value::String(string)) => !string.trim().is_empty(),
+                Some(serde_json::Value::Array(array)) => !array.is_empty(),
+                Some(serde_json::Value::Object(..) | serde_json::Value::Number(..)) => true,
+                _ => false,
+            },
+        }
+    }
+
+    /// Get the value of a string (or number) variable
+    fn string(&self, variable: &str) -> String {
+        let value = match variable {
+            "citation-number" if self.number > 0 => return self.number.to_string(),
+            "locator" => self.locator.unwrap_or_default().to_string(),
+            _ => match self.item.get(variable) {
+                Some(serde_json::Value::String(string)) => string.trim().to_string(),
+                Some(serde_json::Value::Number(number)) => number.to_string(),
+                _ => String::new(),
+            },
+        };
+        if matches!(variable, "page" | "locator") && self.style.page_ranges() {
+            value.replace('-', "–")
+        } else {
+            value
+        }
+    }
+
+    /// Get the value of a variable for rendering
+    ///
+    /// Returns an empty string if the variable has been suppressed because it was
+    /// substituted for names. For the short form, the `<variable>-short` variable is
+    /// used if it is available.
+    fn variable(&mut self, variable: &str, short: bool) -> String {
+        if self.suppressed.contains(variable) {
+            return String::new();
+        }
+        let value = if short {
+            match self.string(&[variable, "-short"].concat()) {
+                value if value.is_empty() => self.string(variable),
+                value => value,
+            }
+        } else {
+            self.string(variable)
+        };
+        if !value.is_empty() {
+            self.used(variable);
+        }
+        value
+    }
+
+    /// Record that a non-empty variable has been rendered
+    fn used(&mut self, variable: &str) {
+        if let Some(substituted) = &mut self.substituted {
+            substituted.insert(variable.to_string());
+        }
+    }
+
+    /// Apply the formatting attributes of an element to some text
+    fn format(&self, element: &Element, text: &str) -> String {
+        if text.is_empty() {
+            return String::new();
+        }
+
+        let mut text = text.to_string();
+        if element.attr("strip-periods") == Some("true") {
+            text = text.replace('.', "");
+        }
+        if let Some(case) = element.attr("text-case") {
+            text = text_case(&text, case);
+        }
+        if element.attr("quotes") == Some("true") {
+            text = [
+                self.style.term("open-quote", "long", false),
+                text,
+                self.style.term("close-quote", "long", false),
+            ]
+            .concat();
+        }
+        [
+            element.attr("prefix").unwrap_or(""),
+            &text,
+            element.attr("suffix").unwrap_or(""),
+        ]
+        .concat()
+    }
+}
+
+/// Get the year, month and day of a CSL date variable
+///
+/// Uses the first date of `date-parts`, or parses a `raw` date in ISO 8601 format
+/// (e.g. `2020-03-01`). Returns `None` if the date has neither.
+fn date_parts(date: &serde_json::Value) -> Option<[Option<i64>; 3]> {
+    let values: Vec<Option<i64>> = match date
+        .get("date-parts")
+        .and_then(|parts| parts.get(0))
+        .and_then(|parts| parts.as_array())
+    {
+        Some(parts) => parts
+            .iter()
+            .map(|part| {
+                part.as_i64()
+                    .or_else(|| part.as_str().and_then(|part| part.trim().parse().ok()))
+            })
+            .collect(),
+        None => {
+            let raw = date.get("raw").and_then(|raw| raw.as_str())?;
+            let raw = raw.split('T').next().unwrap_or_default();
+            let parts = raw
+                .split('-')
+                .map(|part| part.parse::<i64>().ok())
+                .collect::<Option<Vec<i64>>>()?;
+            if parts.is_empty() || parts.len() > 3 || parts[0] < 1000 {
+                return None;
+            }
+            parts.into_iter().map(Some).collect()
+        }
+    };
+    values.first().copied().flatten()?;
+    Some([
+        values.first().copied().flatten(),
+        values.get(1).copied().flatten(),
+        values.get(2).copied().flatten(),
+    ])
+}
+
+/// Initialize given names e.g. `Jane Mary` to `J. M.` (when `with` is ". ")
+fn initialize(given: &str, with: &str) -> String {
+    given
+        .split(|chr: char| chr.is_whitespace() || chr == '.')
+        .filter_map(|name| name.chars().next())
+        .map(|initial| [&initial.to_string(), with].concat())
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Is a value numeric (e.g. `5`, `5-7` or `5, 7`)?
+fn is_numeric(value: &str) -> bool {
+    !value.is_empty()
+        && value.split(['-', '–', ',', '&']).all(|part| {
+            let part = part.trim();
+            !part.is_empty() && part.chars().any(|chr| chr.is_ascii_digit())
+        })
+}
+
+/// Is a value plural (e.g. a range of pages)?
+fn is_plural(value: &str) -> bool {
+    value.contains(['-', '–', ',', '&'])
+}
+
+/// Get the English ordinal for a number e.g. `2nd`
+fn ordinal(number: u32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, ..) => "st",
+        (2, ..) => "nd",
+        (3, ..) => "rd",
+        _ => "th",
+    };
+    [number.to_string(), suffix.to_string()].concat()
+}
+
+/// Apply a CSL `text-case` to some text
+fn text_case(text: &str, case: &str) -> String {
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => [first.to_uppercase().collect::<String>(), chars.collect()].concat(),
+            None => String::new(),
+        }
+    };
+    match case {
+        "lowercase" => text.to_lowercase(),
+        "uppercase" => text.to_uppercase(),
+        "capitalize-first" | "sentence" => capitalize(text),
+        "capitalize-all" => text.split(' ').map(capitalize).join(" "),
+        "title" => text
+            .split(' ')
+            .enumerate()
+            .map(|(index, word)| {
+                if index > 0 && STOP_WORDS.contains(&word) {
+                    word.to_string()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .join(" "),
+        _ => text.to_string(),
+    }
+}
+
+/// Tidy rendered text by removing duplicated punctuation and moving periods and
+/// commas inside closing quotes (as is the convention in US English)
+fn tidy(text: &str) -> String {
+    let mut tidied = String::with_capacity(text.len());
+    for chr in text.trim().chars() {
+        let last = tidied.chars().last();
+        match (last, chr) {
+            (Some('.' | '?' | '!'), '.') | (Some(' '), ' ') => continue,
+            (Some(quote @ ('”' | '’')), '.' | ',') => {
+                tidied.pop();
+                tidied.push(chr);
+                tidied.push(quote);
+            }
+            _ => tidied.push(chr),
+        }
+    }
+    tidied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::common::serde_json::json;
+    use test_utils::pretty_assertions::assert_eq;
+
+    #[test]
+    fn bundled() -> Result<()> {
+        for name in Style::bundled_names() {
+            Style::bundled(name)?;
+        }
+        assert!(Style::bundled("foo").is_err());
+        assert!(Style::parse("<foo/>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn names() -> Result<()> {
+        let item = |count: usize| {
+            let authors: Vec<serde_json::Value> = (0..count)
+                .map(
+                    |index| json!({"family": format!("Family{}", index + 1), "given": "Jane Mary"}),
+                )
+                .collect();
+            json!({ "type": "book", "author": authors, "issued": {"date-parts": [[2020]]} })
+        };
+
+        let apa = Style::bundled("apa")?;
+        let cite = |item: &serde_json::Value| {
+            apa.cite(&CiteItem {
+                item,
+                number: 1,
+                locator: None,
+                mode: CiteMode::Normal,
+            })
+        };
+        assert_eq!(cite(&item(1)), "Family1, 2020");
+        assert_eq!(cite(&item(2)), "Family1 & Family2, 2020");
+        assert_eq!(cite(&item(3)), "Family1 et al., 2020");
+        assert_eq!(
+            apa.entry(&item(3), 1).unwrap_or_default(),
+            "Family1, J. M., Family2, J. M., & Family3, J. M. (2020)."
+        );
+
+        let vancouver = Style::bundled("vancouver")?;
+        assert_eq!(
+            vancouver.entry(&item(7), 3).unwrap_or_default(),
+            "3. Family1 JM, Family2 JM, Family3 JM, Family4 JM, Family5 JM, Family6 JM, et al. 2020."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn substitution() -> Result<()> {
+        let item = json!({
+            "type": "article-journal",
+            "title": "Untitled works",
+            "container-title": "Journal",
+            "volume": "1",
+            "page": "10-12",
+            "issued": {"raw": "2021-06"}
+        });
+
+        let apa = Style::bundled("apa")?;
+        assert_eq!(
+            apa.entry(&item, 1).unwrap_or_default(),
+            "Untitled works (2021). Journal, 1, 10–12."
+        );
+
+        let chicago = Style::bundled("chicago-author-date")?;
+        assert_eq!(
+            chicago.entry(&item, 1).unwrap_or_default(),
+            "Untitled works. 2021. Journal 1: 10–12."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sort() -> Result<()> {
+        let items = [
+            json!({"author": [{"family": "Zhang"}], "issued": {"date-parts": [[2001]]}}),
+            json!({"author": [{"family": "Adams"}], "issued": {"date-parts": [[2010]]}}),
+            json!({"author": [{"family": "Adams"}], "issued": {"date-parts": [[2005]]}}),
+        ];
+        let items: Vec<&serde_json::Value> = items.iter().collect();
+
+        assert_eq!(Style::bundled("apa")?.sort(&items), Some(vec![2, 1, 0]));
+        assert_eq!(Style::bundled("vancouver")?.sort(&items), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tidying() {
+        assert_eq!(tidy("Smith, K.. (2020)."), "Smith, K. (2020).");
+        assert_eq!(tidy("“A title”. Journal"), "“A title.” Journal");
+        assert_eq!(tidy("Why?. Because"), "Why? Because");
+    }
+}
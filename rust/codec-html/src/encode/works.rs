@@ -146,14 +146,85 @@ impl ToHtml for Article {
             &self.content.to_html(context),
         );
 
+        let references = match &self.references {
+            Some(references) => elem(
+                "section",
+                &[attr_itemprop("references")],
+                &[
+                    elem("h2", &[], "References"),
+                    elem(
+                        "div",
+                        &[attr_prop("references")],
+                        &concat(references, |reference| {
+                            reference_to_html(reference, context)
+                        }),
+                    ),
+                ]
+                .concat(),
+            ),
+            None => "".to_string(),
+        };
+
         elem(
             "article",
             &[attr_itemtype::<Self>(), attr_id(&self.id)],
-            &[toolbar, title, authors, affiliations, abstract_, content].concat(),
+            &[
+                toolbar,
+                title,
+                authors,
+                affiliations,
+                abstract_,
+                content,
+                references,
+            ]
+            .concat(),
         )
     }
 }
 
+/// Encode one of the `references` of an `Article` as an entry in its reference list
+///
+/// Uses the `text` of the reference, which is rendered, using the document's
+/// citation style, when the document is assembled. Falls back to the title of
+/// the reference if it has not been rendered.
+fn reference_to_html(reference: &CreativeWorkReferences, context: &EncodeContext) -> String {
+    let (itemtype, id, text, title) = match reference {
+        CreativeWorkReferences::String(string) => {
+            return elem("p", &[attr_itemprop("citation")], &string.to_html(context))
+        }
+        CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(article)) => (
+            attr_itemtype::<Article>(),
+            &article.id,
+            &article.text,
+            &article.title,
+        ),
+        CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(work)) => (
+            attr_itemtype::<CreativeWork>(),
+            &work.id,
+            &work.text,
+            &work.title,
+        ),
+        CreativeWorkReferences::CreativeWorkTypes(work) => {
+            return elem("p", &[attr_itemprop("citation")], &work.to_html(context))
+        }
+    };
+
+    let content = match (text, title) {
+        (Some(text), _) => text.to_html(context),
+        (None, Some(title)) => match title.as_ref() {
+            CreativeWorkTitle::String(title) => title.to_html(context),
+            CreativeWorkTitle::VecInlineContent(title) => title.to_html(context),
+        },
+        (None, None) => "".to_string(),
+    };
+
+    elem(
+        "p",
+        &[itemtype, attr_itemprop("citation"), attr_id(id)],
+        &content,
+    )
+}
+
 fn author_person_to_html(person: &Person, orgs: Option<&Vec<&Organization>>) -> String {
     let name_string = if person.given_names.is_some() && person.family_names.is_some() {
         [
@@ -9,6 +9,7 @@ use super::translate::{
     translate_error, translate_mime_bundle, translate_multiline_string, translate_stderr,
     translate_text,
 };
+use crate::{METADATA_TAGS, RAW_MEDIA_TYPE};
 
 /// Decode a Jupyter Notebook to a `Node`.
 ///
@@ -151,6 +152,25 @@ fn translate_code_cell(cell: &serde_json::Value, notebook_lang: &str) -> Vec<Blo
         tracing::warn!("Code cell does not have a `source` property");
         "".to_string()
     };
+    let text = match metadata {
+        Some(metadata) => [
+            translate_metadata_tags(metadata, &programming_language),
+            text,
+        ]
+        .concat(),
+        None => text,
+    };
+
+    let meta = metadata
+        .and_then(|value| value.as_object())
+        .map(translate_metadata_meta)
+        .filter(|meta| !meta.is_empty())
+        .map(Box::new);
+
+    let execute_count = cell
+        .get("execution_count")
+        .and_then(|value| value.as_u64())
+        .map(|count| count as u32);
 
     let mut outputs = Vec::with_capacity(1);
     let mut errors = Vec::new();
@@ -171,6 +191,8 @@ fn translate_code_cell(cell: &serde_json::Value, notebook_lang: &str) -> Vec<Blo
         caption,
         programming_language,
         text,
+        meta,
+        execute_count,
         outputs: if outputs.is_empty() {
             None
         } else {
@@ -187,6 +209,68 @@ fn translate_code_cell(cell: &serde_json::Value, notebook_lang: &str) -> Vec<Blo
     vec![BlockContent::CodeChunk(chunk)]
 }
 
+/// Translate the `metadata` of a Jupyter cell into `@` comment tags
+///
+/// Each of the `METADATA_TAGS` in the metadata is translated into a comment line
+/// e.g. `"tags": ["parameters", "hide-input"]` becomes `# @tags parameters, hide-input`.
+fn translate_metadata_tags(metadata: &serde_json::Value, language: &str) -> String {
+    let prefix = comment_prefix(language);
+    METADATA_TAGS
+        .iter()
+        .filter_map(|name| {
+            let value = match metadata.get(name)? {
+                serde_json::Value::Array(items) => items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+                serde_json::Value::String(string) => string.clone(),
+                value => value.to_string(),
+            };
+            if value.is_empty() {
+                None
+            } else {
+                Some([prefix, " @", name, " ", &value, "\n"].concat())
+            }
+        })
+        .collect()
+}
+
+/// Translate the `metadata` of a Jupyter cell into the `meta` of a `CodeChunk`
+///
+/// Properties that are not decoded into other properties of the chunk, or into
+/// `METADATA_TAGS` in its code, (e.g. `collapsed`, `scrolled`, `editable`) are
+/// kept so that they can be restored to the cell's `metadata` when encoding.
+fn translate_metadata_meta(metadata: &serde_json::Map<String, serde_json::Value>) -> Object {
+    metadata
+        .iter()
+        .filter(|(name, ..)| {
+            !matches!(name.as_str(), "id" | "label" | "caption" | "language_info")
+                && !METADATA_TAGS.contains(&name.as_str())
+        })
+        .filter_map(
+            |(name, value)| match serde_json::from_value::<Primitive>(value.clone()) {
+                Ok(value) => Some((name.clone(), value)),
+                Err(error) => {
+                    tracing::warn!("While translating cell metadata `{}`: {}", name, error);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Get the prefix for a line comment in a programming language
+fn comment_prefix(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "c" | "c++" | "cpp" | "go" | "java" | "javascript" | "js" | "rust" | "scala"
+        | "typescript" | "ts" => "//",
+        "haskell" | "lua" | "sql" => "--",
+        "matlab" | "octave" => "%",
+        _ => "#",
+    }
+}
+
 /// Translate a cell output
 fn translate_output(output: &serde_json::Value, language: &str) -> Option<Node> {
     let output_type = output
@@ -215,22 +299,63 @@ fn translate_output(output: &serde_json::Value, language: &str) -> Option<Node>
 }
 
 /// Translate a Jupyter "markdown" cell
+///
+/// Any `attachments` of the cell (e.g. images pasted into the cell) are translated into
+/// data URIs so that they are decoded as `ImageObject`s with the content embedded.
 fn translate_markdown_cell(cell: &serde_json::Value, default_lang: &str) -> Vec<BlockContent> {
     if let Some(source) = cell.get("source") {
-        codec_md::decode_fragment(
-            &translate_multiline_string(source),
-            Some(default_lang.to_string()),
-        )
+        let mut md = translate_multiline_string(source);
+        if let Some(attachments) = cell.get("attachments").and_then(|value| value.as_object()) {
+            for (name, bundle) in attachments {
+                if let Some(url) = translate_attachment(name, bundle) {
+                    md = md.replace(&["attachment:", name].concat(), &url);
+                }
+            }
+        }
+        codec_md::decode_fragment(&md, Some(default_lang.to_string()))
     } else {
         tracing::warn!("Markdown cell does not have a `source` property");
         Vec::new()
     }
 }
 
+/// Translate a Jupyter markdown cell attachment into a data URI
+///
+/// The name of the attachment is added as a parameter of the data URI
+/// (e.g. `data:image/png;name=image.png;base64,...`) so that it can be restored when encoding.
+fn translate_attachment(name: &str, bundle: &serde_json::Value) -> Option<String> {
+    let (media_type, data) = bundle.as_object()?.iter().next()?;
+    let data = translate_multiline_string(data);
+    Some(["data:", media_type, ";name=", name, ";base64,", &data].concat())
+}
+
 /// Translate a Jupyter "raw" cell
-fn translate_raw_cell(_cell: &serde_json::Value) -> Vec<BlockContent> {
-    tracing::warn!("Decoding of raw cells is not yet supported");
-    Vec::new()
+///
+/// Raw cells are translated to a `CodeBlock` with the `media_type` of the cell
+/// (from its `raw_mimetype` metadata) and no `programming_language`.
+fn translate_raw_cell(cell: &serde_json::Value) -> Vec<BlockContent> {
+    let text = if let Some(source) = cell.get("source") {
+        translate_multiline_string(source)
+    } else {
+        tracing::warn!("Raw cell does not have a `source` property");
+        "".to_string()
+    };
+
+    let media_type = cell
+        .get("metadata")
+        .and_then(|metadata| {
+            metadata
+                .get("raw_mimetype")
+                .or_else(|| metadata.get("format"))
+        })
+        .and_then(|value| value.as_str())
+        .unwrap_or(RAW_MEDIA_TYPE);
+
+    vec![BlockContent::CodeBlock(CodeBlock {
+        text,
+        media_type: Some(Box::new(media_type.to_string())),
+        ..Default::default()
+    })]
 }
 
 #[cfg(test)]
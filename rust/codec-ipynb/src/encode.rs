@@ -1,9 +1,9 @@
-use std::collections::HashMap;
-
 use codec::{
     common::{
         base64,
         eyre::{bail, Result},
+        once_cell::sync::Lazy,
+        regex::{Captures, Regex},
         serde::Serialize,
         serde_json::{self, json},
     },
@@ -13,6 +13,8 @@ use codec::{
 use codec_md::ToMd;
 use codec_txt::ToTxt;
 
+use crate::{METADATA_TAGS, RAW_MEDIA_TYPE};
+
 /// Encode a `Node` to a Jupyter Notebook.
 ///
 /// Note that the order of properties in various JSON objects is
@@ -130,6 +132,13 @@ fn encode_content(content: &Option<Vec<BlockContent>>) -> Vec<serde_json::Value>
                 }
                 cells.push(encode_chunk(chunk));
             }
+            BlockContent::CodeBlock(block) if is_raw(block) => {
+                if !content.is_empty() {
+                    cells.push(encode_markdown(&content));
+                    content.clear()
+                }
+                cells.push(encode_raw(block));
+            }
             _ => content.push(block.clone()),
         }
     }
@@ -142,7 +151,7 @@ fn encode_content(content: &Option<Vec<BlockContent>>) -> Vec<serde_json::Value>
 
 /// Encode a `CodeChunk` to a Jupyter code cell
 fn encode_chunk(chunk: &CodeChunk) -> serde_json::Value {
-    let mut metadata: HashMap<String, serde_json::Value> = HashMap::new();
+    let mut metadata = serde_json::Map::new();
 
     if let Some(id) = chunk.id.as_ref() {
         metadata.insert("id".to_string(), json!(*id));
@@ -169,25 +178,71 @@ fn encode_chunk(chunk: &CodeChunk) -> serde_json::Value {
         );
     }
 
-    let source = encode_multiline_string(&chunk.text);
+    let (tags, text) = encode_metadata_tags(&chunk.text);
+    metadata.extend(tags);
+
+    if let Some(meta) = chunk.meta.as_deref() {
+        for (name, value) in meta {
+            if let Ok(value) = serde_json::to_value(value) {
+                metadata.insert(name.clone(), value);
+            }
+        }
+    }
+
+    let source = encode_multiline_string(text);
 
-    let outputs = if let Some(outputs) = &chunk.outputs {
-        encode_outputs(outputs)
+    let mut outputs = if let Some(outputs) = &chunk.outputs {
+        encode_outputs(outputs, chunk.execute_count)
     } else {
         Vec::new()
     };
+    if let Some(errors) = &chunk.errors {
+        outputs.extend(errors.iter().map(encode_error));
+    }
 
     json!({
         "cell_type": "code",
         "source" : source,
         "outputs": outputs,
-        // The `execution_count` is required in Jupyter Notebook v4.5 but can be `null`;
-        // it is not the same as Stencila `execute_count`which increments across sessions.
-        "execution_count": null,
+        // The `execution_count` is required in Jupyter Notebook v4.5 but can be `null`
+        "execution_count": chunk.execute_count,
         "metadata": metadata
     })
 }
 
+/// Encode any leading `@` comment tags in the code of a `CodeChunk` that correspond
+/// to Jupyter cell `metadata` properties
+///
+/// Returns the metadata properties and the remaining code. This is the reverse of
+/// the translation done when decoding (e.g. `# @tags parameters, hide-input` becomes
+/// `"tags": ["parameters", "hide-input"]`).
+fn encode_metadata_tags(code: &str) -> (serde_json::Map<String, serde_json::Value>, &str) {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\s*(?:#|//|--|%)\s*@([a-z]+)\s+(.*?)\s*$").expect("Unable to create regex")
+    });
+
+    let mut metadata = serde_json::Map::new();
+    let mut rest = code;
+    loop {
+        let (line, remainder) = rest.split_once('\n').unwrap_or((rest, ""));
+        let (name, value) = match REGEX.captures(line) {
+            Some(captures) if METADATA_TAGS.contains(&&captures[1]) => {
+                (captures[1].to_string(), captures[2].to_string())
+            }
+            _ => break,
+        };
+        let value = json!(value
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect::<Vec<&str>>());
+        metadata.insert(name, value);
+        rest = remainder;
+    }
+
+    (metadata, rest)
+}
+
 /// Encode the `outputs` of a Stencila `CodeChunk` to an array of Jupyter `Output`s.
 ///
 /// Note that the Stencila document model does not differentiate among different sources
@@ -195,23 +250,45 @@ fn encode_chunk(chunk: &CodeChunk) -> serde_json::Value {
 /// So, we don't try to revert to the source that may have been in the `ipynb` originally.
 /// Instead, consistent with decoding, preformatted text in a code block goes to a stream
 /// output.
-fn encode_outputs(nodes: &[Node]) -> Vec<serde_json::Value> {
+fn encode_outputs(nodes: &[Node], execution_count: Option<u32>) -> Vec<serde_json::Value> {
     nodes
         .iter()
         .map(|node| match node {
             Node::ImageObject(image) => encode_display_data(image),
-            _ => encode_execute_result(node),
+            _ => encode_execute_result(node, execution_count),
         })
         .collect()
 }
 
 /// Encode a `String` as a Jupyter `Stream`.
-#[allow(dead_code)]
-fn encode_stream(text: &str) -> serde_json::Value {
+fn encode_stream(name: &str, text: &str) -> serde_json::Value {
     json!({
         "output_type": "stream",
-        "name": "stdout",
-        "text": text,
+        "name": name,
+        "text": encode_multiline_string(text),
+    })
+}
+
+/// Encode a `CodeError` as a Jupyter `Error`.
+///
+/// Errors that were decoded from the `stderr` stream are encoded back to a `Stream`.
+fn encode_error(error: &CodeError) -> serde_json::Value {
+    let error_type = error.error_type.as_deref().map(String::as_str);
+    if error_type == Some("stderr") {
+        return encode_stream("stderr", &error.error_message);
+    }
+
+    let traceback: Vec<&str> = error
+        .stack_trace
+        .as_deref()
+        .map(|stack_trace| stack_trace.lines().collect())
+        .unwrap_or_default();
+
+    json!({
+        "output_type": "error",
+        "ename": error_type.unwrap_or("Error"),
+        "evalue": error.error_message,
+        "traceback": traceback,
     })
 }
 
@@ -243,26 +320,67 @@ fn encode_display_data(image: &ImageObject) -> serde_json::Value {
 }
 
 /// Encode a `Node` as a Jupyter `ExecuteResult`.
-fn encode_execute_result(node: &Node) -> serde_json::Value {
+fn encode_execute_result(node: &Node, execution_count: Option<u32>) -> serde_json::Value {
     let text = node.to_txt();
     let data = json!({ "text/plain": encode_multiline_string(&text) });
     json!({
         "output_type": "execute_result",
         "data": data,
-        // The `execution_count` is required in Jupyter Notebook v4.5 but can be `null`;
-        // it is not the same as Stencila `execute_count`which increments across sessions.
-        "execution_count": null,
+        // The `execution_count` is required in Jupyter Notebook v4.5 but can be `null`
+        "execution_count": execution_count,
         "metadata": {},
     })
 }
 
 /// Encode a vector of `BlockContent` to a Jupyter Markdown cell
+///
+/// Images with data URIs that have a `name` parameter (i.e. those that were decoded
+/// from cell attachments) are encoded back to cell `attachments`.
 fn encode_markdown(blocks: &[BlockContent]) -> serde_json::Value {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\(data:([^;,()]+);name=([^;,()]+);base64,([A-Za-z0-9+/=]*)\)")
+            .expect("Unable to create regex")
+    });
+
     let md = blocks.to_md(&EncodeOptions::default());
-    json!({
+
+    let mut attachments = serde_json::Map::new();
+    let md = REGEX.replace_all(&md, |captures: &Captures| {
+        let media_type = &captures[1];
+        let name = &captures[2];
+        let data = &captures[3];
+        attachments.insert(name.to_string(), json!({ media_type: data }));
+        ["(attachment:", name, ")"].concat()
+    });
+
+    let mut cell = json!({
         "cell_type": "markdown",
         "source" : encode_multiline_string(&md),
         "metadata": {}
+    });
+    if !attachments.is_empty() {
+        cell["attachments"] = json!(attachments);
+    }
+    cell
+}
+
+/// Is a `CodeBlock` decoded from a Jupyter raw cell?
+fn is_raw(block: &CodeBlock) -> bool {
+    block.programming_language.is_none() && block.media_type.is_some()
+}
+
+/// Encode a `CodeBlock` to a Jupyter raw cell
+fn encode_raw(block: &CodeBlock) -> serde_json::Value {
+    let mut metadata = serde_json::Map::new();
+    if let Some(media_type) = block.media_type.as_deref() {
+        if media_type != RAW_MEDIA_TYPE {
+            metadata.insert("raw_mimetype".to_string(), json!(media_type));
+        }
+    }
+    json!({
+        "cell_type": "raw",
+        "source" : encode_multiline_string(&block.text),
+        "metadata": metadata
     })
 }
 
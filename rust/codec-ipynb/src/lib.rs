@@ -14,6 +14,26 @@ mod translate;
 use codec_md::MdCodec;
 pub use translate::*;
 
+/// Jupyter cell `metadata` properties that are decoded as `@` tags in the code of a `CodeChunk`
+///
+/// Stencila parses tags from comments in code (e.g. `# @tags parameters`) so representing these
+/// properties in this way makes them available in the chunk's `TagMap`. When encoding,
+/// these tags are removed from the code and restored to the cell's `metadata`.
+///
+/// Properties that only record the state of the notebook UI (e.g. `collapsed`, `scrolled`,
+/// `editable`) are deliberately not included so that they do not clutter the code. Instead,
+/// they are stored in the chunk's `meta` so that they are still round-tripped.
+#[cfg(any(feature = "decode", feature = "encode"))]
+const METADATA_TAGS: &[&str] = &["tags"];
+
+/// The media type given to the `CodeBlock` decoded from a Jupyter raw cell that does
+/// not specify a `raw_mimetype`
+///
+/// Raw cells are decoded to `CodeBlock`s with a `media_type` but without a
+/// `programming_language` so that they can be distinguished from other code blocks.
+#[cfg(any(feature = "decode", feature = "encode"))]
+const RAW_MEDIA_TYPE: &str = "text/plain";
+
 // A codec for Jupyter Notebook (.ipynb) files
 pub struct IpynbCodec {}
 
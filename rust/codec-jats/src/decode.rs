@@ -0,0 +1,867 @@
+use std::collections::HashMap;
+
+use codec::{
+    common::{
+        eyre::{bail, Result},
+        serde_json::{self, json},
+    },
+    stencila_schema::{
+        Article, BlockContent, Cite, CodeBlock, CodeFragment, CreativeWorkAuthors,
+        CreativeWorkContent, CreativeWorkFunders, CreativeWorkTitle, Date, Emphasis, FigureCaption,
+        FigureSimple, Heading, ImageObject, ImageObjectSimple, InlineContent, Link, List, ListItem,
+        ListItemContent, ListOrder, MathBlock, MathFragment, Node, Note, NoteNoteType,
+        Organization, OrganizationAddress, Paragraph, Person, QuoteBlock, Strikeout, Strong,
+        Subscript, Superscript, TableCaption, TableCell, TableCellCellType, TableCellContent,
+        TableRow, TableRowRowType, TableSimple, ThingDescription, ThingIdentifiers, Underline,
+    },
+};
+use codec_csl::CslCodec;
+use roxmltree::{Document, Node as XmlNode};
+
+use crate::{DOI_URL, ORCID_URL, PUBLICATION_TYPES};
+
+/// The XLink namespace (used for `xlink:href` attributes)
+const XLINK: &str = "http://www.w3.org/1999/xlink";
+
+/// Elements that are decoded as blocks, even when they are within a `<p>`
+const BLOCK_ELEMENTS: &[&str] = &[
+    "boxed-text",
+    "code",
+    "disp-formula",
+    "disp-quote",
+    "fig",
+    "fig-group",
+    "list",
+    "preformat",
+    "table-wrap",
+    "table-wrap-group",
+];
+
+/// Decode a JATS XML document into an `Article`
+pub(crate) fn decode(jats: &str) -> Result<Node> {
+    let doc = Document::parse(jats)?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "article" {
+        bail!(
+            "Expected the root element to be `<article>` but got `<{}>`",
+            root.tag_name().name()
+        )
+    }
+
+    let mut article = Article::default();
+
+    if let Some(meta) = child(root, "front").and_then(|front| child(front, "article-meta")) {
+        decode_meta(meta, &mut article);
+    }
+
+    if let Some(body) = child(root, "body") {
+        let content = decode_blocks(body, 1);
+        if !content.is_empty() {
+            article.content = Some(content);
+        }
+    }
+
+    if let Some(back) = child(root, "back") {
+        let items: Vec<serde_json::Value> = back
+            .descendants()
+            .filter(|node| node.has_tag_name("ref"))
+            .filter_map(decode_ref)
+            .collect();
+        if !items.is_empty() {
+            if let Node::Article(Article { references, .. }) = CslCodec::from_json_items(&items)? {
+                article.references = references;
+            }
+        }
+    }
+
+    Ok(Node::Article(article))
+}
+
+/// Get the first child element with a name
+fn child<'a, 'i>(node: XmlNode<'a, 'i>, name: &str) -> Option<XmlNode<'a, 'i>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Get all the child elements with a name
+fn children<'a, 'i: 'a>(
+    node: XmlNode<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = XmlNode<'a, 'i>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+/// Get the first descendant element with a name
+fn descendant<'a, 'i>(node: XmlNode<'a, 'i>, name: &str) -> Option<XmlNode<'a, 'i>> {
+    node.descendants()
+        .find(|descendant| descendant.has_tag_name(name))
+}
+
+/// Get the text content of a node, with whitespace collapsed
+fn text(node: XmlNode) -> String {
+    node.descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Get the text content of the first child element with a name, if it is not empty
+fn child_text(node: XmlNode, name: &str) -> Option<String> {
+    child(node, name).map(text).filter(|text| !text.is_empty())
+}
+
+/// Get the `xlink:href` attribute of a node
+fn href<'a>(node: XmlNode<'a, '_>) -> Option<&'a str> {
+    node.attribute((XLINK, "href"))
+}
+
+/// Get the `id` attribute of a node
+fn id(node: XmlNode) -> Option<String> {
+    node.attribute("id").map(String::from)
+}
+
+/// Decode the `<article-meta>` element into properties of the `Article`
+fn decode_meta(meta: XmlNode, article: &mut Article) {
+    if let Some(title) = child(meta, "title-group").and_then(|group| child(group, "article-title"))
+    {
+        let inlines = decode_inlines(title);
+        article.title = Some(Box::new(match inlines.as_slice() {
+            [InlineContent::String(string)] => CreativeWorkTitle::String(string.clone()),
+            _ => CreativeWorkTitle::VecInlineContent(inlines),
+        }));
+    }
+
+    // Affiliations can be within a `<contrib-group>` or directly within `<article-meta>`
+    let affs: HashMap<&str, Organization> = meta
+        .descendants()
+        .filter(|node| node.has_tag_name("aff"))
+        .filter_map(|aff| aff.attribute("id").map(|id| (id, decode_aff(aff))))
+        .collect();
+
+    let authors: Vec<CreativeWorkAuthors> = meta
+        .descendants()
+        .filter(|node| {
+            node.has_tag_name("contrib")
+                && matches!(node.attribute("contrib-type"), Some("author") | None)
+        })
+        .filter_map(|contrib| decode_contrib(contrib, &affs))
+        .collect();
+    if !authors.is_empty() {
+        article.authors = Some(authors);
+    }
+
+    if let Some(abstract_) = child(meta, "abstract") {
+        let blocks = decode_blocks(abstract_, 1);
+        if !blocks.is_empty() {
+            article.description = Some(Box::new(ThingDescription::VecBlockContent(blocks)));
+        }
+    }
+
+    let keywords: Vec<String> = meta
+        .descendants()
+        .filter(|node| node.has_tag_name("kwd"))
+        .map(text)
+        .filter(|kwd| !kwd.is_empty())
+        .collect();
+    if !keywords.is_empty() {
+        article.keywords = Some(keywords);
+    }
+
+    let pub_date = children(meta, "pub-date")
+        .find(|date| {
+            matches!(
+                date.attribute("date-type")
+                    .or_else(|| date.attribute("pub-type")),
+                Some("pub" | "epub")
+            )
+        })
+        .or_else(|| child(meta, "pub-date"));
+    article.date_published = pub_date.and_then(decode_date).map(Box::new);
+
+    if let Some(history) = child(meta, "history") {
+        for date in children(history, "date") {
+            match date.attribute("date-type") {
+                Some("received") => article.date_received = decode_date(date).map(Box::new),
+                Some("accepted") => article.date_accepted = decode_date(date).map(Box::new),
+                _ => {}
+            }
+        }
+    }
+
+    let mut funders: Vec<String> = Vec::new();
+    for source in meta
+        .descendants()
+        .filter(|node| node.has_tag_name("funding-source"))
+    {
+        let name = child_text(source, "institution").unwrap_or_else(|| text(source));
+        if !name.is_empty() && !funders.contains(&name) {
+            funders.push(name)
+        }
+    }
+    if !funders.is_empty() {
+        article.funders = Some(
+            funders
+                .into_iter()
+                .map(|name| {
+                    CreativeWorkFunders::Organization(Organization {
+                        name: Some(Box::new(name)),
+                        ..Default::default()
+                    })
+                })
+                .collect(),
+        );
+    }
+
+    let identifiers: Vec<ThingIdentifiers> = children(meta, "article-id")
+        .filter(|id| id.attribute("pub-id-type") == Some("doi"))
+        .map(|id| ThingIdentifiers::String([DOI_URL, &text(id)].concat()))
+        .collect();
+    if !identifiers.is_empty() {
+        article.identifiers = Some(identifiers);
+    }
+}
+
+/// Decode an `<aff>` element into an `Organization`
+///
+/// The name is taken from any `<institution>` elements (falling back to the
+/// text of the element) and the address from any `<addr-line>`, `<city>` and
+/// `<country>` elements.
+fn decode_aff(aff: XmlNode) -> Organization {
+    let parts = |names: &[&str]| {
+        aff.descendants()
+            .filter(|node| names.contains(&node.tag_name().name()))
+            .map(text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    let name = parts(&["institution"]);
+    let name = if name.is_empty() {
+        aff.children()
+            .filter(|node| !node.has_tag_name("label"))
+            .map(text)
+            .collect::<Vec<String>>()
+            .join(" ")
+            .trim()
+            .to_string()
+    } else {
+        name
+    };
+
+    let address = parts(&["addr-line", "city", "country"]);
+
+    Organization {
+        name: (!name.is_empty()).then_some(Box::new(name)),
+        address: (!address.is_empty()).then_some(Box::new(OrganizationAddress::String(address))),
+        ..Default::default()
+    }
+}
+
+/// Decode a `<contrib>` element into an author
+fn decode_contrib(
+    contrib: XmlNode,
+    affs: &HashMap<&str, Organization>,
+) -> Option<CreativeWorkAuthors> {
+    if let Some(collab) = child_text(contrib, "collab") {
+        return Some(CreativeWorkAuthors::Organization(Organization {
+            name: Some(Box::new(collab)),
+            ..Default::default()
+        }));
+    }
+
+    let mut person = Person::default();
+    if let Some(name) = child(contrib, "name") {
+        person.family_names = child_text(name, "surname").map(split_names);
+        person.given_names = child_text(name, "given-names").map(split_names);
+    } else if let Some(name) = child_text(contrib, "string-name") {
+        person.name = Some(Box::new(name));
+    } else {
+        return None;
+    }
+
+    let emails: Vec<String> = children(contrib, "email").map(text).collect();
+    if !emails.is_empty() {
+        person.emails = Some(emails);
+    }
+
+    if let Some(orcid) = children(contrib, "contrib-id")
+        .find(|id| id.attribute("contrib-id-type") == Some("orcid"))
+        .map(text)
+    {
+        let orcid = if orcid.starts_with("http") {
+            orcid
+        } else {
+            [ORCID_URL, &orcid].concat()
+        };
+        person.identifiers = Some(vec![ThingIdentifiers::String(orcid)]);
+    }
+
+    let affiliations: Vec<Organization> = children(contrib, "xref")
+        .filter(|xref| xref.attribute("ref-type") == Some("aff"))
+        .filter_map(|xref| xref.attribute("rid"))
+        .flat_map(|rids| rids.split_whitespace())
+        .filter_map(|rid| affs.get(rid).cloned())
+        .chain(children(contrib, "aff").map(decode_aff))
+        .collect();
+    if !affiliations.is_empty() {
+        person.affiliations = Some(affiliations);
+    }
+
+    Some(CreativeWorkAuthors::Person(person))
+}
+
+/// Split a string of names (e.g. `Josiah S`) into a vector of names
+fn split_names(names: String) -> Vec<String> {
+    names.split_whitespace().map(String::from).collect()
+}
+
+/// Decode a date element (e.g. `<pub-date>`) into a `Date`
+///
+/// Months and days are zero padded so that the date is in ISO 8601 format.
+fn decode_date(date: XmlNode) -> Option<Date> {
+    let year = child_text(date, "year")?;
+    let mut value = year;
+    if let Some(month) = child_text(date, "month") {
+        value.push_str(&format!("-{:0>2}", month));
+        if let Some(day) = child_text(date, "day") {
+            value.push_str(&format!("-{:0>2}", day));
+        }
+    }
+    Some(Date {
+        value,
+        ..Default::default()
+    })
+}
+
+/// Decode the child elements of a node into blocks
+///
+/// The `depth` is the depth of `Heading`s created from `<sec>` elements.
+fn decode_blocks(node: XmlNode, depth: u8) -> Vec<BlockContent> {
+    let mut blocks = Vec::new();
+    for child in node.children().filter(|child| child.is_element()) {
+        match child.tag_name().name() {
+            "sec" => {
+                if let Some(title) = self::child(child, "title") {
+                    blocks.push(BlockContent::Heading(Heading {
+                        content: decode_inlines(title),
+                        depth: Some(depth),
+                        id: id(child).map(Box::new),
+                        ..Default::default()
+                    }));
+                }
+                blocks.append(&mut decode_blocks(child, depth + 1));
+            }
+            "p" => blocks.append(&mut decode_paragraph(child, depth)),
+            // Titles and labels are handled by the parent element
+            "title" | "label" => {}
+            _ => blocks.append(&mut decode_block(child, depth)),
+        }
+    }
+    blocks
+}
+
+/// Decode a `<p>` element into blocks
+///
+/// Usually this will be a single `Paragraph` but because JATS allows block elements
+/// (e.g. `<fig>`) within paragraphs, these are split out into separate blocks.
+fn decode_paragraph(para: XmlNode, depth: u8) -> Vec<BlockContent> {
+    let mut blocks = Vec::new();
+    let mut inlines = Vec::new();
+    for child in para.children() {
+        if BLOCK_ELEMENTS.contains(&child.tag_name().name()) {
+            let content = tidy_inlines(std::mem::take(&mut inlines));
+            if !content.is_empty() {
+                blocks.push(BlockContent::Paragraph(Paragraph {
+                    content,
+                    ..Default::default()
+                }));
+            }
+            blocks.append(&mut decode_block(child, depth));
+        } else {
+            decode_inline(child, &mut inlines);
+        }
+    }
+    let content = tidy_inlines(inlines);
+    if !content.is_empty() {
+        blocks.push(BlockContent::Paragraph(Paragraph {
+            content,
+            ..Default::default()
+        }));
+    }
+    blocks
+}
+
+/// Decode a block element (other than `<sec>` and `<p>`)
+///
+/// Returns a vector because some elements (e.g. `<fig-group>`) are "unwrapped"
+/// into their child blocks. Unhandled elements are ignored.
+fn decode_block(node: XmlNode, depth: u8) -> Vec<BlockContent> {
+    let block = match node.tag_name().name() {
+        "boxed-text" | "fig-group" | "table-wrap-group" => return decode_blocks(node, depth),
+        "fig" => {
+            let content: Vec<Node> = node
+                .children()
+                .filter(|child| child.has_tag_name("graphic"))
+                .filter_map(href)
+                .map(|url| {
+                    Node::ImageObject(ImageObject {
+                        content_url: url.to_string(),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            BlockContent::Figure(FigureSimple {
+                id: id(node).map(Box::new),
+                label: child_text(node, "label").map(Box::new),
+                caption: child(node, "caption").map(|caption| {
+                    Box::new(FigureCaption::VecBlockContent(decode_caption(
+                        caption, depth,
+                    )))
+                }),
+                content: (!content.is_empty())
+                    .then_some(Box::new(CreativeWorkContent::VecNode(content))),
+                ..Default::default()
+            })
+        }
+        "table-wrap" => {
+            let rows = child(node, "table").map(decode_rows).unwrap_or_default();
+            BlockContent::Table(TableSimple {
+                id: id(node).map(Box::new),
+                label: child_text(node, "label").map(Box::new),
+                caption: child(node, "caption").map(|caption| {
+                    Box::new(TableCaption::VecBlockContent(decode_caption(
+                        caption, depth,
+                    )))
+                }),
+                rows,
+                ..Default::default()
+            })
+        }
+        "disp-formula" => {
+            let (text, math_language) = decode_math(node);
+            BlockContent::MathBlock(MathBlock {
+                id: id(node).map(Box::new),
+                label: child_text(node, "label").map(Box::new),
+                text,
+                math_language: Some(Box::new(math_language)),
+                ..Default::default()
+            })
+        }
+        "list" => BlockContent::List(List {
+            order: Some(match node.attribute("list-type") {
+                Some("order" | "alpha-lower" | "alpha-upper" | "roman-lower" | "roman-upper") => {
+                    ListOrder::Ascending
+                }
+                _ => ListOrder::Unordered,
+            }),
+            items: children(node, "list-item")
+                .map(|item| ListItem {
+                    content: Some(ListItemContent::VecBlockContent(decode_blocks(item, depth))),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }),
+        "disp-quote" => BlockContent::QuoteBlock(QuoteBlock {
+            content: decode_blocks(node, depth),
+            ..Default::default()
+        }),
+        "code" | "preformat" => BlockContent::CodeBlock(CodeBlock {
+            text: raw_text(node),
+            programming_language: node
+                .attribute("language")
+                .map(|lang| Box::new(lang.to_string())),
+            ..Default::default()
+        }),
+        _ => return Vec::new(),
+    };
+    vec![block]
+}
+
+/// Get the text content of a node without collapsing whitespace (e.g. for code)
+fn raw_text(node: XmlNode) -> String {
+    node.descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect::<String>()
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Decode a `<caption>` element into blocks
+///
+/// Any `<title>` within the caption is decoded as the first paragraph.
+fn decode_caption(caption: XmlNode, depth: u8) -> Vec<BlockContent> {
+    let mut blocks = Vec::new();
+    if let Some(title) = child(caption, "title") {
+        blocks.push(BlockContent::Paragraph(Paragraph {
+            content: decode_inlines(title),
+            ..Default::default()
+        }))
+    }
+    blocks.append(&mut decode_blocks(caption, depth));
+    blocks
+}
+
+/// Decode the rows of a `<table>` element
+fn decode_rows(table: XmlNode) -> Vec<TableRow> {
+    let mut rows = Vec::new();
+    for child in table.children() {
+        let row_type = match child.tag_name().name() {
+            "thead" => Some(TableRowRowType::Header),
+            "tfoot" => Some(TableRowRowType::Footer),
+            "tbody" => None,
+            "tr" => {
+                rows.push(decode_row(child, None));
+                continue;
+            }
+            _ => continue,
+        };
+        for tr in children(child, "tr") {
+            rows.push(decode_row(tr, row_type.clone()))
+        }
+    }
+    rows
+}
+
+/// Decode a `<tr>` element into a `TableRow`
+fn decode_row(tr: XmlNode, row_type: Option<TableRowRowType>) -> TableRow {
+    let span = |cell: XmlNode, name: &str| {
+        cell.attribute(name)
+            .and_then(|span| span.parse::<u32>().ok())
+            .filter(|span| *span != 1)
+    };
+
+    let cells = tr
+        .children()
+        .filter(|cell| cell.has_tag_name("td") || cell.has_tag_name("th"))
+        .map(|cell| {
+            let content = if cell.children().any(|child| child.has_tag_name("p")) {
+                TableCellContent::VecBlockContent(decode_blocks(cell, 1))
+            } else {
+                TableCellContent::VecInlineContent(decode_inlines(cell))
+            };
+            TableCell {
+                cell_type: cell.has_tag_name("th").then_some(TableCellCellType::Header),
+                colspan: span(cell, "colspan"),
+                rowspan: span(cell, "rowspan"),
+                content: Some(content),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    TableRow {
+        cells,
+        row_type,
+        ..Default::default()
+    }
+}
+
+/// Decode the math within a `<disp-formula>` or `<inline-formula>`
+///
+/// Returns the text of the math and its language. MathML is extracted as
+/// the raw XML of the `<mml:math>` element.
+fn decode_math(node: XmlNode) -> (String, String) {
+    if let Some(tex) = descendant(node, "tex-math") {
+        return (raw_text(tex).trim().to_string(), "tex".to_string());
+    }
+    if let Some(math) = descendant(node, "math") {
+        if let Some(mathml) = node.document().input_text().get(math.range()) {
+            return (mathml.to_string(), "mathml".to_string());
+        }
+    }
+    (text(node), "text".to_string())
+}
+
+/// Decode the child nodes of an element into inlines
+fn decode_inlines(node: XmlNode) -> Vec<InlineContent> {
+    let mut inlines = Vec::new();
+    for child in node.children() {
+        decode_inline(child, &mut inlines)
+    }
+    tidy_inlines(inlines)
+}
+
+/// Decode an inline node, appending to a vector of inlines
+///
+/// Elements that are not handled (e.g. `<named-content>`) are "unwrapped"
+/// so that their content is not lost.
+fn decode_inline(node: XmlNode, inlines: &mut Vec<InlineContent>) {
+    if node.is_text() {
+        if let Some(text) = node.text() {
+            inlines.push(InlineContent::String(collapse_whitespace(text)));
+        }
+        return;
+    }
+    if !node.is_element() {
+        return;
+    }
+
+    let inline = match node.tag_name().name() {
+        "italic" => InlineContent::Emphasis(Emphasis {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "bold" => InlineContent::Strong(Strong {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "sub" => InlineContent::Subscript(Subscript {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "sup" => InlineContent::Superscript(Superscript {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "underline" => InlineContent::Underline(Underline {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "strike" => InlineContent::Strikeout(Strikeout {
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "monospace" => InlineContent::CodeFragment(CodeFragment {
+            text: raw_text(node),
+            ..Default::default()
+        }),
+        "ext-link" | "uri" => InlineContent::Link(Link {
+            target: href(node).map(String::from).unwrap_or_else(|| text(node)),
+            content: decode_inlines(node),
+            ..Default::default()
+        }),
+        "xref" => {
+            let rid = node.attribute("rid").unwrap_or_default().to_string();
+            let content = decode_inlines(node);
+            match node.attribute("ref-type") {
+                Some("bibr") => InlineContent::Cite(Cite {
+                    target: rid,
+                    content: (!content.is_empty()).then_some(content),
+                    ..Default::default()
+                }),
+                _ => InlineContent::Link(Link {
+                    target: ["#", &rid].concat(),
+                    content,
+                    ..Default::default()
+                }),
+            }
+        }
+        "inline-formula" => {
+            let (text, math_language) = decode_math(node);
+            InlineContent::MathFragment(MathFragment {
+                text,
+                math_language: Some(Box::new(math_language)),
+                ..Default::default()
+            })
+        }
+        "inline-graphic" => match href(node) {
+            Some(url) => InlineContent::ImageObject(ImageObjectSimple {
+                content_url: url.to_string(),
+                ..Default::default()
+            }),
+            None => return,
+        },
+        "fn" => InlineContent::Note(Note {
+            content: decode_blocks(node, 1),
+            note_type: Some(NoteNoteType::Footnote),
+            ..Default::default()
+        }),
+        "break" => InlineContent::String(" ".to_string()),
+        _ => {
+            for child in node.children() {
+                decode_inline(child, inlines)
+            }
+            return;
+        }
+    };
+    inlines.push(inline)
+}
+
+/// Collapse runs of whitespace in a string into a single space
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for char in text.chars() {
+        if char.is_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(char);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Tidy a vector of inlines by merging adjacent strings, trimming leading and
+/// trailing whitespace, and removing empty strings
+fn tidy_inlines(inlines: Vec<InlineContent>) -> Vec<InlineContent> {
+    let mut tidied: Vec<InlineContent> = Vec::with_capacity(inlines.len());
+    for inline in inlines {
+        match (tidied.last_mut(), inline) {
+            (Some(InlineContent::String(last)), InlineContent::String(string)) => {
+                if last.ends_with(' ') && string.starts_with(' ') {
+                    last.push_str(&string[1..])
+                } else {
+                    last.push_str(&string)
+                }
+            }
+            (_, inline) => tidied.push(inline),
+        }
+    }
+
+    if let Some(InlineContent::String(first)) = tidied.first_mut() {
+        *first = first.trim_start().to_string();
+    }
+    if let Some(InlineContent::String(last)) = tidied.last_mut() {
+        *last = last.trim_end().to_string();
+    }
+    tidied.retain(|inline| !matches!(inline, InlineContent::String(string) if string.is_empty()));
+    tidied
+}
+
+/// Decode a `<ref>` element into a CSL-JSON item
+fn decode_ref(ref_: XmlNode) -> Option<serde_json::Value> {
+    let citation = ref_.children().find(|child| {
+        matches!(
+            child.tag_name().name(),
+            "element-citation" | "mixed-citation" | "citation"
+        )
+    })?;
+
+    let mut item = serde_json::Map::new();
+
+    if let Some(id) = ref_.attribute("id") {
+        item.insert("id".to_string(), json!(id));
+    }
+
+    let type_ = citation
+        .attribute("publication-type")
+        .and_then(|type_| {
+            PUBLICATION_TYPES
+                .iter()
+                .find(|(jats, ..)| *jats == type_)
+                .map(|(.., csl)| *csl)
+        })
+        .unwrap_or("article-journal");
+    item.insert("type".to_string(), json!(type_));
+
+    for group in children(citation, "person-group") {
+        let key = match group.attribute("person-group-type") {
+            Some("author") | None => "author",
+            Some("editor") => "editor",
+            _ => continue,
+        };
+        let names = decode_ref_names(group);
+        if !names.is_empty() {
+            item.insert(key.to_string(), json!(names));
+        }
+    }
+    if !item.contains_key("author") {
+        // Names may be directly within the citation (most often in `<mixed-citation>`s)
+        let names = decode_ref_names(citation);
+        if !names.is_empty() {
+            item.insert("author".to_string(), json!(names));
+        }
+    }
+
+    let title = ["article-title", "chapter-title", "data-title", "part-title"]
+        .iter()
+        .find_map(|name| child_text(citation, name));
+    let source = child_text(citation, "source");
+    match (title, source) {
+        (Some(title), source) => {
+            item.insert("title".to_string(), json!(title));
+            if let Some(source) = source {
+                item.insert("container-title".to_string(), json!(source));
+            }
+        }
+        (None, Some(source)) => {
+            item.insert("title".to_string(), json!(source));
+        }
+        (None, None) => {}
+    }
+
+    if let Some(year) = child_text(citation, "year") {
+        let parts: Vec<String> = [
+            Some(year),
+            child_text(citation, "month"),
+            child_text(citation, "day"),
+        ]
+        .into_iter()
+        .map_while(|part| part)
+        .collect();
+        match parts
+            .iter()
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+        {
+            Ok(parts) => item.insert("issued".to_string(), json!({ "date-parts": [parts] })),
+            Err(..) => item.insert("issued".to_string(), json!({ "raw": parts.join("-") })),
+        };
+    }
+
+    for (name, key) in [("volume", "volume"), ("issue", "issue")] {
+        if let Some(value) = child_text(citation, name) {
+            item.insert(key.to_string(), json!(value));
+        }
+    }
+
+    let page = match (child_text(citation, "fpage"), child_text(citation, "lpage")) {
+        (Some(fpage), Some(lpage)) => Some([fpage, lpage].join("-")),
+        (Some(fpage), None) => Some(fpage),
+        _ => child_text(citation, "elocation-id").or_else(|| child_text(citation, "page-range")),
+    };
+    if let Some(page) = page {
+        item.insert("page".to_string(), json!(page));
+    }
+
+    if let Some(publisher) = child_text(citation, "publisher-name") {
+        item.insert("publisher".to_string(), json!(publisher));
+    }
+
+    if let Some(doi) = children(citation, "pub-id")
+        .find(|id| id.attribute("pub-id-type") == Some("doi"))
+        .map(text)
+    {
+        item.insert("DOI".to_string(), json!(doi));
+    }
+
+    if let Some(url) = child(citation, "ext-link")
+        .map(|link| href(link).map(String::from).unwrap_or_else(|| text(link)))
+        .or_else(|| child_text(citation, "uri"))
+    {
+        item.insert("URL".to_string(), json!(url));
+    }
+
+    Some(serde_json::Value::Object(item))
+}
+
+/// Decode the names (`<name>`, `<string-name>` and `<collab>` elements) within
+/// a node into CSL-JSON `name-variable`s
+fn decode_ref_names(node: XmlNode) -> Vec<serde_json::Value> {
+    node.children()
+        .filter_map(|child| match child.tag_name().name() {
+            "name" | "string-name" if child.children().any(|node| node.has_tag_name("surname")) => {
+                let mut name = serde_json::Map::new();
+                if let Some(surname) = child_text(child, "surname") {
+                    name.insert("family".to_string(), json!(surname));
+                }
+                if let Some(given) = child_text(child, "given-names") {
+                    name.insert("given".to_string(), json!(given));
+                }
+                Some(serde_json::Value::Object(name))
+            }
+            "string-name" | "collab" => Some(json!({ "literal": text(child) })),
+            _ => None,
+        })
+        .collect()
+}
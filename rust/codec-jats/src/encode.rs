@@ -0,0 +1,717 @@
+use codec::{
+    common::{
+        eyre::{bail, Result},
+        serde_json,
+    },
+    stencila_schema::{
+        Article, BlockContent, CreativeWorkAuthors, CreativeWorkContent, CreativeWorkFunders,
+        CreativeWorkTitle, Date, FigureCaption, InlineContent, ListItemContent, ListOrder, Node,
+        Organization, OrganizationAddress, Person, TableCaption, TableCellCellType,
+        TableCellContent, TableRow, TableRowRowType, ThingDescription, ThingIdentifiers,
+    },
+};
+use codec_csl::CslCodec;
+
+use crate::{DOI_URL, ORCID_URL, PUBLICATION_TYPES};
+
+/// Encode an `Article` into a JATS XML document
+pub(crate) fn encode(node: &Node) -> Result<String> {
+    let article = match node {
+        Node::Article(article) => article,
+        _ => bail!("Only an `Article` can be encoded as JATS"),
+    };
+
+    let front = elem(
+        "front",
+        &[],
+        &elem("article-meta", &[], &encode_meta(article)),
+    );
+
+    let body = elem(
+        "body",
+        &[],
+        &encode_sections(article.content.as_deref().unwrap_or_default()),
+    );
+
+    let back = match &article.references {
+        Some(references) => {
+            let refs = references
+                .iter()
+                .filter_map(CslCodec::to_json_reference)
+                .enumerate()
+                .map(|(index, item)| encode_ref(&item, index))
+                .collect::<Vec<String>>()
+                .concat();
+            elem(
+                "back",
+                &[],
+                &elem(
+                    "ref-list",
+                    &[],
+                    &[elem("title", &[], "References"), refs].concat(),
+                ),
+            )
+        }
+        None => String::new(),
+    };
+
+    Ok([
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        "\n",
+        r#"<!DOCTYPE article PUBLIC "-//NLM//DTD JATS (Z39.96) Journal Archiving and Interchange DTD v1.2 20190208//EN" "JATS-archivearticle1.dtd">"#,
+        "\n",
+        r#"<article xmlns:mml="http://www.w3.org/1998/Math/MathML" xmlns:xlink="http://www.w3.org/1999/xlink" article-type="research-article" dtd-version="1.2">"#,
+        "\n",
+        &front,
+        "\n",
+        &body,
+        "\n",
+        &back,
+        "</article>\n",
+    ]
+    .concat())
+}
+
+/// Escape special characters in XML text and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Encode an element with attributes and (already encoded) content
+fn elem(name: &str, attrs: &[(&str, &str)], content: &str) -> String {
+    let attrs = attrs
+        .iter()
+        .map(|(name, value)| [" ", name, "=\"", &escape(value), "\""].concat())
+        .collect::<Vec<String>>()
+        .concat();
+    if content.is_empty() {
+        ["<", name, &attrs, "/>"].concat()
+    } else {
+        ["<", name, &attrs, ">", content, "</", name, ">"].concat()
+    }
+}
+
+/// Encode an element containing text, or nothing if there is no text
+fn elem_text(name: &str, text: Option<&str>) -> String {
+    match text {
+        Some(text) if !text.is_empty() => elem(name, &[], &escape(text)),
+        _ => String::new(),
+    }
+}
+
+/// Encode the metadata of an `Article` as the content of `<article-meta>`
+///
+/// Elements are encoded in the order required by the JATS DTD.
+fn encode_meta(article: &Article) -> String {
+    let mut meta = String::new();
+
+    for id in article.identifiers.iter().flatten() {
+        if let ThingIdentifiers::String(id) = id {
+            if let Some(doi) = id.strip_prefix(DOI_URL) {
+                meta += &elem("article-id", &[("pub-id-type", "doi")], &escape(doi));
+            }
+        }
+    }
+
+    let title = match article.title.as_deref() {
+        Some(CreativeWorkTitle::String(title)) => escape(title),
+        Some(CreativeWorkTitle::VecInlineContent(inlines)) => encode_inlines(inlines),
+        None => String::new(),
+    };
+    meta += &elem("title-group", &[], &elem("article-title", &[], &title));
+
+    if let Some(authors) = &article.authors {
+        meta += &encode_authors(authors);
+    }
+
+    if let Some(date) = &article.date_published {
+        meta += &encode_date("pub-date", "pub", date);
+    }
+    let history = [
+        ("received", &article.date_received),
+        ("accepted", &article.date_accepted),
+    ]
+    .iter()
+    .filter_map(|(type_, date)| date.as_deref().map(|date| encode_date("date", type_, date)))
+    .collect::<Vec<String>>()
+    .concat();
+    if !history.is_empty() {
+        meta += &elem("history", &[], &history);
+    }
+
+    let abstract_ = match article.description.as_deref() {
+        Some(ThingDescription::VecBlockContent(blocks)) => encode_blocks(blocks),
+        Some(ThingDescription::VecInlineContent(inlines)) => {
+            elem("p", &[], &encode_inlines(inlines))
+        }
+        Some(ThingDescription::String(string)) => elem("p", &[], &escape(string)),
+        None => String::new(),
+    };
+    if !abstract_.is_empty() {
+        meta += &elem("abstract", &[], &abstract_);
+    }
+
+    if let Some(keywords) = &article.keywords {
+        let kwds = keywords
+            .iter()
+            .map(|kwd| elem_text("kwd", Some(kwd)))
+            .collect::<Vec<String>>()
+            .concat();
+        meta += &elem("kwd-group", &[], &kwds);
+    }
+
+    if let Some(funders) = &article.funders {
+        let groups = funders
+            .iter()
+            .filter_map(|funder| match funder {
+                CreativeWorkFunders::Organization(org) => org.name.as_deref().cloned(),
+                CreativeWorkFunders::Person(person) => person_name(person),
+            })
+            .map(|name| {
+                elem(
+                    "award-group",
+                    &[],
+                    &elem(
+                        "funding-source",
+                        &[],
+                        &elem_text("institution", Some(&name)),
+                    ),
+                )
+            })
+            .collect::<Vec<String>>()
+            .concat();
+        meta += &elem("funding-group", &[], &groups);
+    }
+
+    meta
+}
+
+/// Get the full name of a `Person`
+fn person_name(person: &Person) -> Option<String> {
+    let names: Vec<String> = person
+        .given_names
+        .iter()
+        .flatten()
+        .chain(person.family_names.iter().flatten())
+        .cloned()
+        .collect();
+    if names.is_empty() {
+        person.name.as_deref().cloned()
+    } else {
+        Some(names.join(" "))
+    }
+}
+
+/// Encode the authors of an `Article` as a `<contrib-group>`
+///
+/// Affiliations are de-duplicated and encoded as `<aff>` elements at the end of the
+/// group and referenced from each `<contrib>` using an `<xref>`.
+fn encode_authors(authors: &[CreativeWorkAuthors]) -> String {
+    let mut affs: Vec<&Organization> = Vec::new();
+    let mut contribs = String::new();
+    for author in authors {
+        let content = match author {
+            CreativeWorkAuthors::Organization(org) => {
+                elem_text("collab", org.name.as_deref().map(String::as_str))
+            }
+            CreativeWorkAuthors::Person(person) => {
+                let mut content = String::new();
+                if let Some(orcid) = person.identifiers.iter().flatten().find_map(|id| match id {
+                    ThingIdentifiers::String(id) if id.starts_with(ORCID_URL) => Some(id),
+                    _ => None,
+                }) {
+                    content += &elem(
+                        "contrib-id",
+                        &[("contrib-id-type", "orcid")],
+                        &escape(orcid),
+                    );
+                }
+
+                if person.family_names.is_some() || person.given_names.is_some() {
+                    let surname = person.family_names.as_ref().map(|names| names.join(" "));
+                    let given_names = person.given_names.as_ref().map(|names| names.join(" "));
+                    content += &elem(
+                        "name",
+                        &[],
+                        &[
+                            elem_text("surname", surname.as_deref()),
+                            elem_text("given-names", given_names.as_deref()),
+                        ]
+                        .concat(),
+                    );
+                } else {
+                    content +=
+                        &elem_text("string-name", person.name.as_deref().map(String::as_str));
+                }
+
+                for email in person.emails.iter().flatten() {
+                    content += &elem_text("email", Some(email));
+                }
+
+                for aff in person.affiliations.iter().flatten() {
+                    let index = match affs.iter().position(|existing| *existing == aff) {
+                        Some(index) => index,
+                        None => {
+                            affs.push(aff);
+                            affs.len() - 1
+                        }
+                    };
+                    let rid = format!("aff{}", index + 1);
+                    content += &elem(
+                        "xref",
+                        &[("ref-type", "aff"), ("rid", &rid)],
+                        &(index + 1).to_string(),
+                    );
+                }
+
+                content
+            }
+        };
+        contribs += &elem("contrib", &[("contrib-type", "author")], &content);
+    }
+
+    let affs = affs
+        .iter()
+        .enumerate()
+        .map(|(index, org)| {
+            let address = match org.address.as_deref() {
+                Some(OrganizationAddress::String(address)) => {
+                    [", ", &elem_text("addr-line", Some(address))].concat()
+                }
+                _ => String::new(),
+            };
+            elem(
+                "aff",
+                &[("id", &format!("aff{}", index + 1))],
+                &[
+                    elem_text("institution", org.name.as_deref().map(String::as_str)),
+                    address,
+                ]
+                .concat(),
+            )
+        })
+        .collect::<Vec<String>>()
+        .concat();
+
+    elem("contrib-group", &[], &[contribs, affs].concat())
+}
+
+/// Encode a `Date` as a JATS date element (e.g. `<pub-date>`)
+fn encode_date(name: &str, type_: &str, date: &Date) -> String {
+    let value = date.value.split('T').next().unwrap_or_default();
+    let mut parts = value.split('-');
+    let year = parts.next();
+    let month = parts.next();
+    let day = parts.next();
+    elem(
+        name,
+        &[("date-type", type_)],
+        &[
+            elem_text("day", day),
+            elem_text("month", month),
+            elem_text("year", year),
+        ]
+        .concat(),
+    )
+}
+
+/// Encode blocks as the content of a `<body>`, nesting them into
+/// `<sec>` elements based on the depth of `Heading`s
+fn encode_sections(blocks: &[BlockContent]) -> String {
+    let mut jats = String::new();
+    let mut depths: Vec<u8> = Vec::new();
+    for block in blocks {
+        if let BlockContent::Heading(heading) = block {
+            let depth = heading.depth.unwrap_or(1);
+            while matches!(depths.last(), Some(last) if *last >= depth) {
+                jats += "</sec>";
+                depths.pop();
+            }
+            match heading.id.as_deref() {
+                Some(id) => jats += &["<sec id=\"", &escape(id), "\">"].concat(),
+                None => jats += "<sec>",
+            }
+            jats += &elem("title", &[], &encode_inlines(&heading.content));
+            depths.push(depth);
+        } else {
+            jats += &encode_block(block);
+        }
+    }
+    jats += &"</sec>".repeat(depths.len());
+    jats
+}
+
+/// Encode blocks (e.g. within a caption or list item)
+///
+/// Any `Heading`s are encoded as bold paragraphs since sections
+/// are only allowed within the `<body>`.
+fn encode_blocks(blocks: &[BlockContent]) -> String {
+    blocks
+        .iter()
+        .map(|block| match block {
+            BlockContent::Heading(heading) => elem(
+                "p",
+                &[],
+                &elem("bold", &[], &encode_inlines(&heading.content)),
+            ),
+            _ => encode_block(block),
+        })
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+/// Encode a block
+///
+/// Block types that have no equivalent in JATS are ignored.
+fn encode_block(block: &BlockContent) -> String {
+    match block {
+        BlockContent::Paragraph(para) => elem("p", &[], &encode_inlines(&para.content)),
+        BlockContent::Figure(figure) => {
+            let caption = match figure.caption.as_deref() {
+                Some(FigureCaption::VecBlockContent(blocks)) => encode_blocks(blocks),
+                Some(FigureCaption::String(string)) => elem("p", &[], &escape(string)),
+                None => String::new(),
+            };
+            let graphics = match figure.content.as_deref() {
+                Some(CreativeWorkContent::VecNode(nodes)) => nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        Node::ImageObject(image) => {
+                            Some(elem("graphic", &[("xlink:href", &image.content_url)], ""))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<String>>()
+                    .concat(),
+                _ => String::new(),
+            };
+            elem(
+                "fig",
+                &id_attr(figure.id.as_deref()),
+                &[
+                    elem_text("label", figure.label.as_deref().map(String::as_str)),
+                    elem_caption(&caption),
+                    graphics,
+                ]
+                .concat(),
+            )
+        }
+        BlockContent::Table(table) => {
+            let caption = match table.caption.as_deref() {
+                Some(TableCaption::VecBlockContent(blocks)) => encode_blocks(blocks),
+                Some(TableCaption::String(string)) => elem("p", &[], &escape(string)),
+                None => String::new(),
+            };
+            elem(
+                "table-wrap",
+                &id_attr(table.id.as_deref()),
+                &[
+                    elem_text("label", table.label.as_deref().map(String::as_str)),
+                    elem_caption(&caption),
+                    elem("table", &[], &encode_rows(&table.rows)),
+                ]
+                .concat(),
+            )
+        }
+        BlockContent::MathBlock(math) => elem(
+            "disp-formula",
+            &id_attr(math.id.as_deref()),
+            &[
+                elem_text("label", math.label.as_deref().map(String::as_str)),
+                encode_math(&math.text, math.math_language.as_deref()),
+            ]
+            .concat(),
+        ),
+        BlockContent::List(list) => {
+            let list_type = match list.order {
+                Some(ListOrder::Ascending) => "order",
+                _ => "bullet",
+            };
+            let items = list
+                .items
+                .iter()
+                .map(|item| {
+                    let content = match &item.content {
+                        Some(ListItemContent::VecBlockContent(blocks)) => encode_blocks(blocks),
+                        Some(ListItemContent::VecInlineContent(inlines)) => {
+                            elem("p", &[], &encode_inlines(inlines))
+                        }
+                        None => String::new(),
+                    };
+                    elem("list-item", &[], &content)
+                })
+                .collect::<Vec<String>>()
+                .concat();
+            elem("list", &[("list-type", list_type)], &items)
+        }
+        BlockContent::QuoteBlock(quote) => elem("disp-quote", &[], &encode_blocks(&quote.content)),
+        BlockContent::CodeBlock(code) => match code.programming_language.as_deref() {
+            Some(lang) => elem("code", &[("language", lang)], &escape(&code.text)),
+            None => elem("preformat", &[], &escape(&code.text)),
+        },
+        BlockContent::CodeChunk(chunk) => elem(
+            "code",
+            &[("language", &chunk.programming_language)],
+            &escape(&chunk.text),
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Encode an `id` attribute, if any
+fn id_attr(id: Option<&String>) -> Vec<(&'static str, &str)> {
+    match id {
+        Some(id) => vec![("id", id.as_str())],
+        None => Vec::new(),
+    }
+}
+
+/// Encode a `<caption>` element, or nothing if there is no caption
+fn elem_caption(content: &str) -> String {
+    match content.is_empty() {
+        true => String::new(),
+        false => elem("caption", &[], content),
+    }
+}
+
+/// Encode the rows of a table into `<thead>`, `<tbody>` and `<tfoot>` elements
+fn encode_rows(rows: &[TableRow]) -> String {
+    let group = |row_type: Option<TableRowRowType>| {
+        rows.iter()
+            .filter(|row| row.row_type == row_type)
+            .map(|row| {
+                let cells = row
+                    .cells
+                    .iter()
+                    .map(|cell| {
+                        let name = match cell.cell_type {
+                            Some(TableCellCellType::Header) => "th",
+                            _ => "td",
+                        };
+                        let colspan = cell.colspan.map(|span| span.to_string());
+                        let rowspan = cell.rowspan.map(|span| span.to_string());
+                        let attrs: Vec<(&str, &str)> = [
+                            colspan.as_deref().map(|span| ("colspan", span)),
+                            rowspan.as_deref().map(|span| ("rowspan", span)),
+                        ]
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                        let content = match &cell.content {
+                            Some(TableCellContent::VecBlockContent(blocks)) => {
+                                encode_blocks(blocks)
+                            }
+                            Some(TableCellContent::VecInlineContent(inlines)) => {
+                                encode_inlines(inlines)
+                            }
+                            None => String::new(),
+                        };
+                        elem(name, &attrs, &content)
+                    })
+                    .collect::<Vec<String>>()
+                    .concat();
+                elem("tr", &[], &cells)
+            })
+            .collect::<Vec<String>>()
+            .concat()
+    };
+
+    [
+        ("thead", group(Some(TableRowRowType::Header))),
+        ("tbody", group(None)),
+        ("tfoot", group(Some(TableRowRowType::Footer))),
+    ]
+    .iter()
+    .filter(|(.., rows)| !rows.is_empty())
+    .map(|(name, rows)| elem(name, &[], rows))
+    .collect::<Vec<String>>()
+    .concat()
+}
+
+/// Encode math as either `<mml:math>` (if MathML) or `<tex-math>`
+fn encode_math(text: &str, math_language: Option<&String>) -> String {
+    match math_language.map(String::as_str) {
+        Some("mathml") => text.to_string(),
+        _ => elem("tex-math", &[], &escape(text)),
+    }
+}
+
+/// Encode inlines
+fn encode_inlines(inlines: &[InlineContent]) -> String {
+    inlines
+        .iter()
+        .map(encode_inline)
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+/// Encode an inline
+///
+/// Inline types that have no equivalent in JATS are ignored.
+fn encode_inline(inline: &InlineContent) -> String {
+    match inline {
+        InlineContent::String(string) => escape(string),
+        InlineContent::Integer(integer) => integer.to_string(),
+        InlineContent::Number(number) => number.to_string(),
+        InlineContent::Boolean(boolean) => boolean.to_string(),
+        InlineContent::Emphasis(node) => elem("italic", &[], &encode_inlines(&node.content)),
+        InlineContent::Strong(node) => elem("bold", &[], &encode_inlines(&node.content)),
+        InlineContent::Subscript(node) => elem("sub", &[], &encode_inlines(&node.content)),
+        InlineContent::Superscript(node) => elem("sup", &[], &encode_inlines(&node.content)),
+        InlineContent::Underline(node) => elem("underline", &[], &encode_inlines(&node.content)),
+        InlineContent::Strikeout(node) => elem("strike", &[], &encode_inlines(&node.content)),
+        InlineContent::Delete(node) => elem("strike", &[], &encode_inlines(&node.content)),
+        InlineContent::Quote(node) => encode_inlines(&node.content),
+        InlineContent::CodeFragment(node) => elem("monospace", &[], &escape(&node.text)),
+        InlineContent::Link(link) => match link.target.strip_prefix('#') {
+            Some(rid) => elem("xref", &[("rid", rid)], &encode_inlines(&link.content)),
+            None => elem(
+                "ext-link",
+                &[("ext-link-type", "uri"), ("xlink:href", &link.target)],
+                &encode_inlines(&link.content),
+            ),
+        },
+        InlineContent::Cite(cite) => {
+            let content = match &cite.content {
+                Some(content) => encode_inlines(content),
+                None => escape(&cite.target),
+            };
+            elem(
+                "xref",
+                &[("ref-type", "bibr"), ("rid", &cite.target)],
+                &content,
+            )
+        }
+        InlineContent::CiteGroup(group) => group
+            .items
+            .iter()
+            .map(|cite| encode_inline(&InlineContent::Cite(cite.clone())))
+            .collect::<Vec<String>>()
+            .join(", "),
+        InlineContent::MathFragment(math) => elem(
+            "inline-formula",
+            &[],
+            &encode_math(&math.text, math.math_language.as_deref()),
+        ),
+        InlineContent::ImageObject(image) => {
+            elem("inline-graphic", &[("xlink:href", &image.content_url)], "")
+        }
+        InlineContent::Note(note) => elem("fn", &[], &encode_blocks(&note.content)),
+        _ => String::new(),
+    }
+}
+
+/// Encode a CSL-JSON item as a `<ref>` element
+fn encode_ref(item: &serde_json::Value, index: usize) -> String {
+    let string = |key: &str| item.get(key).and_then(|value| value.as_str());
+
+    let id = string("id")
+        .map(String::from)
+        .unwrap_or_else(|| format!("bib{}", index + 1));
+
+    let csl_type = string("type").unwrap_or("article-journal");
+    let publication_type = PUBLICATION_TYPES
+        .iter()
+        .find(|(.., csl)| *csl == csl_type)
+        .map_or("journal", |(jats, ..)| *jats);
+
+    let mut citation = String::new();
+
+    for (key, type_) in [("author", "author"), ("editor", "editor")] {
+        if let Some(names) = item.get(key).and_then(|names| names.as_array()) {
+            let names = names
+                .iter()
+                .map(|name| {
+                    let part = |key: &str| name.get(key).and_then(|value| value.as_str());
+                    match part("literal") {
+                        Some(literal) => elem_text("collab", Some(literal)),
+                        None => elem(
+                            "name",
+                            &[],
+                            &[
+                                elem_text("surname", part("family")),
+                                elem_text("given-names", part("given")),
+                            ]
+                            .concat(),
+                        ),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .concat();
+            citation += &elem("person-group", &[("person-group-type", type_)], &names);
+        }
+    }
+
+    if let Some(parts) = item
+        .get("issued")
+        .and_then(|issued| issued.get("date-parts"))
+        .and_then(|parts| parts.get(0))
+        .and_then(|parts| parts.as_array())
+    {
+        let part = |index: usize| {
+            parts.get(index).and_then(|part| {
+                part.as_u64()
+                    .map(|part| part.to_string())
+                    .or_else(|| part.as_str().map(String::from))
+            })
+        };
+        citation += &elem_text("year", part(0).as_deref());
+        citation += &elem_text("month", part(1).as_deref());
+        citation += &elem_text("day", part(2).as_deref());
+    } else if let Some(raw) = item.get("issued").and_then(|issued| issued.get("raw")) {
+        citation += &elem_text("year", raw.as_str());
+    }
+
+    match string("container-title") {
+        Some(source) => {
+            let title_elem = match csl_type {
+                "chapter" => "chapter-title",
+                "dataset" => "data-title",
+                _ => "article-title",
+            };
+            citation += &elem_text(title_elem, string("title"));
+            citation += &elem_text("source", Some(source));
+        }
+        None => citation += &elem_text("source", string("title")),
+    }
+
+    citation += &elem_text("volume", string("volume"));
+    citation += &elem_text("issue", string("issue"));
+
+    if let Some(page) = string("page") {
+        if page.contains(',') {
+            citation += &elem_text("page-range", Some(page));
+        } else if let Some((fpage, lpage)) = page.split_once('-') {
+            citation += &elem_text("fpage", Some(fpage.trim()));
+            citation += &elem_text("lpage", Some(lpage.trim()));
+        } else {
+            citation += &elem_text("fpage", Some(page));
+        }
+    }
+
+    citation += &elem_text("publisher-name", string("publisher"));
+
+    if let Some(doi) = string("DOI") {
+        citation += &elem("pub-id", &[("pub-id-type", "doi")], &escape(doi));
+    }
+    if let Some(url) = string("URL") {
+        citation += &elem(
+            "ext-link",
+            &[("ext-link-type", "uri"), ("xlink:href", url)],
+            &escape(url),
+        );
+    }
+
+    elem(
+        "ref",
+        &[("id", &id)],
+        &elem(
+            "element-citation",
+            &[("publication-type", publication_type)],
+            &citation,
+        ),
+    )
+}
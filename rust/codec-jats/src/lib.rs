@@ -0,0 +1,307 @@
+use codec::{
+    common::eyre::Result, stencila_schema::Node, utils::vec_string, Codec, CodecTrait,
+    DecodeOptions, EncodeOptions,
+};
+
+mod decode;
+mod encode;
+
+/// The prefix used for DOI identifiers
+const DOI_URL: &str = "https://doi.org/";
+
+/// The prefix used for ORCID identifiers
+const ORCID_URL: &str = "https://orcid.org/";
+
+/// The mapping between JATS `publication-type`s and CSL item types
+///
+/// Used when decoding (first match on the JATS type) and when
+/// encoding (first match on the CSL type) references.
+const PUBLICATION_TYPES: &[(&str, &str)] = &[
+    ("journal", "article-journal"),
+    ("book", "book"),
+    ("chapter", "chapter"),
+    ("confproc", "paper-conference"),
+    ("data", "dataset"),
+    ("patent", "patent"),
+    ("preprint", "article"),
+    ("report", "report"),
+    ("software", "software"),
+    ("thesis", "thesis"),
+    ("web", "webpage"),
+    ("other", "document"),
+];
+
+/// A codec for JATS XML
+///
+/// Decodes a [JATS](https://jats.nlm.nih.gov/) XML document into an `Article`:
+///
+/// - `<front>` metadata to the `title`, `authors` (and their affiliations), `funders`,
+///   `description` (from the abstract), `keywords`, `identifiers`, and dates of the article
+/// - `<body>` content to blocks, with each `<sec>` becoming a `Heading` (with `depth` based
+///   on the nesting of sections) followed by its content
+/// - `<back><ref-list>` to `references`
+///
+/// Encodes an `Article` into JATS using the same mappings (headings are nested into `<sec>`
+/// elements based on their depth). References are decoded and encoded via CSL-JSON so that
+/// the mapping to and from Stencila nodes is shared with other bibliographic codecs.
+pub struct JatsCodec;
+
+impl CodecTrait for JatsCodec {
+    fn spec() -> Codec {
+        Codec {
+            status: "alpha".to_string(),
+            formats: vec_string!["jats"],
+            root_types: vec_string!["Article"],
+            ..Default::default()
+        }
+    }
+
+    fn from_str(str: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+        decode::decode(str)
+    }
+
+    fn to_string(node: &Node, _options: Option<EncodeOptions>) -> Result<String> {
+        encode::encode(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::common::serde_json;
+    use codec::stencila_schema::{
+        Article, BlockContent, CreativeWorkAuthors, CreativeWorkFunders, CreativeWorkReferences,
+        CreativeWorkTypes, InlineContent, ThingDescription,
+    };
+    use test_utils::{assert_json_is, pretty_assertions::assert_eq};
+
+    const JATS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE article PUBLIC "-//NLM//DTD JATS (Z39.96) Journal Archiving and Interchange DTD v1.2 20190208//EN" "JATS-archivearticle1.dtd">
+<article xmlns:mml="http://www.w3.org/1998/Math/MathML" xmlns:xlink="http://www.w3.org/1999/xlink" article-type="research-article">
+  <front>
+    <article-meta>
+      <article-id pub-id-type="doi">10.7554/eLife.00001</article-id>
+      <title-group>
+        <article-title>The <italic>title</italic> of the article</article-title>
+      </title-group>
+      <contrib-group>
+        <contrib contrib-type="author">
+          <contrib-id contrib-id-type="orcid">https://orcid.org/0000-0002-1825-0097</contrib-id>
+          <name><surname>Carberry</surname><given-names>Josiah S</given-names></name>
+          <email>josiah@example.org</email>
+          <xref ref-type="aff" rid="aff1">1</xref>
+        </contrib>
+        <contrib contrib-type="author">
+          <collab>The Consortium</collab>
+        </contrib>
+        <aff id="aff1"><label>1</label><institution>Brown University</institution>, <country>USA</country></aff>
+      </contrib-group>
+      <pub-date date-type="pub"><day>4</day><month>3</month><year>2021</year></pub-date>
+      <history>
+        <date date-type="received"><day>01</day><month>10</month><year>2020</year></date>
+        <date date-type="accepted"><day>15</day><month>02</month><year>2021</year></date>
+      </history>
+      <abstract><p>An   abstract.</p></abstract>
+      <kwd-group><kwd>one</kwd><kwd>two</kwd></kwd-group>
+      <funding-group>
+        <award-group><funding-source><institution>Wellcome Trust</institution></funding-source></award-group>
+      </funding-group>
+    </article-meta>
+  </front>
+  <body>
+    <sec id="s1">
+      <title>Introduction</title>
+      <p>Some <bold>bold</bold> text citing <xref ref-type="bibr" rid="bib1">Smith, 2020</xref>.</p>
+      <sec>
+        <title>Background</title>
+        <p>A paragraph with a figure<fig id="fig1"><label>Figure 1</label><caption><p>A caption.</p></caption><graphic xlink:href="fig1.png"/></fig></p>
+      </sec>
+    </sec>
+    <sec>
+      <title>Methods</title>
+      <disp-formula id="eq1"><tex-math>E = mc^2</tex-math></disp-formula>
+      <table-wrap id="tab1">
+        <label>Table 1</label>
+        <table>
+          <thead><tr><th>A</th><th>B</th></tr></thead>
+          <tbody><tr><td>1</td><td colspan="1">2</td></tr></tbody>
+        </table>
+      </table-wrap>
+    </sec>
+  </body>
+  <back>
+    <ref-list>
+      <title>References</title>
+      <ref id="bib1">
+        <element-citation publication-type="journal">
+          <person-group person-group-type="author">
+            <name><surname>Smith</surname><given-names>Jane</given-names></name>
+          </person-group>
+          <year>2020</year>
+          <article-title>A cited article</article-title>
+          <source>Journal of Things</source>
+          <volume>12</volume>
+          <fpage>65</fpage>
+          <lpage>70</lpage>
+          <pub-id pub-id-type="doi">10.1000/xyz123</pub-id>
+        </element-citation>
+      </ref>
+    </ref-list>
+  </back>
+</article>
+"#;
+
+    fn article(node: &Node) -> &Article {
+        match node {
+            Node::Article(article) => article,
+            _ => panic!("Expected an article"),
+        }
+    }
+
+    #[test]
+    fn decode_front() -> Result<()> {
+        let node = JatsCodec::from_str(JATS, None)?;
+        let article = article(&node);
+
+        assert_json_is!(
+            article.title,
+            [ "The ", { "type": "Emphasis", "content": ["title"] }, " of the article" ]
+        );
+
+        let authors = article.authors.as_ref().unwrap();
+        assert_eq!(authors.len(), 2);
+        match &authors[0] {
+            CreativeWorkAuthors::Person(person) => {
+                assert_json_is!(person.family_names, ["Carberry"]);
+                assert_json_is!(person.given_names, ["Josiah", "S"]);
+                assert_json_is!(person.emails, ["josiah@example.org"]);
+                assert_json_is!(
+                    person.identifiers,
+                    ["https://orcid.org/0000-0002-1825-0097"]
+                );
+                assert_json_is!(
+                    person.affiliations,
+                    [{ "type": "Organization", "name": "Brown University", "address": "USA" }]
+                );
+            }
+            _ => panic!("Expected a person"),
+        }
+        assert!(
+            matches!(&authors[1], CreativeWorkAuthors::Organization(org) if org.name.as_deref().map(String::as_str) == Some("The Consortium"))
+        );
+
+        assert!(matches!(
+            article.description.as_deref(),
+            Some(ThingDescription::VecBlockContent(blocks)) if blocks.len() == 1
+        ));
+        assert_json_is!(article.keywords, ["one", "two"]);
+        assert_json_is!(article.identifiers, ["https://doi.org/10.7554/eLife.00001"]);
+        assert_eq!(article.date_published.as_ref().unwrap().value, "2021-03-04");
+        assert_eq!(article.date_received.as_ref().unwrap().value, "2020-10-01");
+        assert_eq!(article.date_accepted.as_ref().unwrap().value, "2021-02-15");
+        assert!(
+            matches!(&article.funders.as_ref().unwrap()[0], CreativeWorkFunders::Organization(org) if org.name.as_deref().map(String::as_str) == Some("Wellcome Trust"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_body_back() -> Result<()> {
+        let node = JatsCodec::from_str(JATS, None)?;
+        let article = article(&node);
+
+        let content = article.content.as_ref().unwrap();
+        let types: Vec<&str> = content
+            .iter()
+            .map(|block| match block {
+                BlockContent::Heading(..) => "Heading",
+                BlockContent::Paragraph(..) => "Paragraph",
+                BlockContent::Figure(..) => "Figure",
+                BlockContent::MathBlock(..) => "MathBlock",
+                BlockContent::Table(..) => "Table",
+                _ => "Other",
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                "Heading",
+                "Paragraph",
+                "Heading",
+                "Paragraph",
+                "Figure",
+                "Heading",
+                "MathBlock",
+                "Table"
+            ]
+        );
+
+        match (&content[0], &content[2]) {
+            (BlockContent::Heading(h1), BlockContent::Heading(h2)) => {
+                assert_eq!(h1.depth, Some(1));
+                assert_eq!(h2.depth, Some(2));
+            }
+            _ => unreachable!(),
+        }
+        match &content[1] {
+            BlockContent::Paragraph(para) => assert!(para.content.iter().any(
+                |inline| matches!(inline, InlineContent::Cite(cite) if cite.target == "bib1")
+            )),
+            _ => unreachable!(),
+        }
+        match &content[4] {
+            BlockContent::Figure(figure) => {
+                assert_json_is!(figure.label, "Figure 1");
+                assert_json_is!(figure.content, [{ "type": "ImageObject", "contentUrl": "fig1.png" }]);
+            }
+            _ => unreachable!(),
+        }
+        match &content[6] {
+            BlockContent::MathBlock(math) => {
+                assert_eq!(math.text, "E = mc^2");
+                assert_json_is!(math.math_language, "tex");
+            }
+            _ => unreachable!(),
+        }
+        match &content[7] {
+            BlockContent::Table(table) => {
+                assert_eq!(table.rows.len(), 2);
+                assert_json_is!(table.rows[0].row_type, "Header");
+            }
+            _ => unreachable!(),
+        }
+
+        let references = article.references.as_ref().unwrap();
+        match &references[0] {
+            CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(reference)) => {
+                assert_json_is!(reference.id, "bib1");
+                assert_json_is!(reference.title, "A cited article");
+                assert_json_is!(reference.identifiers, ["https://doi.org/10.1000/xyz123"]);
+            }
+            _ => panic!("Expected an article reference"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let node = JatsCodec::from_str(JATS, None)?;
+        let jats = JatsCodec::to_string(&node, None)?;
+
+        assert!(jats.starts_with("<?xml"));
+        assert!(jats.contains(r#"<sec id="s1"><title>Introduction</title>"#));
+        assert!(jats.contains(r#"<xref ref-type="bibr" rid="bib1">Smith, 2020</xref>"#));
+        assert!(jats.contains(r#"<aff id="aff1"><institution>Brown University</institution>"#));
+        assert!(jats.contains(r#"<element-citation publication-type="journal">"#));
+
+        let round_tripped = JatsCodec::from_str(&jats, None)?;
+        assert_eq!(
+            serde_json::to_value(&round_tripped)?,
+            serde_json::to_value(&node)?
+        );
+
+        Ok(())
+    }
+}
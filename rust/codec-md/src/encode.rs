@@ -1,10 +1,11 @@
 use std::cmp::max;
 
 use codec::{
-    common::{eyre::Result, itertools::Itertools, serde_json},
+    common::{eyre::Result, itertools::Itertools, serde_json, serde_yaml},
     stencila_schema::*,
     EncodeOptions,
 };
+use codec_txt::ToTxt;
 
 use crate::utils::escape;
 
@@ -14,6 +15,32 @@ pub fn encode(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
     Ok(node.to_md(&options).trim().to_string())
 }
 
+/// Encode the properties of an `Article`, other than `content`, as YAML front matter
+///
+/// The reverse of `decode_frontmatter`. A `title` with inline content is encoded
+/// as plain text. Returns an empty string if there are no properties to encode.
+pub fn encode_frontmatter(article: &Article) -> Result<String> {
+    let mut value = serde_json::to_value(article)?;
+    let object = value.as_object_mut().expect("Should be an object");
+    object.remove("type");
+    object.remove("content");
+
+    if let Some(CreativeWorkTitle::VecInlineContent(inlines)) = article.title.as_deref() {
+        object.insert(
+            "title".to_string(),
+            serde_json::Value::String(inlines.to_txt()),
+        );
+    }
+
+    if object.is_empty() {
+        return Ok(String::new());
+    }
+
+    let yaml = serde_yaml::to_string(&value)?;
+    let yaml = yaml.trim_start_matches("---\n");
+    Ok(["---\n", yaml, "---\n\n"].concat())
+}
+
 /// A trait to encode a `Node` as Markdown
 pub trait ToMd {
     fn to_md(&self, options: &EncodeOptions) -> String;
@@ -222,11 +249,71 @@ impl ToMd for Link {
     }
 }
 
+impl ToMd for Cite {
+    /// Encode a `Cite` to Markdown
+    ///
+    /// Uses Pandoc's citation syntax (e.g. `[@smith2020]`) so that the citation can be
+    /// decoded again. If encoding with loss of information, the rendered `content` of the
+    /// citation (if any) is used instead.
+    fn to_md(&self, options: &EncodeOptions) -> String {
+        if let (true, Some(content)) = (options.lossy, &self.content) {
+            return content.to_md(options);
+        }
+
+        match self.citation_mode {
+            Some(CiteCitationMode::Narrative) => ["@", &self.target].concat(),
+            _ => ["[@", &self.target, "]"].concat(),
+        }
+    }
+}
+
+impl ToMd for CiteGroup {
+    /// Encode a `CiteGroup` to Markdown
+    ///
+    /// As for `Cite`, uses Pandoc's citation syntax (e.g. `[@smith2020; @jones2021]`) unless
+    /// encoding with loss of information.
+    fn to_md(&self, options: &EncodeOptions) -> String {
+        if options.lossy && self.items.iter().all(|cite| cite.content.is_some()) {
+            return self
+                .items
+                .iter()
+                .map(|cite| cite.content.to_md(options))
+                .collect();
+        }
+
+        [
+            "[",
+            &self
+                .items
+                .iter()
+                .map(|cite| ["@", &cite.target].concat())
+                .join("; "),
+            "]",
+        ]
+        .concat()
+    }
+}
+
 macro_rules! inline_media_object_to_md {
     ($type:ty) => {
         impl ToMd for $type {
+            /// Encode a media object to Markdown
+            ///
+            /// The `caption` is used as the alt text and the `title` (if any) is
+            /// added after the URL (e.g. `![Caption](image.png "Title")`).
             fn to_md(&self, _options: &EncodeOptions) -> String {
-                ["![", "](", &self.content_url, ")"].concat()
+                let alt = self.caption.as_deref().map_or("", |caption| caption.as_str());
+                let title = match self.title.as_deref() {
+                    Some(CreativeWorkTitle::String(title)) => title.to_string(),
+                    Some(CreativeWorkTitle::VecInlineContent(inlines)) => inlines.to_txt(),
+                    None => String::new(),
+                };
+                let title = if title.is_empty() {
+                    String::new()
+                } else {
+                    [" \"", &title.replace('"', "\\\""), "\""].concat()
+                };
+                ["![", &escape(alt), "](", &self.content_url, &title, ")"].concat()
             }
         }
     };
@@ -550,9 +637,41 @@ macro_rules! content_to_md {
     };
 }
 
-content_to_md!(Article);
 content_to_md!(CreativeWork);
 
+impl ToMd for Article {
+    /// Encode an `Article` to Markdown
+    ///
+    /// If encoding with loss of information, the list of references, as rendered when the
+    /// document was assembled, is added after the content. Otherwise, only the content
+    /// is encoded (the references are usually defined in a separate bibliography file).
+    fn to_md(&self, options: &EncodeOptions) -> String {
+        let content = self.content.to_md(options);
+
+        let references = match (options.lossy, &self.references) {
+            (true, Some(references)) if !references.is_empty() => references,
+            _ => return content,
+        };
+
+        let entries = references
+            .iter()
+            .filter_map(|reference| match reference {
+                CreativeWorkReferences::String(string) => Some(string),
+                CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(article)) => {
+                    article.text.as_deref()
+                }
+                CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                    work,
+                )) => work.text.as_deref(),
+                _ => None,
+            })
+            .map(|entry| [entry, "\n\n"].concat())
+            .collect::<String>();
+
+        [content, "# References\n\n".to_string(), entries].concat()
+    }
+}
+
 impl ToMd for CreativeWorkContent {
     fn to_md(&self, options: &EncodeOptions) -> String {
         match self {
@@ -600,6 +719,8 @@ impl ToMd for InlineContent {
         match self {
             InlineContent::AudioObject(node) => node.to_md(options),
             InlineContent::Boolean(node) => node.to_md(options),
+            InlineContent::Cite(node) => node.to_md(options),
+            InlineContent::CiteGroup(node) => node.to_md(options),
             InlineContent::CodeExpression(node) => node.to_md(options),
             InlineContent::CodeFragment(node) => node.to_md(options),
             InlineContent::Emphasis(node) => node.to_md(options),
@@ -702,4 +823,41 @@ mod tests {
         .unwrap();
         assert_eq!(md, "> Hello world.")
     }
+
+    /// Test that citations use Pandoc syntax unless lossy, in which case their rendered content is used
+    #[test]
+    fn encode_cite() {
+        let cite = |target: &str, content: &str| Cite {
+            target: target.to_string(),
+            content: Some(vec![InlineContent::String(content.to_string())]),
+            ..Default::default()
+        };
+        let node = Node::Paragraph(Paragraph {
+            content: vec![
+                InlineContent::Cite(cite("smith2020", "(Smith, 2020)")),
+                InlineContent::String(" and ".to_string()),
+                InlineContent::CiteGroup(CiteGroup {
+                    items: vec![
+                        cite("smith2020", "(Smith, 2020"),
+                        cite("lee2019", "; Lee, 2019)"),
+                    ],
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        });
+
+        let md = encode(&node, None).unwrap();
+        assert_eq!(md, "[@smith2020] and [@smith2020; @lee2019]");
+
+        let md = encode(
+            &node,
+            Some(EncodeOptions {
+                lossy: true,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(md, "(Smith, 2020) and (Smith, 2020; Lee, 2019)");
+    }
 }
@@ -9,13 +9,13 @@ mod utils;
 mod decode;
 
 #[cfg(feature = "decode")]
-pub use decode::decode_fragment;
+pub use decode::{decode_fragment, decode_frontmatter};
 
 #[cfg(feature = "encode")]
 mod encode;
 
 #[cfg(feature = "encode")]
-pub use encode::ToMd;
+pub use encode::{encode_frontmatter, ToMd};
 
 /// A codec for Markdown
 pub struct MdCodec {}
@@ -0,0 +1,256 @@
+use codec::{
+    common::{
+        eyre::{bail, Result},
+        once_cell::sync::Lazy,
+        regex::Regex,
+        serde_yaml, tracing,
+    },
+    stencila_schema::*,
+    DecodeOptions,
+};
+use codec_md::{decode_fragment, decode_frontmatter};
+
+use crate::ADMONITIONS;
+
+/// Decode a MyST Markdown document to a `Node`
+pub fn decode(input: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+    let (end, node) = decode_frontmatter(input)?;
+
+    let md = match end {
+        Some(end) => &input[end..],
+        None => input,
+    };
+
+    let mut node = match node {
+        Some(node) => node,
+        None => Node::Article(Article::default()),
+    };
+
+    let content = decode_blocks(md);
+    if !content.is_empty() {
+        let content = Some(content);
+        match &mut node {
+            Node::Article(article) => article.content = content,
+            _ => bail!("Unsupported node type {:?}", node),
+        }
+    }
+
+    Ok(node)
+}
+
+/// Decode MyST Markdown into a vector of `BlockContent`
+fn decode_blocks(md: &str) -> Vec<BlockContent> {
+    let mut blocks = decode_fragment(md, None);
+    transform_blocks(&mut blocks);
+    blocks
+}
+
+fn transform_blocks(blocks: &mut Vec<BlockContent>) {
+    static DIRECTIVE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\{([\w:-]+)\}\s*(.*?)\s*$").expect("Unable to create regex"));
+
+    for block in blocks {
+        match block {
+            // Code blocks with a curly braced directive name are transformed based on the directive
+            BlockContent::CodeBlock(CodeBlock {
+                programming_language: Some(lang),
+                text,
+                ..
+            }) => {
+                let transformed = DIRECTIVE
+                    .captures(lang)
+                    .and_then(|captures| decode_directive(&captures[1], &captures[2], text));
+                if let Some(transformed) = transformed {
+                    *block = transformed
+                }
+            }
+            // Transform the inline content of other block types
+            BlockContent::Paragraph(Paragraph { content, .. }) => transform_inlines(content),
+            _ => (),
+        }
+    }
+}
+
+/// Decode a MyST directive
+///
+/// Returns `None` if the directive is not handled, in which case it is left as a `CodeBlock`.
+fn decode_directive(name: &str, arg: &str, body: &str) -> Option<BlockContent> {
+    let (options, content) = directive_options(body);
+
+    let block = match name {
+        "code-cell" => BlockContent::CodeChunk(CodeChunk {
+            programming_language: arg.to_string(),
+            text: content.to_string(),
+            id: find_option(&options, &["name", "label"]).map(Box::new),
+            caption: find_option(&options, &["caption"])
+                .map(|caption| Box::new(CodeChunkCaption::String(caption))),
+            ..Default::default()
+        }),
+        "math" => BlockContent::MathBlock(MathBlock {
+            text: content.to_string(),
+            math_language: Some(Box::new("tex".to_string())),
+            id: find_option(&options, &["label", "name"]).map(Box::new),
+            ..Default::default()
+        }),
+        "figure" => {
+            let caption = decode_blocks(content);
+            BlockContent::Figure(FigureSimple {
+                id: find_option(&options, &["name", "label"]).map(Box::new),
+                content: Some(Box::new(CreativeWorkContent::VecNode(vec![
+                    Node::ImageObject(ImageObject {
+                        content_url: arg.to_string(),
+                        ..Default::default()
+                    }),
+                ]))),
+                caption: (!caption.is_empty())
+                    .then_some(Box::new(FigureCaption::VecBlockContent(caption))),
+                ..Default::default()
+            })
+        }
+        _ if ADMONITIONS.contains(&name) => BlockContent::Claim(ClaimSimple {
+            id: find_option(&options, &["name", "label"]).map(Box::new),
+            label: Some(Box::new(name.to_string())),
+            title: (!arg.is_empty())
+                .then_some(Box::new(CreativeWorkTitle::String(arg.to_string()))),
+            content: decode_blocks(content),
+            ..Default::default()
+        }),
+        _ => return None,
+    };
+
+    Some(block)
+}
+
+/// Find the value of the first of the named options of a directive
+fn find_option(options: &[(String, String)], names: &[&str]) -> Option<String> {
+    options
+        .iter()
+        .find(|(name, ..)| names.contains(&name.as_str()))
+        .map(|(.., value)| value.clone())
+}
+
+/// Split the options (either `:name: value` lines or a YAML block) from the start of
+/// the body of a directive
+fn directive_options(body: &str) -> (Vec<(String, String)>, &str) {
+    static OPTION: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^:([\w-]+):\s*(.*?)\s*$").expect("Unable to create regex"));
+
+    let mut options = Vec::new();
+    let mut rest = body;
+
+    if let Some(yaml) = body.strip_prefix("---\n") {
+        if let Some((yaml, content)) = yaml.split_once("\n---") {
+            match serde_yaml::from_str::<serde_yaml::Mapping>(yaml) {
+                Ok(mapping) => {
+                    for (name, value) in mapping {
+                        if let Some(name) = name.as_str() {
+                            let value = match value {
+                                serde_yaml::Value::String(string) => string,
+                                value => serde_yaml::to_string(&value)
+                                    .unwrap_or_default()
+                                    .trim_start_matches("---")
+                                    .trim()
+                                    .to_string(),
+                            };
+                            options.push((name.to_string(), value))
+                        }
+                    }
+                }
+                Err(error) => tracing::warn!("While parsing MyST directive options: {}", error),
+            }
+            rest = content.strip_prefix('\n').unwrap_or(content);
+        }
+    } else {
+        while let Some(captures) = rest.lines().next().and_then(|line| OPTION.captures(line)) {
+            options.push((captures[1].to_string(), captures[2].to_string()));
+            rest = rest.split_once('\n').map_or("", |(.., rest)| rest);
+        }
+    }
+
+    if !options.is_empty() {
+        rest = rest.strip_prefix('\n').unwrap_or(rest);
+    }
+
+    (options, rest)
+}
+
+fn transform_inlines(inlines: &mut Vec<InlineContent>) {
+    static ROLE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\{([\w:-]+)\}$").expect("Unable to create regex"));
+
+    let mut transformed: Vec<InlineContent> = Vec::with_capacity(inlines.len());
+    for mut inline in inlines.drain(..) {
+        match &mut inline {
+            // Code fragments preceded by a curly braced role name are transformed based on the role
+            InlineContent::CodeFragment(CodeFragment { text, .. }) => {
+                if let Some(InlineContent::String(string)) = transformed.last_mut() {
+                    let role = ROLE
+                        .captures(string)
+                        .map(|captures| (captures[0].len(), captures[1].to_string()));
+                    if let Some((length, role)) = role {
+                        if let Some(node) = decode_role(&role, text) {
+                            string.truncate(string.len() - length);
+                            if string.is_empty() {
+                                transformed.pop();
+                            }
+                            inline = node;
+                        }
+                    }
+                }
+            }
+            // Recursively transform other inlines
+            InlineContent::Strikeout(Strikeout { content, .. })
+            | InlineContent::Emphasis(Emphasis { content, .. })
+            | InlineContent::Subscript(Subscript { content, .. })
+            | InlineContent::Superscript(Superscript { content, .. })
+            | InlineContent::Strong(Strong { content, .. })
+            | InlineContent::Underline(Underline { content, .. }) => transform_inlines(content),
+            _ => (),
+        }
+        transformed.push(inline);
+    }
+    *inlines = transformed;
+}
+
+/// Decode a MyST role
+///
+/// Returns `None` if the role is not handled, in which case the role name is left as
+/// text preceding a `CodeFragment`.
+fn decode_role(role: &str, text: &str) -> Option<InlineContent> {
+    let cite = |target: &str, citation_mode: Option<CiteCitationMode>| Cite {
+        target: target.trim().to_string(),
+        citation_mode,
+        ..Default::default()
+    };
+
+    let node = match role {
+        "math" => InlineContent::MathFragment(MathFragment {
+            text: text.to_string(),
+            math_language: Some(Box::new("tex".to_string())),
+            ..Default::default()
+        }),
+        "cite" | "cite:p" | "cite:t" => {
+            let mode = if role == "cite:t" {
+                CiteCitationMode::Narrative
+            } else {
+                CiteCitationMode::Parenthetical
+            };
+            let mut items: Vec<Cite> = text
+                .split(',')
+                .map(|target| cite(target, Some(mode.clone())))
+                .collect();
+            if items.len() == 1 {
+                InlineContent::Cite(items.remove(0))
+            } else {
+                InlineContent::CiteGroup(CiteGroup {
+                    items,
+                    ..Default::default()
+                })
+            }
+        }
+        "ref" | "numref" | "eq" => InlineContent::Cite(cite(text, None)),
+        _ => return None,
+    };
+
+    Some(node)
+}
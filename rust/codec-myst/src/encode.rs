@@ -0,0 +1,206 @@
+use codec::{common::eyre::Result, stencila_schema::*, CodecTrait, EncodeOptions};
+use codec_md::{encode_frontmatter, MdCodec, ToMd};
+use codec_txt::ToTxt;
+
+use crate::ADMONITIONS;
+
+/// Encode a `Node` to MyST Markdown
+pub fn encode(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
+    let article = match node {
+        Node::Article(article) => article,
+        _ => return MdCodec::to_string(node, options),
+    };
+    let options = options.unwrap_or_default();
+
+    let frontmatter = encode_frontmatter(article)?;
+    let content = match &article.content {
+        Some(blocks) => encode_blocks(blocks, &options),
+        None => String::new(),
+    };
+
+    Ok([frontmatter, content].concat().trim().to_string())
+}
+
+fn encode_blocks(blocks: &[BlockContent], options: &EncodeOptions) -> String {
+    blocks
+        .iter()
+        .map(|block| encode_block(block, options))
+        .collect()
+}
+
+fn encode_block(block: &BlockContent, options: &EncodeOptions) -> String {
+    match block {
+        BlockContent::CodeChunk(chunk) => {
+            let caption = chunk.caption.as_deref().map(|caption| match caption {
+                CodeChunkCaption::String(string) => string.clone(),
+                CodeChunkCaption::VecBlockContent(blocks) => blocks.to_txt().trim().to_string(),
+            });
+            encode_directive(
+                "code-cell",
+                &chunk.programming_language,
+                &[("name", chunk.id.as_deref().cloned()), ("caption", caption)],
+                &chunk.text,
+            )
+        }
+        BlockContent::MathBlock(math) => encode_directive(
+            "math",
+            "",
+            &[("label", math.id.as_deref().cloned())],
+            &math.text,
+        ),
+        BlockContent::Figure(figure) => match encode_figure(figure, options) {
+            Some(md) => md,
+            None => block.to_md(options),
+        },
+        BlockContent::Claim(claim) => match claim.label.as_deref() {
+            Some(label) if ADMONITIONS.contains(&label.as_str()) => {
+                let title = claim.title.as_deref().map(|title| match title {
+                    CreativeWorkTitle::String(string) => string.clone(),
+                    CreativeWorkTitle::VecInlineContent(inlines) => inlines.to_txt(),
+                });
+                encode_directive(
+                    label,
+                    &title.unwrap_or_default(),
+                    &[("name", claim.id.as_deref().cloned())],
+                    encode_blocks(&claim.content, options).trim_end(),
+                )
+            }
+            _ => block.to_md(options),
+        },
+        BlockContent::Paragraph(paragraph) => {
+            let mut paragraph = paragraph.clone();
+            transform_inlines(&mut paragraph.content);
+            paragraph.to_md(options)
+        }
+        _ => block.to_md(options),
+    }
+}
+
+/// Encode a MyST directive
+///
+/// Options that are `None` are ignored. The fence is made longer than any fence
+/// within the body so that directives can be nested.
+fn encode_directive(
+    name: &str,
+    arg: &str,
+    options: &[(&str, Option<String>)],
+    body: &str,
+) -> String {
+    let longest = body
+        .lines()
+        .map(|line| {
+            line.trim_start()
+                .chars()
+                .take_while(|chr| *chr == '`')
+                .count()
+        })
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(std::cmp::max(3, longest + 1));
+
+    let options = options
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_ref()
+                .map(|value| [":", name, ": ", value, "\n"].concat())
+        })
+        .collect::<String>();
+
+    let arg = if arg.is_empty() {
+        String::new()
+    } else {
+        [" ", arg].concat()
+    };
+
+    [
+        fence.as_str(),
+        "{",
+        name,
+        "}",
+        &arg,
+        "\n",
+        &options,
+        body,
+        "\n",
+        &fence,
+        "\n\n",
+    ]
+    .concat()
+}
+
+/// Encode a `Figure` with a single image as a MyST `{figure}` directive
+///
+/// Returns `None` if the figure has other content.
+fn encode_figure(figure: &FigureSimple, options: &EncodeOptions) -> Option<String> {
+    let image = match figure.content.as_deref() {
+        Some(CreativeWorkContent::VecNode(nodes)) => match nodes.as_slice() {
+            [Node::ImageObject(image)] => image,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let caption = match figure.caption.as_deref() {
+        Some(FigureCaption::String(string)) => string.clone(),
+        Some(FigureCaption::VecBlockContent(blocks)) => encode_blocks(blocks, options),
+        None => String::new(),
+    };
+    let caption = caption.trim_end();
+    let body = if caption.is_empty() {
+        String::new()
+    } else {
+        ["\n", caption].concat()
+    };
+
+    Some(encode_directive(
+        "figure",
+        &image.content_url,
+        &[("name", figure.id.as_deref().cloned())],
+        &body,
+    ))
+}
+
+fn transform_inlines(inlines: &mut Vec<InlineContent>) {
+    for inline in inlines {
+        match inline {
+            // Math fragments, citations and cross-references are transformed to roles
+            InlineContent::MathFragment(MathFragment { text, .. }) => {
+                *inline = encode_role("math", text)
+            }
+            InlineContent::Cite(cite) => {
+                let role = match cite.citation_mode {
+                    Some(CiteCitationMode::Narrative) => "cite:t",
+                    Some(CiteCitationMode::Parenthetical) => "cite:p",
+                    _ => "numref",
+                };
+                *inline = encode_role(role, &cite.target)
+            }
+            InlineContent::CiteGroup(group) => {
+                let targets = group
+                    .items
+                    .iter()
+                    .map(|cite| cite.target.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(",");
+                *inline = encode_role("cite:p", &targets)
+            }
+            // Recursively transform other inlines
+            InlineContent::Strikeout(Strikeout { content, .. })
+            | InlineContent::Emphasis(Emphasis { content, .. })
+            | InlineContent::Subscript(Subscript { content, .. })
+            | InlineContent::Superscript(Superscript { content, .. })
+            | InlineContent::Strong(Strong { content, .. })
+            | InlineContent::Underline(Underline { content, .. }) => transform_inlines(content),
+            _ => (),
+        }
+    }
+}
+
+/// Encode a MyST role
+///
+/// A `String` is used (rather than a `CodeFragment` preceded by a `String`) because the
+/// Markdown encoder does not escape strings.
+fn encode_role(role: &str, text: &str) -> InlineContent {
+    InlineContent::String(["{", role, "}`", text, "`"].concat())
+}
@@ -0,0 +1,220 @@
+use codec::{
+    common::eyre::Result, stencila_schema::Node, utils::vec_string, Codec, CodecTrait,
+    DecodeOptions, EncodeOptions,
+};
+use codec_md::MdCodec;
+
+#[cfg(feature = "decode")]
+mod decode;
+
+#[cfg(feature = "encode")]
+mod encode;
+
+/// The names of MyST admonition directives
+///
+/// Admonitions are decoded to a `Claim` with the name of the directive as its `label`.
+#[cfg(any(feature = "decode", feature = "encode"))]
+const ADMONITIONS: &[&str] = &[
+    "admonition",
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "seealso",
+    "tip",
+    "warning",
+];
+
+/// A codec for MyST Markdown
+///
+/// Builds on the Markdown codec, handling the MyST specific syntax for:
+///
+/// - directives (e.g. ` ```{code-cell} python `) and their `:option: value` lines:
+///   `{code-cell}` is decoded to a `CodeChunk`, `{math}` to a `MathBlock`, `{figure}` to a
+///   `Figure` and admonitions (e.g. `{note}`) to a `Claim`
+/// - roles (e.g. `` {math}`x^2` ``): `{math}` is decoded to a `MathFragment`, `{cite}`
+///   (and its `:p` and `:t` variants) to a `Cite` or `CiteGroup`, and `{ref}`, `{numref}`
+///   and `{eq}` to a `Cite` with no citation mode
+///
+/// The `name` option of directives (`label` for `{math}`) is mapped to the `id` of the node.
+/// YAML front matter is decoded to, and encoded from, the properties of the `Article`.
+pub struct MystCodec {}
+
+impl CodecTrait for MystCodec {
+    fn spec() -> Codec {
+        let md_codec = MdCodec::spec();
+        Codec {
+            status: "alpha".to_string(),
+            formats: vec_string!["myst"],
+            root_types: vec_string!["Article"],
+            from_string: cfg!(feature = "decode"),
+            from_path: cfg!(feature = "decode"),
+            to_string: cfg!(feature = "encode"),
+            to_path: cfg!(feature = "encode"),
+            unsupported_types: md_codec.unsupported_types,
+            unsupported_properties: md_codec.unsupported_properties,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "decode")]
+    fn from_str(str: &str, options: Option<DecodeOptions>) -> Result<Node> {
+        decode::decode(str, options)
+    }
+
+    #[cfg(feature = "encode")]
+    fn to_string(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
+        encode::encode(node, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::common::serde_json;
+    use codec::stencila_schema::{Article, BlockContent};
+    use test_utils::{assert_json_is, pretty_assertions::assert_eq};
+
+    const MYST: &str = r#"---
+title: Elephants
+---
+
+# Introduction
+
+Elephants weigh about {math}`10^4` kg {cite:p}`smith2020,jones2021` as shown in {numref}`fig-elephant`.
+
+```{code-cell} python
+:name: sizes
+sizes = [1, 2]
+```
+
+```{math}
+:label: eq-mass
+m = \rho V
+```
+
+```{figure} elephant.png
+:name: fig-elephant
+
+An _elephant_.
+```
+
+````{note} Heads up
+Elephants are big.
+
+```python
+print("big")
+```
+````
+"#;
+
+    fn article(node: &Node) -> &Article {
+        match node {
+            Node::Article(article) => article,
+            _ => panic!("Expected an article"),
+        }
+    }
+
+    #[test]
+    fn decode() -> Result<()> {
+        let node = MystCodec::from_str(MYST, None)?;
+        let article = article(&node);
+        assert_json_is!(article.title, "Elephants");
+
+        let content = article.content.as_ref().unwrap();
+        assert_eq!(content.len(), 6);
+
+        match &content[1] {
+            BlockContent::Paragraph(para) => {
+                assert_json_is!(para.content, [
+                    "Elephants weigh about ",
+                    { "type": "MathFragment", "text": "10^4", "mathLanguage": "tex" },
+                    " kg ",
+                    {
+                        "type": "CiteGroup",
+                        "items": [
+                            { "type": "Cite", "target": "smith2020", "citationMode": "Parenthetical" },
+                            { "type": "Cite", "target": "jones2021", "citationMode": "Parenthetical" }
+                        ]
+                    },
+                    " as shown in ",
+                    { "type": "Cite", "target": "fig-elephant" },
+                    "."
+                ]);
+            }
+            _ => panic!("Expected a paragraph"),
+        }
+
+        match &content[2] {
+            BlockContent::CodeChunk(chunk) => {
+                assert_eq!(chunk.programming_language, "python");
+                assert_eq!(chunk.text, "sizes = [1, 2]");
+                assert_json_is!(chunk.id, "sizes");
+            }
+            _ => panic!("Expected a code chunk"),
+        }
+
+        match &content[3] {
+            BlockContent::MathBlock(math) => {
+                assert_eq!(math.text, "m = \\rho V");
+                assert_json_is!(math.id, "eq-mass");
+                assert_json_is!(math.math_language, "tex");
+            }
+            _ => panic!("Expected a math block"),
+        }
+
+        match &content[4] {
+            BlockContent::Figure(figure) => {
+                assert_json_is!(figure.id, "fig-elephant");
+                assert_json_is!(figure.content, [{ "type": "ImageObject", "contentUrl": "elephant.png" }]);
+                assert_json_is!(figure.caption, [{
+                    "type": "Paragraph",
+                    "content": ["An ", { "type": "Emphasis", "content": ["elephant"] }, "."]
+                }]);
+            }
+            _ => panic!("Expected a figure"),
+        }
+
+        match &content[5] {
+            BlockContent::Claim(claim) => {
+                assert_json_is!(claim.label, "note");
+                assert_json_is!(claim.title, "Heads up");
+                assert_json_is!(claim.content, [
+                    { "type": "Paragraph", "content": ["Elephants are big."] },
+                    { "type": "CodeBlock", "programmingLanguage": "python", "text": "print(\"big\")" }
+                ]);
+            }
+            _ => panic!("Expected a claim"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let node = MystCodec::from_str(MYST, None)?;
+        let myst = MystCodec::to_string(&node, None)?;
+
+        assert!(myst.starts_with("---\ntitle: Elephants\n---\n"));
+        assert!(myst.contains(
+            "{math}`10^4` kg {cite:p}`smith2020,jones2021` as shown in {numref}`fig-elephant`."
+        ));
+        assert!(myst.contains("```{code-cell} python\n:name: sizes\nsizes = [1, 2]\n```"));
+        assert!(myst.contains("```{math}\n:label: eq-mass\nm = \\rho V\n```"));
+        assert!(
+            myst.contains("```{figure} elephant.png\n:name: fig-elephant\n\nAn _elephant_.\n```")
+        );
+        assert!(myst.contains("````{note} Heads up\nElephants are big.\n\n```python\n"));
+
+        let round_tripped = MystCodec::from_str(&myst, None)?;
+        assert_eq!(
+            serde_json::to_value(&round_tripped)?,
+            serde_json::to_value(&node)?
+        );
+
+        Ok(())
+    }
+}
@@ -268,9 +268,33 @@ inline_media_to_pandoc_image!(AudioObjectSimple);
 inline_media_to_pandoc_image!(ImageObjectSimple);
 inline_media_to_pandoc_image!(VideoObjectSimple);
 
-unimplemented_to_pandoc!(Cite);
+impl ToPandoc for Cite {
+    /// Encode a `Cite` to a Pandoc inline element
+    ///
+    /// Uses the rendered `content` of the citation (generated when the document is assembled)
+    /// falling back to Pandoc's citation syntax if the citation has not been rendered.
+    fn to_pandoc_inline(&self, context: &mut EncodeContext) -> pandoc::Inline {
+        match &self.content {
+            Some(content) => {
+                pandoc::Inline::Span(attrs_empty(), content.to_pandoc_inlines(context))
+            }
+            None => pandoc::Inline::Str(["[@", &self.target, "]"].concat()),
+        }
+    }
+}
 
-unimplemented_to_pandoc!(CiteGroup);
+impl ToPandoc for CiteGroup {
+    /// Encode a `CiteGroup` to a Pandoc inline element
+    fn to_pandoc_inline(&self, context: &mut EncodeContext) -> pandoc::Inline {
+        pandoc::Inline::Span(
+            attrs_empty(),
+            self.items
+                .iter()
+                .map(|cite| cite.to_pandoc_inline(context))
+                .collect(),
+        )
+    }
+}
 
 impl ToPandoc for CodeExpression {
     /// Encode a `CodeExpression` to a Pandoc inline element
@@ -654,11 +678,37 @@ impl ToPandoc for Article {
     fn to_pandoc(&self, context: &mut EncodeContext) -> pandoc::Pandoc {
         let meta = HashMap::new();
 
-        let blocks = self
+        let mut blocks = self
             .content
             .as_ref()
             .map_or_else(Vec::new, |content| content.to_pandoc_blocks(context));
 
+        // Add the list of references, as rendered when the document was assembled
+        let entries: Vec<pandoc::Block> = self
+            .references
+            .iter()
+            .flatten()
+            .filter_map(|reference| match reference {
+                CreativeWorkReferences::String(string) => Some(string),
+                CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(article)) => {
+                    article.text.as_deref()
+                }
+                CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                    work,
+                )) => work.text.as_deref(),
+                _ => None,
+            })
+            .map(|entry| pandoc::Block::Para(vec![pandoc::Inline::Str(entry.clone())]))
+            .collect();
+        if !entries.is_empty() {
+            blocks.push(pandoc::Block::Header(
+                1,
+                attrs_empty(),
+                vec![pandoc::Inline::Str("References".to_string())],
+            ));
+            blocks.extend(entries);
+        }
+
         pandoc::Pandoc { meta, blocks }
     }
 }
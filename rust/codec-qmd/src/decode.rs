@@ -0,0 +1,378 @@
+use codec::{
+    common::{
+        eyre::{bail, Result},
+        once_cell::sync::Lazy,
+        regex::Regex,
+        serde_json, tracing,
+    },
+    stencila_schema::*,
+    DecodeOptions,
+};
+use codec_md::{decode_fragment, decode_frontmatter};
+
+use crate::{CALLOUT_TYPES, XREF_PREFIXES};
+
+/// Decode a Quarto Markdown document to a `Node`
+pub fn decode(input: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+    let (end, node) = decode_frontmatter(input)?;
+
+    let md = match end {
+        Some(end) => &input[end..],
+        None => input,
+    };
+
+    let mut node = match node {
+        Some(node) => node,
+        None => Node::Article(Article::default()),
+    };
+
+    let content = decode_blocks(md);
+    if !content.is_empty() {
+        let content = Some(content);
+        match &mut node {
+            Node::Article(article) => article.content = content,
+            _ => bail!("Unsupported node type {:?}", node),
+        }
+    }
+
+    Ok(node)
+}
+
+/// Decode Quarto Markdown into a vector of `BlockContent`
+///
+/// The Markdown parser does not handle Pandoc fenced divs (e.g. `::: {.callout-note}`)
+/// so they are split out here, with the Markdown between them decoded as usual.
+fn decode_blocks(md: &str) -> Vec<BlockContent> {
+    static DIV_START: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^:{3,}\s*\{(.*)\}\s*$").expect("Unable to create regex"));
+
+    let lines: Vec<&str> = md.lines().collect();
+
+    let mut blocks = Vec::new();
+    let mut markdown = String::new();
+    let mut fence: Option<&str> = None;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+
+        if let Some(marker) = fence {
+            if line.trim_start().starts_with(marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = code_fence(line) {
+            fence = Some(marker);
+        } else if let Some(captures) = DIV_START.captures(line) {
+            if let Some(length) = div_length(&lines[index..]) {
+                blocks.append(&mut decode_markdown(&markdown));
+                markdown.clear();
+
+                let content = lines[index..(index + length)].join("\n");
+                blocks.append(&mut decode_div(&captures[1], &content));
+                index += length + 1;
+                continue;
+            }
+        }
+
+        markdown.push_str(line);
+        markdown.push('\n');
+    }
+    blocks.append(&mut decode_markdown(&markdown));
+
+    blocks
+}
+
+/// Get the marker of a line that starts a fenced code block (if any)
+fn code_fence(line: &str) -> Option<&'static str> {
+    let line = line.trim_start();
+    if line.starts_with("```") {
+        Some("```")
+    } else if line.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Get the number of lines of content in a fenced div (i.e. before its closing fence)
+///
+/// Returns `None` if the div is not closed.
+fn div_length(lines: &[&str]) -> Option<usize> {
+    static DIV_FENCE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^:{3,}\s*(\S*)").expect("Unable to create regex"));
+
+    let mut depth = 0;
+    let mut fence: Option<&str> = None;
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(marker) = fence {
+            if line.trim_start().starts_with(marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = code_fence(line) {
+            fence = Some(marker);
+        } else if let Some(captures) = DIV_FENCE.captures(line) {
+            if captures[1].is_empty() {
+                if depth == 0 {
+                    return Some(index);
+                }
+                depth -= 1;
+            } else {
+                depth += 1;
+            }
+        }
+    }
+    None
+}
+
+/// Decode a fenced div
+///
+/// Callouts (e.g. `{.callout-tip}`), theorems (e.g. `{#thm-line}`) and proofs
+/// (`{.proof}`) are decoded to a `Claim`. The content of other divs is decoded
+/// as if it was not within a div.
+fn decode_div(attrs: &str, content: &str) -> Vec<BlockContent> {
+    let (id, classes, pairs) = parse_attrs(attrs);
+    let mut content = decode_blocks(content);
+
+    let callout = classes
+        .iter()
+        .filter_map(|class| class.strip_prefix("callout-"))
+        .find(|callout| CALLOUT_TYPES.contains(callout));
+
+    let claim_type = match id.as_deref().and_then(|id| id.split_once('-')) {
+        Some(("thm", ..)) => Some(ClaimClaimType::Theorem),
+        Some(("lem", ..)) => Some(ClaimClaimType::Lemma),
+        Some(("cor", ..)) => Some(ClaimClaimType::Corollary),
+        Some(("prp", ..)) => Some(ClaimClaimType::Proposition),
+        _ if classes.iter().any(|class| class == "proof") => Some(ClaimClaimType::Proof),
+        _ => None,
+    };
+
+    if callout.is_none() && claim_type.is_none() {
+        tracing::debug!("Unhandled Quarto div with attributes `{}`", attrs);
+        return content;
+    }
+
+    let title = if let Some((.., title)) = pairs.iter().find(|(name, ..)| name == "title") {
+        Some(Box::new(CreativeWorkTitle::String(title.clone())))
+    } else if let Some(BlockContent::Heading(..)) = content.first() {
+        match content.remove(0) {
+            BlockContent::Heading(heading) => Some(Box::new(CreativeWorkTitle::VecInlineContent(
+                heading.content,
+            ))),
+            _ => unreachable!(),
+        }
+    } else {
+        None
+    };
+
+    vec![BlockContent::Claim(ClaimSimple {
+        id: id.map(Box::new),
+        label: callout.map(|callout| Box::new(callout.to_string())),
+        claim_type,
+        title,
+        content,
+        ..Default::default()
+    })]
+}
+
+/// Parse Pandoc attributes (within curly braces) into an id, classes and key-value pairs
+fn parse_attrs(attrs: &str) -> (Option<String>, Vec<String>, Vec<(String, String)>) {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"#([\w-]+)|\.([\w-]+)|([\w-]+)=(?:"([^"]*)"|(\S+))"#)
+            .expect("Unable to create regex")
+    });
+
+    let mut id = None;
+    let mut classes = Vec::new();
+    let mut pairs = Vec::new();
+    for captures in REGEX.captures_iter(attrs) {
+        if let Some(value) = captures.get(1) {
+            id = Some(value.as_str().to_string());
+        } else if let Some(value) = captures.get(2) {
+            classes.push(value.as_str().to_string());
+        } else if let Some(name) = captures.get(3) {
+            let value = captures.get(4).or_else(|| captures.get(5));
+            pairs.push((
+                name.as_str().to_string(),
+                value
+                    .map(|value| value.as_str().to_string())
+                    .unwrap_or_default(),
+            ));
+        }
+    }
+
+    (id, classes, pairs)
+}
+
+/// Decode Markdown, and then transform the Quarto specific parts of the resulting blocks
+fn decode_markdown(md: &str) -> Vec<BlockContent> {
+    if md.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut blocks = decode_fragment(md, None);
+    transform_blocks(&mut blocks);
+    blocks
+}
+
+fn transform_blocks(blocks: &mut Vec<BlockContent>) {
+    for block in blocks {
+        match block {
+            // Code blocks with curly braced language are transformed to code chunks
+            BlockContent::CodeBlock(CodeBlock {
+                programming_language: Some(lang),
+                text,
+                ..
+            }) if lang.starts_with('{') && lang.ends_with('}') => {
+                let lang = lang[1..(lang.len() - 1)]
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                *block = BlockContent::CodeChunk(decode_chunk(lang, text));
+            }
+            // Paragraphs with only an image followed by an id are transformed to figures
+            BlockContent::Paragraph(Paragraph { content, .. }) => {
+                if let Some(figure) = decode_figure(content) {
+                    *block = BlockContent::Figure(figure)
+                } else {
+                    transform_inlines(content)
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Decode a Quarto code chunk
+///
+/// The `label` and `fig-cap` or `tbl-cap` options in the `#|` comments at the start of
+/// the chunk are mapped to the `id` and `caption` of the `CodeChunk`. Other options
+/// are left in the code.
+fn decode_chunk(lang: String, code: &str) -> CodeChunk {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(?:#|//)\|\s*([\w-]+)\s*:\s*(.*?)\s*$").expect("Unable to create regex")
+    });
+
+    let mut id = None;
+    let mut caption = None;
+    let mut lines = Vec::new();
+    for line in code.lines() {
+        if let Some(captures) = REGEX.captures(line) {
+            match &captures[1] {
+                "label" => {
+                    id = Some(Box::new(unquote(&captures[2])));
+                    continue;
+                }
+                "fig-cap" | "tbl-cap" => {
+                    caption = Some(Box::new(CodeChunkCaption::String(unquote(&captures[2]))));
+                    continue;
+                }
+                _ => (),
+            }
+        }
+        lines.push(line);
+    }
+
+    CodeChunk {
+        programming_language: lang,
+        text: lines.join("\n"),
+        id,
+        caption,
+        ..Default::default()
+    }
+}
+
+/// Remove any quotes from around an option value
+fn unquote(value: &str) -> String {
+    if value.starts_with('"') && value.ends_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value.trim_matches('"').to_string())
+    } else if value.starts_with('\'') && value.ends_with('\'') && value.len() > 1 {
+        value[1..(value.len() - 1)].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Decode a figure from the content of a paragraph e.g. `![Caption](image.png){#fig-id}`
+fn decode_figure(inlines: &[InlineContent]) -> Option<FigureSimple> {
+    static REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\{#([\w-]+)[^}]*\}$").expect("Unable to create regex"));
+
+    let (image, attrs) = match inlines {
+        [InlineContent::ImageObject(image), InlineContent::String(attrs)] => (image, attrs),
+        _ => return None,
+    };
+    let id = REGEX.captures(attrs.trim())?[1].to_string();
+
+    let caption = image.caption.as_deref().map(|caption| {
+        Box::new(FigureCaption::VecBlockContent(vec![
+            BlockContent::Paragraph(Paragraph {
+                content: vec![InlineContent::String(caption.clone())],
+                ..Default::default()
+            }),
+        ]))
+    });
+
+    Some(FigureSimple {
+        id: Some(Box::new(id)),
+        caption,
+        content: Some(Box::new(CreativeWorkContent::VecNode(vec![
+            Node::ImageObject(ImageObject {
+                content_url: image.content_url.clone(),
+                ..Default::default()
+            }),
+        ]))),
+        ..Default::default()
+    })
+}
+
+fn transform_inlines(inlines: &mut Vec<InlineContent>) {
+    static EXPR: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\{(\w+)\}\s+(.*)$").expect("Unable to create regex"));
+    static XREF: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^-[\w-]*\w").expect("Unable to create regex"));
+
+    let mut transformed: Vec<InlineContent> = Vec::with_capacity(inlines.len());
+    for mut inline in inlines.drain(..) {
+        match &mut inline {
+            // Code fragments starting with a curly braced language become code expressions
+            InlineContent::CodeFragment(CodeFragment { text, .. }) => {
+                let expr = EXPR.captures(text).map(|captures| CodeExpression {
+                    programming_language: captures[1].to_string(),
+                    text: captures[2].to_string(),
+                    ..Default::default()
+                });
+                if let Some(expr) = expr {
+                    inline = InlineContent::CodeExpression(expr);
+                }
+            }
+            // The Markdown parser splits cross-references (e.g. `@fig-elephant`) at the
+            // hyphen so join them back together
+            InlineContent::String(string) => {
+                if let Some(InlineContent::Cite(cite)) = transformed.last_mut() {
+                    if XREF_PREFIXES.contains(&cite.target.as_str()) {
+                        if let Some(suffix) = XREF.find(string) {
+                            cite.target.push_str(suffix.as_str());
+                            cite.citation_mode = Some(CiteCitationMode::Narrative);
+                            string.replace_range(..suffix.end(), "");
+                            if string.is_empty() {
+                                continue;
+                            }
+                        }
+                    }
+                }
+            }
+            // Recursively transform other inlines
+            InlineContent::Strikeout(Strikeout { content, .. })
+            | InlineContent::Emphasis(Emphasis { content, .. })
+            | InlineContent::Subscript(Subscript { content, .. })
+            | InlineContent::Superscript(Superscript { content, .. })
+            | InlineContent::Strong(Strong { content, .. })
+            | InlineContent::Underline(Underline { content, .. }) => transform_inlines(content),
+            _ => (),
+        }
+        transformed.push(inline);
+    }
+    *inlines = transformed;
+}
@@ -0,0 +1,181 @@
+use codec::{
+    common::{eyre::Result, serde_json},
+    stencila_schema::*,
+    CodecTrait, EncodeOptions,
+};
+use codec_md::{encode_frontmatter, MdCodec, ToMd};
+use codec_txt::ToTxt;
+
+use crate::CALLOUT_TYPES;
+
+/// Encode a `Node` to Quarto Markdown
+pub fn encode(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
+    let article = match node {
+        Node::Article(article) => article,
+        _ => return MdCodec::to_string(node, options),
+    };
+    let options = options.unwrap_or_default();
+
+    let frontmatter = encode_frontmatter(article)?;
+    let content = match &article.content {
+        Some(blocks) => encode_blocks(blocks, &options),
+        None => String::new(),
+    };
+
+    Ok([frontmatter, content].concat().trim().to_string())
+}
+
+fn encode_blocks(blocks: &[BlockContent], options: &EncodeOptions) -> String {
+    blocks
+        .iter()
+        .map(|block| encode_block(block, options))
+        .collect()
+}
+
+fn encode_block(block: &BlockContent, options: &EncodeOptions) -> String {
+    match block {
+        BlockContent::CodeChunk(chunk) => encode_chunk(chunk),
+        BlockContent::Claim(claim) => encode_claim(claim, options),
+        BlockContent::Figure(figure) => match encode_figure(figure, options) {
+            Some(md) => md,
+            None => block.to_md(options),
+        },
+        BlockContent::Paragraph(paragraph) => {
+            let mut paragraph = paragraph.clone();
+            transform_inlines(&mut paragraph.content);
+            paragraph.to_md(options)
+        }
+        _ => block.to_md(options),
+    }
+}
+
+/// Encode a `CodeChunk` to a Quarto code chunk
+///
+/// The `id` and `caption` of the chunk are encoded as `#|` option comments.
+fn encode_chunk(chunk: &CodeChunk) -> String {
+    let prefix = match chunk.programming_language.as_str() {
+        "js" | "javascript" | "ojs" => "//|",
+        _ => "#|",
+    };
+
+    let mut options = String::new();
+    if let Some(id) = chunk.id.as_deref() {
+        options.push_str(&[prefix, " label: ", id, "\n"].concat());
+    }
+    if let Some(caption) = chunk.caption.as_deref() {
+        let caption = match caption {
+            CodeChunkCaption::String(string) => string.clone(),
+            CodeChunkCaption::VecBlockContent(blocks) => blocks.to_txt().trim().to_string(),
+        };
+        let option = match chunk.id.as_deref() {
+            Some(id) if id.starts_with("tbl-") => "tbl-cap",
+            _ => "fig-cap",
+        };
+        let caption = serde_json::to_string(&caption).unwrap_or_default();
+        options.push_str(&[prefix, " ", option, ": ", &caption, "\n"].concat());
+    }
+
+    [
+        "```{",
+        &chunk.programming_language,
+        "}\n",
+        &options,
+        &chunk.text,
+        "\n```\n\n",
+    ]
+    .concat()
+}
+
+/// Encode a `Claim` as a Quarto callout or theorem div
+fn encode_claim(claim: &ClaimSimple, options: &EncodeOptions) -> String {
+    let callout = claim
+        .label
+        .as_deref()
+        .filter(|label| CALLOUT_TYPES.contains(&label.as_str()));
+
+    let mut attrs = Vec::new();
+    if let Some(id) = claim.id.as_deref() {
+        attrs.push(["#", id].concat());
+    }
+    if let Some(callout) = callout {
+        attrs.push([".callout-", callout].concat());
+    }
+    if matches!(claim.claim_type, Some(ClaimClaimType::Proof)) {
+        attrs.push(".proof".to_string());
+    }
+
+    let title = claim.title.as_deref().map(|title| match title {
+        CreativeWorkTitle::String(string) => string.clone(),
+        CreativeWorkTitle::VecInlineContent(inlines) => inlines.to_md(options),
+    });
+    let heading = match (title, callout) {
+        (Some(title), Some(..)) => {
+            attrs.push(["title=", &serde_json::to_string(&title).unwrap_or_default()].concat());
+            String::new()
+        }
+        (Some(title), None) => ["## ", &title, "\n\n"].concat(),
+        _ => String::new(),
+    };
+
+    [
+        "::: {",
+        &attrs.join(" "),
+        "}\n",
+        &heading,
+        &encode_blocks(&claim.content, options),
+        ":::\n\n",
+    ]
+    .concat()
+}
+
+/// Encode a `Figure` with a single image as a Quarto figure
+/// e.g. `![Caption](image.png){#fig-id}`
+///
+/// Returns `None` if the figure does not have an `id` or has other content.
+fn encode_figure(figure: &FigureSimple, options: &EncodeOptions) -> Option<String> {
+    let id = figure.id.as_deref()?;
+    let image = match figure.content.as_deref() {
+        Some(CreativeWorkContent::VecNode(nodes)) => match nodes.as_slice() {
+            [Node::ImageObject(image)] => image,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let caption = match figure.caption.as_deref() {
+        Some(FigureCaption::String(string)) => string.clone(),
+        Some(FigureCaption::VecBlockContent(blocks)) => match blocks.as_slice() {
+            [BlockContent::Paragraph(paragraph)] => paragraph.content.to_md(options),
+            _ => blocks.to_txt().trim().to_string(),
+        },
+        None => String::new(),
+    };
+
+    Some(["![", &caption, "](", &image.content_url, "){#", id, "}\n\n"].concat())
+}
+
+fn transform_inlines(inlines: &mut Vec<InlineContent>) {
+    for inline in inlines {
+        match inline {
+            // Code expressions become code fragments prefixed with the curly braced language
+            InlineContent::CodeExpression(CodeExpression {
+                programming_language,
+                text,
+                ..
+            }) => {
+                *inline = InlineContent::CodeFragment(CodeFragment {
+                    text: ["{", programming_language, "} ", text].concat(),
+                    ..Default::default()
+                })
+            }
+            // Recursively transform other inlines
+            InlineContent::Strikeout(Strikeout { content, .. })
+            | InlineContent::Emphasis(Emphasis { content, .. })
+            | InlineContent::Subscript(Subscript { content, .. })
+            | InlineContent::Superscript(Superscript { content, .. })
+            | InlineContent::Strong(Strong { content, .. })
+            | InlineContent::Underline(Underline { content, .. }) => transform_inlines(content),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use codec::{
+    common::eyre::Result, stencila_schema::Node, utils::vec_string, Codec, CodecTrait,
+    DecodeOptions, EncodeOptions,
+};
+use codec_md::MdCodec;
+
+#[cfg(feature = "decode")]
+mod decode;
+
+#[cfg(feature = "encode")]
+mod encode;
+
+/// The types of Quarto callouts
+///
+/// Callouts are decoded to a `Claim` with the type of callout as its `label`.
+#[cfg(any(feature = "decode", feature = "encode"))]
+const CALLOUT_TYPES: &[&str] = &["note", "warning", "important", "tip", "caution"];
+
+/// The prefixes of Quarto cross-reference labels e.g. `@fig-elephant`
+#[cfg(any(feature = "decode", feature = "encode"))]
+const XREF_PREFIXES: &[&str] = &["fig", "tbl", "sec", "eq", "lst", "thm", "lem", "cor", "prp"];
+
+/// A codec for Quarto Markdown
+///
+/// Builds on the Markdown codec, handling the Quarto specific syntax for:
+///
+/// - executable code chunks (e.g. ` ```{python} `) and their `#|` option comments
+///   (the `label` option is mapped to the `id` of the `CodeChunk` and `fig-cap` or
+///   `tbl-cap` to its `caption`)
+/// - inline code expressions (e.g. `` `{python} 1 + 1` ``)
+/// - cross-references to figures, tables etc (e.g. `@fig-elephant`) which are decoded
+///   to narrative `Cite`s
+/// - figures with cross-reference labels (e.g. `![Caption](elephant.png){#fig-elephant}`)
+/// - callout divs (e.g. `::: {.callout-note}`) and theorem divs (e.g. `::: {#thm-line}`)
+///   which are decoded to `Claim`s
+///
+/// YAML front matter is decoded to, and encoded from, the properties of the `Article`.
+pub struct QmdCodec {}
+
+impl CodecTrait for QmdCodec {
+    fn spec() -> Codec {
+        let md_codec = MdCodec::spec();
+        Codec {
+            status: "alpha".to_string(),
+            formats: vec_string!["qmd"],
+            root_types: vec_string!["Article"],
+            from_string: cfg!(feature = "decode"),
+            from_path: cfg!(feature = "decode"),
+            to_string: cfg!(feature = "encode"),
+            to_path: cfg!(feature = "encode"),
+            unsupported_types: md_codec.unsupported_types,
+            unsupported_properties: md_codec.unsupported_properties,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "decode")]
+    fn from_str(str: &str, options: Option<DecodeOptions>) -> Result<Node> {
+        decode::decode(str, options)
+    }
+
+    #[cfg(feature = "encode")]
+    fn to_string(node: &Node, options: Option<EncodeOptions>) -> Result<String> {
+        encode::encode(node, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::common::serde_json;
+    use codec::stencila_schema::{Article, BlockContent, ClaimClaimType, InlineContent};
+    use test_utils::{assert_json_is, pretty_assertions::assert_eq};
+
+    const QMD: &str = r#"---
+title: Elephants
+---
+
+# Introduction
+
+See @fig-elephant and @tbl-sizes, and the mean of `{python} mean`.
+
+![An elephant](elephant.png){#fig-elephant}
+
+```{python}
+#| label: tbl-sizes
+#| tbl-cap: "Sizes of elephants"
+#| echo: false
+sizes
+```
+
+::: {.callout-note title="Heads up"}
+Elephants are _big_.
+:::
+
+::: {#thm-size}
+## Size
+
+All elephants are bigger than all mice.
+:::
+"#;
+
+    fn article(node: &Node) -> &Article {
+        match node {
+            Node::Article(article) => article,
+            _ => panic!("Expected an article"),
+        }
+    }
+
+    #[test]
+    fn decode() -> Result<()> {
+        let node = QmdCodec::from_str(QMD, None)?;
+        let article = article(&node);
+        assert_json_is!(article.title, "Elephants");
+
+        let content = article.content.as_ref().unwrap();
+        assert_eq!(content.len(), 6);
+
+        match &content[1] {
+            BlockContent::Paragraph(para) => {
+                assert_json_is!(para.content[1], {
+                    "type": "Cite", "target": "fig-elephant", "citationMode": "Narrative"
+                });
+                assert_json_is!(para.content[3], {
+                    "type": "Cite", "target": "tbl-sizes", "citationMode": "Narrative"
+                });
+                assert!(matches!(
+                    &para.content[5],
+                    InlineContent::CodeExpression(expr) if expr.programming_language == "python" && expr.text == "mean"
+                ));
+            }
+            _ => panic!("Expected a paragraph"),
+        }
+
+        match &content[2] {
+            BlockContent::Figure(figure) => {
+                assert_json_is!(figure.id, "fig-elephant");
+                assert_json_is!(figure.content, [{ "type": "ImageObject", "contentUrl": "elephant.png" }]);
+                assert_json_is!(figure.caption, [{ "type": "Paragraph", "content": ["An elephant"] }]);
+            }
+            _ => panic!("Expected a figure"),
+        }
+
+        match &content[3] {
+            BlockContent::CodeChunk(chunk) => {
+                assert_eq!(chunk.programming_language, "python");
+                assert_eq!(chunk.text, "#| echo: false\nsizes");
+                assert_json_is!(chunk.id, "tbl-sizes");
+                assert_json_is!(chunk.caption, "Sizes of elephants");
+            }
+            _ => panic!("Expected a code chunk"),
+        }
+
+        match &content[4] {
+            BlockContent::Claim(claim) => {
+                assert_json_is!(claim.label, "note");
+                assert_json_is!(claim.title, "Heads up");
+                assert_json_is!(claim.content, [{
+                    "type": "Paragraph",
+                    "content": ["Elephants are ", { "type": "Emphasis", "content": ["big"] }, "."]
+                }]);
+            }
+            _ => panic!("Expected a claim"),
+        }
+
+        match &content[5] {
+            BlockContent::Claim(claim) => {
+                assert_json_is!(claim.id, "thm-size");
+                assert_eq!(claim.claim_type, Some(ClaimClaimType::Theorem));
+                assert_json_is!(claim.title, ["Size"]);
+                assert_eq!(claim.content.len(), 1);
+            }
+            _ => panic!("Expected a claim"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let node = QmdCodec::from_str(QMD, None)?;
+        let qmd = QmdCodec::to_string(&node, None)?;
+
+        assert!(qmd.starts_with("---\ntitle: Elephants\n---\n"));
+        assert!(qmd.contains("See @fig-elephant and @tbl-sizes"));
+        assert!(qmd.contains("`{python} mean`"));
+        assert!(qmd.contains("![An elephant](elephant.png){#fig-elephant}"));
+        assert!(qmd.contains("```{python}\n#| label: tbl-sizes\n#| tbl-cap: \"Sizes of elephants\"\n#| echo: false\nsizes\n```"));
+        assert!(qmd.contains("::: {.callout-note title=\"Heads up\"}"));
+        assert!(qmd.contains("::: {#thm-size}\n## Size\n"));
+
+        let round_tripped = QmdCodec::from_str(&qmd, None)?;
+        assert_eq!(
+            serde_json::to_value(&round_tripped)?,
+            serde_json::to_value(&node)?
+        );
+
+        Ok(())
+    }
+}
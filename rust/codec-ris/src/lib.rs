@@ -0,0 +1,461 @@
+use codec::{
+    common::{
+        eyre::{bail, Result},
+        serde_json::{self, json},
+    },
+    stencila_schema::Node,
+    utils::vec_string,
+    Codec, CodecTrait, DecodeOptions, EncodeOptions,
+};
+use codec_csl::CslCodec;
+
+/// The mapping between RIS reference types and CSL item types
+///
+/// When decoding, the first match on the RIS type is used (unknown
+/// types are decoded as `document`). When encoding, the first match on
+/// the CSL type is used (unknown types are encoded as `GEN`).
+const TYPES: &[(&str, &str)] = &[
+    ("JOUR", "article-journal"),
+    ("BOOK", "book"),
+    ("CHAP", "chapter"),
+    ("CONF", "paper-conference"),
+    ("CPAPER", "paper-conference"),
+    ("DATA", "dataset"),
+    ("ELEC", "webpage"),
+    ("GEN", "document"),
+    ("MGZN", "article-magazine"),
+    ("NEWS", "article-newspaper"),
+    ("PAMP", "pamphlet"),
+    ("RPRT", "report"),
+    ("COMP", "software"),
+    ("THES", "thesis"),
+    ("UNPB", "manuscript"),
+    ("JOUR", "article"),
+];
+
+/// A codec for Research Information Systems (RIS) citation files
+///
+/// Decodes a RIS file to an `Article` with each record as one of its `references`,
+/// and encodes the `references` of an `Article` to RIS. As for `BibtexCodec`, uses
+/// CSL-JSON as an intermediate representation so that the mapping to and from
+/// Stencila nodes is done by `CslCodec`.
+///
+/// See https://en.wikipedia.org/wiki/RIS_(file_format) for a description of the tags.
+pub struct RisCodec;
+
+impl CodecTrait for RisCodec {
+    fn spec() -> Codec {
+        Codec {
+            formats: vec_string!["ris"],
+            root_types: vec_string!["Article"],
+            ..Default::default()
+        }
+    }
+
+    fn from_str(str: &str, _options: Option<DecodeOptions>) -> Result<Node> {
+        let items = decode(str)?;
+        CslCodec::from_json_items(&items)
+    }
+
+    fn to_string(node: &Node, _options: Option<EncodeOptions>) -> Result<String> {
+        let items = CslCodec::to_json_items(node)?;
+        Ok(encode(&items))
+    }
+}
+
+/// Decode RIS into a list of CSL-JSON items
+fn decode(ris: &str) -> Result<Vec<serde_json::Value>> {
+    let mut items = Vec::new();
+    let mut record: Option<Vec<(String, String)>> = None;
+    for line in ris.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        let (tag, value) = match parse_line(line) {
+            Some(tag_value) => tag_value,
+            None => {
+                // Continuation of the previous value (some exporters wrap long values)
+                if let Some((.., value)) = record.as_mut().and_then(|record| record.last_mut()) {
+                    if !line.trim().is_empty() {
+                        value.push(' ');
+                        value.push_str(line.trim());
+                    }
+                }
+                continue;
+            }
+        };
+        match tag {
+            "TY" => {
+                if record.is_some() {
+                    bail!("Unexpected `TY` tag before `ER` tag of previous record")
+                }
+                record = Some(vec![(tag.to_string(), value.to_string())]);
+            }
+            "ER" => match record.take() {
+                Some(record) => items.push(record_to_item(&record)),
+                None => bail!("Unexpected `ER` tag without a preceding `TY` tag"),
+            },
+            _ => match record.as_mut() {
+                Some(record) => record.push((tag.to_string(), value.to_string())),
+                None => bail!("Unexpected `{}` tag without a preceding `TY` tag", tag),
+            },
+        }
+    }
+    if record.is_some() {
+        bail!("Expected an `ER` tag at the end of the last record")
+    }
+    Ok(items)
+}
+
+/// Parse a RIS line into a tag and a value
+///
+/// Lines have the form `XY  - value` (although some exporters use fewer spaces).
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..2)?;
+    if !tag
+        .chars()
+        .all(|char| char.is_ascii_uppercase() || char.is_ascii_digit())
+    {
+        return None;
+    }
+    let rest = line[2..].trim_start_matches(' ');
+    let value = rest.strip_prefix('-')?;
+    Some((tag, value.trim()))
+}
+
+/// Convert a RIS record into a CSL-JSON item
+fn record_to_item(record: &[(String, String)]) -> serde_json::Value {
+    let field = |tags: &[&str]| {
+        tags.iter().find_map(|tag| {
+            record
+                .iter()
+                .find(|(name, value)| name == tag && !value.is_empty())
+                .map(|(.., value)| value.as_str())
+        })
+    };
+    let fields = |tags: &[&str]| {
+        record
+            .iter()
+            .filter(|(name, value)| tags.contains(&name.as_str()) && !value.is_empty())
+            .map(|(.., value)| value.as_str())
+            .collect::<Vec<&str>>()
+    };
+
+    let mut item = serde_json::Map::new();
+
+    if let Some(id) = field(&["ID"]) {
+        item.insert("id".to_string(), json!(id));
+    }
+
+    let type_ = field(&["TY"])
+        .and_then(|type_| {
+            TYPES
+                .iter()
+                .find(|(ris, ..)| *ris == type_)
+                .map(|(.., csl)| *csl)
+        })
+        .unwrap_or("document");
+    item.insert("type".to_string(), json!(type_));
+
+    let authors = fields(&["AU", "A1"]);
+    if !authors.is_empty() {
+        let authors: Vec<serde_json::Value> = authors.into_iter().map(decode_name).collect();
+        item.insert("author".to_string(), json!(authors));
+    }
+    let editors = fields(&["ED", "A2"]);
+    if !editors.is_empty() {
+        let editors: Vec<serde_json::Value> = editors.into_iter().map(decode_name).collect();
+        item.insert("editor".to_string(), json!(editors));
+    }
+
+    if let Some(title) = field(&["TI", "T1", "CT"]) {
+        item.insert("title".to_string(), json!(title));
+    }
+    if let Some(container) = field(&["T2", "JF", "JO", "JA", "BT"]) {
+        item.insert("container-title".to_string(), json!(container));
+    }
+
+    if let Some(date) = decode_date(field(&["DA", "PY", "Y1"])) {
+        item.insert("issued".to_string(), date);
+    }
+
+    if let Some(volume) = field(&["VL"]) {
+        item.insert("volume".to_string(), json!(volume));
+    }
+    if let Some(issue) = field(&["IS"]) {
+        item.insert("issue".to_string(), json!(issue));
+    }
+    match (field(&["SP"]), field(&["EP"])) {
+        (Some(start), Some(end)) => {
+            item.insert("page".to_string(), json!([start, "-", end].concat()));
+        }
+        (Some(start), None) => {
+            item.insert("page".to_string(), json!(start));
+        }
+        _ => {}
+    }
+
+    if let Some(publisher) = field(&["PB"]) {
+        item.insert("publisher".to_string(), json!(publisher));
+    }
+    if let Some(doi) = field(&["DO"]) {
+        let doi = doi
+            .trim_start_matches("https://doi.org/")
+            .trim_start_matches("http://dx.doi.org/");
+        item.insert("DOI".to_string(), json!(doi));
+    }
+    if let Some(url) = field(&["UR"]) {
+        item.insert("URL".to_string(), json!(url));
+    }
+    if let Some(abstract_) = field(&["AB", "N2"]) {
+        item.insert("abstract".to_string(), json!(abstract_));
+    }
+
+    serde_json::Value::Object(item)
+}
+
+/// Decode a RIS name into a CSL `name-variable`
+///
+/// Names are expected to be in "Family, Given" form. Names without
+/// a comma are treated as literal names (e.g. organizations).
+fn decode_name(name: &str) -> serde_json::Value {
+    let mut parts = name.splitn(3, ',').map(|part| part.trim());
+    match (parts.next(), parts.next()) {
+        (Some(family), Some(given)) if !given.is_empty() => {
+            json!({ "family": family, "given": given })
+        }
+        (Some(family), _) if name.contains(',') => json!({ "family": family }),
+        _ => json!({ "literal": name.trim() }),
+    }
+}
+
+/// Decode a RIS date (`YYYY/MM/DD/other` with all but the year optional)
+fn decode_date(date: Option<&str>) -> Option<serde_json::Value> {
+    let date = date?;
+    let parts: Vec<u64> = date
+        .split('/')
+        .take(3)
+        .map_while(|part| part.trim().parse::<u64>().ok())
+        .collect();
+    if parts.is_empty() {
+        Some(json!({ "raw": date }))
+    } else {
+        Some(json!({ "date-parts": [parts] }))
+    }
+}
+
+/// Encode a list of CSL-JSON items as RIS
+fn encode(items: &[serde_json::Value]) -> String {
+    items
+        .iter()
+        .map(encode_item)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Encode a single CSL-JSON item as a RIS record
+fn encode_item(item: &serde_json::Value) -> String {
+    let string = |name: &str| item.get(name).and_then(|value| value.as_str());
+
+    let type_ = string("type")
+        .and_then(|type_| {
+            TYPES
+                .iter()
+                .find(|(.., csl)| *csl == type_)
+                .map(|(ris, ..)| *ris)
+        })
+        .unwrap_or("GEN");
+
+    let mut tags: Vec<(&str, String)> = vec![("TY", type_.to_string())];
+
+    if let Some(id) = string("id") {
+        tags.push(("ID", id.to_string()));
+    }
+
+    for (name, tag) in [("author", "AU"), ("editor", "ED")] {
+        for person in item
+            .get(name)
+            .and_then(|names| names.as_array())
+            .iter()
+            .flat_map(|names| names.iter())
+        {
+            if let Some(person) = encode_name(person) {
+                tags.push((tag, person));
+            }
+        }
+    }
+
+    if let Some(title) = string("title") {
+        tags.push(("TI", title.to_string()));
+    }
+    if let Some(container) = string("container-title") {
+        let tag = match type_ {
+            "JOUR" | "MGZN" | "NEWS" => "JO",
+            _ => "T2",
+        };
+        tags.push((tag, container.to_string()));
+    }
+
+    if let Some(parts) = item
+        .get("issued")
+        .and_then(|issued| issued.get("date-parts"))
+        .and_then(|parts| parts.get(0))
+        .and_then(|parts| parts.as_array())
+    {
+        let parts: Vec<String> = parts
+            .iter()
+            .filter_map(|part| part.as_u64())
+            .map(|part| part.to_string())
+            .collect();
+        if let Some(year) = parts.first() {
+            tags.push(("PY", year.clone()));
+        }
+        if parts.len() > 1 {
+            let month = format!("{:0>2}", parts[1]);
+            let day = parts.get(2).map(|day| format!("{:0>2}", day));
+            tags.push((
+                "DA",
+                format!("{}/{}/{}", parts[0], month, day.unwrap_or_default()),
+            ));
+        }
+    }
+
+    if let Some(volume) = string("volume") {
+        tags.push(("VL", volume.to_string()));
+    }
+    if let Some(issue) = string("issue") {
+        tags.push(("IS", issue.to_string()));
+    }
+    if let Some(page) = string("page") {
+        let mut pages = page.splitn(2, '-');
+        if let Some(start) = pages.next() {
+            tags.push(("SP", start.trim().to_string()));
+        }
+        if let Some(end) = pages.next() {
+            tags.push(("EP", end.trim().to_string()));
+        }
+    }
+
+    if let Some(publisher) = string("publisher") {
+        tags.push(("PB", publisher.to_string()));
+    }
+    if let Some(doi) = string("DOI") {
+        tags.push(("DO", doi.to_string()));
+    }
+    if let Some(url) = string("URL") {
+        tags.push(("UR", url.to_string()));
+    }
+    if let Some(abstract_) = string("abstract") {
+        tags.push(("AB", abstract_.to_string()));
+    }
+
+    tags.push(("ER", String::new()));
+
+    tags.into_iter()
+        .map(|(tag, value)| match value.is_empty() {
+            true => format!("{}  -\n", tag),
+            false => format!("{}  - {}\n", tag, value),
+        })
+        .collect()
+}
+
+/// Encode a CSL `name-variable` as a RIS name
+fn encode_name(name: &serde_json::Value) -> Option<String> {
+    if let Some(literal) = name.get("literal").and_then(|literal| literal.as_str()) {
+        return Some(literal.to_string());
+    }
+    let family = name.get("family").and_then(|family| family.as_str())?;
+    Some(match name.get("given").and_then(|given| given.as_str()) {
+        Some(given) => [family, ", ", given].concat(),
+        None => [family, ","].concat(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::{assert_json_eq, assert_json_is, pretty_assertions::assert_eq};
+
+    #[test]
+    fn decode_records() -> Result<()> {
+        let ris = "TY  - JOUR
+AU  - Carlsson, Kristoffer
+AU  - Ekre, Fredrik
+TI  - Tensors.jl — Tensor Computations
+  in Julia
+JO  - Journal of Open Research Software
+PY  - 2019
+DA  - 2019/03/21/
+VL  - 7
+SP  - 65
+EP  - 70
+DO  - 10.5334/jors.182
+ER  -
+
+TY  - RPRT
+AU  - World Health Organization
+TI  - World Health Statistics
+PY  - 2021
+ER  -
+";
+        assert_json_is!(
+            decode(ris)?,
+            [{
+                "type": "article-journal",
+                "author": [
+                    {"family": "Carlsson", "given": "Kristoffer"},
+                    {"family": "Ekre", "given": "Fredrik"}
+                ],
+                "title": "Tensors.jl — Tensor Computations in Julia",
+                "container-title": "Journal of Open Research Software",
+                "issued": {"date-parts": [[2019, 3, 21]]},
+                "volume": "7",
+                "page": "65-70",
+                "DOI": "10.5334/jors.182"
+            }, {
+                "type": "report",
+                "author": [
+                    {"literal": "World Health Organization"}
+                ],
+                "title": "World Health Statistics",
+                "issued": {"date-parts": [[2021]]}
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let ris = "TY  - JOUR
+ID  - carlsson2019
+AU  - Carlsson, Kristoffer
+AU  - Ekre, Fredrik
+TI  - Tensors.jl — Tensor Computations in Julia
+JO  - Journal of Open Research Software
+PY  - 2019
+DA  - 2019/03/21
+VL  - 7
+IS  - 1
+SP  - 65
+EP  - 70
+PB  - Ubiquity Press, Ltd.
+DO  - 10.5334/jors.182
+UR  - http://dx.doi.org/10.5334/jors.182
+ER  -
+
+TY  - CHAP
+AU  - Smith, Jane
+ED  - Doe, John
+TI  - A Chapter
+T2  - A Book
+PY  - 2020
+ER  -
+";
+        let node = RisCodec::from_str(ris, None)?;
+        assert_eq!(RisCodec::to_string(&node, None)?, ris);
+
+        let again = RisCodec::from_str(&RisCodec::to_string(&node, None)?, None)?;
+        assert_json_eq!(again, node);
+
+        Ok(())
+    }
+}
@@ -98,6 +98,10 @@ struct Codecs {
 macro_rules! dispatch_builtins {
     ($format:expr, $method:ident $(,$arg:expr)*) => {
         match $format {
+            #[cfg(feature = "codec-bibtex")]
+            Format::BibTeX => Some(codec_bibtex::BibtexCodec::$method($($arg),*)),
+            #[cfg(feature = "codec-csl")]
+            Format::Csl => Some(codec_csl::CslCodec::$method($($arg),*)),
             #[cfg(feature = "codec-date")]
             Format::Date => Some(codec_date::DateCodec::$method($($arg),*)),
             #[cfg(feature = "codec-docx")]
@@ -108,6 +112,8 @@ macro_rules! dispatch_builtins {
             Format::Html => Some(codec_html::HtmlCodec::$method($($arg),*)),
             #[cfg(feature = "codec-ipynb")]
             Format::Ipynb => Some(codec_ipynb::IpynbCodec::$method($($arg),*)),
+            #[cfg(feature = "codec-jats")]
+            Format::Jats => Some(codec_jats::JatsCodec::$method($($arg),*)),
             #[cfg(feature = "codec-json")]
             Format::Json => Some(codec_json::JsonCodec::$method($($arg),*)),
             #[cfg(feature = "codec-json5")]
@@ -116,6 +122,8 @@ macro_rules! dispatch_builtins {
             Format::LaTeX => Some(codec_latex::LatexCodec::$method($($arg),*)),
             #[cfg(feature = "codec-md")]
             Format::Markdown => Some(codec_md::MdCodec::$method($($arg),*)),
+            #[cfg(feature = "codec-myst")]
+            Format::Myst => Some(codec_myst::MystCodec::$method($($arg),*)),
             #[cfg(feature = "codec-pandoc")]
             Format::Pandoc => Some(codec_pandoc::PandocCodec::$method($($arg),*)),
             #[cfg(feature = "codec-pdf")]
@@ -124,8 +132,12 @@ macro_rules! dispatch_builtins {
             Format::Person => Some(codec_person::PersonCodec::$method($($arg),*)),
             #[cfg(feature = "codec-png")]
             Format::Png => Some(codec_png::PngCodec::$method($($arg),*)),
+            #[cfg(feature = "codec-qmd")]
+            Format::QuartoMarkdown => Some(codec_qmd::QmdCodec::$method($($arg),*)),
             #[cfg(feature = "codec-rmd")]
             Format::RMarkdown => Some(codec_rmd::RmdCodec::$method($($arg),*)),
+            #[cfg(feature = "codec-ris")]
+            Format::Ris => Some(codec_ris::RisCodec::$method($($arg),*)),
             #[cfg(feature = "codec-rpng")]
             Format::Rpng => Some(codec_rpng::RpngCodec::$method($($arg),*)),
             #[cfg(feature = "codec-toml")]
@@ -163,6 +175,10 @@ impl Codecs {
     /// consistent with format names or aliases.
     pub fn new() -> Self {
         let inner = vec![
+            #[cfg(feature = "codec-bibtex")]
+            ("bibtex", codec_bibtex::BibtexCodec::spec()),
+            #[cfg(feature = "codec-csl")]
+            ("csl", codec_csl::CslCodec::spec()),
             #[cfg(feature = "codec-date")]
             ("date", codec_date::DateCodec::spec()),
             #[cfg(feature = "codec-docx")]
@@ -173,6 +189,8 @@ impl Codecs {
             ("html", codec_html::HtmlCodec::spec()),
             #[cfg(feature = "codec-ipynb")]
             ("ipynb", codec_ipynb::IpynbCodec::spec()),
+            #[cfg(feature = "codec-jats")]
+            ("jats", codec_jats::JatsCodec::spec()),
             #[cfg(feature = "codec-json")]
             ("json", codec_json::JsonCodec::spec()),
             #[cfg(feature = "codec-json5")]
@@ -181,6 +199,8 @@ impl Codecs {
             ("latex", codec_latex::LatexCodec::spec()),
             #[cfg(feature = "codec-md")]
             ("md", codec_md::MdCodec::spec()),
+            #[cfg(feature = "codec-myst")]
+            ("myst", codec_myst::MystCodec::spec()),
             #[cfg(feature = "codec-pandoc")]
             ("pandoc", codec_pandoc::PandocCodec::spec()),
             #[cfg(feature = "codec-pdf")]
@@ -189,8 +209,12 @@ impl Codecs {
             ("person", codec_person::PersonCodec::spec()),
             #[cfg(feature = "codec-png")]
             ("png", codec_png::PngCodec::spec()),
+            #[cfg(feature = "codec-qmd")]
+            ("qmd", codec_qmd::QmdCodec::spec()),
             #[cfg(feature = "codec-rmd")]
             ("rmd", codec_rmd::RmdCodec::spec()),
+            #[cfg(feature = "codec-ris")]
+            ("ris", codec_ris::RisCodec::spec()),
             #[cfg(feature = "codec-rpng")]
             ("rpng", codec_rpng::RpngCodec::spec()),
             #[cfg(feature = "codec-script")]
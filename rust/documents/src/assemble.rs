@@ -5,9 +5,11 @@ use common::{
     tokio::sync::{mpsc::UnboundedSender, RwLock},
 };
 use node_address::{Address, AddressMap};
-use stencila_schema::Node;
+use node_patch::diff_address;
+use stencila_schema::{Article, Node};
 
 use crate::{
+    citations::{citation_style, Citations},
     document::CallDocuments,
     executable::{AssembleContext, Executable},
     messages::{PatchRequest, When},
@@ -19,10 +21,15 @@ use crate::{
 /// Uses a `RwLock` for `root` and `call_docs` so that write lock can be held for as short as
 /// time as possible and for consistency with the `execute` function.
 ///
+/// Citations in the document are rendered, along with its list of references, using the
+/// citation style specified for the document or its project (see [`citation_style`]).
+///
 /// # Arguments
 ///
 /// - `path`: The path of the document to be compiled
 ///
+/// - `project`: The project of the document to be compiled
+///
 /// - `root`: The root node to be compiled
 ///
 /// - `call_docs`: The [`CallDocuments`] to which documents that a `Call`ed by this one will be added
@@ -30,16 +37,26 @@ use crate::{
 /// - `patch_sender`: A [`Patch`] channel sender to send patches describing the changes to
 pub async fn assemble(
     path: &Path,
+    project: &Path,
     root: &Arc<RwLock<Node>>,
     call_docs: &Arc<RwLock<CallDocuments>>,
     patch_sender: &UnboundedSender<PatchRequest>,
 ) -> Result<AddressMap> {
+    let references = match &*root.read().await {
+        Node::Article(Article {
+            references: Some(references),
+            ..
+        }) => references.clone(),
+        _ => Vec::new(),
+    };
+
     let mut address = Address::default();
     let mut context = AssembleContext {
         path: path.into(),
         call_docs: call_docs.clone(),
         address_map: AddressMap::default(),
         ids: HashMap::default(),
+        citations: Citations::new(&references, citation_style(path, project)),
         patches: Vec::default(),
     };
     root.write()
@@ -47,6 +64,18 @@ pub async fn assemble(
         .assemble(&mut address, &mut context)
         .await?;
 
+    // Render the list of references
+    if !references.is_empty() {
+        let rendered = context.citations.references(&references);
+        if rendered != references {
+            context.patches.push(diff_address(
+                Address::from("references"),
+                &references,
+                &rendered,
+            ));
+        }
+    }
+
     send_patches(patch_sender, context.patches, When::Never);
 
     Ok(context.address_map)
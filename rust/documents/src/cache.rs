@@ -0,0 +1,503 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use common::{
+    dirs,
+    eyre::Result,
+    serde::{Deserialize, Serialize},
+    serde_json,
+    serde_with::skip_serializing_none,
+    tracing,
+};
+use graph::Plan;
+use graph_triples::{Resource, ResourceInfo};
+use hash_utils::str_sha256_hex;
+use kernels::KernelSelector;
+use stencila_schema::{
+    CodeChunk, CodeError, CodeExpression, Cord, Date, ExecuteAuto, ExecuteRequired, ExecuteStatus,
+    Node, Number,
+};
+
+/// An on-disk cache of the results of executing code nodes
+///
+/// Entries are keyed by the semantic digest of the node's code, the digest of its upstream
+/// dependencies and the kernel selector. Because those digests are stable across sessions,
+/// the results of executing a node in one session can be restored in another, provided that
+/// neither the node's code, nor any of its dependencies (including files), have changed.
+#[derive(Debug, Clone)]
+pub struct ExecuteCache {
+    /// The directory that cache entries are stored in
+    dir: PathBuf,
+
+    /// Whether to restore results from the cache (if `false` results are only stored)
+    restore: bool,
+}
+
+impl Default for ExecuteCache {
+    fn default() -> Self {
+        Self::new(Self::default_dir())
+    }
+}
+
+impl ExecuteCache {
+    /// Create a new execution cache in a directory
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, restore: true }
+    }
+
+    /// Get the default directory of the execution cache
+    ///
+    /// Uses the `STENCILA_EXECUTE_CACHE` environment variable, if set, falling back
+    /// to a `stencila/execute` folder in the user's cache directory.
+    pub fn default_dir() -> PathBuf {
+        match env::var("STENCILA_EXECUTE_CACHE") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(..) => dirs::cache_dir()
+                .unwrap_or_else(|| env::current_dir().unwrap_or_default())
+                .join("stencila")
+                .join("execute"),
+        }
+    }
+
+    /// Only store results in the cache, do not restore them
+    ///
+    /// Used when a user explicitly requests that a node be executed.
+    pub fn store_only(self) -> Self {
+        Self {
+            restore: false,
+            ..self
+        }
+    }
+
+    /// Generate the cache key for a task
+    ///
+    /// Returns `None` if the results of the task should not be cached, i.e. it is not a `CodeChunk`
+    /// or `CodeExpression`, has not been compiled, or is tagged as `@autorun always` (which
+    /// indicates that it is non-deterministic).
+    pub fn key(resource_info: &ResourceInfo, kernel_selector: &KernelSelector) -> Option<String> {
+        let kind = match &resource_info.resource {
+            Resource::Code(code) if code.kind == "CodeChunk" || code.kind == "CodeExpression" => {
+                &code.kind
+            }
+            _ => return None,
+        };
+
+        if matches!(resource_info.execute_auto, Some(ExecuteAuto::Always)) {
+            return None;
+        }
+
+        let digest = resource_info.compile_digest.as_ref()?;
+        let semantic_digest = match digest.semantic_digest {
+            0 => digest.content_digest,
+            digest => digest,
+        };
+
+        let key = [
+            kind.as_str(),
+            &semantic_digest.to_string(),
+            &digest.dependencies_digest.to_string(),
+            &kernel_selector.to_string(),
+        ]
+        .join(":");
+        Some(str_sha256_hex(&key))
+    }
+
+    /// Get the path of the cache entry for a key
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join([key, ".json"].concat())
+    }
+
+    /// Get an entry from the cache
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        if !path.exists() {
+            return None;
+        }
+        match fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|error| error.to_string()))
+        {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                tracing::warn!(
+                    "While reading execution cache entry `{}`: {}",
+                    path.display(),
+                    error
+                );
+                None
+            }
+        }
+    }
+
+    /// Store the results of executing a node in the cache
+    ///
+    /// Only nodes that succeeded are stored.
+    pub fn put(&self, key: &str, node: &Node) -> Result<()> {
+        let entry = match CacheEntry::from_node(node) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), serde_json::to_string(&entry)?)?;
+
+        Ok(())
+    }
+
+    /// Get the entries that can be restored for the tasks in a plan
+    ///
+    /// Returns the cache keys for all tasks in the plan (to be able to store the
+    /// results of those that are executed) and the entries for those that can be
+    /// restored. Because restoring a node does not update the state of its kernel
+    /// (e.g. variables that it assigns), a node is only restored if none of the nodes
+    /// in the plan that depend upon it, directly or indirectly, need to be executed.
+    pub fn restorable(
+        &self,
+        plan: &Plan,
+    ) -> (HashMap<Resource, String>, HashMap<Resource, CacheEntry>) {
+        let tasks: Vec<&ResourceInfo> = plan
+            .stages
+            .iter()
+            .flat_map(|stage| stage.tasks.iter())
+            .map(|task| &task.resource_info)
+            .collect();
+
+        let mut keys = HashMap::new();
+        let mut hits = HashMap::new();
+        for task in plan.stages.iter().flat_map(|stage| stage.tasks.iter()) {
+            let resource_info = &task.resource_info;
+            let resource = &resource_info.resource;
+
+            let key = match Self::key(resource_info, &task.kernel_selector) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            if let Some(entry) = self.restore.then(|| self.get(&key)).flatten() {
+                hits.insert(resource.clone(), entry);
+            }
+
+            keys.insert(resource.clone(), key);
+        }
+
+        // Walk the plan in reverse, adding the dependencies of every task that will be
+        // executed to those that are required, and removing any required tasks from those
+        // that can be restored (so that they are executed and their dependencies become required),
+        // until there are no more changes.
+        let mut required: HashSet<Resource> = HashSet::new();
+        loop {
+            let mut changed = false;
+            for resource_info in tasks.iter().rev() {
+                let resource = &resource_info.resource;
+                if hits.contains_key(resource) {
+                    if !required.contains(resource) {
+                        continue;
+                    }
+                    hits.remove(resource);
+                }
+                for dependency in resource_info.dependencies.iter().flatten() {
+                    changed |= required.insert(dependency.clone());
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        (keys, hits)
+    }
+
+    /// Get the number of entries in, and total size of, the cache
+    pub fn summary(&self) -> Result<(usize, u64)> {
+        let mut count = 0;
+        let mut size = 0;
+        for (.., metadata) in self.entries()? {
+            count += 1;
+            size += metadata.len();
+        }
+        Ok((count, size))
+    }
+
+    /// Remove entries from the cache
+    ///
+    /// If `max_age` is `Some` then only entries last modified longer ago than that will be
+    /// removed, otherwise all entries are removed. Returns the number of entries removed
+    /// and their total size.
+    pub fn prune(&self, max_age: Option<Duration>) -> Result<(usize, u64)> {
+        let now = SystemTime::now();
+        let mut count = 0;
+        let mut size = 0;
+        for (path, metadata) in self.entries()? {
+            if let Some(max_age) = max_age {
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .unwrap_or_default();
+                if age <= max_age {
+                    continue;
+                }
+            }
+            fs::remove_file(&path)?;
+            count += 1;
+            size += metadata.len();
+        }
+        Ok((count, size))
+    }
+
+    /// Get the paths and metadata of the entries in the cache
+    fn entries(&self) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                entries.push((path, metadata));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Get the directory of the cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// An entry in the execution cache
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct CacheEntry {
+    /// The outputs of the node (for a `CodeExpression` there will be at most one)
+    outputs: Option<Vec<Node>>,
+
+    /// Any errors, or warnings, from the execution of the node
+    errors: Option<Vec<CodeError>>,
+
+    /// When the node was executed
+    execute_ended: Option<Date>,
+
+    /// The duration of the execution of the node in seconds
+    execute_duration: Option<f64>,
+}
+
+impl CacheEntry {
+    /// Create a cache entry from a node that has been executed
+    ///
+    /// Returns `None` if the node is not a `CodeChunk` or `CodeExpression`, or did not succeed.
+    fn from_node(node: &Node) -> Option<Self> {
+        match node {
+            Node::CodeChunk(CodeChunk {
+                execute_status: Some(ExecuteStatus::Succeeded),
+                outputs,
+                errors,
+                execute_ended,
+                execute_duration,
+                ..
+            }) => Some(Self {
+                outputs: outputs.clone(),
+                errors: errors.clone(),
+                execute_ended: execute_ended.as_deref().cloned(),
+                execute_duration: execute_duration.as_ref().map(|duration| duration.0),
+            }),
+            Node::CodeExpression(CodeExpression {
+                execute_status: Some(ExecuteStatus::Succeeded),
+                output,
+                errors,
+                execute_ended,
+                execute_duration,
+                ..
+            }) => Some(Self {
+                outputs: output.as_deref().map(|output| vec![output.clone()]),
+                errors: errors.clone(),
+                execute_ended: execute_ended.as_deref().cloned(),
+                execute_duration: execute_duration.as_ref().map(|duration| duration.0),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Restore the results of a previous execution to a node
+    ///
+    /// The node's `execute_digest` is only updated if the node is pure (has no side effects).
+    /// For impure nodes, the state of the kernel does not reflect the restored execution, so
+    /// the node is left stale to ensure it is executed if a node that depends on it is run.
+    pub fn restore(self, node: &mut Node, resource_info: &ResourceInfo) {
+        let digest = resource_info
+            .compile_digest
+            .as_ref()
+            .map(|digest| Box::new(Cord(digest.to_string())));
+        let pure = resource_info.is_pure();
+
+        macro_rules! restore {
+            ($node:expr) => {
+                $node.compile_digest = digest.clone();
+                if pure {
+                    $node.execute_digest = digest;
+                }
+                $node.execute_required = Some(ExecuteRequired::No);
+                $node.execute_status = Some(ExecuteStatus::Succeeded);
+                $node.execute_ended = self.execute_ended.map(Box::new);
+                $node.execute_duration = self.execute_duration.map(Number);
+                $node.errors = self.errors;
+            };
+        }
+
+        match node {
+            Node::CodeChunk(chunk) => {
+                restore!(chunk);
+                chunk.outputs = self.outputs;
+            }
+            Node::CodeExpression(expr) => {
+                restore!(expr);
+                expr.output = self
+                    .outputs
+                    .and_then(|outputs| outputs.into_iter().next())
+                    .map(Box::new);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::tempfile;
+    use graph_triples::resources::{self, ResourceDigest};
+
+    #[test]
+    fn put_get_prune() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = ExecuteCache::new(dir.path().to_path_buf());
+
+        let mut resource_info = ResourceInfo::default(resources::code(
+            Path::new("doc.md"),
+            "cc-1",
+            "CodeChunk",
+            Some("Python".to_string()),
+        ));
+        let selector = KernelSelector::default();
+
+        // Not cacheable until it has been compiled
+        assert!(ExecuteCache::key(&resource_info, &selector).is_none());
+        resource_info.compile_digest = Some(ResourceDigest::from_strings("a = 1", None));
+        let key = ExecuteCache::key(&resource_info, &selector).expect("Should have key");
+        assert!(cache.get(&key).is_none());
+
+        // Only nodes that succeeded are stored
+        let mut chunk = CodeChunk {
+            execute_status: Some(ExecuteStatus::Failed),
+            outputs: Some(vec![Node::Integer(1)]),
+            ..Default::default()
+        };
+        cache.put(&key, &Node::CodeChunk(chunk.clone()))?;
+        assert!(cache.get(&key).is_none());
+
+        chunk.execute_status = Some(ExecuteStatus::Succeeded);
+        cache.put(&key, &Node::CodeChunk(chunk))?;
+        let entry = cache.get(&key).expect("Should have entry");
+
+        let mut restored = Node::CodeChunk(CodeChunk::default());
+        entry.restore(&mut restored, &resource_info);
+        match restored {
+            Node::CodeChunk(chunk) => {
+                assert_eq!(chunk.outputs, Some(vec![Node::Integer(1)]));
+                assert_eq!(chunk.execute_status, Some(ExecuteStatus::Succeeded));
+            }
+            _ => unreachable!(),
+        }
+
+        // A change in the dependencies changes the key
+        let mut changed = resource_info.clone();
+        if let Some(digest) = changed.compile_digest.as_mut() {
+            digest.dependencies_digest = 42;
+        }
+        assert_ne!(ExecuteCache::key(&changed, &selector), Some(key));
+
+        assert_eq!(cache.summary()?.0, 1);
+        assert_eq!(cache.prune(Some(Duration::from_secs(3600)))?.0, 0);
+        assert_eq!(cache.prune(None)?.0, 1);
+        assert_eq!(cache.summary()?.0, 0);
+
+        Ok(())
+    }
+    #[test]
+    fn restorable_transitive() -> Result<()> {
+        use graph::{PlanStage, PlanTask};
+
+        let dir = tempfile::tempdir()?;
+        let cache = ExecuteCache::new(dir.path().to_path_buf());
+        let selector = KernelSelector::default();
+
+        // A chain of three chunks where `c` depends on `b` which depends on `a`
+        let chunk = |id: &str, code: &str, dependencies: Vec<&ResourceInfo>| {
+            let mut resource_info = ResourceInfo::default(resources::code(
+                Path::new("doc.md"),
+                id,
+                "CodeChunk",
+                Some("Python".to_string()),
+            ));
+            resource_info.compile_digest = Some(ResourceDigest::from_strings(code, None));
+            resource_info.dependencies = Some(
+                dependencies
+                    .iter()
+                    .map(|dependency| dependency.resource.clone())
+                    .collect(),
+            );
+            resource_info
+        };
+        let a = chunk("cc-a", "a = 1", vec![]);
+        let b = chunk("cc-b", "b = a + 1", vec![&a]);
+        let c = chunk("cc-c", "c = b + 1", vec![&b]);
+
+        // Only `a` and `b` have entries in the cache
+        let succeeded = Node::CodeChunk(CodeChunk {
+            execute_status: Some(ExecuteStatus::Succeeded),
+            ..Default::default()
+        });
+        for resource_info in [&a, &b] {
+            let key = ExecuteCache::key(resource_info, &selector).expect("Should have key");
+            cache.put(&key, &succeeded)?;
+        }
+
+        let plan = |resource_infos: Vec<&ResourceInfo>| Plan {
+            stages: resource_infos
+                .into_iter()
+                .map(|resource_info| PlanStage {
+                    tasks: vec![PlanTask {
+                        resource_info: resource_info.clone(),
+                        kernel_selector: selector.clone(),
+                        is_fork: false,
+                        estimated_duration: None,
+                    }],
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        // Without `c`, both `a` and `b` can be restored
+        let (keys, hits) = cache.restorable(&plan(vec![&a, &b]));
+        assert_eq!(keys.len(), 2);
+        assert!(hits.contains_key(&a.resource));
+        assert!(hits.contains_key(&b.resource));
+
+        // Because `c` needs to be executed, so does `b`, and therefore so does `a`
+        let (keys, hits) = cache.restorable(&plan(vec![&a, &b, &c]));
+        assert_eq!(keys.len(), 3);
+        assert!(hits.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,680 @@
+//! Rendering of in-text citations and reference lists
+//!
+//! References are converted to CSL-JSON items (by `codec-csl`) and then rendered as plain
+//! text using a CSL style. The rendered text is stored in the document (in the `content` of
+//! `Cite` nodes and in the `text` of each reference) so that it is available to all encoders
+//! without them needing to know about citation styles.
+
+use std::{fs, path::Path};
+
+use codec_csl::{
+    style::{CiteItem, CiteMode, Style},
+    CslCodec,
+};
+use common::{once_cell::sync::Lazy, regex::Regex, serde_json, serde_yaml, tracing};
+use stencila_schema::{
+    Article, Cite, CiteCitationMode, CitePageEnd, CitePageStart, CreativeWork,
+    CreativeWorkReferences, CreativeWorkTypes,
+};
+
+/// The name of the property used to specify the citation style of a document or project
+const STYLE_PROPERTY: &str = "citationStyle";
+
+/// Get the citation style for a document
+///
+/// The style is taken from the `citationStyle` property of the document (e.g. in the YAML
+/// header of a Markdown document) or, if that is not specified, from the `citationStyle`
+/// property of the project's `project.json` file. The property can be the name of one of
+/// the bundled styles (e.g. `vancouver`) or the path of a CSL file, relative to the document
+/// or project. Falls back to the default style (APA) if neither is specified, or if the
+/// specified style can not be loaded.
+pub fn citation_style(path: &Path, project: &Path) -> Style {
+    let document = document_style(path).map(|name| {
+        let dir = path.parent().unwrap_or(project).to_path_buf();
+        (name, dir)
+    });
+    let project = project_style(project).map(|name| (name, project.to_path_buf()));
+
+    if let Some((name, dir)) = document.or(project) {
+        let style = if name.to_lowercase().ends_with(".csl") {
+            Style::read(&dir.join(&name))
+        } else {
+            Style::bundled(&name.to_lowercase())
+        };
+        match style {
+            Ok(style) => return style,
+            Err(error) => tracing::warn!(
+                "Unable to load citation style `{}`; using default: {}",
+                name,
+                error
+            ),
+        }
+    }
+
+    Style::default()
+}
+
+/// Get the citation style specified in a document, if any
+///
+/// For JSON and YAML documents, the style is a top level property. For other formats, it is
+/// a property in the YAML front matter (if any) of the document.
+fn document_style(path: &Path) -> Option<String> {
+    static FRONTMATTER: Lazy<Regex> =
+        Lazy::new(|| Regex::new("^-{3,}((.|\\n)*)?\\n-{3,}").expect("Unable to create regex"));
+
+    let content = fs::read_to_string(path).ok()?;
+    let config: serde_json::Value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&content).ok()?,
+        Some("yaml" | "yml") => serde_yaml::from_str(&content).ok()?,
+        _ => serde_yaml::from_str(FRONTMATTER.captures(&content)?.get(1)?.as_str()).ok()?,
+    };
+    config
+        .get(STYLE_PROPERTY)
+        .and_then(|style| style.as_str())
+        .map(|style| style.to_string())
+}
+
+/// Get the citation style specified for a project, if any
+///
+/// As for other project settings, the `project.json` file in the project folder takes
+/// precedence over the one in the project's `.stencila` folder.
+fn project_style(project: &Path) -> Option<String> {
+    [
+        project.join("project.json"),
+        project.join(".stencila").join("project.json"),
+    ]
+    .iter()
+    .find_map(|file| {
+        let json = fs::read_to_string(file).ok()?;
+        let config: serde_json::Value = serde_json::from_str(&json).ok()?;
+        config
+            .get(STYLE_PROPERTY)
+            .and_then(|style| style.as_str())
+            .map(|style| style.to_string())
+    })
+}
+
+/// One of the references of a document
+#[derive(Debug)]
+struct Reference {
+    /// The `id` of the referenced work, if any
+    id: Option<String>,
+
+    /// The CSL-JSON item for the reference
+    item: serde_json::Value,
+
+    /// The text of the reference, if it is unstructured (e.g. just a string)
+    literal: Option<String>,
+}
+
+/// The result of rendering a `Cite`: its text and any errors (e.g. if it could not be resolved)
+pub type Rendered = (String, Option<Vec<String>>);
+
+/// The references of a document and the style used to render citations of them
+#[derive(Debug, Default)]
+pub struct Citations {
+    /// The citation style
+    style: Style,
+
+    /// The references of the document, in the order they appear in the document
+    references: Vec<Reference>,
+
+    /// The order of the references in the reference list, if the style sorts it
+    sorted: Option<Vec<usize>>,
+
+    /// The indices of the references that have been cited, in the order they were first cited
+    cited: Vec<usize>,
+}
+
+impl Citations {
+    /// Create a new set of citations from the `references` of a document
+    pub fn new(references: &[CreativeWorkReferences], style: Style) -> Self {
+        let references: Vec<Reference> = references
+            .iter()
+            .map(|reference| {
+                let (id, literal) = match reference {
+                    CreativeWorkReferences::String(string) => (None, Some(string.clone())),
+                    CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(
+                        Article {
+                            id,
+                            title,
+                            authors,
+                            text,
+                            ..
+                        },
+                    ))
+                    | CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                        CreativeWork {
+                            id,
+                            title,
+                            authors,
+                            text,
+                            ..
+                        },
+                    )) => {
+                        // A work with text, but no title or authors, is an unstructured reference
+                        let literal = match (title, authors) {
+                            (None, None) => text.as_deref().cloned(),
+                            _ => None,
+                        };
+                        (id.as_deref().cloned(), literal)
+                    }
+                    _ => (None, None),
+                };
+                let item = match &literal {
+                    Some(literal) => literal_item(literal),
+                    None => CslCodec::to_json_reference(reference)
+                        .unwrap_or_else(|| serde_json::json!({})),
+                };
+                Reference { id, item, literal }
+            })
+            .collect();
+
+        let items: Vec<&serde_json::Value> =
+            references.iter().map(|reference| &reference.item).collect();
+        let sorted = style.sort(&items);
+
+        Citations {
+            style,
+            references,
+            sorted,
+            cited: Vec::new(),
+        }
+    }
+
+    /// Render a `Cite` node as an in-text citation
+    ///
+    /// If the target of the citation can not be resolved, the target itself is used in
+    /// place of the rendered citation and an error is returned.
+    pub fn cite(&mut self, cite: &Cite) -> Rendered {
+        let (open, _, close) = self.style.citation_affixes();
+        let (open, close) = (open.to_string(), close.to_string());
+
+        let index = match self.resolve(cite) {
+            Some(index) => index,
+            None => {
+                return (
+                    [open, cite.target.clone(), close].concat(),
+                    unresolved(cite),
+                )
+            }
+        };
+
+        let citation = match &cite.citation_mode {
+            Some(CiteCitationMode::NarrativeAuthor) => {
+                self.render(index, cite, CiteMode::AuthorOnly)
+            }
+            Some(CiteCitationMode::NarrativeYear) => {
+                self.render(index, cite, CiteMode::SuppressAuthor)
+            }
+            Some(CiteCitationMode::Narrative) => [
+                self.render(index, cite, CiteMode::AuthorOnly),
+                " ".to_string(),
+                open,
+                self.render(index, cite, CiteMode::SuppressAuthor),
+                close,
+            ]
+            .concat(),
+            Some(CiteCitationMode::suppressAuthor) => [
+                open,
+                self.render(index, cite, CiteMode::SuppressAuthor),
+                close,
+            ]
+            .concat(),
+            _ => [open, self.part(index, cite), close].concat(),
+        };
+
+        (affix(cite, citation), None)
+    }
+
+    /// Render the items of a `CiteGroup` node as a single in-text citation
+    ///
+    /// Returns the rendered text for each item. These are designed to be concatenated, so the
+    /// first item includes the opening delimiter, subsequent items the separator, and the last
+    /// item the closing delimiter e.g. `["(Smith, 2020", "; Jones, 2021)"]`.
+    pub fn cite_group(&mut self, items: &[Cite]) -> Vec<Rendered> {
+        let (open, separator, close) = self.style.citation_affixes();
+        let (open, separator, close) = (open.to_string(), separator.to_string(), close.to_string());
+
+        let last = items.len().saturating_sub(1);
+        items
+            .iter()
+            .enumerate()
+            .map(|(position, cite)| {
+                let (part, errors) = match self.resolve(cite) {
+                    Some(index) => (self.part(index, cite), None),
+                    None => (cite.target.clone(), unresolved(cite)),
+                };
+                let text = [
+                    if position == 0 {
+                        open.as_str()
+                    } else {
+                        separator.as_str()
+                    },
+                    part.as_str(),
+                    if position == last { close.as_str() } else { "" },
+                ]
+                .concat();
+                (text, errors)
+            })
+            .collect()
+    }
+
+    /// Render the references list of a document
+    ///
+    /// Returns a copy of `references`, in the order of the reference list, with the `text` of
+    /// each `Article` and `CreativeWork` set to its rendered entry in the list. If the style
+    /// sorts the reference list, that order is used. Otherwise, references are listed in the
+    /// order that they were first cited, followed by those not cited.
+    ///
+    /// If the order is changed, references that have no `id` (and so are cited by position
+    /// e.g. `ref3`) are given one, so that existing citations of them continue to resolve.
+    /// String references are converted to a `CreativeWork` for this purpose.
+    pub fn references(&self, references: &[CreativeWorkReferences]) -> Vec<CreativeWorkReferences> {
+        let order = self.order();
+        let reordered = order
+            .iter()
+            .enumerate()
+            .any(|(position, index)| position != *index);
+
+        order
+            .iter()
+            .enumerate()
+            .filter_map(|(position, index)| {
+                let reference = references.get(*index)?;
+                let text = match &self.references[*index].literal {
+                    Some(literal) => literal.clone(),
+                    None => self
+                        .style
+                        .entry(&self.references[*index].item, position + 1)
+                        .unwrap_or_default(),
+                };
+                let text = Some(Box::new(text));
+                let id = |id: &Option<Box<String>>| match id {
+                    Some(id) => Some(id.clone()),
+                    None if reordered => Some(Box::new(format!("ref{}", index + 1))),
+                    None => None,
+                };
+                let reference = match reference {
+                    CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(
+                        article,
+                    )) => CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(
+                        Article {
+                            id: id(&article.id),
+                            text,
+                            ..article.clone()
+                        },
+                    )),
+                    CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                        work,
+                    )) => CreativeWorkReferences::CreativeWorkTypes(
+                        CreativeWorkTypes::CreativeWork(CreativeWork {
+                            id: id(&work.id),
+                            text,
+                            ..work.clone()
+                        }),
+                    ),
+                    CreativeWorkReferences::String(..) if reordered => {
+                        CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                            CreativeWork {
+                                id: id(&None),
+                                text,
+                                ..Default::default()
+                            },
+                        ))
+                    }
+                    _ => reference.clone(),
+                };
+                Some(reference)
+            })
+            .collect()
+    }
+
+    /// Resolve the target of a `Cite` to the index of one of the references
+    ///
+    /// Matches on the `id` of the reference or, for references without an `id`, on
+    /// their position in the list (e.g. `ref3`). Records the reference as cited so
+    /// that numeric styles can number references in the order they are first cited.
+    fn resolve(&mut self, cite: &Cite) -> Option<usize> {
+        let index = self
+            .references
+            .iter()
+            .enumerate()
+            .position(|(index, reference)| match &reference.id {
+                Some(id) => *id == cite.target,
+                None => cite.target == format!("ref{}", index + 1),
+            })?;
+        if !self.cited.contains(&index) {
+            self.cited.push(index);
+        }
+        Some(index)
+    }
+
+    /// Get the order of the references in the reference list
+    fn order(&self) -> Vec<usize> {
+        match &self.sorted {
+            Some(sorted) => sorted.clone(),
+            None => self
+                .cited
+                .iter()
+                .copied()
+                .chain((0..self.references.len()).filter(|index| !self.cited.contains(index)))
+                .collect(),
+        }
+    }
+
+    /// Get the number of a reference (its position in the reference list)
+    fn number(&self, index: usize) -> usize {
+        self.order()
+            .iter()
+            .position(|other| *other == index)
+            .map_or(0, |position| position + 1)
+    }
+
+    /// Render a reference using the citation layout of the style
+    fn render(&self, index: usize, cite: &Cite, mode: CiteMode) -> String {
+        self.style.cite(&CiteItem {
+            item: &self.references[index].item,
+            number: self.number(index),
+            locator: locator(cite),
+            mode,
+        })
+    }
+
+    /// Render the part of an in-text citation for a single reference e.g. `Smith, 2020, p. 5`
+    fn part(&self, index: usize, cite: &Cite) -> String {
+        let part = self.render(index, cite, CiteMode::Normal);
+        let part = match cite.citation_prefix.as_deref() {
+            Some(prefix) => [prefix, " ", &part].concat(),
+            None => part,
+        };
+        match cite.citation_suffix.as_deref() {
+            Some(suffix) => [part.as_str(), suffix].concat(),
+            None => part,
+        }
+    }
+}
+
+/// Create the errors for a `Cite` whose target could not be resolved
+fn unresolved(cite: &Cite) -> Option<Vec<String>> {
+    Some(vec![format!(
+        "Unable to resolve citation target `{}` to one of the document's references",
+        cite.target
+    )])
+}
+
+/// Add the prefix and suffix of a `Cite` to a narrative citation
+fn affix(cite: &Cite, citation: String) -> String {
+    if !matches!(
+        cite.citation_mode,
+        Some(CiteCitationMode::Narrative)
+            | Some(CiteCitationMode::NarrativeAuthor)
+            | Some(CiteCitationMode::NarrativeYear)
+    ) {
+        // Parenthetical citations have the prefix and suffix within the delimiters
+        return citation;
+    }
+    let citation = match cite.citation_prefix.as_deref() {
+        Some(prefix) => [prefix, " ", &citation].concat(),
+        None => citation,
+    };
+    match cite.citation_suffix.as_deref() {
+        Some(suffix) => [citation.as_str(), suffix].concat(),
+        None => citation,
+    }
+}
+
+/// Get the locator (i.e. pages) of a `Cite` e.g. `5` or `5-7`
+fn locator(cite: &Cite) -> Option<String> {
+    let start = cite.page_start.as_deref().map(|start| match start {
+        CitePageStart::Integer(page) => page.to_string(),
+        CitePageStart::String(page) => page.clone(),
+    });
+    let end = cite.page_end.as_deref().map(|end| match end {
+        CitePageEnd::Integer(page) => page.to_string(),
+        CitePageEnd::String(page) => page.clone(),
+    });
+    match (start, end, cite.pagination.as_deref()) {
+        (Some(start), Some(end), _) => Some([start, end].join("-")),
+        (Some(start), None, _) => Some(start),
+        (None, _, Some(pagination)) => Some(pagination.clone()),
+        _ => None,
+    }
+}
+
+/// Create a CSL item for an unstructured reference
+///
+/// Assumes that the reference starts with the author (up to the first comma, parenthesis
+/// or period) and that the first four digit number is the year it was issued.
+fn literal_item(literal: &str) -> serde_json::Value {
+    let author = literal
+        .split([',', '(', '.'])
+        .next()
+        .map(|author| author.trim())
+        .unwrap_or_default();
+    let year = literal
+        .split(|chr: char| !chr.is_ascii_digit())
+        .find(|digits| digits.len() == 4)
+        .and_then(|year| year.parse::<u32>().ok());
+
+    let mut item = serde_json::json!({
+        "type": "document",
+        "title": literal,
+    });
+    if !author.is_empty() {
+        item["author"] = serde_json::json!([{ "literal": author }]);
+    }
+    if let Some(year) = year {
+        item["issued"] = serde_json::json!({ "date-parts": [[year]] });
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{eyre::Result, tempfile};
+    use stencila_schema::{CreativeWorkAuthors, CreativeWorkTitle, Date, Person};
+    use test_utils::pretty_assertions::assert_eq;
+
+    /// Create a list of references for testing
+    fn fixtures() -> Vec<CreativeWorkReferences> {
+        let person = |given: &str, family: &str| {
+            CreativeWorkAuthors::Person(Person {
+                given_names: Some(vec![given.to_string()]),
+                family_names: Some(vec![family.to_string()]),
+                ..Default::default()
+            })
+        };
+        vec![
+            CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(Article {
+                id: Some(Box::new("smith2020".to_string())),
+                authors: Some(vec![person("Jane", "Smith"), person("Kay", "Jones")]),
+                title: Some(Box::new(CreativeWorkTitle::String(
+                    "A study of things".to_string(),
+                ))),
+                date_published: Some(Box::new(Date {
+                    value: "2020-03-01".to_string(),
+                    ..Default::default()
+                })),
+                genre: Some(vec!["book".to_string()]),
+                ..Default::default()
+            })),
+            CreativeWorkReferences::String(
+                "Lee, M. (2019). An unstructured reference.".to_string(),
+            ),
+        ]
+    }
+
+    /// Get the text of each of a list of references
+    fn texts(references: &[CreativeWorkReferences]) -> Vec<String> {
+        references
+            .iter()
+            .map(|reference| match reference {
+                CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::Article(
+                    Article { text, .. },
+                ))
+                | CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                    CreativeWork { text, .. },
+                )) => text.as_deref().cloned().unwrap_or_default(),
+                CreativeWorkReferences::String(string) => string.clone(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cite() -> Result<()> {
+        let smith = Cite {
+            target: "smith2020".to_string(),
+            ..Default::default()
+        };
+        let lee = Cite {
+            target: "ref2".to_string(),
+            ..Default::default()
+        };
+        let narrative = Cite {
+            citation_mode: Some(CiteCitationMode::Narrative),
+            page_start: Some(Box::new(CitePageStart::Integer(5))),
+            ..smith.clone()
+        };
+        let missing = Cite {
+            target: "missing".to_string(),
+            ..Default::default()
+        };
+        let text = |rendered: Vec<Rendered>| -> Vec<String> {
+            rendered.into_iter().map(|(text, ..)| text).collect()
+        };
+
+        let mut apa = Citations::new(&fixtures(), Style::bundled("apa")?);
+        assert_eq!(
+            apa.cite(&smith),
+            ("(Smith & Jones, 2020)".to_string(), None)
+        );
+        assert_eq!(apa.cite(&narrative).0, "Smith & Jones (2020, p. 5)");
+        assert_eq!(
+            text(apa.cite_group(&[smith.clone(), lee.clone()])),
+            vec!["(Smith & Jones, 2020", "; Lee, 2019)"]
+        );
+
+        let (text_, errors) = apa.cite(&missing);
+        assert_eq!(text_, "(missing)");
+        assert_eq!(
+            errors,
+            Some(vec![
+                "Unable to resolve citation target `missing` to one of the document's references"
+                    .to_string()
+            ])
+        );
+
+        let mut chicago = Citations::new(&fixtures(), Style::bundled("chicago-author-date")?);
+        assert_eq!(chicago.cite(&smith).0, "(Smith and Jones 2020)");
+        assert_eq!(chicago.cite(&narrative).0, "Smith and Jones (2020, 5)");
+
+        // Numbered in order of first citation
+        let mut vancouver = Citations::new(&fixtures(), Style::bundled("vancouver")?);
+        assert_eq!(vancouver.cite(&lee).0, "[1]");
+        assert_eq!(vancouver.cite(&smith).0, "[2]");
+        assert_eq!(
+            text(vancouver.cite_group(&[smith, lee, missing])),
+            vec!["[2", ",1", ",missing]"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reference_list() -> Result<()> {
+        // Author-date styles sort the list by author
+        let apa = Citations::new(&fixtures(), Style::bundled("apa")?).references(&fixtures());
+        assert_eq!(
+            texts(&apa),
+            vec![
+                "Lee, M. (2019). An unstructured reference.",
+                "Smith, J., & Jones, K. (2020). A study of things."
+            ]
+        );
+        // ...and references without an id are given one so they can still be cited
+        match &apa[0] {
+            CreativeWorkReferences::CreativeWorkTypes(CreativeWorkTypes::CreativeWork(
+                CreativeWork { id, .. },
+            )) => assert_eq!(id.as_deref(), Some(&"ref2".to_string())),
+            _ => panic!("Expected a `CreativeWork`"),
+        }
+
+        let chicago = Citations::new(&fixtures(), Style::bundled("chicago-author-date")?)
+            .references(&fixtures());
+        assert_eq!(
+            texts(&chicago),
+            vec![
+                "Lee, M. (2019). An unstructured reference.",
+                "Smith, Jane, and Kay Jones. 2020. A study of things."
+            ]
+        );
+
+        // Numeric styles list references in the order they are first cited
+        let vancouver = Citations::new(&fixtures(), Style::bundled("vancouver")?);
+        assert_eq!(
+            texts(&vancouver.references(&fixtures())),
+            vec![
+                "1. Smith J, Jones K. A study of things. 2020.",
+                "Lee, M. (2019). An unstructured reference."
+            ]
+        );
+        let mut vancouver = vancouver;
+        vancouver.cite(&Cite {
+            target: "ref2".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(
+            texts(&vancouver.references(&fixtures())),
+            vec![
+                "Lee, M. (2019). An unstructured reference.",
+                "2. Smith J, Jones K. A study of things. 2020."
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn style_config() -> Result<()> {
+        let project = tempfile::tempdir()?;
+        let project = project.path();
+        let doc = project.join("doc.md");
+        let cite = Cite {
+            target: "smith2020".to_string(),
+            ..Default::default()
+        };
+        let render = || {
+            Citations::new(&fixtures(), citation_style(&doc, project))
+                .cite(&cite)
+                .0
+        };
+
+        // Defaults to APA
+        fs::write(&doc, "# Heading\n")?;
+        assert_eq!(render(), "(Smith & Jones, 2020)");
+
+        // Project config
+        fs::write(
+            project.join("project.json"),
+            r#"{"citationStyle": "vancouver"}"#,
+        )?;
+        assert_eq!(render(), "[1]");
+
+        // Document config takes precedence, and can be a path to a CSL file
+        fs::write(
+            project.join("style.csl"),
+            r#"<style xmlns="http://purl.org/net/xbiblio/csl" class="in-text" version="1.0">
+                <citation>
+                    <layout prefix="&lt;" suffix="&gt;"><text variable="title"/></layout>
+                </citation>
+            </style>"#,
+        )?;
+        fs::write(&doc, "---\ncitationStyle: style.csl\n---\n\n# Heading\n")?;
+        assert_eq!(render(), "<A study of things>");
+
+        Ok(())
+    }
+}
@@ -16,15 +16,14 @@ use common::{
     tokio::sync::Mutex,
     tracing,
 };
-use graph::{PlanOptions, PlanOrdering};
-use graph_triples::resources;
+use graph::PlanOrdering;
 use node_address::Address;
 use node_patch::{diff, diff_display};
 use stencila_schema::{
     EnumValidator, IntegerValidator, Node, NumberValidator, StringValidator, ValidatorTypes,
 };
 
-use crate::{document::Document, messages::When};
+use crate::{cache::ExecuteCache, document::Document, messages::When};
 
 use super::*;
 
@@ -60,10 +59,16 @@ pub enum Action {
     Params(Params),
     Run(Run_),
     Plan(Plan),
+    Lint(Lint),
     Query(Query),
     Diff(Diff),
     Merge(Merge),
+    Undo(Undo),
+    Redo(Redo),
+    History(History),
+    Revert(Revert),
     Detect(Detect),
+    Cache(Cache),
 }
 
 #[async_trait]
@@ -93,10 +98,16 @@ impl Run for Command {
             Action::Params(action) => action.run().await,
             Action::Run(action) => action.run().await,
             Action::Plan(action) => action.run().await,
+            Action::Lint(action) => action.run().await,
             Action::Query(action) => action.run().await,
             Action::Diff(action) => action.run().await,
             Action::Merge(action) => action.run().await,
+            Action::Undo(action) => action.run().await,
+            Action::Redo(action) => action.run().await,
+            Action::History(action) => action.run().await,
+            Action::Revert(action) => action.run().await,
             Action::Detect(action) => action.run().await,
+            Action::Cache(action) => action.run().await,
         }
     }
 }
@@ -173,11 +184,29 @@ pub struct Show {
     /// Some, usually large, document properties are only shown when specified with a
     /// pointer (e.g. `content` and `root`).
     pub pointer: Option<String>,
+
+    /// Show a past version of the document (see `documents history`)
+    #[clap(long)]
+    at: Option<usize>,
+
+    /// The format to show the past version of the document in
+    ///
+    /// Defaults to the format of the document.
+    #[clap(long, short)]
+    to: Option<String>,
 }
 #[async_trait]
 impl Run for Show {
     async fn run(&self) -> Result {
         let document = Document::open(&self.file.path, self.file.format.clone()).await?;
+        if let Some(version) = self.at {
+            let format = self
+                .to
+                .clone()
+                .unwrap_or_else(|| document.format.extension.clone());
+            let content = document.dump_at(version, &format).await?;
+            return result::content(&format, &content);
+        }
         if let Some(pointer) = &self.pointer {
             if pointer == "content" {
                 result::content(&document.format.extension, &document.content)
@@ -530,6 +559,14 @@ pub struct Run_ {
     /// Defaults to the number of CPUs on the machine.
     #[clap(short, long)]
     concurrency: Option<usize>,
+
+    /// Do not use the execution cache
+    ///
+    /// By default, the results of code chunks and expressions whose code and
+    /// dependencies have not changed since a previous run are restored from
+    /// the execution cache rather than being re-executed.
+    #[clap(long)]
+    no_cache: bool,
 }
 
 #[async_trait]
@@ -549,6 +586,7 @@ impl Run for Run_ {
                     self.start.clone(),
                     self.ordering,
                     self.concurrency,
+                    self.no_cache,
                 )
                 .await?;
         }
@@ -598,6 +636,14 @@ pub struct Plan {
     /// Defaults to the number of CPUs on the machine.
     #[clap(short, long)]
     concurrency: Option<usize>,
+
+    /// The format to output the plan as
+    ///
+    /// Use `mermaid` or `dot` to generate a diagram of the plan with stages as
+    /// clusters and tasks annotated with their kernel, whether they will run in
+    /// a fork, and their estimated duration.
+    #[clap(long, short, default_value = "md", possible_values = &graph::PLAN_FORMATS)]
+    to: String,
 }
 
 #[async_trait]
@@ -606,24 +652,57 @@ impl Run for Plan {
         // Open document
         let document = Document::open(&self.input, self.from.clone()).await?;
 
-        let start = self
-            .start
-            .as_ref()
-            .map(|node_id| resources::code(&document.path, node_id, "", None));
+        let plan = document
+            .plan(self.start.clone(), self.ordering, self.concurrency)
+            .await?;
 
-        let options = PlanOptions {
-            ordering: self.ordering.unwrap_or_else(PlanOptions::default_ordering),
-            max_concurrency: self
-                .concurrency
-                .unwrap_or_else(PlanOptions::default_max_concurrency),
-        };
+        let content = plan.to_format(&self.to)?;
+        result::new(&self.to, &content, &plan)
+    }
+}
 
-        let plan = {
-            let graph = document.graph.write().await;
-            graph.plan(start, None, None, Some(options)).await?
-        };
+/// Check the code in a document for problems without executing it
+///
+/// Reports syntax errors, uses of undefined symbols, assignments to symbols that
+/// are never used, and imports of modules that are not installed. Exits with an error
+/// if there are any problems so that it can be used in continuous integration.
+///
+/// Lines and columns are one-based and relative to the start of each code node.
+#[derive(Parser)]
+pub struct Lint {
+    /// The path of the document file
+    pub file: String,
+}
+
+#[async_trait]
+impl Run for Lint {
+    async fn run(&self) -> Result {
+        let mut document = Document::open(&self.file, None).await?;
+        let diagnostics = document.lint().await?;
+        if diagnostics.is_empty() {
+            return result::nothing();
+        }
 
-        result::new("md", &plan.to_markdown(), &plan)
+        let lines = diagnostics
+            .iter()
+            .map(|(node_id, diagnostic)| {
+                let (line, column, ..) = diagnostic.range;
+                format!(
+                    "{} {}:{} {}: {}",
+                    node_id,
+                    line + 1,
+                    column + 1,
+                    diagnostic.error_type,
+                    diagnostic.message
+                )
+            })
+            .join("\n");
+        eyre::bail!(
+            "Found {} problem/s in `{}`:\n{}",
+            diagnostics.len(),
+            self.file,
+            lines
+        )
     }
 }
 
@@ -774,6 +853,158 @@ impl Run for Merge {
     }
 }
 
+/// Undo the last edit to a document
+///
+/// Edits are patches to the document made by clients (e.g. a web browser),
+/// or providers, while it has been open. Because the undo history is not persisted,
+/// the document must already be open (e.g. in interactive mode, or by the server)
+/// and this command will not open it.
+#[derive(Parser)]
+pub struct Undo {
+    /// The path of the document file
+    path: String,
+}
+
+#[async_trait]
+impl Run for Undo {
+    async fn run(&self) -> Result {
+        if !opened(&self.path)
+            .await?
+            .lock()
+            .await
+            .undo(When::Soon, When::Soon)
+            .await?
+        {
+            eyre::bail!("There are no edits to undo for document `{}`", self.path)
+        }
+        result::nothing()
+    }
+}
+
+/// Redo the last undone edit to a document
+///
+/// As for `undo`, the document must already be open.
+#[derive(Parser)]
+pub struct Redo {
+    /// The path of the document file
+    path: String,
+}
+
+#[async_trait]
+impl Run for Redo {
+    async fn run(&self) -> Result {
+        if !opened(&self.path)
+            .await?
+            .lock()
+            .await
+            .redo(When::Soon, When::Soon)
+            .await?
+        {
+            eyre::bail!(
+                "There are no undone edits to redo for document `{}`",
+                self.path
+            )
+        }
+        result::nothing()
+    }
+}
+
+/// Get a document that is already open
+async fn opened(path: &str) -> eyre::Result<Arc<Mutex<Document>>> {
+    match DOCUMENTS.find(path).await? {
+        Some(id) => DOCUMENTS.get(&id).await,
+        None => eyre::bail!(
+            "Document `{}` is not open; use `documents open` (in interactive mode) to open it first",
+            path
+        ),
+    }
+}
+
+/// List the versions of a document
+///
+/// Versions are recorded in the document's history (in the `.stencila/history` folder
+/// of the project) when it is opened and each time it is patched. Use `documents show --at`
+/// to show a version and `documents revert` to revert the changes made in one.
+#[derive(Parser)]
+pub struct History {
+    #[clap(flatten)]
+    file: File,
+}
+
+/// A row in the table of document versions
+#[derive(Serialize, Table)]
+#[serde(crate = "common::serde")]
+#[table(crate = "cli_utils::cli_table")]
+struct Version {
+    #[table(title = "Version")]
+    version: usize,
+
+    #[table(title = "Time")]
+    time: String,
+
+    #[table(title = "Change")]
+    change: String,
+
+    #[table(title = "Actor")]
+    actor: String,
+
+    #[table(title = "Sequence")]
+    sequence: String,
+}
+
+#[async_trait]
+impl Run for History {
+    async fn run(&self) -> Result {
+        let document = self.file.get().await?;
+        let document = document.lock().await;
+        let history = match &document.history {
+            Some(history) => history,
+            None => eyre::bail!("Document does not have a history"),
+        };
+
+        let versions = history
+            .entries()?
+            .into_iter()
+            .map(|entry| Version {
+                version: entry.version,
+                time: entry.time.to_rfc3339(),
+                change: match &entry.patch {
+                    Some(patch) => format!("Patch ({} operations)", patch.ops.len()),
+                    None => "Snapshot".to_string(),
+                },
+                actor: entry.actor.unwrap_or_default(),
+                sequence: entry
+                    .sequence
+                    .map(|sequence| sequence.to_string())
+                    .unwrap_or_default(),
+            })
+            .collect_vec();
+        result::table(versions, Version::title())
+    }
+}
+
+/// Revert the changes made to a document in a past version
+///
+/// Applies the inverse of the patch recorded for the version to the document.
+/// The revert is itself recorded as a new version (and can be undone).
+#[derive(Parser)]
+pub struct Revert {
+    #[clap(flatten)]
+    file: File,
+
+    /// The version to revert (see `documents history`)
+    version: usize,
+}
+
+#[async_trait]
+impl Run for Revert {
+    async fn run(&self) -> Result {
+        let document = self.file.get().await?;
+        document.lock().await.revert(self.version).await?;
+        result::nothing()
+    }
+}
+
 /// Detect entities within a document
 #[derive(Parser)]
 pub struct Detect {
@@ -790,3 +1021,73 @@ impl Run for Detect {
         result::value(nodes)
     }
 }
+
+/// Manage the execution cache
+///
+/// The execution cache stores the outputs of code chunks and expressions so that,
+/// when a document is run, those whose code and dependencies have not changed
+/// do not need to be re-executed.
+#[derive(Parser)]
+pub struct Cache {
+    #[clap(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Parser)]
+enum CacheAction {
+    Show(CacheShow),
+    Prune(CachePrune),
+}
+
+#[async_trait]
+impl Run for Cache {
+    async fn run(&self) -> Result {
+        match &self.action {
+            CacheAction::Show(action) => action.run().await,
+            CacheAction::Prune(action) => action.run().await,
+        }
+    }
+}
+
+/// Show the location, number of entries, and size of the execution cache
+#[derive(Parser)]
+struct CacheShow {}
+
+#[async_trait]
+impl Run for CacheShow {
+    async fn run(&self) -> Result {
+        let cache = ExecuteCache::default();
+        let (entries, bytes) = cache.summary()?;
+        result::value(serde_json::json!({
+            "dir": cache.dir(),
+            "entries": entries,
+            "bytes": bytes
+        }))
+    }
+}
+
+/// Remove entries from the execution cache
+#[derive(Parser)]
+struct CachePrune {
+    /// Only remove entries that were created more than this number of days ago
+    ///
+    /// Defaults to removing all entries.
+    #[clap(long)]
+    older_than: Option<u64>,
+}
+
+#[async_trait]
+impl Run for CachePrune {
+    async fn run(&self) -> Result {
+        let max_age = self
+            .older_than
+            .map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60));
+        let (entries, bytes) = ExecuteCache::default().prune(max_age)?;
+        tracing::info!(
+            "Pruned {} entries ({} bytes) from execution cache",
+            entries,
+            bytes
+        );
+        result::nothing()
+    }
+}
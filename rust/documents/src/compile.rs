@@ -2,7 +2,13 @@ use std::{collections::HashMap, path::Path, sync::Arc};
 
 use common::{
     eyre::Result,
-    tokio::sync::{mpsc::UnboundedSender, RwLock},
+    tokio::{
+        self,
+        sync::{
+            mpsc::{Sender, UnboundedSender},
+            RwLock,
+        },
+    },
     tracing,
 };
 use graph::Graph;
@@ -19,7 +25,8 @@ use stencila_schema::{
 use crate::{
     document::CallDocuments,
     executable::{CompileContext, Executable},
-    messages::{PatchRequest, When},
+    lint::{check_modules, lint, merge_diagnostics},
+    messages::{CompileRequest, PatchRequest, RequestId, When},
     utils::send_patches,
 };
 
@@ -54,6 +61,11 @@ use crate::{
 ///
 /// - `patch_sender`: A [`Patch`] channel sender to send patches describing the changes to
 ///                   executed nodes
+///
+/// - `compile_sender`: A [`CompileRequest`] channel sender to request that the document be
+///                     recompiled if background checks (e.g. of whether imported modules are
+///                     installed) change its diagnostics
+#[allow(clippy::too_many_arguments)]
 pub async fn compile(
     path: &Path,
     project: &Path,
@@ -62,6 +74,7 @@ pub async fn compile(
     tag_map: &Arc<RwLock<TagMap>>,
     call_docs: &Arc<RwLock<CallDocuments>>,
     patch_sender: &UnboundedSender<PatchRequest>,
+    compile_sender: Option<&Sender<CompileRequest>>,
 ) -> Result<Graph> {
     let root = root.read().await;
     let address_map = address_map.read().await;
@@ -77,7 +90,31 @@ pub async fn compile(
         let pointer = resolve(&*root, Some(address.clone()), Some(id.clone()))?;
         pointer.compile(&mut context).await?;
     }
-    let resource_infos = context.resource_infos;
+    let mut resource_infos = context.resource_infos;
+
+    // Add diagnostics derived from the relations between resources and check any
+    // unchecked modules in the background, recompiling if that changes the diagnostics
+    let unchecked = lint(&mut resource_infos);
+    if !unchecked.is_empty() {
+        let project = project.to_path_buf();
+        let compile_sender = compile_sender.cloned();
+        tokio::spawn(async move {
+            if check_modules(&project, unchecked).await {
+                if let Some(compile_sender) = compile_sender {
+                    let request = CompileRequest::new(
+                        vec![RequestId::new()],
+                        When::Soon,
+                        When::Never,
+                        When::Never,
+                        None,
+                    );
+                    if let Err(error) = compile_sender.send(request).await {
+                        tracing::debug!("While sending compile request: {}", error);
+                    }
+                }
+            }
+        });
+    }
 
     // Update the document's global tag map with those from those collected by the compile context
     *tag_map.write().await = context.global_tags;
@@ -278,6 +315,7 @@ pub async fn compile(
                     code_dependents,
                     compile_digest,
                     execute_required,
+                    errors,
                     ..
                 })
                 | Node::CodeExpression(CodeExpression {
@@ -285,12 +323,14 @@ pub async fn compile(
                     code_dependents,
                     compile_digest,
                     execute_required,
+                    errors,
                     ..
                 }) => {
                     *code_dependencies = Some(dependencies);
                     *code_dependents = Some(dependents);
                     *compile_digest = new_compile_digest;
                     *execute_required = new_execute_required.to_owned();
+                    *errors = merge_diagnostics(errors.take(), resource_info.diagnostics.as_ref());
                 }
                 Node::Parameter(Parameter { compile_digest, .. }) => {
                     *compile_digest = new_compile_digest;
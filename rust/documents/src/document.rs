@@ -1,5 +1,5 @@
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     env, fs,
     ops::Deref,
     path::{Path, PathBuf},
@@ -19,18 +19,21 @@ use common::{
     strum::Display,
     tokio::{
         self,
-        sync::{broadcast, mpsc, Mutex, RwLock},
+        sync::{broadcast, mpsc, oneshot, Mutex, RwLock},
         task::JoinHandle,
     },
     tracing,
 };
 use events::publish;
 use formats::FormatSpec;
-use graph::{Graph, PlanOptions, PlanOrdering, PlanScope};
-use graph_triples::{resources, Relations, Resource, ResourceChange, TagMap};
+use graph::{Graph, Plan, PlanOptions, PlanOrdering, PlanScope};
+use graph_triples::{
+    resources::{self, Diagnostic},
+    Relation, Relations, Resource, ResourceChange, TagMap,
+};
 use kernels::{KernelInfos, KernelSpace, KernelSymbols};
 use node_address::{Address, AddressMap};
-use node_patch::{apply, diff, merge, Patch};
+use node_patch::{apply, diff, merge, Effect, Patch};
 use node_pointer::{resolve, resolve_mut};
 use node_reshape::reshape;
 use node_validate::Validator;
@@ -40,11 +43,16 @@ use stencila_schema::{Article, InlineContent, Node, Parameter};
 
 use crate::{
     assemble::assemble,
+    cache::ExecuteCache,
     compile::compile,
+    documents::DOCUMENTS,
+    durations::{plan_durations, ExecuteDurations},
     execute::execute,
+    history::DocumentHistory,
+    lint::check_modules,
     messages::{
-        AssembleRequest, CancelRequest, CompileRequest, ExecuteRequest, PatchRequest, RequestId,
-        Response, When, WriteRequest,
+        AssembleRequest, CancelRequest, CompileRequest, ExecuteRequest, History, PatchRequest,
+        RequestId, Response, When, WriteRequest,
     },
 };
 
@@ -197,7 +205,7 @@ pub struct Document {
     /// This is where document variables are stored and executable nodes such as
     /// `CodeChunk`s and `Parameters`s are executed.
     #[serde(skip)]
-    kernels: Arc<RwLock<KernelSpace>>,
+    pub(crate) kernels: Arc<RwLock<KernelSpace>>,
 
     /// The set of dependency relations between this document, or nodes in this document,
     /// and other resources.
@@ -233,6 +241,12 @@ pub struct Document {
     ///    completed e.g. `encoded:html`
     subscriptions: HashMap<String, HashSet<String>>,
 
+    /// The persistent history of changes to the document
+    ///
+    /// `None` for temporary documents.
+    #[serde(skip)]
+    pub(crate) history: Option<DocumentHistory>,
+
     #[serde(skip)]
     patch_request_sender: mpsc::UnboundedSender<PatchRequest>,
 
@@ -328,6 +342,8 @@ impl Document {
             .expect("Unable to get path parent")
             .to_path_buf();
 
+        let history = (!temporary).then(|| DocumentHistory::new(&project, &path));
+
         let (resource_changes_sender, mut resource_changes_receiver) =
             mpsc::channel::<ResourceChange>(100);
 
@@ -336,10 +352,14 @@ impl Document {
         let call_docs = Arc::new(RwLock::new(CallDocuments::default()));
         let tags = Arc::new(RwLock::new(TagMap::default()));
         let graph = Arc::new(RwLock::new(Graph::default()));
-        let kernels = Arc::new(RwLock::new(KernelSpace::new(
-            Some(&project),
-            Some(resource_changes_sender),
-        )));
+        let mut kernel_space = KernelSpace::new(Some(&project), Some(resource_changes_sender));
+        if let Some(sandbox) = DOCUMENTS.sandbox() {
+            // The sandbox was validated when it was set so this should not fail
+            if let Err(error) = kernel_space.set_sandbox(Some(&sandbox)) {
+                tracing::error!("While setting sandbox of document: {}", error);
+            }
+        }
+        let kernels = Arc::new(RwLock::new(kernel_space));
         let last_write = Arc::new(RwLock::new(Instant::now()));
 
         let (patch_request_sender, mut patch_request_receiver) =
@@ -368,11 +388,13 @@ impl Document {
         let compile_sender_clone = compile_request_sender.clone();
         let write_sender_clone = write_request_sender.clone();
         let response_sender_clone = response_sender.clone();
+        let history_clone = history.clone();
         tokio::spawn(async move {
             Self::patch_task(
                 &id_clone,
                 &root_clone,
                 &addresses_clone,
+                history_clone.as_ref(),
                 &compile_sender_clone,
                 &write_sender_clone,
                 &mut patch_request_receiver,
@@ -430,6 +452,7 @@ impl Document {
         let call_docs_clone = call_docs.clone();
         let graph_clone = graph.clone();
         let patch_sender_clone = patch_request_sender.clone();
+        let compile_sender_clone = compile_request_sender.clone();
         let execute_sender_clone = execute_request_sender.clone();
         let write_sender_clone = write_request_sender.clone();
         let response_sender_clone = response_sender.clone();
@@ -444,6 +467,7 @@ impl Document {
                 &call_docs_clone,
                 &graph_clone,
                 &patch_sender_clone,
+                &compile_sender_clone,
                 &execute_sender_clone,
                 &write_sender_clone,
                 &mut compile_request_receiver,
@@ -519,6 +543,7 @@ impl Document {
 
             relations: Default::default(),
             subscriptions: Default::default(),
+            history,
 
             assemble_request_sender,
             patch_request_sender,
@@ -801,6 +826,32 @@ impl Document {
         }
     }
 
+    /// Dump a past version of the document to a format
+    ///
+    /// Reconstructs the version from the document's history and encodes it.
+    pub async fn dump_at(&self, version: usize, format: &str) -> Result<String> {
+        let history = match &self.history {
+            Some(history) => history,
+            None => bail!("Document `{}` does not have a history", self.id),
+        };
+        let node = history.node_at(version)?;
+        codecs::to_string(&node, format, None).await
+    }
+
+    /// Revert the changes made to the document in a past version
+    ///
+    /// The inverse of the patch for the version is applied (as an edit, so that it can be undone)
+    /// and the document written.
+    pub async fn revert(&mut self, version: usize) -> Result<()> {
+        let history = match &self.history {
+            Some(history) => history,
+            None => bail!("Document `{}` does not have a history", self.id),
+        };
+        let patch = history.revert(version)?;
+        self.patch(patch, When::Never, When::Never, When::Never, When::Now)
+            .await
+    }
+
     /// Load content into the document
     ///
     /// If the format of the new content is different to the document's format
@@ -883,6 +934,15 @@ impl Document {
     /// block (if there are lots of patches) and thereby hold on to locks causing a
     /// deadlock.
     ///
+    /// Patches that have a `version` earlier than the latest published patch were generated
+    /// concurrently with those since published. So that multiple actors can safely edit the
+    /// document at the same time, they are transformed against the effects of the patches
+    /// from other actors (an actor's own patches having already been applied to its version).
+    ///
+    /// The inverses of patches that are edits are kept on a bounded undo stack so that they can
+    /// be reverted by [`History::Undo`] requests (and then re-applied by [`History::Redo`] requests).
+    /// Both stacks are transformed against subsequent patches so that they remain applicable.
+    ///
     /// # Arguments
     ///
     /// - `id`: The id of the document (used in the published event topic)
@@ -892,6 +952,8 @@ impl Document {
     /// - `addresses`: The [`AddressMap`] to use to locate nodes within the root
     ///                node (will be read locked)
     ///
+    /// - `history`: The [`DocumentHistory`] to record each applied edit in (using a [`Document::history_task`])
+    ///
     /// - `compile_sender`: The channel to send any [`CompileRequest`]s after a patch is applied
     ///
     /// - `write_sender`: The channel to send any [`WriteRequest`]s after a patch is applied
@@ -899,16 +961,38 @@ impl Document {
     /// - `request_receiver`: The channel to receive [`PatchRequest`]s on
     ///
     /// - `response_sender`: The channel to send a [`Response`] on when each request if fulfilled
+    #[allow(clippy::too_many_arguments)]
     async fn patch_task(
         id: &str,
         root: &Arc<RwLock<Node>>,
         addresses: &Arc<RwLock<AddressMap>>,
+        history: Option<&DocumentHistory>,
         compile_sender: &mpsc::Sender<CompileRequest>,
         write_sender: &mpsc::UnboundedSender<WriteRequest>,
         request_receiver: &mut mpsc::UnboundedReceiver<PatchRequest>,
         response_sender: &broadcast::Sender<Response>,
     ) {
+        // The maximum number of published patches to keep the effects of
+        const HISTORY_LENGTH: usize = 1000;
+
+        // The maximum number of edits that can be undone
+        const UNDO_LENGTH: usize = 100;
+
+        let record_sender = history.map(|history| {
+            let (record_sender, mut record_receiver) =
+                mpsc::unbounded_channel::<(Patch, Option<Node>)>();
+            let id = id.to_string();
+            let history = history.clone();
+            tokio::spawn(
+                async move { Self::history_task(&id, &history, &mut record_receiver).await },
+            );
+            record_sender
+        });
+
         let mut counter = 0u32;
+        let mut published: VecDeque<(u32, Option<String>, Vec<Effect>)> = VecDeque::new();
+        let mut undos: VecDeque<Patch> = VecDeque::new();
+        let mut redos: Vec<Patch> = Vec::new();
         while let Some(request) = request_receiver.recv().await {
             tracing::trace!(
                 "Patching document `{}` for requests `{}`",
@@ -916,15 +1000,55 @@ impl Document {
                 request.ids.iter().join(",")
             );
 
-            let mut patch = request.patch;
+            // Send whether an undo or redo request was fulfilled
+            let mut history_sender = request.history_sender;
+            let mut history_done = |done: bool| {
+                if let Some(sender) = history_sender.take() {
+                    sender.send(done).ok();
+                }
+            };
+
+            let mut patch = match request.history {
+                History::Ignore | History::Edit => request.patch,
+                History::Undo | History::Redo => {
+                    let patch = match request.history {
+                        History::Undo => undos.pop_back(),
+                        _ => redos.pop(),
+                    };
+                    match patch {
+                        Some(patch) => patch,
+                        None => {
+                            tracing::debug!(
+                                "Nothing to {:?} for document `{}`",
+                                request.history,
+                                id
+                            );
+                            history_done(false);
+                            for request_id in request.ids {
+                                if let Err(error) = response_sender.send(Response::new(request_id))
+                                {
+                                    tracing::debug!(
+                                        "While sending response for document `{}` from patch task: {}",
+                                        id,
+                                        error
+                                    );
+                                }
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
             let start = patch.target.clone();
 
             // If the patch is empty then continue early rather than obtain locks etc
             if patch.is_empty() {
+                history_done(true);
                 continue;
             }
 
             // Block to ensure locks are retained for only as long as needed
+            let mut record = None;
             {
                 let root = &mut *root.write().await;
                 let addresses = &*addresses.read().await;
@@ -937,22 +1061,149 @@ impl Document {
                     }
                 }
 
-                // Apply the patch to the root node
-                apply(root, &patch);
+                // The absolute address of the patch is unknown if the `target` could not be found
+                // in `address_map`, in which case it can not be transformed, or be transformed against
+                let resolved = patch.address.is_some() || patch.target.is_none();
+
+                // Transform the patch against concurrent patches from other actors
+                if let Some(version) = patch.version {
+                    if version < counter {
+                        if !resolved {
+                            tracing::warn!(
+                                "Unable to transform patch for document `{}` with unresolved target",
+                                id
+                            );
+                        } else if matches!(
+                            published.front(),
+                            Some((sequence, ..)) if *sequence > version + 1
+                        ) {
+                            tracing::warn!(
+                                "Unable to transform patch for document `{}` with version `{}` that is older than retained history",
+                                id,
+                                version
+                            );
+                        } else {
+                            let patch_actor = patch.actor.clone();
+                            for (_sequence, actor, effects) in
+                                published.iter().filter(|(sequence, actor, ..)| {
+                                    *sequence > version
+                                        && (actor.is_none() || *actor != patch_actor)
+                                })
+                            {
+                                tracing::trace!(
+                                    "Transforming patch for document `{}` against patch from `{}`",
+                                    id,
+                                    actor.as_deref().unwrap_or("unknown")
+                                );
+                                patch.transform(effects);
+                            }
+                        }
+                    }
+                }
+
+                // Generate the inverse of the patch (before it is applied) so that it can be undone, or redone
+                let inverse = match (request.history, resolved) {
+                    (History::Ignore, ..) => None,
+                    (_, false) => {
+                        tracing::warn!(
+                            "Unable to record patch for document `{}` with unresolved target in undo history",
+                            id
+                        );
+                        None
+                    }
+                    (_, true) => match patch.invert(root) {
+                        Ok(inverse) => Some(inverse),
+                        Err(error) => {
+                            tracing::warn!(
+                                "While inverting patch for document `{}`: {}",
+                                id,
+                                error
+                            );
+                            None
+                        }
+                    },
+                };
+
+                // Apply the patch to the root node. If that fails, then respond to the requests
+                // without recording the patch in the undo, or persistent, history or publishing it.
+                if let Err(error) = apply(root, &patch) {
+                    tracing::error!("While applying patch to document `{}`: {}", id, error);
+                    history_done(false);
+                    for request_id in request.ids {
+                        if let Err(error) = response_sender.send(Response::new(request_id)) {
+                            tracing::debug!(
+                                "While sending response for document `{}` from patch task: {}",
+                                id,
+                                error
+                            );
+                        }
+                    }
+                    continue;
+                }
 
                 // Pre-publish the patch
                 counter += 1;
                 patch.prepublish(counter, root);
+
+                // Record the effects of the patch so that subsequent concurrent patches can be transformed
+                let effects = if resolved {
+                    patch.effects()
+                } else {
+                    Vec::new()
+                };
+                for patch in undos.iter_mut().chain(redos.iter_mut()) {
+                    patch.transform(&effects);
+                }
+                if let Some(inverse) = inverse {
+                    match request.history {
+                        History::Edit | History::Redo => {
+                            if matches!(request.history, History::Edit) {
+                                redos.clear();
+                            }
+                            undos.push_back(inverse);
+                            if undos.len() > UNDO_LENGTH {
+                                undos.pop_front();
+                            }
+                        }
+                        History::Undo => redos.push(inverse),
+                        History::Ignore => {}
+                    }
+                }
+                published.push_back((counter, patch.actor.clone(), effects));
+                if published.len() > HISTORY_LENGTH {
+                    published.pop_front();
+                }
+
+                // Record edits in the document's persistent history (other patches, e.g. those
+                // with the outputs of executed nodes, are captured by the periodic snapshots).
+                // Only the snapshot, if one is due, is taken here; the patch is sent to be
+                // recorded once the locks are released.
+                if let (Some(history), false) =
+                    (history, matches!(request.history, History::Ignore))
+                {
+                    record = Some(history.snapshot_due().then(|| root.clone()));
+                }
             }
+            history_done(true);
 
             // Publish the patch
-            publish(
-                &["documents:", id, ":patched"].concat(),
-                &DocumentEvent {
-                    type_: DocumentEventType::Patched,
-                    patch: Some(patch),
-                },
-            );
+            let event = DocumentEvent {
+                type_: DocumentEventType::Patched,
+                patch: Some(patch),
+            };
+            publish(&["documents:", id, ":patched"].concat(), &event);
+
+            if let (Some(record_sender), Some(node), Some(patch)) =
+                (&record_sender, record, event.patch)
+            {
+                if let Err(error) = record_sender.send((patch, node)) {
+                    tracing::error!(
+                        "While sending patch to history task for document `{}`: {}",
+                        id,
+                        error
+                    );
+                }
+            }
 
             // Possibly compile, execute, and/or write; or respond
             if !matches!(request.compile, When::Never) {
@@ -1032,6 +1283,7 @@ impl Document {
         let request = PatchRequest::new(
             vec![request_id.clone()],
             patch,
+            History::Edit,
             When::Now,
             assemble,
             compile,
@@ -1082,6 +1334,99 @@ impl Document {
         Ok(())
     }
 
+    /// Undo the last edit to the document
+    ///
+    /// Edits are patches applied using [`Document::patch_request`] (e.g. from clients, or providers)
+    /// while the document has been open, and not those generated when compiling or executing it.
+    ///
+    /// Returns `false` if there was no edit to undo. Waits until the inverse of the edit has been
+    /// applied to the document but not until it has been compiled or written.
+    ///
+    /// # Arguments
+    ///
+    /// - `compile`: Should the document be compiled after the edit is undone?
+    ///
+    /// - `write`: Should the document be written after the edit is undone?
+    #[tracing::instrument(skip(self))]
+    pub async fn undo(&self, compile: When, write: When) -> Result<bool> {
+        self.history_request(History::Undo, compile, write).await
+    }
+
+    /// Redo the last undone edit to the document
+    ///
+    /// Returns `false` if there was no undone edit to redo. See [`Document::undo`].
+    #[tracing::instrument(skip(self))]
+    pub async fn redo(&self, compile: When, write: When) -> Result<bool> {
+        self.history_request(History::Redo, compile, write).await
+    }
+
+    /// Send an undo or redo request to the patch task and wait for it to be fulfilled
+    async fn history_request(&self, history: History, compile: When, write: When) -> Result<bool> {
+        tracing::debug!("Sending {:?} request for document `{}`", history, self.id);
+
+        let (history_sender, history_receiver) = oneshot::channel();
+        let mut request = PatchRequest::new(
+            vec![RequestId::new()],
+            Patch::default(),
+            history,
+            When::Now,
+            When::Never,
+            compile,
+            When::Never,
+            write,
+        );
+        request.history_sender = Some(history_sender);
+        if let Err(error) = self.patch_request_sender.send(request) {
+            bail!(
+                "When sending {:?} request for document `{}`: {}",
+                history,
+                self.id,
+                error
+            )
+        };
+
+        match history_receiver.await {
+            Ok(done) => Ok(done),
+            Err(..) => bail!(
+                "Patch task for document `{}` ended before {:?} request was fulfilled",
+                self.id,
+                history
+            ),
+        }
+    }
+
+    /// A background task to record patches in the persistent history of the document
+    ///
+    /// # Arguments
+    ///
+    /// - `id`: The id of the document
+    ///
+    /// - `history`: The [`DocumentHistory`] to record patches in
+    ///
+    /// - `patch_receiver`: The channel to receive patches, and any snapshot of the document, on
+    async fn history_task(
+        id: &str,
+        history: &DocumentHistory,
+        patch_receiver: &mut mpsc::UnboundedReceiver<(Patch, Option<Node>)>,
+    ) {
+        while let Some((patch, node)) = patch_receiver.recv().await {
+            let history = history.clone();
+            let result =
+                tokio::task::spawn_blocking(move || history.patch(&patch, node.as_ref())).await;
+            if let Some(error) = match result {
+                Ok(Ok(..)) => None,
+                Ok(Err(error)) => Some(error.to_string()),
+                Err(error) => Some(error.to_string()),
+            } {
+                tracing::warn!(
+                    "While recording patch in history of document `{}`: {}",
+                    id,
+                    error
+                );
+            }
+        }
+    }
+
     /// A background task to react to changes in a resource in the document's graph
     ///
     /// # Arguments
@@ -1113,6 +1458,7 @@ impl Document {
                     None,
                     None,
                     None,
+                    false,
                 ))
                 .await
             {
@@ -1208,7 +1554,7 @@ impl Document {
             );
 
             // Assemble the root node
-            match assemble(path, root, call_docs, patch_sender).await {
+            match assemble(path, project, root, call_docs, patch_sender).await {
                 Ok(new_address_map) => {
                     // Update the address map
                     *address_map.write().await = new_address_map;
@@ -1270,6 +1616,7 @@ impl Document {
                         None,
                         None,
                         None,
+                        false,
                     ))
                     .await
                 {
@@ -1381,6 +1728,9 @@ impl Document {
     /// - `patch_sender`: A [`PatchRequest`] channel to send patches describing the changes to
     ///                   compiled nodes
     ///
+    /// - `compile_sender`: A [`CompileRequest`] channel to send requests to recompile the
+    ///                     document (e.g. after background checks of imported modules)
+    ///
     /// - `execute_sender`: An [`ExecuteRequest`] channel to send any requests to execute the
     ///                     document after it has been compiled
     ///
@@ -1400,6 +1750,7 @@ impl Document {
         call_docs: &Arc<RwLock<CallDocuments>>,
         graph: &Arc<RwLock<Graph>>,
         patch_sender: &mpsc::UnboundedSender<PatchRequest>,
+        compile_sender: &mpsc::Sender<CompileRequest>,
         execute_sender: &mpsc::Sender<ExecuteRequest>,
         write_sender: &mpsc::UnboundedSender<WriteRequest>,
         request_receiver: &mut mpsc::Receiver<CompileRequest>,
@@ -1449,6 +1800,7 @@ impl Document {
                 tags,
                 call_docs,
                 patch_sender,
+                Some(compile_sender),
             )
             .await
             {
@@ -1473,6 +1825,7 @@ impl Document {
                         None,
                         None,
                         None,
+                        false,
                     ))
                     .await
                 {
@@ -1626,6 +1979,7 @@ impl Document {
         let mut start = None;
         let mut ordering = PlanOptions::default_ordering();
         let mut max_concurrency = PlanOptions::default_max_concurrency();
+        let mut no_cache = false;
         let mut write = When::Never;
         loop {
             match tokio::time::timeout(duration, request_receiver.recv()).await {
@@ -1645,11 +1999,13 @@ impl Document {
                             start = None;
                         }
 
-                        // Precedence for appearance, over topological, over single ordering
+                        // Precedence for appearance, over topological, over cost, over single ordering
                         if let Some(request_ordering) = request.ordering {
                             use PlanOrdering::*;
                             match (ordering, request_ordering) {
-                                (Single, Topological | Appearance) | (Topological, Appearance) => {
+                                (Single, Cost | Topological | Appearance)
+                                | (Cost, Topological | Appearance)
+                                | (Topological, Appearance) => {
                                     ordering = request_ordering;
                                 }
                                 _ => {}
@@ -1663,6 +2019,9 @@ impl Document {
                             }
                         }
 
+                        // Precedence for not using the execution cache
+                        no_cache |= request.no_cache;
+
                         write.no_later_than(request.write);
 
                         if !matches!(request.when, When::Now) {
@@ -1686,7 +2045,18 @@ impl Document {
                 request_ids.iter().join(",")
             );
 
-            // Generate the execution plan
+            // Use the execution cache unless requested not to. If executing from a particular
+            // node then only store results (the user has explicitly asked for it to be executed)
+            let cache = if no_cache {
+                None
+            } else if start.is_some() {
+                Some(ExecuteCache::default().store_only())
+            } else {
+                Some(ExecuteCache::default())
+            };
+
+            // Generate the execution plan, using the durations of previous executions
+            let mut durations = ExecuteDurations::load(path);
             let start = start
                 .clone()
                 .map(|node_id| resources::code(path, &node_id, "", None));
@@ -1698,6 +2068,7 @@ impl Document {
                     start,
                     None,
                     Some(&*tags_guard),
+                    Some(durations.durations()),
                     Some(PlanOptions {
                         ordering,
                         max_concurrency,
@@ -1721,11 +2092,18 @@ impl Document {
                 tags,
                 kernel_space,
                 call_docs,
+                cache.as_ref(),
+                Some(&mut durations),
                 patch_sender,
                 cancel_receiver,
             )
             .await;
 
+            // Save the durations of executed nodes for use in future plans
+            if let Err(error) = durations.save() {
+                tracing::warn!("While saving execution durations: {}", error);
+            }
+
             // Possibly write document; or respond
             if !matches!(write, When::Never) {
                 tracing::trace!(
@@ -1754,6 +2132,7 @@ impl Document {
             }
 
             request_ids.clear();
+            no_cache = false;
             write = When::Never;
         }
     }
@@ -1766,6 +2145,7 @@ impl Document {
         start: Option<String>,
         ordering: Option<PlanOrdering>,
         max_concurrency: Option<usize>,
+        no_cache: bool,
     ) -> Result<RequestId> {
         tracing::debug!("Sending execute request for document `{}`", self.id);
 
@@ -1777,6 +2157,7 @@ impl Document {
             start,
             ordering,
             max_concurrency,
+            no_cache,
         );
         if let Err(error) = self.execute_request_sender.send(request).await {
             bail!(
@@ -1801,10 +2182,11 @@ impl Document {
         start: Option<String>,
         ordering: Option<PlanOrdering>,
         max_concurrency: Option<usize>,
+        no_cache: bool,
     ) -> Result<()> {
         // Execute the document. Do not write now; maybe at end of this func.
         let request_id = self
-            .execute_request(When::Never, start, ordering, max_concurrency)
+            .execute_request(When::Never, start, ordering, max_concurrency, no_cache)
             .await?;
 
         // Wait for execution to finish
@@ -1831,6 +2213,97 @@ impl Document {
         Ok(())
     }
 
+    /// Compile the document and get its diagnostics
+    ///
+    /// Unlike `diagnostics`, waits for checks of whether imported modules are installed
+    /// (usually done in the background after compiling) and recompiles the document if
+    /// necessary.
+    pub async fn lint(&mut self) -> Result<Vec<(String, Diagnostic)>> {
+        self.compile(When::Never, When::Never, None).await?;
+
+        let modules = self
+            .graph
+            .read()
+            .await
+            .get_resource_infos()
+            .values()
+            .flat_map(|resource_info| resource_info.relations.iter().flatten())
+            .filter_map(|(relation, object)| match (relation, object) {
+                (Relation::Imports(..), Resource::Module(module)) => {
+                    Some((module.language.to_lowercase(), module.name.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        if check_modules(&self.project, modules).await {
+            self.compile(When::Never, When::Never, None).await?;
+        }
+
+        Ok(self.diagnostics().await)
+    }
+
+    /// Get the diagnostics from static analysis of the code in the document
+    ///
+    /// Returns the diagnostics (e.g. syntax errors, uses of undefined symbols) for each
+    /// code node, as of the last compile, as pairs of node id and diagnostic.
+    pub async fn diagnostics(&self) -> Vec<(String, Diagnostic)> {
+        let graph = self.graph.read().await;
+        graph
+            .get_resource_infos()
+            .iter()
+            .filter_map(|(resource, resource_info)| match resource {
+                Resource::Code(resources::Code { id, .. }) => resource_info
+                    .diagnostics
+                    .as_ref()
+                    .map(|diagnostics| (id, diagnostics)),
+                _ => None,
+            })
+            .flat_map(|(id, diagnostics)| {
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| (id.clone(), diagnostic.clone()))
+            })
+            .collect()
+    }
+
+    /// Generate an execution plan for the document
+    ///
+    /// Generates the same plan as would be used by `execute` but without executing it.
+    /// Each task in the plan is annotated with an estimated duration based on the
+    /// recorded duration of previous executions of its node (if any), falling back
+    /// to the node's `execute_duration`.
+    pub async fn plan(
+        &self,
+        start: Option<String>,
+        ordering: Option<PlanOrdering>,
+        max_concurrency: Option<usize>,
+    ) -> Result<Plan> {
+        let start = start.map(|node_id| resources::code(&self.path, &node_id, "", None));
+        let options = PlanOptions {
+            ordering: ordering.unwrap_or_else(PlanOptions::default_ordering),
+            max_concurrency: max_concurrency.unwrap_or_else(PlanOptions::default_max_concurrency),
+        };
+
+        let mut durations = ExecuteDurations::load(&self.path).durations().clone();
+
+        let mut plan = {
+            let tags = self.tags.read().await;
+            let graph = self.graph.read().await;
+            graph
+                .plan(start, None, Some(&*tags), Some(&durations), Some(options))
+                .await?
+        };
+
+        let root = self.root.read().await;
+        let addresses = self.addresses.read().await;
+        for (node_id, duration) in plan_durations(&plan, &root, &addresses) {
+            durations.entry(node_id).or_insert(duration);
+        }
+        plan.estimate_durations(&durations);
+
+        Ok(plan)
+    }
+
     /// React to a change in a file path
     ///
     /// If the path corresponds to a `File` resource in the document's graph then re-compile,
@@ -1867,7 +2340,7 @@ impl Document {
             } else if !matches!(compile, When::Never) {
                 self.compile_request(execute, write, None).await
             } else if !matches!(execute, When::Never) {
-                self.execute_request(write, None, None, None).await
+                self.execute_request(write, None, None, None, false).await
             } else {
                 return;
             };
@@ -1937,7 +2410,7 @@ impl Document {
             }
         }
 
-        self.execute(When::Never, None, None, None).await?;
+        self.execute(When::Never, None, None, None, false).await?;
 
         Ok(())
     }
@@ -2080,9 +2553,46 @@ impl Document {
                 | Node::VideoObject(..)
         );
 
+        // Determine if a snapshot needs to be recorded in the document's history before
+        // obtaining the lock below, because that involves serializing the root
+        let snapshot = match &self.history {
+            Some(history) => match DocumentHistory::digest(&root).and_then(|digest| {
+                history
+                    .is_changed(&digest)
+                    .map(|changed| changed.then_some(digest))
+            }) {
+                Ok(digest) => digest,
+                Err(error) => {
+                    tracing::warn!(
+                        "While checking history of document `{}`: {}",
+                        self.id,
+                        error
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
         // Set the root, assemble and compile
         // TODO: Reconsider this in refactoring of alternative format representations of docs
-        *self.root.write().await = root;
+        {
+            let mut guard = self.root.write().await;
+
+            // Record the snapshot in the document's history (while the lock is held so
+            // that it is ordered correctly with respect to patches)
+            if let (Some(history), Some(digest)) = (&self.history, snapshot) {
+                if let Err(error) = history.snapshot(&root, Some(digest)) {
+                    tracing::warn!(
+                        "While recording snapshot in history of document `{}`: {}",
+                        self.id,
+                        error
+                    );
+                }
+            }
+
+            *guard = root;
+        }
         self.assemble(When::Now, When::Never, When::Never).await?;
 
         // Publish any events for which there are subscriptions (this will probably go elsewhere)
@@ -2403,7 +2913,9 @@ impl DocumentHandler {
 
 #[cfg(test)]
 mod tests {
-    use test_utils::fixtures;
+    use node_patch::Operation;
+    use stencila_schema::{BlockContent, Paragraph};
+    use test_utils::{assert_json_eq, fixtures};
 
     use super::*;
 
@@ -2439,4 +2951,189 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn patch_concurrent() -> Result<()> {
+        let para = |text: &str| {
+            BlockContent::Paragraph(Paragraph {
+                content: vec![InlineContent::String(text.to_string())],
+                ..Default::default()
+            })
+        };
+        let article = |paras: &[&str]| {
+            Node::Article(Article {
+                content: Some(paras.iter().map(|text| para(text)).collect()),
+                ..Default::default()
+            })
+        };
+        let replace = |index: usize, text: &str, actor: &str, version: u32| {
+            let mut patch = Patch::from_ops(vec![Operation::Replace {
+                address: Address::from("content").add_index(index),
+                items: 1,
+                value: Box::new(vec![para(text)]),
+                length: 1,
+                html: None,
+            }]);
+            patch.actor = Some(actor.to_string());
+            patch.version = Some(version);
+            patch
+        };
+
+        let mut doc = Document::new(None, Some("md".to_string()));
+        *doc.root.write().await = article(&["one", "two", "three"]);
+
+        // One actor inserts a paragraph at the start
+        let mut patch = Patch::from_ops(vec![Operation::Add {
+            address: Address::from("content").add_index(0),
+            value: Box::new(vec![para("zero")]),
+            length: 1,
+            html: None,
+        }]);
+        patch.actor = Some("a".to_string());
+        patch.version = Some(0);
+        doc.patch(patch, When::Never, When::Never, When::Never, When::Never)
+            .await?;
+
+        // Another actor, which has not yet received that patch, replaces the second and then third
+        // paragraphs. Both patches are stale and need to be transformed against the insertion (but
+        // not against each other because the second was generated after the first was applied)
+        doc.patch(
+            replace(1, "TWO", "b", 0),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        doc.patch(
+            replace(2, "THREE", "b", 0),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        assert_json_eq!(
+            *doc.root.read().await,
+            article(&["zero", "one", "TWO", "THREE"])
+        );
+
+        // Once up to date, patches are not transformed
+        doc.patch(
+            replace(0, "ZERO", "b", 3),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        assert_json_eq!(
+            *doc.root.read().await,
+            article(&["ZERO", "one", "TWO", "THREE"])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn undo_redo() -> Result<()> {
+        let para = |text: &str| {
+            BlockContent::Paragraph(Paragraph {
+                content: vec![InlineContent::String(text.to_string())],
+                ..Default::default()
+            })
+        };
+        let article = |paras: &[&str]| {
+            Node::Article(Article {
+                content: Some(paras.iter().map(|text| para(text)).collect()),
+                ..Default::default()
+            })
+        };
+        let replace = |index: usize, text: &str| {
+            Patch::from_ops(vec![Operation::Replace {
+                address: Address::from("content").add_index(index),
+                items: 1,
+                value: Box::new(vec![para(text)]),
+                length: 1,
+                html: None,
+            }])
+        };
+
+        let mut doc = Document::new(None, Some("md".to_string()));
+        *doc.root.write().await = article(&["one", "two"]);
+
+        // Nothing to undo or redo yet
+        assert!(!doc.undo(When::Never, When::Never).await?);
+        assert!(!doc.redo(When::Never, When::Never).await?);
+
+        // Make two edits
+        doc.patch(
+            replace(0, "ONE"),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        doc.patch(
+            Patch::from_ops(vec![Operation::Add {
+                address: Address::from("content").add_index(2),
+                value: Box::new(vec![para("three")]),
+                length: 1,
+                html: None,
+            }]),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "two", "three"]));
+
+        // Undo both of them, and then no more
+        assert!(doc.undo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "two"]));
+        assert!(doc.undo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["one", "two"]));
+        assert!(!doc.undo(When::Never, When::Never).await?);
+
+        // Redo the first
+        assert!(doc.redo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "two"]));
+
+        // A new edit clears the redo stack but can itself be undone
+        doc.patch(
+            replace(1, "TWO"),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        assert!(!doc.redo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "TWO"]));
+        assert!(doc.undo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "two"]));
+
+        // An edit that fails to apply is not recorded in the undo history
+        doc.patch(
+            Patch::from_ops(vec![Operation::Replace {
+                address: Address::from("content").add_index(1),
+                items: 1,
+                value: Box::new(true),
+                length: 1,
+                html: None,
+            }]),
+            When::Never,
+            When::Never,
+            When::Never,
+            When::Never,
+        )
+        .await?;
+        assert_json_eq!(*doc.root.read().await, article(&["ONE", "two"]));
+        assert!(doc.undo(When::Never, When::Never).await?);
+        assert_json_eq!(*doc.root.read().await, article(&["one", "two"]));
+
+        Ok(())
+    }
 }
@@ -1,7 +1,7 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc, sync::RwLock};
 
 use common::{
-    eyre::{bail, Result},
+    eyre::{bail, eyre, Result},
     once_cell::sync::Lazy,
     tokio::sync::Mutex,
 };
@@ -14,6 +14,9 @@ use crate::document::{Document, DocumentHandler};
 pub struct Documents {
     /// A mapping of file paths to open documents
     registry: Mutex<HashMap<String, DocumentHandler>>,
+
+    /// The sandbox that the kernels of all documents are run in (if any)
+    sandbox: RwLock<Option<String>>,
 }
 
 impl Documents {
@@ -22,6 +25,38 @@ impl Documents {
         Self::default()
     }
 
+    /// Set the sandbox that the kernels of all documents are run in
+    ///
+    /// Applies to documents that are already open and to all documents opened after it is
+    /// set (including those opened using `Document::open` rather than this store). Because
+    /// the sandbox of a document's kernel space takes precedence over those specified within
+    /// the document, this forces sandboxing of documents from untrusted sources.
+    pub async fn set_sandbox(&self, sandbox: Option<&str>) -> Result<()> {
+        if let Some(spec) = sandbox {
+            kernels::Sandbox::parse(spec)?;
+        }
+        *self
+            .sandbox
+            .write()
+            .map_err(|error| eyre!("While setting sandbox: {}", error))? =
+            sandbox.map(String::from);
+
+        for handler in self.registry.lock().await.values() {
+            let document = handler.document.lock().await;
+            document.kernels.write().await.set_sandbox(sandbox)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the sandbox that the kernels of all documents are run in (if any)
+    pub(crate) fn sandbox(&self) -> Option<String> {
+        self.sandbox
+            .read()
+            .map(|sandbox| sandbox.clone())
+            .unwrap_or_default()
+    }
+
     /// List documents that are currently open
     ///
     /// Returns a vector of document paths (relative to the current working directory)
@@ -70,11 +105,8 @@ impl Documents {
     pub async fn open<P: AsRef<Path>>(&self, path: P, format: Option<String>) -> Result<String> {
         let path = Path::new(path.as_ref()).canonicalize()?;
 
-        for handler in self.registry.lock().await.values() {
-            let document = handler.document.lock().await;
-            if document.path == path {
-                return Ok(document.id.clone());
-            }
+        if let Some(id) = self.find(&path).await? {
+            return Ok(id);
         }
 
         let document = Document::open(path, format).await?;
@@ -88,6 +120,22 @@ impl Documents {
         Ok(document_id)
     }
 
+    /// Find an open document
+    ///
+    /// Returns the id of the document with the path, or `None` if it is not open.
+    pub async fn find<P: AsRef<Path>>(&self, path: P) -> Result<Option<String>> {
+        let path = Path::new(path.as_ref()).canonicalize()?;
+
+        for handler in self.registry.lock().await.values() {
+            let document = handler.document.lock().await;
+            if document.path == path {
+                return Ok(Some(document.id.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Close a document
     ///
     /// # Arguments
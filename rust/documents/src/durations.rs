@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use common::{eyre::Result, serde_json, tracing};
+use graph::Plan;
+use hash_utils::str_sha256_hex;
+use node_address::AddressMap;
+use stencila_schema::{CodeChunk, CodeExpression, Node};
+
+use crate::{cache::ExecuteCache, utils::resource_to_node};
+
+/// The durations of previous executions of the code nodes in a document
+///
+/// Durations are keyed by node id and persisted in a JSON file (named using a hash of the path
+/// of the document) in a `durations` folder within the execution cache directory. Unlike the
+/// `execute_duration` property of nodes, which may be lost when a document is saved to a format
+/// such as Markdown, these durations are available across sessions for estimating the
+/// duration of the tasks in an execution plan.
+#[derive(Debug)]
+pub struct ExecuteDurations {
+    /// The path of the file that the durations are persisted in
+    path: PathBuf,
+
+    /// The duration, in seconds, of the last execution of each node
+    durations: HashMap<String, f64>,
+}
+
+impl ExecuteDurations {
+    /// Load the durations for a document
+    ///
+    /// Returns an empty set of durations if the file does not exist or could not be read.
+    pub fn load(document: &Path) -> Self {
+        let path = ExecuteCache::default_dir()
+            .join("durations")
+            .join([&str_sha256_hex(&document.to_string_lossy()), ".json"].concat());
+
+        let durations = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|error| {
+                tracing::warn!("While parsing `{}`: {}", path.display(), error);
+                HashMap::new()
+            }),
+            Err(..) => HashMap::new(),
+        };
+
+        Self { path, durations }
+    }
+
+    /// Get the durations, keyed by node id
+    pub fn durations(&self) -> &HashMap<String, f64> {
+        &self.durations
+    }
+
+    /// Record the duration of an executed node
+    pub fn record(&mut self, node_id: &str, node: &Node) {
+        if let Some(duration) = node_duration(node) {
+            self.durations.insert(node_id.to_string(), duration);
+        }
+    }
+
+    /// Save the durations to file
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string(&self.durations)?)?;
+        Ok(())
+    }
+}
+
+/// Get the duration of the last execution of a node
+fn node_duration(node: &Node) -> Option<f64> {
+    match node {
+        Node::CodeChunk(CodeChunk {
+            execute_duration, ..
+        })
+        | Node::CodeExpression(CodeExpression {
+            execute_duration, ..
+        }) => execute_duration.as_ref().map(|duration| duration.0),
+        _ => None,
+    }
+}
+
+/// Get the durations of the last executions of the nodes in a plan, keyed by node id
+///
+/// Nodes that have not been executed (i.e. do not have an `execute_duration`) are ignored.
+pub fn plan_durations(plan: &Plan, root: &Node, address_map: &AddressMap) -> HashMap<String, f64> {
+    plan.stages
+        .iter()
+        .flat_map(|stage| stage.tasks.iter())
+        .filter_map(|task| {
+            let (node, node_id, ..) =
+                resource_to_node(&task.resource_info.resource, root, address_map).ok()?;
+            node_duration(&node).map(|duration| (node_id, duration))
+        })
+        .collect()
+}
@@ -30,7 +30,10 @@ use node_validate::Validator as _;
 use path_utils::merge;
 use stencila_schema::*;
 
-use crate::document::{CallDocuments, Document};
+use crate::{
+    citations::{Citations, Rendered},
+    document::{CallDocuments, Document},
+};
 
 /// Trait for executable document nodes
 ///
@@ -81,6 +84,9 @@ pub struct AssembleContext {
     /// (thereby reducing startup times associated with each execution of the call)
     pub call_docs: Arc<RwLock<CallDocuments>>,
 
+    /// The references of the document and the style used to render citations of them
+    pub citations: Citations,
+
     /// A list of patch operations representing changes to nodes.
     pub patches: Vec<Patch>,
 }
@@ -1039,6 +1045,74 @@ impl Executable for Call {
     }
 }
 
+#[async_trait]
+impl Executable for Cite {
+    /// Assemble a `Cite` node
+    ///
+    /// Renders the in-text citation, using the document's citation style, and
+    /// then assembles its content.
+    async fn assemble(
+        &mut self,
+        address: &mut Address,
+        context: &mut AssembleContext,
+    ) -> Result<()> {
+        let rendered = context.citations.cite(self);
+        assemble_cite(self, rendered, address, context).await
+    }
+}
+
+#[async_trait]
+impl Executable for CiteGroup {
+    /// Assemble a `CiteGroup` node
+    ///
+    /// Renders the group as a single in-text citation (e.g. with one set of parentheses)
+    /// spread across the `content` of each of its items, and then assembles each item.
+    async fn assemble(
+        &mut self,
+        address: &mut Address,
+        context: &mut AssembleContext,
+    ) -> Result<()> {
+        let rendered = context.citations.cite_group(&self.items);
+        address.push_back(Slot::Name("items".to_string()));
+        for (index, (cite, rendered)) in self.items.iter_mut().zip(rendered).enumerate() {
+            address.push_back(Slot::Index(index));
+            assemble_cite(cite, rendered, address, context).await?;
+            address.pop_back();
+        }
+        address.pop_back();
+        Ok(())
+    }
+}
+
+/// Assemble a `Cite` node given its rendered text and errors
+///
+/// Generates a patch if these differ from the current `content` and `errors` of the node.
+async fn assemble_cite(
+    cite: &mut Cite,
+    (text, errors): Rendered,
+    address: &mut Address,
+    context: &mut AssembleContext,
+) -> Result<()> {
+    let content = Some(vec![InlineContent::String(text)]);
+    if content != cite.content || errors != cite.errors {
+        context.patches.push(diff_address(
+            address.clone(),
+            &*cite,
+            &Cite {
+                content,
+                errors,
+                ..cite.to_owned()
+            },
+        ));
+    }
+
+    address.push_back(Slot::Name("content".to_string()));
+    cite.content.assemble(address, context).await?;
+    address.pop_back();
+
+    Ok(())
+}
+
 // Nodes types that simply need an `id` assigned so that custom web component patch events have a target
 
 macro_rules! executable_assemble_id_only {
@@ -1288,8 +1362,6 @@ macro_rules! executable_fields {
     };
 }
 
-executable_fields!(CiteGroup, items);
-
 executable_fields!(Collection, parts);
 executable_fields!(CollectionSimple, parts);
 
@@ -1315,7 +1387,6 @@ macro_rules! executable_content {
 
 executable_content!(
     Article,
-    Cite,
     Claim,
     ClaimSimple,
     Comment,
@@ -1,11 +1,14 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
+    env,
     sync::Arc,
+    time::Duration,
 };
 
 use common::{
     eyre::{bail, Report, Result},
     futures::stream::{FuturesUnordered, StreamExt},
+    once_cell::sync::Lazy,
     tokio::{
         self,
         sync::{
@@ -17,18 +20,47 @@ use common::{
 };
 use graph::{Plan, PlanScope};
 use graph_triples::{Resource, TagMap};
-use kernels::KernelSpace;
+use kernels::{parse_timeout, KernelSpace};
 use node_address::{Address, AddressMap};
 use node_patch::{diff, mutate, Patch};
-use stencila_schema::{CodeChunk, CodeExpression, ExecuteStatus, Node};
+use stencila_schema::{CodeChunk, CodeError, CodeExpression, ExecuteRequired, ExecuteStatus, Node};
 
 use crate::{
+    cache::ExecuteCache,
     document::CallDocuments,
+    durations::ExecuteDurations,
     executable::Executable,
     messages::{CancelRequest, PatchRequest, When},
     utils::{resource_to_node, send_patch, send_patches},
 };
 
+/// The default timeout for tasks
+///
+/// Set using the `STENCILA_EXECUTE_TIMEOUT` environment variable e.g. `10m`. Defaults to
+/// no timeout. Overridden for a document by a `@global @timeout` tag, and for a node by a
+/// `@timeout` tag.
+static DEFAULT_TIMEOUT: Lazy<Option<Duration>> =
+    Lazy::new(|| match env::var("STENCILA_EXECUTE_TIMEOUT") {
+        Ok(value) => task_timeout(&value, None),
+        Err(..) => None,
+    });
+
+/// Parse the timeout for a task, falling back to a default if it is invalid
+///
+/// A value of `none` means that the task has no timeout.
+fn task_timeout(value: &str, default: Option<Duration>) -> Option<Duration> {
+    if value.trim() == "none" {
+        return None;
+    }
+    match parse_timeout(value) {
+        Ok(timeout) => Some(timeout),
+        Err(error) => {
+            tracing::warn!("While parsing timeout: {}", error);
+            default
+        }
+    }
+}
+
 /// Execute a [`Plan`] on a [`Node`]
 ///
 /// Uses a `RwLock` for `root` and `address_map` so that read locks can be held for as short as
@@ -48,6 +80,13 @@ use crate::{
 ///
 /// - `kernel_space`: The [`KernelSpace`] within which to execute the plan
 ///
+/// - `call_docs`: The [`Document`]s to call for each `Call` node
+///
+/// - `cache`: The [`ExecuteCache`] to restore the results of unchanged nodes from, and
+///            to store the results of executed nodes in (if any)
+///
+/// - `durations`: The [`ExecuteDurations`] to record the duration of executed nodes in (if any)
+///
 /// - `patch_request_sender`: A [`PatchRequest`] channel sender to send patches describing the
 ///                   changes to executed nodes
 ///
@@ -61,6 +100,8 @@ pub async fn execute(
     tag_map: &Arc<RwLock<TagMap>>,
     kernel_space: &Arc<RwLock<KernelSpace>>,
     call_docs: &Arc<RwLock<CallDocuments>>,
+    cache: Option<&ExecuteCache>,
+    mut durations: Option<&mut ExecuteDurations>,
     patch_request_sender: &UnboundedSender<PatchRequest>,
     cancel_request_receiver: &mut Receiver<CancelRequest>,
 ) -> Result<()> {
@@ -105,6 +146,13 @@ pub async fn execute(
     drop(root_guard);
     drop(address_map_guard);
 
+    // Determine which nodes can have their results restored from the cache, rather than
+    // being executed, and the cache keys for storing the results of the others
+    let (cache_keys, mut cache_hits) = match cache {
+        Some(cache) => cache.restorable(plan),
+        None => (HashMap::new(), HashMap::new()),
+    };
+
     // Set the `execute_status` of all nodes in stages other than the first
     // to `Scheduled` or `ScheduledPreviouslyFailed` and send the resulting patch.
     // Do not do this for first stage as an optimization to avoid unnecessary patches
@@ -127,6 +175,7 @@ pub async fn execute(
     // For each stage in plan...
     let stage_count = plan.stages.len();
     let mut cancelled = Vec::new();
+    let mut timed_out = BTreeSet::new();
     for (stage_index, stage) in plan.stages.iter().enumerate() {
         // Before running the tasks in this stage, check that all their dependencies have succeeded
         // and stop if they have not. Collects to a `BTreeSet` to generate unique set (some tasks in
@@ -225,9 +274,19 @@ pub async fn execute(
             let mut resource_info = task.resource_info.clone();
             let kernel_selector = task.kernel_selector.clone();
             let is_fork = task.is_fork;
-
-            // Merge the global tag map into the resource's
-            resource_info.tags.merge(&*tags);
+            let cache_hit = cache_hits.remove(&task.resource_info.resource);
+            let cache_put = cache
+                .cloned()
+                .zip(cache_keys.get(&task.resource_info.resource).cloned());
+
+            // Merge the global tag map into the resource's (with the resource's taking precedence)
+            resource_info.tags = tags.merge(&resource_info.tags);
+
+            // Determine the timeout for the task (if any)
+            let timeout = match resource_info.tags.get_value("timeout") {
+                Some(value) => task_timeout(&value, *DEFAULT_TIMEOUT),
+                None => *DEFAULT_TIMEOUT,
+            };
 
             // Create a future for the task that will be spawned later
             let future = async move {
@@ -242,70 +301,101 @@ pub async fn execute(
                 // Create a mutable draft of the node and execute it in the kernel space
                 let mut executed = node_info.node.clone();
 
-                // Start execution of the node
-                let task_info = match executed
-                    .execute_begin(
-                        &resource_info,
-                        &*kernel_space.read().await,
-                        &kernel_selector,
-                        is_fork,
-                        &*call_docs.read().await,
-                    )
-                    .await
-                {
-                    Ok(task_info) => task_info,
-                    Err(error) => {
-                        tracing::error!(
-                            "While beginning task {}/{}: {}",
-                            task_index + 1,
-                            task_count,
-                            error
-                        );
-                        None
-                    }
-                };
+                if let Some(entry) = cache_hit {
+                    // Restore the results of a previous execution from the cache
+                    tracing::trace!(
+                        "Restoring node `{}` from execution cache",
+                        node_info.node_id
+                    );
+                    entry.restore(&mut executed, &resource_info);
+                } else {
+                    // Start execution of the node
+                    let task_info = match executed
+                        .execute_begin(
+                            &resource_info,
+                            &*kernel_space.read().await,
+                            &kernel_selector,
+                            is_fork,
+                            &*call_docs.read().await,
+                        )
+                        .await
+                    {
+                        Ok(task_info) => task_info,
+                        Err(error) => {
+                            tracing::error!(
+                                "While beginning task {}/{}: {}",
+                                task_index + 1,
+                                task_count,
+                                error
+                            );
+                            None
+                        }
+                    };
 
-                // If a `TaskInfo` was returned, the execution is async, can potentially be
-                // interrupted, and needs to be waited for...
-                if let Some(mut task_info) = task_info {
-                    // Hook the `cancel_receiver` to the task's `interrupter`, if it has one
-                    // (i.e. if it is interruptable)
-                    if let Some(interrupter) = task_info.task.interrupter.clone() {
-                        tracing::trace!("Task is `{}` interruptable", task_info.task.id);
-                        let task_id = task_info.task.id.clone();
-                        tokio::spawn(async move {
-                            if let Ok(..) = cancel_receiver.await {
-                                tracing::trace!(
-                                    "Attempting to interrupt task `{}` in stage {}/{}",
-                                    task_id,
-                                    stage_index + 1,
-                                    stage_count
-                                );
-                                if let Err(error) = interrupter.send(()).await {
-                                    tracing::error!(
-                                        "While attempting to cancel task `{}`: {}",
+                    // If a `TaskInfo` was returned, the execution is async, can potentially be
+                    // interrupted, and needs to be waited for...
+                    if let Some(mut task_info) = task_info {
+                        // Hook the `cancel_receiver` to the task's `interrupter`, if it has one
+                        // (i.e. if it is interruptable)
+                        if let Some(interrupter) = task_info.task.interrupter.clone() {
+                            tracing::trace!("Task is `{}` interruptable", task_info.task.id);
+                            let task_id = task_info.task.id.clone();
+                            tokio::spawn(async move {
+                                if let Ok(..) = cancel_receiver.await {
+                                    tracing::trace!(
+                                        "Attempting to interrupt task `{}` in stage {}/{}",
                                         task_id,
-                                        error
+                                        stage_index + 1,
+                                        stage_count
                                     );
+                                    if let Err(error) = interrupter.send(()).await {
+                                        tracing::error!(
+                                            "While attempting to cancel task `{}`: {}",
+                                            task_id,
+                                            error
+                                        );
+                                    }
                                 }
+                            });
+                        } else {
+                            tracing::trace!("Task `{}` is not interruptable", task_info.task.id);
+                        };
+
+                        // Wait for the task to finish (or be cancelled, or time out) and update the executed node when it has
+                        let task_result = match timeout {
+                            Some(timeout) => {
+                                KernelSpace::result_within(&kernel_space, &mut task_info, timeout)
+                                    .await?
                             }
-                        });
-                    } else {
-                        tracing::trace!("Task `{}` is not interruptable", task_info.task.id);
-                    };
+                            None => task_info.result().await?,
+                        };
+                        executed.execute_end(task_info, task_result).await?;
+                    }
 
-                    // Wait for the task to finish (or be cancelled and update the executed node when it has
-                    let task_result = task_info.result().await?;
-                    executed.execute_end(task_info, task_result).await?;
+                    // Store the results in the cache for next time
+                    if let Some((cache, key)) = cache_put {
+                        if let Err(error) = cache.put(&key, &executed) {
+                            tracing::warn!("While storing results in execution cache: {}", error);
+                        }
+                    }
                 }
 
                 // Update the resource to indicate that the resource was executed
-                let execute_failed = match &executed {
-                    Node::CodeChunk(CodeChunk { execute_status, .. })
-                    | Node::CodeExpression(CodeExpression { execute_status, .. }) => {
-                        matches!(execute_status, Some(ExecuteStatus::Failed))
-                    }
-                    _ => false,
+                let (execute_failed, execute_timed_out) = match &executed {
+                    Node::CodeChunk(CodeChunk {
+                        execute_status,
+                        errors,
+                        ..
+                    })
+                    | Node::CodeExpression(CodeExpression {
+                        execute_status,
+                        errors,
+                        ..
+                    }) => (
+                        matches!(execute_status, Some(ExecuteStatus::Failed)),
+                        errors.iter().flatten().any(is_timeout),
+                    ),
+                    _ => (false, false),
                 };
                 resource_info.did_execute(execute_failed);
 
@@ -318,7 +408,13 @@ pub async fn execute(
                 // for assessing execution status etc)
                 node_info.node = executed;
 
-                Ok::<_, Report>((task_index, resource_info, node_info, patch))
+                Ok::<_, Report>((
+                    task_index,
+                    resource_info,
+                    node_info,
+                    patch,
+                    execute_timed_out,
+                ))
             };
             cancellers.insert(node_id, cancel_sender);
             futures.push(future);
@@ -368,7 +464,7 @@ pub async fn execute(
                         }
                     };
 
-                    if let Some((task_index, resource_info, mut node_info, patch)) = result {
+                    if let Some((task_index, resource_info, mut node_info, patch, execute_timed_out)) = result {
                         tracing::trace!(
                             "Finished task {}/{} of stage {}/{}",
                             task_index + 1,
@@ -393,6 +489,15 @@ pub async fn execute(
                         } else {
                             // Send the patch reflecting the changed state of the executed node
                             send_patch(patch_request_sender, patch, When::Soon);
+
+                            // Record the duration of the node for use in future plans
+                            if let Some(durations) = durations.as_deref_mut() {
+                                durations.record(&node_info.node_id, &node_info.node);
+                            }
+
+                            if execute_timed_out {
+                                timed_out.insert(resource_info.resource.clone());
+                            }
                         }
 
                         // Update the node_info record used elsewhere in this function (mainly for the new execution status of nodes)
@@ -416,6 +521,36 @@ pub async fn execute(
         tracing::trace!("Finished stage {}/{}", stage_index + 1, stage_count);
     }
 
+    // Nodes that depend, directly or indirectly, on a node that timed out will not have been run
+    // (because their dependencies did not succeed) but may still have results from a previous
+    // execution. Mark them as stale so that they are not mistaken for being up to date.
+    if !timed_out.is_empty() {
+        let mut stale = timed_out;
+        let mut patches = Vec::new();
+        for task in plan.stages.iter().flat_map(|stage| stage.tasks.iter()) {
+            let resource = &task.resource_info.resource;
+            if stale.contains(resource)
+                || !task
+                    .resource_info
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .any(|dependency| stale.contains(dependency))
+            {
+                continue;
+            }
+            if let Some(node_info) = node_infos.get_mut(resource) {
+                tracing::debug!(
+                    "Marking node `{}` as stale because a dependency timed out",
+                    node_info.node_id
+                );
+                patches.push(node_info.set_execute_required_dependencies_failed());
+            }
+            stale.insert(resource.clone());
+        }
+        send_patches(patch_request_sender, patches, When::Soon);
+    }
+
     // For nodes that were scheduled but never got to run (e.g. because dependencies did not succeed
     // or the plan was cancelled), or were running but got cancelled, reset execute status
     send_patches(
@@ -530,6 +665,25 @@ impl NodeInfo {
         )
     }
 
+    fn set_execute_required_dependencies_failed(&mut self) -> Patch {
+        mutate(
+            &mut self.node,
+            Some(self.node_id.to_string()),
+            Some(self.node_address.clone()),
+            &|node: &mut Node| match node {
+                Node::CodeChunk(CodeChunk {
+                    execute_required, ..
+                })
+                | Node::CodeExpression(CodeExpression {
+                    execute_required, ..
+                }) => {
+                    *execute_required = Some(ExecuteRequired::DependenciesFailed);
+                }
+                _ => {}
+            },
+        )
+    }
+
     fn reset_execute_status(&mut self) -> Patch {
         mutate(
             &mut self.node,
@@ -558,6 +712,11 @@ impl NodeInfo {
     }
 }
 
+/// Is an error the result of a task timing out?
+fn is_timeout(error: &CodeError) -> bool {
+    error.error_type.as_deref().map(String::as_str) == Some("Timeout")
+}
+
 fn get_node_info(node_infos: &BTreeMap<Resource, NodeInfo>, node_id: &str) -> Option<NodeInfo> {
     for node_info in node_infos.values() {
         if node_info.node_id == node_id {
@@ -0,0 +1,516 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use common::{
+    chrono::{DateTime, Utc},
+    eyre::{bail, eyre, Result},
+    serde::{Deserialize, Serialize},
+    serde_json,
+    serde_with::skip_serializing_none,
+};
+use hash_utils::str_sha256_hex;
+use node_patch::{apply, Effect, Patch};
+use stencila_schema::Node;
+
+/// The number of patches recorded between snapshots of the document
+const SNAPSHOT_INTERVAL: usize = 100;
+
+/// The maximum number of versions retained in a history
+const MAX_VERSIONS: usize = 10_000;
+
+/// A persistent history of the changes made to a document
+///
+/// Stored as a JSON Lines file, with one [`HistoryEntry`] per line, in the `.stencila/history`
+/// folder of the document's project. Each entry is a version of the document, numbered from one.
+/// A snapshot of the document is recorded when it is loaded (if it differs from the latest version)
+/// and each edit made to the document thereafter is appended to the history. So that versions can
+/// be reconstructed without applying large numbers of patches, a snapshot is also recorded after
+/// every [`SNAPSHOT_INTERVAL`] patches. When a snapshot is recorded, versions before the earliest
+/// snapshot within the latest [`MAX_VERSIONS`] are removed.
+#[derive(Debug, Clone)]
+pub struct DocumentHistory {
+    /// The path of the history file
+    path: PathBuf,
+
+    /// The number of patches recorded between snapshots
+    snapshot_interval: usize,
+
+    /// The maximum number of versions to retain
+    max_versions: usize,
+
+    /// A summary of the history file (read when first needed)
+    summary: Arc<Mutex<Option<HistorySummary>>>,
+}
+
+/// An entry in the history of a document
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct HistoryEntry {
+    /// The version of the document
+    pub version: usize,
+
+    /// The time that the entry was recorded
+    pub time: DateTime<Utc>,
+
+    /// The id of the actor that made the change (e.g. a web browser client)
+    pub actor: Option<String>,
+
+    /// The sequence number of the patch within the session (see `Patch::prepublish`)
+    pub sequence: Option<u32>,
+
+    /// A snapshot of the whole document
+    pub snapshot: Option<Node>,
+
+    /// The SHA-256 digest of the snapshot (see [`DocumentHistory::digest`])
+    pub digest: Option<String>,
+
+    /// A patch applied to the previous version of the document
+    pub patch: Option<Patch>,
+}
+
+/// A summary of a history file used to avoid reading it each time an entry is recorded
+#[derive(Debug)]
+struct HistorySummary {
+    /// The earliest version in the history
+    first: usize,
+
+    /// The latest version in the history (zero if there are no versions)
+    latest: usize,
+
+    /// The number of patches recorded since the latest snapshot
+    patches: usize,
+
+    /// The digest of the latest version, if it is a snapshot
+    digest: Option<String>,
+}
+
+impl DocumentHistory {
+    /// Create a history for a document
+    ///
+    /// The history file is named using the name of the document file and a hash of its path so that
+    /// documents with the same name in different folders have different histories.
+    pub fn new(project: &Path, path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let hash = str_sha256_hex(&path.to_string_lossy());
+        let path = project
+            .join(".stencila")
+            .join("history")
+            .join([&name, "-", &hash[..12], ".jsonl"].concat());
+        Self {
+            path,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            max_versions: MAX_VERSIONS,
+            summary: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Read the entries in the history
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = fs::File::open(&self.path)?;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line)?);
+        }
+        Ok(entries)
+    }
+
+    /// Reconstruct the document at a version
+    ///
+    /// Starts with the latest snapshot at, or before, the version and applies the
+    /// patches recorded after it.
+    pub fn node_at(&self, version: usize) -> Result<Node> {
+        let entries = self.entries()?;
+        Self::reconstruct(&entries, version)
+    }
+
+    /// Get the index of a version in a list of entries
+    fn index(entries: &[HistoryEntry], version: usize) -> Result<usize> {
+        let (first, latest) = match (entries.first(), entries.last()) {
+            (Some(first), Some(latest)) => (first.version, latest.version),
+            _ => bail!(
+                "Version `{}` is not in the document's history (which is empty)",
+                version
+            ),
+        };
+        if version < first || version > latest {
+            bail!(
+                "Version `{}` is not in the document's history (versions {} to {})",
+                version,
+                first,
+                latest
+            )
+        }
+        Ok(version - first)
+    }
+
+    /// Reconstruct the document at a version from a list of entries
+    fn reconstruct(entries: &[HistoryEntry], version: usize) -> Result<Node> {
+        let index = Self::index(entries, version)?;
+
+        let start = match entries[..=index]
+            .iter()
+            .rposition(|entry| entry.snapshot.is_some())
+        {
+            Some(start) => start,
+            None => bail!(
+                "No snapshot of the document at or before version `{}`",
+                version
+            ),
+        };
+
+        let mut node = entries[start]
+            .snapshot
+            .clone()
+            .expect("Should be a snapshot");
+        for entry in entries.iter().take(index + 1).skip(start + 1) {
+            if let Some(patch) = &entry.patch {
+                if let Err(error) = apply(&mut node, patch) {
+                    bail!(
+                        "While applying patch for version `{}`: {}",
+                        entry.version,
+                        error
+                    )
+                }
+            }
+        }
+        Ok(node)
+    }
+
+    /// Generate a patch that reverts the changes made in a version
+    ///
+    /// The inverse of the version's patch is transformed against the patches
+    /// recorded after it so that it can be applied to the latest version.
+    pub fn revert(&self, version: usize) -> Result<Patch> {
+        let entries = self.entries()?;
+        let index = Self::index(&entries, version)?;
+        let patch = match &entries[index].patch {
+            Some(patch) => patch,
+            None => bail!(
+                "Version `{}` is not a patch so can not be reverted",
+                version
+            ),
+        };
+
+        let node = Self::reconstruct(&entries, version - 1)?;
+        let mut inverse = patch.invert(&node)?;
+
+        // As when transforming concurrent patches, the effects of patches whose target
+        // could not be resolved to an address are unknown so are not transformed against
+        let effects: Vec<Effect> = entries[index + 1..]
+            .iter()
+            .filter_map(|entry| entry.patch.as_ref())
+            .filter(|patch| patch.address.is_some() || patch.target.is_none())
+            .flat_map(|patch| patch.effects())
+            .collect();
+        inverse.transform(&effects);
+
+        Ok(inverse)
+    }
+
+    /// Calculate the digest of a node for comparison with snapshots
+    pub fn digest(node: &Node) -> Result<String> {
+        Ok(str_sha256_hex(&serde_json::to_string(node)?))
+    }
+
+    /// Is a node (with the given digest) different to the latest version in the history?
+    ///
+    /// Only compares against the latest version if it is a snapshot (i.e. rather than reconstructing
+    /// it from patches) so may return `true` for an unchanged node. Because that requires the history
+    /// file to be read the first time it is called, should not be called while holding locks.
+    pub fn is_changed(&self, digest: &str) -> Result<bool> {
+        self.with_summary(|summary| Ok(summary.digest.as_deref() != Some(digest)))
+    }
+
+    /// Record a snapshot of the document
+    ///
+    /// Not recorded if the document is the same as the latest version in the history
+    /// (see [`DocumentHistory::is_changed`]). If the `digest` of the node has already been
+    /// calculated it can be passed to avoid doing that again.
+    pub fn snapshot(&self, node: &Node, digest: Option<String>) -> Result<()> {
+        let digest = match digest {
+            Some(digest) => digest,
+            None => Self::digest(node)?,
+        };
+        self.with_summary(|summary| {
+            if summary.digest.as_deref() == Some(&digest) {
+                return Ok(());
+            }
+            self.record_snapshot(summary, node, digest)
+        })
+    }
+
+    /// Is a snapshot due to be recorded with the next patch?
+    ///
+    /// Does not read the history file, or wait for the history to be recorded to (returning
+    /// `false` in those cases), so can be called while holding locks.
+    pub fn snapshot_due(&self) -> bool {
+        matches!(
+            self.summary.try_lock().as_deref(),
+            Ok(Some(summary)) if summary.patches + 1 >= self.snapshot_interval
+        )
+    }
+
+    /// Record a patch applied to the document
+    ///
+    /// The `node` is the document after the patch was applied (if a snapshot is due, see
+    /// [`DocumentHistory::snapshot_due`]). It is recorded as a snapshot if the number of
+    /// patches since the last snapshot has reached the snapshot interval.
+    pub fn patch(&self, patch: &Patch, node: Option<&Node>) -> Result<()> {
+        let mut json = serde_json::to_value(patch)?;
+
+        // HTML is generated for publishing patches but is not needed in the history
+        if let Some(ops) = json.get_mut("ops").and_then(|ops| ops.as_array_mut()) {
+            for op in ops {
+                if let Some(op) = op.as_object_mut() {
+                    op.remove("html");
+                }
+            }
+        }
+
+        self.with_summary(|summary| {
+            self.append(&HistoryEntry {
+                version: summary.latest + 1,
+                time: Utc::now(),
+                actor: patch.actor.clone(),
+                sequence: patch.sequence,
+                snapshot: None,
+                digest: None,
+                patch: Some(serde_json::from_value(json)?),
+            })?;
+            summary.latest += 1;
+            summary.patches += 1;
+            summary.digest = None;
+
+            if let (Some(node), true) = (node, summary.patches >= self.snapshot_interval) {
+                let digest = Self::digest(node)?;
+                self.record_snapshot(summary, node, digest)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Call a function with the summary of the history, reading the history file if necessary
+    fn with_summary<Func, Type>(&self, func: Func) -> Result<Type>
+    where
+        Func: FnOnce(&mut HistorySummary) -> Result<Type>,
+    {
+        let mut guard = self
+            .summary
+            .lock()
+            .map_err(|error| eyre!("While locking history summary: {}", error))?;
+        if guard.is_none() {
+            let entries = self.entries()?;
+            let patches = entries
+                .iter()
+                .rev()
+                .take_while(|entry| entry.snapshot.is_none())
+                .count();
+            *guard = Some(HistorySummary {
+                first: entries.first().map_or(1, |entry| entry.version),
+                latest: entries.last().map_or(0, |entry| entry.version),
+                patches,
+                digest: entries.last().and_then(|entry| entry.digest.clone()),
+            });
+        }
+        func(guard.as_mut().expect("Should be initialized"))
+    }
+
+    /// Append a snapshot to the history and compact it if necessary
+    fn record_snapshot(
+        &self,
+        summary: &mut HistorySummary,
+        node: &Node,
+        digest: String,
+    ) -> Result<()> {
+        self.append(&HistoryEntry {
+            version: summary.latest + 1,
+            time: Utc::now(),
+            actor: None,
+            sequence: None,
+            snapshot: Some(node.clone()),
+            digest: Some(digest.clone()),
+            patch: None,
+        })?;
+        summary.latest += 1;
+        summary.patches = 0;
+        summary.digest = Some(digest);
+
+        if summary.latest - summary.first >= self.max_versions {
+            summary.first = self.compact(summary.latest + 1 - self.max_versions)?;
+        }
+        Ok(())
+    }
+
+    /// Remove versions before the earliest snapshot at, or after, a version
+    ///
+    /// Returns the earliest version retained.
+    fn compact(&self, version: usize) -> Result<usize> {
+        let entries = self.entries()?;
+        let start = match entries
+            .iter()
+            .position(|entry| entry.version >= version && entry.snapshot.is_some())
+        {
+            Some(start) => start,
+            None => return Ok(entries.first().map_or(1, |entry| entry.version)),
+        };
+
+        let mut content = String::new();
+        for entry in &entries[start..] {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        let temp = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp, content)?;
+        fs::rename(&temp, &self.path)?;
+
+        Ok(entries[start].version)
+    }
+
+    /// Append an entry to the history file
+    fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::tempfile;
+    use node_patch::diff;
+    use stencila_schema::{Article, BlockContent, InlineContent, Paragraph};
+
+    fn article(paras: &[&str]) -> Node {
+        Node::Article(Article {
+            content: Some(
+                paras
+                    .iter()
+                    .map(|text| {
+                        BlockContent::Paragraph(Paragraph {
+                            content: vec![InlineContent::String(text.to_string())],
+                            ..Default::default()
+                        })
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn history() -> Result<()> {
+        let project = tempfile::tempdir()?;
+        let history = DocumentHistory::new(project.path(), &project.path().join("doc.md"));
+        assert!(history.entries()?.is_empty());
+
+        let v1 = article(&["One"]);
+        let v2 = article(&["One", "Two"]);
+        let v3 = article(&["Uno", "Two"]);
+
+        history.snapshot(&v1, None)?;
+        history.patch(&diff(&v1, &v2), Some(&v2))?;
+        history.patch(&diff(&v2, &v3), Some(&v3))?;
+        assert_eq!(history.entries()?.len(), 3);
+
+        // Snapshots of documents that are unchanged since the last snapshot are not recorded
+        history.snapshot(&v3, None)?;
+        history.snapshot(&v3, None)?;
+        assert_eq!(history.entries()?.len(), 4);
+        assert!(
+            !DocumentHistory::new(project.path(), &project.path().join("doc.md"))
+                .is_changed(&DocumentHistory::digest(&v3)?)?
+        );
+
+        // Each version can be reconstructed
+        for (version, node) in [(1, &v1), (2, &v2), (3, &v3), (4, &v3)] {
+            assert_eq!(
+                serde_json::to_value(history.node_at(version)?)?,
+                serde_json::to_value(node)?
+            );
+        }
+        assert!(history.node_at(0).is_err());
+        assert!(history.node_at(5).is_err());
+
+        // Reverting version 2 removes the second paragraph but keeps the later edit
+        let patch = history.revert(2)?;
+        let mut node = v3.clone();
+        apply(&mut node, &patch)?;
+        assert_eq!(
+            serde_json::to_value(node)?,
+            serde_json::to_value(article(&["Uno"]))?
+        );
+
+        // Snapshots can not be reverted
+        assert!(history.revert(1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compaction() -> Result<()> {
+        let project = tempfile::tempdir()?;
+        let mut history = DocumentHistory::new(project.path(), &project.path().join("doc.md"));
+        history.snapshot_interval = 2;
+        history.max_versions = 4;
+
+        let nodes = [
+            article(&["1"]),
+            article(&["1", "2"]),
+            article(&["1", "2", "3"]),
+            article(&["1", "2", "3", "4"]),
+            article(&["1", "2", "3", "4", "5"]),
+        ];
+        history.snapshot(&nodes[0], None)?;
+        for index in 1..nodes.len() {
+            let node = history.snapshot_due().then_some(&nodes[index]);
+            history.patch(&diff(&nodes[index - 1], &nodes[index]), node)?;
+        }
+
+        // A snapshot is recorded after every two patches and versions before
+        // the earliest snapshot within the last four are removed
+        let versions = history
+            .entries()?
+            .iter()
+            .map(|entry| (entry.version, entry.snapshot.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(versions, [(4, true), (5, false), (6, false), (7, true)]);
+
+        assert!(history.node_at(3).is_err());
+        for (version, node) in [
+            (4, &nodes[2]),
+            (5, &nodes[3]),
+            (6, &nodes[4]),
+            (7, &nodes[4]),
+        ] {
+            assert_eq!(
+                serde_json::to_value(history.node_at(version)?)?,
+                serde_json::to_value(node)?
+            );
+        }
+
+        Ok(())
+    }
+}
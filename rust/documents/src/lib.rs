@@ -1,9 +1,14 @@
 mod assemble;
+mod cache;
+mod citations;
 mod compile;
 mod document;
 mod documents;
+mod durations;
 mod executable;
 mod execute;
+mod history;
+mod lint;
 mod messages;
 mod utils;
 
@@ -0,0 +1,443 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    process::Stdio,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use common::{
+    once_cell::sync::Lazy,
+    tokio::{process::Command, time::timeout},
+    tracing,
+};
+use graph_triples::{
+    resources::{self, Diagnostic},
+    Relation, Resource, ResourceInfo,
+};
+use stencila_schema::CodeError;
+
+/// The types of diagnostics generated during compilation
+///
+/// Used to distinguish compile-time diagnostics from errors arising from
+/// execution so that the former can be replaced on each compile.
+const DIAGNOSTIC_TYPES: &[&str] = &[
+    "SyntaxError",
+    "UndefinedSymbol",
+    "UnusedAssignment",
+    "ModuleNotFound",
+];
+
+/// Lint the code resources of a document
+///
+/// In addition to any diagnostics already recorded by parsers (e.g. syntax errors), uses
+/// the relations between resources to add diagnostics for:
+///
+/// - uses of symbols that are not assigned or declared anywhere in the document
+///   (only for languages without imports, since those may bring any symbol into scope)
+///
+/// - assignments to symbols that are not used anywhere in the document
+///
+/// - imports of modules that are not installed (for languages that we know how to check)
+///
+/// To keep compilation fast, only the cached results of previous checks for modules are used.
+/// Returns the modules which need to be checked (because they have not been checked before,
+/// or because a previous check found them missing a while ago) so that the caller can
+/// check them using `check_modules`, off the compile path.
+pub fn lint(resource_infos: &mut [ResourceInfo]) -> HashSet<Module> {
+    let mut assigned = HashSet::new();
+    let mut used = HashSet::new();
+    let mut importing = HashSet::new();
+    let mut modules = HashSet::new();
+    for resource_info in resource_infos.iter() {
+        let language = match &resource_info.resource {
+            Resource::Code(resources::Code { language, .. }) => {
+                language.as_deref().unwrap_or_default().to_lowercase()
+            }
+            _ => continue,
+        };
+        for (relation, object) in resource_info.relations.iter().flatten() {
+            match (relation, object) {
+                (
+                    Relation::Assigns(..) | Relation::Declares(..) | Relation::Alters(..),
+                    Resource::Symbol(symbol),
+                ) => {
+                    assigned.insert(symbol.name.clone());
+                }
+                (Relation::Uses(..), Resource::Symbol(symbol)) => {
+                    used.insert(symbol.name.clone());
+                }
+                (Relation::Imports(..), object) => {
+                    importing.insert(language.clone());
+                    if let Resource::Module(module) = object {
+                        modules.insert((module.language.to_lowercase(), module.name.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut missing = HashSet::new();
+    let mut unchecked = HashSet::new();
+    for module in modules {
+        let (installed, stale) = module_status(&module);
+        if installed == Some(false) {
+            missing.insert(module.clone());
+        }
+        if stale {
+            unchecked.insert(module);
+        }
+    }
+
+    for resource_info in resource_infos.iter_mut() {
+        let language = match &resource_info.resource {
+            Resource::Code(resources::Code { kind, language, .. })
+                if kind == "CodeChunk" || kind == "CodeExpression" =>
+            {
+                language.as_deref().unwrap_or_default().to_lowercase()
+            }
+            _ => continue,
+        };
+
+        let mut diagnostics = Vec::new();
+        for (relation, object) in resource_info.relations.iter().flatten() {
+            match (relation, object) {
+                (Relation::Uses(uses), Resource::Symbol(symbol))
+                    if !assigned.contains(&symbol.name) && !importing.contains(&language) =>
+                {
+                    diagnostics.push(Diagnostic::new(
+                        "UndefinedSymbol",
+                        &format!("Symbol `{}` is not defined", symbol.name),
+                        uses.range,
+                    ))
+                }
+                (Relation::Assigns(assigns), Resource::Symbol(symbol))
+                    if !used.contains(&symbol.name) =>
+                {
+                    diagnostics.push(Diagnostic::new(
+                        "UnusedAssignment",
+                        &format!("Symbol `{}` is assigned but never used", symbol.name),
+                        assigns.range,
+                    ))
+                }
+                (Relation::Imports(imports), Resource::Module(module))
+                    if missing.contains(&(module.language.to_lowercase(), module.name.clone())) =>
+                {
+                    diagnostics.push(Diagnostic::new(
+                        "ModuleNotFound",
+                        &format!("Module `{}` is not installed", module.name),
+                        imports.range,
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        if !diagnostics.is_empty() {
+            resource_info
+                .diagnostics
+                .get_or_insert_with(Vec::new)
+                .append(&mut diagnostics);
+        }
+    }
+
+    unchecked
+}
+
+/// Merge the diagnostics of a resource into the existing errors of a node
+///
+/// Any existing compile-time diagnostics are removed but errors from execution are retained.
+pub fn merge_diagnostics(
+    errors: Option<Vec<CodeError>>,
+    diagnostics: Option<&Vec<Diagnostic>>,
+) -> Option<Vec<CodeError>> {
+    let errors = errors
+        .into_iter()
+        .flatten()
+        .filter(|error| !is_diagnostic(error))
+        .chain(
+            diagnostics
+                .into_iter()
+                .flatten()
+                .map(|diagnostic| diagnostic.to_code_error()),
+        )
+        .collect::<Vec<CodeError>>();
+
+    if errors.is_empty() {
+        None
+    } else {
+        Some(errors)
+    }
+}
+
+/// Is a `CodeError` a compile-time diagnostic?
+///
+/// Diagnostics have one of the `DIAGNOSTIC_TYPES` and a `code_location`
+/// (see `Diagnostic::to_code_error`).
+fn is_diagnostic(error: &CodeError) -> bool {
+    error.code_location.is_some()
+        && error.error_type.as_deref().map_or(false, |error_type| {
+            DIAGNOSTIC_TYPES.contains(&error_type.as_str())
+        })
+}
+
+/// A module identified by its (lowercase) language and name
+pub type Module = (String, String);
+
+/// The duration after which a check finding that a module is missing is considered stale
+///
+/// Positive results are cached indefinitely but negative results are rechecked after this
+/// duration so that installing a module is reflected in subsequent compiles.
+const MISSING_TTL: Duration = Duration::from_secs(60);
+
+/// The results of previous checks of whether modules are installed
+static CHECKED: Lazy<Mutex<HashMap<Module, (bool, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Modules that are currently being checked in the background
+static CHECKING: Lazy<Mutex<HashSet<Module>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Get the cached status of a module
+///
+/// Returns whether the module is installed (if known) and whether it needs to be checked.
+/// Modules that are already being checked do not need to be checked again.
+fn module_status(module: &Module) -> (Option<bool>, bool) {
+    let installed = CHECKED
+        .lock()
+        .ok()
+        .and_then(|checked| checked.get(module).cloned());
+    let stale = match installed {
+        Some((true, ..)) => false,
+        Some((false, time)) => time.elapsed() > MISSING_TTL,
+        None => true,
+    };
+    let stale = stale
+        && CHECKING
+            .lock()
+            .map_or(false, |mut checking| checking.insert(module.clone()));
+    (installed.map(|(installed, ..)| installed), stale)
+}
+
+/// Check whether modules are installed and cache the results
+///
+/// Returns `true` if the installation status of any of the modules has changed
+/// (including becoming known), in which case the document should be recompiled.
+pub async fn check_modules(project: &Path, modules: HashSet<Module>) -> bool {
+    let mut changed = false;
+    for module in modules {
+        let installed = module_installed(project, &module.0, &module.1).await;
+        if let Ok(mut checking) = CHECKING.lock() {
+            checking.remove(&module);
+        }
+
+        let installed = match installed {
+            Some(installed) => installed,
+            None => continue,
+        };
+        if let Ok(mut checked) = CHECKED.lock() {
+            let previous = checked.insert(module, (installed, Instant::now()));
+            if previous.map(|(previous, ..)| previous) != Some(installed) {
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Check whether a module is installed
+///
+/// Returns `None` if the language is not supported, or if the check could
+/// not be done (e.g. because the language's runtime is not available).
+async fn module_installed(project: &Path, language: &str, name: &str) -> Option<bool> {
+    let (program, args): (&str, Vec<&str>) = match language {
+        "python" => (
+            "python3",
+            vec![
+                "-c",
+                "import importlib.util, sys; sys.exit(0 if importlib.util.find_spec(sys.argv[1]) else 1)",
+                // Only check the top level package (finding sub-modules requires importing parents)
+                name.split('.').next().unwrap_or(name),
+            ],
+        ),
+        "r" => (
+            "Rscript",
+            vec![
+                "-e",
+                "quit(status = if (nzchar(system.file(package = commandArgs(TRUE)[1]))) 0 else 1)",
+                "--args",
+                name,
+            ],
+        ),
+        "javascript" => (
+            "node",
+            vec!["-e", "require.resolve(process.argv[1])", name],
+        ),
+        _ => return None,
+    };
+
+    let child = Command::new(program)
+        .args(args)
+        .current_dir(project)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    match timeout(Duration::from_secs(10), child).await {
+        Ok(Ok(status)) => Some(status.success()),
+        Ok(Err(error)) => {
+            tracing::debug!("Unable to check if `{}` is installed: {}", name, error);
+            None
+        }
+        Err(..) => {
+            tracing::debug!("Timed out checking if `{}` is installed", name);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::tokio;
+    use graph_triples::relations;
+
+    #[tokio::test]
+    async fn lint_relations() {
+        let path = Path::new("doc.md");
+        let chunk = |id: &str, relations: Vec<(Relation, Resource)>| {
+            let mut resource_info = ResourceInfo::default(resources::code(
+                path,
+                id,
+                "CodeChunk",
+                Some("Python".to_string()),
+            ));
+            resource_info.relations = Some(relations);
+            resource_info
+        };
+
+        let mut resource_infos = vec![
+            chunk(
+                "cc-1",
+                vec![
+                    (
+                        relations::assigns((0, 0, 0, 1)),
+                        resources::symbol(path, "a", ""),
+                    ),
+                    (
+                        relations::assigns((1, 0, 1, 1)),
+                        resources::symbol(path, "b", ""),
+                    ),
+                ],
+            ),
+            chunk(
+                "cc-2",
+                vec![
+                    (
+                        relations::uses((0, 4, 0, 5)),
+                        resources::symbol(path, "a", ""),
+                    ),
+                    (
+                        relations::uses((0, 8, 0, 9)),
+                        resources::symbol(path, "c", ""),
+                    ),
+                ],
+            ),
+        ];
+        lint(&mut resource_infos);
+
+        assert_eq!(
+            resource_infos[0].diagnostics,
+            Some(vec![Diagnostic::new(
+                "UnusedAssignment",
+                "Symbol `b` is assigned but never used",
+                (1, 0, 1, 1)
+            )])
+        );
+        assert_eq!(
+            resource_infos[1].diagnostics,
+            Some(vec![Diagnostic::new(
+                "UndefinedSymbol",
+                "Symbol `c` is not defined",
+                (0, 8, 0, 9)
+            )])
+        );
+
+        // Diagnostics replace previous diagnostics but not execution errors
+        let runtime = CodeError {
+            error_type: Some(Box::new("NameError".to_string())),
+            error_message: "name 'c' is not defined".to_string(),
+            ..Default::default()
+        };
+        let errors = merge_diagnostics(
+            Some(vec![
+                runtime.clone(),
+                Diagnostic::new("UndefinedSymbol", "Old", (0, 0, 0, 1)).to_code_error(),
+            ]),
+            resource_infos[1].diagnostics.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], runtime);
+        assert_eq!(errors[1].error_message, "Symbol `c` is not defined");
+        assert_eq!(errors[1].code_location, Some(vec![0, 8, 0, 9]));
+
+        // Imports disable checks for undefined symbols in the same language
+        resource_infos[1].diagnostics = None;
+        resource_infos[0].relations.as_mut().unwrap().push((
+            relations::imports((2, 0, 2, 10)),
+            resources::file(Path::new("module.py")),
+        ));
+        lint(&mut resource_infos);
+        assert_eq!(resource_infos[1].diagnostics, None);
+    }
+
+    #[tokio::test]
+    async fn lint_modules() {
+        let path = Path::new("doc.md");
+        let mut resource_info = ResourceInfo::default(resources::code(
+            path,
+            "cc-1",
+            "CodeChunk",
+            Some("Python".to_string()),
+        ));
+        resource_info.relations = Some(vec![(
+            relations::imports((0, 0, 0, 30)),
+            resources::module("python", "stencila_lint_missing_module"),
+        )]);
+        let mut resource_infos = vec![resource_info];
+
+        // Unchecked modules are returned rather than checked
+        let unchecked = lint(&mut resource_infos);
+        assert_eq!(
+            unchecked,
+            HashSet::from([(
+                "python".to_string(),
+                "stencila_lint_missing_module".to_string()
+            )])
+        );
+        assert_eq!(resource_infos[0].diagnostics, None);
+
+        // Modules being checked are not returned again
+        resource_infos[0].diagnostics = None;
+        assert!(lint(&mut resource_infos).is_empty());
+
+        if !check_modules(Path::new("."), unchecked).await {
+            // Python is not available so unable to check
+            return;
+        }
+
+        // Once checked, the module is reported as missing without being checked again
+        resource_infos[0].diagnostics = None;
+        assert!(lint(&mut resource_infos).is_empty());
+        assert_eq!(
+            resource_infos[0].diagnostics,
+            Some(vec![Diagnostic::new(
+                "ModuleNotFound",
+                "Module `stencila_lint_missing_module` is not installed",
+                (0, 0, 0, 30)
+            )])
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use common::strum::EnumString;
+use common::{strum::EnumString, tokio::sync::oneshot};
 use graph::{PlanOrdering, PlanScope};
 use node_patch::Patch;
 use uuids::uuid_family;
@@ -25,22 +25,41 @@ impl When {
 
 uuid_family!(RequestId, "re");
 
+/// How a patch request relates to the undo/redo history of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum History {
+    /// The patch is not recorded in the history (e.g. patches made when compiling or executing)
+    Ignore,
+    /// The patch is an edit that can be undone
+    Edit,
+    /// Undo the last edit (the patch of the request is not used)
+    Undo,
+    /// Redo the last undone edit (the patch of the request is not used)
+    Redo,
+}
+
 /// An internal request to patch a document
 #[derive(Debug)]
 pub struct PatchRequest {
     pub ids: Vec<RequestId>,
     pub patch: Patch,
+    pub history: History,
     pub when: When,
     pub assemble: When,
     pub compile: When,
     pub execute: When,
     pub write: When,
+    /// A channel to send whether an undo, or redo, request was fulfilled on (`false`
+    /// if there was nothing to undo or redo)
+    pub history_sender: Option<oneshot::Sender<bool>>,
 }
 
 impl PatchRequest {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ids: Vec<RequestId>,
         patch: Patch,
+        history: History,
         when: When,
         assemble: When,
         compile: When,
@@ -50,11 +69,13 @@ impl PatchRequest {
         Self {
             ids,
             patch,
+            history,
             when,
             assemble,
             compile,
             execute,
             write,
+            history_sender: None,
         }
     }
 }
@@ -118,6 +139,7 @@ pub struct ExecuteRequest {
     pub start: Option<String>,
     pub ordering: Option<PlanOrdering>,
     pub max_concurrency: Option<usize>,
+    pub no_cache: bool,
 }
 
 impl ExecuteRequest {
@@ -128,6 +150,7 @@ impl ExecuteRequest {
         start: Option<String>,
         ordering: Option<PlanOrdering>,
         max_concurrency: Option<usize>,
+        no_cache: bool,
     ) -> Self {
         Self {
             ids,
@@ -136,6 +159,7 @@ impl ExecuteRequest {
             start,
             ordering,
             max_concurrency,
+            no_cache,
         }
     }
 }
@@ -71,7 +71,7 @@ async fn md_articles() -> Result<()> {
         let tag_map = Arc::new(RwLock::new(TagMap::default()));
 
         // Assemble the article and snapshot the result
-        let address_map = assemble(path, &root, &call_docs, &patch_request_sender).await?;
+        let address_map = assemble(path, project, &root, &call_docs, &patch_request_sender).await?;
         snapshot_set_suffix(&[name, "-assemble"].concat(), || {
             assert_json_snapshot!(&address_map)
         });
@@ -86,6 +86,7 @@ async fn md_articles() -> Result<()> {
             &tag_map,
             &call_docs,
             &patch_request_sender,
+            None,
         )
         .await?;
         snapshot_set_suffix(&[name, "-compile"].concat(), || {
@@ -126,19 +127,90 @@ async fn md_articles() -> Result<()> {
             ),
         ] {
             let plan = graph
-                .plan(None, Some(kernels.clone()), None, Some(options))
+                .plan(None, Some(kernels.clone()), None, None, Some(options))
                 .await?;
             snapshot_set_suffix(&[name, "-", suffix].concat(), || {
                 assert_json_snapshot!(&plan)
             });
         }
 
+        // Generate a cost-aware plan and check that it has the same tasks as the topological
+        // plan and that the dependencies of each task are in earlier stages
+        let topological = graph
+            .plan(
+                None,
+                Some(kernels.clone()),
+                None,
+                None,
+                Some(PlanOptions {
+                    ordering: PlanOrdering::Topological,
+                    max_concurrency: 10,
+                }),
+            )
+            .await?;
+        let resources = |plan: &Plan| {
+            plan.stages
+                .iter()
+                .flat_map(|stage| stage.tasks.iter())
+                .map(|task| task.resource_info.resource.clone())
+                .collect::<Vec<_>>()
+        };
+        let durations = resources(&topological)
+            .iter()
+            .enumerate()
+            .filter_map(|(index, resource)| {
+                resource
+                    .node_id()
+                    .map(|node_id| (node_id.to_string(), (index % 3) as f64))
+            })
+            .collect();
+        for max_concurrency in [0, 1, 3, 10] {
+            let cost = graph
+                .plan(
+                    None,
+                    Some(kernels.clone()),
+                    None,
+                    Some(&durations),
+                    Some(PlanOptions {
+                        ordering: PlanOrdering::Cost,
+                        max_concurrency,
+                    }),
+                )
+                .await?;
+
+            let mut expected = resources(&topological);
+            let mut actual = resources(&cost);
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected);
+
+            let mut scheduled = Vec::new();
+            for stage in &cost.stages {
+                assert!(stage.tasks.len() <= max_concurrency.max(1));
+                assert!(stage.tasks.len() == 1 || stage.tasks.iter().all(|task| task.is_fork));
+                for task in &stage.tasks {
+                    for dependency in task.resource_info.dependencies.iter().flatten() {
+                        if actual.contains(dependency) {
+                            assert!(scheduled.contains(dependency));
+                        }
+                    }
+                }
+                scheduled.extend(
+                    stage
+                        .tasks
+                        .iter()
+                        .map(|task| task.resource_info.resource.clone()),
+                );
+            }
+        }
+
         // Execute the article (with topological execution plan) and snapshot the resultant patches
         let plan = graph
             .plan(
                 None,
                 Some(kernels.clone()),
                 None,
+                None,
                 Some(PlanOptions {
                     ordering: PlanOrdering::Topological,
                     max_concurrency: 10,
@@ -177,6 +249,8 @@ async fn md_articles() -> Result<()> {
             &tag_map,
             &Arc::new(RwLock::new(KernelSpace::new(None, None))),
             &call_docs,
+            None,
+            None,
             &patch_request_sender,
             &mut cancel_request_receiver,
         )
@@ -253,7 +327,14 @@ async fn assemble_compile_plan_execute(node: Node) -> Result<(Plan, Vec<Patch>)>
 
     let (_cancel_request_sender, mut cancel_request_receiver) = mpsc::channel::<CancelRequest>(1);
 
-    let address_map = assemble(&PathBuf::new(), &root, &call_docs, &patch_request_sender).await?;
+    let address_map = assemble(
+        &PathBuf::new(),
+        &PathBuf::new(),
+        &root,
+        &call_docs,
+        &patch_request_sender,
+    )
+    .await?;
     let address_map = &Arc::new(RwLock::new(address_map));
 
     let graph = compile(
@@ -264,10 +345,11 @@ async fn assemble_compile_plan_execute(node: Node) -> Result<(Plan, Vec<Patch>)>
         &tags,
         &call_docs,
         &patch_request_sender,
+        None,
     )
     .await?;
 
-    let plan = graph.plan(None, None, None, None).await?;
+    let plan = graph.plan(None, None, None, None, None).await?;
 
     execute(
         &plan,
@@ -276,6 +358,8 @@ async fn assemble_compile_plan_execute(node: Node) -> Result<(Plan, Vec<Patch>)>
         &tags,
         &Arc::new(RwLock::new(KernelSpace::new(None, None))),
         &call_docs,
+        None,
+        None,
         &patch_request_sender,
         &mut cancel_request_receiver,
     )
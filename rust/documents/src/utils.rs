@@ -9,7 +9,7 @@ use node_patch::Patch;
 use node_pointer::resolve;
 use stencila_schema::Node;
 
-use crate::messages::{PatchRequest, RequestId, When};
+use crate::messages::{History, PatchRequest, RequestId, When};
 
 /// Get the [`Node`] corresponding to a [`Resource`]
 ///
@@ -65,6 +65,7 @@ pub(crate) fn send_patch(
         if let Err(..) = patch_sender.send(PatchRequest::new(
             vec![RequestId::new()],
             patch,
+            History::Ignore,
             When::Now,
             When::Never,
             compile,
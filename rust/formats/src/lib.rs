@@ -15,7 +15,9 @@ use common::{
 #[strum(serialize_all = "lowercase", crate = "common::strum")]
 pub enum Format {
     Bash,
+    BibTeX,
     Calc,
+    Csl,
     Date,
     Directory,
     Dockerfile,
@@ -25,6 +27,7 @@ pub enum Format {
     Gif,
     Html,
     Ipynb,
+    Jats,
     JavaScript,
     Jpeg,
     Json,
@@ -35,6 +38,7 @@ pub enum Format {
     Markdown,
     Mp3,
     Mp4,
+    Myst,
     Odt,
     Ogg,
     Ogv,
@@ -44,8 +48,10 @@ pub enum Format {
     PlainText,
     Png,
     Python,
+    QuartoMarkdown,
     R,
     RMarkdown,
+    Ris,
     Rpng,
     Rust,
     Shell,
@@ -94,13 +100,21 @@ impl Format {
             Format::Gdoc => FormatSpec::new("Google Docs", "gdoc", &[], false, true, FormatNodeType::Article),
             Format::Html => FormatSpec::new("HTML", "html", &[], false, true, FormatNodeType::Article),
             Format::Ipynb => FormatSpec::new("Jupyter Notebook", "ipynb", &[], false, true, FormatNodeType::Article),
+            Format::Jats => FormatSpec::new("JATS", "jats", &["jats-xml"], false, true, FormatNodeType::Article),
             Format::Markdown => FormatSpec::new("Markdown", "md", &[], false, true, FormatNodeType::Article),
+            Format::Myst => FormatSpec::new("MyST Markdown", "myst", &[], false, true, FormatNodeType::Article),
             Format::Odt => FormatSpec::new("Open Office Text", "odt", &[], true, true, FormatNodeType::Article),
             Format::Pandoc => FormatSpec::new("Pandoc JSON", "pandoc", &[], false, true, FormatNodeType::Article),
             Format::Pdf => FormatSpec::new("Portable Document Format", "pdf", &[], true, false, FormatNodeType::Article),
+            Format::QuartoMarkdown => FormatSpec::new("Quarto Markdown", "qmd", &[], false, true, FormatNodeType::Article),
             Format::RMarkdown => FormatSpec::new("R Markdown", "rmd", &[], false, true, FormatNodeType::Article),
             Format::LaTeX => FormatSpec::new("LaTeX", "latex", &["tex"], false, true, FormatNodeType::Article),
 
+            // Bibliographic formats
+            Format::BibTeX => FormatSpec::new("BibTeX", "bib", &["bibtex"], false, false, FormatNodeType::Article),
+            Format::Csl => FormatSpec::new("CSL-JSON", "csl", &["csl-json"], false, false, FormatNodeType::Article),
+            Format::Ris => FormatSpec::new("RIS", "ris", &[], false, false, FormatNodeType::Article),
+
             // Audio formats
             Format::Flac => FormatSpec::new("FLAC", "flac", &[], true, true, FormatNodeType::AudioObject),
             Format::Mp3 => FormatSpec::new("MP3", "mp3", &[], true, true, FormatNodeType::AudioObject),
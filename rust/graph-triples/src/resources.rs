@@ -17,9 +17,9 @@ use common::{
 };
 use hash_utils::str_seahash;
 use path_utils::path_slash::PathExt;
-use stencila_schema::{Cord, ExecuteAuto};
+use stencila_schema::{CodeError, Cord, ExecuteAuto};
 
-use crate::{Pairs, Relation};
+use crate::{relations::Range, Pairs, Relation};
 
 /// A resource in a dependency graph (the nodes of the graph)
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema, Serialize)]
@@ -450,6 +450,12 @@ pub struct ResourceInfo {
 
     /// The tags defined in the resource (if it is a `CodeChunk`)
     pub tags: TagMap,
+
+    /// Diagnostics from static analysis of the resource's code (e.g. syntax errors)
+    ///
+    /// Set by parsers. Diagnostics that require knowledge of other resources (e.g. use
+    /// of undefined symbols) are derived after the graph is built.
+    pub diagnostics: Option<Vec<Diagnostic>>,
 }
 
 impl ResourceInfo {
@@ -467,6 +473,7 @@ impl ResourceInfo {
             execute_digest: None,
             execute_failed: None,
             tags: TagMap::default(),
+            diagnostics: None,
         }
     }
 
@@ -492,6 +499,7 @@ impl ResourceInfo {
             execute_digest,
             execute_failed,
             tags: TagMap::default(),
+            diagnostics: None,
         }
     }
 
@@ -583,6 +591,47 @@ impl ResourceInfo {
     }
 }
 
+/// A diagnostic from static analysis of the code of a resource
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct Diagnostic {
+    /// The type of diagnostic e.g. "SyntaxError", "UndefinedSymbol"
+    pub error_type: String,
+
+    /// The diagnostic message
+    pub message: String,
+
+    /// The range within the code that the diagnostic applies to
+    pub range: Range,
+}
+
+impl Diagnostic {
+    /// Create a new diagnostic
+    pub fn new(error_type: &str, message: &str, range: Range) -> Self {
+        Self {
+            error_type: error_type.to_string(),
+            message: message.to_string(),
+            range,
+        }
+    }
+
+    /// Convert the diagnostic into a `CodeError` with the range as its `code_location`
+    pub fn to_code_error(&self) -> CodeError {
+        let (line_start, column_start, line_end, column_end) = self.range;
+        CodeError {
+            error_type: Some(Box::new(self.error_type.clone())),
+            error_message: self.message.clone(),
+            code_location: Some(
+                [line_start, column_start, line_end, column_end]
+                    .into_iter()
+                    .map(|value| value as i64)
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+}
+
 /// A change to a resource
 #[derive(Debug, Serialize)]
 #[serde(crate = "common::serde")]
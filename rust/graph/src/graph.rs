@@ -26,8 +26,8 @@ use common::{
     strum::Display,
 };
 use graph_triples::{
-    direction, relations, stencila_schema::ExecuteAuto, Direction, Pairs, Relation, Resource,
-    ResourceInfo, TagMap, Triple,
+    direction, relations, resources::Symbol, stencila_schema::ExecuteAuto, Direction, Pairs,
+    Relation, Resource, ResourceInfo, TagMap, Triple,
 };
 use hash_utils::seahash;
 use kernels::{Kernel, KernelSelector};
@@ -262,6 +262,14 @@ impl Graph {
         }
     }
 
+    /// Get the [`Resource`]s in the graph
+    ///
+    /// Unlike `get_resource_infos`, this includes resources that have only been added as
+    /// the subject or object of a triple (e.g. a `Module` imported by code).
+    pub fn get_resources(&self) -> Vec<&Resource> {
+        self.indices.keys().collect()
+    }
+
     /// Get [`ResourceInfo`] objects in the graph
     pub fn get_resource_infos(&self) -> &BTreeMap<Resource, ResourceInfo> {
         &self.resources
@@ -540,12 +548,18 @@ impl Graph {
     ///
     /// - `tags`: The document's global tags
     ///
+    /// - `durations`: The durations, in seconds, of previous executions of nodes, keyed by node id
+    ///
     /// - `options`: Options for the plan
+    ///
+    /// The `durations` are used to estimate the duration of tasks, and for ordering tasks when
+    /// using `PlanOrdering::Cost`.
     pub async fn plan(
         &self,
         start: Option<Resource>,
         kernels: Option<Vec<Kernel>>,
         tags: Option<&TagMap>,
+        durations: Option<&HashMap<String, f64>>,
         options: Option<PlanOptions>,
     ) -> Result<Plan> {
         let kernels = match kernels {
@@ -559,12 +573,22 @@ impl Graph {
             None => &empty_tags,
         };
 
+        let empty_durations = HashMap::new();
+        let durations = match durations {
+            Some(durations) => durations,
+            None => &empty_durations,
+        };
+
         let options = options.unwrap_or_default();
-        match options.ordering {
+        let mut plan = match options.ordering {
             PlanOrdering::Single => self.plan_single(start, kernels, tags, options),
             PlanOrdering::Appearance => self.plan_appearance(start, kernels, tags, options),
             PlanOrdering::Topological => self.plan_topological(start, kernels, tags, options),
-        }
+            PlanOrdering::Cost => self.plan_cost(start, kernels, tags, durations, options),
+        }?;
+        plan.estimate_durations(durations);
+
+        Ok(plan)
     }
 
     /// Generate an execution plan for a single node
@@ -630,6 +654,7 @@ impl Graph {
                     resource_info,
                     kernel_selector,
                     is_fork,
+                    estimated_duration: None,
                 }],
             }],
         })
@@ -712,6 +737,7 @@ impl Graph {
                 resource_info: resource_info.clone(),
                 kernel_selector,
                 is_fork,
+                estimated_duration: None,
             };
 
             // Add the task to a new, or the current, stage
@@ -905,6 +931,7 @@ impl Graph {
                 resource_info: resource_info.clone(),
                 kernel_selector,
                 is_fork,
+                estimated_duration: None,
             };
 
             // Add the task to a new, or the current, stage
@@ -933,6 +960,257 @@ impl Graph {
         })
     }
 
+    /// Generate an execution plan based on the estimated cost of tasks
+    ///
+    /// The generated plan includes the same tasks as for `plan_topological` but orders
+    /// them using the durations of previous executions so that tasks on the critical path
+    /// (the longest chain of dependent tasks) are executed first and tasks that can run in
+    /// a kernel fork are packed into stages of up to `max_concurrency` tasks.
+    ///
+    /// Tasks that are not run in a fork may have side effects on the state of their kernel
+    /// so they are always executed in the same relative order as in `plan_topological`.
+    /// Tasks that are run in a fork are executed before any later task (that is not run in
+    /// a fork) that modifies a symbol that they use, if an earlier task has already modified it.
+    /// Tasks that have not been executed before are assumed to take the mean duration of
+    /// those that have.
+    ///
+    /// # Arguments
+    ///
+    /// - `start`: The node at which the plan should start (see `plan_topological`).
+    ///
+    /// - `kernels`: The kernels available to execute the plan
+    ///
+    /// - `tags`: The document's global tags
+    ///
+    /// - `durations`: The durations, in seconds, of previous executions of nodes, keyed by node id
+    ///
+    /// - `options`: Options for the plan
+    ///
+    /// Note that the ids of code nodes are often generated from their position in the document
+    /// (e.g. `cc-3`) so, if nodes are added or removed, an estimated duration may be for a different
+    /// node. An estimate is only used to order tasks so this affects the efficiency of the plan but
+    /// not its correctness.
+    pub fn plan_cost(
+        &self,
+        start: Option<Resource>,
+        kernels: Vec<Kernel>,
+        tags: &TagMap,
+        durations: &HashMap<String, f64>,
+        options: PlanOptions,
+    ) -> Result<Plan> {
+        // Get the tasks, in topological order, and determine which of them can run in a fork
+        let tasks: Vec<(PlanTask, bool)> = self
+            .plan_topological(start, kernels.clone(), tags, options.clone())?
+            .stages
+            .into_iter()
+            .flat_map(|stage| stage.tasks)
+            .map(|task| {
+                let kernel_forkable = task
+                    .kernel_selector
+                    .select(&kernels)
+                    .map(|kernel| kernel.forkable)
+                    .unwrap_or(false);
+                let forkable =
+                    Self::should_run_in_fork(kernel_forkable, &task.resource_info, 0, &options);
+                (task, forkable)
+            })
+            .collect();
+
+        // Estimate the duration of each task, using the mean of known durations for those
+        // that have not been executed before
+        let known: Vec<f64> = tasks
+            .iter()
+            .filter_map(|(task, ..)| {
+                task.resource_info
+                    .resource
+                    .node_id()
+                    .and_then(|node_id| durations.get(node_id))
+                    .cloned()
+            })
+            .collect();
+        let mean = if known.is_empty() {
+            1.0
+        } else {
+            known.iter().sum::<f64>() / known.len() as f64
+        };
+        let costs: Vec<f64> = tasks
+            .iter()
+            .map(|(task, ..)| {
+                task.resource_info
+                    .resource
+                    .node_id()
+                    .and_then(|node_id| durations.get(node_id))
+                    .cloned()
+                    .unwrap_or(mean)
+            })
+            .collect();
+
+        // Determine the indices of the dependencies of each task that are within the plan
+        let indices: HashMap<&Resource, usize> = tasks
+            .iter()
+            .enumerate()
+            .map(|(index, (task, ..))| (&task.resource_info.resource, index))
+            .collect();
+        let mut dependencies: Vec<Vec<usize>> = tasks
+            .iter()
+            .map(|(task, ..)| {
+                task.resource_info
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .filter_map(|dependency| indices.get(dependency).cloned())
+                    .collect()
+            })
+            .collect();
+
+        // The dependency graph makes a task that uses a symbol depend on all the tasks that modify
+        // it. But a forked task that uses a symbol, which has already been modified by an earlier
+        // task, should use that value and not one from a later task. So that the forked task is not
+        // deferred until after the symbol has been changed again, make any later, non-forked, task
+        // that modifies the symbol depend on the forked task (rather than the reverse).
+        let appearance: HashMap<&Resource, usize> = self
+            .appearance_order
+            .iter()
+            .enumerate()
+            .map(|(position, resource)| (resource, position))
+            .collect();
+        let mut first_modified: HashMap<Symbol, usize> = HashMap::new();
+        for resource_info in self.resources.values() {
+            if let Some(&position) = appearance.get(&resource_info.resource) {
+                for symbol in resource_info.symbols_modified() {
+                    let first = first_modified.entry(symbol).or_insert(position);
+                    *first = (*first).min(position);
+                }
+            }
+        }
+        let positions: Vec<usize> = tasks
+            .iter()
+            .map(|(task, ..)| {
+                appearance
+                    .get(&task.resource_info.resource)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let modified: Vec<Vec<Symbol>> = tasks
+            .iter()
+            .map(|(task, ..)| task.resource_info.symbols_modified())
+            .collect();
+        for (reader, (task, forkable)) in tasks.iter().enumerate() {
+            if !forkable {
+                continue;
+            }
+            let used: Vec<Symbol> = task
+                .resource_info
+                .symbols_used()
+                .into_iter()
+                .filter(|symbol| {
+                    matches!(first_modified.get(symbol), Some(&first) if first < positions[reader])
+                })
+                .collect();
+            for writer in 0..tasks.len() {
+                if tasks[writer].1
+                    || positions[writer] <= positions[reader]
+                    || !used.iter().any(|symbol| modified[writer].contains(symbol))
+                {
+                    continue;
+                }
+                dependencies[reader].retain(|&dependency| dependency != writer);
+                if !dependencies[writer].contains(&reader) {
+                    dependencies[writer].push(reader);
+                }
+            }
+        }
+
+        // Calculate the priority of each task as the length of the longest path from it
+        // to the end of the plan (its "bottom level"). Because of the dependencies added above,
+        // tasks may no longer be in topological order, so repeat until there are no changes.
+        let mut priorities = costs.clone();
+        loop {
+            let mut changed = false;
+            for index in (0..tasks.len()).rev() {
+                for &dependency in &dependencies[index] {
+                    let priority = costs[dependency] + priorities[index];
+                    if priority > priorities[dependency] {
+                        priorities[dependency] = priority;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Schedule tasks into stages. At each step, of the tasks that are ready (all their
+        // dependencies have been scheduled in a previous stage), choose the one with the
+        // highest priority. If it is to run in a fork then pack the stage with other ready,
+        // forkable tasks in order of priority.
+        let max_concurrency = options.max_concurrency.max(1);
+        let mut scheduled = vec![false; tasks.len()];
+        let mut stage_indices: Vec<Vec<usize>> = Vec::new();
+        while scheduled.iter().any(|done| !done) {
+            let is_ready = |index: usize| {
+                !scheduled[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| scheduled[dependency])
+            };
+
+            // The next, non-forked task can only be run if it is ready
+            let next_unforked = (0..tasks.len())
+                .find(|&index| !scheduled[index] && !tasks[index].1)
+                .filter(|&index| is_ready(index));
+
+            let mut forked: Vec<usize> = (0..tasks.len())
+                .filter(|&index| tasks[index].1 && is_ready(index))
+                .collect();
+            forked.sort_by(|a, b| {
+                priorities[*b]
+                    .partial_cmp(&priorities[*a])
+                    .unwrap_or(Ordering::Equal)
+            });
+
+            let stage = match (next_unforked, forked.first()) {
+                (Some(unforked), Some(fork)) if priorities[unforked] > priorities[*fork] => {
+                    vec![unforked]
+                }
+                (_, Some(..)) => forked.into_iter().take(max_concurrency).collect(),
+                (Some(unforked), None) => vec![unforked],
+                (None, None) => bail!("Unable to schedule tasks: dependency cycle in plan"),
+            };
+
+            for &index in &stage {
+                scheduled[index] = true;
+            }
+            stage_indices.push(stage);
+        }
+
+        // Move the tasks into the stages
+        let mut tasks: Vec<Option<(PlanTask, bool)>> = tasks.into_iter().map(Some).collect();
+        let stages = stage_indices
+            .into_iter()
+            .map(|indices| PlanStage {
+                tasks: indices
+                    .into_iter()
+                    .filter_map(|index| tasks[index].take())
+                    .map(|(task, forkable)| PlanTask {
+                        is_fork: forkable,
+                        ..task
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Plan {
+            options: PlanOptions {
+                ordering: PlanOrdering::Cost,
+                ..options
+            },
+            stages,
+        })
+    }
+
     /// Convert the graph to some format
     pub fn to_format(&self, format: &str) -> Result<String> {
         Ok(match format {
@@ -1232,3 +1510,81 @@ pub fn schemas() -> Result<serde_json::Value> {
         }),
     ]))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graph_triples::{relations::NULL_RANGE, resources};
+    use kernels::KernelType;
+
+    /// Test that, in a cost-aware plan, a task run in a fork is not deferred until after a
+    /// later task, that is not run in a fork, changes a symbol that it uses
+    #[test]
+    fn plan_cost_fork_before_writer() -> Result<()> {
+        let path = Path::new("doc.md");
+        let symbol = resources::symbol(path, "a", "");
+        let chunk = |id: &str, relation: Relation| {
+            let mut resource_info = ResourceInfo::default(resources::code(
+                path,
+                id,
+                "CodeChunk",
+                Some("calc".to_string()),
+            ));
+            resource_info.relations = Some(vec![(relation, symbol.clone())]);
+            resource_info
+        };
+
+        // `a = 1`, `print(a)` (which can be run in a fork), `a = 2`
+        let graph = Graph::from_resource_infos(
+            path,
+            vec![
+                chunk("cc-1", relations::assigns(NULL_RANGE)),
+                chunk("cc-2", relations::uses(NULL_RANGE)),
+                chunk("cc-3", relations::assigns(NULL_RANGE)),
+            ],
+        )?;
+        let kernels = vec![Kernel::new(
+            "calc",
+            KernelType::Builtin,
+            &["calc"],
+            true,
+            false,
+            true,
+        )];
+
+        // Make the reader much shorter than the last writer so that it has a lower priority
+        let durations = HashMap::from([
+            ("cc-1".to_string(), 1.0),
+            ("cc-2".to_string(), 0.1),
+            ("cc-3".to_string(), 10.0),
+        ]);
+        let plan = graph.plan_cost(
+            None,
+            kernels,
+            &TagMap::default(),
+            &durations,
+            PlanOptions {
+                ordering: PlanOrdering::Cost,
+                max_concurrency: 10,
+            },
+        )?;
+
+        let order: Vec<(&str, bool)> = plan
+            .stages
+            .iter()
+            .flat_map(|stage| stage.tasks.iter())
+            .map(|task| {
+                (
+                    task.resource_info.resource.node_id().unwrap_or_default(),
+                    task.is_fork,
+                )
+            })
+            .collect();
+        assert_eq!(
+            order,
+            vec![("cc-1", false), ("cc-2", true), ("cc-3", false)]
+        );
+
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use common::{
     eyre::{self, bail, Result},
+    itertools::Itertools,
     once_cell::sync::Lazy,
     serde::Serialize,
+    serde_json,
+    serde_with::skip_serializing_none,
     strum::AsRefStr,
 };
 use graph_triples::ResourceInfo;
@@ -20,7 +23,52 @@ pub struct Plan {
     pub stages: Vec<PlanStage>,
 }
 
+/// The formats that a plan can be converted to
+pub const PLAN_FORMATS: [&str; 4] = ["md", "json", "mermaid", "dot"];
+
 impl Plan {
+    /// Set the estimated duration of each task in the plan
+    ///
+    /// The `durations` are the durations, in seconds, of previous executions
+    /// of each node, keyed by node id.
+    pub fn estimate_durations(&mut self, durations: &HashMap<String, f64>) {
+        for task in self
+            .stages
+            .iter_mut()
+            .flat_map(|stage| stage.tasks.iter_mut())
+        {
+            task.estimated_duration = task
+                .resource_info
+                .resource
+                .node_id()
+                .and_then(|node_id| durations.get(node_id))
+                .cloned();
+        }
+    }
+
+    /// Get the estimated duration of the plan
+    ///
+    /// Because tasks within a stage run concurrently, this is the sum of the
+    /// longest task in each stage. Returns `None` if no tasks have an
+    /// estimated duration.
+    pub fn estimated_duration(&self) -> Option<f64> {
+        self.stages
+            .iter()
+            .filter_map(PlanStage::estimated_duration)
+            .reduce(|sum, duration| sum + duration)
+    }
+
+    /// Convert the plan to some format
+    pub fn to_format(&self, format: &str) -> Result<String> {
+        Ok(match format {
+            "md" => self.to_markdown(),
+            "json" => serde_json::to_string_pretty(self)?,
+            "mermaid" => self.to_mermaid(),
+            "dot" => self.to_dot(),
+            _ => bail!("Unknown plan format '{}'", format),
+        })
+    }
+
     pub fn to_markdown(&self) -> String {
         let options = self.options.to_markdown();
         let stages = self
@@ -32,6 +80,122 @@ impl Plan {
             .join("\n\n");
         format!("{}\n\n{}", options, stages)
     }
+
+    /// Convert the plan to a Mermaid flowchart
+    ///
+    /// Each stage is a subgraph containing its tasks, with stages linked in the
+    /// order that they will be executed.
+    pub fn to_mermaid(&self) -> String {
+        let stages = self
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(stage_index, stage)| {
+                let tasks = stage
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .map(|(task_index, task)| {
+                        format!(
+                            "    s{}t{}[\"{}\"]",
+                            stage_index,
+                            task_index,
+                            task.to_label("<br/>").replace('"', "#quot;")
+                        )
+                    })
+                    .join("\n");
+                format!(
+                    "  subgraph s{index} [\"{label}\"]\n{tasks}\n  end",
+                    index = stage_index,
+                    label = stage.to_label(stage_index),
+                    tasks = tasks
+                )
+            })
+            .join("\n");
+
+        let edges = (1..self.stages.len())
+            .map(|index| format!("  s{} --> s{}", index - 1, index))
+            .join("\n");
+
+        format!("flowchart TB\n{}\n{}\n", stages, edges)
+    }
+
+    /// Convert the plan to a Graphviz dot graph
+    ///
+    /// Each stage is a cluster containing its tasks, with clusters linked in the
+    /// order that they will be executed.
+    pub fn to_dot(&self) -> String {
+        let subgraphs = self
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(stage_index, stage)| {
+                let tasks = stage
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .map(|(task_index, task)| {
+                        format!(
+                            r#"    s{stage}t{task} [shape="box" style="{style}" fillcolor="{fill_color}" label="{label}"]"#,
+                            stage = stage_index,
+                            task = task_index,
+                            style = if task.is_fork {
+                                "filled,dashed"
+                            } else {
+                                "filled"
+                            },
+                            fill_color = if task.is_fork { "#efe0b8" } else { "#efe0a6" },
+                            label = task.to_label("\\n").replace('\"', "\\\"")
+                        )
+                    })
+                    .join("\n");
+                [
+                    &format!("  subgraph cluster{} {{\n", stage_index),
+                    &format!("    label=\"{}\"\n", stage.to_label(stage_index)),
+                    &tasks,
+                    "\n  }",
+                ]
+                .concat()
+            })
+            .join("\n");
+
+        // Edges between the first task of each stage, with the head and tail
+        // clipped to the stage clusters
+        let edges = (1..self.stages.len())
+            .map(|index| {
+                format!(
+                    r#"  s{from}t0 -> s{to}t0 [ltail="cluster{from}" lhead="cluster{to}"]"#,
+                    from = index - 1,
+                    to = index
+                )
+            })
+            .join("\n");
+
+        format!(
+            r#"digraph {{
+  graph [rankdir=TB compound=true fontname=Helvetica fontsize=12 labeljust=l color=gray]
+  node [fontname=Helvetica fontsize=11]
+  edge [fontname=Helvetica fontsize=10]
+
+{subgraphs}
+
+{edges}
+}}
+"#,
+            subgraphs = subgraphs,
+            edges = edges
+        )
+    }
+}
+
+/// Format a duration, in seconds, for display in a plan
+fn format_duration(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        let seconds = seconds.round() as u64;
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
 }
 
 /// Options for generating a plan
@@ -93,6 +257,12 @@ pub enum PlanOrdering {
     /// Nodes are executed in the order that ensures
     /// that the dependencies of a node are executed before it is
     Topological,
+
+    /// Nodes are executed in an order that ensures that the dependencies
+    /// of a node are executed before it is, but which uses the durations of
+    /// previous executions to prioritize the critical path and to pack
+    /// nodes that can run concurrently into stages
+    Cost,
 }
 
 impl FromStr for PlanOrdering {
@@ -103,6 +273,7 @@ impl FromStr for PlanOrdering {
             "s" | "si" | "sin" | "single" => PlanOrdering::Single,
             "a" | "ap" | "app" | "appear" | "appearance" => PlanOrdering::Appearance,
             "t" | "to" | "top" | "topo" | "topological" => PlanOrdering::Topological,
+            "c" | "co" | "cost" | "critical" => PlanOrdering::Cost,
             _ => bail!("Unrecognized plan ordering: {}", str),
         })
     }
@@ -143,6 +314,24 @@ pub struct PlanStage {
 }
 
 impl PlanStage {
+    /// Get the estimated duration of the stage
+    ///
+    /// The duration of the longest task in the stage (since they are run concurrently).
+    pub fn estimated_duration(&self) -> Option<f64> {
+        self.tasks
+            .iter()
+            .filter_map(|task| task.estimated_duration)
+            .reduce(f64::max)
+    }
+
+    /// Get a label for the stage (used in diagrams)
+    fn to_label(&self, index: usize) -> String {
+        match self.estimated_duration() {
+            Some(duration) => format!("Stage {} (~{})", index + 1, format_duration(duration)),
+            None => format!("Stage {}", index + 1),
+        }
+    }
+
     pub fn to_markdown(&self) -> String {
         self.tasks
             .iter()
@@ -155,6 +344,7 @@ impl PlanStage {
 /// A task in an execution plan
 ///
 /// A task is the smallest unit in an execution plan and corresponds to a kernel [`Task`]
+#[skip_serializing_none]
 #[derive(Debug, Serialize)]
 #[serde(crate = "common::serde")]
 pub struct PlanTask {
@@ -174,6 +364,12 @@ pub struct PlanTask {
     /// Code that has no side effects or who's side effects should be
     /// ignored (i.e. is "@pure") are executed in a fork of the kernel.
     pub is_fork: bool,
+
+    /// The estimated duration of the task in seconds
+    ///
+    /// Based on the duration of previous executions of the node. Will be `None`
+    /// if the node has not been executed before.
+    pub estimated_duration: Option<f64>,
 }
 
 impl PlanTask {
@@ -182,10 +378,115 @@ impl PlanTask {
         let node_id = self.resource_info.resource.node_id().unwrap_or("?");
         let kernel_selector = self.kernel_selector.to_string();
         let fork = if self.is_fork { "**fork**" } else { "" };
+        let duration = match self.estimated_duration {
+            Some(duration) => format!(" (~{})", format_duration(duration)),
+            None => String::new(),
+        };
 
         format!(
-            "Run `{}` *{}* in *{}* kernel {}",
-            node_type, node_id, kernel_selector, fork,
+            "Run `{}` *{}* in *{}* kernel {}{}",
+            node_type, node_id, kernel_selector, fork, duration
         )
     }
+
+    /// Get a label for the task (used in diagrams)
+    ///
+    /// The `separator` is used to separate the node type and id from the annotations.
+    fn to_label(&self, separator: &str) -> String {
+        let node_type = self.resource_info.resource.node_type().unwrap_or("?");
+        let node_id = self.resource_info.resource.node_id().unwrap_or("?");
+
+        let mut annotations = vec![self.kernel_selector.to_string()];
+        if self.is_fork {
+            annotations.push("fork".to_string());
+        }
+        if let Some(duration) = self.estimated_duration {
+            annotations.push(["~", &format_duration(duration)].concat());
+        }
+
+        [node_type, " ", node_id, separator, &annotations.join(" · ")].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graph_triples::resources;
+    use std::path::Path;
+    use test_snaps::{insta::assert_snapshot, snapshot_settings};
+
+    /// Create a plan with a stage with a single task, and a stage with a task
+    /// run in a fork and a task with quotes in its id (which need to be escaped)
+    fn plan() -> Plan {
+        let task = |id: &str, is_fork: bool| PlanTask {
+            resource_info: ResourceInfo::default(resources::code(
+                Path::new("doc.md"),
+                id,
+                "CodeChunk",
+                Some("calc".to_string()),
+            )),
+            kernel_selector: KernelSelector {
+                any: Some("calc".to_string()),
+                ..Default::default()
+            },
+            is_fork,
+            estimated_duration: None,
+        };
+        Plan {
+            options: PlanOptions {
+                ordering: PlanOrdering::Topological,
+                max_concurrency: 2,
+            },
+            stages: vec![
+                PlanStage {
+                    tasks: vec![task("cc-1", false)],
+                },
+                PlanStage {
+                    tasks: vec![task("cc-2", true), task(r#"cc-"3""#, false)],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn estimate_durations() {
+        let mut plan = plan();
+        assert_eq!(plan.estimated_duration(), None);
+
+        plan.estimate_durations(&HashMap::from([
+            ("cc-2".to_string(), 1.5),
+            (r#"cc-"3""#.to_string(), 75.0),
+            ("cc-4".to_string(), 10.0),
+        ]));
+        let durations = plan
+            .stages
+            .iter()
+            .flat_map(|stage| stage.tasks.iter())
+            .map(|task| task.estimated_duration)
+            .collect::<Vec<_>>();
+        assert_eq!(durations, [None, Some(1.5), Some(75.0)]);
+        assert_eq!(plan.stages[0].estimated_duration(), None);
+        assert_eq!(plan.stages[1].estimated_duration(), Some(75.0));
+        assert_eq!(plan.estimated_duration(), Some(75.0));
+    }
+
+    #[test]
+    fn to_format() -> Result<()> {
+        let mut plan = plan();
+        plan.estimate_durations(&HashMap::from([
+            ("cc-2".to_string(), 1.5),
+            (r#"cc-"3""#.to_string(), 75.0),
+        ]));
+
+        snapshot_settings(|| {
+            for format in PLAN_FORMATS {
+                assert_snapshot!(["plan.", format].concat(), plan.to_format(format).unwrap());
+            }
+        });
+        assert_eq!(plan.to_format("mermaid")?, plan.to_mermaid());
+        assert_eq!(plan.to_format("dot")?, plan.to_dot());
+        assert!(plan.to_format("foo").is_err());
+
+        Ok(())
+    }
 }
@@ -5,7 +5,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use common::{eyre::Result, serde, serde_json};
+use common::{eyre::Result, itertools::Itertools, serde, serde_json};
 use oci_spec::image::{Descriptor, DescriptorBuilder, MediaType};
 
 use hash_utils::sha2::{Digest, Sha256};
@@ -85,14 +85,33 @@ impl BlobWriter {
     }
 
     /// Write an object as a JSON based media type
+    ///
+    /// The keys of JSON objects are sorted so that the digest of the blob does not depend upon
+    /// the iteration order of maps (e.g. the `HashMap`s used for labels and annotations).
     pub fn write_json<S: serde::Serialize>(
         object: &S,
         media_type: MediaType,
         annotations: Option<HashMap<String, String>>,
         layout_dir: Option<&Path>,
     ) -> Result<Descriptor> {
+        fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Object(object) => serde_json::Value::Object(
+                    object
+                        .into_iter()
+                        .map(|(key, value)| (key, sort_keys(value)))
+                        .sorted_by(|a, b| a.0.cmp(&b.0))
+                        .collect(),
+                ),
+                serde_json::Value::Array(array) => {
+                    serde_json::Value::Array(array.into_iter().map(sort_keys).collect())
+                }
+                _ => value,
+            }
+        }
+
         let mut writer = Self::new()?;
-        serde_json::to_writer_pretty(&mut writer, object)?;
+        serde_json::to_writer_pretty(&mut writer, &sort_keys(serde_json::to_value(object)?))?;
         writer.finish(media_type, annotations, layout_dir)
     }
 }
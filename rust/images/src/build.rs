@@ -0,0 +1,337 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use buildpacks::{Buildpacks, PACKS};
+use common::{
+    eyre::{bail, Result},
+    tracing,
+};
+
+use crate::{
+    change_set::{Change, ChangeSet},
+    image_reference::ImageReference,
+    snapshot::Snapshot,
+};
+
+/// The directory, within the image's root filesystem, that buildpack layers are placed in
+const LAYERS_DIR: &str = "/layers";
+
+/// The directory, within the image's root filesystem, that the project is placed in
+///
+/// Should be the same as the working directory set in the image config.
+const WORKSPACE_DIR: &str = "/work";
+
+/// Build the [`ChangeSet`]s for an image of a project using the built-in buildpacks
+///
+/// Runs `detect` for each buildpack, in the order that they are registered, and runs `build` for
+/// those that match. The layers directory of each buildpack that was built is captured as a
+/// separate change set (and hence a separate image layer) so that layers are reused across images
+/// when the dependencies of a project do not change. The project directory itself, excluding its
+/// `.stencila` directory, is captured as the final change set.
+///
+/// Buildpacks install tools and packages against the root filesystem that they are run within.
+/// As for the `save` command, this is intended to be run within a container of the base image.
+///
+/// # Arguments
+///
+/// - `dir`: The project directory to build
+///
+/// - `base`: The base image that the image is being built from (see [`check_base`])
+///
+/// The base image defaults to the `STENCILA_IMAGE_BASE` and then the `STENCILA_IMAGE_REF`
+/// environment variables (as for [`Image::new`](crate::image::Image::new)).
+pub fn change_sets(dir: &Path, base: Option<&str>) -> Result<Vec<ChangeSet>> {
+    // Check the base image before running any buildpacks
+    let base = match base
+        .map(String::from)
+        .or_else(|| env::var("STENCILA_IMAGE_BASE").ok())
+        .or_else(|| env::var("STENCILA_IMAGE_REF").ok())
+    {
+        Some(base) => base,
+        None => bail!("Unable to resolve the base image"),
+    };
+    check_base(&base, env::var("STENCILA_IMAGE_REF").ok().as_deref())?;
+
+    let dir = match dir.canonicalize() {
+        Ok(dir) => dir,
+        Err(..) => bail!("Project directory `{}` does not exist", dir.display()),
+    };
+
+    // Use the same layers directory that buildpacks use by default when they are run
+    // within a container or within a project
+    let root_layers = PathBuf::from(LAYERS_DIR);
+    let layers_dir = if root_layers.exists() {
+        root_layers
+    } else {
+        dir.join(".stencila").join("layers")
+    };
+
+    let mut change_sets = Vec::new();
+    for (buildpack_id, matched) in PACKS.detect_all(Some(&dir), None)? {
+        if !matched {
+            continue;
+        }
+
+        let code = PACKS.build(&buildpack_id, Some(&dir), Some(&layers_dir), None, None)?;
+        if code != 0 {
+            bail!(
+                "Buildpack `{}` failed to build with exit code {}",
+                buildpack_id,
+                code
+            )
+        }
+
+        let slug = Buildpacks::slugify_buildpack_id(&buildpack_id);
+        let source_dir = layers_dir.join(&slug);
+        if !source_dir.exists() {
+            tracing::debug!("Buildpack `{}` did not create any layers", buildpack_id);
+            continue;
+        }
+
+        let mut change_set =
+            Snapshot::new_with(&source_dir, PathBuf::from(LAYERS_DIR).join(&slug)).replicate();
+        change_set.comment = Some(format!("Layers built by buildpack `{}`", buildpack_id));
+        change_sets.push(change_set);
+    }
+
+    change_sets.push(workspace(&dir));
+
+    Ok(change_sets)
+}
+
+/// Check that the base image is the image that buildpacks will be run within
+///
+/// The layers created by buildpacks are only valid for the root filesystem that they were built
+/// against. Rather than unpacking the root filesystem of the base image (which would require running
+/// the buildpacks within a container, or chroot, of it), fail early if the base image is not the
+/// current image (as given by the `STENCILA_IMAGE_REF` environment variable).
+fn check_base(base: &str, current: Option<&str>) -> Result<()> {
+    let base: ImageReference = base.parse()?;
+    let current: ImageReference = match current {
+        Some(current) => current.parse()?,
+        None => bail!(
+            "Unable to build from base image `{}`: builds must be run within a container of the base image but the current image is unknown",
+            base.to_string_tag_or_latest()
+        ),
+    };
+
+    let same = base.registry == current.registry
+        && base.repository == current.repository
+        && match (&base.digest, &current.digest) {
+            (Some(base), Some(current)) => base == current,
+            _ => base.tag_or_latest() == current.tag_or_latest(),
+        };
+    if !same {
+        bail!(
+            "Unable to build from base image `{}`: builds must be run within a container of the base image but the current image is `{}`",
+            base.to_string_tag_or_latest(),
+            current.to_string_tag_or_latest()
+        )
+    }
+
+    Ok(())
+}
+
+/// Create a [`ChangeSet`] for the files in a project directory
+///
+/// Excludes the `.stencila` directory which contains buildpack layers (which have
+/// their own change sets) and other build artifacts.
+fn workspace(dir: &Path) -> ChangeSet {
+    let mut change_set = Snapshot::new_with(dir, WORKSPACE_DIR).replicate();
+    change_set.items.retain(|change| match change {
+        Change::Added(path) | Change::Modified(path) | Change::Removed(path) => {
+            !Path::new(path).starts_with(".stencila")
+        }
+    });
+    // The path of the project is not included in the comment so that the image does not
+    // depend upon where the project is located
+    change_set.comment = Some("Project workspace".to_string());
+    change_set
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use common::{
+        serde_json,
+        tempfile::{tempdir, TempDir},
+        tokio::{
+            self,
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        },
+    };
+    use hash_utils::str_sha256_hex;
+    use oci_spec::image::{
+        DescriptorBuilder, ImageConfigurationBuilder, ImageManifestBuilder, MediaType,
+        RootFsBuilder, SCHEMA_VERSION,
+    };
+
+    use super::*;
+    use crate::{
+        image::Image,
+        storage::{image_path, IMAGES_MAP},
+    };
+
+    #[test]
+    fn base_is_current() -> Result<()> {
+        check_base("ubuntu", Some("docker.io/library/ubuntu:latest"))?;
+        check_base("localhost:5000/base:1", Some("localhost:5000/base:1"))?;
+
+        let error = check_base("ubuntu", None).unwrap_err().to_string();
+        assert!(error.contains("current image is unknown"), "{}", error);
+
+        let error = check_base("localhost:5000/base:2", Some("localhost:5000/base:1"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("`localhost:5000/base:1`"), "{}", error);
+
+        // The base is checked before any buildpacks are run (or even the project directory is checked)
+        match change_sets(Path::new("does-not-exist"), Some("localhost:5000/base:2")) {
+            Ok(..) => bail!("Expected an error"),
+            Err(error) => assert!(
+                error
+                    .to_string()
+                    .starts_with("Unable to build from base image"),
+                "{}",
+                error
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Create a project directory with files having a modification time
+    fn project(modified: SystemTime) -> Result<TempDir> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("main.md"), "# Hello")?;
+        fs::create_dir_all(dir.path().join(".stencila").join("layers"))?;
+        fs::write(dir.path().join(".stencila").join("layers").join("a"), "A")?;
+        fs::File::options()
+            .write(true)
+            .open(dir.path().join("main.md"))?
+            .set_modified(modified)?;
+        Ok(dir)
+    }
+
+    /// Test that the workspace change set excludes the `.stencila` directory and that the
+    /// layer written for it does not depend on file modification times
+    #[test]
+    fn workspace_reproducible() -> Result<()> {
+        let write = |modified: SystemTime| -> Result<String> {
+            let dir = project(modified)?;
+
+            let change_set = workspace(dir.path());
+            assert_eq!(change_set.items, vec![Change::Added("main.md".to_string())]);
+
+            let (diff_id, ..) =
+                change_set.write_layer(&MediaType::ImageLayerGzip, &tempdir()?, true)?;
+            Ok(diff_id)
+        };
+
+        let diff_id_1 = write(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000))?;
+        let diff_id_2 = write(SystemTime::UNIX_EPOCH + Duration::from_secs(2_000_000))?;
+        assert_eq!(diff_id_1, diff_id_2);
+
+        Ok(())
+    }
+
+    /// Serve a base image with no layers from a minimal stand-in for an image registry
+    ///
+    /// Responds to requests for the manifest and config of the image only.
+    /// Returns the reference for the image.
+    async fn serve_base() -> Result<String> {
+        let config = serde_json::to_string(
+            &ImageConfigurationBuilder::default()
+                .os("linux")
+                .architecture("amd64")
+                .rootfs(RootFsBuilder::default().diff_ids(Vec::new()).build()?)
+                .build()?,
+        )?;
+        let config_digest = ["sha256:", &str_sha256_hex(&config)].concat();
+        let manifest = serde_json::to_string(
+            &ImageManifestBuilder::default()
+                .schema_version(SCHEMA_VERSION)
+                .media_type(MediaType::ImageManifest)
+                .config(
+                    DescriptorBuilder::default()
+                        .media_type(MediaType::ImageConfig)
+                        .digest(config_digest.clone())
+                        .size(config.len() as i64)
+                        .build()?,
+                )
+                .layers(Vec::new())
+                .build()?,
+        )?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, ..)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(..) => break,
+                        Ok(bytes) => request.extend_from_slice(&buffer[..bytes]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split(' ').nth(1).unwrap_or_default();
+
+                let (status, content_type, body) = if path.ends_with("/manifests/latest") {
+                    ("200 OK", MediaType::ImageManifest.to_string(), &manifest)
+                } else if path.ends_with(&["/blobs/", &config_digest].concat()) {
+                    ("200 OK", MediaType::ImageConfig.to_string(), &config)
+                } else {
+                    ("404 Not Found", "text/plain".to_string(), &String::new())
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.ok();
+            }
+        });
+
+        Ok(format!("localhost:{}/base", port))
+    }
+
+    /// Test that an image built from the same project, at different times and with
+    /// different file modification times, has the same manifest and config digests
+    #[tokio::test]
+    async fn image_reproducible() -> Result<()> {
+        let base = serve_base().await?;
+        let reference = "localhost:5000/test/image-reproducible";
+
+        let mut digests = Vec::new();
+        for modified in [1_000_000, 2_000_000] {
+            let dir = project(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))?;
+
+            let mut image = Image::new(
+                reference,
+                Some(&base),
+                vec![workspace(dir.path())],
+                None,
+                None,
+            )?;
+            image.set_deterministic_layers(true);
+            let (written, id) = image.write().await?;
+            assert!(image_path(&id).join("index.json").is_file());
+
+            digests.push((written, id, image.reference().digest.clone()));
+        }
+        assert_eq!(digests[0], digests[1]);
+
+        // Removing the reference also removes the image
+        IMAGES_MAP.write().await.remove(&digests[0].0)?;
+
+        Ok(())
+    }
+}
@@ -13,14 +13,13 @@ use archive_utils::{
     zstd,
 };
 use common::{
-    chrono::Utc,
     eyre::{bail, eyre, Result},
     itertools::Itertools,
     tracing,
 };
 use hash_utils::{sha2::Digest, sha2::Sha256};
 
-use crate::{blob_writer::BlobWriter, storage::digest_to_parts};
+use crate::{blob_writer::BlobWriter, storage::digest_to_parts, utils::timestamp};
 
 /// A change in a path between two snapshots
 ///
@@ -295,7 +294,7 @@ impl ChangeSet {
 
         let mut annotations: HashMap<String, String> = [
             ("io.stencila.version", env!("CARGO_PKG_VERSION").to_string()),
-            ("io.stencila.layer.created", Utc::now().to_rfc3339()),
+            ("io.stencila.layer.created", timestamp(deterministic)),
             (
                 "io.stencila.layer.directory",
                 self.dest_dir.to_string_lossy().to_string(),
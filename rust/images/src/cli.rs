@@ -5,7 +5,7 @@ use cli_utils::{
     common::async_trait::async_trait,
     result, Result, Run,
 };
-use common::tracing;
+use common::{chrono, serde_json, tracing};
 
 use crate::{
     change_set::{Change, ChangeSet},
@@ -32,8 +32,12 @@ enum Action {
     Pull(Pull),
     Push(Push),
     Remove(Remove),
+    Prune(Prune),
+    Du(Du),
     Snap(Snap),
     Save(Save),
+    #[cfg(feature = "build")]
+    Build(Build),
 }
 
 #[async_trait]
@@ -44,8 +48,12 @@ impl Run for Command {
             Action::Pull(action) => action.run().await,
             Action::Push(action) => action.run().await,
             Action::Remove(action) => action.run().await,
+            Action::Prune(action) => action.run().await,
+            Action::Du(action) => action.run().await,
             Action::Snap(action) => action.run().await,
             Action::Save(action) => action.run().await,
+            #[cfg(feature = "build")]
+            Action::Build(action) => action.run().await,
         }
     }
 }
@@ -110,7 +118,8 @@ impl Run for Push {
 
 /// Remove an image from the local image store
 ///
-/// Equivalent to `docker rmi` and `podman rmi`.
+/// Equivalent to `docker rmi` and `podman rmi`. Blobs (e.g. layers) that are no
+/// longer referenced by any image are not removed until `stencila images prune` is run.
 #[derive(Parser)]
 struct Remove {
     /// The image to remove (a reference, id, or hash component of id)
@@ -132,6 +141,64 @@ impl Run for Remove {
     }
 }
 
+/// Remove unused images and blobs from the local image store
+///
+/// Similar to `docker image prune` and `podman image prune`. Dangling images (those no longer
+/// referenced e.g. because their tag was reused for a newer image) and blobs that are not
+/// referenced by any image are always removed.
+#[derive(Parser)]
+struct Prune {
+    /// Also remove images created longer ago than this duration e.g. `30days`, `2weeks`
+    #[clap(long)]
+    older_than: Option<String>,
+
+    /// Also remove images so that only this number of the most recent images are kept for each repository
+    #[clap(long)]
+    keep_last: Option<usize>,
+}
+
+#[async_trait]
+impl Run for Prune {
+    async fn run(&self) -> Result {
+        let older_than = match &self.older_than {
+            Some(older_than) => Some(chrono::Duration::from_std(humantime::parse_duration(
+                older_than,
+            )?)?),
+            None => None,
+        };
+
+        let mut image_map = IMAGES_MAP.write().await;
+        let pruned = image_map.prune(older_than, self.keep_last)?;
+        tracing::info!(
+            "Removed {} images and {} blobs, reclaiming {}",
+            pruned.images.len(),
+            pruned.blobs.len(),
+            bytesize::ByteSize(pruned.bytes)
+        );
+        result::value(pruned)
+    }
+}
+
+/// Show the disk usage of the local image store
+///
+/// Similar to `docker system df -v`. Reports the disk usage of each image, and of the
+/// layers that are shared between images.
+#[derive(Parser)]
+struct Du;
+
+#[async_trait]
+impl Run for Du {
+    async fn run(&self) -> Result {
+        let images = IMAGES_MAP.read().await;
+        let (images, blobs, md) = images.usage()?;
+        result::new(
+            "md",
+            &md,
+            serde_json::json!({ "images": images, "blobs": blobs }),
+        )
+    }
+}
+
 /// Take a snapshot of the filesystem
 ///
 /// This command is used create a snapshot of the filesystem that can be used
@@ -316,3 +383,85 @@ impl Run for Save {
         result::nothing()
     }
 }
+
+/// Build an image for a project using buildpacks
+///
+/// Runs the buildpacks that match the project (e.g. Python, R, Node and Apt) in order, and
+/// writes an image with one layer for each buildpack plus a layer for the project itself.
+/// Layers are deterministic (file modification times are ignored and timestamps are
+/// set from `SOURCE_DATE_EPOCH`) so that the same project produces the same layers.
+///
+/// As for the `save` command, this is intended to be run within a container of the base image.
+#[cfg(feature = "build")]
+#[derive(Parser)]
+struct Build {
+    /// The project directory to build
+    #[clap(default_value = ".")]
+    dir: PathBuf,
+
+    /// The registry, repository and tag to give the image
+    ///
+    /// Equivalent to the `--tag` option to Docker build.
+    /// Must be a valid image reference e.g. `localhost:5000/my-project`
+    #[clap(long, short, aliases = &["ref", "tag"])]
+    reference: String,
+
+    /// The base image to build from
+    ///
+    /// Equivalent to the `FROM` directive in a Dockerfile. Defaults to the `STENCILA_IMAGE_REF` (i.e. the
+    /// current image, if Stencila is running in a container). Because buildpacks are run against the
+    /// current root filesystem, this must be the current image.
+    #[clap(long, short, alias = "from")]
+    base: Option<String>,
+
+    /// The format to use for image layers
+    #[clap(
+        long,
+        default_value = "tar+zstd",
+        possible_values = &["tar", "tar+gzip", "tgz", "tar+zstd", "tzs"]
+    )]
+    layer_format: String,
+
+    /// The format to use for the image manifest
+    #[clap(
+        long,
+        default_value = "oci",
+        possible_values = &["oci", "v2s2"]
+    )]
+    manifest_format: String,
+
+    /// Do not push the image to the repository after writing it
+    #[clap(long)]
+    no_push: bool,
+}
+
+#[cfg(feature = "build")]
+#[async_trait]
+impl Run for Build {
+    async fn run(&self) -> Result {
+        let change_sets = crate::build::change_sets(&self.dir, self.base.as_deref())?;
+
+        let mut image = Image::new(
+            &self.reference,
+            self.base.as_deref(),
+            change_sets,
+            Some(self.layer_format.as_str()),
+            Some(self.manifest_format.as_str()),
+        )?;
+        image.set_deterministic_layers(true);
+
+        let (reference, id) = image.write().await?;
+
+        if !self.no_push {
+            image.push().await?;
+            tracing::info!(
+                "Image built and pushed to `{}`.",
+                image.reference().to_string()
+            );
+        } else {
+            tracing::info!("Image `{}` built and written to storage", reference);
+        }
+
+        result::value(id)
+    }
+}
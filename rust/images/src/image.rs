@@ -7,8 +7,8 @@ use oci_spec::image::{
 };
 
 use common::{
-    chrono::Utc,
     eyre::{bail, Result},
+    itertools::Itertools,
     serde::Serialize,
     serde_json, tokio,
 };
@@ -19,7 +19,7 @@ use crate::{
     distribution::{push, Client},
     image_reference::ImageReference,
     storage::{image_path, image_path_safe, write_oci_layout_file, IMAGES_MAP},
-    utils::unique_string,
+    utils::{timestamp, unique_string},
 };
 
 /// A container image
@@ -49,6 +49,9 @@ pub struct Image {
 
     /// Whether to make the layers for each change set deterministic (i.e. ignore file
     /// modification time and other metadata)
+    ///
+    /// Also causes fixed timestamps to be used in the image's config and manifest so that
+    /// the image as a whole is reproducible.
     deterministic_layers: bool,
 
     /// The format used when writing layers for each change set
@@ -116,6 +119,11 @@ impl Image {
         &self.base
     }
 
+    /// Set whether the layers (and other blobs) of the image should be deterministic
+    pub fn set_deterministic_layers(&mut self, deterministic_layers: bool) {
+        self.deterministic_layers = deterministic_layers;
+    }
+
     /// Fetches the manifest and configuration of the base image
     ///
     /// Used when writing the image because the DiffIDs (from the config) and the layers (from the
@@ -169,7 +177,7 @@ impl Image {
                 format!("Change set for {} with {additions} additions, {deletions} deletions, and {modifications} modifications.", change_set.source_dir.display())
             });
             let history = HistoryBuilder::default()
-                .created(Utc::now().to_rfc3339())
+                .created(timestamp(self.deterministic_layers))
                 .created_by(format!(
                     "stencila {}",
                     env::args().skip(1).collect::<Vec<String>>().join(" ")
@@ -218,6 +226,7 @@ impl Image {
 
         let env: Vec<String> = env
             .iter()
+            .sorted()
             .map(|(name, value)| [name, "=", value].concat())
             .collect();
         config.set_env(Some(env));
@@ -233,7 +242,7 @@ impl Image {
         let rootfs = RootFsBuilder::default().diff_ids(diff_ids).build()?;
 
         let configuration = ImageConfigurationBuilder::default()
-            .created(Utc::now().to_rfc3339())
+            .created(timestamp(self.deterministic_layers))
             .os(env::consts::OS)
             // Not that arch should be one of the values listed at https://go.dev/doc/install/source#environment
             // and that `env::consts::ARCH` does not necessarily return that
@@ -299,7 +308,7 @@ impl Image {
             ),
             (
                 "org.opencontainers.image.created".to_string(),
-                Utc::now().to_rfc3339(),
+                timestamp(self.deterministic_layers),
             ),
             (
                 "org.opencontainers.image.base.name".to_string(),
@@ -344,9 +353,14 @@ impl Image {
         write_oci_layout_file(&layout_dir)?;
         let config_digest = config_descriptor.digest();
 
-        // Now we know the id of the image, rename the dir
+        // Now we know the id of the image, rename the dir (unless an identical image,
+        // e.g. from a previous reproducible build, has already been written)
         let image_dir = image_path_safe(config_digest)?;
-        fs::rename(&layout_dir, image_dir).await?;
+        if image_dir.exists() {
+            fs::remove_dir_all(&layout_dir).await?;
+        } else {
+            fs::rename(&layout_dir, image_dir).await?;
+        }
 
         // Add an entry in the images map
         let mut images = IMAGES_MAP.write().await;
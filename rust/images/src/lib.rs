@@ -1,4 +1,6 @@
 pub mod blob_writer;
+#[cfg(feature = "build")]
+pub mod build;
 pub mod change_set;
 pub mod distribution;
 pub mod image;
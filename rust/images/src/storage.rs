@@ -3,12 +3,16 @@
 use std::{
     collections::HashMap,
     env,
-    fs::{create_dir_all, read_to_string, remove_dir_all, write},
+    fs::{
+        create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename,
+        symlink_metadata, write,
+    },
     path::{Path, PathBuf},
 };
 
+use bytesize::ByteSize;
 use common::{
-    chrono::{DateTime, Utc},
+    chrono::{DateTime, Duration, Utc},
     chrono_humanize::HumanTime,
     dirs,
     eyre::{bail, eyre, Result},
@@ -21,7 +25,7 @@ use common::{
     tracing,
 };
 use fs_utils::symlink_file;
-use oci_spec::image::ImageManifest;
+use oci_spec::image::{ImageIndex, ImageManifest};
 use path_utils::pathdiff::diff_paths;
 
 use crate::image_reference::ImageReference;
@@ -177,6 +181,10 @@ impl ImagesMap {
     }
 
     /// Remove an entry from the images map
+    ///
+    /// Blobs that are no longer referenced by any image are not removed (because
+    /// other images may be being pulled or built into the shared blob store) but
+    /// are reclaimed when the store is pruned (see [`ImagesMap::prune`]).
     pub fn remove(&mut self, reference: &str) -> Result<Vec<String>> {
         let mut removed_refs = Vec::new();
         let mut removed_ids = Vec::new();
@@ -210,9 +218,14 @@ impl ImagesMap {
 
         for reference in removed_refs {
             if let Some(info) = self.inner.remove(&reference) {
-                let image_path = image_path(&info.id);
-                remove_dir_all(image_path)?;
-                removed_ids.push(info.id);
+                // Only remove the image if there are no other references to it
+                if !self.inner.values().any(|other| other.id == info.id) {
+                    let image_path = image_path(&info.id);
+                    if image_path.exists() {
+                        remove_dir_all(image_path)?;
+                    }
+                    removed_ids.push(info.id);
+                }
             }
         }
         self.write()?;
@@ -220,6 +233,202 @@ impl ImagesMap {
         Ok(removed_ids)
     }
 
+    /// Prune images and blobs from the local image store
+    ///
+    /// Dangling images (those whose layout directory is not referenced in the images map e.g. because
+    /// their reference was reused for a newer image) are always removed, as are blobs which are not referenced
+    /// by any image.
+    ///
+    /// # Arguments
+    ///
+    /// - `older_than`: remove references to images that were created longer ago than this
+    /// - `keep_last`: keep only this number of the most recent references within each repository
+    pub fn prune(
+        &mut self,
+        older_than: Option<Duration>,
+        keep_last: Option<usize>,
+    ) -> Result<Pruned> {
+        let mut pruned = Pruned::default();
+
+        if let Some(older_than) = older_than {
+            let cutoff = Utc::now() - older_than;
+            for (reference, info) in &self.inner {
+                if info.created < cutoff {
+                    pruned.references.push(reference.clone())
+                }
+            }
+        }
+
+        if let Some(keep_last) = keep_last {
+            let repositories = self.inner.iter().into_group_map_by(|(reference, ..)| {
+                reference
+                    .parse::<ImageReference>()
+                    .map(|reference| {
+                        [reference.registry, "/".to_string(), reference.repository].concat()
+                    })
+                    .unwrap_or_else(|_| reference.to_string())
+            });
+            for references in repositories.values() {
+                for (reference, ..) in references
+                    .iter()
+                    .sorted_by(|a, b| b.1.created.cmp(&a.1.created))
+                    .skip(keep_last)
+                {
+                    if !pruned.references.contains(*reference) {
+                        pruned.references.push(reference.to_string())
+                    }
+                }
+            }
+        }
+
+        for reference in &pruned.references {
+            self.inner.remove(reference);
+        }
+        self.write()?;
+
+        // Remove the layout directories of images that are no longer referenced. Temporary layouts
+        // are only removed if they are more than an hour old since they may be in use.
+        for (id, layout_dir) in layout_dirs(&IMAGES_DIR)? {
+            if self.inner.values().any(|info| info.id == id) {
+                continue;
+            }
+            if id.starts_with("temp:") {
+                let age = symlink_metadata(&layout_dir)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok());
+                if !matches!(age, Some(age) if age.as_secs() > 3600) {
+                    continue;
+                }
+            }
+            remove_dir_all(&layout_dir)?;
+            pruned.images.push(id);
+        }
+
+        let (blobs, bytes) = collect_garbage(&IMAGES_DIR, &BLOBS_DIR)?;
+        pruned.blobs = blobs;
+        pruned.bytes = bytes;
+
+        Ok(pruned)
+    }
+
+    /// Get the disk usage of images and blobs (as vectors and a Markdown summary; for CLI inspection)
+    pub fn usage(&self) -> Result<(Vec<ImageUsage>, Vec<BlobUsage>, String)> {
+        let references = blob_references(&IMAGES_DIR)?;
+
+        let blobs: Vec<BlobUsage> = blob_files(&BLOBS_DIR)?
+            .into_iter()
+            .map(|(digest, size)| BlobUsage {
+                images: references.get(&digest).cloned().unwrap_or_default(),
+                digest,
+                size,
+            })
+            .sorted_by(|a, b| b.size.cmp(&a.size))
+            .collect();
+
+        let images: Vec<ImageUsage> = layout_dirs(&IMAGES_DIR)?
+            .into_iter()
+            .map(|(id, ..)| {
+                let mut usage = ImageUsage {
+                    references: self
+                        .inner
+                        .iter()
+                        .filter(|(.., info)| info.id == id)
+                        .map(|(reference, ..)| reference.clone())
+                        .sorted()
+                        .collect(),
+                    id,
+                    blobs: 0,
+                    size: 0,
+                    unique_size: 0,
+                };
+                for blob in &blobs {
+                    if blob.images.contains(&usage.id) {
+                        usage.blobs += 1;
+                        usage.size += blob.size;
+                        if blob.images.len() == 1 {
+                            usage.unique_size += blob.size;
+                        }
+                    }
+                }
+                usage
+            })
+            .sorted_by(|a, b| b.size.cmp(&a.size))
+            .collect();
+
+        let image_rows = images
+            .iter()
+            .map(|image| {
+                format!(
+                    "|{}|{}|{}|{}|{}|",
+                    match image.references.is_empty() {
+                        true => "*dangling*".to_string(),
+                        false => image.references.join(", "),
+                    },
+                    &image.id[..std::cmp::min(image.id.len(), 19)],
+                    image.blobs,
+                    ByteSize(image.size),
+                    ByteSize(image.unique_size)
+                )
+            })
+            .join("\n");
+
+        let shared_rows = blobs
+            .iter()
+            .filter(|blob| blob.images.len() > 1)
+            .map(|blob| {
+                format!(
+                    "|{}|{}|{}|",
+                    &blob.digest[..std::cmp::min(blob.digest.len(), 19)],
+                    ByteSize(blob.size),
+                    blob.images.len()
+                )
+            })
+            .join("\n");
+
+        let total: u64 = blobs.iter().map(|blob| blob.size).sum();
+        let unreferenced: u64 = blobs
+            .iter()
+            .filter(|blob| blob.images.is_empty())
+            .map(|blob| blob.size)
+            .sum();
+
+        let mut md = if image_rows.is_empty() {
+            "*No images built or pulled yet*\n".to_string()
+        } else {
+            format!(
+                r"
+|-----------|----|-------|------|--------|
+| Reference | Id | Blobs | Size | Unique |
+|-----------|----|-------|------|--------|
+{}
+|-----------|----|-------|------|--------|
+",
+                image_rows
+            )
+        };
+        if !shared_rows.is_empty() {
+            md += &format!(
+                r"
+|--------------|------|--------|
+| Shared layer | Size | Images |
+|--------------|------|--------|
+{}
+|--------------|------|--------|
+",
+                shared_rows
+            );
+        }
+        md += &format!(
+            "\nTotal of {} in {} blobs ({} in unreferenced blobs can be reclaimed using `prune`)\n",
+            ByteSize(total),
+            blobs.len(),
+            ByteSize(unreferenced)
+        );
+
+        Ok((images, blobs, md))
+    }
+
     /// Get the image info
     pub fn get_id(&self, reference: &str) -> Option<ImageInfo> {
         if let Ok(reference) = reference.parse::<ImageReference>() {
@@ -280,6 +489,228 @@ pub fn blob_symlink(blob_path: &Path, layout_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// List the image layout directories within an images directory
+///
+/// Returns pairs of the id of each image (e.g. `sha256:...`) and the path of its layout directory.
+/// Includes the temporary directories that images are written or pulled into (which have
+/// ids starting with `temp:`).
+fn layout_dirs(images_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut dirs = Vec::new();
+    if !images_dir.exists() {
+        return Ok(dirs);
+    }
+    for algo in read_dir(images_dir)?.flatten() {
+        if !algo.path().is_dir() {
+            continue;
+        }
+        let algo_name = algo.file_name().to_string_lossy().to_string();
+        for layout in read_dir(algo.path())?.flatten() {
+            if layout.path().is_dir() {
+                let id = [&algo_name, ":", &layout.file_name().to_string_lossy()].concat();
+                dirs.push((id, layout.path()))
+            }
+        }
+    }
+    Ok(dirs)
+}
+
+/// List the blobs within a blobs directory
+///
+/// Returns pairs of the digest and size of each blob. Does not include blobs that are
+/// currently being written (which are in the `temp` subdirectory).
+fn blob_files(blobs_dir: &Path) -> Result<Vec<(String, u64)>> {
+    let mut blobs = Vec::new();
+    if !blobs_dir.exists() {
+        return Ok(blobs);
+    }
+    for algo in read_dir(blobs_dir)?.flatten() {
+        let algo_name = algo.file_name().to_string_lossy().to_string();
+        if algo_name == "temp" || !algo.path().is_dir() {
+            continue;
+        }
+        for blob in read_dir(algo.path())?.flatten() {
+            let metadata = blob.metadata()?;
+            if metadata.is_file() {
+                let digest = [&algo_name, ":", &blob.file_name().to_string_lossy()].concat();
+                blobs.push((digest, metadata.len()))
+            }
+        }
+    }
+    Ok(blobs)
+}
+
+/// Get the digests of the blobs referenced by an image layout directory
+///
+/// Reads each manifest listed in the layout's `index.json` and returns the digests of the manifest,
+/// its config, and its layers. The blobs linked to from the layout's `blobs` directory are also included
+/// so that blobs are not treated as unreferenced if a manifest can not be read. Note that the layers of
+/// the base image of an image that has been built locally are referenced but may not be in the blob store.
+pub fn layout_blobs(layout_dir: &Path) -> Result<Vec<String>> {
+    let mut digests = Vec::new();
+
+    let index_path = layout_dir.join("index.json");
+    if index_path.exists() {
+        let index: ImageIndex = serde_json::from_str(&read_to_string(index_path)?)?;
+        for descriptor in index.manifests() {
+            let (algo, hash) = digest_to_parts(descriptor.digest());
+            let manifest_path = layout_dir.join("blobs").join(algo).join(hash);
+            match read_to_string(&manifest_path)
+                .map_err(|error| eyre!(error))
+                .and_then(|json| {
+                    serde_json::from_str::<ImageManifest>(&json).map_err(|error| eyre!(error))
+                }) {
+                Ok(manifest) => {
+                    digests.push(descriptor.digest().to_string());
+                    digests.push(manifest.config().digest().to_string());
+                    for layer in manifest.layers() {
+                        digests.push(layer.digest().to_string())
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!("While reading {}: {}", manifest_path.display(), error)
+                }
+            }
+        }
+    }
+
+    let blobs_dir = layout_dir.join("blobs");
+    if blobs_dir.exists() {
+        for algo in read_dir(blobs_dir)?.flatten() {
+            let algo_name = algo.file_name().to_string_lossy().to_string();
+            for blob in read_dir(algo.path())?.flatten() {
+                digests.push([&algo_name, ":", &blob.file_name().to_string_lossy()].concat())
+            }
+        }
+    }
+
+    Ok(digests.into_iter().unique().collect())
+}
+
+/// Move any blobs that are files, rather than symlinks, within a layout directory into a blobs directory
+///
+/// Blobs are usually written to the shared blob store and symlinked to from each layout (see [`blob_symlink`]).
+/// However, layouts created by other tools, or by older versions of Stencila, may contain copies
+/// of blobs. Returns the number of bytes reclaimed by removing duplicates.
+fn dedupe_layout(layout_dir: &Path, blobs_dir: &Path) -> Result<u64> {
+    let mut reclaimed = 0;
+
+    let layout_blobs_dir = layout_dir.join("blobs");
+    if !layout_blobs_dir.exists() {
+        return Ok(reclaimed);
+    }
+
+    for algo in read_dir(layout_blobs_dir)?.flatten() {
+        for blob in read_dir(algo.path())?.flatten() {
+            let path = blob.path();
+            let metadata = symlink_metadata(&path)?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let shared_path = blobs_dir.join(algo.file_name()).join(blob.file_name());
+            if shared_path.exists() {
+                remove_file(&path)?;
+                reclaimed += metadata.len();
+            } else {
+                create_dir_all(shared_path.parent().expect("Should have parent"))?;
+                rename(&path, &shared_path)?;
+            }
+            blob_symlink(&shared_path, layout_dir)?;
+        }
+    }
+
+    Ok(reclaimed)
+}
+
+/// Count the number of image layouts that reference each blob
+fn blob_references(images_dir: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let mut references: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, layout_dir) in layout_dirs(images_dir)? {
+        for digest in layout_blobs(&layout_dir)? {
+            references.entry(digest).or_default().push(id.clone())
+        }
+    }
+    Ok(references)
+}
+
+/// Remove blobs that are not referenced by any image layout
+///
+/// Layouts are de-duplicated (see [`dedupe_layout`]) before unreferenced blobs are removed.
+/// Returns the digests of the removed blobs and the number of bytes reclaimed.
+fn collect_garbage(images_dir: &Path, blobs_dir: &Path) -> Result<(Vec<String>, u64)> {
+    let mut reclaimed = 0;
+    for (.., layout_dir) in layout_dirs(images_dir)? {
+        reclaimed += dedupe_layout(&layout_dir, blobs_dir)?;
+    }
+
+    let references = blob_references(images_dir)?;
+
+    let mut removed = Vec::new();
+    for (digest, size) in blob_files(blobs_dir)? {
+        if !references.contains_key(&digest) {
+            let (algo, hash) = digest_to_parts(&digest);
+            remove_file(blobs_dir.join(algo).join(hash))?;
+            removed.push(digest);
+            reclaimed += size;
+        }
+    }
+
+    Ok((removed, reclaimed))
+}
+
+/// The disk usage of an image in the local image store
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct ImageUsage {
+    /// The id of the image
+    pub id: String,
+
+    /// The references to the image in the images map
+    pub references: Vec<String>,
+
+    /// The number of blobs in the blob store that the image references
+    pub blobs: usize,
+
+    /// The total size of the blobs that the image references
+    pub size: u64,
+
+    /// The size of the blobs that are only referenced by this image
+    ///
+    /// This is the disk space that would be reclaimed if the image was removed.
+    pub unique_size: u64,
+}
+
+/// The disk usage of a blob (e.g. an image layer) in the local image store
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct BlobUsage {
+    /// The digest of the blob
+    pub digest: String,
+
+    /// The size of the blob
+    pub size: u64,
+
+    /// The ids of the images that reference the blob
+    pub images: Vec<String>,
+}
+
+/// The result of pruning the local image store
+#[derive(Debug, Default, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct Pruned {
+    /// The image references that were removed from the images map
+    pub references: Vec<String>,
+
+    /// The ids of the images whose layout directories were removed
+    pub images: Vec<String>,
+
+    /// The digests of the blobs that were removed
+    pub blobs: Vec<String>,
+
+    /// The number of bytes reclaimed
+    pub bytes: u64,
+}
+
 /// A persistent mapping of blobs to the registries and repositories they occur in
 ///
 /// Used for [Cross Repository Blob Mounting](https://github.com/opencontainers/distribution-spec/blob/main/spec.md#mounting-a-blob-from-another-repository)
@@ -391,3 +822,81 @@ impl BlobsMap {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::tempfile::tempdir;
+
+    use super::*;
+
+    /// Test that unreferenced blobs are removed and that blobs duplicated in layouts
+    /// are moved to the shared blob store
+    #[test]
+    fn garbage_collection() -> Result<()> {
+        let temp = tempdir()?;
+        let images_dir = temp.path().join("images");
+        let blobs_dir = temp.path().join("blobs");
+        let layout_dir = images_dir.join("sha256").join("1111");
+
+        let manifest = r#"{
+            "schemaVersion": 2,
+            "config": {"mediaType": "application/vnd.oci.image.config.v1+json", "digest": "sha256:cccc", "size": 2},
+            "layers": [
+                {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:aaaa", "size": 4},
+                {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:bbbb", "size": 4}
+            ]
+        }"#;
+        let index = r#"{
+            "schemaVersion": 2,
+            "manifests": [
+                {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:mmmm", "size": 1}
+            ]
+        }"#;
+
+        // Shared blobs, including one that is not referenced by any image
+        create_dir_all(blobs_dir.join("sha256"))?;
+        for (hash, content) in [
+            ("mmmm", manifest),
+            ("cccc", "{}"),
+            ("aaaa", "AAAA"),
+            ("zzzz", "ZZZZ"),
+        ] {
+            write(blobs_dir.join("sha256").join(hash), content)?;
+        }
+
+        // A layout with symlinks to some blobs and a copy of one that is not yet in the blob store
+        create_dir_all(layout_dir.join("blobs").join("sha256"))?;
+        write(layout_dir.join("index.json"), index)?;
+        for hash in ["mmmm", "cccc", "aaaa"] {
+            blob_symlink(&blobs_dir.join("sha256").join(hash), &layout_dir)?;
+        }
+        write(layout_dir.join("blobs").join("sha256").join("bbbb"), "BBBB")?;
+
+        let references = blob_references(&images_dir)?;
+        assert_eq!(references.len(), 4);
+        assert_eq!(references["sha256:aaaa"], vec!["sha256:1111".to_string()]);
+
+        let (removed, reclaimed) = collect_garbage(&images_dir, &blobs_dir)?;
+        assert_eq!(removed, vec!["sha256:zzzz".to_string()]);
+        assert_eq!(reclaimed, 4);
+
+        // The copy of the blob has been moved to the blob store and symlinked to
+        assert!(blobs_dir.join("sha256").join("bbbb").is_file());
+        assert!(layout_dir
+            .join("blobs")
+            .join("sha256")
+            .join("bbbb")
+            .is_symlink());
+        assert_eq!(
+            read_to_string(layout_dir.join("blobs").join("sha256").join("bbbb"))?,
+            "BBBB"
+        );
+
+        // Once the layout is removed, all blobs are unreferenced
+        remove_dir_all(&layout_dir)?;
+        let (removed, ..) = collect_garbage(&images_dir, &blobs_dir)?;
+        assert_eq!(removed.len(), 4);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use common::chrono::{TimeZone, Utc};
+
 /// Create an (almost) unique string without use of external crates
 ///
 /// Based on https://users.rust-lang.org/t/random-number-without-using-the-external-crate/17260/9
@@ -14,3 +16,22 @@ pub fn unique_string() -> String {
 
     format!("{:x}", nanos + ptr1 + ptr2)
 }
+
+/// Get a timestamp, as an RFC 3339 string, for use in image metadata
+///
+/// If `deterministic` is true, then the time given by the `SOURCE_DATE_EPOCH` environment
+/// variable (or the Unix epoch if that is not set) is used so that the timestamps, and
+/// hence the digests of the blobs that contain them, are reproducible.
+/// See https://reproducible-builds.org/docs/source-date-epoch/.
+pub fn timestamp(deterministic: bool) -> String {
+    if deterministic {
+        std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.trim().parse().ok())
+            .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+            .unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap())
+            .to_rfc3339()
+    } else {
+        Utc::now().to_rfc3339()
+    }
+}
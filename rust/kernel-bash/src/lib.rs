@@ -26,11 +26,13 @@ mod tests {
     use kernel::{
         common::{
             eyre::{bail, Result},
+            tempfile::tempdir,
             tokio,
         },
         stencila_schema::Node,
         KernelTrait,
     };
+    use kernel_micro::Sandbox;
     use test_utils::{assert_json_eq, common::serde_json::json, skip_ci_os};
 
     /// Tests of basic functionality
@@ -102,4 +104,111 @@ mod tests {
 
         Ok(())
     }
+
+    /// Whether unprivileged user namespaces, which sandboxing requires, are available
+    fn sandbox_unavailable() -> bool {
+        let unavailable = !cfg!(target_os = "linux")
+            || std::fs::read_to_string("/proc/sys/user/max_user_namespaces")
+                .map(|max| max.trim() == "0")
+                .unwrap_or(true);
+        if unavailable {
+            eprintln!("Skipping test because sandboxing is not available");
+        }
+        unavailable
+    }
+
+    /// Test that a sandboxed kernel can only write to its scratch directory
+    #[tokio::test]
+    async fn sandbox_writes() -> Result<()> {
+        if sandbox_unavailable() {
+            return Ok(());
+        }
+
+        let project = tempdir()?;
+        std::fs::write(project.path().join("data.txt"), "data")?;
+
+        let mut kernel = new();
+        kernel.set_sandbox(Some(Sandbox::parse("scratch=tmp")?));
+        kernel.start(project.path()).await?;
+
+        // Can read from, but not write to, the project
+        let (outputs, messages) = kernel.exec("cat data.txt", None).await?;
+        assert_json_eq!(messages, json!([]));
+        assert_json_eq!(outputs, ["data"]);
+
+        let (.., messages) = kernel.exec("echo changed > data.txt", None).await?;
+        assert!(messages[0].error_message.contains("Read-only file system"));
+
+        // Can not write elsewhere on the file system, including the system temporary directory
+        let outside = std::env::temp_dir().join(format!(
+            "stencila-sandbox-{}",
+            project.path().file_name().unwrap_or_default().to_string_lossy()
+        ));
+        let (.., messages) = kernel
+            .exec(&format!("echo hello > {}", outside.display()), None)
+            .await?;
+        assert!(messages[0].error_message.contains("Read-only file system"));
+        assert!(!outside.exists());
+
+        // Can write to the scratch directory
+        let (outputs, messages) = kernel
+            .exec("echo hello > $TMPDIR/hello.txt && cat tmp/hello.txt", None)
+            .await?;
+        assert_json_eq!(messages, json!([]));
+        assert_json_eq!(outputs, ["hello"]);
+        assert!(project.path().join("tmp").join("hello.txt").exists());
+
+        kernel.stop().await?;
+
+        Ok(())
+    }
+
+    /// Test that a sandboxed kernel does not have network access unless allowed
+    #[tokio::test]
+    async fn sandbox_network() -> Result<()> {
+        if sandbox_unavailable() {
+            return Ok(());
+        }
+
+        let project = tempdir()?;
+
+        let mut kernel = new();
+        kernel.set_sandbox(Some(Sandbox::default()));
+        kernel.start(project.path()).await?;
+
+        let (.., messages) = kernel
+            .exec("exec 3<>/dev/tcp/1.1.1.1/80", None)
+            .await?;
+        assert!(messages[0].error_message.contains("Network is unreachable"));
+
+        kernel.stop().await?;
+
+        Ok(())
+    }
+
+    /// Test that a sandboxed kernel that exceeds its CPU time limit results in a `SandboxError`
+    #[tokio::test]
+    async fn sandbox_cpu() -> Result<()> {
+        if sandbox_unavailable() {
+            return Ok(());
+        }
+
+        let project = tempdir()?;
+
+        let mut kernel = new();
+        kernel.set_sandbox(Some(Sandbox::parse("cpu=1")?));
+        kernel.start(project.path()).await?;
+
+        let (.., messages) = kernel.exec("while :; do :; done", None).await?;
+        let error = messages
+            .iter()
+            .find(|message| message.error_type.as_deref().map(|error_type| error_type.as_str()) == Some("SandboxError"))
+            .expect("Expected a SandboxError");
+        assert_eq!(
+            error.error_message,
+            "Kernel exceeded the sandbox CPU time limit of 1 seconds"
+        );
+
+        Ok(())
+    }
 }
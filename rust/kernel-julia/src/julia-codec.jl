@@ -0,0 +1,127 @@
+import Base64
+
+# Decode JSON to a Julia value
+#
+# Datatables are decoded to a `DataFrame` if the `DataFrames` package has been loaded,
+# and to a `NamedTuple` of columns otherwise.
+function decode_value(json)
+  value = JSON.parse(json)
+  if isa(value, AbstractDict) && get(value, "type", nothing) == "Datatable"
+    decode_datatable(value)
+  else
+    value
+  end
+end
+
+# Decode a `Datatable` to a `DataFrame` or `NamedTuple`
+function decode_datatable(datatable)
+  columns = [Symbol(column["name"]) => column["values"] for column in datatable["columns"]]
+  data_frames = loaded_module("DataFrames")
+  if data_frames !== nothing
+    Base.invokelatest(data_frames.DataFrame, columns...)
+  else
+    (; columns...)
+  end
+end
+
+# Get a loaded module by name, or `nothing` if it has not been loaded
+function loaded_module(name)
+  for (id, mod) in Base.loaded_modules
+    if id.name == name
+      return mod
+    end
+  end
+  nothing
+end
+
+# Encode a Julia value to JSON
+encode_value(value) = JSON.json(convert_value(value))
+
+# Convert a value prior to encoding
+function convert_value(value)
+  # The order of these if statements is important (since for e.g. a data frame may be showable as PNG)
+  if value === nothing || value === missing
+    nothing
+  elseif isa(value, Union{Bool,AbstractString})
+    value
+  elseif isa(value, Real)
+    isfinite(value) ? value : nothing
+  elseif isa(value, Symbol)
+    string(value)
+  elseif is_data_frame(value)
+    convert_data_frame(value)
+  elseif isa(value, AbstractDict)
+    Dict(string(key) => convert_value(item) for (key, item) in value)
+  elseif isa(value, NamedTuple)
+    Dict(string(key) => convert_value(item) for (key, item) in pairs(value))
+  elseif isa(value, AbstractMatrix)
+    [convert_value(collect(row)) for row in eachrow(value)]
+  elseif isa(value, Union{AbstractVector,Tuple})
+    [convert_value(item) for item in value]
+  elseif showable(MIME("image/png"), value)
+    convert_plot(value)
+  else
+    repr(MIME("text/plain"), value)
+  end
+end
+
+# Convert a plot (or any other value that can be shown as a PNG) to an `ImageObject`
+function convert_plot(value)
+  Dict(
+    "type" => "ImageObject",
+    "contentUrl" => string("data:image/png;base64,", Base64.base64encode(show, MIME("image/png"), value)),
+  )
+end
+
+# Is a value a `DataFrame`?
+#
+# The name of the type is checked so that the `DataFrames` package does not need to be
+# loaded by this script.
+is_data_frame(value) = nameof(typeof(value)) == :DataFrame
+
+# Convert a `DataFrame` to a `Datatable`
+function convert_data_frame(df)
+  columns = [convert_data_frame_column(name, getproperty(df, Symbol(name))) for name in names(df)]
+  Dict(
+    "type" => "Datatable",
+    "columns" => filter(column -> column !== nothing, columns),
+  )
+end
+
+# Convert a `DataFrame` column to a `DatatableColumn`
+#
+# Because the levels of a `CategoricalArray` are strings, categorical columns are converted into a
+# column with `validator.items` of type `EnumValidator` with `values` containing the levels.
+function convert_data_frame_column(name, column)
+  type = nonmissingtype(eltype(column))
+  if nameof(typeof(column)) in (:CategoricalArray, :CategoricalVector)
+    levels = getfield(parentmodule(typeof(column)), :levels)(column)
+    validator = Dict("type" => "EnumValidator", "values" => string.(levels))
+    values = [ismissing(value) ? nothing : string(value) for value in column]
+  elseif type <: Bool
+    validator = Dict("type" => "BooleanValidator")
+    values = [ismissing(value) ? nothing : value for value in column]
+  elseif type <: Real
+    validator = Dict("type" => "NumberValidator")
+    values = [ismissing(value) || !isfinite(value) ? nothing : value for value in column]
+  elseif type <: AbstractString
+    validator = Dict("type" => "StringValidator")
+    values = [ismissing(value) ? nothing : value for value in column]
+  else
+    return nothing
+  end
+
+  Dict(
+    "type" => "DatatableColumn",
+    "name" => string(name),
+    "values" => values,
+    "validator" => Dict("type" => "ArrayValidator", "itemsValidator" => validator),
+  )
+end
+
+# Encode a message to JSON
+encode_message(message, type) = JSON.json(Dict(
+  "type" => "CodeError",
+  "errorType" => type,
+  "errorMessage" => message,
+))
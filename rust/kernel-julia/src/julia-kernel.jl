@@ -0,0 +1,131 @@
+#!/usr/bin/env julia
+
+# Check that required packages are installed. They are not installed here because
+# that would change the user's environment (and require network access).
+try
+  import JSON
+catch
+  println(stderr, "The Julia kernel requires the `JSON` package. Install it using `julia -e 'import Pkg; Pkg.add(\"JSON\")'`")
+  exit(1)
+end
+
+# Include sibling files
+include(joinpath(@__DIR__, "julia-codec.jl"))
+
+# Use easier-to-type flags during development manual testing
+if isa(stdin, Base.TTY)
+  const READY = "READY"
+  const RESULT = "RESULT"
+  const TASK = "TASK"
+  const FORK = "FORK"
+  const NEWLINE = "NEWLINE"
+  const EXIT = "EXIT"
+else
+  const READY = "\U10ACDC"
+  const RESULT = "\U10CB40"
+  const TASK = "\U10ABBA"
+  const FORK = "\U10DE70"
+  const NEWLINE = "\U10B522"
+  const EXIT = "\U10CC00"
+end
+
+# Throw an `InterruptException` on SIGINT, rather than exiting, so that
+# tasks can be interrupted
+Base.exit_on_sigint(false)
+
+# Functions to write results and messages (`CodeMessage`s).
+result(json) = (println(stdout, json, RESULT); nothing)
+message(msg, type) = (println(stderr, encode_message(msg, type), RESULT); nothing)
+
+# Module in which code will be executed
+const context = Module(:Stencila)
+Core.eval(context, :(const decode_value = $decode_value))
+
+# The pattern of the code generated from the microkernel's `set_template`.
+# Setting variables is handled directly, rather than by evaluating the code, because
+# Julia raw strings do not preserve backslashes before quotes in the JSON.
+const SET_PATTERN = r"^(\w+) = decode_value\(raw\"\"\"(.*)\"\"\"\)$"s
+
+# Execute code, writing any outputs and messages
+function execute(code)
+  set = match(SET_PATTERN, code)
+  if set !== nothing
+    Core.eval(context, :($(Symbol(set[1])) = $(decode_value(set[2]))))
+    return
+  end
+
+  exprs = Meta.parseall(code)
+  for expr in exprs.args
+    if isa(expr, Expr) && expr.head in (:error, :incomplete)
+      message(string(expr.args[1]), "SyntaxError")
+      return
+    end
+  end
+
+  # Evaluate the code capturing anything written to `stdout` (e.g. by `println`)
+  # so that it can be returned as a string output
+  (path, io) = mktemp()
+  value = try
+    redirect_stdout(io) do
+      Core.eval(context, exprs)
+    end
+  catch error
+    if isa(error, InterruptException)
+      message("Code execution was interrupted", "Interrupt")
+    else
+      message(sprint(showerror, error), "RuntimeError")
+    end
+    nothing
+  finally
+    close(io)
+  end
+  output = read(path, String)
+  rm(path)
+  if !isempty(output)
+    result(encode_value(output))
+  end
+
+  # Only return value if not `nothing`, the code does not end with a semicolon,
+  # and the last expression is not an assignment or function definition
+  last = findlast(expr -> !isa(expr, LineNumberNode), exprs.args)
+  last = last === nothing ? nothing : exprs.args[last]
+  silent =
+    endswith(rstrip(code), ";") ||
+    (isa(last, Expr) && last.head in (:(=), :function, :macro, :struct, :using, :import))
+  if value !== nothing && !silent
+    result(encode_value(value))
+  end
+end
+
+println(stdout, READY)
+flush(stdout)
+println(stderr, READY)
+flush(stderr)
+
+while true
+  try
+    # If `stdin` is closed then exit gracefully
+    if eof(stdin)
+      exit()
+    end
+    task = readline(stdin)
+    lines = split(task, NEWLINE)
+
+    if lines[1] == EXIT
+      exit()
+    end
+
+    execute(join(lines, "\n"))
+
+    println(stdout, TASK)
+    flush(stdout)
+    println(stderr, TASK)
+    flush(stderr)
+  catch error
+    # Ignore any interrupts that get accidentally sent while there
+    # is no task running to avoid ending the kernel process
+    if !isa(error, InterruptException)
+      rethrow()
+    end
+  end
+end
@@ -0,0 +1,267 @@
+use kernel_micro::{include_file, MicroKernel};
+
+/// A microkernel for Julia
+///
+/// The kernel is not forkable because the Julia runtime is multithreaded (e.g. it has
+/// garbage collection and `libuv` threads) and a `fork` only copies the calling thread.
+/// It requires the `JSON` package to be installed in the user's Julia environment.
+pub fn new() -> MicroKernel {
+    MicroKernel::new(
+        "julia-micro",
+        &["julia"],
+        true,
+        cfg!(not(target_os = "windows")),
+        false,
+        ("julia", "*"),
+        &[
+            "--quiet",
+            "--startup-file=no",
+            "--history-file=no",
+            "{{script}}",
+        ],
+        include_file!("julia-kernel.jl"),
+        &[include_file!("julia-codec.jl")],
+        "{{name}} = decode_value(\"{{json_escaped}}\")",
+        "{{name}}",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use kernel::{
+        common::{
+            eyre::{bail, Result},
+            tokio,
+        },
+        stencila_schema::{Array, Node, Number, Object, Primitive},
+        KernelTrait,
+    };
+    use test_utils::{assert_json_eq, assert_json_is};
+
+    use super::*;
+
+    async fn skip_or_kernel() -> Result<MicroKernel> {
+        let mut kernel = new();
+        if !kernel.is_available().await {
+            eprintln!("Julia not available on this machine");
+            bail!("Skipping")
+        } else {
+            kernel.start_here().await?;
+        }
+
+        Ok(kernel)
+    }
+
+    /// Tests of basic functionality
+    /// This test is replicated in all the microkernels.
+    /// Other test should be written for language specific quirks and regressions.
+    #[tokio::test]
+    async fn basics() -> Result<()> {
+        let mut kernel = match skip_or_kernel().await {
+            Ok(kernel) => kernel,
+            Err(..) => return Ok(()),
+        };
+
+        // Assign a variable and output it
+        let (outputs, messages) = kernel.exec("a = 2\na", None).await?;
+        assert_json_is!(messages, []);
+        assert_json_is!(outputs, [2]);
+
+        // Print the variable twice and then output it
+        let (outputs, messages) = kernel.exec("println(a)\nprintln(a)\na", None).await?;
+        assert_json_is!(messages, []);
+        assert_json_is!(outputs, ["2\n2\n", 2]);
+
+        // Syntax error
+        let (outputs, messages) = kernel.exec("bad ^ # syntax", None).await?;
+        assert_json_is!(messages[0].error_type, "SyntaxError");
+        assert_json_is!(outputs, []);
+
+        // Runtime error
+        let (outputs, messages) = kernel.exec("foo", None).await?;
+        assert_json_is!(messages[0].error_type, "RuntimeError");
+        assert!(messages[0].error_message.starts_with("UndefVarError"));
+        assert_json_is!(outputs, []);
+
+        // Set and get another variable
+        kernel.set("b", Node::Integer(3)).await?;
+        let b = kernel.get("b").await?;
+        assert_json_is!(b, 3);
+
+        // Use both variables
+        let (outputs, messages) = kernel.exec("a*b", None).await?;
+        assert_json_is!(messages, []);
+        assert_json_is!(outputs, [6]);
+
+        // Set and get are "reversible"
+        let vars: HashMap<String, Node> = [
+            ("var1", Node::Boolean(true)),
+            ("var2", Node::Boolean(false)),
+            ("var3", Node::Integer(123)),
+            ("var4", Node::Number(Number(1.23))),
+            (
+                "var5",
+                Node::Array(Array::from([
+                    Primitive::Integer(1),
+                    Primitive::Integer(2),
+                    Primitive::Integer(3),
+                ])),
+            ),
+            (
+                "var6",
+                Node::Object(Object::from([
+                    ("a".to_string(), Primitive::Integer(1)),
+                    ("b".to_string(), Primitive::Integer(2)),
+                    ("c".to_string(), Primitive::Integer(3)),
+                ])),
+            ),
+            (
+                "var7",
+                Node::String(r#"say "hi", \ cost $5 or """$(a)""""#.to_string()),
+            ),
+        ]
+        .map(|(name, node)| (name.to_string(), node))
+        .into();
+
+        for (name, value) in vars {
+            kernel.set(&name, value.clone()).await?;
+            let got = kernel.get(&name).await?;
+            assert_json_eq!(got, value)
+        }
+
+        Ok(())
+    }
+
+    /// Test that an assignment, or a semicolon, on the last line does not generate an output
+    #[tokio::test]
+    async fn assignment_no_output() -> Result<()> {
+        let mut kernel = match skip_or_kernel().await {
+            Ok(kernel) => kernel,
+            Err(..) => return Ok(()),
+        };
+
+        let (outputs, messages) = kernel.exec("a = 1", None).await?;
+        assert!(messages.is_empty());
+        assert_json_is!(outputs, []);
+
+        let (outputs, messages) = kernel.exec("a + 1;", None).await?;
+        assert!(messages.is_empty());
+        assert_json_is!(outputs, []);
+
+        let (outputs, messages) = kernel.exec("f(x) = x^2", None).await?;
+        assert!(messages.is_empty());
+        assert_json_is!(outputs, []);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encode_dataframes() -> Result<()> {
+        let mut kernel = match skip_or_kernel().await {
+            Ok(kernel) => kernel,
+            Err(..) => return Ok(()),
+        };
+
+        let (.., messages) = kernel.exec("using DataFrames", None).await?;
+        if !messages.is_empty() {
+            eprintln!("DataFrames package not available");
+            return Ok(());
+        }
+
+        let (outputs, messages) = kernel
+            .exec(
+                r#"DataFrame(a = 1:2, b = [true, false], c = ["x", "y"])"#,
+                None,
+            )
+            .await?;
+        assert_json_is!(messages, []);
+        let dt = match &outputs[0] {
+            Node::Datatable(dt) => dt.clone(),
+            _ => bail!("unexpected type {:?}", outputs[0]),
+        };
+        assert_eq!(
+            dt.columns
+                .iter()
+                .map(|column| column.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_json_is!(
+            dt.columns[0].validator.as_ref().unwrap().items_validator,
+            { "type": "NumberValidator"}
+        );
+        assert_json_is!(
+            dt.columns[1].validator.as_ref().unwrap().items_validator,
+            { "type": "BooleanValidator"}
+        );
+        assert_json_is!(
+            dt.columns[2].validator.as_ref().unwrap().items_validator,
+            { "type": "StringValidator"}
+        );
+
+        // Set a Datatable and get it back
+        kernel.set("df", Node::Datatable(dt.clone())).await?;
+        let (outputs, messages) = kernel.exec("size(df)", None).await?;
+        assert_json_is!(messages, []);
+        assert_json_is!(outputs, [[2, 3]]);
+
+        Ok(())
+    }
+
+    /// Test interrupting a task
+    #[tokio::test]
+    async fn exec_async() -> Result<()> {
+        let mut kernel = match skip_or_kernel().await {
+            Ok(kernel) => {
+                if kernel.is_interruptable().await {
+                    kernel
+                } else {
+                    eprintln!("Not interruptable on this OS");
+                    return Ok(());
+                }
+            }
+            Err(..) => return Ok(()),
+        };
+
+        // Start a long running task in the kernel that should get interrupted
+        let mut task = kernel
+            .exec_async("started = true; sleep(10); finished = true", None)
+            .await?;
+
+        // Sleep a little to allow the task to start, then interrupt it
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        task.interrupt().await?;
+
+        // Check that was started but not finished
+        let (outputs, messages) = kernel
+            .exec("[started, isdefined(@__MODULE__, :finished)]", None)
+            .await?;
+        assert_json_is!(messages, []);
+        assert_json_is!(outputs, [[true, false]]);
+
+        Ok(())
+    }
+
+    /// Test that the kernel is not forkable
+    #[tokio::test]
+    async fn not_forkable() -> Result<()> {
+        let kernel = new();
+        assert!(!kernel.is_forkable().await);
+        Ok(())
+    }
+
+    /// Test setting and getting of vars of different types
+    #[tokio::test]
+    async fn set_get_vars() -> Result<()> {
+        let mut kernel = match skip_or_kernel().await {
+            Ok(kernel) => kernel,
+            Err(..) => return Ok(()),
+        };
+
+        kernel_micro::tests::set_get_strings(&mut kernel).await?;
+
+        Ok(())
+    }
+}
@@ -37,6 +37,7 @@ use path_utils::path_slash::PathBufExt;
 mod connection;
 pub mod dirs;
 mod messages;
+mod remote;
 mod server;
 
 use crate::{
@@ -48,6 +49,7 @@ use crate::{
     },
 };
 
+pub use remote::JupyterRemoteKernel;
 pub use server::JupyterServer;
 
 // A UUID for kernels
@@ -939,11 +941,16 @@ fn shutdown(_language: &str) -> Result<Option<String>> {
 }
 
 /// Language specific code for getting a variable
-fn _get(language: &str, name: &str) -> Result<Option<String>> {
+///
+/// The JSON is printed to `stdout` so that it is available as a string output.
+fn get(language: &str, name: &str) -> Result<Option<String>> {
     Ok(match language {
-        "javascript" => Some(format!("JSON.stringify({})", name)),
-        "python" => Some(format!("json.dumps({})", name)),
-        "r" => Some(format!("jsonlite::toJSON({})", name)),
+        "javascript" => Some(format!("console.log(JSON.stringify({}))", name)),
+        "python" => Some(format!("print(json.dumps({}))", name)),
+        "r" => Some(format!(
+            "cat(jsonlite::toJSON({}, auto_unbox = TRUE))",
+            name
+        )),
         _ => None,
     })
 }
@@ -0,0 +1,726 @@
+use std::{env, path::Path, sync::Arc, time::Duration};
+
+use reqwest::{Method, RequestBuilder, Url};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use kernel::{
+    common::{
+        async_trait::async_trait,
+        eyre::{bail, eyre, Result},
+        futures::{SinkExt, StreamExt},
+        serde::{Deserialize, Serialize},
+        serde_json::{self, json},
+        tokio::{
+            self,
+            sync::{broadcast, mpsc, RwLock},
+            task::JoinHandle,
+            time::timeout,
+        },
+        tracing,
+    },
+    stencila_schema::{CodeError, Node},
+    Kernel, KernelSelector, KernelStatus, KernelTrait, KernelType, TagMap, Task, TaskResult,
+};
+
+use crate::{
+    get,
+    messages::{JupyterKernelInfoReply, JupyterMessage, JupyterMessageHeader},
+    set, shutdown, startup, JupyterKernel, JupyterSessionId,
+};
+
+/// The environment variable used to specify the default URL of a Jupyter Server or Kernel Gateway
+///
+/// May include a `token` query parameter e.g. `http://compute.example.org:8888?token=abc123`.
+const GATEWAY_ENV_VAR: &str = "STENCILA_JUPYTER_GATEWAY";
+
+/// A kernel that delegates to a Jupyter kernel running on a remote Jupyter Server or Kernel Gateway
+///
+/// The kernel is started, stopped and interrupted using the server's REST API and messages are
+/// sent to, and received from, it using the server's WebSocket API
+/// (see https://jupyter-server.readthedocs.io/en/latest/developers/rest-api.html).
+///
+/// The URL of the server, including any `token` query parameter, is taken from the `config` of
+/// the kernel selector e.g. `python type:jupyter-remote config:http://host:8888?token=abc`,
+/// falling back to the `STENCILA_JUPYTER_GATEWAY` environment variable.
+#[derive(Debug, Serialize)]
+#[serde(crate = "kernel::common::serde")]
+pub struct JupyterRemoteKernel {
+    /// The name of the kernel spec on the server e.g. `python3`, `ir`
+    ///
+    /// May be empty until the kernel is started, in which case the first kernel spec
+    /// on the server supporting the `language` will be used.
+    name: String,
+
+    /// The language of the kernel
+    language: String,
+
+    /// The base URL of the server (without the `token`)
+    url: Option<String>,
+
+    /// The token used to authenticate with the server
+    #[serde(skip)]
+    token: Option<String>,
+
+    /// The id of the kernel on the server
+    ///
+    /// Will be `None` until the kernel is started.
+    id: Option<String>,
+
+    /// The kernel session id
+    session: JupyterSessionId,
+
+    /// The status of the kernel
+    #[serde(skip)]
+    status: Arc<RwLock<KernelStatus>>,
+
+    /// Details of the connection to the kernel once started
+    #[serde(skip)]
+    details: Option<RemoteDetails>,
+}
+
+/// Runtime details of the connection to a remote kernel
+#[derive(Debug)]
+struct RemoteDetails {
+    /// The sender for messages to send to the kernel over the WebSocket
+    outgoing_sender: mpsc::UnboundedSender<String>,
+
+    /// The sender for IOPub messages received from the kernel
+    iopub_sender: broadcast::Sender<JupyterMessage>,
+
+    /// The sender for shell messages (i.e. replies) received from the kernel
+    shell_sender: broadcast::Sender<JupyterMessage>,
+
+    /// The async task that sends and receives messages over the WebSocket
+    websocket_task: JoinHandle<()>,
+}
+
+/// A Jupyter message as sent over the Jupyter Server WebSocket API
+///
+/// Differs from the ZeroMQ wire protocol in that the message is a single JSON object
+/// which includes the name of the channel and is not signed.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, crate = "kernel::common::serde")]
+struct WebSocketMessage {
+    channel: String,
+    header: JupyterMessageHeader,
+    parent_header: Option<JupyterMessageHeader>,
+    metadata: serde_json::Value,
+    content: serde_json::Value,
+    buffers: Vec<serde_json::Value>,
+}
+
+impl JupyterRemoteKernel {
+    /// Create a new `JupyterRemoteKernel`
+    ///
+    /// Does not make any requests to the server (that is done in `start()`).
+    pub fn new(selector: &KernelSelector) -> Self {
+        let config = selector
+            .config
+            .clone()
+            .or_else(|| env::var(GATEWAY_ENV_VAR).ok());
+        let (url, token) = match config.as_deref().map(parse_url) {
+            Some(Ok((url, token))) => (Some(url), token),
+            Some(Err(error)) => {
+                tracing::warn!("Invalid URL for Jupyter server: {}", error);
+                (None, None)
+            }
+            None => (None, None),
+        };
+
+        Self {
+            name: selector
+                .name
+                .clone()
+                .or_else(|| selector.any.clone())
+                .unwrap_or_default(),
+            language: selector
+                .lang
+                .clone()
+                .or_else(|| selector.any.clone())
+                .unwrap_or_default(),
+            url,
+            token,
+            id: None,
+            session: JupyterSessionId::new(),
+            status: Arc::new(RwLock::new(KernelStatus::Pending)),
+            details: None,
+        }
+    }
+
+    /// Get a list of the kernels available on the server specified by `STENCILA_JUPYTER_GATEWAY`
+    ///
+    /// Returns an empty list if the environment variable is not set.
+    pub async fn available() -> Result<Vec<Kernel>> {
+        let config = match env::var(GATEWAY_ENV_VAR) {
+            Ok(config) => config,
+            Err(..) => return Ok(Vec::new()),
+        };
+        let (url, token) = parse_url(&config)?;
+
+        let kernels = kernelspecs(&url, token.as_deref())
+            .await?
+            .into_iter()
+            .map(|(name, language)| {
+                Kernel::new(
+                    &name,
+                    KernelType::JupyterRemote,
+                    &[&language],
+                    true,
+                    true,
+                    false,
+                )
+            })
+            .collect();
+        Ok(kernels)
+    }
+
+    /// Get the base URL of the server
+    fn url(&self) -> Result<&str> {
+        self.url.as_deref().ok_or_else(|| {
+            eyre!(
+                "No URL for the Jupyter server; use a `config` selector or set `{}`",
+                GATEWAY_ENV_VAR
+            )
+        })
+    }
+
+    /// Get the id of the kernel on the server
+    fn id(&self) -> Result<&str> {
+        self.id
+            .as_deref()
+            .ok_or_else(|| eyre!("Remote Jupyter kernel has not been started"))
+    }
+
+    /// Send a message to the kernel on a channel
+    fn send(&self, channel: &str, message: &JupyterMessage) -> Result<()> {
+        let details = self
+            .details
+            .as_ref()
+            .ok_or_else(|| eyre!("Remote Jupyter kernel has not been started"))?;
+
+        let mut header = message.header.clone();
+        header.session = self.session.to_string();
+        let json = serde_json::to_string(&WebSocketMessage {
+            channel: channel.to_string(),
+            header,
+            parent_header: None,
+            metadata: message.metadata.clone(),
+            content: message.content.clone(),
+            buffers: Vec::new(),
+        })?;
+
+        details.outgoing_sender.send(json)?;
+
+        Ok(())
+    }
+
+    /// Send a message to the kernel on the shell channel and wait for the reply
+    async fn request(&self, message: &JupyterMessage, wait: Duration) -> Result<JupyterMessage> {
+        let details = self
+            .details
+            .as_ref()
+            .ok_or_else(|| eyre!("Remote Jupyter kernel has not been started"))?;
+
+        // Subscribe before sending so that the reply is not missed
+        let mut shell_receiver = details.shell_sender.subscribe();
+        self.send("shell", message)?;
+
+        let msg_id = message.header.msg_id.clone();
+        let reply = timeout(wait, async move {
+            while let Ok(reply) = shell_receiver.recv().await {
+                if let Some(parent_header) = &reply.parent_header {
+                    if parent_header.msg_id == msg_id {
+                        return Some(reply);
+                    }
+                }
+            }
+            None
+        })
+        .await;
+
+        match reply {
+            Ok(Some(reply)) => Ok(reply),
+            Ok(None) => bail!("Connection to remote Jupyter kernel was closed"),
+            Err(..) => bail!("Timed out waiting for reply from remote Jupyter kernel"),
+        }
+    }
+
+    /// Send an `execute_request` to the kernel and wait for the results
+    async fn execute(&self, code: &str) -> Result<(Vec<Node>, Vec<CodeError>)> {
+        let details = self
+            .details
+            .as_ref()
+            .ok_or_else(|| eyre!("Remote Jupyter kernel has not been started"))?;
+
+        let request = JupyterMessage::execute_request(code);
+        let iopub_receiver = details.iopub_sender.subscribe();
+        self.send("shell", &request)?;
+
+        JupyterKernel::exec_results(
+            &request.header.msg_id,
+            &self.language,
+            self.status.clone(),
+            iopub_receiver,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl KernelTrait for JupyterRemoteKernel {
+    async fn spec(&self) -> Kernel {
+        let name = if self.name.is_empty() {
+            "jupyter-remote"
+        } else {
+            &self.name
+        };
+        Kernel::new(
+            name,
+            KernelType::JupyterRemote,
+            &[&self.language],
+            self.url.is_some(),
+            true,
+            false,
+        )
+    }
+
+    async fn start(&mut self, _directory: &Path) -> Result<()> {
+        let url = self.url()?.to_string();
+        let token = self.token.clone();
+
+        // Resolve the kernel spec to use, matching on name and then on language
+        let specs = kernelspecs(&url, token.as_deref()).await?;
+        let (name, language) = specs
+            .iter()
+            .find(|(name, ..)| name == &self.name)
+            .or_else(|| {
+                specs
+                    .iter()
+                    .find(|(.., language)| language.eq_ignore_ascii_case(&self.language))
+            })
+            .cloned()
+            .ok_or_else(|| {
+                eyre!(
+                    "Unable to find a kernel on Jupyter server `{}` matching name `{}` or language `{}`",
+                    url,
+                    self.name,
+                    self.language
+                )
+            })?;
+
+        // Start the kernel on the server
+        *(self.status.write().await) = KernelStatus::Starting;
+        let response = request(Method::POST, &url, "api/kernels", token.as_deref())
+            .body(json!({ "name": name }).to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        let kernel: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+        let id = kernel
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| eyre!("Response from Jupyter server did not include a kernel id"))?
+            .to_string();
+        tracing::debug!("Started remote Jupyter kernel `{}` on `{}`", id, url);
+
+        // Connect to the kernel's WebSocket
+        let mut ws_url = Url::parse(&url)?.join(&format!("api/kernels/{}/channels", id))?;
+        let scheme = if ws_url.scheme() == "https" {
+            "wss"
+        } else {
+            "ws"
+        };
+        ws_url
+            .set_scheme(scheme)
+            .map_err(|_| eyre!("Unable to set scheme of WebSocket URL"))?;
+        ws_url
+            .query_pairs_mut()
+            .append_pair("session_id", &self.session);
+        if let Some(token) = &token {
+            ws_url.query_pairs_mut().append_pair("token", token);
+        }
+        let (websocket, ..) = connect_async(ws_url.as_str()).await?;
+        let (mut sink, mut stream) = websocket.split();
+
+        // Spawn a task to send outgoing messages and to publish incoming messages on
+        // the channels that `exec()` and other methods listen on
+        let (outgoing_sender, mut outgoing_receiver) = mpsc::unbounded_channel::<String>();
+        let (iopub_sender, ..) = broadcast::channel(256);
+        let (shell_sender, ..) = broadcast::channel(32);
+        let iopub_sender_clone = iopub_sender.clone();
+        let shell_sender_clone = shell_sender.clone();
+        let kernel_id = id.clone();
+        let status = self.status.clone();
+        let websocket_task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    outgoing = outgoing_receiver.recv() => match outgoing {
+                        Some(json) => {
+                            if let Err(error) = sink.send(Message::Text(json)).await {
+                                tracing::error!(
+                                    "When sending message to remote Jupyter kernel `{}`: {}",
+                                    kernel_id,
+                                    error
+                                );
+                                *(status.write().await) = KernelStatus::Unresponsive;
+                                break;
+                            }
+                        }
+                        None => break,
+                    },
+                    incoming = stream.next() => match incoming {
+                        Some(Ok(Message::Text(json))) => {
+                            let WebSocketMessage {
+                                channel,
+                                header,
+                                parent_header,
+                                metadata,
+                                content,
+                                ..
+                            } = match serde_json::from_str(&json) {
+                                Ok(message) => message,
+                                Err(error) => {
+                                    tracing::debug!(
+                                        "Ignoring message from remote Jupyter kernel `{}`: {}",
+                                        kernel_id,
+                                        error
+                                    );
+                                    continue;
+                                }
+                            };
+                            let message = JupyterMessage {
+                                identities: Vec::new(),
+                                header,
+                                parent_header,
+                                metadata,
+                                content,
+                            };
+                            // Sending fails if there are no receivers, which is not an error
+                            match channel.as_str() {
+                                "iopub" => iopub_sender_clone.send(message).ok(),
+                                "shell" => shell_sender_clone.send(message).ok(),
+                                _ => None,
+                            };
+                        }
+                        Some(Ok(Message::Close(..))) | None => {
+                            tracing::debug!(
+                                "WebSocket for remote Jupyter kernel `{}` was closed",
+                                kernel_id
+                            );
+                            *(status.write().await) = KernelStatus::Finished;
+                            break;
+                        }
+                        Some(Ok(..)) => {}
+                        Some(Err(error)) => {
+                            tracing::error!(
+                                "When receiving message from remote Jupyter kernel `{}`: {}",
+                                kernel_id,
+                                error
+                            );
+                            *(status.write().await) = KernelStatus::Unresponsive;
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        self.name = name;
+        self.language = language;
+        self.id = Some(id);
+        self.details = Some(RemoteDetails {
+            outgoing_sender,
+            iopub_sender,
+            shell_sender,
+            websocket_task,
+        });
+
+        // Get the kernel info to confirm that the kernel is ready
+        let reply = self
+            .request(
+                &JupyterMessage::kernel_info_request(),
+                Duration::from_secs(60),
+            )
+            .await?;
+        let kernel_info: JupyterKernelInfoReply = reply.content();
+        tracing::debug!(
+            "Got kernel info for remote Jupyter kernel: {:?}",
+            kernel_info
+        );
+
+        *(self.status.write().await) = KernelStatus::Idle;
+
+        // Run any startup code
+        if let Some(code) = startup(&self.language)? {
+            self.execute(&code).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(code) = shutdown(&self.language)? {
+            self.execute(&code).await?;
+        }
+
+        if let (Ok(url), Ok(id)) = (self.url(), self.id()) {
+            let path = ["api/kernels/", id].concat();
+            if let Err(error) = request(Method::DELETE, url, &path, self.token.as_deref())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                tracing::warn!("While shutting down remote Jupyter kernel: {}", error)
+            }
+        }
+
+        if let Some(RemoteDetails { websocket_task, .. }) = &self.details {
+            websocket_task.abort();
+        }
+        self.details = None;
+
+        *(self.status.write().await) = KernelStatus::Finished;
+
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<KernelStatus> {
+        let status = self.status.read().await.clone();
+        Ok(status)
+    }
+
+    async fn get(&mut self, name: &str) -> Result<Node> {
+        let code = match get(&self.language, name)? {
+            Some(code) => code,
+            None => bail!(
+                "Getting a symbol from a `{}` Jupyter kernel is not currently supported",
+                self.language
+            ),
+        };
+
+        let (outputs, errors) = self.execute(&code).await?;
+        if let Some(error) = errors.first() {
+            bail!(
+                "While getting symbol from remote Jupyter kernel: {}",
+                error.error_message
+            )
+        }
+        match outputs.first() {
+            Some(Node::String(json)) => Ok(serde_json::from_str(json)?),
+            _ => bail!("While getting symbol from remote Jupyter kernel did not get JSON string"),
+        }
+    }
+
+    async fn set(&mut self, name: &str, value: Node) -> Result<()> {
+        let json = serde_json::to_string(&value)?;
+        let code = match set(&self.language, name, &json)? {
+            Some(code) => code,
+            None => bail!(
+                "Setting a symbol in a `{}` Jupyter kernel is not currently supported",
+                self.language
+            ),
+        };
+
+        let (.., errors) = self.execute(&code).await?;
+        if let Some(error) = errors.first() {
+            bail!(
+                "While setting symbol in remote Jupyter kernel: {}",
+                error.error_message
+            )
+        }
+
+        Ok(())
+    }
+
+    async fn exec_sync(&mut self, code: &str, _tags: Option<&TagMap>) -> Result<Task> {
+        let mut task = Task::begin_sync();
+        let (outputs, messages) = self.execute(code).await?;
+        task.end(TaskResult::new(outputs, messages));
+        Ok(task)
+    }
+
+    async fn exec_async(&mut self, code: &str, _tags: Option<&TagMap>) -> Result<Task> {
+        let details = self
+            .details
+            .as_ref()
+            .ok_or_else(|| eyre!("Remote Jupyter kernel has not been started"))?;
+
+        // Setup channels and execution task
+        let (result_sender, ..) = broadcast::channel(1);
+        let (interrupt_sender, mut interrupt_receiver) = mpsc::channel(1);
+        let task = Task::begin(Some(result_sender.clone()), Some(interrupt_sender));
+
+        // Send the request
+        let request = JupyterMessage::execute_request(code);
+        let iopub_receiver = details.iopub_sender.subscribe();
+        self.send("shell", &request)?;
+
+        // Start async task to wait for results and send on to receivers
+        let task_id = task.id.clone();
+        let language = self.language.clone();
+        let status = self.status.clone();
+        tokio::spawn(async move {
+            let result = match JupyterKernel::exec_results(
+                &request.header.msg_id,
+                &language,
+                status,
+                iopub_receiver,
+            )
+            .await
+            {
+                Ok((outputs, messages)) => TaskResult::new(outputs, messages),
+                Err(error) => {
+                    tracing::error!(
+                        "When receiving result for exec_async task `{}`: {}",
+                        task_id,
+                        error
+                    );
+                    TaskResult::internal_error("Error receiving result from remote Jupyter kernel")
+                }
+            };
+            if let Err(error) = result_sender.send(result) {
+                tracing::debug!(
+                    "When sending result for exec_async task `{}`: {}",
+                    task_id,
+                    error
+                );
+            }
+        });
+
+        // Start async task to listen for interruption message and, if received, ask the
+        // server to interrupt the kernel
+        let task_id = task.id.clone();
+        let url = self.url()?.to_string();
+        let path = ["api/kernels/", self.id()?, "/interrupt"].concat();
+        let token = self.token.clone();
+        tokio::spawn(async move {
+            if let Some(..) = interrupt_receiver.recv().await {
+                tracing::debug!("Interrupting exec_async task `{}`", task_id);
+                if let Err(error) = request(Method::POST, &url, &path, token.as_deref())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                {
+                    tracing::error!("While interrupting remote Jupyter kernel: {}", error)
+                }
+            }
+        });
+
+        Ok(task)
+    }
+}
+
+/// Parse a Jupyter server URL into a base URL and an optional token
+fn parse_url(url: &str) -> Result<(String, Option<String>)> {
+    let mut url = Url::parse(url)?;
+
+    let token = url
+        .query_pairs()
+        .find(|(key, ..)| key == "token")
+        .map(|(.., value)| value.to_string());
+    url.set_query(None);
+
+    let mut url = url.to_string();
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+
+    Ok((url, token))
+}
+
+/// Create a request to the Jupyter server REST API
+fn request(method: Method, url: &str, path: &str, token: Option<&str>) -> RequestBuilder {
+    let request = reqwest::Client::new().request(method, [url, path].concat());
+    match token {
+        Some(token) => request.header("Authorization", ["token ", token].concat()),
+        None => request,
+    }
+}
+
+/// Get the names and languages of the kernel specs available on a Jupyter server
+async fn kernelspecs(url: &str, token: Option<&str>) -> Result<Vec<(String, String)>> {
+    let response = request(Method::GET, url, "api/kernelspecs", token)
+        .send()
+        .await?
+        .error_for_status()?;
+    let json: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+    let specs = json
+        .get("kernelspecs")
+        .and_then(|specs| specs.as_object())
+        .map(|specs| {
+            specs
+                .iter()
+                .map(|(name, spec)| {
+                    let language = spec
+                        .pointer("/spec/language")
+                        .and_then(|language| language.as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    (name.clone(), language)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(specs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url() -> Result<()> {
+        assert_eq!(
+            parse_url("http://127.0.0.1:8888")?,
+            ("http://127.0.0.1:8888/".to_string(), None)
+        );
+        assert_eq!(
+            parse_url("https://example.org/jupyter?token=abc123")?,
+            (
+                "https://example.org/jupyter/".to_string(),
+                Some("abc123".to_string())
+            )
+        );
+
+        let kernel = JupyterRemoteKernel::new(&KernelSelector::parse(
+            "python type:jupyter-remote config:http://localhost:8888?token=abc",
+        ));
+        assert_eq!(kernel.url.as_deref(), Some("http://localhost:8888/"));
+        assert_eq!(kernel.token.as_deref(), Some("abc"));
+        assert_eq!(kernel.language, "python");
+
+        Ok(())
+    }
+
+    /// Test against a running Jupyter Server or Kernel Gateway
+    ///
+    /// Only runs if `STENCILA_JUPYTER_GATEWAY` is set e.g.
+    ///
+    /// ```sh
+    /// jupyter kernelgateway --KernelGatewayApp.auth_token=test &
+    /// STENCILA_JUPYTER_GATEWAY=http://127.0.0.1:8888?token=test cargo test -p kernel-jupyter remote
+    /// ```
+    #[tokio::test]
+    async fn remote() -> Result<()> {
+        if env::var(GATEWAY_ENV_VAR).is_err() {
+            return Ok(());
+        }
+
+        let mut kernel = JupyterRemoteKernel::new(&KernelSelector::parse("python"));
+        kernel.start_here().await?;
+        assert_eq!(kernel.status().await?, KernelStatus::Idle);
+
+        kernel.set("a", Node::Integer(21)).await?;
+        let (outputs, messages) = kernel.exec("print(a * 2)", None).await?;
+        assert!(messages.is_empty());
+        assert_eq!(outputs, vec![Node::String("42\n".to_string())]);
+
+        assert_eq!(kernel.get("a").await?, Node::Integer(21));
+
+        kernel.stop().await?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{env, fs, path::Path, sync::Arc};
+use std::{env, fs, path::Path, process::Stdio, sync::Arc};
 
 use kernel::{
     common::{
@@ -9,7 +9,7 @@ use kernel::{
         regex::Regex,
         serde::Serialize,
         serde_json,
-        tempfile::tempdir,
+        tempfile::{tempdir, TempDir},
         tokio::{
             self,
             fs::File,
@@ -24,6 +24,9 @@ use kernel::{
     TaskResult,
 };
 
+mod sandbox;
+pub use sandbox::Sandbox;
+
 // Line end flags for the Microkernel protocol
 // On Windows, Rscript (and possibly other binaries) escapes unicode on stdout and stderr
 // So the _ALT flags are provided for these instances (or where it is not possible to output Unicode at all).
@@ -88,6 +91,10 @@ pub struct MicroKernel {
     others: Vec<(String, String)>,
 
     /// The code template for setting a variable
+    ///
+    /// `{{name}}` is replaced with the name of the variable and `{{json}}` with its JSON.
+    /// Use `{{json_escaped}}` instead of `{{json}}` for the JSON with `\`, `"` and `$`
+    /// escaped for use within a double quoted string literal.
     #[serde(skip)]
     set_template: String,
 
@@ -95,6 +102,24 @@ pub struct MicroKernel {
     #[serde(skip)]
     get_template: String,
 
+    /// The code template for writing a variable to an Apache Arrow IPC file (if supported)
+    ///
+    /// Should output `true` if the variable was written, `false` if it was not
+    /// (e.g. because it is not tabular).
+    #[serde(skip)]
+    get_arrow_template: Option<String>,
+
+    /// The code template for setting a variable from an Apache Arrow IPC file (if supported)
+    #[serde(skip)]
+    set_arrow_template: Option<String>,
+
+    /// The sandbox that the kernel is run in (if any)
+    sandbox: Option<Sandbox>,
+
+    /// The temporary scratch directory of the sandbox (if any)
+    #[serde(skip)]
+    scratch: Option<TempDir>,
+
     /// The process id of the kernel
     pid: Option<u32>,
 
@@ -140,6 +165,12 @@ struct MicroKernelState {
     stdin: Option<Stdin>,
     stdout: Stdout,
     stderr: Stderr,
+
+    /// The sandbox that the kernel is run in (if any)
+    sandbox: Option<Sandbox>,
+
+    /// The process id of the kernel if it is a child process (i.e. not a fork)
+    child_pid: Option<u32>,
 }
 
 impl MicroKernelState {
@@ -159,12 +190,21 @@ impl MicroKernelState {
     }
 
     /// Receive outputs and messages from the microkernel
+    ///
+    /// If the kernel is sandboxed and it ended while performing the task (e.g. because
+    /// it exceeded a resource limit) then a `CodeError` describing why is added to the messages.
     async fn receive_result(&mut self) -> Result<(TaskOutputs, TaskMessages)> {
-        match (&mut self.stdout, &mut self.stderr) {
+        let (outputs, mut messages, ended) = match (&mut self.stdout, &mut self.stderr) {
             (Stdout::Child(stdout), Stderr::Child(stderr)) => receive_results(stdout, stderr).await,
             (Stdout::File(stdout), Stderr::File(stderr)) => receive_results(stdout, stderr).await,
             _ => unreachable!(),
+        }?;
+
+        if let (true, Some(sandbox)) = (ended, &self.sandbox) {
+            messages.push(sandbox.exit_error(self.child_pid).await);
         }
+
+        Ok((outputs, messages))
     }
 }
 
@@ -199,7 +239,11 @@ impl MicroKernel {
                 .collect(),
             set_template: set_template.into(),
             get_template: get_template.into(),
+            get_arrow_template: None,
+            set_arrow_template: None,
+            sandbox: None,
 
+            scratch: None,
             pid: None,
             forked_from: None,
             child: None,
@@ -207,6 +251,22 @@ impl MicroKernel {
             status: Arc::new(RwLock::new(KernelStatus::Pending)),
         }
     }
+
+    /// Set the sandbox that the kernel should be run in
+    ///
+    /// Must be called before the kernel is started to have any effect.
+    pub fn set_sandbox(&mut self, sandbox: Option<Sandbox>) {
+        self.sandbox = sandbox;
+    }
+
+    /// Set the code templates for transferring variables using Apache Arrow IPC files
+    ///
+    /// In both templates, `{{name}}` is replaced with the name of the variable and
+    /// `{{path}}` with the path of the file.
+    pub fn set_arrow_templates(&mut self, get_template: &str, set_template: &str) {
+        self.get_arrow_template = Some(get_template.into());
+        self.set_arrow_template = Some(set_template.into());
+    }
 }
 
 /// Include a file as a (name, content) tuple
@@ -235,6 +295,11 @@ impl MicroKernelSignaller {
         }
     }
 
+    /// Create a new signaller for a [`MicroKernel`], or a fork of one, using its process id
+    pub fn from_pid(pid: u32) -> Self {
+        Self { pid }
+    }
+
     /// Interrupt the [`MicroKernel`]
     pub fn interrupt(&self) {
         #[cfg(not(target_os = "windows"))]
@@ -339,7 +404,21 @@ impl KernelTrait for MicroKernel {
         // Start child process
         let (name, semver) = &self.runtime;
         let binary = binaries::installation(name, semver).await?;
-        let mut child = binary.interact(&args, directory)?;
+        let mut child = match &self.sandbox {
+            Some(sandbox) => {
+                tracing::debug!("Starting kernel `{}` in sandbox `{}`", self.name, sandbox);
+                let mut command = binary.command();
+                command
+                    .args(&args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .current_dir(directory);
+                self.scratch = sandbox.confine(&mut command, directory)?;
+                command.spawn()?
+            }
+            None => binary.interact(&args, directory)?,
+        };
 
         let stdin = child
             .stdin
@@ -369,6 +448,8 @@ impl KernelTrait for MicroKernel {
             stdin: Some(Stdin::Child(stdin)),
             stdout: Stdout::Child(stdout),
             stderr: Stderr::Child(stderr),
+            sandbox: self.sandbox.clone(),
+            child_pid: self.pid,
         })));
 
         *self.status.write().await = KernelStatus::Idle;
@@ -384,6 +465,7 @@ impl KernelTrait for MicroKernel {
             tracing::debug!("Killing kernel with pid `{:?}`", self.pid);
             child.kill().await?;
             self.child = None;
+            self.scratch = None;
         } else if let Some(..) = self.pid {
             // For forks
             MicroKernelSignaller::new(self)?.kill();
@@ -421,10 +503,19 @@ impl KernelTrait for MicroKernel {
     /// Set a symbol in the kernel
     async fn set(&mut self, name: &str, value: Node) -> Result<()> {
         let json = serde_json::to_string(&value)?;
-        let code = self
-            .set_template
-            .replace("{{name}}", name)
-            .replace("{{json}}", &json);
+        let code = if self.set_template.contains("{{json_escaped}}") {
+            let escaped = json
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "\\$");
+            self.set_template
+                .replace("{{name}}", name)
+                .replace("{{json_escaped}}", &escaped)
+        } else {
+            self.set_template
+                .replace("{{name}}", name)
+                .replace("{{json}}", &json)
+        };
 
         let (_outputs, messages) = self.state().await.send_receive(&[code.to_string()]).await?;
 
@@ -441,6 +532,58 @@ impl KernelTrait for MicroKernel {
         }
     }
 
+    /// Does the kernel support transferring variables using Apache Arrow?
+    async fn supports_arrow(&self) -> bool {
+        self.get_arrow_template.is_some() && self.set_arrow_template.is_some()
+    }
+
+    /// Write a variable in the kernel to an Apache Arrow IPC file
+    async fn get_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        let template = match &self.get_arrow_template {
+            Some(template) => template,
+            None => return Ok(false),
+        };
+        let code = template
+            .replace("{{name}}", name)
+            .replace("{{path}}", &path.to_string_lossy());
+
+        let (outputs, messages) = self.state().await.send_receive(&[code]).await?;
+
+        match outputs.first() {
+            Some(Node::Boolean(written)) => Ok(*written),
+            _ => {
+                let message = messages
+                    .first()
+                    .map(|message| message.error_message.clone())
+                    .unwrap_or_else(|| "Unexpected output".to_string());
+                bail!("Unable to write symbol `{}` to Arrow: {}", name, message)
+            }
+        }
+    }
+
+    /// Set a variable in the kernel from an Apache Arrow IPC file
+    async fn set_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        let template = match &self.set_arrow_template {
+            Some(template) => template,
+            None => return Ok(false),
+        };
+        let code = template
+            .replace("{{name}}", name)
+            .replace("{{path}}", &path.to_string_lossy());
+
+        let (_outputs, messages) = self.state().await.send_receive(&[code]).await?;
+
+        if messages.is_empty() {
+            Ok(true)
+        } else {
+            let message = messages
+                .first()
+                .map(|message| message.error_message.clone())
+                .unwrap_or_else(|| "Unknown error".to_string());
+            bail!("Unable to set symbol `{}` from Arrow: {}", name, message)
+        }
+    }
+
     /// Execute code in the kernel synchronously
     async fn exec_sync(&mut self, code: &str, _tags: Option<&TagMap>) -> Result<Task> {
         let mut task = Task::begin_sync();
@@ -526,11 +669,12 @@ impl KernelTrait for MicroKernel {
         // Setup channels and execution task
         let (sender, _receiver) = broadcast::channel(1);
         let (interrupt_sender, mut interrupt_receiver) = mpsc::channel(1);
-        let task = Task::begin(Some(sender.clone()), Some(interrupt_sender));
+        let mut task = Task::begin(Some(sender.clone()), Some(interrupt_sender));
 
         // Start the fork and create signaller for it
         let mut fork = self.create_fork(code).await?;
         let signaller = MicroKernelSignaller::new(&fork)?;
+        task.fork_pid = fork.pid;
 
         // Start async task to wait for result and send to receivers
         let task_id = task.id.clone();
@@ -645,8 +789,13 @@ impl MicroKernel {
             // Small properties that may be needed for fork to get symbols
             set_template: self.set_template.clone(),
             get_template: self.get_template.clone(),
+            get_arrow_template: self.get_arrow_template.clone(),
+            set_arrow_template: self.set_arrow_template.clone(),
+            // Forks run in the same sandbox as the parent
+            sandbox: self.sandbox.clone(),
 
             // Runtime properties..
+            scratch: None,
             pid: Some(fork_pid),
             forked_from: self.pid,
             child: None,
@@ -654,6 +803,8 @@ impl MicroKernel {
                 stdin: None,
                 stdout: Stdout::File(BufReader::new(fork_stdout)),
                 stderr: Stderr::File(BufReader::new(fork_stderr)),
+                sandbox: self.sandbox.clone(),
+                child_pid: None,
             }))),
             status: Arc::new(RwLock::new(KernelStatus::Busy)),
         })
@@ -684,7 +835,7 @@ async fn startup_warnings<R1: AsyncBufRead + Unpin, R2: AsyncBufRead + Unpin>(
     stderr: &mut R2,
 ) {
     match receive_results(stdout, stderr).await {
-        Ok((.., messages)) => {
+        Ok((_outputs, messages, ..)) => {
             if !messages.is_empty() {
                 let messages = messages
                     .into_iter()
@@ -705,18 +856,25 @@ async fn startup_warnings<R1: AsyncBufRead + Unpin, R2: AsyncBufRead + Unpin>(
 }
 
 /// Receive results (outputs on stdout and messages on stderr) from a kernel
+///
+/// Also returns whether the kernel's stdout ended (i.e. the kernel exited) before
+/// the end of the result.
 async fn receive_results<R1: AsyncBufRead + Unpin, R2: AsyncBufRead + Unpin>(
     stdout: &mut R1,
     stderr: &mut R2,
-) -> Result<(Vec<Node>, Vec<CodeError>)> {
+) -> Result<(Vec<Node>, Vec<CodeError>, bool)> {
     // Capture outputs separating them as we go
     let mut output = String::new();
     let mut outputs = Vec::new();
+    let mut ended = false;
     let mut lines = stdout.lines();
     loop {
         let line = match lines.next_line().await {
             Ok(Some(line)) => line.to_string(),
-            Ok(None) => break,
+            Ok(None) => {
+                ended = true;
+                break;
+            }
             Err(error) => {
                 bail!("When receiving outputs from kernel: {}", error)
             }
@@ -778,7 +936,7 @@ async fn receive_results<R1: AsyncBufRead + Unpin, R2: AsyncBufRead + Unpin>(
         })
         .collect();
 
-    Ok((outputs, messages))
+    Ok((outputs, messages, ended))
 }
 
 /// Handle a line of stdout or stderr
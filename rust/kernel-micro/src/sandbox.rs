@@ -0,0 +1,654 @@
+//! Sandboxed execution of microkernels
+//!
+//! When executing documents from untrusted sources it is desirable to limit what the
+//! code in them can do. A [`Sandbox`] confines a microkernel process using Linux
+//! namespaces (a read-only view of the file system except for a scratch directory, no
+//! network), `seccomp` (so that the confinement can not be undone from within the kernel),
+//! and resource limits (CPU time and memory).
+
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use kernel::{
+    common::{
+        eyre::{bail, Result},
+        serde::Serialize,
+        tempfile::TempDir,
+        tokio::process::Command,
+    },
+    stencila_schema::CodeError,
+};
+
+/// The sandbox that a microkernel should be run in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(crate = "kernel::common::serde")]
+pub struct Sandbox {
+    /// The maximum CPU time, in seconds, that the kernel may use
+    pub cpu: Option<u64>,
+
+    /// The maximum memory, in megabytes, that the kernel may use
+    ///
+    /// Limits the data segment (the heap and private mappings) rather than the address space
+    /// so that runtimes which reserve large amounts of virtual memory up front (e.g. V8, BLAS
+    /// libraries) can still be run.
+    pub memory: Option<u64>,
+
+    /// Whether the kernel has access to the network
+    pub network: bool,
+
+    /// A directory, relative to the project directory, that the kernel may write to
+    ///
+    /// The rest of the file system, including the project, is read-only. If not specified, a temporary directory
+    /// is created for the kernel and made available to it via the `TMPDIR` environment variable.
+    pub scratch: Option<PathBuf>,
+}
+
+impl fmt::Display for Sandbox {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut items = Vec::new();
+        if let Some(cpu) = self.cpu {
+            items.push(format!("cpu={}", cpu));
+        }
+        if let Some(memory) = self.memory {
+            items.push(format!("memory={}", memory));
+        }
+        if self.network {
+            items.push("network=on".to_string());
+        }
+        if let Some(scratch) = &self.scratch {
+            items.push(format!("scratch={}", scratch.display()));
+        }
+        if items.is_empty() {
+            items.push("on".to_string());
+        }
+        write!(formatter, "{}", items.join(","))
+    }
+}
+
+impl Sandbox {
+    /// Parse a sandbox specification
+    ///
+    /// The specification is a comma or space separated list of options e.g.
+    /// `cpu=10,memory=512,network=off,scratch=tmp`. An empty specification (or `on`)
+    /// results in the default sandbox: no resource limits, no network, and a read-only file system.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut sandbox = Self::default();
+        for item in spec.split(|chr: char| chr == ',' || chr.is_whitespace()) {
+            if item.is_empty() || item == "on" || item == "true" {
+                continue;
+            }
+
+            let (name, value) = item.split_once('=').unwrap_or((item, ""));
+            match name {
+                "cpu" => sandbox.cpu = Some(parse_limit(name, value)?),
+                "memory" | "mem" => sandbox.memory = Some(parse_limit(name, value)?),
+                "network" | "net" => {
+                    sandbox.network = match value {
+                        "" | "on" | "true" | "yes" => true,
+                        "off" | "false" | "no" => false,
+                        _ => bail!("Invalid value for sandbox option `network`: {}", value),
+                    }
+                }
+                "scratch" => {
+                    let scratch = PathBuf::from(value);
+                    if value.is_empty()
+                        || !scratch
+                            .components()
+                            .all(|component| matches!(component, Component::Normal(..)))
+                    {
+                        bail!("The sandbox `scratch` option must be a path within the project")
+                    }
+                    sandbox.scratch = Some(scratch)
+                }
+                _ => bail!("Unknown sandbox option `{}`", name),
+            }
+        }
+        Ok(sandbox)
+    }
+
+    /// Confine the process that will be spawned by a command to the sandbox
+    ///
+    /// Returns the temporary scratch directory (if one was created) which should be
+    /// kept for as long as the kernel is running.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn confine(
+        &self,
+        command: &mut Command,
+        directory: &Path,
+    ) -> Result<Option<TempDir>> {
+        use kernel::common::{tempfile::tempdir, tracing};
+        use nix::unistd::{getgid, getuid};
+        use std::fs;
+
+        let project = directory.canonicalize()?;
+
+        let (scratch, temp) = match &self.scratch {
+            Some(scratch) => {
+                // Symlinks are not followed so that the scratch directory can not be used to
+                // make a directory outside of the project writable
+                let mut path = project.clone();
+                for component in scratch.components() {
+                    path.push(component);
+                    if path.is_symlink() {
+                        bail!(
+                            "The sandbox `scratch` directory must not be, or be within, a symlink: {}",
+                            path.display()
+                        )
+                    }
+                }
+                fs::create_dir_all(&path)?;
+
+                let scratch = path.canonicalize()?;
+                if !scratch.starts_with(&project) {
+                    bail!(
+                        "The sandbox `scratch` directory must be within the project: {}",
+                        scratch.display()
+                    )
+                }
+                (scratch, None)
+            }
+            None => {
+                let temp = tempdir()?;
+                (temp.path().to_path_buf(), Some(temp))
+            }
+        };
+        command.env("TMPDIR", &scratch);
+
+        let mounts = writable_mounts(&scratch)?;
+
+        // Everything that requires allocation is done here, before the process is forked
+        let confinement = Confinement {
+            cpu: self.cpu,
+            memory: self.memory.map(|memory| memory * 1024 * 1024),
+            network: self.network,
+            uid_map: format!("{} {} 1", getuid(), getuid()),
+            gid_map: format!("{} {} 1", getgid(), getgid()),
+            scratch,
+            mounts,
+            filter: seccomp::filter(),
+        };
+        if confinement.filter.is_none() {
+            tracing::warn!(
+                "Seccomp filtering of sandboxed kernels is not supported on this architecture"
+            );
+        }
+
+        // Safety: the closure runs in the forked child process, before `exec`, and only makes
+        // system calls and writes to pre-allocated buffers.
+        unsafe {
+            command.pre_exec(move || confinement.enter());
+        }
+
+        Ok(temp)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn confine(
+        &self,
+        _command: &mut Command,
+        _directory: &Path,
+    ) -> Result<Option<TempDir>> {
+        bail!("Sandboxed execution of kernels is only supported on Linux")
+    }
+
+    /// Create a `CodeError` for when a sandboxed kernel ended while executing a task
+    ///
+    /// If the process id of the kernel is known (i.e. it is not a fork) then its exit signal
+    /// is used to determine whether it exceeded the CPU time or memory limit.
+    pub(crate) async fn exit_error(&self, pid: Option<u32>) -> CodeError {
+        #[cfg(target_os = "linux")]
+        let signal = match pid {
+            Some(pid) => exit_signal(pid).await,
+            None => None,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let signal: Option<i32> = {
+            let _pid = pid;
+            None
+        };
+
+        let error_message = match (signal, self.cpu, self.memory) {
+            (Some(nix::libc::SIGXCPU), Some(cpu), ..) | (Some(nix::libc::SIGKILL), Some(cpu), None) => {
+                format!("Kernel exceeded the sandbox CPU time limit of {} seconds", cpu)
+            }
+            (.., Some(memory)) => format!(
+                "Kernel was terminated, possibly because it exceeded the sandbox memory limit of {} MB",
+                memory
+            ),
+            _ => "Kernel ended unexpectedly while running in a sandbox".to_string(),
+        };
+
+        CodeError {
+            error_message,
+            error_type: Some(Box::new("SandboxError".to_string())),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse the value of a sandbox resource limit
+fn parse_limit(name: &str, value: &str) -> Result<u64> {
+    match value.parse::<u64>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => bail!("Invalid value for sandbox option `{}`: {}", name, value),
+    }
+}
+
+/// Get the signal that terminated a child process (if any)
+///
+/// Waits a short time for the process to exit (its output streams may be closed slightly
+/// before it does) but does not reap it, so that it can still be waited on by its owner.
+#[cfg(target_os = "linux")]
+async fn exit_signal(pid: u32) -> Option<i32> {
+    use kernel::common::tokio::time::{sleep, Duration};
+
+    for _attempt in 0..20 {
+        if let Some(signal) = exited(pid) {
+            return signal;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    None
+}
+
+/// Check whether a child process has exited, without reaping it
+///
+/// Returns `None` if it is still running, otherwise the signal that terminated it (if any).
+#[cfg(target_os = "linux")]
+fn exited(pid: u32) -> Option<Option<i32>> {
+    use nix::libc;
+
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let result = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    if result != 0 {
+        return Some(None);
+    }
+    if unsafe { info.si_pid() } == 0 {
+        return None;
+    }
+    match info.si_code {
+        libc::CLD_KILLED | libc::CLD_DUMPED => Some(Some(unsafe { info.si_status() })),
+        _ => Some(None),
+    }
+}
+
+/// File system types which are not remounted as read-only in the sandbox
+///
+/// These are virtual file systems which either can not be remounted from within a user
+/// namespace or are already read-only to an unprivileged user.
+#[cfg(target_os = "linux")]
+const VIRTUAL_FS: &[&str] = &[
+    "proc",
+    "sysfs",
+    "cgroup",
+    "cgroup2",
+    "devpts",
+    "mqueue",
+    "securityfs",
+    "debugfs",
+    "tracefs",
+    "pstore",
+    "bpf",
+    "fusectl",
+    "configfs",
+    "binfmt_misc",
+    "autofs",
+];
+
+/// Get the mounts which need to be remounted as read-only within the sandbox
+///
+/// Returns the mount point of each writable mount (other than virtual file systems and
+/// the scratch directory), along with the flags of the mount which are "locked" in a user
+/// namespace and so must be retained when remounting it. Mounts which can not be accessed
+/// are skipped because they are also inaccessible to the sandboxed process.
+#[cfg(target_os = "linux")]
+fn writable_mounts(scratch: &Path) -> Result<Vec<(PathBuf, nix::mount::MsFlags)>> {
+    use nix::{
+        mount::MsFlags,
+        sys::statvfs::{statvfs, FsFlags},
+    };
+    use std::fs;
+
+    let mut mounts: Vec<(PathBuf, MsFlags)> = Vec::new();
+    for line in fs::read_to_string("/proc/self/mountinfo")?.lines() {
+        // See `man proc` for the format of each line
+        let (fields, rest) = line.split_once(" - ").unwrap_or((line, ""));
+        let mount_point = match fields.split(' ').nth(4) {
+            Some(mount_point) => PathBuf::from(unescape_mount_point(mount_point)),
+            None => continue,
+        };
+        let fs_type = rest.split(' ').next().unwrap_or_default();
+        if VIRTUAL_FS.contains(&fs_type)
+            || mount_point.starts_with("/proc")
+            || mount_point.starts_with("/sys")
+            || mount_point == scratch
+        {
+            continue;
+        }
+
+        let existing = match statvfs(&mount_point) {
+            Ok(stat) => stat.flags(),
+            Err(..) => continue,
+        };
+        if existing.contains(FsFlags::ST_RDONLY) {
+            continue;
+        }
+
+        let mut locked = MsFlags::empty();
+        for (fs_flag, ms_flag) in [
+            (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+            (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+            (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+            (FsFlags::ST_NOATIME, MsFlags::MS_NOATIME),
+            (FsFlags::ST_NODIRATIME, MsFlags::MS_NODIRATIME),
+            (FsFlags::ST_RELATIME, MsFlags::MS_RELATIME),
+        ] {
+            if existing.contains(fs_flag) {
+                locked |= ms_flag;
+            }
+        }
+
+        if !mounts.iter().any(|(path, ..)| path == &mount_point) {
+            mounts.push((mount_point, locked));
+        }
+    }
+    Ok(mounts)
+}
+
+/// Unescape the octal escapes (e.g. `\040` for a space) used in mount points in `mountinfo`
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(mount_point: &str) -> String {
+    let bytes = mount_point.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' && index + 3 < bytes.len() {
+            let octal = std::str::from_utf8(&bytes[index + 1..index + 4]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(octal, 8) {
+                unescaped.push(byte);
+                index += 4;
+                continue;
+            }
+        }
+        unescaped.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+/// The confinement of a sandboxed process
+///
+/// Entered in the child process after it is forked but before the kernel's runtime is executed.
+#[cfg(target_os = "linux")]
+struct Confinement {
+    cpu: Option<u64>,
+    memory: Option<u64>,
+    network: bool,
+    uid_map: String,
+    gid_map: String,
+    scratch: PathBuf,
+    mounts: Vec<(PathBuf, nix::mount::MsFlags)>,
+    filter: Option<Vec<nix::libc::sock_filter>>,
+}
+
+#[cfg(target_os = "linux")]
+impl Confinement {
+    fn enter(&self) -> std::io::Result<()> {
+        use nix::{
+            libc,
+            mount::{mount, MsFlags},
+            sched::{unshare, CloneFlags},
+        };
+        use std::{fs, io::Error};
+
+        const NONE: Option<&str> = None;
+
+        // Resource limits. The hard CPU limit is one second more than the soft limit
+        // so that the kernel receives `SIGXCPU` before it is killed.
+        if let Some(cpu) = self.cpu {
+            let limit = libc::rlimit {
+                rlim_cur: cpu as libc::rlim_t,
+                rlim_max: (cpu + 1) as libc::rlim_t,
+            };
+            if unsafe { libc::setrlimit(libc::RLIMIT_CPU, &limit) } != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+        if let Some(memory) = self.memory {
+            let limit = libc::rlimit {
+                rlim_cur: memory as libc::rlim_t,
+                rlim_max: memory as libc::rlim_t,
+            };
+            if unsafe { libc::setrlimit(libc::RLIMIT_DATA, &limit) } != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        // New user and mount namespaces (and network namespace, with only an unconfigured
+        // loopback interface, if network access is not allowed)
+        let mut flags = CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS;
+        if !self.network {
+            flags |= CloneFlags::CLONE_NEWNET;
+        }
+        unshare(flags)?;
+        fs::write("/proc/self/setgroups", "deny")?;
+        fs::write("/proc/self/uid_map", &self.uid_map)?;
+        fs::write("/proc/self/gid_map", &self.gid_map)?;
+
+        // Make the file system read-only, except for the scratch directory, without
+        // propagating any of these mounts back to the parent namespace. The scratch directory
+        // is bind mounted onto itself first so that it is not affected by the remounts.
+        mount(NONE, "/", NONE, MsFlags::MS_REC | MsFlags::MS_PRIVATE, NONE)?;
+        mount(
+            Some(&self.scratch),
+            &self.scratch,
+            NONE,
+            MsFlags::MS_BIND | MsFlags::MS_REC,
+            NONE,
+        )?;
+        for (mount_point, locked) in &self.mounts {
+            mount(
+                NONE,
+                mount_point,
+                NONE,
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | *locked,
+                NONE,
+            )?;
+        }
+
+        // Prevent the above from being undone (e.g. by unmounting the read-only project)
+        if unsafe {
+            libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            )
+        } != 0
+        {
+            return Err(Error::last_os_error());
+        }
+        if let Some(filter) = &self.filter {
+            let program = libc::sock_fprog {
+                len: filter.len() as libc::c_ushort,
+                filter: filter.as_ptr() as *mut libc::sock_filter,
+            };
+            if unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                    &program as *const libc::sock_fprog,
+                )
+            } != 0
+            {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Construction of the `seccomp` filter for sandboxed kernels
+#[cfg(target_os = "linux")]
+mod seccomp {
+    use nix::libc::{self, sock_filter};
+
+    // Berkeley Packet Filter instruction classes and fields
+    const BPF_LD: u16 = 0x00;
+    const BPF_JMP: u16 = 0x05;
+    const BPF_RET: u16 = 0x06;
+    const BPF_W: u16 = 0x00;
+    const BPF_ABS: u16 = 0x20;
+    const BPF_JEQ: u16 = 0x10;
+    #[cfg(target_arch = "x86_64")]
+    const BPF_JGE: u16 = 0x30;
+    const BPF_K: u16 = 0x00;
+
+    // Seccomp return actions
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    // Offsets of fields within `struct seccomp_data`
+    const OFFSET_NR: u32 = 0;
+    const OFFSET_ARCH: u32 = 4;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// System calls that are denied
+    ///
+    /// The newer mount API calls are not defined for all versions of `libc` but have the
+    /// same numbers on all architectures.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED: &[libc::c_long] = &[
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_ptrace,
+        428, // open_tree
+        429, // move_mount
+        430, // fsopen
+        432, // fsmount
+        433, // fspick
+        442, // mount_setattr
+    ];
+
+    fn statement(code: u16, k: u32) -> sock_filter {
+        sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter { code, jt, jf, k }
+    }
+
+    /// Create a filter which denies system calls that could be used to escape the sandbox
+    ///
+    /// Returns `None` if seccomp filtering is not supported for the current architecture.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn filter() -> Option<Vec<sock_filter>> {
+        let deny = statement(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | libc::EPERM as u32);
+
+        let mut filter = vec![
+            // Kill the process if the architecture is not the expected one
+            statement(BPF_LD | BPF_W | BPF_ABS, OFFSET_ARCH),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            statement(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD | BPF_W | BPF_ABS, OFFSET_NR),
+        ];
+
+        // Deny x32 system calls which would otherwise bypass the filter
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([jump(BPF_JMP | BPF_JGE | BPF_K, 0x4000_0000, 0, 1), deny]);
+
+        for syscall in DENIED {
+            filter.extend([jump(BPF_JMP | BPF_JEQ | BPF_K, *syscall as u32, 0, 1), deny]);
+        }
+        filter.push(statement(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+
+        Some(filter)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn filter() -> Option<Vec<sock_filter>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() -> Result<()> {
+        assert_eq!(Sandbox::parse("")?, Sandbox::default());
+        assert_eq!(Sandbox::parse("on")?, Sandbox::default());
+
+        let sandbox = Sandbox::parse("cpu=10,memory=512 network=on scratch=tmp")?;
+        assert_eq!(sandbox.cpu, Some(10));
+        assert_eq!(sandbox.memory, Some(512));
+        assert!(sandbox.network);
+        assert_eq!(sandbox.scratch, Some(PathBuf::from("tmp")));
+        assert_eq!(
+            sandbox.to_string(),
+            "cpu=10,memory=512,network=on,scratch=tmp"
+        );
+
+        assert!(Sandbox::parse("cpu=0").is_err());
+        assert!(Sandbox::parse("memory=lots").is_err());
+        assert!(Sandbox::parse("scratch=../outside").is_err());
+        assert!(Sandbox::parse("scratch=tmp/../../outside").is_err());
+        assert!(Sandbox::parse("scratch=/tmp").is_err());
+        assert!(Sandbox::parse("scratch=./tmp").is_err());
+        assert!(Sandbox::parse("foo=bar").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn confine_scratch() -> Result<()> {
+        use kernel::common::tempfile::tempdir;
+
+        let project = tempdir()?;
+        let outside = tempdir()?;
+
+        let sandbox = Sandbox::parse("scratch=tmp/scratch")?;
+        sandbox.confine(&mut Command::new("true"), project.path())?;
+        assert!(project.path().join("tmp").join("scratch").is_dir());
+
+        // A scratch directory that is a symlink to, or is within a symlink to, a
+        // directory outside of the project is rejected
+        std::os::unix::fs::symlink(outside.path(), project.path().join("link"))?;
+        for spec in ["scratch=link", "scratch=link/scratch"] {
+            let sandbox = Sandbox::parse(spec)?;
+            assert!(sandbox
+                .confine(&mut Command::new("true"), project.path())
+                .is_err());
+        }
+        assert!(!outside.path().join("scratch").exists());
+
+        Ok(())
+    }
+}
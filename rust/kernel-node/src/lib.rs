@@ -2,7 +2,7 @@ use kernel_micro::{include_file, MicroKernel};
 
 /// A microkernel for Node
 pub fn new() -> MicroKernel {
-    MicroKernel::new(
+    let mut kernel = MicroKernel::new(
         "node-micro",
         &["javascript"],
         true,
@@ -14,7 +14,12 @@ pub fn new() -> MicroKernel {
         &[include_file!("node-codec.js")],
         "{{name}} = decodeValue({{json}})",
         "{{name}}",
-    )
+    );
+    kernel.set_arrow_templates(
+        "writeArrow({{name}}, String.raw`{{path}}`)",
+        "{{name}} = readArrow(String.raw`{{path}}`)",
+    );
+    kernel
 }
 
 #[cfg(test)]
@@ -25,7 +30,10 @@ mod tests {
         stencila_schema::Node,
         KernelTrait,
     };
-    use test_utils::{assert_json_eq, common::serde_json::json};
+    use test_utils::{
+        assert_json_eq,
+        common::serde_json::{self, json},
+    };
 
     /// Tests of basic functionality
     /// This test is replicated in all the microkernels.
@@ -168,6 +176,34 @@ console.error("Error message")
         Ok(())
     }
 
+    /// Test that a `Datatable` is set as an array of row objects, as when it is
+    /// set from an Arrow IPC file
+    #[tokio::test]
+    async fn set_datatable() -> Result<()> {
+        let mut kernel = new();
+        match kernel.is_available().await {
+            true => kernel.start_here().await?,
+            false => return Ok(()),
+        }
+
+        let datatable = serde_json::from_value(json!({
+            "type": "Datatable",
+            "columns": [
+                {"type": "DatatableColumn", "name": "a", "values": [1, 2]},
+                {"type": "DatatableColumn", "name": "b", "values": [{"type": "Date", "value": "2020-01-01"}, null]}
+            ]
+        }))?;
+        kernel.set("dt", datatable).await?;
+
+        let (outputs, messages) = kernel
+            .exec("[dt.length, dt[0].a, dt[0].b.toISOString(), dt[1].b]", None)
+            .await?;
+        assert_json_eq!(messages, json!([]));
+        assert_json_eq!(outputs, json!([[2, 1, "2020-01-01T00:00:00.000Z", null]]));
+
+        Ok(())
+    }
+
     /// Test re-declarations of variables
     #[tokio::test]
     async fn redeclarations() -> Result<()> {
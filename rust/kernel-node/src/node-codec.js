@@ -1,11 +1,100 @@
+const fs = require('fs')
+
+// Decode a value received from another kernel
+//
+// A `Datatable` is decoded to an array of row objects so that tables are the
+// same type whether they are mirrored using JSON or Arrow (see `readArrow`).
 function decodeValue(value) {
+  if (value !== null && typeof value === 'object' && value.type === 'Datatable') {
+    return datatableToRows(value)
+  }
   return value
 }
 
+// Convert a `Datatable` to an array of row objects
+//
+// `Date` nodes are converted to JavaScript `Date`s.
+function datatableToRows(datatable) {
+  const columns = datatable.columns ?? []
+  const rows = columns.reduce((max, column) => Math.max(max, column.values?.length ?? 0), 0)
+  return Array.from({ length: rows }, (_, row) =>
+    Object.fromEntries(
+      columns.map((column) => {
+        const value = column.values?.[row] ?? null
+        return [
+          column.name,
+          value !== null && typeof value === 'object' && value.type === 'Date'
+            ? new Date(value.value)
+            : value,
+        ]
+      })
+    )
+  )
+}
+
 function encodeValue(value) {
   return JSON.stringify(value)
 }
 
+// Get the `apache-arrow` module, resolved from the current working directory
+// (usually the project) because that is where it is likely to be installed
+function arrowModule() {
+  return require(require.resolve('apache-arrow', { paths: [process.cwd()] }))
+}
+
+// Write a value to an Apache Arrow IPC file
+//
+// Used for fast transfer of tables between kernels. Returns `false` if the value
+// is not an Arrow `Table` or an array of objects, or if `apache-arrow` is not installed.
+function writeArrow(value, path) {
+  let arrow
+  try {
+    arrow = arrowModule()
+  } catch (error) {
+    return false
+  }
+
+  let table
+  if (value instanceof arrow.Table) {
+    table = value
+  } else if (
+    Array.isArray(value) &&
+    value.length > 0 &&
+    value.every((item) => item !== null && typeof item === 'object' && !Array.isArray(item))
+  ) {
+    table = arrow.tableFromJSON(value)
+  } else {
+    return false
+  }
+
+  fs.writeFileSync(path, arrow.tableToIPC(table, 'file'))
+  return true
+}
+
+// Read an Apache Arrow IPC file into an array of row objects
+//
+// As for `decodeValue`, so that mirrored tables are the same type regardless of
+// how they were transferred: 64-bit integers (which Arrow reads as `BigInt`s) are
+// converted to numbers, and dates and timestamps to JavaScript `Date`s.
+function readArrow(path) {
+  const arrow = arrowModule()
+  const table = arrow.tableFromIPC(fs.readFileSync(path))
+  const columns = table.schema.fields.map((field, index) => {
+    const vector = table.getChildAt(index)
+    const isDate = arrow.DataType.isDate(field.type) || arrow.DataType.isTimestamp(field.type)
+    const convert = (value) => {
+      if (value === null || value === undefined) return null
+      if (isDate) return value instanceof Date ? value : new Date(Number(value))
+      if (typeof value === 'bigint') return Number(value)
+      return value
+    }
+    return [field.name, vector, convert]
+  })
+  return Array.from({ length: table.numRows }, (_, row) =>
+    Object.fromEntries(columns.map(([name, vector, convert]) => [name, convert(vector.get(row))]))
+  )
+}
+
 function encodeError(error) {
   const codeError = { type: 'CodeError' }
   if (error.name) codeError.errorType = error.name
@@ -19,4 +108,6 @@ module.exports = {
   decodeValue,
   encodeValue,
   encodeError,
+  writeArrow,
+  readArrow,
 }
@@ -3,7 +3,13 @@
 const readline = require('readline')
 const vm = require('vm')
 
-const { decodeValue, encodeValue, encodeError } = require('./node-codec')
+const {
+  decodeValue,
+  encodeValue,
+  encodeError,
+  writeArrow,
+  readArrow,
+} = require('./node-codec')
 
 const READY = '\u{10ACDC}\n'
 const RESULT = '\u{10CB40}\n'
@@ -49,6 +55,8 @@ const context = {
   decodeValue,
   encodeValue,
   encodeError,
+  writeArrow,
+  readArrow,
 }
 vm.createContext(context)
 
@@ -2,7 +2,7 @@ use kernel_micro::{include_file, MicroKernel};
 
 /// A microkernel for Python
 pub fn new() -> MicroKernel {
-    MicroKernel::new(
+    let mut kernel = MicroKernel::new(
         "python-micro",
         &["python"],
         true,
@@ -14,7 +14,14 @@ pub fn new() -> MicroKernel {
         &[include_file!("python_codec.py")],
         "{{name}} = __decode_value__(r'''{{json}}''')",
         "{{name}}",
-    )
+    );
+    // The codec module is imported, rather than added to the execution context,
+    // so that the user's namespace is not polluted
+    kernel.set_arrow_templates(
+        "__import__('python_codec').write_arrow({{name}}, r'''{{path}}''')",
+        "{{name}} = __import__('python_codec').read_arrow(r'''{{path}}''')",
+    );
+    kernel
 }
 
 #[cfg(test)]
@@ -105,6 +105,39 @@ def convert_dataframe(df):
     return dict(type="Datatable", columns=columns)
 
 
+def write_arrow(value, path):
+    """
+    Write a value to an Apache Arrow IPC file
+
+    Used for fast transfer of tables between kernels. Returns `False` if the value
+    is not a Pandas `DataFrame` or Arrow `Table`, or if `pyarrow` is not installed.
+    """
+    try:
+        import pyarrow
+    except ImportError:
+        return False
+
+    if isinstance(value, DataFrame):
+        table = pyarrow.Table.from_pandas(value, preserve_index=False)
+    elif isinstance(value, pyarrow.Table):
+        table = value
+    else:
+        return False
+
+    with pyarrow.OSFile(path, "wb") as sink:
+        with pyarrow.ipc.new_file(sink, table.schema) as writer:
+            writer.write_table(table)
+    return True
+
+
+def read_arrow(path):
+    """Read an Apache Arrow IPC file into a Pandas `DataFrame`"""
+    import pyarrow
+
+    with pyarrow.memory_map(path, "r") as source:
+        return pyarrow.ipc.open_file(source).read_pandas()
+
+
 def is_matplotlib(value):
     """Is the value a matplotlib value or return of a matplotlib call?"""
     from matplotlib.artist import Artist
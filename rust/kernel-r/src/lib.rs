@@ -4,7 +4,7 @@ use kernel_micro::{include_file, MicroKernel};
 
 /// A microkernel for R
 pub fn new() -> MicroKernel {
-    MicroKernel::new(
+    let mut kernel = MicroKernel::new(
         "r-micro",
         &["r"],
         true,
@@ -16,7 +16,12 @@ pub fn new() -> MicroKernel {
         &[include_file!("r-codec.r")],
         "{{name}} <- decode_value(r\"({{json}})\")",
         "cat(encode_value({{name}}, unbox = TRUE))",
-    )
+    );
+    kernel.set_arrow_templates(
+        "cat(encode_value(write_arrow({{name}}, r\"({{path}})\"), unbox = TRUE))",
+        "{{name}} <- read_arrow(r\"({{path}})\")",
+    );
+    kernel
 }
 
 /// Check to see if the installation of R allows for forking of microkernel
@@ -121,6 +121,23 @@ convert_data_frame_column <- function(name, object) {
   )
 }
 
+# Write a value to an Apache Arrow IPC file
+#
+# Used for fast transfer of tables between kernels. Returns `FALSE` if the value
+# is not a `data.frame` or if the `arrow` package is not installed.
+write_arrow <- function(value, path) {
+  if (!is.data.frame(value) || !requireNamespace("arrow", quietly = TRUE)) {
+    return(FALSE)
+  }
+  arrow::write_ipc_file(value, path)
+  TRUE
+}
+
+# Read an Apache Arrow IPC file into a `data.frame`
+read_arrow <- function(path) {
+  as.data.frame(arrow::read_ipc_file(path))
+}
+
 # Encode a message to JSON
 encode_message <- function(message, type) {
   escaped <- gsub('\\"', '\\\\"', message)
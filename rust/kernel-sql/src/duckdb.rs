@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use ::duckdb::{
+    appender_params_from_iter,
+    arrow::{
+        array::{
+            Array, ArrayData, ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array,
+            StringArray, Time64MicrosecondArray, TimestampMicrosecondArray,
+        },
+        compute::cast,
+        datatypes::{DataType, TimeUnit as ArrowTimeUnit},
+        ipc::{reader::FileReader, writer::FileWriter},
+    },
+    params_from_iter,
+    types::{TimeUnit, Value},
+    Connection,
+};
+
+use kernel::{
+    common::{
+        chrono::{Duration, NaiveDate, NaiveDateTime},
+        eyre::{eyre, Result},
+        itertools::Itertools,
+        regex::Captures,
+        serde_json,
+        tokio::task,
+        tracing,
+    },
+    stencila_schema::{
+        ArrayValidator, BooleanValidator, Datatable, DatatableColumn, Date, IntegerValidator, Node,
+        Null, Number, NumberValidator, StringValidator, ValidatorTypes,
+    },
+};
+
+use crate::{datatable_rows, ColumnType, BINDING_REGEX};
+
+/// A connection to a DuckDB database
+///
+/// DuckDB is an embedded database with a synchronous API so, rather than a pool of
+/// connections, a single connection is shared (behind a mutex) and all calls to it
+/// are made on a blocking thread.
+pub type DuckPool = Arc<Mutex<Connection>>;
+
+/// Open a connection to a DuckDB database
+///
+/// The `spec` is the part of the URL after `duckdb://` and is either `:memory:` (for an
+/// in-memory database) or the path to a database file. The `directory` is added to
+/// DuckDB's file search path so that Parquet and CSV files can be queried using paths
+/// relative to the document e.g. `SELECT * FROM 'data/table.parquet'`.
+pub async fn connect(spec: &str, directory: Option<&Path>) -> Result<DuckPool> {
+    let spec = spec.to_string();
+    let directory = directory.map(|directory| directory.to_string_lossy().to_string());
+    task::spawn_blocking(move || -> Result<DuckPool> {
+        let connection = if spec.is_empty() || spec == ":memory:" {
+            Connection::open_in_memory()?
+        } else {
+            Connection::open(&spec)?
+        };
+
+        if let Some(directory) = directory {
+            if let Err(error) = connection.execute_batch(&format!(
+                "SET file_search_path = '{}'",
+                directory.replace('\'', "''")
+            )) {
+                tracing::debug!("While setting DuckDB file search path: {}", error)
+            }
+        }
+
+        Ok(Arc::new(Mutex::new(connection)))
+    })
+    .await?
+}
+
+/// Bind parameters to an SQL statement based on name
+fn bind(sql: &str, parameters: &HashMap<String, Node>) -> (String, Vec<Value>) {
+    let mut arguments = Vec::new();
+    let sql = BINDING_REGEX.replace_all(sql, |captures: &Captures| {
+        let name = captures[1].to_string();
+        let value = parameters.get(&name).unwrap();
+        arguments.push(node_to_value(value));
+        "?"
+    });
+    (sql.to_string(), arguments)
+}
+
+/// Convert a Stencila [`Node`] into a DuckDB [`Value`]
+///
+/// Nodes other than primitives are converted to JSON strings.
+fn node_to_value(node: &Node) -> Value {
+    match node {
+        Node::Null(..) => Value::Null,
+        Node::Boolean(value) => Value::Boolean(*value),
+        Node::Integer(value) => Value::BigInt(*value),
+        Node::Number(value) => Value::Double(value.0),
+        Node::String(value) => Value::Text(value.clone()),
+        _ => Value::Text(serde_json::to_string(node).unwrap_or_default()),
+    }
+}
+
+/// Convert a DuckDB [`Value`] into a Stencila [`Node`]
+fn value_to_node(value: Value) -> Node {
+    match value {
+        Value::Null => Node::Null(Null {}),
+        Value::Boolean(value) => Node::Boolean(value),
+        Value::TinyInt(value) => Node::Integer(value as i64),
+        Value::SmallInt(value) => Node::Integer(value as i64),
+        Value::Int(value) => Node::Integer(value as i64),
+        Value::BigInt(value) => Node::Integer(value),
+        Value::HugeInt(value) => Node::Integer(value as i64),
+        Value::UTinyInt(value) => Node::Integer(value as i64),
+        Value::USmallInt(value) => Node::Integer(value as i64),
+        Value::UInt(value) => Node::Integer(value as i64),
+        Value::UBigInt(value) => Node::Integer(value as i64),
+        Value::Float(value) => Node::Number(Number(value as f64)),
+        Value::Double(value) => Node::Number(Number(value)),
+        Value::Decimal(value) => match value.to_string().parse::<f64>() {
+            Ok(value) => Node::Number(Number(value)),
+            Err(..) => Node::String(value.to_string()),
+        },
+        Value::Text(value) => {
+            // Arrays and objects are stored as JSON strings so attempt to parse them back
+            if value.starts_with('[') || value.starts_with('{') {
+                if let Ok(node @ (Node::Array(..) | Node::Object(..))) =
+                    serde_json::from_str::<Node>(&value)
+                {
+                    return node;
+                }
+            }
+            Node::String(value)
+        }
+        Value::Date32(days) => match NaiveDate::from_ymd_opt(1970, 1, 1) {
+            Some(epoch) => Node::Date(Date::from(
+                (epoch + Duration::days(days as i64)).to_string(),
+            )),
+            None => Node::Null(Null {}),
+        },
+        Value::Timestamp(unit, value) => {
+            let micros = match unit {
+                TimeUnit::Second => value * 1_000_000,
+                TimeUnit::Millisecond => value * 1_000,
+                TimeUnit::Microsecond => value,
+                TimeUnit::Nanosecond => value / 1_000,
+            };
+            match NaiveDateTime::from_timestamp_opt(0, 0) {
+                Some(epoch) => Node::Date(Date::from(
+                    (epoch + Duration::microseconds(micros)).to_string(),
+                )),
+                None => Node::Null(Null {}),
+            }
+        }
+        _ => {
+            tracing::debug!("Unhandled DuckDB value type, will be null: {:?}", value);
+            Node::Null(Null {})
+        }
+    }
+}
+
+/// Execute an SQL statement in DuckDB
+///
+/// Only returns a `Datatable` for convenience elsewhere in the code
+pub async fn execute_statement(
+    sql: &str,
+    parameters: &HashMap<String, Node>,
+    pool: &DuckPool,
+) -> Result<Datatable> {
+    let (sql, args) = bind(sql, parameters);
+    let pool = pool.clone();
+    task::spawn_blocking(move || -> Result<Datatable> {
+        let connection = pool
+            .lock()
+            .map_err(|error| eyre!("While locking DuckDB connection: {}", error))?;
+        connection.execute(&sql, params_from_iter(args))?;
+        Ok(Datatable::default())
+    })
+    .await?
+}
+
+/// Run a query in DuckDB and return the result as a Stencila [`Datatable`]
+///
+/// Because DuckDB can query Parquet and CSV files directly (e.g. `SELECT * FROM 'data.parquet'`
+/// or `SELECT * FROM read_csv_auto('data.csv')`) this is also how those files are read.
+pub async fn query_to_datatable(
+    query: &str,
+    parameters: &HashMap<String, Node>,
+    pool: &DuckPool,
+) -> Result<Datatable> {
+    // Run the query
+    let (sql, args) = bind(query, parameters);
+    let pool = pool.clone();
+    let (names, rows) = task::spawn_blocking(move || -> Result<(Vec<String>, Vec<Vec<Value>>)> {
+        let connection = pool
+            .lock()
+            .map_err(|error| eyre!("While locking DuckDB connection: {}", error))?;
+        let mut statement = connection.prepare(&sql)?;
+
+        let mut rows = Vec::new();
+        {
+            let mut results = statement.query(params_from_iter(args))?;
+            while let Some(row) = results.next()? {
+                let count = row.as_ref().column_count();
+                let values = (0..count)
+                    .map(|index| row.get::<usize, Value>(index))
+                    .collect::<Result<Vec<Value>, _>>()?;
+                rows.push(values);
+            }
+        }
+        let names = statement.column_names();
+
+        Ok((names, rows))
+    })
+    .await??;
+
+    // Pre-allocate an vector of the size needed to hold all values and insert them in
+    // column-first order
+    let rows_len = rows.len();
+    let mut values: Vec<Node> = vec![Node::Null(Null {}); names.len() * rows_len];
+    for (row_index, row) in rows.into_iter().enumerate() {
+        for (col_index, value) in row.into_iter().enumerate() {
+            let position = col_index * rows_len + row_index;
+            if position < values.len() {
+                values[position] = value_to_node(value);
+            }
+        }
+    }
+
+    // Create datatable, transforming the type of the first non-null value in each
+    // column into a validator
+    let columns = names
+        .into_iter()
+        .map(|name| {
+            let values: Vec<Node> = values.drain(..rows_len).collect();
+            let validator = values.iter().find_map(|value| match value {
+                Node::Boolean(..) => {
+                    Some(ValidatorTypes::BooleanValidator(BooleanValidator::default()))
+                }
+                Node::Integer(..) => {
+                    Some(ValidatorTypes::IntegerValidator(IntegerValidator::default()))
+                }
+                Node::Number(..) => {
+                    Some(ValidatorTypes::NumberValidator(NumberValidator::default()))
+                }
+                Node::String(..) => {
+                    Some(ValidatorTypes::StringValidator(StringValidator::default()))
+                }
+                _ => None,
+            });
+            DatatableColumn {
+                name,
+                validator: validator.map(|validator| {
+                    Box::new(ArrayValidator {
+                        items_validator: Some(Box::new(validator)),
+                        ..Default::default()
+                    })
+                }),
+                values,
+                ..Default::default()
+            }
+        })
+        .collect();
+    Ok(Datatable {
+        columns,
+        ..Default::default()
+    })
+}
+
+/// The maximum number of rows to insert in each `INSERT` statement
+const BATCH_ROWS: usize = 1_000;
+
+/// Create a DuckDB table from a Stencila [`Datatable`]
+///
+/// The table is created, and rows inserted in batches, within a transaction so that
+/// if there is an error any existing table with the same name is left unchanged.
+pub async fn table_from_datatable(
+    name: &str,
+    datatable: Datatable,
+    pool: &DuckPool,
+    temporary: bool,
+) -> Result<()> {
+    let name = name.replace('"', "-");
+    let rows = datatable_rows(&datatable)?;
+    let pool = pool.clone();
+    task::spawn_blocking(move || -> Result<()> {
+        let mut connection = pool
+            .lock()
+            .map_err(|error| eyre!("While locking DuckDB connection: {}", error))?;
+        let transaction = connection.transaction()?;
+
+        let (schema, create) = if temporary {
+            ("temp", "CREATE TEMPORARY TABLE")
+        } else {
+            ("main", "CREATE TABLE")
+        };
+
+        let types = datatable
+            .columns
+            .iter()
+            .map(ColumnType::from_column)
+            .collect_vec();
+        let columns = datatable
+            .columns
+            .iter()
+            .zip(&types)
+            .map(|(column, column_type)| {
+                let datatype = match column_type {
+                    ColumnType::Boolean => "BOOLEAN",
+                    ColumnType::Integer => "BIGINT",
+                    ColumnType::Number => "DOUBLE",
+                    // JSON is stored as strings because the DuckDB JSON extension may not be available
+                    ColumnType::String | ColumnType::Json => "VARCHAR",
+                };
+                format!("\"{}\" {}", column.name.replace('"', "-"), datatype)
+            })
+            .collect_vec()
+            .join(", ");
+        transaction.execute_batch(&format!(
+            "DROP TABLE IF EXISTS {schema}.\"{name}\";\n{create} \"{name}\"({columns});\n"
+        ))?;
+
+        let cols = datatable.columns.len();
+        if rows > 0 && cols > 0 {
+            for start in (0..rows).step_by(BATCH_ROWS) {
+                let end = (start + BATCH_ROWS).min(rows);
+
+                let mut sql = format!("INSERT INTO {schema}.\"{name}\" VALUES\n");
+                sql += &vec![format!(" ({})", vec!["?"; cols].join(", ")); end - start].join(",\n");
+
+                let mut values = Vec::with_capacity((end - start) * cols);
+                for row in start..end {
+                    for (column, column_type) in datatable.columns.iter().zip(&types) {
+                        let node = &column.values[row];
+                        values.push(match (column_type, node) {
+                            (ColumnType::Number, Node::Integer(value)) => {
+                                Value::Double(*value as f64)
+                            }
+                            _ => node_to_value(node),
+                        });
+                    }
+                }
+                transaction.execute(&sql, params_from_iter(values))?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Write a DuckDB table (or view) to an Apache Arrow IPC file
+pub async fn table_to_arrow(name: &str, path: &Path, pool: &DuckPool) -> Result<()> {
+    let sql = format!("SELECT * FROM \"{}\"", name.replace('"', "-"));
+    let path = path.to_path_buf();
+    let pool = pool.clone();
+    task::spawn_blocking(move || -> Result<()> {
+        let connection = pool
+            .lock()
+            .map_err(|error| eyre!("While locking DuckDB connection: {}", error))?;
+        let mut statement = connection.prepare(&sql)?;
+        let batches = statement.query_arrow([])?;
+
+        let file = File::create(&path)?;
+        let mut writer = FileWriter::try_new(file, &batches.get_schema())?;
+        for batch in batches {
+            writer.write(&batch)?;
+        }
+        writer.finish()?;
+
+        Ok(())
+    })
+    .await?
+}
+
+/// A column of an Arrow record batch cast to one of the types used for DuckDB tables
+enum ArrowColumn {
+    Boolean(BooleanArray),
+    Integer(Int64Array),
+    Number(Float64Array),
+    String(StringArray),
+    Date(Date32Array),
+    Time(Time64MicrosecondArray),
+    Timestamp(TimestampMicrosecondArray),
+}
+
+impl ArrowColumn {
+    /// Get the DuckDB column type for an Arrow data type
+    fn sql_type(data_type: &DataType) -> &'static str {
+        match data_type {
+            DataType::Boolean => "BOOLEAN",
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32 => "BIGINT",
+            DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Decimal128(..) => "DOUBLE",
+            DataType::Date32 | DataType::Date64 => "DATE",
+            DataType::Time32(..) | DataType::Time64(..) => "TIME",
+            DataType::Timestamp(..) => "TIMESTAMP",
+            _ => "VARCHAR",
+        }
+    }
+
+    /// Cast an Arrow array to a column
+    fn from_array(array: &ArrayRef) -> Result<Self> {
+        let data = |data_type: DataType| -> Result<ArrayData> {
+            Ok(cast(array, &data_type)?.data().clone())
+        };
+        Ok(match Self::sql_type(array.data_type()) {
+            "BOOLEAN" => Self::Boolean(BooleanArray::from(data(DataType::Boolean)?)),
+            "BIGINT" => Self::Integer(Int64Array::from(data(DataType::Int64)?)),
+            "DOUBLE" => Self::Number(Float64Array::from(data(DataType::Float64)?)),
+            "DATE" => Self::Date(Date32Array::from(data(DataType::Date32)?)),
+            "TIME" => Self::Time(Time64MicrosecondArray::from(data(DataType::Time64(
+                ArrowTimeUnit::Microsecond,
+            ))?)),
+            "TIMESTAMP" => Self::Timestamp(TimestampMicrosecondArray::from(data(
+                DataType::Timestamp(ArrowTimeUnit::Microsecond, None),
+            )?)),
+            _ => Self::String(StringArray::from(data(DataType::Utf8)?)),
+        })
+    }
+
+    /// Get the value of a row of the column
+    ///
+    /// The DuckDB appender does not accept `Date32` or `Time64` values but casts the values
+    /// appended to the type of the column. So dates are appended as timestamps at midnight,
+    /// and times as strings (e.g. `12:34:56.789000`).
+    fn value(&self, row: usize) -> Value {
+        match self {
+            Self::Boolean(array) if array.is_valid(row) => Value::Boolean(array.value(row)),
+            Self::Integer(array) if array.is_valid(row) => Value::BigInt(array.value(row)),
+            Self::Number(array) if array.is_valid(row) => Value::Double(array.value(row)),
+            Self::String(array) if array.is_valid(row) => Value::Text(array.value(row).to_string()),
+            Self::Date(array) if array.is_valid(row) => {
+                Value::Timestamp(TimeUnit::Second, array.value(row) as i64 * 86_400)
+            }
+            Self::Time(array) if array.is_valid(row) => {
+                let micros = array.value(row);
+                Value::Text(format!(
+                    "{:02}:{:02}:{:02}.{:06}",
+                    micros / 3_600_000_000,
+                    micros / 60_000_000 % 60,
+                    micros / 1_000_000 % 60,
+                    micros % 1_000_000
+                ))
+            }
+            Self::Timestamp(array) if array.is_valid(row) => {
+                Value::Timestamp(TimeUnit::Microsecond, array.value(row))
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Create a DuckDB table from an Apache Arrow IPC file
+///
+/// The table is created, and rows added using a DuckDB `Appender`, within a transaction.
+/// This avoids the overhead of converting the table to, and from, a `Datatable` and of
+/// parsing `INSERT` statements. Dates, times and timestamps are preserved as `DATE`,
+/// `TIME` and `TIMESTAMP` columns.
+///
+/// Ideally, record batches would be appended (or scanned using `arrow_scan`) directly but
+/// that requires a newer version of the `duckdb` crate than can currently be used.
+pub async fn table_from_arrow(
+    name: &str,
+    path: &Path,
+    pool: &DuckPool,
+    temporary: bool,
+) -> Result<()> {
+    let name = name.replace('"', "-");
+    let path = path.to_path_buf();
+    let pool = pool.clone();
+    task::spawn_blocking(move || -> Result<()> {
+        let reader = FileReader::try_new(File::open(&path)?, None)?;
+        let schema = reader.schema();
+
+        let mut connection = pool
+            .lock()
+            .map_err(|error| eyre!("While locking DuckDB connection: {}", error))?;
+        let transaction = connection.transaction()?;
+
+        let (schema_name, create) = if temporary {
+            ("temp", "CREATE TEMPORARY TABLE")
+        } else {
+            ("main", "CREATE TABLE")
+        };
+
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                format!(
+                    "\"{}\" {}",
+                    field.name().replace('"', "-"),
+                    ArrowColumn::sql_type(field.data_type())
+                )
+            })
+            .join(", ");
+        transaction.execute_batch(&format!(
+            "DROP TABLE IF EXISTS {schema_name}.\"{name}\";\n{create} \"{name}\"({columns});\n"
+        ))?;
+
+        if !schema.fields().is_empty() {
+            let mut appender = transaction.appender_to_db(&name, schema_name)?;
+            for batch in reader {
+                let batch = batch?;
+                let columns = batch
+                    .columns()
+                    .iter()
+                    .map(ArrowColumn::from_array)
+                    .collect::<Result<Vec<ArrowColumn>>>()?;
+                for row in 0..batch.num_rows() {
+                    appender.append_row(appender_params_from_iter(
+                        columns.iter().map(|column| column.value(row)),
+                    ))?;
+                }
+            }
+            appender.flush();
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    })
+    .await?
+}
@@ -6,7 +6,10 @@ use std::{
     sync::Arc,
 };
 
-use sqlx::{any::AnyConnectOptions, ConnectOptions, PgPool, SqlitePool};
+use sqlx::{
+    any::AnyConnectOptions, pool::PoolOptions, ConnectOptions, Database, MySqlPool, PgPool,
+    SqlitePool,
+};
 
 use kernel::{
     common::{
@@ -18,14 +21,18 @@ use kernel::{
         regex::Regex,
         serde::Serialize,
         serde_with::skip_serializing_none,
+        strum::EnumString,
         tokio::sync::{mpsc, RwLock},
         tracing::{self, log::LevelFilter},
     },
     graph_triples::ResourceChange,
-    stencila_schema::{CodeError, Datatable, Node},
+    stencila_schema::{CodeError, Datatable, DatatableColumn, Node, ValidatorTypes},
     Kernel, KernelSelector, KernelStatus, KernelTrait, KernelType, TagMap, Task, TaskResult,
 };
 
+#[cfg(feature = "duckdb")]
+mod duckdb;
+mod mysql;
 mod postgres;
 mod sqlite;
 
@@ -38,8 +45,148 @@ static SYMBOL_REGEX: Lazy<Regex> =
 static BINDING_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"\$([a-zA-Z_][a-zA-Z_0-9]*)").expect("Unable to create regex"));
 
+/// How `Datatable`s that are `set()` in the kernel are materialized in the database
+///
+/// Specified using the `tables` query parameter of the database URL
+/// e.g. `postgres://localhost/db?tables=temporary`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, Serialize)]
+#[serde(rename_all = "lowercase", crate = "kernel::common::serde")]
+#[strum(
+    serialize_all = "lowercase",
+    ascii_case_insensitive,
+    crate = "kernel::common::strum"
+)]
+enum TableMode {
+    /// Create a table in the database, replacing any existing table with the same name
+    #[default]
+    Persistent,
+
+    /// Create a temporary table which is only visible to the kernel and which is
+    /// dropped when the kernel's connection to the database is closed
+    #[strum(serialize = "temporary", serialize = "temp")]
+    Temporary,
+
+    /// Do not create a table; the `Datatable` is only available to the kernel's `get()`
+    None,
+}
+
+impl TableMode {
+    /// Extract the table mode from a database URL
+    ///
+    /// Returns the URL with the `tables` query parameter removed (so that it can be
+    /// passed on to database drivers) and the table mode.
+    fn extract(url: &str) -> Result<(String, TableMode)> {
+        let (base, query) = match url.split_once('?') {
+            Some((base, query)) => (base, query),
+            None => return Ok((url.to_string(), TableMode::default())),
+        };
+
+        let mut mode = TableMode::default();
+        let mut params = Vec::new();
+        for param in query.split('&') {
+            match param.strip_prefix("tables=") {
+                Some(value) => {
+                    mode = TableMode::from_str(value).map_err(|_| {
+                        eyre!(
+                            "Invalid value for `tables` parameter `{}`; expected `persistent`, `temporary`, or `none`",
+                            value
+                        )
+                    })?
+                }
+                None => params.push(param),
+            }
+        }
+
+        let url = if params.is_empty() {
+            base.to_string()
+        } else {
+            [base, "?", &params.join("&")].concat()
+        };
+        Ok((url, mode))
+    }
+}
+
+/// The type of a table column created from a `DatatableColumn`
+///
+/// Each database module maps these to its own SQL types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Json,
+}
+
+impl ColumnType {
+    /// Determine the type of a `DatatableColumn`
+    ///
+    /// Uses the column's validator if it has one that maps directly to a SQL type. Otherwise,
+    /// infers the type from the column's values: integers and numbers are widened to numbers,
+    /// and columns with other, or mixed, types of values are stored as JSON.
+    fn from_column(column: &DatatableColumn) -> Self {
+        let validator = column
+            .validator
+            .as_deref()
+            .and_then(|array_validator| array_validator.items_validator.as_deref());
+        match validator {
+            Some(ValidatorTypes::BooleanValidator(..)) => return ColumnType::Boolean,
+            Some(ValidatorTypes::IntegerValidator(..)) => return ColumnType::Integer,
+            Some(ValidatorTypes::NumberValidator(..)) => return ColumnType::Number,
+            Some(ValidatorTypes::StringValidator(..)) => return ColumnType::String,
+            _ => {}
+        }
+
+        let mut column_type = None;
+        for value in &column.values {
+            let value_type = match value {
+                Node::Null(..) => continue,
+                Node::Boolean(..) => ColumnType::Boolean,
+                Node::Integer(..) => ColumnType::Integer,
+                Node::Number(..) => ColumnType::Number,
+                Node::String(..) => ColumnType::String,
+                _ => return ColumnType::Json,
+            };
+            column_type = match (column_type, value_type) {
+                (None, value_type) => Some(value_type),
+                (Some(column_type), value_type) if column_type == value_type => Some(column_type),
+                (Some(ColumnType::Integer), ColumnType::Number)
+                | (Some(ColumnType::Number), ColumnType::Integer) => Some(ColumnType::Number),
+                _ => return ColumnType::Json,
+            };
+        }
+        column_type.unwrap_or(ColumnType::Json)
+    }
+}
+
+/// Check that all the columns of a `Datatable` have the same number of values
+/// and return that number
+fn datatable_rows(datatable: &Datatable) -> Result<usize> {
+    let rows = datatable
+        .columns
+        .first()
+        .map(|column| column.values.len())
+        .unwrap_or(0);
+    if let Some(column) = datatable
+        .columns
+        .iter()
+        .find(|column| column.values.len() != rows)
+    {
+        bail!(
+            "Column `{}` has {} values but expected {}",
+            column.name,
+            column.values.len(),
+            rows
+        )
+    }
+    Ok(rows)
+}
+
 #[derive(Debug)]
 enum MetaPool {
+    #[cfg(feature = "duckdb")]
+    DuckDb(duckdb::DuckPool),
+    MySql(MySqlPool),
     Postgres(PgPool),
     Sqlite(SqlitePool),
 }
@@ -56,12 +203,17 @@ pub struct SqlKernel {
 
     /// The directory that the kernel is started in
     ///
-    /// Used to be able to resolve the path to SQLite files with relative paths
+    /// Used to be able to resolve the path to SQLite and DuckDB files with relative paths
     directory: Option<PathBuf>,
 
     /// The URL of the database (resolved just-in-time before connecting)
     url: Option<String>,
 
+    /// How `Datatable`s that are `set()` in the kernel are materialized in the database
+    ///
+    /// Resolved from the database URL when connecting.
+    tables: TableMode,
+
     /// The kernel's database connection pool
     #[serde(skip)]
     pool: Option<MetaPool>,
@@ -111,12 +263,16 @@ impl SqlKernel {
         }
 
         // Resolve the database URL, falling back to env var and then to in-memory SQLite
-        let mut url = self
+        let url = self
             .config
             .clone()
             .or_else(|| env::var("DATABASE_URL").ok())
             .unwrap_or_else(|| "sqlite://:memory:".to_string());
 
+        // Extract any table mode from the URL
+        let (mut url, tables) = TableMode::extract(&url)?;
+        self.tables = tables;
+
         // If the URL is for a SQLite and the file path is relative then make it
         // absolute using the directory that the kernel was started in.
         if let (Some(spec), Some(directory)) = (url.strip_prefix("sqlite://"), &self.directory) {
@@ -126,17 +282,46 @@ impl SqlKernel {
             }
         }
 
+        // DuckDB is not supported by `sqlx` so is handled separately
+        #[cfg(not(feature = "duckdb"))]
+        if url.starts_with("duckdb://") {
+            bail!("DuckDB databases are not supported by this build of the SQL kernel (it was built without the `duckdb` feature)")
+        }
+        #[cfg(feature = "duckdb")]
+        if let Some(spec) = url.strip_prefix("duckdb://") {
+            let mut spec = spec.to_string();
+            if let Some(directory) = &self.directory {
+                let path = PathBuf::from(&spec);
+                if !(spec.is_empty() || spec == ":memory:") && path.is_relative() {
+                    spec = directory.join(path).to_string_lossy().to_string();
+                    url = ["duckdb://", &spec].concat();
+                }
+            }
+
+            tracing::trace!("Connecting to database: {}", url);
+
+            let pool = duckdb::connect(&spec, self.directory.as_deref()).await?;
+            self.pool = Some(MetaPool::DuckDb(pool));
+            self.url = Some(url);
+
+            return Ok(());
+        }
+
         let mut options = AnyConnectOptions::from_str(&url)?;
 
         tracing::trace!("Connecting to database: {}", url);
 
-        let pool = if let Some(options) = options.as_postgres_mut() {
+        let pool = if let Some(options) = options.as_mysql_mut() {
+            options.log_statements(LevelFilter::Trace);
+            let pool: MySqlPool = self.pool_options().connect_with(options.clone()).await?;
+            MetaPool::MySql(pool)
+        } else if let Some(options) = options.as_postgres_mut() {
             options.log_statements(LevelFilter::Trace);
-            let pool = PgPool::connect_with(options.clone()).await?;
+            let pool: PgPool = self.pool_options().connect_with(options.clone()).await?;
             MetaPool::Postgres(pool)
         } else if let Some(options) = options.as_sqlite_mut() {
             options.log_statements(LevelFilter::Trace);
-            let pool = SqlitePool::connect_with(options.clone()).await?;
+            let pool: SqlitePool = self.pool_options().connect_with(options.clone()).await?;
             MetaPool::Sqlite(pool)
         } else {
             bail!(
@@ -151,6 +336,21 @@ impl SqlKernel {
         Ok(())
     }
 
+    /// Get the options for creating a database connection pool
+    fn pool_options<DB: Database>(&self) -> PoolOptions<DB> {
+        let options = PoolOptions::new();
+        if self.tables == TableMode::Temporary {
+            // Temporary tables are only visible on the connection that created them so
+            // use a single connection that is never closed for being idle or too old
+            options
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            options
+        }
+    }
+
     /// Listen for notifications from the database (if not already listening)
     async fn watch(&mut self, tables: &[String]) -> Result<()> {
         self.connect().await?;
@@ -174,6 +374,13 @@ impl SqlKernel {
         if !self.watching {
             let watches = self.watches.clone();
             match pool {
+                #[cfg(feature = "duckdb")]
+                MetaPool::DuckDb(..) => {
+                    bail!("Watching tables is not supported for DuckDB databases")
+                }
+                MetaPool::MySql(pool) => {
+                    mysql::watch(url, pool, watches, sender).await?;
+                }
                 MetaPool::Postgres(pool) => {
                     postgres::watch(url, pool, watches, sender).await?;
                 }
@@ -190,6 +397,9 @@ impl SqlKernel {
             if first == "@all" {
                 let schema = tables.get(1);
                 let tables = match pool {
+                    #[cfg(feature = "duckdb")]
+                    MetaPool::DuckDb(..) => Vec::new(),
+                    MetaPool::MySql(pool) => mysql::watch_all(schema, pool).await?,
                     MetaPool::Postgres(pool) => postgres::watch_all(schema, pool).await?,
                     MetaPool::Sqlite(pool) => sqlite::watch_all(schema, pool).await?,
                 };
@@ -203,6 +413,9 @@ impl SqlKernel {
         for table in tables {
             if !watches.contains(table) {
                 match pool {
+                    #[cfg(feature = "duckdb")]
+                    MetaPool::DuckDb(..) => {}
+                    MetaPool::MySql(pool) => mysql::watch_table(table, pool).await?,
                     MetaPool::Postgres(pool) => postgres::watch_table(table, pool).await?,
                     MetaPool::Sqlite(pool) => sqlite::watch_table(table, pool).await?,
                 }
@@ -299,6 +512,12 @@ impl KernelTrait for SqlKernel {
         // Attempt to get a table or view with the same name
         let query = format!("SELECT * FROM \"{}\"", name.replace('"', "-"));
         if let Ok(datatable) = match pool {
+            #[cfg(feature = "duckdb")]
+            MetaPool::DuckDb(pool) => duckdb::query_to_datatable(&query, params, pool).await,
+            MetaPool::MySql(pool) => {
+                let query = format!("SELECT * FROM {}", mysql::quote(name));
+                mysql::query_to_datatable(&query, params, pool).await
+            }
             MetaPool::Postgres(pool) => postgres::query_to_datatable(&query, params, pool).await,
             MetaPool::Sqlite(pool) => sqlite::query_to_datatable(&query, params, pool).await,
         } {
@@ -319,20 +538,86 @@ impl KernelTrait for SqlKernel {
             .as_ref()
             .expect("connect() should ensure connection");
 
-        if let Node::Datatable(datatable) = value {
-            match pool {
-                MetaPool::Postgres(pool) => {
-                    postgres::table_from_datatable(name, datatable, pool).await
-                }
-                MetaPool::Sqlite(pool) => sqlite::table_from_datatable(name, datatable, pool).await,
+        let datatable = match value {
+            Node::Datatable(datatable) => datatable,
+            _ => {
+                self.parameters.insert(name.to_string(), value);
+                return Ok(());
+            }
+        };
+
+        let temporary = match self.tables {
+            TableMode::Persistent => false,
+            TableMode::Temporary => true,
+            TableMode::None => {
+                self.assigned.insert(name.to_string(), datatable);
+                return Ok(());
+            }
+        };
+
+        // Each module creates the table, and inserts rows, within a transaction so that if
+        // there is an error any existing table is left intact
+        match pool {
+            #[cfg(feature = "duckdb")]
+            MetaPool::DuckDb(pool) => {
+                duckdb::table_from_datatable(name, datatable, pool, temporary).await
+            }
+            MetaPool::MySql(pool) => {
+                mysql::table_from_datatable(name, datatable, pool, temporary).await
+            }
+            MetaPool::Postgres(pool) => {
+                postgres::table_from_datatable(name, datatable, pool, temporary).await
+            }
+            MetaPool::Sqlite(pool) => {
+                sqlite::table_from_datatable(name, datatable, pool, temporary).await
+            }
+        }
+        .map_err(|error| eyre!("While setting table `{}` in SQL kernel: {}", name, error))
+    }
+
+    /// Only DuckDB databases support transferring tables using Apache Arrow
+    #[cfg(feature = "duckdb")]
+    async fn supports_arrow(&self) -> bool {
+        matches!(self.pool, Some(MetaPool::DuckDb(..)))
+    }
+
+    #[cfg(feature = "duckdb")]
+    async fn get_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        let pool = match &self.pool {
+            Some(MetaPool::DuckDb(pool)) => pool,
+            _ => return Ok(false),
+        };
+
+        // If the symbol is not a table or view (e.g. an assigned query result) then
+        // it will be got using `get()`
+        match duckdb::table_to_arrow(name, path, pool).await {
+            Ok(..) => Ok(true),
+            Err(error) => {
+                tracing::trace!("Unable to write table `{}` to Arrow: {}", name, error);
+                Ok(false)
             }
-            .map_err(|error| eyre!("While setting table `{}` in SQL kernel: {}", name, error))
-        } else {
-            self.parameters.insert(name.to_string(), value);
-            Ok(())
         }
     }
 
+    #[cfg(feature = "duckdb")]
+    async fn set_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        let pool = match &self.pool {
+            Some(MetaPool::DuckDb(pool)) => pool,
+            _ => return Ok(false),
+        };
+
+        let temporary = match self.tables {
+            TableMode::Persistent => false,
+            TableMode::Temporary => true,
+            TableMode::None => return Ok(false),
+        };
+
+        duckdb::table_from_arrow(name, path, pool, temporary)
+            .await
+            .map_err(|error| eyre!("While setting table `{}` in SQL kernel: {}", name, error))?;
+        Ok(true)
+    }
+
     async fn exec_sync(&mut self, code: &str, tags: Option<&TagMap>) -> Result<Task> {
         let mut task = Task::begin_sync();
         let mut outputs = Vec::new();
@@ -362,6 +647,13 @@ impl KernelTrait for SqlKernel {
             for statement in statements {
                 let result = if statement.trim_start().to_lowercase().starts_with("select") {
                     match pool {
+                        #[cfg(feature = "duckdb")]
+                        MetaPool::DuckDb(pool) => {
+                            duckdb::query_to_datatable(&statement, params, pool).await
+                        }
+                        MetaPool::MySql(pool) => {
+                            mysql::query_to_datatable(&statement, params, pool).await
+                        }
                         MetaPool::Postgres(pool) => {
                             postgres::query_to_datatable(&statement, params, pool).await
                         }
@@ -371,6 +663,13 @@ impl KernelTrait for SqlKernel {
                     }
                 } else {
                     match pool {
+                        #[cfg(feature = "duckdb")]
+                        MetaPool::DuckDb(pool) => {
+                            duckdb::execute_statement(&statement, params, pool).await
+                        }
+                        MetaPool::MySql(pool) => {
+                            mysql::execute_statement(&statement, params, pool).await
+                        }
                         MetaPool::Postgres(pool) => {
                             postgres::execute_statement(&statement, params, pool).await
                         }
@@ -0,0 +1,606 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sqlx::{
+    mysql::MySqlArguments,
+    types::chrono::{NaiveDate, NaiveDateTime},
+    Arguments, Column, Executor, MySql, MySqlPool, Row, Transaction, TypeInfo,
+};
+
+use hash_utils::str_sha256_hex;
+use kernel::{
+    common::{
+        eyre::{bail, Report, Result},
+        itertools::Itertools,
+        regex::Captures,
+        serde_json,
+        tokio::{self, sync::mpsc, time},
+        tracing,
+    },
+    graph_triples::{
+        resources::{self, ResourceChangeAction},
+        ResourceChange,
+    },
+    stencila_schema::{
+        ArrayValidator, BooleanValidator, Datatable, DatatableColumn, Date, IntegerValidator, Node,
+        Null, Number, NumberValidator, StringValidator, ValidatorTypes,
+    },
+};
+
+use crate::{datatable_rows, ColumnType, WatchedTables, BINDING_REGEX};
+
+/// The maximum number of placeholders allowed in a single MySQL prepared statement
+const MAX_PLACEHOLDERS: usize = 65_535;
+
+/// Quote an identifier (e.g. table or column name) for use in MySQL
+///
+/// Uses backticks rather than double quotes because the latter are only treated as
+/// identifier quotes when the `ANSI_QUOTES` SQL mode is enabled.
+pub fn quote(name: &str) -> String {
+    ["`", &name.replace('`', "``"), "`"].concat()
+}
+
+/// Bind parameters to an SQL statement based on name
+///
+/// Returns an error if any of the parameters are not available.
+fn bind(sql: &str, parameters: &HashMap<String, Node>) -> Result<(String, MySqlArguments)> {
+    let mut arguments = MySqlArguments::default();
+    let mut missing = Vec::new();
+    let sql = BINDING_REGEX.replace_all(sql, |captures: &Captures| {
+        let name = captures[1].to_string();
+        match parameters.get(&name) {
+            Some(Node::Boolean(value)) => arguments.add(value),
+            Some(Node::Integer(value)) => arguments.add(value),
+            Some(Node::Number(value)) => arguments.add(value.0),
+            Some(Node::String(value)) => arguments.add(value),
+            Some(value) => {
+                arguments.add(serde_json::to_value(value).unwrap_or(serde_json::Value::Null))
+            }
+            None => missing.push(name),
+        };
+        "?"
+    });
+    if !missing.is_empty() {
+        bail!(
+            "Parameter/s used in SQL are not available: {}",
+            missing
+                .iter()
+                .unique()
+                .map(|name| ["$", name].concat())
+                .join(", ")
+        )
+    }
+    Ok((sql.to_string(), arguments))
+}
+
+/// Execute an SQL statement in MySQL
+///
+/// Statements without any parameter bindings are sent using MySQL's text protocol because
+/// some statements (e.g. `CREATE TRIGGER`) can not be used as prepared statements.
+///
+/// Only returns a `Datatable` for convenience elsewhere in the code
+pub async fn execute_statement(
+    sql: &str,
+    parameters: &HashMap<String, Node>,
+    pool: &MySqlPool,
+) -> Result<Datatable> {
+    if BINDING_REGEX.is_match(sql) {
+        let (sql, args) = bind(sql, parameters)?;
+        sqlx::query_with(&sql, args).execute(pool).await?;
+    } else {
+        pool.execute(sql).await?;
+    }
+    Ok(Datatable::default())
+}
+
+/// Run a query in MySQL and return the result as a Stencila [`Datatable`]
+pub async fn query_to_datatable(
+    query: &str,
+    parameters: &HashMap<String, Node>,
+    pool: &MySqlPool,
+) -> Result<Datatable> {
+    // Run the query
+    let (sql, args) = bind(query, parameters)?;
+    let rows = sqlx::query_with(&sql, args).fetch_all(pool).await?;
+
+    // Get the names of the columns and transform their types into validators
+    let columns = if let Some(row) = rows.first() {
+        row.columns()
+            .iter()
+            .map(|column| {
+                let name = column.name().to_string();
+                let col_type = column.type_info().name().to_string();
+                let validator = match col_type.as_str() {
+                    "BOOLEAN" => {
+                        Some(ValidatorTypes::BooleanValidator(BooleanValidator::default()))
+                    }
+                    "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" | "YEAR"
+                    | "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED"
+                    | "INT UNSIGNED" | "BIGINT UNSIGNED" => {
+                        Some(ValidatorTypes::IntegerValidator(IntegerValidator::default()))
+                    }
+                    "FLOAT" | "DOUBLE" | "DECIMAL" => {
+                        Some(ValidatorTypes::NumberValidator(NumberValidator::default()))
+                    }
+                    "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT"
+                    | "ENUM" => Some(ValidatorTypes::StringValidator(StringValidator::default())),
+                    "JSON" | "DATE" | "DATETIME" | "TIMESTAMP" | "NULL" => None,
+                    _ => {
+                        tracing::debug!(
+                            "Unhandled column type, will have no validator: {}",
+                            col_type
+                        );
+                        None
+                    }
+                };
+                (name, col_type, validator)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Pre-allocate an vector of the size needed to hold all values and insert them in
+    // column-first order
+    let rows_len = rows.len();
+    let mut values: Vec<Node> = vec![Node::Null(Null {}); columns.len() * rows_len];
+    for (row_index, row) in rows.into_iter().enumerate() {
+        for (col_index, (_name, col_type, ..)) in columns.iter().enumerate() {
+            let position = col_index * rows_len + row_index;
+            let value = match col_type.as_str() {
+                "BOOLEAN" => row
+                    .try_get::<bool, usize>(col_index)
+                    .map(Node::Boolean)
+                    .ok(),
+                "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" => {
+                    row.try_get::<i64, usize>(col_index).map(Node::Integer).ok()
+                }
+                "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED"
+                | "INT UNSIGNED" | "BIGINT UNSIGNED" | "YEAR" => row
+                    .try_get::<u64, usize>(col_index)
+                    .map(|int| Node::Integer(int as i64))
+                    .ok(),
+                "FLOAT" => row
+                    .try_get::<f32, usize>(col_index)
+                    .map(|num| Node::Number(Number(num as f64)))
+                    .ok(),
+                "DOUBLE" => row
+                    .try_get::<f64, usize>(col_index)
+                    .map(|num| Node::Number(Number(num)))
+                    .ok(),
+                "DECIMAL" => row
+                    .try_get_unchecked::<String, usize>(col_index)
+                    .ok()
+                    .and_then(|string| string.parse::<f64>().ok())
+                    .map(|num| Node::Number(Number(num))),
+                "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" | "ENUM" => {
+                    row.try_get::<String, usize>(col_index)
+                        .map(Node::String)
+                        .ok()
+                }
+                "DATE" => row
+                    .try_get::<NaiveDate, usize>(col_index)
+                    .map(|date| Node::Date(Date::from(date.to_string())))
+                    .ok(),
+                "DATETIME" | "TIMESTAMP" => row
+                    .try_get::<NaiveDateTime, usize>(col_index)
+                    .map(|datetime| Node::Date(Date::from(datetime.to_string())))
+                    .ok(),
+                _ => row
+                    .try_get_unchecked::<String, usize>(col_index)
+                    .ok()
+                    .and_then(|string| {
+                        serde_json::from_str(&string).unwrap_or(Some(Node::String(string)))
+                    }),
+            };
+            if let Some(value) = value {
+                values[position] = value;
+            }
+        }
+    }
+
+    // Create datatable
+    let columns = columns
+        .into_iter()
+        .map(|(name, _col_type, validator)| DatatableColumn {
+            name,
+            validator: validator.map(|validator| {
+                Box::new(ArrayValidator {
+                    items_validator: Some(Box::new(validator)),
+                    ..Default::default()
+                })
+            }),
+            values: values.drain(..rows_len).collect(),
+            ..Default::default()
+        })
+        .collect();
+    Ok(Datatable {
+        columns,
+        ..Default::default()
+    })
+}
+
+/// Create a MySQL table from a Stencila [`Datatable`]
+///
+/// MySQL implicitly commits on `DROP TABLE` and `CREATE TABLE` so, for persistent tables, the
+/// table is created, and rows inserted, in a staging table which then atomically replaces any
+/// existing table using `RENAME TABLE`. That way, if there is an error, any existing table with
+/// the same name is left unchanged. Any watches on the existing table are recreated on the
+/// new table.
+///
+/// Statements on temporary tables do not cause an implicit commit so temporary tables are
+/// dropped, created, and filled within a single transaction.
+pub async fn table_from_datatable(
+    name: &str,
+    datatable: Datatable,
+    pool: &MySqlPool,
+    temporary: bool,
+) -> Result<()> {
+    let rows = datatable_rows(&datatable)?;
+
+    if temporary {
+        let mut transaction = pool.begin().await?;
+        sqlx::query(&format!("DROP TEMPORARY TABLE IF EXISTS {}", quote(name)))
+            .execute(&mut transaction)
+            .await?;
+        create_table(
+            &mut transaction,
+            "CREATE TEMPORARY TABLE",
+            name,
+            &datatable,
+            rows,
+        )
+        .await?;
+        transaction.commit().await?;
+        return Ok(());
+    }
+
+    let hash = &str_sha256_hex(name)[..16];
+    let staging = ["stencila_staging_", hash].concat();
+    let replaced = ["stencila_replaced_", hash].concat();
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query(&format!("DROP TABLE IF EXISTS {}", quote(&staging)))
+        .execute(&mut transaction)
+        .await?;
+    let filled =
+        match create_table(&mut transaction, "CREATE TABLE", &staging, &datatable, rows).await {
+            Ok(..) => transaction.commit().await.map_err(Report::from),
+            Err(error) => {
+                // Roll back before dropping the staging table so that locks on it are released
+                transaction.rollback().await.ok();
+                Err(error)
+            }
+        };
+    if let Err(error) = filled {
+        if let Err(error) = pool
+            .execute(format!("DROP TABLE IF EXISTS {}", quote(&staging)).as_str())
+            .await
+        {
+            tracing::warn!("While dropping MySQL staging table: {}", error);
+        }
+        return Err(error);
+    }
+
+    let exists = sqlx::query(
+        r#"
+        SELECT 1 FROM information_schema.tables
+        WHERE table_schema = DATABASE() AND table_name = ?
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?
+    .is_some();
+    if !exists {
+        pool.execute(format!("RENAME TABLE {} TO {}", quote(&staging), quote(name)).as_str())
+            .await?;
+        return Ok(());
+    }
+
+    let watched = sqlx::query(
+        r#"
+        SELECT 1 FROM information_schema.triggers
+        WHERE trigger_schema = DATABASE()
+            AND event_object_table = ?
+            AND trigger_name LIKE 'stencila\_resource\_%'
+        LIMIT 1
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?
+    .is_some();
+
+    pool.execute(
+        format!(
+            "RENAME TABLE {name} TO {replaced}, {staging} TO {name}",
+            name = quote(name),
+            replaced = quote(&replaced),
+            staging = quote(&staging)
+        )
+        .as_str(),
+    )
+    .await?;
+
+    // Triggers are moved with the renamed table, and are dropped with it, so drop it before
+    // recreating the triggers (trigger names must be unique within a database)
+    pool.execute(format!("DROP TABLE {}", quote(&replaced)).as_str())
+        .await?;
+    if watched {
+        watch_table(name, pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Create a MySQL table, and insert rows into it, from a Stencila [`Datatable`]
+///
+/// Rows are inserted in batches so as not to exceed the maximum number of
+/// placeholders allowed in a prepared statement.
+async fn create_table(
+    transaction: &mut Transaction<'_, MySql>,
+    create: &str,
+    name: &str,
+    datatable: &Datatable,
+    rows: usize,
+) -> Result<()> {
+    let types = datatable
+        .columns
+        .iter()
+        .map(ColumnType::from_column)
+        .collect_vec();
+    let columns = datatable
+        .columns
+        .iter()
+        .zip(&types)
+        .map(|(column, column_type)| {
+            let datatype = match column_type {
+                ColumnType::Boolean => "BOOLEAN",
+                ColumnType::Integer => "BIGINT",
+                ColumnType::Number => "DOUBLE",
+                ColumnType::String => "TEXT",
+                ColumnType::Json => "JSON",
+            };
+            format!("{} {}", quote(&column.name), datatype)
+        })
+        .collect_vec()
+        .join(", ");
+    sqlx::query(&format!("{} {}({})", create, quote(name), columns))
+        .execute(&mut *transaction)
+        .await?;
+
+    let cols = datatable.columns.len();
+    if rows > 0 && cols > 0 {
+        let batch = (MAX_PLACEHOLDERS / cols).max(1);
+        for start in (0..rows).step_by(batch) {
+            let end = (start + batch).min(rows);
+
+            let mut sql = format!("INSERT INTO {} VALUES\n", quote(name));
+            sql += &vec![format!(" ({})", vec!["?"; cols].join(", ")); end - start].join(",\n");
+
+            let mut query = sqlx::query(&sql);
+            for row in start..end {
+                for (column, column_type) in datatable.columns.iter().zip(&types) {
+                    let node = &column.values[row];
+                    match (column_type, node) {
+                        (_, Node::Null(..)) => query = query.bind(Option::<String>::None),
+                        (ColumnType::Json, _) => {
+                            query = query.bind(serde_json::to_string(node).unwrap_or_default())
+                        }
+                        (_, Node::Boolean(value)) => query = query.bind(value),
+                        (_, Node::Integer(value)) => query = query.bind(value),
+                        (_, Node::Number(value)) => query = query.bind(value.0),
+                        (_, Node::String(value)) => query = query.bind(value),
+                        _ => query = query.bind(serde_json::to_string(node).unwrap_or_default()),
+                    }
+                }
+            }
+            query.execute(&mut *transaction).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Start a background task to listen for notifications of changes to tables
+ *
+ * MySQL and MariaDB do not have an equivalent to Postgres' `LISTEN`/`NOTIFY` and the alternative,
+ * reading the binary log, requires replication privileges that users often do not have.
+ *
+ * Given that, as for SQLite, this implements a polling approach which listens for changes in a hidden
+ * notifications table which is written to by triggers on each watched table. Because MySQL does not
+ * allow a trigger to modify the table it is defined on, old changes are purged by the polling task
+ * rather than by a trigger.
+ */
+pub async fn watch(
+    url: &str,
+    pool: &MySqlPool,
+    watches: WatchedTables,
+    sender: mpsc::Sender<ResourceChange>,
+) -> Result<()> {
+    // Create table for recording changes
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS stencila_resource_changes(
+            `time` BIGINT,
+            `action` VARCHAR(16),
+            `table` VARCHAR(64)
+        )
+        "#,
+    )
+    .await?;
+
+    let url = url.to_string();
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(300));
+        let mut last_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        loop {
+            interval.tick().await;
+
+            let watches = watches.read().await;
+            if watches.is_empty() {
+                continue;
+            }
+
+            let rows = match sqlx::query(
+                r#"
+                SELECT `time`, `action`, `table`
+                FROM stencila_resource_changes
+                WHERE `time` > ?
+                GROUP BY `time`, `action`, `table`
+                ORDER BY `time`
+                "#,
+            )
+            .bind(last_time as i64)
+            .fetch_all(&pool)
+            .await
+            {
+                Ok(row) => row,
+                Err(error) => {
+                    tracing::error!("While polling for MySQL notifications: {}", error);
+                    continue;
+                }
+            };
+
+            let path = PathBuf::from(url.clone());
+
+            for row in rows {
+                let name = row.get_unchecked::<String, _>("table");
+                let time = row.get_unchecked::<i64, _>("time");
+
+                last_time = time as u128;
+
+                if !watches.contains(&name) {
+                    continue;
+                }
+
+                let change = ResourceChange {
+                    resource: resources::symbol(&path, &name, "Datatable"),
+                    action: ResourceChangeAction::Updated,
+                    time: time.to_string(),
+                };
+                if let Err(error) = sender.send(change).await {
+                    tracing::error!(
+                        "While sending resource change from MySQL listener: {}",
+                        error
+                    );
+                }
+            }
+
+            // Purge changes older than 60s
+            if let Err(error) =
+                sqlx::query("DELETE FROM stencila_resource_changes WHERE `time` < ?")
+                    .bind(last_time as i64 - 60 * 1000)
+                    .execute(&pool)
+                    .await
+            {
+                tracing::error!("While purging MySQL notifications: {}", error);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// The maximum length of identifiers (e.g. trigger names) in MySQL
+pub const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+/// Create the name of a trigger for watching a table
+///
+/// Names longer than the maximum allowed length are truncated and suffixed
+/// with a hash of the full name so that they remain unique.
+pub fn trigger_name(action: &str, table: &str) -> String {
+    let name = format!("stencila_resource_{action}_{table}");
+    if name.chars().count() <= MAX_IDENTIFIER_LENGTH {
+        return name;
+    }
+
+    let hash = &str_sha256_hex(&name)[..8];
+    let prefix: String = name
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH - hash.len() - 1)
+        .collect();
+    [&prefix, "_", hash].concat()
+}
+
+/// Set up watches for a particular table
+///
+/// Checks for existing triggers, rather than using `CREATE TRIGGER IF NOT EXISTS`,
+/// because the latter is only available in MySQL 8.0.29 and later.
+pub async fn watch_table(table: &str, pool: &MySqlPool) -> Result<()> {
+    for action in ["insert", "update", "delete"] {
+        let trigger = trigger_name(action, table);
+
+        let exists = sqlx::query(
+            r#"
+            SELECT 1 FROM information_schema.triggers
+            WHERE trigger_schema = DATABASE() AND trigger_name = ?
+            "#,
+        )
+        .bind(&trigger)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+        if exists {
+            continue;
+        }
+
+        let trigger = quote(&trigger);
+        let name = quote(table);
+        let table = table.replace('\'', "''");
+        pool.execute(
+            format!(
+                r#"
+                CREATE TRIGGER {trigger}
+                AFTER {action} ON {name}
+                FOR EACH ROW
+                INSERT INTO stencila_resource_changes(`time`, `action`, `table`)
+                VALUES(
+                    CAST(UNIX_TIMESTAMP(NOW(3)) * 1000 AS SIGNED),
+                    '{action}',
+                    '{table}'
+                )
+                "#
+            )
+            .as_str(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Set up watches for `@all` tables in a database
+///
+/// If no `schema` (i.e database) is specified then the current database is used.
+pub async fn watch_all(schema: Option<&String>, pool: &MySqlPool) -> Result<Vec<String>> {
+    let tables = sqlx::query(
+        r#"
+        SELECT CAST(table_name AS CHAR) AS name FROM information_schema.tables
+        WHERE table_schema = COALESCE(?, DATABASE())
+            AND table_type = 'BASE TABLE'
+            AND table_name != 'stencila_resource_changes'
+        "#,
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+
+    let mut names = Vec::with_capacity(tables.len());
+    for table in tables {
+        let name = table.get_unchecked::<String, _>("name");
+        watch_table(&name, pool).await?;
+        names.push(name);
+    }
+
+    Ok(names)
+}
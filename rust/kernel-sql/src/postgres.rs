@@ -24,7 +24,7 @@ use kernel::{
     },
 };
 
-use crate::{WatchedTables, BINDING_REGEX};
+use crate::{datatable_rows, ColumnType, WatchedTables, BINDING_REGEX};
 
 /// Bind parameters to an SQL statement based on name
 fn bind(sql: &str, parameters: &HashMap<String, Node>) -> (String, PgArguments) {
@@ -170,126 +170,127 @@ pub async fn query_to_datatable(
 
 /// Create a Postgres table from a Stencila [`Datatable`]
 ///
-/// This function follows the recommendation [here](https://github.com/launchbadge/sqlx/blob/main/FAQ.md#how-can-i-bind-an-array-to-a-values-clause-how-can-i-do-bulk-inserts)
-/// for doing bulk inserts into Postgres tables.
-pub async fn table_from_datatable(name: &str, datatable: Datatable, pool: &PgPool) -> Result<()> {
-    sqlx::query(&format!("DROP TABLE IF EXISTS \"{}\"", name))
-        .execute(pool)
-        .await?;
+/// The table is created, and rows inserted, within a transaction so that if there is
+/// an error any existing table with the same name is left unchanged. Rows are inserted
+/// using a single statement that binds each column as an array.
+pub async fn table_from_datatable(
+    name: &str,
+    datatable: Datatable,
+    pool: &PgPool,
+    temporary: bool,
+) -> Result<()> {
+    let name = name.replace('"', "-");
+    let rows = datatable_rows(&datatable)?;
 
-    let columns = datatable
-        .columns
-        .iter()
-        .map(|column| {
-            let validator = column
-                .validator
-                .as_deref()
-                .and_then(|array_validator| array_validator.items_validator.clone());
-            let datatype = match validator.as_deref() {
-                Some(ValidatorTypes::BooleanValidator(..)) => "BOOLEAN",
-                Some(ValidatorTypes::IntegerValidator(..)) => "INTEGER",
-                Some(ValidatorTypes::NumberValidator(..)) => "REAL",
-                Some(ValidatorTypes::StringValidator(..)) => "TEXT",
-                _ => "JSON",
-            };
-            format!("{} {}", column.name, datatype)
-        })
-        .collect_vec()
-        .join(", ");
-    sqlx::query(&format!("CREATE TABLE \"{}\"({});\n", name, columns))
-        .execute(pool)
-        .await?;
+    let mut transaction = pool.begin().await?;
 
-    let rows = datatable
-        .columns
-        .first()
-        .map(|column| column.values.len())
-        .unwrap_or(0);
-    if rows == 0 {
-        return Ok(());
-    }
+    let (drop, create) = if temporary {
+        (
+            format!("DROP TABLE IF EXISTS pg_temp.\"{}\"", name),
+            "CREATE TEMPORARY TABLE",
+        )
+    } else {
+        (format!("DROP TABLE IF EXISTS \"{}\"", name), "CREATE TABLE")
+    };
+    sqlx::query(&drop).execute(&mut transaction).await?;
 
-    let validators = datatable
+    let types = datatable
         .columns
         .iter()
         .map(|column| {
-            column
-                .validator
-                .as_deref()
-                .and_then(|array_validator| array_validator.items_validator.as_deref())
+            let column_type = ColumnType::from_column(column);
+            let datatype = match column_type {
+                ColumnType::Boolean => "BOOLEAN",
+                ColumnType::Integer => "BIGINT",
+                ColumnType::Number => "DOUBLE PRECISION",
+                ColumnType::String => "TEXT",
+                ColumnType::Json => "JSON",
+            };
+            (column_type, datatype)
         })
         .collect_vec();
 
-    let bindings = validators
+    let columns = datatable
+        .columns
         .iter()
-        .enumerate()
-        .map(|(index, validator)| {
-            let datatype = match validator {
-                Some(ValidatorTypes::BooleanValidator(..)) => "BOOLEAN",
-                Some(ValidatorTypes::IntegerValidator(..)) => "INTEGER",
-                Some(ValidatorTypes::NumberValidator(..)) => "REAL",
-                Some(ValidatorTypes::StringValidator(..)) => "TEXT",
-                _ => "JSON",
-            };
-            format!("${}::{}[]", index + 1, datatype)
+        .zip(&types)
+        .map(|(column, (_, datatype))| {
+            format!("\"{}\" {}", column.name.replace('"', "-"), datatype)
         })
         .collect_vec()
         .join(", ");
-    let sql = format!(
-        "INSERT INTO \"{}\"\nSELECT * FROM UNNEST({})",
-        name, bindings
-    );
-
-    let mut query = sqlx::query(&sql);
-    for (index, validator) in validators.iter().enumerate() {
-        let values = datatable.columns[index].values.iter();
-        match validator {
-            Some(ValidatorTypes::BooleanValidator(..)) => {
-                let values = values
-                    .map(|node| match node {
-                        Node::Boolean(value) => Some(*value),
-                        _ => None,
-                    })
-                    .collect_vec();
-                query = query.bind(values);
-            }
-            Some(ValidatorTypes::IntegerValidator(..)) => {
-                let values = values
-                    .map(|node| match node {
-                        Node::Integer(value) => Some(*value),
-                        _ => None,
-                    })
-                    .collect_vec();
-                query = query.bind(values);
-            }
-            Some(ValidatorTypes::NumberValidator(..)) => {
-                let values = values
-                    .map(|node| match node {
-                        Node::Number(value) => Some(value.0),
-                        _ => None,
-                    })
-                    .collect_vec();
-                query = query.bind(values);
-            }
-            Some(ValidatorTypes::StringValidator(..)) => {
-                let values = values
-                    .map(|node| match node {
-                        Node::String(value) => Some(value.clone()),
-                        _ => None,
-                    })
-                    .collect_vec();
-                query = query.bind(values);
-            }
-            _ => {
-                let values = values
-                    .map(|node| serde_json::to_string(node).unwrap_or_default())
-                    .collect_vec();
-                query = query.bind(values);
+    sqlx::query(&format!("{} \"{}\"({});\n", create, name, columns))
+        .execute(&mut transaction)
+        .await?;
+
+    if rows > 0 && !types.is_empty() {
+        let bindings = types
+            .iter()
+            .enumerate()
+            .map(|(index, (_, datatype))| format!("${}::{}[]", index + 1, datatype))
+            .collect_vec()
+            .join(", ");
+        let sql = format!(
+            "INSERT INTO \"{}\"\nSELECT * FROM UNNEST({})",
+            name, bindings
+        );
+
+        let mut query = sqlx::query(&sql);
+        for (column, (column_type, ..)) in datatable.columns.iter().zip(&types) {
+            let values = column.values.iter();
+            match column_type {
+                ColumnType::Boolean => {
+                    let values = values
+                        .map(|node| match node {
+                            Node::Boolean(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    query = query.bind(values);
+                }
+                ColumnType::Integer => {
+                    let values = values
+                        .map(|node| match node {
+                            Node::Integer(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    query = query.bind(values);
+                }
+                ColumnType::Number => {
+                    let values = values
+                        .map(|node| match node {
+                            Node::Number(value) => Some(value.0),
+                            Node::Integer(value) => Some(*value as f64),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    query = query.bind(values);
+                }
+                ColumnType::String => {
+                    let values = values
+                        .map(|node| match node {
+                            Node::String(value) => Some(value.clone()),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    query = query.bind(values);
+                }
+                ColumnType::Json => {
+                    let values = values
+                        .map(|node| match node {
+                            Node::Null(..) => None,
+                            _ => Some(serde_json::to_string(node).unwrap_or_default()),
+                        })
+                        .collect_vec();
+                    query = query.bind(values);
+                }
             }
         }
+        query.execute(&mut transaction).await?;
     }
 
-    query.execute(pool).await?;
+    transaction.commit().await?;
 
     Ok(())
 }
@@ -325,7 +326,9 @@ pub async fn watch(
     .execute(pool)
     .await?;
 
-    let mut listener = PgListener::connect_with(pool).await?;
+    // Use a separate connection for the listener (rather than one from the pool) so
+    // that it does not hold on to the kernel's connection if the pool only has one
+    let mut listener = PgListener::connect(url).await?;
     listener.listen("stencila_resource_change").await?;
 
     let url = url.to_string();
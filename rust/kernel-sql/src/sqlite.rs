@@ -25,7 +25,7 @@ use kernel::{
     },
 };
 
-use crate::{WatchedTables, BINDING_REGEX};
+use crate::{datatable_rows, ColumnType, WatchedTables, BINDING_REGEX};
 
 /// Bind parameters to an SQL statement based on name
 fn bind<'lt>(sql: &str, parameters: &'lt HashMap<String, Node>) -> (String, SqliteArguments<'lt>) {
@@ -111,25 +111,34 @@ pub async fn query_to_datatable(
             let position = col_index * rows_len + row_index;
             let value = match col_type.as_str() {
                 "BOOLEAN" => row
-                    .try_get::<bool, usize>(col_index)
-                    .map(Node::Boolean)
-                    .ok(),
-                "INTEGER" => row.try_get::<i64, usize>(col_index).map(Node::Integer).ok(),
+                    .try_get::<Option<bool>, usize>(col_index)
+                    .ok()
+                    .flatten()
+                    .map(Node::Boolean),
+                "INTEGER" => row
+                    .try_get::<Option<i64>, usize>(col_index)
+                    .ok()
+                    .flatten()
+                    .map(Node::Integer),
                 "REAL" => row
-                    .try_get::<f64, usize>(col_index)
-                    .map(|num| Node::Number(Number(num)))
-                    .ok(),
+                    .try_get::<Option<f64>, usize>(col_index)
+                    .ok()
+                    .flatten()
+                    .map(|num| Node::Number(Number(num))),
                 "TEXT" => row
-                    .try_get::<String, usize>(col_index)
-                    .map(Node::String)
-                    .ok(),
+                    .try_get::<Option<String>, usize>(col_index)
+                    .ok()
+                    .flatten()
+                    .map(Node::String),
                 "DATETIME" => row
-                    .try_get::<String, usize>(col_index)
-                    .map(|date| Node::Date(Date::from(date)))
-                    .ok(),
+                    .try_get::<Option<String>, usize>(col_index)
+                    .ok()
+                    .flatten()
+                    .map(|date| Node::Date(Date::from(date))),
                 _ => row
-                    .try_get_unchecked::<String, usize>(col_index)
+                    .try_get_unchecked::<Option<String>, usize>(col_index)
                     .ok()
+                    .flatten()
                     .and_then(|json| serde_json::from_str(&json).ok()),
             };
             if let Some(value) = value {
@@ -159,69 +168,91 @@ pub async fn query_to_datatable(
     })
 }
 
+/// The maximum number of placeholders in a SQLite statement
+///
+/// This is the default value of `SQLITE_MAX_VARIABLE_NUMBER` for SQLite versions after 3.32.0.
+const MAX_PLACEHOLDERS: usize = 32_766;
+
 /// Create a SQLite table from a Stencila [`Datatable`]
+///
+/// The table is created, and rows inserted, within a transaction so that if there is
+/// an error any existing table with the same name is left unchanged.
 pub async fn table_from_datatable(
     name: &str,
     datatable: Datatable,
     pool: &SqlitePool,
+    temporary: bool,
 ) -> Result<()> {
-    sqlx::query(&format!("DROP TABLE IF EXISTS \"{}\"", name))
-        .execute(pool)
+    let name = name.replace('"', "-");
+    let rows = datatable_rows(&datatable)?;
+
+    let mut transaction = pool.begin().await?;
+
+    let (schema, create) = if temporary {
+        ("temp", "CREATE TEMPORARY TABLE")
+    } else {
+        ("main", "CREATE TABLE")
+    };
+    sqlx::query(&format!("DROP TABLE IF EXISTS {}.\"{}\"", schema, name))
+        .execute(&mut transaction)
         .await?;
 
+    let types = datatable
+        .columns
+        .iter()
+        .map(ColumnType::from_column)
+        .collect_vec();
     let columns = datatable
         .columns
         .iter()
-        .map(|column| {
-            let validator = column
-                .validator
-                .as_deref()
-                .and_then(|array_validator| array_validator.items_validator.clone());
-            let datatype = match validator.as_deref() {
-                Some(ValidatorTypes::BooleanValidator(..)) => "BOOLEAN",
-                Some(ValidatorTypes::IntegerValidator(..)) => "INTEGER",
-                Some(ValidatorTypes::NumberValidator(..)) => "REAL",
-                Some(ValidatorTypes::StringValidator(..)) => "TEXT",
-                _ => "JSON",
+        .zip(&types)
+        .map(|(column, column_type)| {
+            let datatype = match column_type {
+                ColumnType::Boolean => "BOOLEAN",
+                ColumnType::Integer => "INTEGER",
+                ColumnType::Number => "REAL",
+                ColumnType::String => "TEXT",
+                ColumnType::Json => "JSON",
             };
-            format!("{} {}", column.name, datatype)
+            format!("\"{}\" {}", column.name.replace('"', "-"), datatype)
         })
         .collect_vec()
         .join(", ");
-    sqlx::query(&format!("CREATE TABLE \"{}\"({});\n", name, columns))
-        .execute(pool)
+    sqlx::query(&format!("{} \"{}\"({});\n", create, name, columns))
+        .execute(&mut transaction)
         .await?;
 
-    let rows = datatable
-        .columns
-        .first()
-        .map(|column| column.values.len())
-        .unwrap_or(0);
-    if rows == 0 {
-        return Ok(());
-    }
-
     let cols = datatable.columns.len();
-
-    let mut sql = format!("INSERT INTO \"{}\" VALUES\n", name);
-    sql += &vec![format!(" ({})", vec!["?"; cols].join(", ")); rows].join(",\n");
-
-    let mut query = sqlx::query(&sql);
-    for row in 0..rows {
-        for col in 0..cols {
-            let column = &datatable.columns[col];
-            let node = &column.values[row];
-            match node {
-                Node::Null(..) => query = query.bind("null"),
-                Node::Boolean(value) => query = query.bind(value),
-                Node::Integer(value) => query = query.bind(value),
-                Node::Number(value) => query = query.bind(value.0),
-                Node::String(value) => query = query.bind(value),
-                _ => query = query.bind(serde_json::to_string(node).unwrap_or_default()),
+    if rows > 0 && cols > 0 {
+        let batch = (MAX_PLACEHOLDERS / cols).max(1);
+        for start in (0..rows).step_by(batch) {
+            let end = (start + batch).min(rows);
+
+            let mut sql = format!("INSERT INTO {}.\"{}\" VALUES\n", schema, name);
+            sql += &vec![format!(" ({})", vec!["?"; cols].join(", ")); end - start].join(",\n");
+
+            let mut query = sqlx::query(&sql);
+            for row in start..end {
+                for (column, column_type) in datatable.columns.iter().zip(&types) {
+                    let node = &column.values[row];
+                    match (column_type, node) {
+                        (_, Node::Null(..)) => query = query.bind(Option::<String>::None),
+                        (ColumnType::Json, _) => {
+                            query = query.bind(serde_json::to_string(node).unwrap_or_default())
+                        }
+                        (_, Node::Boolean(value)) => query = query.bind(value),
+                        (_, Node::Integer(value)) => query = query.bind(value),
+                        (_, Node::Number(value)) => query = query.bind(value.0),
+                        (_, Node::String(value)) => query = query.bind(value),
+                        _ => query = query.bind(serde_json::to_string(node).unwrap_or_default()),
+                    }
+                }
             }
+            query.execute(&mut transaction).await?;
         }
     }
-    query.execute(pool).await?;
+
+    transaction.commit().await?;
 
     Ok(())
 }
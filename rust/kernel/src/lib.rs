@@ -32,6 +32,8 @@ pub enum KernelType {
     Builtin,
     Micro,
     Jupyter,
+    #[strum(serialize = "jupyter-remote")]
+    JupyterRemote,
 }
 
 /// A specification for kernels
@@ -171,6 +173,10 @@ pub struct KernelSelector {
 
     /// A string that will match against the kernel `id`
     pub id: Option<String>,
+
+    /// A specification of the sandbox that the kernel should be run in (if any)
+    /// e.g. `cpu=10,memory=512`
+    pub sandbox: Option<String>,
 }
 
 impl fmt::Display for KernelSelector {
@@ -196,6 +202,10 @@ impl fmt::Display for KernelSelector {
             str.push_str(" id:");
             str.push_str(id);
         }
+        if let Some(sandbox) = &self.sandbox {
+            str.push_str(" sandbox:");
+            str.push_str(sandbox);
+        }
         write!(formatter, "{}", str.trim())
     }
 }
@@ -203,38 +213,65 @@ impl fmt::Display for KernelSelector {
 impl KernelSelector {
     /// Create a new `KernelSelector`
     pub fn from_lang_and_tags(lang: Option<&str>, tags: Option<&TagMap>) -> Self {
-        let (name, config) = if let Some(tags) = tags {
-            let name = tags.get_value("kernel");
+        let (name, r#type, config, sandbox) = if let Some(tags) = tags {
+            // The `@kernel` tag may be a name, or a selector e.g. `python type:jupyter-remote`
+            let (name, r#type, config, sandbox) = match tags.get_value("kernel") {
+                Some(kernel) => {
+                    let selector = Self::parse(&kernel);
+                    (
+                        selector.name.or(selector.any),
+                        selector.r#type,
+                        selector.config,
+                        selector.sandbox,
+                    )
+                }
+                None => (None, None, None, None),
+            };
             let config = match lang {
-                Some("SQL") => tags.get_value("db"),
-                _ => None,
+                Some("SQL") => config.or_else(|| tags.get_value("db")),
+                _ => config,
             };
-            (name, config)
+            // The `@sandbox` tag may have an empty value (use default sandbox) or
+            // a space or comma separated list of sandbox options e.g. `@sandbox cpu=10 memory=512`
+            let sandbox = sandbox.or_else(|| {
+                tags.get("sandbox")
+                    .map(|_| tags.get_items("sandbox").join(","))
+            });
+            (name, r#type, config, sandbox)
         } else {
-            (None, None)
+            (None, None, None, None)
         };
 
         Self {
             any: None,
             lang: lang.map(String::from),
-            r#type: None,
+            r#type,
             name,
             config,
             id: None,
+            sandbox,
         }
     }
 
     /// Parse a kernel selector string into a `KernelSelector`
+    ///
+    /// The `config` of the selector (e.g. a URL) may contain any non-whitespace characters.
+    /// The `sandbox` of the selector is a comma separated list of options e.g. `sandbox:cpu=10,memory=512`
+    /// (or `sandbox:on` to use the default sandbox options).
     pub fn parse(selector: &str) -> Self {
         static REGEX: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(\b(name|lang|type)\s*:\s*([\w-]+)\b)|([\w-]+)")
-                .expect("Unable to create regex")
+            Regex::new(
+                r"(\b(name|lang|type)\s*:\s*([\w-]+)\b)|(\b(config|sandbox)\s*:\s*(\S+))|([\w-]+)",
+            )
+            .expect("Unable to create regex")
         });
 
         let mut any = None;
         let mut name = None;
         let mut lang = None;
         let mut r#type = None;
+        let mut config = None;
+        let mut sandbox = None;
         for captures in REGEX.captures_iter(selector) {
             if let Some(tag) = captures.get(2) {
                 let value = Some(captures[3].to_string());
@@ -262,8 +299,27 @@ impl KernelSelector {
                     }
                     _ => (),
                 }
+            } else if let Some(tag) = captures.get(5) {
+                let value = Some(captures[6].to_string());
+                match tag.as_str() {
+                    "config" => {
+                        if config.is_none() {
+                            config = value
+                        } else {
+                            tracing::warn!("Ignoring additional kernel `config` selector");
+                        }
+                    }
+                    "sandbox" => {
+                        if sandbox.is_none() {
+                            sandbox = value
+                        } else {
+                            tracing::warn!("Ignoring additional kernel `sandbox` selector");
+                        }
+                    }
+                    _ => (),
+                }
             } else if any.is_none() {
-                any = Some(captures[4].to_string())
+                any = Some(captures[7].to_string())
             } else {
                 tracing::warn!(
                     "Ignoring extraneous kernel selector: {}",
@@ -277,8 +333,9 @@ impl KernelSelector {
             name,
             lang,
             r#type,
-            config: None,
+            config,
             id: None,
+            sandbox,
         }
     }
 
@@ -425,6 +482,12 @@ pub struct Task {
     /// The interrupter for the task (may be set after the task is started)
     #[serde(skip)]
     pub interrupter: Option<TaskInterrupter>,
+
+    /// The process id of the fork that the task is running in (if any)
+    ///
+    /// Used to kill the fork if the task does not end after being interrupted.
+    #[serde(skip)]
+    pub fork_pid: Option<u32>,
 }
 
 impl Task {
@@ -440,6 +503,7 @@ impl Task {
             result: None,
             sender,
             interrupter,
+            fork_pid: None,
         }
     }
 
@@ -623,6 +687,31 @@ pub trait KernelTrait {
     /// Must be implemented by [`KernelTrait`] implementations.
     async fn set(&mut self, name: &str, value: Node) -> Result<()>;
 
+    /// Does the kernel support transferring symbols using Apache Arrow?
+    ///
+    /// Should be overridden by [`KernelTrait`] implementations that implement
+    /// `get_arrow` and `set_arrow`.
+    async fn supports_arrow(&self) -> bool {
+        false
+    }
+
+    /// Write a symbol in the kernel to an Apache Arrow IPC file
+    ///
+    /// Used for the fast transfer of tabular data (e.g. data frames) between kernels.
+    /// Should return `false` if the symbol was not written (e.g. because it is not
+    /// tabular) in which case `get` will be used instead.
+    async fn get_arrow(&mut self, _name: &str, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Set a symbol in the kernel from an Apache Arrow IPC file
+    ///
+    /// Should return `false` if the symbol was not set, in which case `set` will
+    /// be used instead.
+    async fn set_arrow(&mut self, _name: &str, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Execute code in the kernel and get outputs and messages
     ///
     /// This is a convenience method when all you want to do is get [`Task`]
@@ -712,6 +801,21 @@ mod test {
         assert_eq!(ks.lang, some_string!("r"));
         assert_eq!(ks.r#type, some_string!("jupyter"));
         assert_eq!(ks.to_string(), "name:ir lang:r type:jupyter");
+
+        let ks =
+            KernelSelector::parse("python3 type:jupyter-remote config:http://host:8888?token=a-b");
+        assert_eq!(ks.any, some_string!("python3"));
+        assert_eq!(ks.r#type, some_string!("jupyter-remote"));
+        assert_eq!(ks.config, some_string!("http://host:8888?token=a-b"));
+        assert_eq!(
+            ks.to_string(),
+            "python3 type:jupyter-remote config:http://host:8888?token=a-b"
+        );
+
+        let ks = KernelSelector::parse("python sandbox:cpu=10,memory=512");
+        assert_eq!(ks.any, some_string!("python"));
+        assert_eq!(ks.sandbox, some_string!("cpu=10,memory=512"));
+        assert_eq!(ks.to_string(), "python sandbox:cpu=10,memory=512");
     }
 
     #[test]
@@ -740,5 +844,16 @@ mod test {
         assert!(!KernelSelector::parse("name:foo lang:quax").matches(&k));
         assert!(!KernelSelector::parse("name:foo lang:bar type:quax").matches(&k));
         assert!(!KernelSelector::parse("foo type:quax").matches(&k));
+
+        let k = Kernel::new(
+            "python3",
+            KernelType::JupyterRemote,
+            &["python"],
+            true,
+            true,
+            false,
+        );
+        assert!(KernelSelector::parse("python type:jupyter-remote").matches(&k));
+        assert!(!KernelSelector::parse("python type:jupyter").matches(&k));
     }
 }
@@ -6,7 +6,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use common::{once_cell::sync::Lazy, tokio::sync::RwLock};
+use common::{once_cell::sync::Lazy, tempfile, tokio::sync::RwLock};
 use graph_triples::{ResourceChange, ResourceInfo};
 #[allow(unused_imports)]
 use kernel::{
@@ -31,6 +31,20 @@ use kernel::{
 
 // Re-exports
 pub use kernel::{Kernel, KernelSelector, KernelType, Task, TaskResult};
+#[cfg(feature = "kernel-micro")]
+pub use kernel_micro::Sandbox;
+
+/// Parse a task timeout e.g. `30s`, `1m 30s`, or `90` (seconds)
+pub fn parse_timeout(timeout: &str) -> Result<Duration> {
+    let timeout = timeout.trim();
+    if let Ok(seconds) = timeout.parse::<f64>() {
+        if seconds.is_finite() && seconds > 0.0 {
+            return Ok(Duration::from_secs_f64(seconds));
+        }
+        bail!("Invalid timeout `{}`", timeout);
+    }
+    Ok(humantime::parse_duration(timeout)?)
+}
 
 /// A "meta" kernel to dispatch to different types of kernels
 ///
@@ -52,6 +66,9 @@ enum MetaKernel {
     #[cfg(feature = "kernel-jupyter")]
     Jupyter(kernel_jupyter::JupyterKernel),
 
+    #[cfg(feature = "kernel-jupyter")]
+    JupyterRemote(kernel_jupyter::JupyterRemoteKernel),
+
     #[cfg(feature = "kernel-sql")]
     Sql(kernel_sql::SqlKernel),
 }
@@ -82,24 +99,54 @@ impl MetaKernel {
             };
         }
 
+        // Micro kernels are the only kernels which can be run in a sandbox
+        #[cfg(feature = "kernel-micro")]
+        let sandbox = match &selector.sandbox {
+            Some(spec) => Some(kernel_micro::Sandbox::parse(spec)?),
+            None => None,
+        };
+
+        macro_rules! matches_micro_kernel {
+            ($feat:literal, $kernel:expr) => {
+                #[cfg(feature = $feat)]
+                {
+                    let mut kernel = $kernel;
+                    if selector.matches(&kernel.spec().await) && kernel.is_available().await {
+                        kernel.set_sandbox(sandbox.clone());
+                        return Ok(MetaKernel::Micro(kernel));
+                    }
+                }
+            };
+        }
+
         matches_kernel!(
             "kernel-calc",
             MetaKernel::Calc,
             kernel_calc::CalcKernel::new()
         );
 
-        matches_kernel!(
-            "kernel-sql",
-            MetaKernel::Sql,
-            kernel_sql::SqlKernel::new(selector, resource_changes_sender.clone())
-        );
+        if selector.sandbox.is_none() {
+            matches_kernel!(
+                "kernel-sql",
+                MetaKernel::Sql,
+                kernel_sql::SqlKernel::new(selector, resource_changes_sender.clone())
+            );
+        }
 
-        matches_kernel!("kernel-bash", MetaKernel::Micro, kernel_bash::new());
-        matches_kernel!("kernel-deno", MetaKernel::Micro, kernel_deno::new());
-        matches_kernel!("kernel-node", MetaKernel::Micro, kernel_node::new());
-        matches_kernel!("kernel-python", MetaKernel::Micro, kernel_python::new());
-        matches_kernel!("kernel-r", MetaKernel::Micro, kernel_r::new());
-        matches_kernel!("kernel-zsh", MetaKernel::Micro, kernel_zsh::new());
+        matches_micro_kernel!("kernel-bash", kernel_bash::new());
+        matches_micro_kernel!("kernel-deno", kernel_deno::new());
+        matches_micro_kernel!("kernel-julia", kernel_julia::new());
+        matches_micro_kernel!("kernel-node", kernel_node::new());
+        matches_micro_kernel!("kernel-python", kernel_python::new());
+        matches_micro_kernel!("kernel-r", kernel_r::new());
+        matches_micro_kernel!("kernel-zsh", kernel_zsh::new());
+
+        if selector.sandbox.is_some() {
+            bail!(
+                "Unable to create a sandboxed execution kernel for selector `{}`; only micro kernels can be sandboxed",
+                selector
+            )
+        }
 
         matches_kernel!(
             "kernel-jupyter",
@@ -107,6 +154,12 @@ impl MetaKernel {
             kernel_jupyter::JupyterKernel::new(selector).await
         );
 
+        matches_kernel!(
+            "kernel-jupyter",
+            MetaKernel::JupyterRemote,
+            kernel_jupyter::JupyterRemoteKernel::new(selector)
+        );
+
         bail!(
             "Unable to create an execution kernel for selector `{}`",
             selector
@@ -127,6 +180,8 @@ macro_rules! dispatch_variants {
             MetaKernel::Micro(kernel) => kernel.$method($($arg),*),
             #[cfg(feature = "kernel-jupyter")]
             MetaKernel::Jupyter(kernel) => kernel.$method($($arg),*),
+            #[cfg(feature = "kernel-jupyter")]
+            MetaKernel::JupyterRemote(kernel) => kernel.$method($($arg),*),
         }
     };
 }
@@ -169,6 +224,18 @@ impl KernelTrait for MetaKernel {
         dispatch_variants!(self, set, name, value).await
     }
 
+    async fn supports_arrow(&self) -> bool {
+        dispatch_variants!(self, supports_arrow).await
+    }
+
+    async fn get_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        dispatch_variants!(self, get_arrow, name, path).await
+    }
+
+    async fn set_arrow(&mut self, name: &str, path: &Path) -> Result<bool> {
+        dispatch_variants!(self, set_arrow, name, path).await
+    }
+
     async fn exec(
         &mut self,
         code: &str,
@@ -212,6 +279,51 @@ impl KernelMap {
             .ok_or_else(|| eyre!("Unknown kernel `{}`", kernel_id))
     }
 
+    /// Mirror a symbol from one kernel to another
+    ///
+    /// Attempts to transfer the symbol via an Apache Arrow IPC file (much faster than JSON for
+    /// large tables) if both kernels support Arrow, falling back to `get`ting the symbol as a
+    /// `Node` from one kernel and `set`ting it in the other. Arrow is not used for sandboxed
+    /// kernels because they do not share the temporary directory with other kernels.
+    async fn mirror(&mut self, name: &str, from: &str, to: &str) -> Result<()> {
+        let sandboxed = [from, to].iter().any(|kernel_id| {
+            matches!((**self).get(*kernel_id), Some((selector, ..)) if selector.sandbox.is_some())
+        });
+        if !sandboxed
+            && self.get(from)?.supports_arrow().await
+            && self.get(to)?.supports_arrow().await
+        {
+            match self.mirror_arrow(name, from, to).await {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(error) => tracing::debug!(
+                    "While mirroring symbol `{}` using Arrow, falling back to JSON: {}",
+                    name,
+                    error
+                ),
+            }
+        }
+
+        let value = self.get_mut(from)?.get(name).await?;
+        self.get_mut(to)?.set(name, value).await
+    }
+
+    /// Mirror a symbol from one kernel to another via an Apache Arrow IPC file
+    ///
+    /// Returns `false` if the symbol was not transferred (e.g. because it is not tabular).
+    async fn mirror_arrow(&mut self, name: &str, from: &str, to: &str) -> Result<bool> {
+        let file = tempfile::Builder::new()
+            .prefix("stencila-")
+            .suffix(".arrow")
+            .tempfile()?;
+        let path = file.path();
+
+        if !self.get_mut(from)?.get_arrow(name, path).await? {
+            return Ok(false);
+        }
+        self.get_mut(to)?.set_arrow(name, path).await
+    }
+
     /// Ensure that a kernel exists for a selector
     ///
     /// Returns the kernel's id.
@@ -235,6 +347,10 @@ impl KernelMap {
             else if desired_selector.config.is_some() {
                 matched = desired_selector == existing_selector;
             }
+            // Never use a kernel that is not in the desired sandbox (or is in a different sandbox)
+            else if desired_selector.sandbox != existing_selector.sandbox {
+                matched = false;
+            }
             // Finally, if the selector does not specify `id` or `config` then match lang etc
             // against the spec
             else if desired_selector.matches(&kernel.spec().await) {
@@ -774,6 +890,13 @@ pub struct KernelSpace {
 
     /// The monitoring task for the kernel space
     monitoring: Option<JoinHandle<()>>,
+
+    /// The sandbox that all kernels in the kernel space are run in (if any)
+    ///
+    /// Used when executing documents from untrusted sources. Takes precedence over
+    /// the sandbox specified in any kernel selector so that code within a document
+    /// can not loosen the sandbox that it is executed in.
+    sandbox: Option<String>,
 }
 
 impl Drop for KernelSpace {
@@ -808,6 +931,27 @@ impl KernelSpace {
         kernel_space
     }
 
+    /// Set the sandbox that all kernels in the kernel space are run in
+    ///
+    /// Only applies to kernels that are started after it is set.
+    #[cfg(feature = "kernel-micro")]
+    pub fn set_sandbox(&mut self, sandbox: Option<&str>) -> Result<()> {
+        if let Some(spec) = sandbox {
+            kernel_micro::Sandbox::parse(spec)?;
+        }
+        self.sandbox = sandbox.map(String::from);
+        Ok(())
+    }
+
+    /// Apply the sandbox of the kernel space (if any) to a kernel selector
+    fn sandboxed(&self, selector: &KernelSelector) -> KernelSelector {
+        let mut selector = selector.clone();
+        if self.sandbox.is_some() {
+            selector.sandbox = self.sandbox.clone();
+        }
+        selector
+    }
+
     /// Monitor the kernels space
     ///
     /// Monitors the health of kernels and cleans up the task list to
@@ -838,7 +982,11 @@ impl KernelSpace {
     pub async fn start(&self, selector: &KernelSelector) -> Result<KernelId> {
         let kernels = &mut *self.kernels.lock().await;
         kernels
-            .start(selector, &self.directory, &self.resource_changes_sender)
+            .start(
+                &self.sandboxed(selector),
+                &self.directory,
+                &self.resource_changes_sender,
+            )
             .await
     }
 
@@ -866,8 +1014,12 @@ impl KernelSpace {
         for id in &ids {
             let kernel = kernels.get(id)?;
             let spec = kernel.spec().await;
+            let sandbox = (**kernels)
+                .get(id)
+                .and_then(|(selector, ..)| selector.sandbox.clone());
             let selector = KernelSelector {
                 name: Some(spec.name),
+                sandbox,
                 ..Default::default()
             };
 
@@ -898,7 +1050,11 @@ impl KernelSpace {
         let kernels = &mut *self.kernels.lock().await;
 
         let kernel_id = kernels
-            .ensure(selector, &self.directory, &self.resource_changes_sender)
+            .ensure(
+                &self.sandboxed(selector),
+                &self.directory,
+                &self.resource_changes_sender,
+            )
             .await?;
         tracing::debug!("Setting symbol `{}` in kernel `{}`", name, kernel_id);
 
@@ -932,7 +1088,11 @@ impl KernelSpace {
 
         // Determine the kernel to execute in
         let kernel_id = kernels
-            .ensure(selector, &self.directory, &self.resource_changes_sender)
+            .ensure(
+                &self.sandboxed(selector),
+                &self.directory,
+                &self.resource_changes_sender,
+            )
             .await?;
         tracing::trace!("Dispatching task to kernel `{}`", kernel_id);
 
@@ -965,11 +1125,7 @@ impl KernelSpace {
                 kernel_id
             );
 
-            let home_kernel = kernels.get_mut(&symbol.home)?;
-            let value = home_kernel.get(name).await?;
-
-            let mirror_kernel = kernels.get_mut(&kernel_id)?;
-            mirror_kernel.set(name, value).await?;
+            kernels.mirror(name, &symbol.home, &kernel_id).await?;
 
             symbol
                 .mirrored
@@ -1016,6 +1172,120 @@ impl KernelSpace {
         Ok(task_info)
     }
 
+    /// Wait for the result of a task, giving up after a timeout
+    ///
+    /// If the task does not finish within the `timeout` it is interrupted. If it has
+    /// not ended within a grace period after that, the kernel that it is running in is
+    /// restarted (i.e. killed and started again; symbols previously assigned in it will be lost),
+    /// or if it is running in a fork, the fork is killed. In either case, a `CodeError` is
+    /// added to the result and the task is marked as finished (rather than interrupted) so
+    /// that the node it was for is marked as failed.
+    ///
+    /// Takes the kernel space lock, rather than `&self`, so that the kernel space is only
+    /// locked while the kernel is being restarted, and not while waiting for the task.
+    pub async fn result_within(
+        kernel_space: &RwLock<KernelSpace>,
+        task_info: &mut TaskInfo,
+        timeout: Duration,
+    ) -> Result<TaskResult> {
+        const GRACE: Duration = Duration::from_secs(5);
+
+        // Synchronous, or already ended, tasks have a result already
+        let mut receiver = match (task_info.task.is_ended(), task_info.task.subscribe()) {
+            (false, Ok(receiver)) => receiver,
+            _ => return task_info.result().await,
+        };
+
+        if let Ok(result) = tokio::time::timeout(timeout, receiver.recv()).await {
+            let result = result
+                .map_err(|_| eyre!("Result sender for task `{}` dropped", task_info.task.id))?;
+            task_info.task.end(result.clone());
+            return Ok(result);
+        }
+
+        let task_id = task_info.task.id.clone();
+        let timeout = humantime::format_duration(timeout);
+        tracing::warn!("Task `{}` timed out after {}", task_id, timeout);
+
+        // Attempt to interrupt the task and wait for it to end
+        let mut result = None;
+        if let Some(interrupter) = &task_info.task.interrupter {
+            tracing::debug!("Interrupting task `{}`", task_id);
+            if interrupter.send(()).await.is_ok() {
+                if let Ok(Ok(interrupted)) = tokio::time::timeout(GRACE, receiver.recv()).await {
+                    result = Some(interrupted);
+                }
+            }
+        }
+
+        // If the task did not end (or could not be interrupted) then restart the kernel,
+        // or kill the fork
+        let mut restarted = false;
+        #[cfg_attr(not(feature = "kernel-micro"), allow(unused_mut))]
+        let mut killed = false;
+        if result.is_none() {
+            match (
+                &task_info.kernel_id,
+                task_info.is_fork,
+                task_info.task.fork_pid,
+            ) {
+                (Some(kernel_id), false, ..) => {
+                    tracing::warn!(
+                        "Task `{}` did not end after interrupt; restarting kernel `{}`",
+                        task_id,
+                        kernel_id
+                    );
+                    let restart = kernel_space
+                        .read()
+                        .await
+                        .restart(Some(kernel_id.clone()))
+                        .await;
+                    match restart {
+                        Ok(..) => restarted = true,
+                        Err(error) => {
+                            tracing::error!("While restarting kernel `{}`: {}", kernel_id, error)
+                        }
+                    }
+                }
+                #[cfg(feature = "kernel-micro")]
+                (.., true, Some(pid)) => {
+                    tracing::warn!(
+                        "Task `{}` did not end after interrupt; killing fork with pid `{}`",
+                        task_id,
+                        pid
+                    );
+                    kernel_micro::MicroKernelSignaller::from_pid(pid).kill();
+                    killed = true;
+                }
+                _ => tracing::warn!("Task `{}` did not end after interrupt", task_id),
+            }
+        }
+
+        // Replace any interrupt message with a timeout error
+        let mut result = result.unwrap_or_else(|| TaskResult::new(Vec::new(), Vec::new()));
+        result.messages.retain(|message| {
+            message.error_type.as_deref().map(String::as_str) != Some("Interrupt")
+        });
+        result.messages.push(CodeError {
+            error_type: Some(Box::new("Timeout".to_string())),
+            error_message: format!(
+                "Execution timed out after {}{}",
+                timeout,
+                if restarted {
+                    " and the kernel was restarted"
+                } else if killed {
+                    " and the fork was killed"
+                } else {
+                    ""
+                }
+            ),
+            ..Default::default()
+        });
+        task_info.task.end(result.clone());
+
+        Ok(result)
+    }
+
     /// Store a task (either one that has been dispatched or is deferred)
     ///
     /// If the task is async, subscribe to it so that it's result can be updated when it
@@ -1321,6 +1591,7 @@ pub async fn available() -> Vec<Kernel> {
     }
     microkernel_available!("kernel-bash", kernel_bash, available);
     microkernel_available!("kernel-deno", kernel_deno, available);
+    microkernel_available!("kernel-julia", kernel_julia, available);
     microkernel_available!("kernel-node", kernel_node, available);
     microkernel_available!("kernel-python", kernel_python, available);
     microkernel_available!("kernel-r", kernel_r, available);
@@ -1333,6 +1604,16 @@ pub async fn available() -> Vec<Kernel> {
             .unwrap_or_default(),
     );
 
+    #[cfg(feature = "kernel-jupyter")]
+    available.append(
+        &mut kernel_jupyter::JupyterRemoteKernel::available()
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!("While listing remote Jupyter kernels: {}", error);
+                Vec::new()
+            }),
+    );
+
     *AVAILABLE_LIST.write().await = available.clone();
     *AVAILABLE_UPDATED.write().await = Instant::now();
 
@@ -1817,3 +2098,270 @@ pub mod commands {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graph_triples::resources;
+
+    use super::*;
+
+    #[test]
+    fn timeouts() -> Result<()> {
+        assert_eq!(parse_timeout("90")?, Duration::from_secs(90));
+        assert_eq!(parse_timeout(" 1.5 ")?, Duration::from_millis(1500));
+        assert_eq!(parse_timeout("30s")?, Duration::from_secs(30));
+        assert_eq!(parse_timeout("1m 30s")?, Duration::from_secs(90));
+        assert_eq!(parse_timeout("2h")?, Duration::from_secs(7200));
+
+        assert!(parse_timeout("0").is_err());
+        assert!(parse_timeout("-1").is_err());
+        assert!(parse_timeout("inf").is_err());
+        assert!(parse_timeout("soon").is_err());
+
+        Ok(())
+    }
+
+    /// Create a `TaskInfo` for a task that is not associated with a kernel
+    fn new_task_info(task: Task, is_fork: bool) -> TaskInfo {
+        let resource = resources::code(Path::new(""), "cc-1", "CodeChunk", None);
+        TaskInfo {
+            num: 1,
+            code: String::new(),
+            resource_info: ResourceInfo::default(resource),
+            kernel_id: None,
+            is_fork,
+            is_async: task.is_async(),
+            is_interruptable: task.is_interruptable(),
+            task,
+        }
+    }
+
+    /// Get the error types of the messages in a task result
+    fn error_types(result: &TaskResult) -> Vec<String> {
+        result
+            .messages
+            .iter()
+            .filter_map(|message| message.error_type.as_deref().cloned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn result_within() -> Result<()> {
+        let kernel_space = RwLock::new(KernelSpace::default());
+
+        // A task that finishes within the timeout
+        let (sender, ..) = broadcast::channel(1);
+        let mut task_info = new_task_info(Task::begin(Some(sender.clone()), None), false);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sender
+                .send(TaskResult::new(vec![Node::Integer(42)], Vec::new()))
+                .ok();
+        });
+        let result =
+            KernelSpace::result_within(&kernel_space, &mut task_info, Duration::from_secs(10))
+                .await?;
+        assert_eq!(result.outputs, vec![Node::Integer(42)]);
+        assert!(result.messages.is_empty());
+        assert!(task_info.task.finished.is_some());
+
+        // A task that times out and ends when interrupted: the interrupt message
+        // is replaced by a timeout error
+        let (sender, ..) = broadcast::channel(1);
+        let (interrupter, mut interrupt_receiver) = mpsc::channel(1);
+        let mut task_info =
+            new_task_info(Task::begin(Some(sender.clone()), Some(interrupter)), false);
+        tokio::spawn(async move {
+            if interrupt_receiver.recv().await.is_some() {
+                let interrupted = CodeError {
+                    error_type: Some(Box::new("Interrupt".to_string())),
+                    error_message: "Interrupted".to_string(),
+                    ..Default::default()
+                };
+                sender
+                    .send(TaskResult::new(Vec::new(), vec![interrupted]))
+                    .ok();
+            }
+        });
+        let result =
+            KernelSpace::result_within(&kernel_space, &mut task_info, Duration::from_millis(50))
+                .await?;
+        assert_eq!(error_types(&result), vec!["Timeout"]);
+        assert_eq!(
+            result.messages[0].error_message,
+            "Execution timed out after 50ms"
+        );
+        assert!(task_info.task.finished.is_some());
+        assert!(task_info.task.interrupted.is_none());
+
+        // A task that times out and can not be interrupted
+        let (sender, ..) = broadcast::channel(1);
+        let mut task_info = new_task_info(Task::begin(Some(sender), None), false);
+        let result =
+            KernelSpace::result_within(&kernel_space, &mut task_info, Duration::from_millis(50))
+                .await?;
+        assert_eq!(error_types(&result), vec!["Timeout"]);
+        assert!(task_info.task.finished.is_some());
+
+        Ok(())
+    }
+
+    /// Test that a fork which does not end after being interrupted is killed
+    #[cfg(all(feature = "kernel-micro", unix))]
+    #[tokio::test]
+    async fn result_within_kills_fork() -> Result<()> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let kernel_space = RwLock::new(KernelSpace::default());
+
+        // Use a process that ignores interrupts as a stand in for a hung fork
+        let mut fork = tokio::process::Command::new("sh")
+            .args(["-c", "trap '' INT; sleep 60"])
+            .spawn()?;
+
+        let (sender, ..) = broadcast::channel(1);
+        let (interrupter, mut interrupt_receiver) = mpsc::channel(1);
+        let mut task = Task::begin(Some(sender), Some(interrupter));
+        task.fork_pid = fork.id();
+        let mut task_info = new_task_info(task, true);
+        tokio::spawn(async move { while interrupt_receiver.recv().await.is_some() {} });
+
+        let result =
+            KernelSpace::result_within(&kernel_space, &mut task_info, Duration::from_millis(50))
+                .await?;
+        assert_eq!(error_types(&result), vec!["Timeout"]);
+        assert_eq!(
+            result.messages[0].error_message,
+            "Execution timed out after 50ms and the fork was killed"
+        );
+
+        let status = tokio::time::timeout(Duration::from_secs(5), fork.wait()).await??;
+        assert_eq!(
+            status.signal(),
+            Some(9),
+            "Should have been killed with SIGKILL"
+        );
+
+        Ok(())
+    }
+
+    /// Test mirroring data frames between Python and R kernels using Arrow
+    ///
+    /// Skipped if Python or R, or their Arrow packages (`pyarrow` and `arrow`), are not available.
+    #[cfg(all(feature = "kernel-python", feature = "kernel-r"))]
+    #[tokio::test]
+    async fn mirror_arrow_python_r() -> Result<()> {
+        let directory = tempfile::tempdir()?;
+        let mut kernels = KernelMap::default();
+
+        let python = kernels
+            .ensure(&KernelSelector::parse("python"), directory.path(), &None)
+            .await;
+        let r = kernels
+            .ensure(&KernelSelector::parse("r"), directory.path(), &None)
+            .await;
+        let (python, r) = match (python, r) {
+            (Ok(python), Ok(r)) => (python, r),
+            _ => {
+                eprintln!("Python or R not available on this machine");
+                return Ok(());
+            }
+        };
+
+        let (.., python_messages) = kernels
+            .get_mut(&python)?
+            .exec("import pyarrow, pandas", None)
+            .await?;
+        let (r_outputs, ..) = kernels
+            .get_mut(&r)?
+            .exec("requireNamespace('arrow', quietly = TRUE)", None)
+            .await?;
+        if !python_messages.is_empty() || r_outputs.first() != Some(&Node::Boolean(true)) {
+            eprintln!("Arrow packages for Python or R not available on this machine");
+            return Ok(());
+        }
+
+        // Python to R
+        let (.., messages) = kernels
+            .get_mut(&python)?
+            .exec(
+                r#"import datetime
+df1 = pandas.DataFrame({
+    "a": [1, 2, 3],
+    "b": [0.5, 1.5, 2.5],
+    "c": ["x", "y", "z"],
+    "d": [datetime.date(2020, 1, day) for day in (1, 2, 3)]
+})"#,
+                None,
+            )
+            .await?;
+        assert!(messages.is_empty(), "{:?}", messages);
+
+        assert!(kernels.mirror_arrow("df1", &python, &r).await?);
+        let (outputs, messages) = kernels
+            .get_mut(&r)?
+            .exec(
+                "c(nrow(df1), unname(sapply(df1, function(column) class(column)[1])))",
+                None,
+            )
+            .await?;
+        assert!(messages.is_empty(), "{:?}", messages);
+        assert_eq!(
+            serde_json::to_value(&outputs[0])?,
+            serde_json::json!(["3", "integer", "numeric", "character", "Date"])
+        );
+
+        // R to Python
+        let (.., messages) = kernels
+            .get_mut(&r)?
+            .exec(
+                "df2 <- data.frame(e = as.Date('2021-06-01') + 0:1, f = c(TRUE, FALSE))",
+                None,
+            )
+            .await?;
+        assert!(messages.is_empty(), "{:?}", messages);
+
+        assert!(kernels.mirror_arrow("df2", &r, &python).await?);
+        let (outputs, messages) = kernels
+            .get_mut(&python)?
+            .exec("[len(df2), str(df2.e[0]), str(df2.f.dtype)]", None)
+            .await?;
+        assert!(messages.is_empty(), "{:?}", messages);
+        assert_eq!(
+            serde_json::to_value(&outputs[0])?,
+            serde_json::json!([2, "2021-06-01", "bool"])
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "kernel-micro")]
+    #[test]
+    fn sandboxed() -> Result<()> {
+        let selector = KernelSelector::parse("python sandbox:network=on");
+
+        let mut kernel_space = KernelSpace::default();
+        assert_eq!(
+            kernel_space.sandboxed(&selector).sandbox.as_deref(),
+            Some("network=on")
+        );
+
+        // The sandbox of the kernel space takes precedence over that of the selector
+        kernel_space.set_sandbox(Some("cpu=10"))?;
+        assert_eq!(
+            kernel_space.sandboxed(&selector).sandbox.as_deref(),
+            Some("cpu=10")
+        );
+        assert_eq!(
+            kernel_space
+                .sandboxed(&KernelSelector::parse("python"))
+                .sandbox
+                .as_deref(),
+            Some("cpu=10")
+        );
+
+        assert!(kernel_space.set_sandbox(Some("network=maybe")).is_err());
+
+        Ok(())
+    }
+}
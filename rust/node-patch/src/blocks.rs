@@ -101,7 +101,13 @@ patchable_variants!(
 // Make `CodeError`s replaceable to avoid large patches associated with
 // changes in stack trace and to simplify Web Component development (no
 // need to observe for change in error level or presence/absence of stacktrace)
-replaceable_struct!(CodeError, error_message, error_type, stack_trace);
+replaceable_struct!(
+    CodeError,
+    error_message,
+    error_type,
+    stack_trace,
+    code_location
+);
 
 patchable_variants!(
     CodeExecutableCodeDependencies,
@@ -172,6 +172,7 @@ replaceable_struct!(
     citation_prefix,
     citation_suffix,
     content,
+    errors,
     page_end,
     page_start,
     pagination
@@ -0,0 +1,379 @@
+//! Inversion of patches
+//!
+//! The inverse of a patch reverts the changes that the patch makes to a node. `Add` and `Move`
+//! operations (and `Transform` operations, which do not lose content) can be inverted from the
+//! operation alone. `Remove` and `Replace` operations need the values that they removed, which
+//! are obtained from the node before the operation was applied (the "pre-image").
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use common::{
+    eyre::{bail, Result},
+    serde::Serialize,
+    serde_json,
+};
+use node_address::{Address, Slot};
+use node_pointer::{resolve, Pointable, Pointer};
+
+use crate::{Operation, Patch, Patchable};
+
+impl Patch {
+    /// Generate the inverse of the patch
+    ///
+    /// Given `node`, the node *before* this patch is applied to it, returns a patch that
+    /// reverts the changes made by this patch i.e. such that applying this patch, and
+    /// then its inverse, leaves the node unchanged.
+    ///
+    /// The inverse has the same `address` and `target` as this patch. Values in its
+    /// `Add` and `Replace` operations are JSON values (as they are for patches from clients).
+    pub fn invert<Type>(&self, node: &Type) -> Result<Patch>
+    where
+        Type: Patchable + Pointable + Clone + Serialize,
+    {
+        // Pre-images are only needed if there are `Remove` or `Replace` operations
+        let ops = match self.ops.iter().rposition(needs_pre_image) {
+            None => self
+                .ops
+                .iter()
+                .rev()
+                .map(|op| op.invert(None))
+                .collect::<Result<Vec<Operation>>>()?,
+            Some(last) => {
+                if self.address.is_some() || self.target.is_some() {
+                    match resolve(node, self.address.clone(), self.target.clone())? {
+                        Pointer::Inline(inline) => self.invert_ops(inline.clone(), last)?,
+                        Pointer::Block(block) => self.invert_ops(block.clone(), last)?,
+                        Pointer::Node(node) => self.invert_ops(node.clone(), last)?,
+                        _ => bail!("Pointer points to unhandled node type"),
+                    }
+                } else {
+                    self.invert_ops(node.clone(), last)?
+                }
+            }
+        };
+
+        Ok(Patch {
+            ops,
+            address: self.address.clone(),
+            target: self.target.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Invert the operations of this patch given a copy of the node that they are applied to
+    ///
+    /// Operations, up to and including the one at index `last` (the last one that
+    /// needs a pre-image), are applied, one by one, to the copy so that the pre-image of
+    /// each operation can be obtained. Returns the inverted operations in reverse order.
+    fn invert_ops<Type>(&self, mut node: Type, last: usize) -> Result<Vec<Operation>>
+    where
+        Type: Patchable + Serialize,
+    {
+        let mut ops = Vec::with_capacity(self.ops.len());
+        for (index, op) in self.ops.iter().enumerate() {
+            let pre_image = match needs_pre_image(op) {
+                true => Some(serde_json::to_value(&node)?),
+                false => None,
+            };
+            ops.push(op.invert(pre_image.as_ref())?);
+            if index < last {
+                node.apply_op(op)?;
+            }
+        }
+        ops.reverse();
+        Ok(ops)
+    }
+}
+
+/// Does an operation need the pre-image of the node it is applied to in order to be inverted?
+fn needs_pre_image(op: &Operation) -> bool {
+    matches!(op, Operation::Remove { .. } | Operation::Replace { .. })
+}
+
+impl Operation {
+    /// Generate the inverse of the operation
+    ///
+    /// The `pre_image` is the JSON value of the node before the operation is applied
+    /// and is required for `Remove` and `Replace` operations.
+    pub fn invert(&self, pre_image: Option<&serde_json::Value>) -> Result<Operation> {
+        let values = |address: &Address, items: usize| match pre_image {
+            Some(json) => json_values(json, address, items),
+            None => bail!("The pre-image is required to invert operation `{}`", self),
+        };
+
+        let op = match self {
+            Operation::Add {
+                address, length, ..
+            } => Operation::Remove {
+                address: address.clone(),
+                items: *length,
+            },
+            Operation::Remove { address, items } => Operation::Add {
+                address: address.clone(),
+                value: Box::new(values(address, *items)?),
+                length: *items,
+                html: None,
+            },
+            Operation::Replace {
+                address,
+                items,
+                length,
+                ..
+            } => Operation::Replace {
+                address: address.clone(),
+                items: *length,
+                value: Box::new(values(address, *items)?),
+                length: *items,
+                html: None,
+            },
+            Operation::Move { from, items, to } => {
+                let (from_index, to_index) = match (from.back(), to.back()) {
+                    (Some(Slot::Index(from)), Some(Slot::Index(to))) => (*from, *to),
+                    _ => bail!("Addresses of a `Move` operation should end with an index"),
+                };
+                // See `apply_move` for `Vec`s for how `to` is interpreted
+                let (inverse_from, inverse_to) = if from_index < to_index {
+                    (to_index - items, from_index)
+                } else {
+                    (to_index, from_index + items)
+                };
+                let mut from = from.clone();
+                let mut to = to.clone();
+                from.pop_back();
+                from.push_back(Slot::Index(inverse_from));
+                to.pop_back();
+                to.push_back(Slot::Index(inverse_to));
+                Operation::Move {
+                    from,
+                    items: *items,
+                    to,
+                }
+            }
+            Operation::Transform { address, from, to } => Operation::Transform {
+                address: address.clone(),
+                from: to.clone(),
+                to: from.clone(),
+            },
+        };
+        Ok(op)
+    }
+}
+
+/// Get the JSON value(s) at an address within a JSON value
+///
+/// If the address ends in an index, then `items` items of an array, or graphemes
+/// of a string, are returned.
+fn json_values(
+    json: &serde_json::Value,
+    address: &Address,
+    items: usize,
+) -> Result<serde_json::Value> {
+    // Serializing the address converts `Name` slots to camelCase to match the JSON
+    let slots = match serde_json::to_value(address)? {
+        serde_json::Value::Array(slots) => slots,
+        _ => bail!("Expected address to serialize to an array"),
+    };
+
+    let mut value = json;
+    for (position, slot) in slots.iter().enumerate() {
+        let last = position == slots.len() - 1;
+        value = match (slot, value) {
+            (serde_json::Value::String(name), serde_json::Value::Object(object)) => {
+                match object.get(name) {
+                    Some(value) => value,
+                    // A property that is absent (i.e. `None`) is represented as `null`
+                    None if last => return Ok(serde_json::Value::Null),
+                    None => bail!("Property `{}` not found in pre-image", name),
+                }
+            }
+            (serde_json::Value::Number(index), serde_json::Value::Array(array)) => {
+                let index = index.as_u64().unwrap_or_default() as usize;
+                if last {
+                    return match array.get(index..(index + items)) {
+                        Some(values) => Ok(serde_json::Value::Array(values.to_vec())),
+                        None => bail!(
+                            "Items `{}..{}` not found in pre-image",
+                            index,
+                            index + items
+                        ),
+                    };
+                }
+                match array.get(index) {
+                    Some(value) => value,
+                    None => bail!("Item `{}` not found in pre-image", index),
+                }
+            }
+            (serde_json::Value::Number(index), serde_json::Value::String(string)) if last => {
+                let index = index.as_u64().unwrap_or_default() as usize;
+                let graphemes: String = string.graphemes(true).skip(index).take(items).collect();
+                return Ok(serde_json::Value::String(graphemes));
+            }
+            _ => bail!("Slot `{}` not found in pre-image", slot),
+        }
+    }
+    Ok(value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apply, diff};
+    use stencila_schema::{
+        Article, BlockContent, CodeChunk, Emphasis, InlineContent, Node, Paragraph,
+    };
+    use test_utils::{assert_json_eq, assert_json_is};
+
+    fn para(content: Vec<InlineContent>) -> BlockContent {
+        BlockContent::Paragraph(Paragraph {
+            content,
+            ..Default::default()
+        })
+    }
+
+    fn text(text: &str) -> InlineContent {
+        InlineContent::String(text.to_string())
+    }
+
+    #[test]
+    fn test_invert() -> Result<()> {
+        let a = Node::Article(Article {
+            content: Some(vec![
+                para(vec![text("One two three")]),
+                para(vec![
+                    text("Four "),
+                    InlineContent::Emphasis(Emphasis {
+                        content: vec![text("five")],
+                        ..Default::default()
+                    }),
+                ]),
+                BlockContent::CodeChunk(CodeChunk {
+                    text: "a = 1".to_string(),
+                    programming_language: "python".to_string(),
+                    ..Default::default()
+                }),
+            ]),
+            ..Default::default()
+        });
+        let b = Node::Article(Article {
+            content: Some(vec![
+                para(vec![
+                    text("Four "),
+                    InlineContent::Strong(Default::default()),
+                ]),
+                para(vec![text("One too")]),
+                BlockContent::CodeChunk(CodeChunk {
+                    text: "a = 2".to_string(),
+                    programming_language: "python".to_string(),
+                    label: Some(Box::new("Chunk".to_string())),
+                    ..Default::default()
+                }),
+            ]),
+            ..Default::default()
+        });
+
+        // For a variety of patches, applying the patch and then its inverse
+        // should return the original node
+        for (before, after) in [(&a, &b), (&b, &a)] {
+            let patch = diff(before, after);
+            let inverse = patch.invert(before)?;
+
+            let mut node = before.clone();
+            apply(&mut node, &patch)?;
+            assert_json_eq!(node, after);
+            apply(&mut node, &inverse)?;
+            assert_json_eq!(node, before);
+        }
+
+        // For a patch with an address, only the addressed node is used to
+        // obtain pre-images
+        let before = para(vec![text("One two three")]);
+        let after = para(vec![
+            text("One "),
+            InlineContent::Strong(Default::default()),
+        ]);
+        let mut patch = diff(&before, &after);
+        patch.address = Some(Address::from("content").add_index(0));
+        let inverse = patch.invert(&a)?;
+        assert_eq!(inverse.address, patch.address);
+
+        let mut node = a.clone();
+        apply(&mut node, &patch)?;
+        assert_ne!(serde_json::to_value(&node)?, serde_json::to_value(&a)?);
+        apply(&mut node, &inverse)?;
+        assert_json_eq!(node, a);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invert_ops() -> Result<()> {
+        let json = serde_json::json!({
+            "content": [{"type": "Paragraph", "content": ["Hello world"]}],
+            "label": "A label"
+        });
+
+        // Removed graphemes are added back
+        let op = Operation::Remove {
+            address: Address::from("content")
+                .add_index(0)
+                .add_name("content")
+                .add_index(0)
+                .add_index(6),
+            items: 5,
+        };
+        assert_json_is!(
+            op.invert(Some(&json))?,
+            {
+                "type": "Add",
+                "address": ["content", 0, "content", 0, 6],
+                "value": "world",
+                "length": 5
+            }
+        );
+
+        // Replaced properties are replaced back
+        let op = Operation::Replace {
+            address: Address::from("label"),
+            items: 1,
+            value: Box::new("Another label".to_string()),
+            length: 1,
+            html: None,
+        };
+        assert_json_is!(
+            op.invert(Some(&json))?,
+            {
+                "type": "Replace",
+                "address": ["label"],
+                "items": 1,
+                "value": "A label",
+                "length": 1
+            }
+        );
+
+        // Moves are moved back
+        let op = Operation::Move {
+            from: Address::from(1),
+            items: 2,
+            to: Address::from(5),
+        };
+        assert_json_is!(
+            op.invert(None)?,
+            {
+                "type": "Move",
+                "from": [3],
+                "items": 2,
+                "to": [1]
+            }
+        );
+
+        // The pre-image is required for removals
+        assert!(Operation::Remove {
+            address: Address::from(0),
+            items: 1
+        }
+        .invert(None)
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -170,11 +170,13 @@ where
 {
     let patches: Vec<Patch> = derived.iter().map(|node| diff(ancestor, *node)).collect();
 
-    // TODO transform operations (shift address based on other operations) and resolve conflicts
-    tracing::warn!("Merging is work in progress");
-
-    for patch in patches {
+    // Each patch is relative to the ancestor so needs to be transformed against the
+    // effects of the patches that have already been applied
+    let mut effects = Vec::new();
+    for mut patch in patches {
+        patch.transform(&effects);
         apply(ancestor, &patch)?;
+        effects.append(&mut patch.effects());
     }
     Ok(())
 }
@@ -575,6 +577,13 @@ pub struct Patch {
     /// Used so that actors can ignore patches that they created and
     /// that hae already been applied.
     pub actor: Option<String>,
+
+    /// The version of the document that the patch was generated against
+    ///
+    /// Should be present on patches sent by clients. It is the `sequence` of the last published
+    /// patch that the actor had applied when it generated this patch. Used to transform
+    /// the patch against any concurrent patches that have been applied since (see [`Patch::transform`]).
+    pub version: Option<u32>,
 }
 
 impl Patch {
@@ -892,6 +901,10 @@ pub trait Patchable {
     }
 }
 
+mod invert;
+mod transform;
+pub use transform::Effect;
+
 mod errors;
 use errors::{invalid_patch_operation, invalid_patch_value};
 
@@ -0,0 +1,754 @@
+//! Transformation of patch operations against concurrent operations
+//!
+//! When two actors (e.g. two web browser clients) edit the same document at the same time,
+//! a patch from one of them may be generated against a version of the document that does
+//! not include the patches from the other that have since been applied. The addresses in the
+//! stale patch need to be transformed (e.g. indices shifted for items that were added or removed
+//! by the concurrent patches) before it is applied.
+//!
+//! The values of operations are not needed for this, so the concurrent operations are recorded as
+//! lightweight, cloneable [`Effect`]s.
+
+use std::mem;
+
+use node_address::{Address, Slot};
+
+use crate::{Operation, Patch};
+
+/// The effect of an [`Operation`] on the addresses of other operations
+///
+/// All addresses are absolute (i.e. relative to the root node of the document).
+/// `Transform` operations change the type of a node but not its address and so have no effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    Add {
+        address: Address,
+        length: usize,
+    },
+    Remove {
+        address: Address,
+        items: usize,
+    },
+    Replace {
+        address: Address,
+        items: usize,
+        length: usize,
+    },
+    Move {
+        from: Address,
+        items: usize,
+        to: Address,
+    },
+}
+
+impl Patch {
+    /// Get the [`Effect`]s of the operations in the patch
+    ///
+    /// The `address` of the patch (if any) is prepended to the address of each operation.
+    /// Should only be called on a patch which has an `address`, or has neither an `address`
+    /// nor a `target`, because the absolute address of a `target` is not known.
+    pub fn effects(&self) -> Vec<Effect> {
+        let prefix = self.address.clone().unwrap_or_default();
+        self.ops
+            .iter()
+            .filter_map(|op| op.effect(&prefix))
+            .collect()
+    }
+
+    /// Transform the operations in the patch against the [`Effect`]s of concurrent operations
+    ///
+    /// The `effects` should be from patches that have been applied since the version of the node
+    /// that this patch was generated against, and be in the order that they were applied. After
+    /// transformation the patch can be applied to the current version of the node.
+    ///
+    /// Each operation in the patch is relative to the node after the preceding operations have been
+    /// applied. So, as each operation is transformed against an effect, the effect is itself
+    /// transformed against the operation before being used to transform the next operation.
+    ///
+    /// Operations targeting nodes that were removed (or replaced) by a concurrent operation are
+    /// dropped. Otherwise, conflicts are resolved in favour of this patch (i.e. the last writer wins).
+    /// If the patch has an `address` it is transformed as well; the patch should have an `address`
+    /// if it has a `target` (see [`Patch::effects`]).
+    pub fn transform(&mut self, effects: &[Effect]) {
+        if effects.is_empty() {
+            return;
+        }
+
+        let prefix = self.address.clone().unwrap_or_default();
+        let mut ops: Vec<Operation> = mem::take(&mut self.ops)
+            .into_iter()
+            .map(|op| op.prepend(&prefix))
+            .collect();
+
+        let mut prefix = Some(prefix);
+        for effect in effects {
+            let mut concurrent = Some(effect.clone());
+            ops = ops
+                .into_iter()
+                .filter_map(|op| {
+                    let effect = match concurrent.take() {
+                        Some(effect) => effect,
+                        None => return Some(op),
+                    };
+                    concurrent = match op.effect(&Address::default()) {
+                        Some(op_effect) => effect.clone().transform(&op_effect),
+                        None => Some(effect.clone()),
+                    };
+                    op.transform(&effect)
+                })
+                .collect();
+            prefix = prefix.and_then(|prefix| {
+                let mut prefix = Target::Node(prefix);
+                match prefix.transform(effect) {
+                    true => Some(prefix.into_address()),
+                    false => None,
+                }
+            });
+        }
+
+        // If the node at the address of the patch has been removed, so have all its operations
+        let prefix = match prefix {
+            Some(prefix) => prefix,
+            None => return,
+        };
+
+        self.ops = ops.into_iter().filter_map(|op| op.strip(&prefix)).collect();
+        if self.address.is_some() {
+            self.address = Some(prefix);
+        }
+    }
+}
+
+impl Effect {
+    /// Transform the effect against the [`Effect`] of an operation applied before it
+    ///
+    /// Returns `None` if the effect no longer applies because the node(s) that it
+    /// targets have been removed, or replaced by exactly the same range. When both are
+    /// additions at the same position, the other is considered to be after this one
+    /// (i.e. this effect is not shifted). Both are consistent with the transformation
+    /// of operations in [`Operation::transform`].
+    fn transform(self, other: &Effect) -> Option<Effect> {
+        match self {
+            Effect::Add { address, length } => {
+                if let Effect::Add {
+                    address: other_address,
+                    ..
+                } = other
+                {
+                    if *other_address == address {
+                        return Some(Effect::Add { address, length });
+                    }
+                }
+                let mut target = Target::Range(address, 0);
+                match target.transform(other) {
+                    true => Some(Effect::Add {
+                        address: target.into_address(),
+                        length,
+                    }),
+                    false => None,
+                }
+            }
+            Effect::Remove { address, items } => {
+                let mut target = Target::Range(address, items);
+                match target.transform(other) {
+                    true => match target {
+                        Target::Range(address, items) => Some(Effect::Remove { address, items }),
+                        Target::Node(..) => None,
+                    },
+                    false => None,
+                }
+            }
+            Effect::Replace {
+                address,
+                items,
+                length,
+            } => {
+                if let Effect::Replace {
+                    address: other_address,
+                    items: other_items,
+                    ..
+                } = other
+                {
+                    if *other_address == address && *other_items == items {
+                        return None;
+                    }
+                }
+                let mut target = Target::Range(address, items);
+                match target.transform(other) {
+                    true => match target {
+                        Target::Range(address, items) => Some(Effect::Replace {
+                            address,
+                            items,
+                            length,
+                        }),
+                        Target::Node(..) => None,
+                    },
+                    false => None,
+                }
+            }
+            Effect::Move { from, items, to } => {
+                let mut source = Target::Range(from, items);
+                let mut dest = Target::Range(to, 0);
+                match source.transform(other) && dest.transform(other) {
+                    true => match source {
+                        Target::Range(from, items) => Some(Effect::Move {
+                            from,
+                            items,
+                            to: dest.into_address(),
+                        }),
+                        Target::Node(..) => None,
+                    },
+                    false => None,
+                }
+            }
+        }
+    }
+}
+
+impl Operation {
+    /// Get the [`Effect`] of the operation with its address(es) prefixed by `prefix`
+    pub fn effect(&self, prefix: &Address) -> Option<Effect> {
+        match self {
+            Operation::Add {
+                address, length, ..
+            } => Some(Effect::Add {
+                address: prefix.concat(address),
+                length: *length,
+            }),
+            Operation::Remove { address, items } => Some(Effect::Remove {
+                address: prefix.concat(address),
+                items: *items,
+            }),
+            Operation::Replace {
+                address,
+                items,
+                length,
+                ..
+            } => Some(Effect::Replace {
+                address: prefix.concat(address),
+                items: *items,
+                length: *length,
+            }),
+            Operation::Move { from, items, to } => Some(Effect::Move {
+                from: prefix.concat(from),
+                items: *items,
+                to: prefix.concat(to),
+            }),
+            Operation::Transform { .. } => None,
+        }
+    }
+
+    /// Transform the operation against the [`Effect`] of a concurrent operation
+    ///
+    /// Returns `None` if the operation should be dropped because the node that it
+    /// targets has been removed.
+    pub fn transform(mut self, effect: &Effect) -> Option<Operation> {
+        let keep = match &mut self {
+            Operation::Add { address, .. } => {
+                let mut target = Target::Range(address.clone(), 0);
+                let keep = target.transform(effect);
+                *address = target.into_address();
+                keep
+            }
+            Operation::Remove { address, items } | Operation::Replace { address, items, .. } => {
+                let mut target = Target::Range(address.clone(), *items);
+                let keep = target.transform(effect);
+                if let Target::Range(_, range) = &target {
+                    *items = *range
+                }
+                *address = target.into_address();
+                keep
+            }
+            Operation::Move { from, items, to } => {
+                let mut source = Target::Range(from.clone(), *items);
+                let mut dest = Target::Range(to.clone(), 0);
+                let keep = source.transform(effect) && dest.transform(effect);
+                if let Target::Range(_, range) = &source {
+                    *items = *range
+                }
+                *from = source.into_address();
+                *to = dest.into_address();
+                keep
+            }
+            Operation::Transform { address, .. } => {
+                let mut target = Target::Node(address.clone());
+                let keep = target.transform(effect);
+                *address = target.into_address();
+                keep
+            }
+        };
+        if keep {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Prepend an address to the address(es) of the operation
+    fn prepend(mut self, prefix: &Address) -> Operation {
+        match &mut self {
+            Operation::Add { address, .. }
+            | Operation::Remove { address, .. }
+            | Operation::Replace { address, .. }
+            | Operation::Transform { address, .. } => address.prepend(prefix),
+            Operation::Move { from, to, .. } => {
+                from.prepend(prefix);
+                to.prepend(prefix);
+            }
+        };
+        self
+    }
+
+    /// Strip an address from the start of the address(es) of the operation
+    ///
+    /// Returns `None` if any of the operation's addresses do not start with the prefix.
+    fn strip(mut self, prefix: &Address) -> Option<Operation> {
+        let strip = |address: &mut Address| match starts_with(address, prefix) {
+            true => {
+                address.drain(..prefix.len());
+                true
+            }
+            false => false,
+        };
+        let keep = match &mut self {
+            Operation::Add { address, .. }
+            | Operation::Remove { address, .. }
+            | Operation::Replace { address, .. }
+            | Operation::Transform { address, .. } => strip(address),
+            Operation::Move { from, to, .. } => strip(from) && strip(to),
+        };
+        if keep {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+/// The part of a node tree targeted by an operation
+enum Target {
+    /// A node (or a property of a node) at an address
+    Node(Address),
+
+    /// A range of items in a vector, or characters in a string, starting at an
+    /// address (ending in an index) and with a number of items (zero for a
+    /// position at which items are inserted)
+    Range(Address, usize),
+}
+
+impl Target {
+    /// Get the address of the target
+    fn into_address(self) -> Address {
+        match self {
+            Target::Node(address) | Target::Range(address, ..) => address,
+        }
+    }
+
+    /// Transform the target against an [`Effect`]
+    ///
+    /// Returns `false` if the target has been removed by the effect.
+    fn transform(&mut self, effect: &Effect) -> bool {
+        let (address, items) = match self {
+            Target::Node(address) => (address, None),
+            Target::Range(address, items) => (address, Some(items)),
+        };
+
+        let (parent, index, removed, added) = match effect {
+            Effect::Add { address, length } => match split_index(address) {
+                Some((parent, index)) => (parent, index, 0, *length),
+                // Adding a property does not affect any other address
+                None => return true,
+            },
+            Effect::Remove {
+                address: effect_address,
+                items: effect_items,
+            }
+            | Effect::Replace {
+                address: effect_address,
+                items: effect_items,
+                ..
+            } => {
+                let length = match effect {
+                    Effect::Replace { length, .. } => *length,
+                    _ => 0,
+                };
+                match split_index(effect_address) {
+                    Some((parent, index)) => (parent, index, *effect_items, length),
+                    // Removing, or replacing, a property removes any nodes within it
+                    None => return !is_within(address, effect_address),
+                }
+            }
+            Effect::Move {
+                from,
+                items: count,
+                to,
+            } => {
+                return match (split_index(from), split_index(to)) {
+                    (Some((parent, from)), Some((_, to))) => {
+                        move_target(address, items, &parent, from, *count, to);
+                        true
+                    }
+                    _ => true,
+                }
+            }
+        };
+
+        let depth = parent.len();
+        let position = match address.get(depth) {
+            Some(Slot::Index(position)) if starts_with(address, &parent) => *position,
+            _ => return true,
+        };
+
+        let is_range = items.is_some() && address.len() == depth + 1;
+        if !is_range {
+            // The target is the item at `position` (or a node within it)
+            if position >= index + removed {
+                address[depth] = Slot::Index(position + added - removed);
+            } else if position >= index && removed > 0 {
+                return false;
+            }
+            return true;
+        }
+
+        let items = items.expect("is range");
+        let (start, end) = (position, position + *items);
+
+        // A concurrent replacement of exactly the same range is overwritten by this one
+        if removed > 0 && added > 0 && start == index && *items == removed {
+            *items = added;
+            return true;
+        }
+
+        // Map the range through the removal and then the addition
+        let map = |position: usize| {
+            if position <= index {
+                position
+            } else if position >= index + removed {
+                position - removed
+            } else {
+                index
+            }
+        };
+        let (mut start, mut end) = (map(start), map(end));
+        if end == start && *items > 0 {
+            return false;
+        }
+        if added > 0 {
+            if index <= start {
+                start += added;
+                end += added;
+            } else if index < end {
+                end += added;
+            }
+        }
+
+        address[depth] = Slot::Index(start);
+        *items = end - start;
+        true
+    }
+}
+
+/// Transform an address (and possibly a range) against a `Move` within a vector
+fn move_target(
+    address: &mut Address,
+    items: Option<&mut usize>,
+    parent: &Address,
+    from: usize,
+    count: usize,
+    to: usize,
+) {
+    let depth = parent.len();
+    let position = match address.get(depth) {
+        Some(Slot::Index(position)) if starts_with(address, parent) => *position,
+        _ => return,
+    };
+
+    // Insertion positions are treated as items so that they move along with
+    // the item that they are before
+    let end = position + items.as_deref().copied().unwrap_or(1).max(1);
+    let map = |position: usize| -> usize {
+        if from < to {
+            if position >= from && position < from + count {
+                position - from + to - count
+            } else if position >= from + count && position < to {
+                position - count
+            } else {
+                position
+            }
+        } else if position >= from && position < from + count {
+            to + position - from
+        } else if position >= to && position < from {
+            position + count
+        } else {
+            position
+        }
+    };
+
+    let moved = position >= from && end <= from + count;
+    let unmoved = end <= from || position >= from + count;
+    if moved || unmoved {
+        address[depth] = Slot::Index(map(position));
+    } else if let Some(items) = items {
+        // The range partially overlaps the moved items so only keep the part that was not moved
+        let (start, end) = if position < from {
+            (position, from)
+        } else {
+            (from + count, end)
+        };
+        address[depth] = Slot::Index(map(start));
+        *items = end - start;
+    }
+}
+
+/// Split an address into the address of its parent and its last slot, if that is an index
+fn split_index(address: &Address) -> Option<(Address, usize)> {
+    let mut parent = address.clone();
+    match parent.pop_back() {
+        Some(Slot::Index(index)) => Some((parent, index)),
+        _ => None,
+    }
+}
+
+/// Does an address start with another address?
+fn starts_with(address: &Address, prefix: &Address) -> bool {
+    address.len() >= prefix.len() && address.iter().zip(prefix.iter()).all(|(a, b)| a == b)
+}
+
+/// Is an address strictly within another address?
+fn is_within(address: &Address, container: &Address) -> bool {
+    address.len() > container.len() && starts_with(address, container)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apply, merge};
+    use common::eyre::Result;
+    use stencila_schema::{Article, BlockContent, InlineContent, Paragraph};
+    use test_utils::{assert_json_eq, assert_json_is};
+
+    fn para(text: &str) -> BlockContent {
+        BlockContent::Paragraph(Paragraph {
+            content: vec![InlineContent::String(text.to_string())],
+            ..Default::default()
+        })
+    }
+
+    fn article(paras: &[&str]) -> Article {
+        Article {
+            content: Some(paras.iter().map(|text| para(text)).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_transform_concurrent() -> Result<()> {
+        let text = |para: usize, index: usize| {
+            Address::from("content")
+                .add_index(para)
+                .add_name("content")
+                .add_index(0)
+                .add_index(index)
+        };
+        let replace = |address: Address, value: &str| Operation::Replace {
+            address,
+            items: 1,
+            value: Box::new(value.to_string()),
+            length: 1,
+            html: None,
+        };
+
+        let mut node = article(&["one", "two", "three"]);
+
+        // One actor inserts a paragraph at the start and removes the last paragraph
+        let patch1 = Patch::from_ops(vec![
+            Operation::Add {
+                address: Address::from("content").add_index(0),
+                value: Box::new(vec![para("zero")]),
+                length: 1,
+                html: None,
+            },
+            Operation::Remove {
+                address: Address::from("content").add_index(3),
+                items: 1,
+            },
+        ]);
+        apply(&mut node, &patch1)?;
+        assert_json_eq!(node, article(&["zero", "one", "two"]));
+
+        // Another actor, concurrently, edits the text of the second paragraph
+        // which needs to be shifted to account for the insertion
+        let mut patch2 = Patch::from_ops(vec![replace(text(1, 1), "o")]);
+        patch2.transform(&patch1.effects());
+        assert_json_is!(
+            patch2.ops,
+            [{
+                "type": "Replace",
+                "address": ["content", 2, "content", 0, 1],
+                "items": 1,
+                "value": "o",
+                "length": 1
+            }]
+        );
+        apply(&mut node, &patch2)?;
+        assert_json_eq!(node, article(&["zero", "one", "too"]));
+
+        // Operations on removed nodes are dropped
+        let mut patch3 = Patch::from_ops(vec![replace(text(2, 1), "r")]);
+        patch3.transform(&patch1.effects());
+        assert_json_is!(patch3.ops, []);
+
+        // Patches with an address have that transformed instead
+        let mut patch4 = Patch::from_ops(vec![replace(Address::from(0), "O")]);
+        patch4.address = Some(
+            Address::from("content")
+                .add_index(0)
+                .add_name("content")
+                .add_index(0),
+        );
+        patch4.target = Some("para-1".to_string());
+        patch4.transform(&patch1.effects());
+        assert_json_is!(patch4.address, ["content", 1, "content", 0]);
+        assert_json_is!(
+            patch4.ops,
+            [{
+                "type": "Replace",
+                "address": [0],
+                "items": 1,
+                "value": "O",
+                "length": 1
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform_sequential() -> Result<()> {
+        let content = |index: usize| Address::from("content").add_index(index);
+
+        let mut node = article(&["a", "b", "c", "d", "e", "f"]);
+
+        // One actor inserts a paragraph before the fifth
+        let patch1 = Patch::from_ops(vec![Operation::Add {
+            address: content(4),
+            value: Box::new(vec![para("N")]),
+            length: 1,
+            html: None,
+        }]);
+        apply(&mut node, &patch1)?;
+        assert_json_eq!(node, article(&["a", "b", "c", "d", "N", "e", "f"]));
+
+        // Another actor, concurrently, removes the first paragraph and then replaces the
+        // fifth (at index 3 after the removal). The insertion needs to be transformed against
+        // the removal before the replacement is transformed against it.
+        let mut patch2 = Patch::from_ops(vec![
+            Operation::Remove {
+                address: content(0),
+                items: 1,
+            },
+            Operation::Replace {
+                address: content(3),
+                items: 1,
+                value: Box::new(vec![para("X")]),
+                length: 1,
+                html: None,
+            },
+        ]);
+        patch2.transform(&patch1.effects());
+        assert_json_is!(
+            patch2.ops,
+            [{
+                "type": "Remove",
+                "address": ["content", 0],
+                "items": 1
+            }, {
+                "type": "Replace",
+                "address": ["content", 4],
+                "items": 1,
+                "value": [{"type": "Paragraph", "content": ["X"]}],
+                "length": 1
+            }]
+        );
+        apply(&mut node, &patch2)?;
+        assert_json_eq!(node, article(&["b", "c", "d", "N", "X", "f"]));
+
+        // Concurrent insertions at the same position are both kept, with the
+        // later ones of this patch also shifted by the earlier ones
+        let mut node = article(&["a", "b"]);
+        let patch1 = Patch::from_ops(vec![Operation::Add {
+            address: content(1),
+            value: Box::new(vec![para("N")]),
+            length: 1,
+            html: None,
+        }]);
+        apply(&mut node, &patch1)?;
+        let mut patch2 = Patch::from_ops(vec![
+            Operation::Add {
+                address: content(1),
+                value: Box::new(vec![para("X")]),
+                length: 1,
+                html: None,
+            },
+            Operation::Add {
+                address: content(3),
+                value: Box::new(vec![para("Y")]),
+                length: 1,
+                html: None,
+            },
+        ]);
+        patch2.transform(&patch1.effects());
+        apply(&mut node, &patch2)?;
+        assert_json_eq!(node, article(&["a", "N", "X", "b", "Y"]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_sequential() -> Result<()> {
+        let mut ancestor = article(&["a", "b", "c", "d", "e", "f"]);
+        let derived1 = article(&["a", "b", "c", "d", "N", "e", "f"]);
+        let derived2 = article(&["b", "c", "d", "X", "f"]);
+        merge(&mut ancestor, &[&derived1, &derived2])?;
+        assert_json_eq!(ancestor, article(&["b", "c", "d", "N", "X", "f"]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform_ranges() {
+        let address = |index: usize| Address::from(index);
+
+        // Insertions before a removal shift it
+        let mut target = Target::Range(address(4), 2);
+        assert!(target.transform(&Effect::Add {
+            address: address(1),
+            length: 3
+        }));
+        assert!(matches!(target, Target::Range(ref address, 2) if address[0] == Slot::Index(7)));
+
+        // Overlapping removals are trimmed
+        let mut target = Target::Range(address(2), 4);
+        assert!(target.transform(&Effect::Remove {
+            address: address(4),
+            items: 5
+        }));
+        assert!(matches!(target, Target::Range(ref address, 2) if address[0] == Slot::Index(2)));
+
+        // Removals of already removed items are dropped
+        let mut target = Target::Range(address(3), 2);
+        assert!(!target.transform(&Effect::Remove {
+            address: address(2),
+            items: 5
+        }));
+
+        // Nodes follow the items that are moved
+        let mut target = Target::Node(address(1).add_name("content"));
+        assert!(target.transform(&Effect::Move {
+            from: address(1),
+            items: 1,
+            to: address(4)
+        }));
+        assert_eq!(target.into_address(), address(3).add_name("content"));
+    }
+}
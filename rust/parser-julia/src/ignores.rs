@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use parser_treesitter::common::{maplit::hashset, once_cell::sync::Lazy};
+
+/// A list of global Julia functions to ignore when generating relations of type `Relation::Use`.
+///
+/// This is a curated subset of the functions exported from `Base` (see `names(Base)`)
+/// that are commonly used in scripts and notebooks.
+pub static USE_IGNORE: Lazy<HashSet<&str>> = Lazy::new(|| {
+    hashset! {
+        "abs",
+        "all",
+        "any",
+        "append!",
+        "collect",
+        "convert",
+        "copy",
+        "count",
+        "deepcopy",
+        "delete!",
+        "display",
+        "eachindex",
+        "eachline",
+        "eltype",
+        "enumerate",
+        "error",
+        "exp",
+        "extrema",
+        "fill",
+        "filter",
+        "findall",
+        "findfirst",
+        "first",
+        "floor",
+        "get",
+        "getindex",
+        "haskey",
+        "include",
+        "isempty",
+        "isnothing",
+        "join",
+        "keys",
+        "last",
+        "length",
+        "log",
+        "map",
+        "max",
+        "maximum",
+        "min",
+        "minimum",
+        "ones",
+        "parse",
+        "pop!",
+        "print",
+        "println",
+        "prod",
+        "push!",
+        "rand",
+        "randn",
+        "range",
+        "reduce",
+        "repeat",
+        "reshape",
+        "reverse",
+        "round",
+        "setdiff",
+        "show",
+        "size",
+        "sort",
+        "sort!",
+        "split",
+        "sqrt",
+        "string",
+        "strip",
+        "sum",
+        "typeof",
+        "union",
+        "unique",
+        "values",
+        "vcat",
+        "hcat",
+        "zeros",
+        "zip",
+    }
+});
+
+/// A list of global Julia constants that are never treated as uses of a symbol
+pub static CONSTANTS: Lazy<HashSet<&str>> = Lazy::new(|| {
+    hashset! {
+        "nothing",
+        "missing",
+        "Inf",
+        "NaN",
+        "pi",
+        "π",
+        "ℯ",
+    }
+});
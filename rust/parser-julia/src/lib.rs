@@ -0,0 +1,358 @@
+use std::path::Path;
+
+use parser_treesitter::{
+    captures_as_args_map,
+    common::{eyre::Result, once_cell::sync::Lazy},
+    formats::Format,
+    graph_triples::{relations, resources, Resource, ResourceInfo},
+    path_utils, resource_info,
+    utils::{is_quoted, remove_quotes},
+    Parser, ParserTrait, TreesitterParser,
+};
+
+/// Tree-sitter based parser for Julia
+static PARSER: Lazy<TreesitterParser> = Lazy::new(|| TreesitterParser::new(language(), QUERY));
+
+/// Get the Tree-sitter language for Julia
+///
+/// The `tree-sitter-julia` grammar is only published as a language function for
+/// newer versions of `tree-sitter`. Its ABI version is supported by the version
+/// used by the other parsers so the function's result is wrapped as a `Language`.
+fn language() -> tree_sitter::Language {
+    // SAFETY: `Language` is a transparent wrapper around the `TSLanguage` pointer
+    // returned by the grammar's language function
+    unsafe {
+        let language = tree_sitter_julia::LANGUAGE.into_raw()();
+        std::mem::transmute::<*const (), tree_sitter::Language>(language)
+    }
+}
+
+/// Tree-sitter AST query
+const QUERY: &str = include_str!("query.scm");
+
+mod ignores;
+use ignores::{CONSTANTS, USE_IGNORE};
+
+/// A parser for Julia
+pub struct JuliaParser {}
+
+impl ParserTrait for JuliaParser {
+    fn spec() -> Parser {
+        Parser {
+            language: Format::Julia.spec().title,
+        }
+    }
+
+    fn parse(resource: Resource, path: &Path, code: &str) -> Result<ResourceInfo> {
+        let code = code.as_bytes();
+        let tree = PARSER.parse(code);
+        let matches = PARSER.query(code, &tree);
+
+        let relations = matches
+            .iter()
+            .filter_map(|(pattern, captures)| match pattern {
+                1 | 2 => {
+                    // Imports a package using `using` or `import`
+                    let range = captures[0].range;
+                    let package = &captures[0].text;
+                    Some((
+                        relations::imports(range),
+                        resources::module("julia", package),
+                    ))
+                }
+                3 => {
+                    // Includes a file using `include`
+                    let args = captures_as_args_map(captures);
+                    args.get("0").map(|file| {
+                        (
+                            relations::imports(file.range),
+                            resources::file(&path_utils::merge(path, remove_quotes(&file.text))),
+                        )
+                    })
+                }
+                4 => {
+                    // Opens a file for reading or writing
+                    let args = captures_as_args_map(captures);
+                    let file = args.get("0")?;
+                    if !is_quoted(&file.text) {
+                        return None;
+                    }
+                    let resource =
+                        resources::file(&path_utils::merge(path, remove_quotes(&file.text)));
+                    let range = file.range;
+                    match args.get("1") {
+                        Some(mode) if is_quoted(&mode.text) => {
+                            let mode = remove_quotes(&mode.text);
+                            if mode.starts_with('w') || mode.starts_with('a') {
+                                Some((relations::writes(range), resource))
+                            } else {
+                                Some((relations::reads(range), resource))
+                            }
+                        }
+                        Some(..) => None,
+                        None => Some((relations::reads(range), resource)),
+                    }
+                }
+                5 => {
+                    // Reads a file
+                    let args = captures_as_args_map(captures);
+                    args.get("0").map(|file| {
+                        (
+                            relations::reads(file.range),
+                            resources::file(&path_utils::merge(path, remove_quotes(&file.text))),
+                        )
+                    })
+                }
+                6 => {
+                    // Writes a file
+                    let args = captures_as_args_map(captures);
+                    args.get("0").map(|file| {
+                        (
+                            relations::writes(file.range),
+                            resources::file(&path_utils::merge(path, remove_quotes(&file.text))),
+                        )
+                    })
+                }
+                7 => {
+                    // Assigns a symbol at the top level of the module
+                    let range = captures[0].range;
+                    let name = captures[0].text.clone();
+                    let value = captures[1].node;
+                    let kind = match value.kind() {
+                        "boolean_literal" => "Boolean",
+                        "integer_literal" => "Integer",
+                        "float_literal" => "Number",
+                        "string_literal" => "String",
+                        "vector_expression" => "Array",
+                        "arrow_function_expression" | "function_definition" => "Function",
+                        "call_expression" => match value
+                            .child(0)
+                            .and_then(|function| function.utf8_text(code).ok())
+                            .unwrap_or_default()
+                        {
+                            "DataFrame" | "CSV.read" | "readdlm" => "Datatable",
+                            "Dict" => "Object",
+                            _ => "",
+                        },
+                        _ => "",
+                    };
+                    Some((
+                        relations::assigns(range),
+                        resources::symbol(path, &name, kind),
+                    ))
+                }
+                8 | 9 => {
+                    // Declares a function at the top level of the module using either
+                    // `function name() ... end` or `name() = ...`
+                    let range = captures[0].range;
+                    let name = captures[0].text.clone();
+                    Some((
+                        relations::declares(range),
+                        resources::symbol(path, &name, "Function"),
+                    ))
+                }
+                10 => {
+                    // Uses a function or variable
+                    let node = captures[0].node;
+                    let range = captures[0].range;
+                    let symbol = captures[0].text.clone();
+
+                    if CONSTANTS.contains(&symbol.as_str()) {
+                        return None;
+                    }
+
+                    let mut child = node;
+                    let mut parent = node.parent();
+                    while let Some(parent_node) = parent {
+                        match parent_node.kind() {
+                            // Skip identifiers that are the left side of an assignment, or
+                            // that are within a short function definition e.g. `f(x) = x^2`
+                            "assignment" => {
+                                if let Some(left) = parent_node.named_child(0) {
+                                    if left == child || left.kind() == "call_expression" {
+                                        return None;
+                                    }
+                                }
+                            }
+                            // Skip identifiers that are the name of a keyword argument
+                            "named_argument" if parent_node.named_child(0) == Some(child) => {
+                                return None;
+                            }
+                            // Skip identifiers that are a field e.g. `df.column`
+                            "field_expression" if parent_node.named_child(1) == Some(node) => {
+                                return None;
+                            }
+                            // Skip identifiers that are the variable of a for loop, or that refer to it
+                            "for_statement" => {
+                                let mut cursor = parent_node.walk();
+                                for binding in parent_node
+                                    .named_children(&mut cursor)
+                                    .filter(|child| child.kind() == "for_binding")
+                                {
+                                    if let Some(name) = binding.named_child(0) {
+                                        if name == node
+                                            || name.utf8_text(code).unwrap_or_default() == symbol
+                                        {
+                                            return None;
+                                        }
+                                    }
+                                }
+                            }
+                            // Skip identifiers within these...
+                            "using_statement"
+                            | "import_statement"
+                            | "function_definition"
+                            | "macro_definition"
+                            | "struct_definition" => return None,
+                            _ => {}
+                        }
+                        child = parent_node;
+                        parent = parent_node.parent();
+                    }
+
+                    let resource = match node.parent() {
+                        Some(parent_node)
+                            if parent_node.kind() == "call_expression"
+                                && parent_node.child(0) == Some(node) =>
+                        {
+                            // As for R, because there are so many globals, only ignore
+                            // apparent uses of global functions in calls.
+                            if USE_IGNORE.contains(&symbol.as_str()) {
+                                return None;
+                            }
+                            resources::symbol(path, &symbol, "Function")
+                        }
+                        _ => resources::symbol(path, &symbol, ""),
+                    };
+
+                    Some((relations::uses(range), resource))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let resource_info = resource_info(
+            resource,
+            path,
+            &Self::spec().language,
+            code,
+            &tree,
+            &["line_comment", "block_comment"],
+            matches,
+            0,
+            relations,
+        );
+        Ok(resource_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use parser_treesitter::graph_triples::{Relation, Resource};
+
+    use super::*;
+
+    fn parse(code: &str) -> Vec<(Relation, Resource)> {
+        let path = PathBuf::new();
+        let resource = resources::code(&path, "", "SoftwareSourceCode", Some("Julia".to_string()));
+        JuliaParser::parse(resource, &path, code)
+            .expect("Unable to parse")
+            .relations
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn imports() {
+        let relations = parse("using DataFrames\nimport CSV\ninclude(\"utils.jl\")");
+        assert!(
+            matches!(relations[0], (Relation::Imports(..), Resource::Module(ref module)) if module.name == "DataFrames")
+        );
+        assert!(
+            matches!(relations[1], (Relation::Imports(..), Resource::Module(ref module)) if module.name == "CSV")
+        );
+        assert!(
+            matches!(relations[2], (Relation::Imports(..), Resource::File(ref file)) if file.path.ends_with("utils.jl"))
+        );
+    }
+
+    #[test]
+    fn reads_writes() {
+        let relations = parse(
+            "df = CSV.read(\"data.csv\", DataFrame)\nCSV.write(\"out.csv\", df)\nopen(\"log.txt\", \"w\")",
+        );
+        assert!(relations.iter().any(|relation| matches!(relation, (Relation::Reads(..), Resource::File(file)) if file.path.ends_with("data.csv"))));
+        assert!(relations.iter().any(|relation| matches!(relation, (Relation::Writes(..), Resource::File(file)) if file.path.ends_with("out.csv"))));
+        assert!(relations.iter().any(|relation| matches!(relation, (Relation::Writes(..), Resource::File(file)) if file.path.ends_with("log.txt"))));
+    }
+
+    #[test]
+    fn assigns_kinds() {
+        let relations = parse(
+            r#"
+a = 1
+b = 1.5
+c = true
+d = "str"
+e = [1, 2]
+f = x -> x + 1
+g = Dict("a" => 1)
+h = DataFrame(a = [1])
+i = CSV.read("data.csv", DataFrame)
+j = a
+"#,
+        );
+
+        let kinds: Vec<(&str, &str)> = relations
+            .iter()
+            .filter_map(|relation| match relation {
+                (Relation::Assigns(..), Resource::Symbol(symbol)) => {
+                    Some((symbol.name.as_str(), symbol.kind.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("a", "Integer"),
+                ("b", "Number"),
+                ("c", "Boolean"),
+                ("d", "String"),
+                ("e", "Array"),
+                ("f", "Function"),
+                ("g", "Object"),
+                ("h", "Datatable"),
+                ("i", "Datatable"),
+                ("j", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn assigns_uses() {
+        let relations =
+            parse("a = 1\nb = a * c\nfunction f(x)\n  x + y\nend\ng(x) = x^2\nprintln(b)");
+
+        let assigns: Vec<String> = relations
+            .iter()
+            .filter_map(|relation| match relation {
+                (Relation::Assigns(..) | Relation::Declares(..), Resource::Symbol(symbol)) => {
+                    Some(symbol.name.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(assigns, vec!["a", "b", "f", "g"]);
+
+        let uses: Vec<String> = relations
+            .iter()
+            .filter_map(|relation| match relation {
+                (Relation::Uses(..), Resource::Symbol(symbol)) => Some(symbol.name.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(uses, vec!["a", "c", "b"]);
+    }
+}
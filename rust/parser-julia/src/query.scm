@@ -0,0 +1,81 @@
+(source_file (line_comment) @comment)
+
+(using_statement [
+    (identifier) @module
+    (selected_import . (identifier) @module)
+    (scoped_identifier . (identifier) @module)
+])
+(import_statement [
+    (identifier) @module
+    (selected_import . (identifier) @module)
+    (scoped_identifier . (identifier) @module)
+])
+
+(call_expression
+    (identifier) @function (#match? @function "^include$")
+    (
+        argument_list
+            .
+            (string_literal) @arg
+    )
+)
+
+(call_expression
+    (identifier) @function (#match? @function "^open$")
+    (
+        argument_list
+            .
+            [(string_literal)(identifier)] @arg
+            .
+            ([(string_literal)(identifier)] @arg)?
+    )
+)
+
+(call_expression
+    [(identifier)(field_expression)] @function (#match? @function "^(read|readlines|readdlm|CSV\.read|CSV\.File)$")
+    (
+        argument_list
+            .
+            (string_literal) @arg
+    )
+)
+
+(call_expression
+    [(identifier)(field_expression)] @function (#match? @function "^(write|writedlm|CSV\.write)$")
+    (
+        argument_list
+            .
+            (string_literal) @arg
+    )
+)
+
+(source_file
+    (assignment
+        .
+        (identifier) @name
+        (operator)
+        .
+        (_) @value
+    )
+)
+(source_file
+    (function_definition
+        (signature
+            (call_expression
+                .
+                (identifier) @name
+            )
+        )
+    )
+)
+(source_file
+    (assignment
+        .
+        (call_expression
+            .
+            (identifier) @name
+        )
+    )
+)
+
+((identifier) @identifer)
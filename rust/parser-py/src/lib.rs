@@ -223,4 +223,20 @@ mod tests {
             assert_json_snapshot!(resource_info);
         })
     }
+
+    #[test]
+    fn parse_py_syntax_errors() {
+        let path = Path::new("code.py");
+        let resource = || resources::code(path, "", "CodeChunk", Some("Python".to_string()));
+
+        let resource_info = PyParser::parse(resource(), path, "a = 1\nb = a + 1").unwrap();
+        assert!(resource_info.diagnostics.is_none());
+
+        let resource_info = PyParser::parse(resource(), path, "a = 1\nb = (a +").unwrap();
+        let diagnostics = resource_info.diagnostics.expect("Should have diagnostics");
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.error_type == "SyntaxError" && diagnostic.range.0 == 1));
+    }
 }
@@ -1,5 +1,9 @@
 use parser::{
-    graph_triples::{relations::Range, resources::ResourceDigest, Pairs, Resource, ResourceInfo},
+    graph_triples::{
+        relations::Range,
+        resources::{Diagnostic, ResourceDigest},
+        Pairs, Resource, ResourceInfo,
+    },
     utils::apply_tags,
 };
 use std::{collections::HashMap, path::Path, sync::Mutex};
@@ -233,6 +237,12 @@ pub fn resource_info(
         None,
     );
 
+    // Record any syntax errors
+    let diagnostics = syntax_diagnostics(tree, code);
+    if !diagnostics.is_empty() {
+        resource_info.diagnostics = Some(diagnostics);
+    }
+
     // Apply tags from comments (this needs to be done at the end because tags
     // may remove pairs if `only` is specified)
     for (pattern_, captures) in matches {
@@ -254,6 +264,55 @@ pub fn resource_info(
     resource_info
 }
 
+/// Get a list of syntax errors in a Tree-sitter tree
+///
+/// Tree-sitter recovers from syntax errors by inserting `ERROR` nodes (for unexpected
+/// code) and zero-width "missing" nodes (for expected, but absent, tokens). This creates a
+/// `SyntaxError` diagnostic for each of these.
+pub fn syntax_diagnostics(tree: &Tree, code: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let root = tree.root_node();
+    if !root.has_error() {
+        return diagnostics;
+    }
+
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let start = node.start_position();
+        let end = node.end_position();
+        let range = (start.row, start.column, end.row, end.column);
+
+        // Only descend into nodes that contain errors
+        let mut descend = node.has_error();
+        if node.is_error() {
+            let text = node.utf8_text(code).unwrap_or_default().trim();
+            let message = match text.lines().next() {
+                Some(line) if !line.is_empty() => format!("Invalid syntax: `{}`", line),
+                _ => "Invalid syntax".to_string(),
+            };
+            diagnostics.push(Diagnostic::new("SyntaxError", &message, range));
+            descend = false;
+        } else if node.is_missing() {
+            let message = format!("Missing `{}`", node.kind());
+            diagnostics.push(Diagnostic::new("SyntaxError", &message, range));
+        }
+
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return diagnostics;
+            }
+        }
+    }
+}
+
 /// Generate a digest of the "semantic content" of a Tree-sitter tree
 ///
 /// The digest excludes "anonymous" nodes and some "named" nodes.
@@ -11,13 +11,13 @@ pub const SCHEMAS: &[(&str, &str)] = &[
     ("Call", r#"{"title":"Call","type":"object","propertyAliases":{"encodingFormat":"mediaType","codeDependencie":"codeDependencies","codeDependent":"codeDependents","error":"errors","argument":"arguments"},"additionalProperties":false,"required":["type","source"],"properties":{"id":{"type":"string"},"text":{"type":"string"},"programmingLanguage":{"type":"string"},"mediaType":{"aliases":["encodingFormat"],"type":"string","isOverride":true},"codeDependencies":{"type":"array","items":{"anyOf":[{"$ref":"CodeChunk.schema.json"},{"$ref":"File.schema.json"},{"$ref":"Parameter.schema.json"}]},"aliases":["codeDependencie"]},"codeDependents":{"type":"array","items":{"anyOf":[{"$ref":"Call.schema.json"},{"$ref":"CodeChunk.schema.json"},{"$ref":"CodeExpression.schema.json"},{"$ref":"File.schema.json"}]},"aliases":["codeDependent"]},"compileDigest":{"type":"string"},"executeCount":{"type":"integer","minimum":0},"executeDigest":{"type":"string"},"executeRequired":{"$ref":"ExecuteRequired.schema.json"},"executeStatus":{"$ref":"ExecuteStatus.schema.json"},"executeEnded":{"$ref":"Date.schema.json"},"executeDuration":{"type":"number","minimum":0},"executeAuto":{"$ref":"ExecuteAuto.schema.json"},"errors":{"type":"array","items":{"$ref":"CodeError.schema.json"},"aliases":["error"]},"source":{"type":"string"},"select":{"type":"string"},"content":{"type":"array","items":{"$ref":"BlockContent.schema.json"}},"arguments":{"type":"array","items":{"$ref":"CallArgument.schema.json"},"aliases":["argument"]}}}"#),
     ("CallArgument", r#"{"title":"CallArgument","type":"object","additionalProperties":false,"required":["type","name"],"propertyAliases":{},"properties":{"id":{"type":"string"},"name":{"type":"string","pattern":"[a-z_][A-z0-9_]*"},"value":{"allOf":[{"$ref":"Node.schema.json"}]},"default":{"allOf":[{"$ref":"Node.schema.json"}]},"validator":{"allOf":[{"$ref":"ValidatorTypes.schema.json"}]},"compileDigest":{"type":"string"},"executeDigest":{"type":"string"},"executeRequired":{"$ref":"ExecuteRequired.schema.json"},"isRequired":{"type":"boolean"},"isVariadic":{"type":"boolean"},"isExtensible":{"type":"boolean"},"symbol":{"type":"string","pattern":"[a-zA-z_][A-z0-9_]*"}}}"#),
     ("CitationIntent", r#"{"title":"CitationIntent","anyOf":[{"const":"AgreesWith","@id":"cito:agreesWith","description":"The citing entity agrees with statements, ideas or conclusions presented in the cited entity"},{"const":"CitesAsAuthority","@id":"cito:citesAsAuthority","description":"The citing entity cites the cited entity as one that provides an authoritative description or definition of the subject under discussion"},{"const":"CitesAsDataSource","@id":"cito:citesAsDataSource","description":"The citing entity cites the cited entity as source of data"},{"const":"CitesAsEvidence","@id":"cito:citesAsEvidence","description":"The citing entity cites the cited entity as source of factual evidence for statements it contains"},{"const":"CitesAsMetadataDocument","@id":"cito:citesAsMetadataDocument","description":"The citing entity cites the cited entity as being the container of metadata describing the citing entity"},{"const":"CitesAsPotentialSolution","@id":"cito:citesAsPotentialSolution","description":"The citing entity cites the cited entity as providing or containing a possible solution to the issues being discussed"},{"const":"CitesAsRecommendedReading","@id":"cito:citesAsRecommendedReading","description":"The citing entity cites the cited entity as an item of recommended reading"},{"const":"CitesAsRelated","@id":"cito:citesAsRelated","description":"The citing entity cites the cited entity as one that is related"},{"const":"CitesAsSourceDocument","@id":"cito:citesAsSourceDocument","description":"The citing entity cites the cited entity as being the entity from which the citing entity is derived, or about which the citing entity contains metadata"},{"const":"CitesForInformation","@id":"cito:citesForInformation","description":"The citing entity cites the cited entity as a source of information on the subject under discussion"},{"const":"Compiles","@id":"cito:compiles","description":"The citing entity is used to create or compile the cited entity"},{"const":"Confirms","@id":"cito:confirms","description":"The citing entity confirms facts, ideas or statements presented in the cited entity"},{"const":"ContainsAssertionFrom","@id":"cito:containsAssertionFrom","description":"The citing entity contains a statement of fact or a logical assertion (or a collection of such facts and/or assertions) originally present in the cited entity"},{"const":"Corrects","@id":"cito:corrects","description":"The citing entity corrects statements, ideas or conclusions presented in the cited entity"},{"const":"Credits","@id":"cito:credits","description":"The citing entity acknowledges contributions made by the cited entity"},{"const":"Critiques","@id":"cito:critiques","description":"The citing entity critiques statements, ideas or conclusions presented in the cited entity"},{"const":"Derides","@id":"cito:derides","description":"The citing entity express derision for the cited entity, or for ideas or conclusions contained within it"},{"const":"Describes","@id":"cito:describes","description":"The citing entity describes the cited entity"},{"const":"DisagreesWith","@id":"cito:disagreesWith","description":"The citing entity disagrees with statements, ideas or conclusions presented in the cited entity"},{"const":"Discusses","@id":"cito:discusses","description":"The citing entity discusses statements, ideas or conclusions presented in the cited entity"},{"const":"Disputes","@id":"cito:disputes","description":"The citing entity disputes statements, ideas or conclusions presented in the cited entity"},{"const":"Documents","@id":"cito:documents","description":"The citing entity documents information about the cited entity"},{"const":"Extends","@id":"cito:extends","description":"The citing entity extends facts, ideas or understandings presented in the cited entity"},{"const":"GivesBackgroundTo","@id":"cito:givesBackgroundTo","description":"The cited entity provides background information for the citing entity"},{"const":"GivesSupportTo","@id":"cito:givesSupportTo","description":"The cited entity provides intellectual or factual support for the citing entity"},{"const":"HasReplyFrom","@id":"cito:hasReplyFrom","description":"The cited entity evokes a reply from the citing entity"},{"const":"IncludesExcerptFrom","@id":"cito:includesExcerptFrom","description":"The citing entity includes one or more excerpts from the cited entity"},{"const":"IncludesQuotationFrom","@id":"cito:includesQuotationFrom","description":"The citing entity includes one or more quotations from the cited entity"},{"const":"IsAgreedWithBy","@id":"cito:isAgreedWithBy","description":"The cited entity contains statements, ideas or conclusions with which the citing entity agrees"},{"const":"IsCitedAsAuthorityBy","@id":"cito:isCitedAsAuthorityBy","description":"The cited entity is cited as providing an authoritative description or definition of the subject under discussion in the citing entity"},{"const":"IsCitedAsDataSourceBy","@id":"cito:isCitedAsDataSourceBy","description":"The cited entity is cited as a data source by the citing entity"},{"const":"IsCitedAsEvidenceBy","@id":"cito:isCitedAsEvidenceBy","description":"The cited entity is cited for providing factual evidence to the citing entity"},{"const":"IsCitedAsMetadataDocumentBy","@id":"cito:isCitedAsMetadataDocumentBy","description":"The cited entity is cited as being the container of metadata relating to the citing entity"},{"const":"IsCitedAsPontentialSolutionBy","@id":"cito:isCitedAsPontentialSolutionBy","description":"The cited entity is cited as providing or containing a possible solution to the issues being discussed in the citing entity"},{"const":"IsCitedAsRecommendedReadingBy","@id":"cito:isCitedAsRecommendedReadingBy","description":"The cited entity is cited by the citing entity as an item of recommended reading"},{"const":"IsCitedAsRelatedBy","@id":"cito:isCitedAsRelatedBy","description":"The cited entity is cited as being related to the citing entity"},{"const":"IsCitedAsSourceDocumentBy","@id":"cito:isCitedAsSourceDocumentBy","description":"The cited entity is cited as being the entity from which the citing entity is derived, or about which the citing entity contains metadata"},{"const":"IsCitedBy","@id":"cito:isCitedBy","description":"The cited entity (the subject of the RDF triple) is cited by the citing entity (the object of the triple)"},{"const":"IsCitedForInformationBy","@id":"cito:isCitedForInformationBy","description":"The cited entity is cited as a source of information on the subject under discussion in the citing entity"},{"const":"IsCompiledBy","@id":"cito:isCompiledBy","description":"The cited entity is the result of a compile or creation event using the citing entity"},{"const":"IsConfirmedBy","@id":"cito:isConfirmedBy","description":"The cited entity presents facts, ideas or statements that are confirmed by the citing entity"},{"const":"IsCorrectedBy","@id":"cito:isCorrectedBy","description":"The cited entity presents statements, ideas or conclusions that are corrected by the citing entity"},{"const":"IsCreditedBy","@id":"cito:isCreditedBy","description":"The cited entity makes contributions that are acknowledged by the citing entity"},{"const":"IsCritiquedBy","@id":"cito:isCritiquedBy","description":"The cited entity presents statements, ideas or conclusions that are critiqued by the citing entity"},{"const":"IsDeridedBy","@id":"cito:isDeridedBy","description":"The cited entity contains ideas or conclusions for which the citing entity express derision"},{"const":"IsDescribedBy","@id":"cito:isDescribedBy","description":"The cited entity is described by the citing entity"},{"const":"IsDisagreedWithBy","@id":"cito:isDisagreedWithBy","description":"The cited entity presents statements, ideas or conclusions that are disagreed with by the citing entity"},{"const":"IsDiscussedBy","@id":"cito:isDiscussedBy","description":"The cited entity presents statements, ideas or conclusions that are discussed by the citing entity"},{"const":"IsDisputedBy","@id":"cito:isDisputedBy","description":"The cited entity presents statements, ideas or conclusions that are disputed by the citing entity"},{"const":"IsDocumentedBy","@id":"cito:isDocumentedBy","description":"Information about the cited entity is documented by the citing entity"},{"const":"IsExtendedBy","@id":"cito:isExtendedBy","description":"The cited entity presents facts, ideas or understandings that are extended by the citing entity"},{"const":"IsLinkedToBy","@id":"cito:isLinkedToBy","description":"The cited entity is the target for an HTTP Uniform Resource Locator (URL) link within the citing entity"},{"const":"IsParodiedBy","@id":"cito:isParodiedBy","description":"The characteristic style or content of the cited entity is imitated by the citing entity for comic effect, usually without explicit citation"},{"const":"IsPlagiarizedBy","@id":"cito:isPlagiarizedBy","description":"The cited entity is plagiarized by the author of the citing entity, who includes within the citing entity textual or other elements from the cited entity without formal acknowledgement of their source"},{"const":"IsQualifiedBy","@id":"cito:isQualifiedBy","description":"The cited entity presents statements, ideas or conclusions that are qualified or have conditions placed upon them by the citing entity"},{"const":"IsRefutedBy","@id":"cito:isRefutedBy","description":"The cited entity presents statements, ideas or conclusions that are refuted by the citing entity"},{"const":"IsRetractedBy","@id":"cito:isRetractedBy","description":"The cited entity is formally retracted by the citing entity"},{"const":"IsReviewedBy","@id":"cito:isReviewedBy","description":"The cited entity presents statements, ideas or conclusions that are reviewed by the citing entity"},{"const":"IsRidiculedBy","@id":"cito:isRidiculedBy","description":"The cited entity or aspects of its contents are ridiculed by the citing entity"},{"const":"IsSpeculatedOnBy","@id":"cito:isSpeculatedOnBy","description":"The cited entity is cited because the citing article contains speculations on its content or ideas"},{"const":"IsSupportedBy","@id":"cito:isSupportedBy","description":"The cited entity receives intellectual or factual support from the citing entity"},{"const":"IsUpdatedBy","@id":"cito:isUpdatedBy","description":"The cited entity presents statements, ideas, hypotheses or understanding that are updated by the cited entity"},{"const":"Likes","@id":"cito:likes","description":"A property that permits you to express appreciation of or interest in something that is the object of the RDF triple, or to express that it is worth thinking about even if you do not agree with its content, enabling social media 'likes' statements to be encoded in RDF"},{"const":"LinksTo","@id":"cito:linksTo","description":"The citing entity provides a link, in the form of an HTTP Uniform Resource Locator (URL), to the cited entity"},{"const":"ObtainsBackgroundFrom","@id":"cito:obtainsBackgroundFrom","description":"The citing entity obtains background information from the cited entity"},{"const":"ObtainsSupportFrom","@id":"cito:obtainsSupportFrom","description":"The citing entity obtains intellectual or factual support from the cited entity"},{"const":"Parodies","@id":"cito:parodies","description":"The citing entity imitates the characteristic style or content of the cited entity for comic effect, usually without explicit citation"},{"const":"Plagiarizes","@id":"cito:plagiarizes","description":"A property indicating that the author of the citing entity plagiarizes the cited entity, by including textual or other elements from the cited entity without formal acknowledgement of their source"},{"const":"ProvidesAssertionFor","@id":"cito:providesAssertionFor","description":"The cited entity contains and is the original source of a statement of fact or a logical assertion (or a collection of such facts and/or assertions) that is to be found in the citing entity"},{"const":"ProvidesConclusionsFor","@id":"cito:providesConclusionsFor","description":"The cited entity presents conclusions that are used in work described in the citing entity"},{"const":"ProvidesDataFor","@id":"cito:providesDataFor","description":"The cited entity presents data that are used in work described in the citing entity"},{"const":"ProvidesExcerptFor","@id":"cito:providesExcerptFor","description":"The cited entity contains information, usually of a textual nature, that is excerpted by (used as an excerpt within) the citing entity"},{"const":"ProvidesMethodFor","@id":"cito:providesMethodFor","description":"The cited entity details a method that is used in work described by the citing entity"},{"const":"ProvidesQuotationFor","@id":"cito:providesQuotationFor","description":"The cited entity contains information, usually of a textual nature, that is quoted by (used as a quotation within) the citing entity"},{"const":"Qualifies","@id":"cito:qualifies","description":"The citing entity qualifies or places conditions or restrictions upon statements, ideas or conclusions presented in the cited entity"},{"const":"Refutes","@id":"cito:refutes","description":"The citing entity refutes statements, ideas or conclusions presented in the cited entity"},{"const":"RepliesTo","@id":"cito:repliesTo","description":"The citing entity replies to statements, ideas or criticisms presented in the cited entity"},{"const":"Retracts","@id":"cito:retracts","description":"The citing entity constitutes a formal retraction of the cited entity"},{"const":"Reviews","@id":"cito:reviews","description":"The citing entity reviews statements, ideas or conclusions presented in the cited entity"},{"const":"Ridicules","@id":"cito:ridicules","description":"The citing entity ridicules the cited entity or aspects of its contents"},{"const":"SharesAuthorInstitutionWith","@id":"cito:sharesAuthorInstitutionWith","description":"Each entity has at least one author that shares a common institutional affiliation with an author of the other entity"},{"const":"SharesAuthorWith","@id":"cito:sharesAuthorWith","description":"Each entity has at least one author in common with the other entity"},{"const":"SharesFundingAgencyWith","@id":"cito:sharesFundingAgencyWith","description":"The two entities result from activities that have been funded by the same funding agency"},{"const":"SharesJournalWith","@id":"cito:sharesJournalWith","description":"The citing and cited bibliographic resources are published in the same journal"},{"const":"SharesPublicationVenueWith","@id":"cito:sharesPublicationVenueWith","description":"The citing and cited bibliographic resources are published in same publication venue"},{"const":"SpeculatesOn","@id":"cito:speculatesOn","description":"The citing entity speculates on something within or related to the cited entity, without firm evidence"},{"const":"Supports","@id":"cito:supports","description":"The citing entity provides intellectual or factual support for statements, ideas or conclusions presented in the cited entity"},{"const":"Updates","@id":"cito:updates","description":"The citing entity updates statements, ideas, hypotheses or understanding presented in the cited entity"},{"const":"UsesConclusionsFrom","@id":"cito:usesConclusionsFrom","description":"The citing entity describes work that uses conclusions presented in the cited entity"},{"const":"UsesDataFrom","@id":"cito:usesDataFrom","description":"The citing entity describes work that uses data presented in the cited entity"},{"const":"UsesMethodIn","@id":"cito:usesMethodIn","description":"The citing entity describes work that uses a method detailed in the cited entity"}]}"#),
-    ("Cite", r#"{"title":"Cite","required":["type","target"],"examples":[{"type":"Article","title":"An example of using the Cite node type","authors":[{"type":"Person","givenNames":["Joe"],"familyNames":["Bloggs"]}],"content":[{"type":"Paragraph","content":["A citation of ",{"type":"Cite","target":"some-one-else-1991"},"."]}],"references":[{"type":"Article","id":"some-one-else-1991","title":"Another article by someone else","authors":[{"type":"Person","givenNames":["Some","One"],"familyNames":["Else"]}],"datePublished":"1991"}]}],"type":"object","additionalProperties":false,"propertyAliases":{},"properties":{"id":{"type":"string"},"citationMode":{"type":"string","enum":["Parenthetical","Narrative","NarrativeAuthor","NarrativeYear","normal","suppressAuthor"],"default":"parenthetical"},"citationIntent":{"type":"array","items":{"$ref":"CitationIntent.schema.json"}},"content":{"type":"array","items":{"$ref":"InlineContent.schema.json"}},"pageStart":{"anyOf":[{"type":"integer"},{"type":"string"}]},"pageEnd":{"anyOf":[{"type":"integer"},{"type":"string"}]},"pagination":{"type":"string"},"citationPrefix":{"type":"string"},"citationSuffix":{"type":"string"},"target":{"type":"string"}}}"#),
+    ("Cite", r#"{"title":"Cite","required":["type","target"],"examples":[{"type":"Article","title":"An example of using the Cite node type","authors":[{"type":"Person","givenNames":["Joe"],"familyNames":["Bloggs"]}],"content":[{"type":"Paragraph","content":["A citation of ",{"type":"Cite","target":"some-one-else-1991"},"."]}],"references":[{"type":"Article","id":"some-one-else-1991","title":"Another article by someone else","authors":[{"type":"Person","givenNames":["Some","One"],"familyNames":["Else"]}],"datePublished":"1991"}]}],"type":"object","additionalProperties":false,"propertyAliases":{},"properties":{"id":{"type":"string"},"citationMode":{"type":"string","enum":["Parenthetical","Narrative","NarrativeAuthor","NarrativeYear","normal","suppressAuthor"],"default":"parenthetical"},"citationIntent":{"type":"array","items":{"$ref":"CitationIntent.schema.json"}},"content":{"type":"array","items":{"$ref":"InlineContent.schema.json"}},"errors":{"type":"array","items":{"type":"string"}},"pageStart":{"anyOf":[{"type":"integer"},{"type":"string"}]},"pageEnd":{"anyOf":[{"type":"integer"},{"type":"string"}]},"pagination":{"type":"string"},"citationPrefix":{"type":"string"},"citationSuffix":{"type":"string"},"target":{"type":"string"}}}"#),
     ("CiteGroup", r#"{"title":"CiteGroup","required":["type","items"],"examples":[{"type":"Article","title":"An example of using the CiteGroup node type","authors":[{"type":"Person","givenNames":["Joe"],"familyNames":["Bloggs"]}],"content":[{"type":"Paragraph","content":["Citing two articles ",{"type":"CiteGroup","items":[{"type":"Cite","target":"some-one-else-1991"},{"type":"Cite","target":"updated-works-2009"}]},"."]}],"references":[{"type":"Article","id":"some-one-else-1991","title":"Another article by someone else","authors":[{"type":"Person","givenNames":["Some","One"],"familyNames":["Else"]}],"datePublished":"1991"},{"type":"Article","id":"update-works-2009","title":"A Better Updated Work","authors":[{"type":"Person","givenNames":["Some","Better"],"familyNames":["Person"]}],"datePublished":"2009"}]}],"type":"object","propertyAliases":{"item":"items"},"additionalProperties":false,"properties":{"id":{"type":"string"},"items":{"type":"array","items":{"$ref":"Cite.schema.json"},"aliases":["item"]}}}"#),
     ("Claim", r#"{"title":"Claim","required":["type","content"],"type":"object","additionalProperties":false,"propertyAliases":{"alternateName":"alternateNames","identifier":"identifiers","image":"images","author":"authors","comment":"comments","date":"datePublished","editor":"editors","funder":"funders","keyword":"keywords","license":"licenses","maintainer":"maintainers","hasParts":"parts","part":"parts","citations":"references","reference":"references","headline":"title"},"properties":{"id":{"type":"string"},"alternateNames":{"type":"array","items":{"type":"string"},"aliases":["alternateName"]},"description":{"anyOf":[{"type":"array","items":{"$ref":"BlockContent.schema.json"}},{"type":"array","items":{"$ref":"InlineContent.schema.json"},"minItems":2},{"type":"string"}]},"identifiers":{"type":"array","items":{"anyOf":[{"$ref":"PropertyValue.schema.json"},{"type":"string"}]},"aliases":["identifier"]},"images":{"type":"array","items":{"anyOf":[{"$ref":"ImageObject.schema.json"},{"type":"string","format":"uri"}]},"aliases":["image"]},"name":{"type":"string"},"url":{"type":"string","format":"uri"},"about":{"type":"array","items":{"$ref":"ThingTypes.schema.json"}},"authors":{"allOf":[{"parser":"scsi"},{"type":"array","items":{"anyOf":[{"$ref":"Person.schema.json"},{"$ref":"Organization.schema.json"}]}}],"aliases":["author"]},"comments":{"type":"array","items":{"$ref":"Comment.schema.json"},"aliases":["comment"]},"content":{"type":"array","items":{"$ref":"BlockContent.schema.json"},"isOverride":true},"dateCreated":{"allOf":[{"$ref":"Date.schema.json"}]},"dateReceived":{"allOf":[{"$ref":"Date.schema.json"}]},"dateAccepted":{"allOf":[{"$ref":"Date.schema.json"}]},"dateModified":{"allOf":[{"$ref":"Date.schema.json"}]},"datePublished":{"aliases":["date"],"allOf":[{"$ref":"Date.schema.json"}]},"editors":{"type":"array","items":{"$ref":"Person.schema.json"},"aliases":["editor"]},"funders":{"type":"array","items":{"anyOf":[{"$ref":"Person.schema.json"},{"$ref":"Organization.schema.json"}]},"aliases":["funder"]},"fundedBy":{"type":"array","items":{"anyOf":[{"$ref":"Grant.schema.json"},{"$ref":"MonetaryGrant.schema.json"}]}},"genre":{"allOf":[{"parser":"csi"},{"type":"array","items":{"type":"string"}}]},"keywords":{"allOf":[{"parser":"csi"},{"type":"array","items":{"type":"string"}}],"aliases":["keyword"]},"isPartOf":{"$ref":"CreativeWorkTypes.schema.json"},"licenses":{"type":"array","items":{"anyOf":[{"$ref":"CreativeWorkTypes.schema.json"},{"type":"string","format":"uri"}]},"aliases":["license"]},"maintainers":{"type":"array","items":{"anyOf":[{"$ref":"Person.schema.json"},{"$ref":"Organization.schema.json"}]},"aliases":["maintainer"]},"parts":{"aliases":["hasParts","part"],"type":"array","items":{"$ref":"CreativeWorkTypes.schema.json"}},"publisher":{"anyOf":[{"$ref":"Person.schema.json"},{"$ref":"Organization.schema.json"}]},"references":{"aliases":["citations","reference"],"type":"array","items":{"anyOf":[{"$ref":"CreativeWorkTypes.schema.json"},{"type":"string"}]}},"text":{"type":"string"},"title":{"aliases":["headline"],"anyOf":[{"type":"array","items":{"$ref":"InlineContent.schema.json"},"minItems":2},{"type":"string"}]},"version":{"anyOf":[{"type":"string"},{"type":"number"}]},"claimType":{"type":"string","enum":["Statement","Theorem","Lemma","Proof","Postulate","Hypothesis","Proposition","Corollary"]},"label":{"type":"string"}}}"#),
     ("Code", r#"{"title":"Code","type":"object","propertyAliases":{"encodingFormat":"mediaType"},"additionalProperties":false,"required":["type"],"properties":{"id":{"type":"string"},"text":{"type":"string"},"programmingLanguage":{"type":"string"},"mediaType":{"aliases":["encodingFormat"],"type":"string"}}}"#),
     ("CodeBlock", r#"{"title":"CodeBlock","required":["type","text"],"type":"object","additionalProperties":false,"propertyAliases":{"encodingFormat":"mediaType"},"properties":{"id":{"type":"string"},"text":{"type":"string","isOverride":true},"programmingLanguage":{"type":"string"},"mediaType":{"aliases":["encodingFormat"],"type":"string"}}}"#),
     ("CodeChunk", r#"{"title":"CodeChunk","required":["type","text","programmingLanguage"],"examples":[{"type":"CodeChunk","programmingLanguage":"python","text":"print('Hello world!')"}],"type":"object","propertyAliases":{"encodingFormat":"mediaType","codeDependencie":"codeDependencies","codeDependent":"codeDependents","error":"errors","output":"outputs"},"additionalProperties":false,"properties":{"id":{"type":"string"},"text":{"type":"string","isOverride":true},"programmingLanguage":{"type":"string","isOverride":true},"mediaType":{"aliases":["encodingFormat"],"type":"string"},"codeDependencies":{"type":"array","items":{"anyOf":[{"$ref":"CodeChunk.schema.json"},{"$ref":"File.schema.json"},{"$ref":"Parameter.schema.json"}]},"aliases":["codeDependencie"]},"codeDependents":{"type":"array","items":{"anyOf":[{"$ref":"Call.schema.json"},{"$ref":"CodeChunk.schema.json"},{"$ref":"CodeExpression.schema.json"},{"$ref":"File.schema.json"}]},"aliases":["codeDependent"]},"compileDigest":{"type":"string"},"executeCount":{"type":"integer","minimum":0},"executeDigest":{"type":"string"},"executeRequired":{"$ref":"ExecuteRequired.schema.json"},"executeStatus":{"$ref":"ExecuteStatus.schema.json"},"executeEnded":{"$ref":"Date.schema.json"},"executeDuration":{"type":"number","minimum":0},"executeAuto":{"$ref":"ExecuteAuto.schema.json"},"errors":{"type":"array","items":{"$ref":"CodeError.schema.json"},"aliases":["error"]},"label":{"type":"string"},"caption":{"anyOf":[{"type":"array","items":{"$ref":"BlockContent.schema.json"}},{"type":"string"}]},"executePure":{"type":"boolean"},"outputs":{"type":"array","items":{"$ref":"Node.schema.json"},"aliases":["output"]}}}"#),
-    ("CodeError", r#"{"title":"CodeError","required":["type","errorMessage"],"type":"object","propertyAliases":{"message":"errorMessage","trace":"stackTrace"},"additionalProperties":false,"properties":{"id":{"type":"string"},"errorType":{"type":"string"},"errorMessage":{"aliases":["message"],"type":"string"},"stackTrace":{"aliases":["trace"],"type":"string"}}}"#),
+    ("CodeError", r#"{"title":"CodeError","required":["type","errorMessage"],"type":"object","propertyAliases":{"message":"errorMessage","trace":"stackTrace"},"additionalProperties":false,"properties":{"id":{"type":"string"},"errorType":{"type":"string"},"errorMessage":{"aliases":["message"],"type":"string"},"stackTrace":{"aliases":["trace"],"type":"string"},"codeLocation":{"type":"array","items":{"type":"integer","minimum":0}}}}"#),
     ("CodeExecutable", r#"{"title":"CodeExecutable","type":"object","propertyAliases":{"encodingFormat":"mediaType","codeDependencie":"codeDependencies","codeDependent":"codeDependents","error":"errors"},"additionalProperties":false,"required":["type"],"properties":{"id":{"type":"string"},"text":{"type":"string"},"programmingLanguage":{"type":"string"},"mediaType":{"aliases":["encodingFormat"],"type":"string"},"codeDependencies":{"type":"array","items":{"anyOf":[{"$ref":"CodeChunk.schema.json"},{"$ref":"File.schema.json"},{"$ref":"Parameter.schema.json"}]},"aliases":["codeDependencie"]},"codeDependents":{"type":"array","items":{"anyOf":[{"$ref":"Call.schema.json"},{"$ref":"CodeChunk.schema.json"},{"$ref":"CodeExpression.schema.json"},{"$ref":"File.schema.json"}]},"aliases":["codeDependent"]},"compileDigest":{"type":"string"},"executeCount":{"type":"integer","minimum":0},"executeDigest":{"type":"string"},"executeRequired":{"$ref":"ExecuteRequired.schema.json"},"executeStatus":{"$ref":"ExecuteStatus.schema.json"},"executeEnded":{"$ref":"Date.schema.json"},"executeDuration":{"type":"number","minimum":0},"executeAuto":{"$ref":"ExecuteAuto.schema.json"},"errors":{"type":"array","items":{"$ref":"CodeError.schema.json"},"aliases":["error"]}}}"#),
     ("CodeExecutableTypes", r#"{"title":"CodeExecutableTypes","anyOf":[{"$ref":"CodeExecutable.schema.json"},{"$ref":"Call.schema.json"},{"$ref":"CodeChunk.schema.json"},{"$ref":"CodeExpression.schema.json"},{"$ref":"Include.schema.json"}]}"#),
     ("CodeExpression", r#"{"title":"CodeExpression","required":["type","text","programmingLanguage"],"type":"object","additionalProperties":false,"propertyAliases":{"encodingFormat":"mediaType","codeDependencie":"codeDependencies","codeDependent":"codeDependents","error":"errors"},"properties":{"id":{"type":"string"},"text":{"type":"string","isOverride":true},"programmingLanguage":{"type":"string","isOverride":true},"mediaType":{"aliases":["encodingFormat"],"type":"string"},"codeDependencies":{"type":"array","items":{"anyOf":[{"$ref":"CodeChunk.schema.json"},{"$ref":"File.schema.json"},{"$ref":"Parameter.schema.json"}]},"aliases":["codeDependencie"]},"codeDependents":{"type":"array","items":{"anyOf":[{"$ref":"Call.schema.json"},{"$ref":"CodeChunk.schema.json"},{"$ref":"CodeExpression.schema.json"},{"$ref":"File.schema.json"}]},"aliases":["codeDependent"]},"compileDigest":{"type":"string"},"executeCount":{"type":"integer","minimum":0},"executeDigest":{"type":"string"},"executeRequired":{"$ref":"ExecuteRequired.schema.json"},"executeStatus":{"$ref":"ExecuteStatus.schema.json"},"executeEnded":{"$ref":"Date.schema.json"},"executeDuration":{"type":"number","minimum":0},"executeAuto":{"$ref":"ExecuteAuto.schema.json"},"errors":{"type":"array","items":{"$ref":"CodeError.schema.json"},"aliases":["error"]},"output":{"allOf":[{"$ref":"Node.schema.json"}]}}}"#),
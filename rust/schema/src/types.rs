@@ -57,6 +57,9 @@ pub struct Cite {
     /// Optional structured content/text of this citation.
     pub content: Option<Vec<InlineContent>>,
 
+    /// Errors that occurred when resolving or rendering the citation.
+    pub errors: Option<Vec<String>>,
+
     /// The identifier for this item.
     #[derivative(PartialEq = "ignore", Hash = "ignore")]
     pub id: Option<Box<String>>,
@@ -284,6 +287,9 @@ pub struct CodeChunk {
     /// Media type, typically expressed using a MIME format, of the code.
     pub media_type: Option<Box<String>>,
 
+    /// Metadata associated with this item.
+    pub meta: Option<Box<Object>>,
+
     /// Outputs from executing the chunk.
     pub outputs: Option<Vec<Node>>,
 }
@@ -545,6 +551,9 @@ pub struct CodeError {
     /// The error message or brief description of the error.
     pub error_message: String,
 
+    /// The location of the error within the code.
+    pub code_location: Option<Vec<Integer>>,
+
     /// The type of error e.g. "SyntaxError", "ZeroDivisionError".
     pub error_type: Option<Box<String>>,
 
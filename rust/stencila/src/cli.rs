@@ -70,6 +70,14 @@ pub struct Cli {
     /// The format to print log events
     #[clap(long, global = true, possible_values = LoggingFormat::VARIANTS, ignore_case = true, env = "STENCILA_LOG_FORMAT")]
     pub log_format: Option<LoggingFormat>,
+
+    /// Execute the code in all documents within a sandbox
+    ///
+    /// Use when executing documents from untrusted sources. Overrides any sandbox specified
+    /// within documents. Use `on` for the default sandbox (no network and a read-only file
+    /// system) or specify options e.g. `cpu=10,memory=512,scratch=tmp`.
+    #[clap(long, global = true, env = "STENCILA_SANDBOX")]
+    pub sandbox: Option<String>,
 }
 
 #[derive(Debug, Default, Parser)]
@@ -110,13 +118,14 @@ impl DisplayOptions {
 /// Global arguments that should be removed when entering interactive mode
 /// because they can only be set / are relevant at startup. Other global arguments,
 /// which need to be accessible at the line level, should be added to `interact::Line` below.
-pub const GLOBAL_ARGS: [&str; 6] = [
+pub const GLOBAL_ARGS: [&str; 7] = [
     "-i",
     "--interact",
     "--interactive",
     "--debug",
     "--log-level",
     "--log-format",
+    "--sandbox",
 ];
 
 #[derive(Parser)]
@@ -146,7 +155,7 @@ pub enum Command {
     Documents(documents::cli::Command),
 
     #[clap(aliases = &["project"])]
-    Projects(cloud::projects::cli::Command),
+    Projects(ProjectsCommand),
 
     #[clap(aliases = &["source"])]
     Sources(cloud::sources::cli::Command),
@@ -435,6 +444,36 @@ impl Run for ShowCommand {
     }
 }
 
+/// Manage projects
+///
+/// Use this command to list your Stencila projects, inspect and update details for individual
+/// projects, to manage project sources, members, and deployments etc, and to infer the
+/// packages that a local project depends upon.
+#[derive(Parser)]
+pub struct ProjectsCommand {
+    #[clap(subcommand)]
+    action: ProjectsAction,
+}
+
+/// Combines the project commands of the `cloud` crate with those for local projects
+#[derive(Parser)]
+enum ProjectsAction {
+    #[clap(flatten)]
+    Cloud(cloud::projects::cli::Action),
+
+    Deps(crate::projects::commands::Deps),
+}
+
+#[async_trait]
+impl Run for ProjectsCommand {
+    async fn run(&self) -> Result {
+        match &self.action {
+            ProjectsAction::Cloud(action) => action.run().await,
+            ProjectsAction::Deps(action) => action.run().await,
+        }
+    }
+}
+
 /// Currently, these top-level commands simply delegate to those in other modules
 type ConvertCommand = codecs::commands::Convert;
 type DiffCommand = documents::cli::Diff;
@@ -637,6 +676,7 @@ pub async fn main() -> eyre::Result<()> {
         debug,
         log_level,
         log_format,
+        sandbox,
         mut interact,
         ..
     } = match parsed_args {
@@ -735,6 +775,11 @@ pub async fn main() -> eyre::Result<()> {
         )?;
     }
 
+    // Force all documents to be executed in a sandbox, if specified
+    if let Some(sandbox) = &sandbox {
+        DOCUMENTS.set_sandbox(Some(sandbox)).await?;
+    }
+
     // If not explicitly upgrading then run an upgrade check in the background
     #[cfg(feature = "upgrade")]
     let upgrade_thread = {
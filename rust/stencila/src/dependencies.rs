@@ -0,0 +1,783 @@
+//! Inference of the packages that a project depends upon
+//!
+//! Collects the modules imported by code in a project (i.e. the `Module` objects of `Imports`
+//! relations) and maps them to the packages that need to be installed to provide them. These
+//! can then be written to the manifest files that buildpacks use to build an environment for
+//! the project (i.e. `requirements.txt`, `DESCRIPTION` and `package.json`).
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use common::{
+    eyre::{bail, Result},
+    serde::Serialize,
+    serde_json, tracing,
+};
+use formats::{Format, FormatNodeType};
+use graph_triples::{
+    resources::{self, Module},
+    Resource,
+};
+use node_pointer::{walk, Address, Visitor};
+use stencila_schema::{BlockContent, InlineContent};
+
+/// Top-level modules in the Python standard library
+///
+/// Based on `sys.stdlib_module_names` for Python 3.10 (excluding private modules
+/// starting with an underscore which are ignored anyway).
+#[rustfmt::skip]
+const PYTHON_STDLIB: &[&str] = &[
+    "abc", "aifc", "antigravity", "argparse", "array", "ast", "asynchat", "asyncio", "asyncore",
+    "atexit", "audioop", "base64", "bdb", "binascii", "binhex", "bisect", "builtins", "bz2",
+    "cProfile", "calendar", "cgi", "cgitb", "chunk", "cmath", "cmd", "code", "codecs", "codeop",
+    "collections", "colorsys", "compileall", "concurrent", "configparser", "contextlib",
+    "contextvars", "copy", "copyreg", "crypt", "csv", "ctypes", "curses", "dataclasses",
+    "datetime", "dbm", "decimal", "difflib", "dis", "distutils", "doctest", "email", "encodings",
+    "ensurepip", "enum", "errno", "faulthandler", "fcntl", "filecmp", "fileinput", "fnmatch",
+    "fractions", "ftplib", "functools", "gc", "genericpath", "getopt", "getpass", "gettext",
+    "glob", "graphlib", "grp", "gzip", "hashlib", "heapq", "hmac", "html", "http", "idlelib",
+    "imaplib", "imghdr", "imp", "importlib", "inspect", "io", "ipaddress", "itertools", "json",
+    "keyword", "lib2to3", "linecache", "locale", "logging", "lzma", "mailbox", "mailcap",
+    "marshal", "math", "mimetypes", "mmap", "modulefinder", "msilib", "msvcrt", "multiprocessing",
+    "netrc", "nis", "nntplib", "nt", "ntpath", "nturl2path", "numbers", "opcode", "operator",
+    "optparse", "os", "ossaudiodev", "pathlib", "pdb", "pickle", "pickletools", "pipes",
+    "pkgutil", "platform", "plistlib", "poplib", "posix", "posixpath", "pprint", "profile",
+    "pstats", "pty", "pwd", "py_compile", "pyclbr", "pydoc", "pydoc_data", "pyexpat", "queue",
+    "quopri", "random", "re", "readline", "reprlib", "resource", "rlcompleter", "runpy", "sched",
+    "secrets", "select", "selectors", "shelve", "shlex", "shutil", "signal", "site", "smtpd",
+    "smtplib", "sndhdr", "socket", "socketserver", "spwd", "sqlite3", "sre_compile",
+    "sre_constants", "sre_parse", "ssl", "stat", "statistics", "string", "stringprep", "struct",
+    "subprocess", "sunau", "symtable", "sys", "sysconfig", "syslog", "tabnanny", "tarfile",
+    "telnetlib", "tempfile", "termios", "textwrap", "this", "threading", "time", "timeit",
+    "tkinter", "token", "tokenize", "trace", "traceback", "tracemalloc", "tty", "turtle",
+    "turtledemo", "types", "typing", "unicodedata", "unittest", "urllib", "uu", "uuid", "venv",
+    "warnings", "wave", "weakref", "webbrowser", "winreg", "winsound", "wsgiref", "xdrlib", "xml",
+    "xmlrpc", "zipapp", "zipfile", "zipimport", "zlib", "zoneinfo",
+];
+
+/// Python modules that are provided by a package with a different name
+const PYTHON_ALIASES: &[(&str, &str)] = &[
+    ("Bio", "biopython"),
+    ("Crypto", "pycryptodome"),
+    ("IPython", "ipython"),
+    ("Levenshtein", "python-Levenshtein"),
+    ("MySQLdb", "mysqlclient"),
+    ("OpenSSL", "pyOpenSSL"),
+    ("PIL", "Pillow"),
+    ("attr", "attrs"),
+    ("bs4", "beautifulsoup4"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("dns", "dnspython"),
+    ("docx", "python-docx"),
+    ("dotenv", "python-dotenv"),
+    ("fitz", "PyMuPDF"),
+    ("gi", "PyGObject"),
+    ("git", "GitPython"),
+    ("jose", "python-jose"),
+    ("jwt", "PyJWT"),
+    ("magic", "python-magic"),
+    ("markdown", "Markdown"),
+    ("mpl_toolkits", "matplotlib"),
+    ("osgeo", "GDAL"),
+    ("pptx", "python-pptx"),
+    ("serial", "pyserial"),
+    ("skimage", "scikit-image"),
+    ("sklearn", "scikit-learn"),
+    ("slugify", "python-slugify"),
+    ("umap", "umap-learn"),
+    ("websocket", "websocket-client"),
+    ("yaml", "PyYAML"),
+    ("zmq", "pyzmq"),
+];
+
+/// R packages that are part of the base R distribution
+const R_BASE: &[&str] = &[
+    "base",
+    "compiler",
+    "datasets",
+    "graphics",
+    "grDevices",
+    "grid",
+    "methods",
+    "parallel",
+    "splines",
+    "stats",
+    "stats4",
+    "tcltk",
+    "tools",
+    "utils",
+];
+
+/// Node.js built-in modules
+const NODE_BUILTINS: &[&str] = &[
+    "assert",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "diagnostics_channel",
+    "dns",
+    "domain",
+    "events",
+    "fs",
+    "http",
+    "http2",
+    "https",
+    "inspector",
+    "module",
+    "net",
+    "os",
+    "path",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "repl",
+    "stream",
+    "string_decoder",
+    "sys",
+    "timers",
+    "tls",
+    "trace_events",
+    "tty",
+    "url",
+    "util",
+    "v8",
+    "vm",
+    "wasi",
+    "worker_threads",
+    "zlib",
+];
+
+/// The packages that a project depends upon
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct Dependencies {
+    /// Python packages (e.g. from PyPI)
+    pub python: BTreeSet<String>,
+
+    /// R packages (e.g. from CRAN)
+    pub r: BTreeSet<String>,
+
+    /// JavaScript packages (e.g. from npm)
+    pub javascript: BTreeSet<String>,
+}
+
+impl Dependencies {
+    /// Create a set of dependencies from the modules imported by code
+    ///
+    /// Modules in the standard library of each language are excluded and module names
+    /// are mapped to the name of the package that provides them.
+    ///
+    /// # Arguments
+    ///
+    /// - `modules`: The modules imported by code in the project
+    ///
+    /// - `local`: The names of the Python modules within the project (see [`local_modules`])
+    pub fn from_modules<'lt>(
+        modules: impl IntoIterator<Item = &'lt Module>,
+        local: &BTreeSet<String>,
+    ) -> Self {
+        let mut deps = Dependencies::default();
+        for Module { language, name } in modules {
+            match language.as_str() {
+                "python" => {
+                    if let Some(package) = python_package(name, local) {
+                        deps.python.insert(package);
+                    }
+                }
+                "r" => {
+                    if let Some(package) = r_package(name) {
+                        deps.r.insert(package);
+                    }
+                }
+                "javascript" | "typescript" => {
+                    if let Some(package) = javascript_package(name) {
+                        deps.javascript.insert(package);
+                    }
+                }
+                _ => {}
+            }
+        }
+        deps
+    }
+
+    /// Write the dependencies to the manifest files in a directory
+    ///
+    /// Existing manifest files are updated by adding any packages that are not already
+    /// in them. Packages are never removed and existing version constraints are left
+    /// unchanged. Returns the paths of the files that were written.
+    ///
+    /// # Arguments
+    ///
+    /// - `dir`: The directory to write the files to (usually the project directory)
+    ///
+    /// - `name`: The name to use for new `DESCRIPTION` and `package.json` files
+    pub fn write(&self, dir: &Path, name: &str) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+
+        if !self.python.is_empty() {
+            let path = dir.join("requirements.txt");
+            if write_requirements_txt(&path, &self.python)? {
+                written.push(path);
+            }
+        }
+
+        if !self.r.is_empty() {
+            let path = dir.join("DESCRIPTION");
+            if write_description(&path, &self.r, name)? {
+                written.push(path);
+            }
+
+            // When there is a `renv.lock` file it is used to restore packages (in preference to
+            // `DESCRIPTION`) but it can only be updated by `renv` so just warn if it is out of date
+            let lockfile = dir.join("renv.lock");
+            if let Ok(json) = fs::read_to_string(&lockfile) {
+                let lock: serde_json::Value = serde_json::from_str(&json)?;
+                let missing: Vec<&str> = self
+                    .r
+                    .iter()
+                    .filter(|package| lock.pointer(&["/Packages/", package].concat()).is_none())
+                    .map(|package| package.as_str())
+                    .collect();
+                if !missing.is_empty() {
+                    tracing::warn!(
+                        "Packages `{}` are not in `renv.lock`; use `renv::snapshot()` to update it",
+                        missing.join("`, `")
+                    );
+                }
+            }
+        }
+
+        if !self.javascript.is_empty() {
+            let path = dir.join("package.json");
+            if write_package_json(&path, &self.javascript, name)? {
+                written.push(path);
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+/// Get the modules imported by the code in a file
+///
+/// Code files (e.g. `analysis.py`) are parsed directly. Documents (e.g. `report.Rmd`) are
+/// decoded and the code in their `CodeChunk` and `CodeExpression` nodes is parsed. Files are
+/// not opened as documents so that they are not compiled, or recorded in their history,
+/// just to infer dependencies.
+pub async fn modules_in_file(path: &Path, format: &Format) -> Result<Vec<Module>> {
+    let code = match format {
+        Format::Ipynb
+        | Format::Markdown
+        | Format::Myst
+        | Format::QuartoMarkdown
+        | Format::RMarkdown => {
+            let node = codecs::from_path(path, None, None).await?;
+            let mut collector = CodeCollector::default();
+            walk(&node, &mut collector);
+            collector.code
+        }
+        _ if matches!(format.spec().node_type, FormatNodeType::SoftwareSourceCode) => {
+            vec![(format.spec().extension, fs::read_to_string(path)?)]
+        }
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut modules = Vec::new();
+    for (language, code) in code {
+        let resource = resources::code(path, "", "SoftwareSourceCode", Some(language));
+        let relations = match parsers::parse(resource, &code) {
+            Ok(resource_info) => resource_info.relations.unwrap_or_default(),
+            // Code in languages without a parser can not have its imports inferred
+            Err(..) => continue,
+        };
+        modules.extend(
+            relations
+                .into_iter()
+                .filter_map(|(.., object)| match object {
+                    Resource::Module(module) => Some(module),
+                    _ => None,
+                }),
+        );
+    }
+    Ok(modules)
+}
+
+/// A node visitor which collects the language and text of code nodes
+#[derive(Default)]
+struct CodeCollector {
+    code: Vec<(String, String)>,
+}
+
+impl Visitor for CodeCollector {
+    fn visit_block(&mut self, _address: &Address, node: &BlockContent) -> bool {
+        if let BlockContent::CodeChunk(chunk) = node {
+            self.code
+                .push((chunk.programming_language.clone(), chunk.text.clone()));
+        }
+        true
+    }
+
+    fn visit_inline(&mut self, _address: &Address, node: &InlineContent) -> bool {
+        if let InlineContent::CodeExpression(expr) = node {
+            self.code
+                .push((expr.programming_language.clone(), expr.text.clone()));
+        }
+        true
+    }
+}
+
+/// Get the names of the Python modules and packages within a project
+///
+/// Includes the names of Python files and of the folders that contain them so that
+/// imports of the project's own code (e.g. `import utils` when there is a `utils.py` in the
+/// project, or `from analysis import clean` when there is an `analysis/clean.py`) are
+/// not treated as dependencies.
+///
+/// # Arguments
+///
+/// - `dir`: The project directory
+///
+/// - `paths`: The paths of the files in the project
+pub fn local_modules<'lt>(
+    dir: &Path,
+    paths: impl IntoIterator<Item = &'lt PathBuf>,
+) -> BTreeSet<String> {
+    let mut modules = BTreeSet::new();
+    for path in paths {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("py") {
+            continue;
+        }
+        let path = path.strip_prefix(dir).unwrap_or(path);
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if stem != "__init__" {
+                modules.insert(stem.to_string());
+            }
+        }
+        for folder in path.parent().into_iter().flat_map(|parent| parent.iter()) {
+            if let Some(folder) = folder.to_str() {
+                modules.insert(folder.to_string());
+            }
+        }
+    }
+    modules
+}
+
+/// Get the Python package that provides a module
+fn python_package(module: &str, local: &BTreeSet<String>) -> Option<String> {
+    // Submodules e.g. `numpy.linalg` are provided by the top-level package and
+    // relative imports e.g. `.utils` are to modules within the project
+    let top = module.split('.').next().unwrap_or_default();
+    if top.is_empty() || top.starts_with('_') || PYTHON_STDLIB.contains(&top) || local.contains(top)
+    {
+        return None;
+    }
+
+    let package = PYTHON_ALIASES
+        .iter()
+        .find(|(module, ..)| *module == top)
+        .map(|(.., package)| *package)
+        .unwrap_or(top);
+    Some(package.to_string())
+}
+
+/// Get the R package that provides a module
+fn r_package(module: &str) -> Option<String> {
+    if module.is_empty() || R_BASE.contains(&module) {
+        None
+    } else {
+        Some(module.to_string())
+    }
+}
+
+/// Get the npm package that provides a module
+fn javascript_package(module: &str) -> Option<String> {
+    // Relative imports are to modules within the project, and URL imports (e.g. in Deno)
+    // do not need to be installed
+    if module.is_empty()
+        || module.starts_with('.')
+        || module.starts_with('/')
+        || module.contains("://")
+        || module.starts_with("node:")
+    {
+        return None;
+    }
+    let module = module.strip_prefix("npm:").unwrap_or(module);
+
+    // Paths within packages e.g. `lodash/fp`, `@babel/core/lib/parse` are provided by the
+    // package e.g. `lodash`, `@babel/core`
+    let mut parts = module.split('/');
+    let package = match (parts.next(), parts.next()) {
+        (Some(scope), Some(name)) if scope.starts_with('@') => [scope, "/", name].concat(),
+        (Some(name), ..) => name.to_string(),
+        _ => return None,
+    };
+
+    // Remove any version e.g. `npm:lodash@4`
+    let package = match package.rfind('@') {
+        Some(index) if index > 0 => package[..index].to_string(),
+        _ => package,
+    };
+
+    if NODE_BUILTINS.contains(&package.as_str()) {
+        None
+    } else {
+        Some(package)
+    }
+}
+
+/// Normalize a Python package name so that names can be compared
+///
+/// See https://peps.python.org/pep-0503/#normalized-names.
+fn python_normalize(name: &str) -> String {
+    name.to_lowercase().replace(|c| c == '_' || c == '.', "-")
+}
+
+/// Add packages to a `requirements.txt` file
+///
+/// Returns `true` if the file was written to.
+fn write_requirements_txt(path: &Path, packages: &BTreeSet<String>) -> Result<bool> {
+    let mut content = fs::read_to_string(path).unwrap_or_default();
+
+    // Get the names of existing requirements, ignoring comments and options e.g. `-r other.txt`
+    let existing: HashSet<String> = content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !(line.is_empty() || line.starts_with('#') || line.starts_with('-')))
+        .map(|line| {
+            let name: String = line
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                .collect();
+            python_normalize(&name)
+        })
+        .collect();
+
+    let missing: Vec<&String> = packages
+        .iter()
+        .filter(|package| !existing.contains(&python_normalize(package)))
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+
+    if !(content.is_empty() || content.ends_with('\n')) {
+        content.push('\n');
+    }
+    for package in missing {
+        content.push_str(package);
+        content.push('\n');
+    }
+    fs::write(path, content)?;
+
+    Ok(true)
+}
+
+/// Add packages to the `Imports` field of a `DESCRIPTION` file
+///
+/// Other fields are left unchanged. If the file does not exist, a minimal one is created.
+/// Returns `true` if the file was written to.
+fn write_description(path: &Path, packages: &BTreeSet<String>, name: &str) -> Result<bool> {
+    // Parse the fields, keeping the raw text of each (including any continuation lines)
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(..) => {
+            // R package names must start with a letter and only contain letters, numbers and dots
+            let name: String = name
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '.')
+                .collect();
+            let name = match name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                true => name,
+                false => ["project", &name].concat(),
+            };
+            format!(
+                "Package: {}\nTitle: Dependencies of the {} project\nVersion: 0.0.0.9000\n",
+                name, name
+            )
+        }
+    };
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some((.., raw)) = fields.last_mut() {
+                raw.push('\n');
+                raw.push_str(line);
+            }
+        } else if let Some((name, ..)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), line.to_string()));
+        }
+    }
+
+    // Get the packages already in `Depends`, `Imports` and `LinkingTo` and any `Imports`
+    // entries (including any version constraints)
+    let entries = |raw: &str| -> Vec<String> {
+        raw.split_once(':')
+            .map(|(.., value)| value)
+            .unwrap_or_default()
+            .split(',')
+            .map(|entry| entry.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .filter(|entry| !entry.is_empty())
+            .collect()
+    };
+    let mut existing = HashSet::new();
+    let mut imports = Vec::new();
+    for (name, raw) in &fields {
+        if matches!(name.as_str(), "Depends" | "Imports" | "LinkingTo") {
+            for entry in entries(raw) {
+                let package = entry
+                    .split(|c: char| c == '(' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                existing.insert(package);
+                if name == "Imports" {
+                    imports.push(entry);
+                }
+            }
+        }
+    }
+
+    let missing: Vec<&String> = packages
+        .iter()
+        .filter(|package| !existing.contains(*package))
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+
+    imports.extend(missing.into_iter().cloned());
+    let raw = ["Imports:\n    ", &imports.join(",\n    ")].concat();
+    match fields.iter_mut().find(|(name, ..)| name == "Imports") {
+        Some(field) => field.1 = raw,
+        None => fields.push(("Imports".to_string(), raw)),
+    }
+
+    let content = fields
+        .into_iter()
+        .map(|(.., raw)| raw + "\n")
+        .collect::<Vec<String>>()
+        .concat();
+    fs::write(path, content)?;
+
+    Ok(true)
+}
+
+/// Add packages to the `dependencies` of a `package.json` file
+///
+/// Packages are added with the version range `*`. If the file does not exist, a minimal one
+/// is created. Returns `true` if the file was written to.
+fn write_package_json(path: &Path, packages: &BTreeSet<String>, name: &str) -> Result<bool> {
+    let mut json = match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(..) => {
+            // npm package names must be lowercase and URL safe
+            let name: String = name
+                .to_lowercase()
+                .chars()
+                .map(
+                    |c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                        true => c,
+                        false => '-',
+                    },
+                )
+                .collect();
+            serde_json::json!({
+                "name": name,
+                "private": true
+            })
+        }
+    };
+    let object = match json.as_object_mut() {
+        Some(object) => object,
+        None => bail!("Expected `{}` to be a JSON object", path.display()),
+    };
+
+    let existing: HashSet<&String> = [
+        "dependencies",
+        "devDependencies",
+        "peerDependencies",
+        "optionalDependencies",
+    ]
+    .iter()
+    .filter_map(|field| object.get(*field).and_then(|deps| deps.as_object()))
+    .flat_map(|deps| deps.keys())
+    .collect();
+    let missing: Vec<String> = packages
+        .iter()
+        .filter(|package| !existing.contains(package))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+
+    let dependencies = object
+        .entry("dependencies")
+        .or_insert_with(|| serde_json::json!({}));
+    let dependencies = match dependencies.as_object_mut() {
+        Some(dependencies) => dependencies,
+        None => bail!(
+            "Expected `dependencies` in `{}` to be an object",
+            path.display()
+        ),
+    };
+    for package in missing {
+        dependencies.insert(package, serde_json::Value::String("*".to_string()));
+    }
+
+    fs::write(path, serde_json::to_string_pretty(&json)? + "\n")?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{tempfile::tempdir, tokio};
+    use graph_triples::resources;
+
+    use super::*;
+
+    #[test]
+    fn from_modules() {
+        let modules: Vec<Module> = [
+            ("python", "numpy.linalg"),
+            ("python", "sklearn"),
+            ("python", "os"),
+            ("python", ".utils"),
+            ("python", "utils.plots"),
+            ("r", "dplyr"),
+            ("r", "stats"),
+            ("javascript", "lodash/fp"),
+            ("javascript", "@babel/core"),
+            ("javascript", "fs/promises"),
+            ("javascript", "node:path"),
+            ("javascript", "./lib"),
+        ]
+        .iter()
+        .filter_map(|(language, name)| match resources::module(language, name) {
+            graph_triples::Resource::Module(module) => Some(module),
+            _ => None,
+        })
+        .collect();
+
+        let local = BTreeSet::from(["utils".to_string()]);
+        let deps = Dependencies::from_modules(&modules, &local);
+        assert_eq!(
+            deps.python.into_iter().collect::<Vec<_>>(),
+            vec!["numpy", "scikit-learn"]
+        );
+        assert_eq!(deps.r.into_iter().collect::<Vec<_>>(), vec!["dplyr"]);
+        assert_eq!(
+            deps.javascript.into_iter().collect::<Vec<_>>(),
+            vec!["@babel/core", "lodash"]
+        );
+    }
+
+    #[test]
+    fn local_modules() {
+        let dir = PathBuf::from("/project");
+        let paths = [
+            "/project/main.py",
+            "/project/analysis/__init__.py",
+            "/project/analysis/clean.py",
+            "/project/report.md",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(
+            super::local_modules(&dir, &paths)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["analysis", "clean", "main"]
+        );
+    }
+
+    #[tokio::test]
+    async fn modules_in_file() -> Result<()> {
+        let dir = tempdir()?;
+        let dir = dir.path();
+
+        let path = dir.join("analysis.py");
+        fs::write(&path, "import pandas as pd\nfrom sklearn import svm\n")?;
+        let modules = super::modules_in_file(&path, &Format::Python).await?;
+        assert_eq!(
+            modules
+                .iter()
+                .map(|module| module.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["pandas", "sklearn"]
+        );
+
+        let path = dir.join("report.md");
+        fs::write(
+            &path,
+            "# Report\n\n```r exec\nlibrary(dplyr)\n```\n\n```python exec\nimport numpy\n```\n",
+        )?;
+        let modules = super::modules_in_file(&path, &Format::Markdown).await?;
+        assert_eq!(
+            modules
+                .iter()
+                .map(|module| (module.language.as_str(), module.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("r", "dplyr"), ("python", "numpy")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write() -> Result<()> {
+        let dir = tempdir()?;
+        let dir = dir.path();
+
+        fs::write(
+            dir.join("requirements.txt"),
+            "# Comment\nNumPy==1.23.0\n-r other.txt",
+        )?;
+        fs::write(
+            dir.join("DESCRIPTION"),
+            "Package: test\nDepends:\n    R (>= 4.0.0),\n    ggplot2\nImports: dplyr (>= 1.0)\n",
+        )?;
+
+        let deps = Dependencies {
+            python: ["numpy", "scikit-learn"].map(String::from).into(),
+            r: ["dplyr", "ggplot2", "tidyr"].map(String::from).into(),
+            javascript: ["lodash"].map(String::from).into(),
+        };
+        let written = deps.write(dir, "My project")?;
+        assert_eq!(written.len(), 3);
+
+        // Existing requirements are kept and only missing packages added
+        assert_eq!(
+            fs::read_to_string(dir.join("requirements.txt"))?,
+            "# Comment\nNumPy==1.23.0\n-r other.txt\nscikit-learn\n"
+        );
+
+        // Existing `Imports` are kept and only packages not in other fields added
+        assert_eq!(
+            fs::read_to_string(dir.join("DESCRIPTION"))?,
+            "Package: test\nDepends:\n    R (>= 4.0.0),\n    ggplot2\nImports:\n    dplyr (>= 1.0),\n    tidyr\n"
+        );
+
+        // A new `package.json` is created
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("package.json"))?)?;
+        assert_eq!(json["name"], "my-project");
+        assert_eq!(json["dependencies"]["lodash"], "*");
+
+        // Writing again does not change anything
+        assert!(deps.write(dir, "My project")?.is_empty());
+
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@
 #![forbid(unsafe_code)]
 
 pub use kernels;
+pub mod dependencies;
 pub mod projects;
 pub mod sessions;
 
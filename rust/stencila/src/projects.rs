@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     fs,
     path::{Path, PathBuf},
     sync::Arc,
@@ -29,6 +29,7 @@ use graph_triples::{resources, Resource};
 use path_utils::pathdiff;
 
 use crate::config::CONFIG;
+use crate::dependencies::{self, Dependencies};
 use crate::utils::schemas;
 
 #[derive(Debug, Display, JsonSchema, Serialize)]
@@ -107,6 +108,13 @@ pub struct Project {
     /// configuration settings.
     theme: Option<String>,
 
+    /// The citation style to use for documents in this project
+    ///
+    /// The name of one of the bundled styles (e.g. `apa`, `vancouver`) or the path
+    /// (within the project) of a CSL file. Can be overridden by documents.
+    /// If not specified, will default to APA.
+    citation_style: Option<String>,
+
     /// Glob patterns for paths to be excluded from file watching
     ///
     /// As a performance optimization, paths that match these patterns are
@@ -197,6 +205,7 @@ impl Project {
             project.image = overrides.image;
             project.main = overrides.main;
             project.theme = overrides.theme;
+            project.citation_style = overrides.citation_style;
             project.watch_exclude_patterns = overrides.watch_exclude_patterns;
         }
 
@@ -435,6 +444,52 @@ impl Project {
         Ok(self)
     }
 
+    /// Infer the packages that a project depends upon
+    ///
+    /// Collects the modules imported by code in the project's graph and in any other
+    /// documents in the project (which may not be linked to from the main document).
+    /// Used by `stencila projects deps`.
+    pub async fn dependencies(&self) -> Result<Dependencies> {
+        let mut modules = BTreeSet::new();
+        let mut paths = Vec::new();
+        for resource in self.graph.get_resources() {
+            match resource {
+                Resource::Module(module) => {
+                    modules.insert(module.clone());
+                }
+                Resource::File(file) => paths.push(file.path.clone()),
+                _ => {}
+            }
+        }
+
+        let mut files = Vec::new();
+        for (path, file) in &self.files.files {
+            // Ignore folders of installed packages and virtual environments
+            let ignore = path.components().any(|component| {
+                matches!(
+                    component.as_os_str().to_str(),
+                    Some(".stencila" | ".venv" | "venv" | "renv" | "node_modules")
+                )
+            });
+            if file.children.is_none() && !ignore {
+                files.push((path, file));
+            }
+        }
+
+        for (path, file) in &files {
+            if paths.contains(path) {
+                continue;
+            }
+            match dependencies::modules_in_file(path, &file.format).await {
+                Ok(file_modules) => modules.extend(file_modules),
+                Err(error) => tracing::warn!("While parsing `{}`: {}", path.display(), error),
+            }
+        }
+
+        let local = dependencies::local_modules(&self.path, files.iter().map(|(path, ..)| *path));
+        Ok(Dependencies::from_modules(&modules, &local))
+    }
+
     /// Show a project
     ///
     /// Generates a Markdown representation of a project.
@@ -885,6 +940,7 @@ pub mod commands {
         Close(Close),
         Show(Show),
         Graph(Graph),
+        Deps(Deps),
     }
 
     #[async_trait]
@@ -898,6 +954,7 @@ pub mod commands {
                 Action::Close(action) => action.run().await,
                 Action::Show(action) => action.run().await,
                 Action::Graph(action) => action.run().await,
+                Action::Deps(action) => action.run().await,
             }
         }
     }
@@ -1008,4 +1065,36 @@ pub mod commands {
             result::content(&self.to, &content)
         }
     }
+
+    /// Infer the packages that a project depends upon from the modules imported in its code
+    ///
+    /// Collects the modules imported by Python, R and JavaScript code in the project's documents
+    /// and maps them to the packages that provide them (excluding standard library modules).
+    /// Missing packages are added to the `requirements.txt`, `DESCRIPTION` and `package.json`
+    /// files in the project folder (which are created if necessary) so that buildpacks can build
+    /// an environment for the project.
+    #[derive(Parser)]
+    pub struct Deps {
+        /// The path of the project folder (defaults to the current project)
+        pub folder: Option<PathBuf>,
+
+        /// Only show the inferred packages; do not write them to manifest files
+        #[clap(long)]
+        pub dry_run: bool,
+    }
+
+    #[async_trait]
+    impl Run for Deps {
+        async fn run(&self) -> Result {
+            let project = PROJECTS.open(self.folder.clone(), false).await?;
+            let deps = project.dependencies().await?;
+            if !self.dry_run {
+                let name = project.name.clone().unwrap_or_default();
+                for path in deps.write(&project.path, &name)? {
+                    tracing::info!("Updated `{}`", path.display());
+                }
+            }
+            result::value(deps)
+        }
+    }
 }
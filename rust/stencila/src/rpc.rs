@@ -66,6 +66,8 @@ impl Request {
             "documents.load" => documents_load(&self.params).await,
             "documents.dump" => documents_dump(&self.params).await,
             "documents.patch" => documents_patch(&self.params).await,
+            "documents.undo" => documents_undo(&self.params).await,
+            "documents.redo" => documents_redo(&self.params).await,
             "documents.execute" => documents_execute(&self.params).await,
             "documents.cancel" => documents_cancel(&self.params).await,
             "documents.restart" => documents_restart(&self.params).await,
@@ -368,7 +370,12 @@ async fn documents_unsubscribe(
 async fn documents_patch(params: &Params) -> Result<(serde_json::Value, Subscription)> {
     let id = required_string(params, "documentId")?;
     let patch = required_value(params, "patch")?;
-    let patch: Patch = serde_json::from_value(patch)?;
+    let mut patch: Patch = serde_json::from_value(patch)?;
+    // The client's version of the document (the sequence number of the last patch it received)
+    // may be supplied as a separate parameter, rather than as part of the patch
+    if let Some(version) = optional_value(params, "version").and_then(|value| value.as_u64()) {
+        patch.version.get_or_insert(version as u32);
+    }
     let assemble = optional_string(params, "assemble")?
         .and_then(|value| When::from_str(&value).ok())
         .unwrap_or(When::Never);
@@ -392,6 +399,47 @@ async fn documents_patch(params: &Params) -> Result<(serde_json::Value, Subscrip
     Ok((json!(true), Subscription::None))
 }
 
+async fn documents_undo(params: &Params) -> Result<(serde_json::Value, Subscription)> {
+    let id = required_string(params, "documentId")?;
+    let (compile, write) = history_whens(params)?;
+
+    let done = DOCUMENTS
+        .get(&id)
+        .await?
+        .lock()
+        .await
+        .undo(compile, write)
+        .await?;
+    Ok((json!(done), Subscription::None))
+}
+
+async fn documents_redo(params: &Params) -> Result<(serde_json::Value, Subscription)> {
+    let id = required_string(params, "documentId")?;
+    let (compile, write) = history_whens(params)?;
+
+    let done = DOCUMENTS
+        .get(&id)
+        .await?
+        .lock()
+        .await
+        .redo(compile, write)
+        .await?;
+    Ok((json!(done), Subscription::None))
+}
+
+/// Get the `compile` and `write` parameters of `documents.undo` and `documents.redo`
+///
+/// Defaults are the same as for `documents.patch`.
+fn history_whens(params: &Params) -> Result<(When, When)> {
+    let compile = optional_string(params, "compile")?
+        .and_then(|value| When::from_str(&value).ok())
+        .unwrap_or(When::Soon);
+    let write = optional_string(params, "write")?
+        .and_then(|value| When::from_str(&value).ok())
+        .unwrap_or(When::Soon);
+    Ok((compile, write))
+}
+
 async fn documents_execute(params: &Params) -> Result<(serde_json::Value, Subscription)> {
     let id = required_string(params, "documentId")?;
     let write = optional_string(params, "write")?
@@ -408,7 +456,7 @@ async fn documents_execute(params: &Params) -> Result<(serde_json::Value, Subscr
         .await?
         .lock()
         .await
-        .execute_request(write, node_id, ordering, None)
+        .execute_request(write, node_id, ordering, None, false)
         .await?;
     Ok((json!(true), Subscription::None))
 }
@@ -473,7 +521,6 @@ fn required_value(params: &Params, name: &str) -> Result<serde_json::Value> {
     }
 }
 
-#[allow(dead_code)]
 fn optional_value(params: &Params, name: &str) -> Option<serde_json::Value> {
     params.get(name).cloned()
 }
@@ -118,6 +118,13 @@ const noBoxTypes = [
   'TableCellContent',
 ]
 
+// Types for which the `meta` property (for ad-hoc extensions) is generated. It is not
+// generated for other types because it is rarely needed and would increase their size.
+const metaTypes = [
+  // Used to store the metadata of Jupyter Notebook cells that has no equivalent property
+  'CodeChunk',
+]
+
 // Properties that need to use a `Box` pointer to prevent circular references
 // (the "recursive type has infinite size" error) or because it is
 // memory efficient (especially for optional properties on deeply nested structs)
@@ -255,7 +262,7 @@ export function interfaceSchemaToStruct(
   const { all } = getSchemaProperties(schema)
 
   const fields = all
-    .filter(({ name }) => name !== 'meta')
+    .filter(({ name }) => name !== 'meta' || metaTypes.includes(title))
     .map(({ name, schema, optional, inherited, override }) => {
       const { description = name, from } = schema
 
@@ -250,6 +250,7 @@ export type Cite = Entity & {
   citationPrefix?: string
   citationSuffix?: string
   content?: Array<InlineContent>
+  errors?: Array<string>
   pageEnd?: Integer | string
   pageStart?: Integer | string
   pagination?: string
@@ -470,6 +471,7 @@ export const codeFragment = (
 export type CodeError = Entity & {
   type: 'CodeError'
   errorMessage: string
+  codeLocation?: Array<number>
   errorType?: string
   stackTrace?: string
 }
@@ -220,9 +220,14 @@ export async function sendPatch(
     for (const op of ops) console.log('  ', JSON.stringify(op))
   }
 
+  // Send the client's version of the document so that the server can transform
+  // the patch against any concurrent patches from other clients
+  const version = window.stencilaWebClient.patchSequence
+
   return client.call('documents.patch', {
     documentId,
     patch,
+    version,
     compile,
     execute,
     write,
@@ -281,6 +286,42 @@ export function receivePatch(
   callback(patch)
 }
 
+/**
+ * Undo the last edit to a document
+ *
+ * Resolves to `false` if there was no edit to undo.
+ */
+export async function undo(
+  client: Client,
+  documentId: DocumentId,
+  compile: When = 'Soon',
+  write: When = 'Soon'
+): Promise<boolean> {
+  return client.call('documents.undo', {
+    documentId,
+    compile,
+    write,
+  }) as Promise<boolean>
+}
+
+/**
+ * Redo the last undone edit to a document
+ *
+ * Resolves to `false` if there was no undone edit to redo.
+ */
+export async function redo(
+  client: Client,
+  documentId: DocumentId,
+  compile: When = 'Soon',
+  write: When = 'Soon'
+): Promise<boolean> {
+  return client.call('documents.redo', {
+    documentId,
+    compile,
+    write,
+  }) as Promise<boolean>
+}
+
 /**
  * Execute a document
  */
@@ -288,7 +329,7 @@ export async function execute(
   client: Client,
   documentId: DocumentId,
   nodeId: null | NodeId,
-  ordering: 'Single' | 'Appearance' | 'Topological' = 'Topological'
+  ordering: 'Single' | 'Appearance' | 'Topological' | 'Cost' = 'Topological'
 ): Promise<void> {
   return client.call('documents.execute', {
     documentId,